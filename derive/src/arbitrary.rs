@@ -0,0 +1,455 @@
+//! Generation of `Arbitrary` implementations that only produce values plod can write and read back
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::parse::Result;
+use syn::{Data, DeriveInput, Fields, Type};
+
+use crate::attributes::{fields_attributes, Attributes};
+use crate::describe::tag_ranges;
+use crate::{
+    blob_as_vec, box_item, bytes_or_text, inline_bytes, is_blob, is_bounded, option_item, primitive_function, primitive_size, primitive_type,
+    resolve_std_types, vec_item,
+};
+
+/// Generate the `Arbitrary` implementation for a given input type (struct or enum)
+pub fn arbitrary_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let self_name = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (code, field_list) = arbitrary_fields(&data.fields, attributes, None)?;
+            quote! {
+                #code
+                Ok(#self_name #field_list)
+            }
+        }
+        Data::Enum(data) => {
+            let tag_type = match &attributes.tag_type {
+                Some(t) => t,
+                None if !attributes.tag_fields.is_empty() => {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "arbitrary doesn't support tag_fields",
+                    ))
+                }
+                None => return Ok(TokenStream::new()),
+            };
+            // replicate the read dispatch to check that a kept tag selects the right variant
+            let mut dispatch = TokenStream::new();
+            let mut has_default = false;
+            let mut variants = Vec::new();
+            for variant in data.variants.iter() {
+                let variant_attributes = attributes.variant(variant)?;
+                if variant_attributes.skip {
+                    continue;
+                }
+                let index = variants.len();
+                // values are read back with the default context
+                let guard = variant_attributes.tag_guard.as_ref().map(|guard| {
+                    quote! { if { let ctx = &<Self as plod::Plod>::Context::default(); #guard } }
+                });
+                match &variant_attributes.tag_pattern() {
+                    Some(pattern) => dispatch.extend(quote! { #pattern #guard => #index, }),
+                    None => {
+                        dispatch.extend(quote! { _ => #index, });
+                        has_default = true;
+                    }
+                }
+                variants.push((variant, variant_attributes));
+            }
+            if !has_default {
+                dispatch.extend(quote! { _ => usize::MAX, });
+            }
+            let mut arms = TokenStream::new();
+            for (index, (variant, variant_attributes)) in variants.iter().enumerate() {
+                let ident = &variant.ident;
+                if variant_attributes.keep_tag && !primitive_type(tag_type) {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "arbitrary doesn't support kept tags of a non primitive tag_type",
+                    ));
+                }
+                let tag = if variant_attributes.keep_tag {
+                    let ranges = match &variant_attributes.tag {
+                        Some(pattern) => tag_ranges(pattern, tag_type),
+                        None => TokenStream::new(),
+                    };
+                    quote! {
+                        let ranges: Vec<std::ops::RangeInclusive<i128>> = vec![#ranges];
+                        let discriminant = if ranges.is_empty() {
+                            u.arbitrary::<#tag_type>()?
+                        } else {
+                            let range = u.choose(&ranges)?.clone();
+                            u.int_in_range(range)? as #tag_type
+                        };
+                        #[allow(unreachable_patterns)]
+                        let selected = match discriminant {
+                            #dispatch
+                        };
+                        if selected != #index {
+                            return Err(plod::testing::arbitrary::Error::IncorrectFormat);
+                        }
+                    }
+                } else if let Some(guard) = &variant_attributes.tag_guard {
+                    quote! {
+                        if !{ let ctx = &<Self as plod::Plod>::Context::default(); #guard } {
+                            return Err(plod::testing::arbitrary::Error::IncorrectFormat);
+                        }
+                    }
+                } else {
+                    TokenStream::new()
+                };
+                let (code, field_list) =
+                    arbitrary_fields(&variant.fields, variant_attributes, Some(tag_type))?;
+                arms.extend(quote! {
+                    #index => {
+                        #tag
+                        #code
+                        Ok(#self_name::#ident #field_list)
+                    }
+                });
+            }
+            let count = variants.len();
+            quote! {
+                match u.choose_index(#count)? {
+                    #arms
+                    _ => unreachable!(),
+                }
+            }
+        }
+        // already reported by plod_impl
+        Data::Union(_) => return Ok(TokenStream::new()),
+    };
+
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let type_params: Vec<_> = input.generics.type_params().collect();
+    let bounds = input.generics.type_params().map(|p| {
+        let ident = &p.ident;
+        quote! { #ident: plod::testing::arbitrary::Arbitrary<'arbitrary>, }
+    });
+    let where_clause = match where_clause {
+        Some(w) => {
+            let predicates = w.predicates.iter();
+            quote! { where #(#predicates,)* #(#bounds)* }
+        }
+        None => quote! { where #(#bounds)* },
+    };
+    Ok(quote! {
+        #[automatically_derived]
+        impl <'arbitrary, #(#type_params),*> plod::testing::arbitrary::Arbitrary<'arbitrary> for #self_name #ty_generics #where_clause {
+            fn arbitrary(u: &mut plod::testing::arbitrary::Unstructured<'arbitrary>) -> plod::testing::arbitrary::Result<Self> {
+                #body
+            }
+        }
+    })
+}
+
+/// Generate values for all fields, a kept tag is stored in `discriminant`
+fn arbitrary_fields(
+    fields: &Fields,
+    attributes: &Attributes,
+    tag_type: Option<&Ident>,
+) -> Result<(TokenStream, TokenStream)> {
+    let mut code = TokenStream::new();
+    let mut lengths = TokenStream::new();
+    let mut field_list = TokenStream::new();
+    let all_attributes = fields_attributes(fields, attributes)?;
+    if all_attributes.iter().any(|a| a.if_remaining) {
+        code.extend(quote! { let mut remaining = true; });
+    }
+    for (i, field) in fields.iter().enumerate() {
+        let field_attributes = &all_attributes[i];
+        let field_ident = match &field.ident {
+            Some(ident) => ident.clone(),
+            None => Ident::new(&format!("field_{}", i), Span::call_site()),
+        };
+        let ty = &field.ty;
+        if let Some(target) = &field_attributes.len_of {
+            // written from the collection, so it must match it to read back the same value
+            let j = fields.iter().position(|f| f.ident.as_ref() == Some(target)).unwrap();
+            let target_attributes = &all_attributes[j];
+            let length = match (&fields.iter().nth(j).unwrap().ty, target_attributes.byte_sized) {
+                (Type::Path(type_path), true) => match vec_item(type_path) {
+                    Some(item_ty) => items_size(
+                        &quote! { #target },
+                        item_ty,
+                        target_attributes.item_align,
+                    ),
+                    None => quote! { #target.len() },
+                },
+                _ => quote! { #target.len() },
+            };
+            let length = match target_attributes.size_unit {
+                Some(unit) => quote! { #length / #unit },
+                None => length,
+            };
+            let length = match target_attributes.size_adjust {
+                0 => length,
+                adjust => quote! {
+                    plod::size::adjusted(#length, #adjust)
+                        .map_err(|_| plod::testing::arbitrary::Error::IncorrectFormat)?
+                },
+            };
+            lengths.extend(quote! {
+                let #field_ident = (#length) as #ty;
+            });
+            field_list.extend(quote! { #field_ident, });
+            continue;
+        }
+        if let Some(target) = &field_attributes.tag_for {
+            // written from the enum, so it must be its tag to read back the same value
+            let j = fields.iter().position(|f| f.ident.as_ref() == Some(target)).unwrap();
+            let target_ty = &fields.iter().nth(j).unwrap().ty;
+            let size = primitive_size(match ty {
+                Type::Path(type_path) => type_path.path.get_ident().unwrap(),
+                _ => unreachable!(),
+            });
+            let (from_method, _) = primitive_function(field_attributes.endianness);
+            lengths.extend(quote! {
+                let mut tag = [0_u8; #size];
+                let ctx = <<#target_ty as plod::Plod>::Context as std::default::Default>::default();
+                plod::helpers::write_tag(&mut tag.as_mut_slice(), &#target, #size, &ctx, 0)
+                    .map_err(|_| plod::testing::arbitrary::Error::IncorrectFormat)?;
+                let #field_ident = <#ty>::#from_method(tag);
+            });
+            field_list.extend(quote! { #field_ident, });
+            continue;
+        }
+        let value = if field_attributes.skip {
+            quote! { <#ty as std::default::Default>::default() }
+        } else if let Some(expected) = &field_attributes.equals {
+            quote! { *#expected }
+        } else if i == 0 && attributes.keep_magic {
+            // only accepted values can be written
+            match &attributes.magic {
+                Some((_, values)) => quote! { *u.choose(&[#(#values as #ty),*])? },
+                None => arbitrary_item(ty, field_attributes),
+            }
+        } else if attributes.keep_tag && i == attributes.tag_field && tag_type.is_some() {
+            match &attributes.keep_diff {
+                // a tag that cannot be read back is not a valid value
+                Some(diff) => quote! {
+                    match (discriminant as #ty).checked_sub(#diff) {
+                        Some(value) => value,
+                        None => return Err(plod::testing::arbitrary::Error::IncorrectFormat),
+                    }
+                },
+                None => quote! { discriminant as #ty },
+            }
+        } else if field_attributes.if_remaining {
+            let mut inner = field_attributes.clone();
+            inner.if_remaining = false;
+            let item = match &resolve_std_types(ty) {
+                Type::Path(type_path) => option_item(type_path).map(|t| arbitrary_item(t, &inner)),
+                _ => None,
+            };
+            // a present field after an absent one would not be read back
+            match item {
+                Some(item) => quote! {
+                    if remaining && u.arbitrary()? {
+                        Some(#item)
+                    } else {
+                        remaining = false;
+                        None
+                    }
+                },
+                None => quote! { None },
+            }
+        } else {
+            arbitrary_item(ty, field_attributes)
+        };
+        let cfg = field_attributes.cfg_attr();
+        code.extend(quote! {
+            #cfg
+            let #field_ident: #ty = #value;
+        });
+        field_list.extend(quote! { #cfg #field_ident, });
+    }
+    if all_attributes.iter().any(|a| a.if_remaining) {
+        code.extend(quote! { let _ = remaining; });
+    }
+    code.extend(lengths);
+    let field_list = match fields {
+        Fields::Named(_) => quote! { { #field_list } },
+        Fields::Unnamed(_) => quote! { ( #field_list ) },
+        Fields::Unit => TokenStream::new(),
+    };
+    Ok((code, field_list))
+}
+
+/// Expression producing an arbitrary value of a single item
+fn arbitrary_item(ty: &Type, attributes: &Attributes) -> TokenStream {
+    if is_blob(ty) {
+        let bytes = arbitrary_item(&blob_as_vec(), attributes);
+        return quote! { plod::Blob::from_bytes(#bytes) };
+    }
+    if let Some(is_text) = bytes_or_text(ty) {
+        let bytes = arbitrary_item(&blob_as_vec(), attributes);
+        // ASCII keeps the size in bytes and can be written with any encoding
+        let utf16 = attributes.text_encoding.as_ref().is_some_and(|e| e == "utf16le" || e == "utf16be");
+        let owned = if is_text && utf16 {
+            // two bytes per character
+            quote! { #bytes.chunks_exact(2).map(|b| (b[0] & 0x7f) as char).collect::<String>() }
+        } else if is_text {
+            quote! { #bytes.into_iter().map(|b| (b & 0x7f) as char).collect::<String>() }
+        } else {
+            bytes
+        };
+        return match &resolve_std_types(ty) {
+            Type::Path(p) if p.path.is_ident("String") => owned,
+            // inline values keep what fits
+            _ if inline_bytes(ty).is_some() => quote! { <#ty>::truncating(&#owned) },
+            _ => quote! { std::borrow::Cow::Owned(#owned) },
+        };
+    }
+    if let Some(as_type) = &attributes.as_type {
+        let mut attributes = attributes.clone();
+        attributes.as_type = None;
+        let item = arbitrary_item(as_type, &attributes);
+        if is_bounded(ty) {
+            return quote! { <#ty>::saturating(#item) };
+        }
+        return quote! { <#ty as std::convert::From<#as_type>>::from(#item) };
+    }
+    match &resolve_std_types(ty) {
+        Type::Path(type_path) => {
+            let first = type_path.path.segments.first();
+            if let Some(inner_type) = box_item(type_path) {
+                let item = arbitrary_item(inner_type, attributes);
+                quote! { Box::new(#item) }
+            } else if first.is_some_and(|s| s.ident == "Vec") {
+                let item_ty = match vec_item(type_path) {
+                    Some(t) => t,
+                    None => return quote! { u.arbitrary()? },
+                };
+                let size_ty = match &attributes.size_type {
+                    Some(t) => t,
+                    None => return quote! { u.arbitrary()? },
+                };
+                let item = if attributes.bitmap {
+                    quote! { u.arbitrary::<bool>()? }
+                } else {
+                    arbitrary_item(item_ty, &attributes.vec_item())
+                };
+                // the stored size must fit in its type and not be negative
+                let adjust = attributes.size_adjust;
+                let size = match (attributes.byte_sized, attributes.bitmap) {
+                    (true, true) => quote! { v.len().div_ceil(8) },
+                    (true, false) => items_size(&quote! { v }, item_ty, attributes.item_align),
+                    (false, _) => quote! { v.len() },
+                };
+                let mut trim = if attributes.byte_sized && attributes.bitmap {
+                    // all the bits of the bytes are read back
+                    quote! {
+                        while #size > max || v.len() % 8 != 0 {
+                            v.pop();
+                        }
+                    }
+                } else if attributes.byte_sized {
+                    quote! {
+                        while #size > max {
+                            v.pop();
+                        }
+                    }
+                } else {
+                    TokenStream::new()
+                };
+                let too_small = match (-adjust).max(0) as usize * attributes.size_unit.unwrap_or(1) {
+                    0 => TokenStream::new(),
+                    min => quote! {
+                        if #size < #min {
+                            return Err(plod::testing::arbitrary::Error::IncorrectFormat);
+                        }
+                    },
+                };
+                // only whole units can be stored
+                if let Some(unit) = attributes.size_unit {
+                    trim.extend(quote! {
+                        while #size % #unit != 0 {
+                            v.pop();
+                        }
+                    });
+                }
+                quote! {
+                    {
+                        let max = (#size_ty::MAX as usize).saturating_add_signed(-#adjust as isize);
+                        let len = u.arbitrary_len::<u8>()?.min(max);
+                        let mut v = Vec::with_capacity(len);
+                        for _ in 0..len {
+                            v.push(#item);
+                        }
+                        #trim
+                        #too_small
+                        v
+                    }
+                }
+            } else {
+                quote! { u.arbitrary::<#type_path>()? }
+            }
+        }
+        Type::Tuple(t) => {
+            let items = t.elems.iter().map(|ty| arbitrary_item(ty, attributes));
+            quote! { ( #(#items,)* ) }
+        }
+        Type::Array(t) => {
+            let n = &t.len;
+            let item = arbitrary_item(&t.elem, &attributes.array_item());
+            quote! {
+                {
+                    let mut v = Vec::with_capacity(#n);
+                    for _ in 0..#n {
+                        v.push(#item);
+                    }
+                    match v.try_into() {
+                        Ok(a) => a,
+                        Err(_) => unreachable!(),
+                    }
+                }
+            }
+        }
+        _ => quote! { u.arbitrary()? },
+    }
+}
+
+/// Expression of the size at rest of `it`, a reference to an item of type `ty`
+fn size_expr(ty: &Type) -> TokenStream {
+    match ty {
+        Type::Path(type_path) => match type_path.path.get_ident() {
+            Some(ident) if primitive_type(ident) => {
+                let size = primitive_size(ident);
+                quote! { #size }
+            }
+            _ => match box_item(type_path) {
+                Some(inner_type) => {
+                    let size = size_expr(inner_type);
+                    quote! { { let it = &**it; #size } }
+                }
+                None => quote! { plod::Plod::size_at_rest(it) },
+            },
+        },
+        Type::Tuple(t) => {
+            let sizes = t.elems.iter().enumerate().map(|(i, ty)| {
+                let index = syn::Index::from(i);
+                let size = size_expr(ty);
+                quote! { { let it = &it.#index; #size } }
+            });
+            quote! { 0 #(+ #sizes)* }
+        }
+        Type::Array(t) => {
+            let size = size_expr(&t.elem);
+            quote! { it.iter().map(|it| #size).sum::<usize>() }
+        }
+        _ => quote! { 0 },
+    }
+}
+
+/// Expression of the size at rest of the `items` of a collection
+fn items_size(items: &TokenStream, item_ty: &Type, item_align: Option<usize>) -> TokenStream {
+    let item_size = size_expr(item_ty);
+    match item_align {
+        Some(align) => quote! {
+            #items.iter().fold(0, |n, it| n + plod::helpers::padding(n, #align) + #item_size)
+        },
+        None => quote! { #items.iter().map(|it| #item_size).sum::<usize>() },
+    }
+}
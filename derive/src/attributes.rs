@@ -1,16 +1,33 @@
-use proc_macro2::Ident;
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
 use quote::quote;
-use syn::parse::{Parse, Result};
-use syn::{Attribute, Lit, LitInt, Pat, Type};
+use syn::parse::{Parse, ParseStream, Result};
+use syn::meta::ParseNestedMeta;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput, Expr, Fields, LitInt, LitStr, Pat, Token, Type, Variant};
+
+use crate::{bytes_or_text, integer_storage, is_phantom, primitive_type, resolve_std_types};
 
 /// Available endiannesses
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Endianness {
     Big,
     Little,
     Native,
 }
 
+/// Encoding of a size prefixed region (compression, transform)
+#[derive(Clone)]
+pub struct Encoding {
+    /// name of the encoding, used by layout description
+    pub name: String,
+    /// path of the `fn(&[u8]) -> Vec<u8>` encoding function
+    pub encode: TokenStream,
+    /// path of the `fn(&[u8]) -> plod::Result<Vec<u8>>` decoding function
+    pub decode: TokenStream,
+    /// constant failing to evaluate if the encoding is not available
+    pub check: Option<TokenStream>,
+}
+
 /// Attributes that can be used with derive, all in one structure to make it easier to parse and inherit.
 #[derive(Clone)]
 pub struct Attributes {
@@ -18,28 +35,168 @@ pub struct Attributes {
     pub tag_type: Option<Ident>,
     /// value of the tag to detect enum variant (per variant)
     pub tag: Option<Pat>,
-    /// does this variant retains the tag in its first item
+    /// guard on the context that must also hold to select the variant (per variant)
+    pub tag_guard: Option<Expr>,
+    /// other tags read as this variant, which is written with `tag` (per variant)
+    pub tag_aliases: Vec<Pat>,
+    /// tags read as the `Reserved` variant (per enum, and on this variant)
+    pub reserved_tags: Option<Pat>,
+    /// tag written for a variant whose `tag` is a pattern (per variant)
+    pub write_tag: Option<Expr>,
+    /// does this variant retains the tag in one of its items
     pub keep_tag: bool,
+    /// index of the field retaining the tag, the first one unless a field `is_tag` (per variant)
+    pub tag_field: usize,
+    /// this field retains the tag of its variant
+    pub is_tag: bool,
     /// is the above retained different from the tag (how much less)
     pub keep_diff: Option<LitInt>,
     /// type of the vector size storage
     pub size_type: Option<Ident>,
+    /// type of the size storage of nested vectors, one per nesting level
+    pub inner_size_types: Vec<Ident>,
     /// is the vector size counted in items or in bytes
     pub byte_sized: bool,
-    /// Size is off by one
-    pub size_is_next: bool,
+    /// the stored size is the actual size plus this, 1 with `size_is_next`
+    pub size_adjust: i64,
+    /// accept a signed size type, negative sizes being empty collections
+    pub signed_size: bool,
+    /// the stored size counts units of this many items or bytes
+    pub size_unit: Option<usize>,
     /// endianness of the struct
     pub endianness: Endianness,
-    /// magic type and value for this item
-    pub magic: Option<(Ident, Lit)>,
+    /// endianness of the enum tag when it differs from the one of the enum, variants may not share it
+    pub tag_endianness: Option<Endianness>,
+    /// the tag is made of these named primitive fields instead of a single `tag_type`
+    pub tag_fields: Vec<(Ident, Ident)>,
+    /// fields of all the variants of an enum, stored once before the tag
+    pub common_fields: Vec<(Ident, Ident)>,
+    /// this variant field is one of the `common_fields` of its enum
+    pub common: bool,
+    /// the variants are selected by their magic, which is then the tag (per enum)
+    pub dispatch_on_magic: bool,
+    /// magic type and accepted values for this item, the first one is written
+    pub magic: Option<(Ident, Vec<Pat>)>,
+    /// the magic value is retained in the first field
+    pub keep_magic: bool,
+    /// magic type and accepted values after the fields of this item, the first one is written
+    pub trailer_magic: Option<(Ident, Vec<Pat>)>,
+    /// the first field is a marker of this type telling the endianness of the struct: big and
+    /// little values
+    pub endian_marker: Option<(Ident, Pat, Pat)>,
     /// skip next item at rest
     pub skip: bool,
     /// context type
     pub context_type: Type,
+    /// this skipped `usize` field holds the position at which it would be read
+    pub record_offset: bool,
     /// this field must be used as a context in subsequent read/write operations
     pub is_context: bool,
+    /// the context of this field is converted with `TryFrom` instead of `Into`
+    pub try_context: bool,
+    /// the fields of this struct are part of the enclosing one, with the same context
+    pub flatten: bool,
     /// do not generate position handling code
     pub no_pos: bool,
+    /// generate an `Arbitrary` implementation
+    pub arbitrary: bool,
+    /// print the generated code during compilation
+    pub debug_derive: bool,
+    /// the in memory representation is the at rest one, implement `plod::pod::Pod`
+    pub pod_cast: bool,
+    /// generate a `new` constructor without the fields managed by the format
+    pub constructor: bool,
+    /// generate a `field_sizes` method reporting the bytes taken by each field
+    pub field_sizes: bool,
+    /// generate a `tag_name` function returning the name of the variant of a tag
+    pub tag_names: bool,
+    /// generate a constant with the tag of each variant
+    pub tag_consts: bool,
+    /// encoding of the text of `String` and `Cow<str>` fields
+    pub text_encoding: Option<Ident>,
+    /// this unsigned integer is stored as a varint of this flavor
+    pub varint: Option<Ident>,
+    /// this text is a `BSTR`, followed by a NUL character not counted in its size
+    pub bstr: bool,
+    /// the item is stored encoded in a size prefixed region
+    pub encoding: Option<Encoding>,
+    /// type of the total byte length written before the whole struct or enum
+    pub length_prefix: Option<Ident>,
+    /// type of the total byte length written after the whole struct or enum, followed by the type
+    /// and the algorithm of a checksum of all its bytes
+    pub length_footer: Option<(Ident, Option<(Ident, Expr)>)>,
+    /// the named `plod::Preserved` field keeps reserved and unknown trailing bytes to write them back
+    pub preserve_unknown: Option<Ident>,
+    /// byte sizes are patched after writing the content when the writer is seekable
+    pub patched_size: bool,
+    /// internal: generating write code for seekable writers
+    pub seek: bool,
+    /// this integer field stores the size of the named collection field
+    pub len_of: Option<Ident>,
+    /// the items of this `Vec` are stored column by column
+    pub columnar: bool,
+    /// the integer field storing the number of items of this columnar `Vec`
+    pub columnar_count: Option<Ident>,
+    /// internal: the collection size is stored in this previous field instead of a prefix
+    pub length_field: Option<Ident>,
+    /// internal: the bytes are allocated in the arena of the context instead of a `Vec`
+    pub arena: bool,
+    /// internal: the bytes are read in this `plod::InlineBytes<N>` instead of a `Vec`
+    pub inline: Option<Type>,
+    /// this integer field stores the tag of the named enum field
+    pub tag_for: Option<Ident>,
+    /// this enum field is stored without its tag, which is stored by a previous `tag_for` field
+    pub external_tag: bool,
+    /// internal: the previous field storing the tag of this enum, its type and endianness
+    pub tag_source: Option<(Ident, Ident, Endianness)>,
+    /// the bytes this field is read from are also kept in the named field
+    pub keep_raw: Option<Ident>,
+    /// internal: this field is not stored, it holds the bytes of another field
+    pub raw_of: bool,
+    /// number of reserved bytes before this field, skipped on read and written as zeroes
+    pub reserved: usize,
+    /// this `Vec<u8>` field holds all the remaining bytes
+    pub rest: bool,
+    /// the size of this last `Vec` is stored after its items, at the end of the input
+    pub size_after: bool,
+    /// this `Vec<bool>` is packed 8 booleans per byte
+    pub bitmap: bool,
+    /// bitmaps are packed from the highest bit of each byte instead of the lowest one
+    pub msb_first: bool,
+    /// this byte array always holds this constant, checked on read and written as is
+    pub equals: Option<Expr>,
+    /// this `Option` field is present only if bytes remain
+    pub if_remaining: bool,
+    /// length prefixed items of this `Vec` containing an unknown tag are skipped
+    pub skip_unknown: bool,
+    /// method called on the value once it has been read
+    pub after_read: Option<Ident>,
+    /// method called on the value before it is written
+    pub before_write: Option<Ident>,
+    /// fallible function building the value from its fields instead of a struct literal
+    pub construct_with: Option<syn::Path>,
+    /// the field is stored at this absolute offset instead of inline
+    pub seek_to: Option<Expr>,
+    /// this integer field is the checksum of the previous fields with this algorithm
+    pub checksum: Option<Expr>,
+    /// every variant of the enum takes this many bytes after the tag
+    pub variant_size: Option<usize>,
+    /// the size at rest must be this, checked at compile time when possible
+    pub expected_size: Option<usize>,
+    /// the size of the struct or enum is padded to a multiple of this
+    pub align_size: Option<usize>,
+    /// items of this collection start at a multiple of this from the first one
+    pub item_align: Option<usize>,
+    /// the field type is handled as this one, for type aliases
+    pub as_type: Option<Type>,
+    /// the field starts at this absolute offset, preceded by fill bytes
+    pub pad_to_offset: Option<Expr>,
+    /// value of the bytes written by pad_to_offset
+    pub fill: Option<u8>,
+    /// do not inherit attributes from the enclosing type or enum
+    pub no_inherit: bool,
+    /// internal: the field only exists when this `#[cfg(...)]` predicate holds
+    pub cfg: Option<TokenStream>,
 }
 
 impl Default for Attributes {
@@ -47,19 +204,221 @@ impl Default for Attributes {
         Attributes {
             tag_type: None,
             tag: None,
+            tag_guard: None,
+            tag_aliases: Vec::new(),
+            reserved_tags: None,
+            write_tag: None,
             keep_tag: false,
+            tag_field: 0,
+            is_tag: false,
             keep_diff: None,
             size_type: None,
+            inner_size_types: Vec::new(),
             byte_sized: false,
-            size_is_next: false,
+            size_adjust: 0,
+            size_unit: None,
+            signed_size: false,
             endianness: Endianness::Native,
+            tag_endianness: None,
+            tag_fields: Vec::new(),
+            common_fields: Vec::new(),
+            common: false,
+            dispatch_on_magic: false,
             magic: None,
+            keep_magic: false,
+            trailer_magic: None,
+            endian_marker: None,
             skip: false,
             context_type: Type::Verbatim(quote! { () }),
+            record_offset: false,
             is_context: false,
+            try_context: false,
+            flatten: false,
             no_pos: false,
+            arbitrary: false,
+            debug_derive: false,
+            pod_cast: false,
+            constructor: false,
+            text_encoding: None,
+            varint: None,
+            bstr: false,
+            field_sizes: false,
+            tag_names: false,
+            tag_consts: false,
+            encoding: None,
+            length_prefix: None,
+            preserve_unknown: None,
+            length_footer: None,
+            patched_size: false,
+            seek: false,
+            len_of: None,
+            columnar: false,
+            columnar_count: None,
+            length_field: None,
+            arena: false,
+            inline: None,
+            tag_for: None,
+            external_tag: false,
+            tag_source: None,
+            keep_raw: None,
+            raw_of: false,
+            reserved: 0,
+            rest: false,
+            size_after: false,
+            bitmap: false,
+            msb_first: false,
+            equals: None,
+            if_remaining: false,
+            skip_unknown: false,
+            after_read: None,
+            construct_with: None,
+            before_write: None,
+            seek_to: None,
+            checksum: None,
+            variant_size: None,
+            expected_size: None,
+            align_size: None,
+            item_align: None,
+            as_type: None,
+            pad_to_offset: None,
+            fill: None,
+            no_inherit: false,
+            cfg: None,
+        }
+    }
+}
+
+/// Parse the type and the accepted values of `<key>(<type>=<values>)`
+fn parse_magic(meta: &ParseNestedMeta, key: &str) -> Result<(Ident, Vec<Pat>)> {
+    let mut magic = None;
+    meta.parse_nested_meta(|meta| {
+        let ident = meta.path.get_ident().ok_or(meta.error(format!(
+            "Magic must be of the form #[plod({}(<type>=<value>))]",
+            key
+        )))?;
+        let pattern = Pat::parse_multi(meta.value()?)?;
+        let mut values = Vec::new();
+        magic_values(&pattern, &mut values)?;
+        magic = Some((ident.clone(), values));
+        Ok(())
+    })?;
+    magic.ok_or(meta.error(format!("Magic must be of the form #[plod({}(<type>=<value>))]", key)))
+}
+
+/// Collect the literals of a magic value, alternatives are separated by `|`
+fn magic_values(pattern: &Pat, values: &mut Vec<Pat>) -> Result<()> {
+    match pattern {
+        _ if is_value(pattern) => values.push(pattern.clone()),
+        Pat::Or(or) => {
+            for case in or.cases.iter() {
+                magic_values(case, values)?;
+            }
+        }
+        _ => {
+            return Err(syn::Error::new(
+                pattern.span(),
+                "Magic values must be literals or constants, alternatives are separated by |",
+            ))
         }
     }
+    Ok(())
+}
+
+/// Is the pattern a single value usable as an expression: a literal or a path to a constant
+pub fn is_value(pattern: &Pat) -> bool {
+    match pattern {
+        Pat::Lit(_) | Pat::Path(_) => true,
+        Pat::Ident(ident) => {
+            ident.by_ref.is_none() && ident.mutability.is_none() && ident.subpat.is_none()
+        }
+        _ => false,
+    }
+}
+
+/// Parse an expression of an attribute, `ctx` refers to the context of generated code even if the
+/// derive is expanded by a macro (like the ones of `plod::plod_profile!`)
+fn parse_expr(input: syn::parse::ParseStream) -> Result<Expr> {
+    fn call_site_ctx(tokens: TokenStream) -> TokenStream {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                TokenTree::Ident(ident) if ident == "ctx" => {
+                    TokenTree::Ident(Ident::new("ctx", ident.span().resolved_at(Span::call_site())))
+                }
+                TokenTree::Group(group) => {
+                    let mut result =
+                        proc_macro2::Group::new(group.delimiter(), call_site_ctx(group.stream()));
+                    result.set_span(group.span());
+                    TokenTree::Group(result)
+                }
+                other => other,
+            })
+            .collect()
+    }
+    let expr = Expr::parse(input)?;
+    syn::parse2(call_site_ctx(quote! { #expr }))
+}
+
+/// All keys accepted in `#[plod(...)]`
+const KEYS: &[&str] = &[
+    "tag", "tag_aliases", "reserved_tags", "write_tag", "keep_diff", "context", "big_endian", "little_endian", "native_endian", "no_pos",
+    "keep_tag", "is_tag", "byte_sized", "len_of", "tag_for", "external_tag", "keep_raw", "columnar", "reserved", "after_read", "before_write", "construct_with", "align_size",
+    "variant_size", "expected_size", "as", "as_vec", "as_primitive", "seek_to", "checksum", "skip_unknown", "rest", "size_after", "bitmap", "bit_order", "equals",
+    "patched_size", "size_is_next", "size_adjust", "size_unit", "signed_size", "skip", "record_offset", "is_context", "try_context", "arbitrary", "debug_derive", "compress",
+    "transform", "pod_cast", "constructor", "field_sizes", "tag_names", "tag_consts", "encoding", "bstr", "varint", "if_remaining", "item_align", "flatten", "pad_to_offset", "fill",
+    "no_inherit", "magic", "keep_magic", "trailer_magic", "endian_marker", "tag_type", "tag_fields", "common_fields", "dispatch_on_magic", "tag_endianness", "length_prefixed", "length_footer", "preserve_unknown", "size_type",
+];
+
+/// Parse a list of primitive fields: `<key>(<name>: <type>, ...)`
+fn parse_fields(meta: &ParseNestedMeta, key: &str) -> Result<Vec<(Ident, Ident)>> {
+    let content;
+    syn::parenthesized!(content in meta.input);
+    let mut fields = Vec::new();
+    while !content.is_empty() {
+        let name = Ident::parse(&content)?;
+        content.parse::<syn::Token![:]>()?;
+        let ty = Ident::parse(&content)?;
+        if !primitive_type(&ty) {
+            return Err(syn::Error::new(ty.span(), format!("{} must be primitive types", key)));
+        }
+        fields.push((name, ty));
+        if !content.is_empty() {
+            content.parse::<syn::Token![,]>()?;
+        }
+    }
+    if fields.is_empty() {
+        return Err(meta.error(format!("{} needs at least one field", key)));
+    }
+    Ok(fields)
+}
+
+/// Error message for an unknown key, with the nearest valid key if there is a close one
+fn unknown_key(path: &syn::Path) -> String {
+    let key = quote! { #path }.to_string().replace(' ', "");
+    let nearest = KEYS
+        .iter()
+        .map(|k| (edit_distance(&key, k), k))
+        .min()
+        .filter(|(distance, _)| *distance <= 2.max(key.len() / 3));
+    match nearest {
+        Some((_, k)) => format!("Unknown plod attribute `{}`, did you mean `{}`?", key, k),
+        None => format!("Unknown plod attribute `{}`", key),
+    }
+}
+
+/// Levenshtein distance between two keys
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
 }
 
 /// A single Attribute structure makes it easier to write parsing code but give worse error reporting
@@ -79,8 +438,26 @@ impl Attributes {
             }
             let meta_parser = syn::meta::parser(|meta| {
                 if meta.path.is_ident("tag") {
-                    let value = Pat::parse_multi(meta.value()?)?;
-                    self.tag = Some(value);
+                    let input = meta.value()?;
+                    self.tag = Some(Pat::parse_multi(input)?);
+                    self.tag_guard = if input.peek(syn::Token![if]) {
+                        input.parse::<syn::Token![if]>()?;
+                        Some(parse_expr(input)?)
+                    } else {
+                        None
+                    };
+                } else if meta.path.is_ident("write_tag") {
+                    self.write_tag = Some(parse_expr(meta.value()?)?);
+                } else if meta.path.is_ident("reserved_tags") {
+                    self.reserved_tags = Some(Pat::parse_multi(meta.value()?)?);
+                } else if meta.path.is_ident("tag_aliases") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let aliases = syn::punctuated::Punctuated::<Pat, Token![,]>::parse_terminated_with(&content, Pat::parse_single)?;
+                    if let Some(alias) = aliases.iter().find(|alias| !is_value(alias)) {
+                        return Err(syn::Error::new(alias.span(), "tag_aliases must be values, not patterns"));
+                    }
+                    self.tag_aliases = aliases.into_iter().collect();
                 } else if meta.path.is_ident("keep_diff") {
                     let lit = LitInt::parse(meta.value()?)?;
                     self.keep_diff = Some(lit);
@@ -93,39 +470,291 @@ impl Attributes {
                     self.endianness = Endianness::Little;
                 } else if meta.path.is_ident("native_endian") {
                     self.endianness = Endianness::Native;
-                } else if meta.path.is_ident("mo_pos") {
+                } else if meta.path.is_ident("no_pos") || meta.path.is_ident("mo_pos") {
+                    // mo_pos is an old misspelling kept for compatibility
                     self.no_pos = true;
                 } else if meta.path.is_ident("keep_tag") {
                     self.keep_tag = true;
+                } else if meta.path.is_ident("is_tag") {
+                    self.is_tag = true;
                 } else if meta.path.is_ident("byte_sized") {
                     self.byte_sized = true;
+                } else if meta.path.is_ident("columnar") {
+                    self.columnar = true;
+                    if meta.input.peek(syn::token::Paren) {
+                        meta.parse_nested_meta(|meta| {
+                            if !meta.path.is_ident("count") {
+                                return Err(meta.error("columnar must be of the form #[plod(columnar)] or #[plod(columnar(count = <field>))]"));
+                            }
+                            self.columnar_count = Some(Ident::parse(meta.value()?)?);
+                            Ok(())
+                        })?;
+                    }
+                } else if meta.path.is_ident("len_of") {
+                    self.len_of = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("tag_for") {
+                    self.tag_for = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("keep_raw") {
+                    self.keep_raw = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("external_tag") {
+                    self.external_tag = true;
+                } else if meta.path.is_ident("reserved") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    self.reserved = LitInt::parse(&content)?.base10_parse()?;
+                } else if meta.path.is_ident("construct_with") {
+                    self.construct_with = Some(syn::Path::parse(meta.value()?)?);
+                } else if meta.path.is_ident("after_read") {
+                    self.after_read = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("before_write") {
+                    self.before_write = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("align_size") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    self.align_size = Some(LitInt::parse(&content)?.base10_parse()?);
+                } else if meta.path.is_ident("item_align") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    self.item_align = Some(LitInt::parse(&content)?.base10_parse()?);
+                } else if meta.path.is_ident("variant_size") {
+                    self.variant_size = Some(LitInt::parse(meta.value()?)?.base10_parse()?);
+                } else if meta.path.is_ident("expected_size") {
+                    self.expected_size = Some(LitInt::parse(meta.value()?)?.base10_parse()?);
+                } else if meta.path.is_ident("as") {
+                    let value = meta.value()?;
+                    self.as_type = Some(if value.peek(LitStr) {
+                        value.parse::<LitStr>()?.parse()?
+                    } else {
+                        Type::parse(value)?
+                    });
+                } else if meta.path.is_ident("as_vec") {
+                    self.as_type = Some(syn::parse_quote! { Vec<u8> });
+                } else if meta.path.is_ident("as_primitive") {
+                    meta.parse_nested_meta(|meta| {
+                        match meta.path.get_ident() {
+                            Some(ident) => self.as_type = Some(syn::parse_quote! { #ident }),
+                            None => return Err(meta.error("as_primitive must be a primitive type")),
+                        }
+                        Ok(())
+                    })?;
+                } else if meta.path.is_ident("seek_to") {
+                    self.seek_to = Some(parse_expr(meta.value()?)?);
+                } else if meta.path.is_ident("checksum") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    self.checksum = Some(parse_expr(&content)?);
+                } else if meta.path.is_ident("pad_to_offset") {
+                    self.pad_to_offset = Some(parse_expr(meta.value()?)?);
+                } else if meta.path.is_ident("fill") {
+                    self.fill = Some(LitInt::parse(meta.value()?)?.base10_parse()?);
+                } else if meta.path.is_ident("no_inherit") {
+                    self.no_inherit = true;
+                } else if meta.path.is_ident("skip_unknown") {
+                    self.skip_unknown = true;
+                } else if meta.path.is_ident("rest") {
+                    self.rest = true;
+                } else if meta.path.is_ident("size_after") {
+                    self.size_after = true;
+                } else if meta.path.is_ident("equals") {
+                    self.equals = Some(parse_expr(meta.value()?)?);
+                } else if meta.path.is_ident("bitmap") {
+                    self.bitmap = true;
+                } else if meta.path.is_ident("bit_order") {
+                    let order = Ident::parse(meta.value()?)?;
+                    self.msb_first = match order.to_string().as_str() {
+                        "lsb_first" => false,
+                        "msb_first" => true,
+                        _ => {
+                            return Err(syn::Error::new(
+                                order.span(),
+                                "Unknown bit order, available bit orders: lsb_first, msb_first",
+                            ))
+                        }
+                    };
+                } else if meta.path.is_ident("if_remaining") {
+                    self.if_remaining = true;
+                } else if meta.path.is_ident("patched_size") {
+                    self.patched_size = true;
                 } else if meta.path.is_ident("size_is_next") {
-                    self.size_is_next = true;
+                    self.size_adjust = 1;
+                } else if meta.path.is_ident("size_adjust") {
+                    self.size_adjust = parse_signed(meta.value()?)?;
+                } else if meta.path.is_ident("signed_size") {
+                    self.signed_size = true;
+                } else if meta.path.is_ident("size_unit") {
+                    let unit: usize = LitInt::parse(meta.value()?)?.base10_parse()?;
+                    if unit == 0 {
+                        return Err(meta.error("size_unit must not be 0"));
+                    }
+                    self.size_unit = Some(unit);
                 } else if meta.path.is_ident("skip") {
                     self.skip = true;
+                } else if meta.path.is_ident("record_offset") {
+                    self.record_offset = true;
+                } else if meta.path.is_ident("flatten") {
+                    self.flatten = true;
                 } else if meta.path.is_ident("is_context") {
                     self.is_context = true;
+                } else if meta.path.is_ident("try_context") {
+                    self.try_context = true;
+                } else if meta.path.is_ident("arbitrary") {
+                    self.arbitrary = true;
+                } else if meta.path.is_ident("debug_derive") {
+                    self.debug_derive = true;
+                } else if meta.path.is_ident("pod_cast") {
+                    self.pod_cast = true;
+                } else if meta.path.is_ident("constructor") {
+                    self.constructor = true;
+                } else if meta.path.is_ident("field_sizes") {
+                    self.field_sizes = true;
+                } else if meta.path.is_ident("tag_names") {
+                    self.tag_names = true;
+                } else if meta.path.is_ident("tag_consts") {
+                    self.tag_consts = true;
+                } else if meta.path.is_ident("encoding") {
+                    let encoding = Ident::parse(meta.value()?)?;
+                    if !["utf8_strict", "utf8_lossy", "latin1", "ascii", "utf16le", "utf16be"].iter().any(|e| encoding == e) {
+                        return Err(syn::Error::new(
+                            encoding.span(),
+                            "Unknown encoding, available encodings: utf8_strict, utf8_lossy, latin1, ascii, utf16le, utf16be",
+                        ));
+                    }
+                    self.text_encoding = Some(encoding);
+                } else if meta.path.is_ident("varint") {
+                    let flavor = Ident::parse(meta.value()?)?;
+                    self.varint = match flavor.to_string().as_str() {
+                        // protobuf varints are LEB128
+                        "leb128" | "protobuf" => Some(Ident::new("leb128", flavor.span())),
+                        "midi_vlq" | "git_vlq" | "sqlite" => Some(flavor),
+                        _ => {
+                            return Err(syn::Error::new(
+                                flavor.span(),
+                                "Unknown varint, available flavors: leb128, protobuf, midi_vlq, git_vlq, sqlite",
+                            ))
+                        }
+                    };
+                } else if meta.path.is_ident("bstr") {
+                    // the size in bytes is always a little endian u32
+                    self.bstr = true;
+                    self.size_type = Some(Ident::new("u32", meta.path.span()));
+                    self.text_encoding = Some(Ident::new("utf16le", meta.path.span()));
+                    self.endianness = Endianness::Little;
+                } else if meta.path.is_ident("compress") {
+                    let algorithm = Ident::parse(meta.value()?)?;
+                    if !["zlib", "zstd", "lz4"].iter().any(|a| algorithm == a) {
+                        return Err(syn::Error::new(
+                            algorithm.span(),
+                            "Unsupported compression, expected zlib, zstd or lz4",
+                        ));
+                    }
+                    self.encoding = Some(Encoding {
+                        name: algorithm.to_string(),
+                        encode: quote! { plod::compress::#algorithm::compress },
+                        decode: quote! { plod::compress::#algorithm::decompress },
+                        check: Some(quote! { plod::compress::#algorithm::Feature::<()>::CHECK }),
+                    });
+                } else if meta.path.is_ident("transform") {
+                    let path = syn::Path::parse(meta.value()?)?;
+                    let name = quote! { #path }.to_string().replace(' ', "");
+                    self.encoding = Some(Encoding {
+                        name,
+                        encode: quote! { #path::encode },
+                        decode: quote! { #path::decode },
+                        check: None,
+                    });
                 } else if meta.path.is_ident("magic") {
+                    self.magic = Some(parse_magic(&meta, "magic")?);
+                } else if meta.path.is_ident("trailer_magic") {
+                    self.trailer_magic = Some(parse_magic(&meta, "trailer_magic")?);
+                } else if meta.path.is_ident("dispatch_on_magic") {
+                    self.dispatch_on_magic = true;
+                } else if meta.path.is_ident("keep_magic") {
+                    self.keep_magic = true;
+                } else if meta.path.is_ident("endian_marker") {
+                    let form = "endian_marker must be of the form #[plod(endian_marker(<type>, big = <value>, little = <value>))]";
+                    let (mut ty, mut big, mut little) = (None, None, None);
                     meta.parse_nested_meta(|meta| {
-                        let ident = meta.path.get_ident().ok_or(
-                            meta.error("Magic must be of the form #[plod(magic(<type>=<value>))]"),
-                        )?;
-                        let lit = Lit::parse(meta.value()?)?;
-                        self.magic = Some((ident.clone(), lit));
+                        if meta.path.is_ident("big") {
+                            big = Some(Pat::parse_single(meta.value()?)?);
+                        } else if meta.path.is_ident("little") {
+                            little = Some(Pat::parse_single(meta.value()?)?);
+                        } else if ty.is_none() {
+                            ty = Some(meta.path.get_ident().cloned().ok_or(meta.error(form))?);
+                        } else {
+                            return Err(meta.error(form));
+                        }
                         Ok(())
                     })?;
+                    match (ty, big, little) {
+                        (Some(ty), Some(big), Some(little)) if is_value(&big) && is_value(&little) => {
+                            self.endian_marker = Some((ty, big, little))
+                        }
+                        _ => return Err(meta.error(form)),
+                    }
                 } else if meta.path.is_ident("tag_type") {
                     meta.parse_nested_meta(|meta| {
                         self.tag_type = meta.path.get_ident().cloned();
                         Ok(())
                     })?;
+                } else if meta.path.is_ident("tag_fields") {
+                    self.tag_fields = parse_fields(&meta, "tag_fields")?;
+                } else if meta.path.is_ident("common_fields") {
+                    self.common_fields = parse_fields(&meta, "common_fields")?;
+                } else if meta.path.is_ident("tag_endianness") {
+                    meta.parse_nested_meta(|meta| {
+                        self.tag_endianness = Some(if meta.path.is_ident("big_endian") {
+                            Endianness::Big
+                        } else if meta.path.is_ident("little_endian") {
+                            Endianness::Little
+                        } else if meta.path.is_ident("native_endian") {
+                            Endianness::Native
+                        } else {
+                            return Err(meta.error(
+                                "tag_endianness accepts big_endian, little_endian or native_endian",
+                            ));
+                        });
+                        Ok(())
+                    })?;
+                } else if meta.path.is_ident("preserve_unknown") {
+                    self.preserve_unknown = Some(Ident::parse(meta.value()?)?);
+                } else if meta.path.is_ident("length_prefixed") {
+                    meta.parse_nested_meta(|meta| {
+                        self.length_prefix = meta.path.get_ident().cloned();
+                        Ok(())
+                    })?;
+                } else if meta.path.is_ident("length_footer") {
+                    let form = "length_footer must be of the form #[plod(length_footer(<type>))] or #[plod(length_footer(<type>, checksum(<type>, <algorithm>)))]";
+                    let (mut length_ty, mut checksum) = (None, None);
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("checksum") {
+                            let content;
+                            syn::parenthesized!(content in meta.input);
+                            let ty = Ident::parse(&content)?;
+                            content.parse::<syn::Token![,]>()?;
+                            checksum = Some((ty, parse_expr(&content)?));
+                        } else if length_ty.is_none() && checksum.is_none() {
+                            length_ty = Some(meta.path.get_ident().ok_or(meta.error(form))?.clone());
+                        } else {
+                            return Err(meta.error(form));
+                        }
+                        Ok(())
+                    })?;
+                    self.length_footer = Some((length_ty.ok_or(meta.error(form))?, checksum));
                 } else if meta.path.is_ident("size_type") {
+                    // one type per nesting level, outermost first
+                    let mut size_types = Vec::new();
                     meta.parse_nested_meta(|meta| {
-                        self.size_type = meta.path.get_ident().cloned();
+                        match meta.path.get_ident() {
+                            Some(ident) => size_types.push(ident.clone()),
+                            None => return Err(meta.error("size_type must be an integer type")),
+                        }
                         Ok(())
                     })?;
+                    let mut size_types = size_types.into_iter();
+                    self.size_type = size_types.next();
+                    self.inner_size_types = size_types.collect();
                 } else {
-                    return Err(meta.error("Unsupported plod value"));
+                    return Err(meta.error(unknown_key(&meta.path)));
                 }
                 Ok(())
             });
@@ -134,13 +763,424 @@ impl Attributes {
         Ok(())
     }
 
+    /// attributes of an `endian_marker` type read or written with one endianness, the marker is
+    /// then a kept magic
+    pub fn with_marker(&self, endianness: Endianness) -> Self {
+        let mut result = self.clone();
+        if let Some((ty, big, little)) = &self.endian_marker {
+            let value = match endianness {
+                Endianness::Big => big,
+                _ => little,
+            };
+            result.endianness = endianness;
+            result.magic = Some((ty.clone(), vec![value.clone()]));
+            result.keep_magic = true;
+        }
+        result
+    }
+
+    /// with `dispatch_on_magic`, the tag type of the enum is the type of the magic of its variants
+    pub fn magic_tag_type(&mut self, input: &DeriveInput) -> Result<()> {
+        if !self.dispatch_on_magic {
+            return Ok(());
+        }
+        let error = |span: proc_macro2::Span, message| Err(syn::Error::new(span, message));
+        let data = match &input.data {
+            Data::Enum(data) => data,
+            _ => return error(input.ident.span(), "dispatch_on_magic only works with enums"),
+        };
+        if self.tag_type.is_some() || !self.tag_fields.is_empty() {
+            return error(input.ident.span(), "dispatch_on_magic replaces tag_type and tag_fields");
+        }
+        let mut tag_type: Option<Ident> = None;
+        for variant in data.variants.iter() {
+            let mut variant_attributes = Attributes::default();
+            variant_attributes._parse(&variant.attrs)?;
+            if variant_attributes.skip {
+                continue;
+            }
+            if variant_attributes.tag.is_some() {
+                return error(variant.ident.span(), "Variants dispatched on their magic have no tag");
+            }
+            match (&variant_attributes.magic, &tag_type) {
+                (None, _) => {
+                    return error(variant.ident.span(), "Each variant needs a magic with dispatch_on_magic")
+                }
+                (Some((ty, _)), Some(previous)) if ty != previous => {
+                    return error(ty.span(), "All variant magics must have the same type")
+                }
+                (Some((ty, _)), _) => tag_type = Some(ty.clone()),
+            }
+        }
+        self.tag_type = tag_type;
+        Ok(())
+    }
+
+    /// Pattern of the tags read as this variant: its tag or one of its aliases
+    pub fn tag_pattern(&self) -> Option<Pat> {
+        let tag = self.tag.clone()?;
+        if self.tag_aliases.is_empty() {
+            return Some(tag);
+        }
+        let mut cases: syn::punctuated::Punctuated<Pat, Token![|]> = match tag {
+            Pat::Or(or) => or.cases,
+            tag => std::iter::once(tag).collect(),
+        };
+        cases.extend(self.tag_aliases.iter().cloned());
+        Some(Pat::Or(syn::PatOr { attrs: Vec::new(), leading_vert: None, cases }))
+    }
+
+    /// total size of the `tag_fields`
+    pub fn tag_fields_size(&self) -> usize {
+        self.tag_fields
+            .iter()
+            .map(|(_, ty)| crate::primitive_size(ty).base10_parse::<usize>().unwrap())
+            .sum()
+    }
+
+    /// index of the field retaining the tag or the magic, already read before the fields
+    pub fn kept_field(&self) -> Option<usize> {
+        if self.keep_tag {
+            Some(self.tag_field)
+        } else if self.keep_magic {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// `#[cfg(...)]` of a field that only exists under some configuration, empty otherwise
+    pub fn cfg_attr(&self) -> TokenStream {
+        match &self.cfg {
+            Some(predicate) => quote! { #[cfg(#predicate)] },
+            None => TokenStream::new(),
+        }
+    }
+
+    /// Expression of `value` when the field exists and of `absent` otherwise
+    pub fn cfg_value(&self, value: TokenStream, absent: TokenStream) -> TokenStream {
+        match &self.cfg {
+            Some(predicate) => quote! {
+                {
+                    #[cfg(#predicate)]
+                    let value = #value;
+                    #[cfg(not(#predicate))]
+                    let value = #absent;
+                    value
+                }
+            },
+            None => value,
+        }
+    }
+
+    /// attributes of the items of an array, the alignment is the one of the collection
+    pub fn array_item(&self) -> Self {
+        let mut result = self.clone();
+        result.item_align = None;
+        result.size_unit = None;
+        result.columnar = false;
+        result.columnar_count = None;
+        result
+    }
+
+    /// attributes of the items of a `Vec`, they use the next size type if there is one
+    pub fn vec_item(&self) -> Self {
+        let mut result = self.array_item();
+        if !result.inner_size_types.is_empty() {
+            result.size_type = Some(result.inner_size_types.remove(0));
+        }
+        result
+    }
+
     /// parse attributes that override existing attributes
     pub fn extend(&self, attrs: &Vec<Attribute>) -> Result<Self> {
+        let mut local = Attributes::default();
+        local._parse(attrs)?;
+        if local.no_inherit {
+            // only keep what is needed to generate code within the type
+            let mut result = Attributes {
+                tag_type: self.tag_type.clone(),
+                tag_endianness: self.tag_endianness,
+                context_type: self.context_type.clone(),
+                no_pos: self.no_pos,
+                arbitrary: self.arbitrary,
+                debug_derive: self.debug_derive,
+                seek: self.seek,
+                ..Attributes::default()
+            };
+            result._parse(attrs)?;
+            return Ok(result);
+        }
         let mut result = self.clone();
         // reset non-inherited attributes
         result.magic = None;
+        result.common = false;
+        result.tag_field = 0;
+        result.is_tag = false;
+        result.keep_magic = false;
+        result.trailer_magic = None;
+        result.endian_marker = None;
+        result.record_offset = false;
         result.is_context = false;
+        result.try_context = false;
+        result.bstr = false;
+        result.varint = None;
+        result.flatten = false;
+        result.encoding = None;
+        result.length_prefix = None;
+        result.reserved_tags = None;
+        result.write_tag = None;
+        result.preserve_unknown = None;
+        result.length_footer = None;
+        result.len_of = None;
+        result.length_field = None;
+        result.arena = false;
+        result.inline = None;
+        result.tag_for = None;
+        result.external_tag = false;
+        result.tag_source = None;
+        result.keep_raw = None;
+        result.raw_of = false;
+        result.columnar = false;
+        result.columnar_count = None;
+        result.reserved = 0;
+        result.rest = false;
+        result.size_after = false;
+        result.bitmap = false;
+        result.equals = None;
+        result.if_remaining = false;
+        result.skip_unknown = false;
+        result.after_read = None;
+        result.before_write = None;
+        result.construct_with = None;
+        result.seek_to = None;
+        result.checksum = None;
+        result.variant_size = None;
+        result.expected_size = None;
+        result.align_size = None;
+        result.item_align = None;
+        result.as_type = None;
+        result.pad_to_offset = None;
+        result.fill = None;
+        result.dispatch_on_magic = false;
+        result.cfg = None;
         result._parse(attrs)?;
+        if self.dispatch_on_magic {
+            // variant of an enum dispatched on magic, the magic is its tag
+            if let Some((_, values)) = result.magic.take() {
+                result.tag = Some(if values.len() == 1 {
+                    values[0].clone()
+                } else {
+                    Pat::Or(syn::PatOr {
+                        attrs: Vec::new(),
+                        leading_vert: None,
+                        cases: values.into_iter().collect(),
+                    })
+                });
+                result.keep_tag = result.keep_magic;
+                result.keep_magic = false;
+            }
+        }
         Ok(result)
     }
+
+    /// attributes of an enum variant, a field marked `is_tag` retains the tag
+    pub fn variant(&self, variant: &Variant) -> Result<Self> {
+        let mut result = self.extend(&variant.attrs)?;
+        // the Reserved variant keeps the tag and the payload of reserved tags
+        if let (Some(tags), true) = (&self.reserved_tags, variant.ident == "Reserved") {
+            let names: Vec<_> = variant.fields.iter().map(|f| f.ident.as_ref().map(|i| i.to_string())).collect();
+            if names != [Some("tag".to_string()), Some("payload".to_string())] {
+                return Err(syn::Error::new(variant.ident.span(), "The Reserved variant must be `Reserved { tag, payload: Vec<u8> }`"));
+            }
+            if result.tag.is_some() || result.keep_tag {
+                return Err(syn::Error::new(variant.ident.span(), "The Reserved variant gets its tag from reserved_tags"));
+            }
+            result.tag = Some(tags.clone());
+            result.keep_tag = true;
+            result.reserved_tags = Some(tags.clone());
+        }
+        let all_attributes = fields_attributes(&variant.fields, &result)?;
+        let mut tag_fields = variant.fields.iter().zip(&all_attributes).enumerate().filter(|(_, (_, a))| a.is_tag);
+        if let Some((i, _)) = tag_fields.next() {
+            if let Some((_, (field, _))) = tag_fields.next() {
+                return Err(syn::Error::new(field.span(), "Only one field can retain the tag"));
+            }
+            result.keep_tag = true;
+            result.tag_field = i;
+        }
+        Ok(result)
+    }
+}
+
+/// Parse an integer literal that may be negative
+fn parse_signed(input: ParseStream) -> Result<i64> {
+    let negative = input.parse::<Option<Token![-]>>()?.is_some();
+    let value: i64 = LitInt::parse(input)?.base10_parse()?;
+    Ok(if negative { -value } else { value })
+}
+
+/// Get the attributes of each field, `len_of` fields are linked to the collection they measure
+pub fn fields_attributes(fields: &Fields, attributes: &Attributes) -> Result<Vec<Attributes>> {
+    let fields: Vec<_> = fields.iter().collect();
+    let mut result = fields
+        .iter()
+        .map(|f| attributes.extend(&f.attrs))
+        .collect::<Result<Vec<_>>>()?;
+    for (field, field_attributes) in fields.iter().zip(result.iter_mut()) {
+        let predicates = field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("cfg"))
+            .map(|a| Ok(a.meta.require_list()?.tokens.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        field_attributes.cfg = match predicates.len() {
+            0 => None,
+            1 => predicates.into_iter().next(),
+            _ => Some(quote! { all(#(#predicates),*) }),
+        };
+        field_attributes.common = attributes
+            .common_fields
+            .iter()
+            .any(|(name, _)| field.ident.as_ref() == Some(name));
+        // a marker of a type parameter, like a generic context, is not stored
+        if is_phantom(&field.ty) {
+            field_attributes.skip = true;
+        }
+        // the position is not stored, it is filled on read
+        if field_attributes.record_offset {
+            field_attributes.skip = true;
+        }
+        // the payload of a reserved tag is the rest of its region
+        if attributes.reserved_tags.is_some() && field.ident.as_ref().is_some_and(|i| i == "payload") {
+            field_attributes.rest = true;
+        }
+        // a duration is stored as its integer
+        if field_attributes.as_type.is_none() {
+            field_attributes.as_type = integer_storage(&field.ty);
+        }
+    }
+    // the kept bytes are filled while reading the other fields
+    if let Some(holder) = &attributes.preserve_unknown {
+        match fields.iter().position(|f| f.ident.as_ref() == Some(holder)) {
+            Some(j) if result[j].cfg.is_none() => result[j].skip = true,
+            Some(_) => return Err(syn::Error::new(holder.span(), "preserve_unknown is not supported on #[cfg] fields")),
+            None => return Err(syn::Error::new(holder.span(), "preserve_unknown references an unknown field")),
+        }
+    }
+    // the count of a columnar Vec is a length field of this Vec
+    for j in 0..result.len() {
+        let count = match &result[j].columnar_count {
+            Some(count) => count.clone(),
+            None => continue,
+        };
+        let error = |message| Err(syn::Error::new(count.span(), message));
+        let target = match &fields[j].ident {
+            Some(ident) => ident.clone(),
+            None => return error("columnar(count) is only supported on named fields"),
+        };
+        match fields.iter().position(|f| f.ident.as_ref() == Some(&count)) {
+            Some(i) if i < j && result[i].len_of.is_none() => result[i].len_of = Some(target),
+            Some(i) if i < j => return error("The count field is already the length of another field"),
+            Some(_) => return error("columnar(count) must reference a field before the Vec"),
+            None => return error("columnar(count) references an unknown field"),
+        }
+    }
+    for i in 0..result.len() {
+        let target = match &result[i].len_of {
+            Some(target) => target.clone(),
+            None => continue,
+        };
+        let error = |message| Err(syn::Error::new(target.span(), message));
+        let length_ident = match &fields[i].ident {
+            Some(ident) => ident.clone(),
+            None => return error("len_of is only supported on named fields"),
+        };
+        let length_ty = match &resolve_std_types(&fields[i].ty) {
+            Type::Path(p) => p.path.get_ident().cloned(),
+            _ => None,
+        };
+        let length_ty = match length_ty {
+            Some(ty) => ty,
+            None => return error("len_of field must be an integer"),
+        };
+        let j = match fields.iter().position(|f| f.ident.as_ref() == Some(&target)) {
+            Some(j) if j > i => j,
+            Some(_) => return error("len_of must reference a field after the length field"),
+            None => return error("len_of references an unknown field"),
+        };
+        let target_ty = resolve_std_types(result[j].as_type.as_ref().unwrap_or(&fields[j].ty));
+        if !matches!(&target_ty, Type::Path(p) if p.path.segments.first().is_some_and(|s| s.ident == "Vec"))
+            && bytes_or_text(&target_ty).is_none()
+        {
+            return Err(syn::Error::new(
+                fields[j].ty.span(),
+                "len_of must reference a Vec or a Cow",
+            ));
+        }
+        result[j].length_field = Some(length_ident);
+        result[j].size_type = Some(length_ty);
+        result[j].inner_size_types = Vec::new();
+    }
+    for i in 0..result.len() {
+        let target = match &result[i].tag_for {
+            Some(target) => target.clone(),
+            None => continue,
+        };
+        let error = |message| Err(syn::Error::new(target.span(), message));
+        let tag_ident = match &fields[i].ident {
+            Some(ident) => ident.clone(),
+            None => return error("tag_for is only supported on named fields"),
+        };
+        let tag_ty = match &resolve_std_types(&fields[i].ty) {
+            Type::Path(p) => p.path.get_ident().filter(|ty| primitive_type(ty)).cloned(),
+            _ => None,
+        };
+        let tag_ty = match tag_ty {
+            Some(ty) => ty,
+            None => return error("tag_for field must be an integer"),
+        };
+        let j = match fields.iter().position(|f| f.ident.as_ref() == Some(&target)) {
+            Some(j) if j > i => j,
+            Some(_) => return error("tag_for must reference a field after the tag field"),
+            None => return error("tag_for references an unknown field"),
+        };
+        if !result[j].external_tag {
+            return error("tag_for must reference an #[plod(external_tag)] field");
+        }
+        if result[j].tag_source.is_some() {
+            return error("The enum already has a tag field");
+        }
+        if let Some(k) = (i..j).find(|&k| result[k].is_context) {
+            return Err(syn::Error::new(fields[k].span(), "A context field cannot be between a tag_for field and its enum"));
+        }
+        result[j].tag_source = Some((tag_ident, tag_ty, result[i].endianness));
+    }
+    for i in 0..result.len() {
+        let target = match &result[i].keep_raw {
+            Some(target) => target.clone(),
+            None => continue,
+        };
+        let error = |message| Err(syn::Error::new(target.span(), message));
+        if fields[i].ident.is_none() {
+            return error("keep_raw is only supported on named fields");
+        }
+        let j = match fields.iter().position(|f| f.ident.as_ref() == Some(&target)) {
+            Some(j) if j != i => j,
+            Some(_) => return error("keep_raw must reference another field"),
+            None => return error("keep_raw references an unknown field"),
+        };
+        if result[j].raw_of {
+            return error("The field already keeps the bytes of another field");
+        }
+        if result[i].cfg.is_some() || result[j].cfg.is_some() {
+            return error("keep_raw is not supported on #[cfg] fields");
+        }
+        result[j].raw_of = true;
+        result[j].skip = true;
+    }
+    if let Some((field, _)) = fields.iter().zip(&result).find(|(_, a)| a.external_tag && a.tag_source.is_none()) {
+        return Err(syn::Error::new(field.span(), "An #[plod(external_tag)] field needs a previous #[plod(tag_for)] field"));
+    }
+    Ok(result)
 }
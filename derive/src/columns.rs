@@ -0,0 +1,143 @@
+//! Generation of `Plod::impl_read_columns` and `Plod::impl_write_columns` for the items of
+//! `#[plod(columnar)]` vectors: each field is a column stored for all items before the next one
+
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::parse::Result;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields};
+
+use crate::attributes::{fields_attributes, Attributes};
+use crate::{generate_for_item, in_field};
+
+/// Column methods of a struct, nothing when the struct cannot be stored in columns, in which case
+/// the default methods return an error and `columnar` vectors of it don't compile
+pub fn columns_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) if !data.fields.is_empty() => &data.fields,
+        _ => return Ok(TokenStream::new()),
+    };
+    // the struct must be nothing but its fields
+    if attributes.magic.is_some()
+        || attributes.trailer_magic.is_some()
+        || attributes.endian_marker.is_some()
+        || attributes.length_prefix.is_some()
+        || attributes.length_footer.is_some()
+        || attributes.variant_size.is_some()
+        || attributes.align_size.is_some()
+        || attributes.after_read.is_some()
+        || attributes.before_write.is_some()
+        || attributes.construct_with.is_some()
+    {
+        return Ok(TokenStream::new());
+    }
+    let all_attributes = fields_attributes(fields, attributes)?;
+    // each field must only depend on itself
+    if all_attributes.iter().any(|a| {
+        a.len_of.is_some()
+            || a.length_field.is_some()
+            || a.tag_for.is_some()
+            || a.external_tag
+            || a.seek_to.is_some()
+            || a.pad_to_offset.is_some()
+            || a.checksum.is_some()
+            || a.cfg.is_some()
+            || a.reserved > 0
+            || a.rest
+            || a.bitmap
+            || a.varint.is_some()
+            || a.equals.is_some()
+            || a.if_remaining
+            || a.flatten
+            || a.is_context
+            || a.keep_raw.is_some()
+            || a.raw_of
+    }) {
+        return Ok(TokenStream::new());
+    }
+    let type_name = input.ident.to_string();
+    let context = quote! { ctx };
+
+    let mut columns_read = TokenStream::new();
+    let mut columns_write = TokenStream::new();
+    let mut columns = Vec::new();
+    let mut field_names = Vec::new();
+    let item = quote! { item. };
+    for (i, (field, field_attributes)) in fields.iter().zip(&all_attributes).enumerate() {
+        let (field_ident, field_name, member) = match &field.ident {
+            Some(ident) => (ident.clone(), ident.to_string(), quote! { #ident }),
+            None => {
+                let index = syn::Index::from(i);
+                (
+                    Ident::new(&format!("field_{}", i), field.span()),
+                    i.to_string(),
+                    quote! { #index },
+                )
+            }
+        };
+        let mut field_size = TokenStream::new();
+        let mut field_read = TokenStream::new();
+        let mut field_write = TokenStream::new();
+        generate_for_item(
+            &field_ident,
+            &field.ty,
+            &quote! { (& #item #member) },
+            &quote! { #item #member . },
+            false,
+            field_attributes,
+            &mut field_size,
+            &mut field_read,
+            &mut field_write,
+            &context,
+            &context,
+        )?;
+        let mut item_read = TokenStream::new();
+        let mut item_write = TokenStream::new();
+        in_field(
+            &field_ident,
+            &type_name,
+            &field_name,
+            field_attributes,
+            field_read,
+            field_write,
+            &mut item_read,
+            &mut item_write,
+        );
+        let column = Ident::new(&format!("column_{}", i), field.span());
+        columns_read.extend(quote! {
+            let mut #column = Vec::new();
+            for _ in 0..count {
+                #item_read
+                #column.push(#field_ident);
+                plod::cancel::check()?;
+            }
+            let mut #column = #column.into_iter();
+        });
+        columns_write.extend(quote! {
+            for item in items {
+                #item_write
+            }
+        });
+        field_names.push(member);
+        columns.push(column);
+    }
+    // all columns have count items
+    let value = match fields {
+        Fields::Named(_) => quote! { Self { #(#field_names: #columns.next().unwrap(),)* } },
+        _ => quote! { Self ( #(#columns.next().unwrap(),)* ) },
+    };
+
+    Ok(quote! {
+        const COLUMNAR: bool = true;
+
+        fn impl_read_columns<R: std::io::Read>(from: &mut R, ctx: &Self::Context, mut _pos: usize, count: usize) -> plod::Result<Vec<Self>> {
+            #columns_read
+            Ok((0..count).map(|_| #value).collect())
+        }
+
+        fn impl_write_columns<W: std::io::Write>(items: &[Self], to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
+            #columns_write
+            Ok(())
+        }
+    })
+}
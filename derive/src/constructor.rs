@@ -0,0 +1,63 @@
+//! Generation of the `new` constructor of `#[plod(constructor)]` structs, which only takes the
+//! fields whose value is not managed by the format
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::Result;
+use syn::{Data, DeriveInput, Fields};
+
+use crate::attributes::{fields_attributes, Attributes};
+use crate::syn_error;
+
+/// Generate an inherent `new` function for a struct with named fields
+pub fn constructor_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let data_fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return syn_error(&input.ident, "constructor only works with structs"),
+    };
+    let fields = match data_fields {
+        Fields::Named(fields) => fields,
+        _ => return syn_error(&input.ident, "constructor only works with named fields"),
+    };
+    let all_attributes = fields_attributes(data_fields, attributes)?;
+    let mut parameters = TokenStream::new();
+    let mut values = TokenStream::new();
+    for (i, (field, field_attributes)) in fields.named.iter().zip(&all_attributes).enumerate() {
+        // all named fields have an ident
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let cfg = field_attributes.cfg_attr();
+        let value = match &attributes.magic {
+            // the kept magic is the written one
+            Some((magic_ty, magic)) if i == 0 && attributes.keep_magic => {
+                let value = &magic[0];
+                quote! { #value as #magic_ty }
+            }
+            // skipped fields are not stored, sizes and tags are computed on write
+            _ if field_attributes.skip || field_attributes.len_of.is_some() || field_attributes.tag_for.is_some() => {
+                quote! { Default::default() }
+            }
+            _ => {
+                parameters.extend(quote! { #cfg #ident: #ty, });
+                quote! { #ident }
+            }
+        };
+        values.extend(quote! { #cfg #ident: #value, });
+    }
+    let name = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let doc = format!(
+        "Create a `{}` from the fields stored as they are, skipped fields have their default value and computed fields are set when written",
+        name
+    );
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc = #doc]
+            #[allow(clippy::too_many_arguments)]
+            #vis fn new(#parameters) -> Self {
+                #name { #values }
+            }
+        }
+    })
+}
@@ -0,0 +1,78 @@
+//! Printing of the generated code, for `#[plod(debug_derive)]`
+
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// Print the code generated for `name` to stderr during compilation
+pub fn print_expansion(name: &str, tokens: &TokenStream) {
+    let mut code = String::new();
+    format_tokens(tokens.clone(), 0, true, &mut code);
+    eprintln!("// plod derive for {}\n{}", name, code.trim_end());
+}
+
+/// Crude pretty printing: one statement per line and indented blocks, `in_block` is true for
+/// the content of a `{}` block
+fn format_tokens(tokens: TokenStream, indent: usize, in_block: bool, out: &mut String) {
+    // no space after `.`, `&`, `#`, `::` and opening delimiters
+    let mut glued = true;
+    for token in tokens {
+        let space = match &token {
+            // macro calls
+            TokenTree::Punct(punct) if punct.as_char() == '!' => {
+                !out.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+            }
+            TokenTree::Punct(punct) => !matches!(punct.as_char(), ',' | ';' | '.' | '?' | ':'),
+            // calls and indexing
+            TokenTree::Group(group) if group.delimiter() != Delimiter::Brace => {
+                !out.ends_with(|c: char| c.is_alphanumeric() || "_>!)".contains(c))
+            }
+            _ => true,
+        };
+        if out.ends_with('\n') {
+            out.push_str(&"    ".repeat(indent));
+        } else if space && !glued {
+            out.push(' ');
+        }
+        glued = false;
+        match token {
+            TokenTree::Group(group) => match group.delimiter() {
+                Delimiter::Brace => {
+                    out.push_str("{\n");
+                    format_tokens(group.stream(), indent + 1, true, out);
+                    if !out.ends_with('\n') {
+                        out.push('\n');
+                    }
+                    out.push_str(&"    ".repeat(indent));
+                    out.push('}');
+                    if in_block {
+                        out.push('\n');
+                    }
+                }
+                Delimiter::Parenthesis => {
+                    out.push('(');
+                    format_tokens(group.stream(), indent, false, out);
+                    out.push(')');
+                }
+                Delimiter::Bracket => {
+                    out.push('[');
+                    format_tokens(group.stream(), indent, false, out);
+                    out.push(']');
+                }
+                Delimiter::None => format_tokens(group.stream(), indent, in_block, out),
+            },
+            TokenTree::Punct(punct) => {
+                let c = punct.as_char();
+                out.push(c);
+                if c == ';' && in_block {
+                    out.push('\n');
+                }
+                glued = punct.spacing() == Spacing::Joint
+                    || matches!(c, '.' | '&' | '#')
+                    || (c == ':' && out.ends_with("::"));
+            }
+            token => out.push_str(&token.to_string()),
+        }
+        if out.ends_with(['(', '[']) {
+            glued = true;
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//! Generation of `Plod::describe()`, the static description of the at-rest layout
+
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::parse::Result;
+use syn::{
+    Data, DeriveInput, Expr, Fields, GenericArgument, Pat, PathArguments, RangeLimits, Type,
+};
+
+use crate::attributes::{fields_attributes, is_value, Attributes, Endianness};
+use crate::{blob_as_vec, box_item, bytes_or_text, is_blob, option_item, is_vec_u8, primitive_size, primitive_type, resolve_std_types};
+
+/// Generate the `describe` method for a given input type (struct or enum)
+pub fn describe_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let length = match &attributes.length_prefix {
+        Some(length_ty) => {
+            let primitive = describe_primitive(length_ty, attributes.endianness);
+            quote! { Some(#primitive) }
+        }
+        None => quote! { None },
+    };
+    let align_size = match attributes.align_size {
+        Some(align) => quote! { Some(#align) },
+        None => quote! { None },
+    };
+    // fields stored in columns are not described
+    let columnar = match &input.data {
+        Data::Struct(data) => fields_attributes(&data.fields, attributes)?.iter().any(|a| a.columnar),
+        Data::Enum(data) => data.variants.iter().try_fold(false, |found, variant| {
+            let variant_attributes = attributes.variant(variant)?;
+            let fields = fields_attributes(&variant.fields, &variant_attributes)?;
+            Ok::<_, syn::Error>(found || fields.iter().any(|a| a.columnar))
+        })?,
+        Data::Union(_) => false,
+    };
+    let layout = match &input.data {
+        _ if columnar => quote! {
+            plod::layout::Layout::Opaque {
+                name: std::any::type_name::<Self>(),
+            }
+        },
+        // an enum without its tag and a size after its items are not described
+        Data::Struct(data) if fields_attributes(&data.fields, attributes)?.iter().any(|a| a.external_tag || a.size_after) => quote! {
+            plod::layout::Layout::Opaque {
+                name: std::any::type_name::<Self>(),
+            }
+        },
+        // kept trailing bytes are not described
+        _ if attributes.preserve_unknown.is_some() && attributes.length_prefix.is_some() => quote! {
+            plod::layout::Layout::Opaque {
+                name: std::any::type_name::<Self>(),
+            }
+        },
+        // footers are not described
+        _ if attributes.length_footer.is_some() => quote! {
+            plod::layout::Layout::Opaque {
+                name: std::any::type_name::<Self>(),
+            }
+        },
+        // a layout has a single endianness
+        Data::Struct(_) if attributes.endian_marker.is_some() => quote! {
+            plod::layout::Layout::Opaque {
+                name: std::any::type_name::<Self>(),
+            }
+        },
+        Data::Struct(data) => {
+            let magic = describe_magic(attributes);
+            let fields = describe_fields(&data.fields, attributes)?;
+            let trailer = describe_trailer(attributes);
+            quote! {
+                plod::layout::Layout::Struct(plod::layout::Struct {
+                    name: std::any::type_name::<Self>(),
+                    length: #length,
+                    magic: #magic,
+                    fields: #fields,
+                    trailer: #trailer,
+                    align_size: #align_size,
+                })
+            }
+        }
+        // tags are described as primitives, right before the variant
+        Data::Enum(_)
+            if !attributes.tag_fields.is_empty()
+                || !attributes.common_fields.is_empty()
+                || attributes.tag_type.as_ref().is_some_and(|t| !primitive_type(t)) =>
+        {
+            quote! {
+                plod::layout::Layout::Opaque {
+                    name: std::any::type_name::<Self>(),
+                }
+            }
+        }
+        Data::Enum(data) => {
+            // tag_type has already been checked by enum_impl
+            let tag_type = match &attributes.tag_type {
+                Some(t) => t,
+                None => return Ok(TokenStream::new()),
+            };
+            let tag_endianness = attributes.tag_endianness.unwrap_or(attributes.endianness);
+            let tag = describe_primitive(tag_type, tag_endianness);
+            let variant_size = match attributes.variant_size {
+                Some(size) => quote! { Some(#size) },
+                None => quote! { None },
+            };
+            let mut variants = TokenStream::new();
+            for variant in data.variants.iter() {
+                let variant_attributes = attributes.variant(variant)?;
+                let name = variant.ident.to_string();
+                let tag_value = match &variant_attributes.tag_pattern() {
+                    Some(pattern) => {
+                        let text = quote!(#pattern).to_string();
+                        let values = tag_ranges(pattern, tag_type);
+                        let guard = match &variant_attributes.tag_guard {
+                            Some(guard) => {
+                                let guard = quote!(#guard).to_string();
+                                quote! { Some(#guard) }
+                            }
+                            None => quote! { None },
+                        };
+                        quote! {
+                            Some(plod::layout::Tag { pattern: #text, values: vec![#values], guard: #guard })
+                        }
+                    }
+                    None => quote! { None },
+                };
+                let keep_tag = variant_attributes.keep_tag;
+                let tag_field = variant_attributes.tag_field;
+                let keep_diff = match &variant_attributes.keep_diff {
+                    Some(diff) => quote! { Some(#diff as i128) },
+                    None => quote! { None },
+                };
+                let skip = variant_attributes.skip;
+                let magic = describe_magic(&variant_attributes);
+                let trailer = describe_trailer(&variant_attributes);
+                let fields = if skip {
+                    quote! { vec![] }
+                } else {
+                    describe_fields(&variant.fields, &variant_attributes)?
+                };
+                variants.extend(quote! {
+                    plod::layout::Variant {
+                        name: #name,
+                        tag: #tag_value,
+                        keep_tag: #keep_tag,
+                        tag_field: #tag_field,
+                        keep_diff: #keep_diff,
+                        skip: #skip,
+                        magic: #magic,
+                        fields: #fields,
+                        trailer: #trailer,
+                    },
+                });
+            }
+            quote! {
+                plod::layout::Layout::Enum(plod::layout::Enum {
+                    name: std::any::type_name::<Self>(),
+                    length: #length,
+                    tag: #tag,
+                    variant_size: #variant_size,
+                    variants: vec![#variants],
+                    align_size: #align_size,
+                })
+            }
+        }
+        // already reported by plod_impl
+        Data::Union(_) => return Ok(TokenStream::new()),
+    };
+    Ok(quote! {
+        fn describe() -> plod::layout::Layout {
+            #layout
+        }
+    })
+}
+
+fn describe_endianness(endianness: Endianness) -> TokenStream {
+    match endianness {
+        Endianness::Big => quote! { plod::layout::Endianness::Big },
+        Endianness::Little => quote! { plod::layout::Endianness::Little },
+        Endianness::Native => quote! { plod::layout::Endianness::Native },
+    }
+}
+
+fn describe_primitive(ty: &Ident, endianness: Endianness) -> TokenStream {
+    let name = ty.to_string();
+    let size = primitive_size(ty);
+    let endianness = describe_endianness(endianness);
+    quote! {
+        plod::layout::Primitive { ty: #name, size: #size, endianness: #endianness }
+    }
+}
+
+fn describe_magic(attributes: &Attributes) -> TokenStream {
+    describe_magic_value(&attributes.magic, attributes.keep_magic, attributes)
+}
+
+fn describe_trailer(attributes: &Attributes) -> TokenStream {
+    describe_magic_value(&attributes.trailer_magic, false, attributes)
+}
+
+fn describe_magic_value(
+    magic: &Option<(Ident, Vec<Pat>)>,
+    keep: bool,
+    attributes: &Attributes,
+) -> TokenStream {
+    match magic {
+        Some((ty, values)) => {
+            let primitive = describe_primitive(ty, attributes.endianness);
+            let text = quote!(#(#values)|*).to_string();
+            let value = &values[0];
+            let to_method = match attributes.endianness {
+                Endianness::Big => quote! { to_be_bytes },
+                Endianness::Little => quote! { to_le_bytes },
+                Endianness::Native => quote! { to_ne_bytes },
+            };
+            quote! {
+                Some(plod::layout::Magic {
+                    ty: #primitive,
+                    value: #text,
+                    bytes: (#value as #ty).#to_method().to_vec(),
+                    values: vec![#(#values as i128),*],
+                    keep: #keep,
+                })
+            }
+        }
+        None => quote! { None },
+    }
+}
+
+/// Describe all non skipped fields of a struct or variant, as an expression of type `Vec<Field>`
+fn describe_fields(fields: &Fields, attributes: &Attributes) -> Result<TokenStream> {
+    // flattened fields are spliced between the other ones
+    let mut parts = Vec::new();
+    let mut result = TokenStream::new();
+    let all_attributes = fields_attributes(fields, attributes)?;
+    for (i, (field, field_attributes)) in fields.iter().zip(all_attributes).enumerate() {
+        let name = match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => i.to_string(),
+        };
+        // the fields of a field that may not exist are a part of their own
+        if field_attributes.cfg.is_some() {
+            parts.push(quote! { vec![#result] });
+            result = TokenStream::new();
+        }
+        if let Some(offset) = &field_attributes.pad_to_offset {
+            let pad_name = format!("pad_{}", name);
+            let fill = field_attributes.fill.unwrap_or(0);
+            result.extend(quote! {
+                plod::layout::Field {
+                    name: #pad_name,
+                    layout: plod::layout::Layout::PadTo {
+                        offset: (#offset) as usize,
+                        fill: #fill,
+                    },
+                },
+            });
+        }
+        let reserved = field_attributes.reserved;
+        if reserved > 0 {
+            let reserved_name = format!("reserved_{}", name);
+            result.extend(quote! {
+                plod::layout::Field {
+                    name: #reserved_name,
+                    layout: plod::layout::Layout::Array {
+                        len: #reserved,
+                        item: Box::new(plod::layout::Layout::Primitive(plod::layout::Primitive {
+                            ty: "u8",
+                            size: 1,
+                            endianness: plod::layout::Endianness::Native,
+                        })),
+                        item_align: None,
+                    },
+                },
+            });
+        }
+        // fields stored elsewhere are not part of the layout
+        if !(field_attributes.skip || field_attributes.seek_to.is_some()) {
+            let layout = describe_item(&field.ty, &field_attributes);
+            if field_attributes.flatten {
+                let flattened = quote! { plod::layout::Field { name: #name, layout: #layout }.flatten() };
+                result = if field_attributes.cfg.is_some() {
+                    quote! { [vec![#result], #flattened].concat() }
+                } else {
+                    parts.push(quote! { vec![#result] });
+                    parts.push(flattened);
+                    TokenStream::new()
+                };
+            } else {
+                result.extend(quote! {
+                    plod::layout::Field { name: #name, layout: #layout },
+                });
+            }
+        }
+        if field_attributes.cfg.is_some() {
+            let field = std::mem::take(&mut result);
+            let fields = if field_attributes.flatten && !(field_attributes.skip || field_attributes.seek_to.is_some()) {
+                field
+            } else {
+                quote! { vec![#field] }
+            };
+            parts.push(field_attributes.cfg_value(fields, quote! { Vec::new() }));
+        }
+    }
+    if parts.is_empty() {
+        return Ok(quote! { vec![#result] });
+    }
+    parts.push(quote! { vec![#result] });
+    Ok(quote! { [#(#parts),*].concat() })
+}
+
+/// Describe a single item, mirrors the type dispatch of `generate_for_item`
+fn describe_item(ty: &Type, attributes: &Attributes) -> TokenStream {
+    if is_blob(ty) || bytes_or_text(ty).is_some() {
+        return describe_item(&blob_as_vec(), attributes);
+    }
+    if let Some(as_type) = &attributes.as_type {
+        let mut attributes = attributes.clone();
+        attributes.as_type = None;
+        return describe_item(as_type, &attributes);
+    }
+    let ty = &resolve_std_types(ty);
+    let item_align = match attributes.item_align {
+        Some(align) => quote! { Some(#align) },
+        None => quote! { None },
+    };
+    if attributes.rest {
+        return quote! { plod::layout::Layout::Rest };
+    }
+    // packed bits, terminated BSTR and varints have no layout
+    let varint = match (&attributes.varint, ty) {
+        (Some(flavor), Type::Path(p)) if p.path.get_ident().is_some_and(primitive_type) => Some(flavor),
+        _ => None,
+    };
+    if attributes.bitmap || attributes.bstr || varint.is_some() {
+        let name = match varint {
+            _ if attributes.bstr => "BSTR".to_string(),
+            _ if attributes.bitmap => "Vec<bool>".to_string(),
+            Some(flavor) => format!("{} varint", flavor),
+            None => unreachable!(),
+        };
+        return quote! {
+            plod::layout::Layout::Opaque {
+                name: #name,
+            }
+        };
+    }
+    if attributes.if_remaining {
+        let mut inner = attributes.clone();
+        inner.if_remaining = false;
+        // the type has already been checked by generate_for_remaining
+        let item = match ty {
+            Type::Path(type_path) => match option_item(type_path) {
+                Some(item) => describe_item(item, &inner),
+                None => return TokenStream::new(),
+            },
+            _ => return TokenStream::new(),
+        };
+        return quote! { plod::layout::Layout::IfRemaining(Box::new(#item)) };
+    }
+    if let Some(encoding) = &attributes.encoding {
+        let name = &encoding.name;
+        // size_type has already been checked by generate_for_encoded
+        let size = match &attributes.size_type {
+            Some(size_ty) => describe_primitive(size_ty, attributes.endianness),
+            None => return TokenStream::new(),
+        };
+        let layout = if is_vec_u8(ty) {
+            quote! { None }
+        } else {
+            let mut inner = attributes.clone();
+            inner.encoding = None;
+            let layout = describe_item(ty, &inner);
+            quote! { Some(Box::new(#layout)) }
+        };
+        return quote! {
+            plod::layout::Layout::Encoded { encoding: #name, size: #size, layout: #layout }
+        };
+    }
+    match ty {
+        Type::Path(type_path) => {
+            let first = type_path.path.segments.first();
+            if let Some(inner_type) = box_item(type_path) {
+                describe_item(inner_type, attributes)
+            } else if let Some(segment) = first.filter(|s| s.ident == "Vec") {
+                let item = match &segment.arguments {
+                    PathArguments::AngleBracketed(pa) => match pa.args.first() {
+                        Some(GenericArgument::Type(t)) => describe_item(t, &attributes.vec_item()),
+                        _ => quote! { plod::layout::Layout::Tuple(vec![]) },
+                    },
+                    _ => quote! { plod::layout::Layout::Tuple(vec![]) },
+                };
+                // size_type has already been checked by generate_for_vec
+                let size = match &attributes.size_type {
+                    Some(size_ty) => describe_primitive(size_ty, attributes.endianness),
+                    None => return TokenStream::new(),
+                };
+                let byte_sized = attributes.byte_sized;
+                let size_adjust = attributes.size_adjust;
+                let size_unit = attributes.size_unit.unwrap_or(1);
+                let length_field = match &attributes.length_field {
+                    Some(field) => {
+                        let name = field.to_string();
+                        quote! { Some(#name) }
+                    }
+                    None => quote! { None },
+                };
+                quote! {
+                    plod::layout::Layout::Vec {
+                        size: #size,
+                        byte_sized: #byte_sized,
+                        size_adjust: #size_adjust,
+                        size_unit: #size_unit,
+                        length_field: #length_field,
+                        item: Box::new(#item),
+                        item_align: #item_align,
+                    }
+                }
+            } else if let Some(ident) = type_path.path.get_ident().filter(|i| primitive_type(i)) {
+                let primitive = describe_primitive(ident, attributes.endianness);
+                quote! { plod::layout::Layout::Primitive(#primitive) }
+            } else {
+                quote! {
+                    plod::layout::Layout::Type {
+                        name: std::any::type_name::<#type_path>(),
+                        layout: <#type_path as plod::Plod>::describe,
+                        read: {
+                            #[allow(unused_imports)]
+                            use plod::layout::{DiscardDefault as _, DiscardNone as _};
+                            (&plod::layout::Discard::<#type_path>(std::marker::PhantomData)).get()
+                        },
+                    }
+                }
+            }
+        }
+        Type::Tuple(t) => {
+            let items = t.elems.iter().map(|ty| describe_item(ty, attributes));
+            quote! { plod::layout::Layout::Tuple(vec![#(#items),*]) }
+        }
+        Type::Array(t) => {
+            let len = &t.len;
+            let item = describe_item(&t.elem, &attributes.array_item());
+            quote! {
+                plod::layout::Layout::Array {
+                    len: #len,
+                    item: Box::new(#item),
+                    item_align: #item_align,
+                }
+            }
+        }
+        // already reported by generate_for_item
+        _ => TokenStream::new(),
+    }
+}
+
+/// Compute the list of tag values a pattern matches, as inclusive ranges of i128
+pub fn tag_ranges(pattern: &Pat, tag_type: &Ident) -> TokenStream {
+    let bound = |expr: &Option<Box<Expr>>, default: TokenStream| match expr {
+        Some(e) => quote! { (#e as i128) },
+        None => default,
+    };
+    match pattern {
+        Pat::Lit(lit) => quote! { (#lit as i128)..=(#lit as i128), },
+        Pat::Path(path) => quote! { (#path as i128)..=(#path as i128), },
+        Pat::Ident(_) if is_value(pattern) => quote! { (#pattern as i128)..=(#pattern as i128), },
+        Pat::Paren(p) => tag_ranges(&p.pat, tag_type),
+        Pat::Or(or) => or.cases.iter().map(|p| tag_ranges(p, tag_type)).collect(),
+        Pat::Wild(_) => quote! { (#tag_type::MIN as i128)..=(#tag_type::MAX as i128), },
+        Pat::Range(range) => {
+            let start = bound(&range.start, quote! { (#tag_type::MIN as i128) });
+            let end = bound(&range.end, quote! { (#tag_type::MAX as i128) });
+            match range.limits {
+                RangeLimits::Closed(_) => quote! { #start..=#end, },
+                RangeLimits::HalfOpen(_) if range.end.is_some() => quote! { #start..=(#end - 1), },
+                RangeLimits::HalfOpen(_) => quote! { #start..=#end, },
+            }
+        }
+        _ => TokenStream::new(),
+    }
+}
@@ -0,0 +1,258 @@
+//! Generation of `Plod::FIXED_SIZE` and of the inherent `SIZE` and `OFFSET_<FIELD>` constants,
+//! computed at compile time from the same rules as `Layout::fixed_size`
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::parse::Result;
+use syn::{Data, DeriveInput, Fields, Type};
+
+use crate::attributes::{fields_attributes, Attributes};
+use crate::{box_item, bytes_or_text, is_blob, primitive_size, primitive_type, resolve_std_types};
+
+/// Expression of type `Option<usize>` for `FIXED_SIZE`, `None` if the type has obviously no
+/// fixed size (it contains a `Vec` for example)
+pub fn fixed_size_impl(
+    input: &DeriveInput,
+    attributes: &Attributes,
+) -> Result<Option<TokenStream>> {
+    // kept trailing bytes are part of the size
+    if attributes.preserve_unknown.is_some() && attributes.length_prefix.is_some() {
+        return Ok(None);
+    }
+    let content = match &input.data {
+        Data::Struct(data) => {
+            let magic = magic_size(attributes);
+            match fields_size(&data.fields, attributes, magic)? {
+                Some(size) => size,
+                None => return Ok(None),
+            }
+        }
+        Data::Enum(data) => {
+            // tag_type has already been checked by enum_impl
+            let tag_size = match &attributes.tag_type {
+                Some(tag_type) if primitive_type(tag_type) => {
+                    let size = primitive_size(tag_type);
+                    quote! { Some(#size) }
+                }
+                Some(tag_type) => quote! { <#tag_type as plod::Plod>::FIXED_SIZE },
+                None if !attributes.tag_fields.is_empty() => {
+                    let size = attributes.tag_fields_size();
+                    quote! { Some(#size) }
+                }
+                None => return Ok(None),
+            };
+            let variants = match attributes.variant_size {
+                Some(variant_size) => quote! { Some(#variant_size) },
+                None => {
+                    let mut sizes = Vec::new();
+                    for variant in data.variants.iter() {
+                        let variant_attributes = attributes.variant(variant)?;
+                        if variant_attributes.skip {
+                            continue;
+                        }
+                        let magic = magic_size(&variant_attributes);
+                        match fields_size(&variant.fields, &variant_attributes, magic)? {
+                            Some(size) => sizes.push(size),
+                            None => return Ok(None),
+                        }
+                    }
+                    quote! { plod::helpers::same_size(&[#(#sizes),*]) }
+                }
+            };
+            quote! { plod::helpers::sum_sizes(&[#tag_size, #variants]) }
+        }
+        // already reported by plod_impl
+        Data::Union(_) => return Ok(None),
+    };
+    let aligned = match attributes.align_size {
+        Some(align) => quote! { plod::helpers::aligned_size(#content, #align) },
+        None => content,
+    };
+    let aligned = match &attributes.length_footer {
+        Some((length_ty, checksum)) => {
+            let mut size: usize = primitive_size(length_ty).base10_parse().unwrap_or(0);
+            if let Some((checksum_ty, _)) = checksum {
+                size += primitive_size(checksum_ty).base10_parse::<usize>().unwrap_or(0);
+            }
+            quote! { plod::helpers::sum_sizes(&[#aligned, Some(#size)]) }
+        }
+        None => aligned,
+    };
+    Ok(Some(match &attributes.length_prefix {
+        Some(length_ty) => {
+            let length_size = primitive_size(length_ty);
+            quote! { plod::helpers::sum_sizes(&[Some(#length_size), #aligned]) }
+        }
+        None => aligned,
+    }))
+}
+
+/// Size of the magic and of the trailer magic
+fn magic_size(attributes: &Attributes) -> usize {
+    [&attributes.magic, &attributes.trailer_magic]
+        .into_iter()
+        .flatten()
+        .map(|(ty, _)| primitive_size(ty).base10_parse().unwrap_or(0))
+        .sum()
+}
+
+/// Size of the stored fields of a struct or variant after its magic
+fn fields_size(
+    fields: &Fields,
+    attributes: &Attributes,
+    magic: usize,
+) -> Result<Option<TokenStream>> {
+    let sizes = match stored_sizes(fields, attributes)? {
+        Some(sizes) => sizes.into_iter().map(|(size, _)| size),
+        None => return Ok(None),
+    };
+    Ok(Some(quote! { plod::helpers::sum_sizes(&[Some(#magic), #(#sizes),*]) }))
+}
+
+/// Size of each field, including its reserved bytes, with the number of reserved bytes, `None`
+/// if a field has obviously no fixed size
+fn stored_sizes(fields: &Fields, attributes: &Attributes) -> Result<Option<Vec<(TokenStream, usize)>>> {
+    let mut sizes = Vec::new();
+    let all_attributes = fields_attributes(fields, attributes)?;
+    // a kept tag or magic is stored before the fields
+    let kept = attributes.kept_field();
+    for (i, (field, field_attributes)) in fields.iter().zip(all_attributes).enumerate() {
+        // the padding depends on where the value starts, an enum without its tag has no fixed size
+        if field_attributes.pad_to_offset.is_some() || field_attributes.external_tag {
+            return Ok(None);
+        }
+        let mut field_sizes = Vec::new();
+        let reserved = field_attributes.reserved;
+        if reserved > 0 {
+            field_sizes.push(quote! { Some(#reserved) });
+        }
+        if !(kept == Some(i) || field_attributes.skip || field_attributes.seek_to.is_some()) {
+            match item_size(&field.ty, &field_attributes) {
+                Some(size) => field_sizes.push(size),
+                None => return Ok(None),
+            }
+        }
+        let size = quote! { plod::helpers::sum_sizes(&[#(#field_sizes),*]) };
+        if field_attributes.cfg.is_some() {
+            sizes.push((field_attributes.cfg_value(size, quote! { Some(0) }), reserved));
+        } else {
+            sizes.push((size, reserved));
+        }
+    }
+    Ok(Some(sizes))
+}
+
+/// Inherent `OFFSET_<FIELD>` constants of a struct that may have a fixed size, the offset of each
+/// stored field from the start of the value at rest
+pub fn offsets_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Ok(TokenStream::new()),
+    };
+    let sizes = match stored_sizes(fields, attributes)? {
+        Some(sizes) => sizes,
+        None => return Ok(TokenStream::new()),
+    };
+    let all_attributes = fields_attributes(fields, attributes)?;
+    let mut start = Vec::new();
+    if let Some(length_ty) = &attributes.length_prefix {
+        let size = primitive_size(length_ty);
+        start.push(quote! { Some(#size) });
+    }
+    let magic: usize = match &attributes.magic {
+        Some((ty, _)) => primitive_size(ty).base10_parse().unwrap_or(0),
+        None => 0,
+    };
+    let mut previous: Vec<TokenStream> = Vec::new();
+    let mut consts = TokenStream::new();
+    for (i, ((field, field_attributes), (size, reserved))) in fields.iter().zip(&all_attributes).zip(sizes).enumerate() {
+        let offset = if attributes.keep_magic && i == 0 {
+            // the kept magic is the magic
+            Some(quote! { plod::helpers::sum_sizes(&[#(#start,)* Some(0)]) })
+        } else if field_attributes.skip || field_attributes.seek_to.is_some() {
+            None
+        } else {
+            Some(quote! { plod::helpers::sum_sizes(&[#(#start,)* Some(#magic), #(#previous,)* Some(#reserved)]) })
+        };
+        previous.push(size);
+        let offset = match offset {
+            Some(offset) => offset,
+            None => continue,
+        };
+        let name = match &field.ident {
+            Some(ident) => ident.unraw().to_string(),
+            None => i.to_string(),
+        };
+        let const_name = format_ident!("OFFSET_{}", name.to_uppercase());
+        let doc = format!("Offset of `{}` at rest, a compile time error if it depends on the value", name);
+        let message = format!("{}.{} has no fixed offset", input.ident, name);
+        let cfg = field_attributes.cfg_attr();
+        consts.extend(quote! {
+            #[doc = #doc]
+            #cfg
+            pub const #const_name: usize = match #offset {
+                Some(offset) => offset,
+                None => panic!(#message),
+            };
+        });
+    }
+    Ok(consts)
+}
+
+/// Size of a single item, mirrors the type dispatch of `generate_for_item`
+fn item_size(ty: &Type, attributes: &Attributes) -> Option<TokenStream> {
+    if let Some(as_type) = &attributes.as_type {
+        let mut attributes = attributes.clone();
+        attributes.as_type = None;
+        return item_size(as_type, &attributes);
+    }
+    if attributes.rest || attributes.if_remaining || attributes.encoding.is_some() || attributes.varint.is_some() || is_blob(ty) || bytes_or_text(ty).is_some() {
+        return None;
+    }
+    match &resolve_std_types(ty) {
+        Type::Path(type_path) => {
+            if let Some(inner_type) = box_item(type_path) {
+                // a boxed type may contain this one, its FIXED_SIZE would depend on itself
+                return match inner_type {
+                    Type::Path(p) if p.path.get_ident().is_some_and(primitive_type) => {
+                        item_size(inner_type, attributes)
+                    }
+                    _ => None,
+                };
+            }
+            if type_path
+                .path
+                .segments
+                .first()
+                .is_some_and(|s| s.ident == "Vec")
+            {
+                return None;
+            }
+            match type_path.path.get_ident() {
+                Some(ident) if primitive_type(ident) => {
+                    let size = primitive_size(ident);
+                    Some(quote! { Some(#size) })
+                }
+                _ => Some(quote! { <#type_path as plod::Plod>::FIXED_SIZE }),
+            }
+        }
+        Type::Array(array) => {
+            let item = item_size(&array.elem, &attributes.array_item())?;
+            let len = &array.len;
+            match attributes.item_align {
+                Some(align) => Some(quote! { plod::helpers::repeat_aligned_size(#item, #len, #align) }),
+                None => Some(quote! { plod::helpers::repeat_size(#item, #len) }),
+            }
+        }
+        Type::Tuple(tuple) => {
+            let items = tuple
+                .elems
+                .iter()
+                .map(|t| item_size(t, attributes))
+                .collect::<Option<Vec<_>>>()?;
+            Some(quote! { plod::helpers::sum_sizes(&[#(#items),*]) })
+        }
+        _ => Some(quote! { <#ty as plod::Plod>::FIXED_SIZE }),
+    }
+}
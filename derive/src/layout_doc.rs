@@ -0,0 +1,319 @@
+//! Generation of `Plod::LAYOUT_DOC`, a markdown description of the at-rest layout that is also
+//! attached to the generated impl as its documentation
+
+use quote::quote;
+use syn::parse::Result;
+use syn::{Data, DeriveInput, Fields, Type};
+
+use crate::attributes::{fields_attributes, Attributes, Endianness};
+use crate::{box_item, bytes_or_text, is_blob, is_vec, option_item, primitive_type, resolve_std_types, vec_item};
+
+/// Describe the layout of a given input type (struct or enum)
+pub fn layout_doc(input: &DeriveInput, attributes: &Attributes) -> Result<String> {
+    let endianness = match &attributes.endian_marker {
+        Some((ty, big, little)) => format!(
+            "big endian if the first field, a `{}`, is `{}`, little endian if it is `{}`",
+            ty,
+            quote!(#big),
+            quote!(#little)
+        ),
+        None => endianness_doc(attributes.endianness).to_string(),
+    };
+    let mut doc = format!("Wire layout ({})", endianness);
+    if let Some(length_ty) = &attributes.length_prefix {
+        doc.push_str(&format!(", prefixed with its length in bytes as a `{}`", length_ty));
+    }
+    if let Some((length_ty, checksum)) = &attributes.length_footer {
+        doc.push_str(&format!(", followed by its total length in bytes as a `{}`", length_ty));
+        if let Some((checksum_ty, _)) = checksum {
+            doc.push_str(&format!(" and a `{}` checksum", checksum_ty));
+        }
+    }
+    match &input.data {
+        Data::Struct(data) => {
+            doc.push_str(":\n");
+            doc.push_str(&magic_doc(attributes, ""));
+            doc.push_str(&fields_doc(&data.fields, attributes, "")?);
+            doc.push_str(&trailer_doc(attributes, ""));
+            if attributes.preserve_unknown.is_some() {
+                doc.push_str("\nReserved and unknown trailing bytes are kept and written back as they were read.\n");
+            }
+        }
+        Data::Enum(data) => {
+            let tag = if attributes.tag_fields.is_empty() {
+                attributes.tag_type.as_ref().map(|t| t.to_string())
+            } else {
+                let fields: Vec<_> = attributes
+                    .tag_fields
+                    .iter()
+                    .map(|(name, ty)| format!("{}: {}", name, ty))
+                    .collect();
+                Some(format!("({})", fields.join(", ")))
+            };
+            if !attributes.common_fields.is_empty() {
+                let fields: Vec<_> = attributes
+                    .common_fields
+                    .iter()
+                    .map(|(name, ty)| format!("`{}`: `{}`", name, ty))
+                    .collect();
+                doc.push_str(&format!(", the common fields {}", fields.join(", ")));
+            }
+            if let Some(tag) = tag {
+                doc.push_str(&format!(", a `{}` tag", tag));
+                if let Some(endianness) = attributes.tag_endianness {
+                    doc.push_str(&format!(" ({})", endianness_doc(endianness)));
+                }
+                doc.push_str(" followed by the variant");
+            }
+            if let Some(size) = attributes.variant_size {
+                doc.push_str(&format!(" padded to {} bytes", size));
+            }
+            doc.push_str(":\n");
+            for variant in data.variants.iter() {
+                let variant_attributes = attributes.variant(variant)?;
+                let tag = match &variant_attributes.tag_pattern() {
+                    Some(pattern) => match &variant_attributes.tag_guard {
+                        Some(guard) => format!(
+                            "tag `{}` if `{}`",
+                            quote!(#pattern).to_string(),
+                            quote!(#guard).to_string()
+                        ),
+                        None => format!("tag `{}`", quote!(#pattern).to_string()),
+                    },
+                    None => "any other tag".to_string(),
+                };
+                if variant_attributes.skip {
+                    doc.push_str(&format!("- `{}` ({}): not readable\n", variant.ident, tag));
+                    continue;
+                }
+                doc.push_str(&format!("- `{}` ({})", variant.ident, tag));
+                if variant_attributes.endianness != attributes.endianness {
+                    doc.push_str(&format!(", {}", endianness_doc(variant_attributes.endianness)));
+                }
+                if variant_attributes.keep_tag {
+                    let tag_field = variant_attributes.tag_field;
+                    match variant.fields.iter().nth(tag_field).and_then(|f| f.ident.as_ref()) {
+                        _ if tag_field == 0 => doc.push_str(", the first field holds the tag"),
+                        Some(ident) => doc.push_str(&format!(", the field `{}` holds the tag", ident)),
+                        None => doc.push_str(&format!(", the field `{}` holds the tag", tag_field)),
+                    }
+                }
+                doc.push('\n');
+                doc.push_str(&magic_doc(&variant_attributes, "  "));
+                doc.push_str(&fields_doc(&variant.fields, &variant_attributes, "  ")?);
+                doc.push_str(&trailer_doc(&variant_attributes, "  "));
+            }
+        }
+        // already reported by plod_impl
+        Data::Union(_) => {}
+    }
+    if let Some(align) = attributes.align_size {
+        doc.push_str(&format!(
+            "\nThe whole is padded with zeroes to a multiple of {} bytes.\n",
+            align
+        ));
+    }
+    Ok(doc)
+}
+
+fn endianness_doc(endianness: Endianness) -> &'static str {
+    match endianness {
+        Endianness::Big => "big endian",
+        Endianness::Little => "little endian",
+        Endianness::Native => "native endian",
+    }
+}
+
+fn magic_doc(attributes: &Attributes, indent: &str) -> String {
+    match &attributes.magic {
+        Some((ty, values)) => {
+            let values = quote!(#(#values)|*).to_string();
+            format!("{}- magic `{}` = `{}`\n", indent, ty, values)
+        }
+        None => String::new(),
+    }
+}
+
+fn trailer_doc(attributes: &Attributes, indent: &str) -> String {
+    match &attributes.trailer_magic {
+        Some((ty, values)) => {
+            let values = quote!(#(#values)|*).to_string();
+            format!("{}- trailer magic `{}` = `{}`\n", indent, ty, values)
+        }
+        None => String::new(),
+    }
+}
+
+/// One line per field of a struct or variant
+fn fields_doc(fields: &Fields, attributes: &Attributes, indent: &str) -> Result<String> {
+    let mut doc = String::new();
+    let all_attributes = fields_attributes(fields, attributes)?;
+    for (i, (field, field_attributes)) in fields.iter().zip(all_attributes).enumerate() {
+        let name = match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => i.to_string(),
+        };
+        if let Some(offset) = &field_attributes.pad_to_offset {
+            doc.push_str(&format!(
+                "{}- bytes of value {} up to the absolute offset `{}`\n",
+                indent,
+                field_attributes.fill.unwrap_or(0),
+                quote!(#offset)
+            ));
+        }
+        if field_attributes.reserved > 0 {
+            doc.push_str(&format!("{}- {} reserved bytes\n", indent, field_attributes.reserved));
+        }
+        let description = if field_attributes.skip {
+            "not stored".to_string()
+        } else if field_attributes.common {
+            "common field, stored before the tag".to_string()
+        } else {
+            let mut description = item_doc(&field.ty, &field_attributes);
+            if let Some(target) = &field_attributes.len_of {
+                description.push_str(&format!(", the size of `{}`", target));
+            }
+            if let Some(target) = &field_attributes.tag_for {
+                description.push_str(&format!(", the tag of `{}`", target));
+            }
+            if let Some((source, _, _)) = &field_attributes.tag_source {
+                description.push_str(&format!(", without its tag stored in `{}`", source));
+            }
+            if let Some(offset) = &field_attributes.seek_to {
+                description.push_str(&format!(", stored at offset `{}`", quote!(#offset)));
+            }
+            description
+        };
+        doc.push_str(&format!("{}- `{}`: {}\n", indent, name, description));
+    }
+    Ok(doc)
+}
+
+/// Description of a single item type
+fn item_doc(ty: &Type, attributes: &Attributes) -> String {
+    if let Some(as_type) = &attributes.as_type {
+        let mut attributes = attributes.clone();
+        attributes.as_type = None;
+        return item_doc(as_type, &attributes);
+    }
+    let ty = &resolve_std_types(ty);
+    let name = quote!(#ty).to_string().replace(' ', "");
+    if let Some(expected) = &attributes.equals {
+        return format!("always `{}`", quote!(#expected).to_string().replace(' ', ""));
+    }
+    if attributes.rest {
+        return "all the remaining bytes".to_string();
+    }
+    if attributes.flatten {
+        return format!("the fields of `{}` (see its own layout), inline", name);
+    }
+    if let Some(align) = attributes.item_align {
+        return format!(
+            "{}, each item starting at a multiple of {} bytes from the first one",
+            item_doc(ty, &attributes.array_item()),
+            align
+        );
+    }
+    if attributes.if_remaining {
+        let mut inner = attributes.clone();
+        inner.if_remaining = false;
+        let item = match ty {
+            Type::Path(type_path) => option_item(type_path),
+            _ => None,
+        };
+        return match item {
+            Some(item) => format!("{}, only if bytes remain", item_doc(item, &inner)),
+            None => format!("`{}`", name),
+        };
+    }
+    if is_blob(ty) {
+        let size_ty = attributes.size_type.as_ref().map(|t| t.to_string()).unwrap_or_default();
+        return format!("bytes streamed as a `Blob`, after their size as a `{}`", size_ty);
+    }
+    if let Some(is_text) = bytes_or_text(ty) {
+        let content = match &attributes.text_encoding {
+            _ if !is_text => "bytes".to_string(),
+            _ if attributes.bstr => "a `BSTR`, UTF-16LE text followed by a NUL character".to_string(),
+            Some(encoding) => format!("{} text", encoding),
+            None => "UTF-8 text".to_string(),
+        };
+        return match (&attributes.length_field, &attributes.size_type) {
+            (Some(field), _) => format!("`{}` stored as {}, its size in bytes is `{}`", name, content, field),
+            (None, size_ty) => {
+                let size_ty = size_ty.as_ref().map(|t| t.to_string()).unwrap_or_default();
+                format!("`{}` stored as {}, after its size in bytes as a `{}`", name, content, size_ty)
+            }
+        };
+    }
+    if let Some(encoding) = &attributes.encoding {
+        let size_ty = attributes.size_type.as_ref().map(|t| t.to_string()).unwrap_or_default();
+        return format!(
+            "`{}` encoded with `{}`, after its encoded size as a `{}`",
+            name, encoding.name, size_ty
+        );
+    }
+    let type_path = match ty {
+        Type::Path(type_path) => type_path,
+        _ => return format!("`{}`", name),
+    };
+    if let Some(inner_type) = box_item(type_path) {
+        return item_doc(inner_type, attributes);
+    }
+    let first = type_path.path.segments.first();
+    if !first.is_some_and(|s| s.ident == "Vec") {
+        let primitive = type_path.path.get_ident().is_some_and(primitive_type);
+        if let Some(flavor) = attributes.varint.as_ref().filter(|_| primitive) {
+            return format!("`{}` as a `{}` varint", name, flavor);
+        }
+        let kind = if primitive { "" } else { " (see its own layout)" };
+        return format!("`{}`{}", name, kind);
+    }
+    if attributes.bitmap {
+        let counted = if attributes.byte_sized { "size in bytes" } else { "bit count" };
+        let size = match (&attributes.length_field, &attributes.size_type) {
+            (Some(field), _) => format!(", its {} is `{}`", counted, field),
+            (None, Some(size_ty)) => format!(", after its {} as a `{}`", counted, size_ty),
+            (None, None) => String::new(),
+        };
+        let bit = if attributes.msb_first { "highest" } else { "lowest" };
+        return format!("`{}` packed 8 per byte, the first item in the {} bit{}", name, bit, size);
+    }
+    let counted = match (attributes.byte_sized, attributes.size_unit) {
+        (true, None) => "size in bytes".to_string(),
+        (false, None) => "item count".to_string(),
+        (true, Some(unit)) => format!("size in units of {} bytes", unit),
+        (false, Some(unit)) => format!("item count in units of {} items", unit),
+    };
+    let next = match attributes.size_adjust {
+        0 => String::new(),
+        1 => " plus one".to_string(),
+        adjust if adjust < 0 => format!(" minus {}", -adjust),
+        adjust => format!(" plus {}", adjust),
+    };
+    let mut doc = match (&attributes.length_field, &attributes.size_type) {
+        (Some(field), _) => format!("`{}`, its {} is `{}`", name, counted, field),
+        (None, Some(size_ty)) if attributes.size_after => {
+            format!("`{}`, followed by its {}{} as a `{}`", name, counted, next, size_ty)
+        }
+        (None, Some(size_ty)) => {
+            format!("`{}`, after its {}{} as a `{}`", name, counted, next, size_ty)
+        }
+        // already reported by generate_for_vec
+        (None, None) => format!("`{}`", name),
+    };
+    // signed size types need signed_size
+    let signed = attributes.size_type.as_ref().is_some_and(|t| t.to_string().starts_with('i'));
+    if signed && attributes.length_field.is_none() {
+        doc.push_str(", a negative size meaning empty");
+    }
+    if attributes.columnar {
+        doc.push_str(", stored column by column");
+    }
+    // nested Vec may have their own size type
+    if let Some(item) = vec_item(type_path).filter(|t| is_vec(t)) {
+        let mut item_attributes = attributes.vec_item();
+        item_attributes.length_field = None;
+        doc.push_str(&format!(", each item being {}", item_doc(item, &item_attributes)));
+    }
+    doc
+}
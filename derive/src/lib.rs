@@ -11,15 +11,34 @@ use quote::quote;
 use syn::parse::Result;
 use syn::spanned::Spanned;
 use syn::{
-    parse_macro_input, Data, DataEnum, DeriveInput, Fields, GenericArgument, Pat, PathArguments,
-    Type, TypePath,
+    parse_macro_input, Data, DataEnum, DeriveInput, Expr, Fields, GenericArgument, Pat, Path,
+    PathArguments, PathSegment, Type, TypePath,
 };
 
 use proc_macro2::Span;
 use syn::LitInt;
 
+mod arbitrary;
+use arbitrary::arbitrary_impl;
 mod attributes;
-use attributes::{Attributes, Endianness};
+mod columns;
+use columns::columns_impl;
+mod constructor;
+use constructor::constructor_impl;
+mod debug;
+use attributes::{fields_attributes, is_value, Attributes, Encoding, Endianness};
+mod describe;
+use describe::describe_impl;
+mod fixed_size;
+use fixed_size::{fixed_size_impl, offsets_impl};
+mod layout_doc;
+use layout_doc::layout_doc;
+mod pod;
+use pod::pod_impl;
+mod spans;
+use spans::{field_sizes_impl, spans_impl};
+mod tag_names;
+use tag_names::{tag_consts_impl, tag_names_impl};
 
 /// produces a token stream of error to warn the final user of the error
 macro_rules! unwrap {
@@ -50,6 +69,74 @@ fn primitive_type(ty: &Ident) -> bool {
     .any(|i| ty == i)
 }
 
+/// Tell if a type is a primitive integer
+fn integer_type(ty: &Ident) -> bool {
+    primitive_type(ty) && !ty.to_string().starts_with('f')
+}
+
+/// Tell if a type is a primitive unsigned integer, the only ones that can store sizes
+fn unsigned_type(ty: &Ident) -> bool {
+    primitive_type(ty) && ty.to_string().starts_with('u')
+}
+
+/// std types handled by the derive and the module they can be qualified with
+const STD_TYPES: [(&str, &str); 5] = [
+    ("vec", "Vec"),
+    ("option", "Option"),
+    ("boxed", "Box"),
+    ("string", "String"),
+    ("borrow", "Cow"),
+];
+
+/// Rewrite std types named with their full path (`std::vec::Vec<u8>`, `core::primitive::u32`...)
+/// to the bare names the derive dispatches on, including within type arguments
+fn resolve_std_types(ty: &Type) -> Type {
+    let mut ty = ty.clone();
+    resolve_in_place(&mut ty);
+    ty
+}
+
+fn resolve_in_place(ty: &mut Type) {
+    match ty {
+        Type::Path(type_path) => {
+            if type_path.qself.is_none() {
+                if let Some(segment) = std_segment(&type_path.path) {
+                    type_path.path = Path::from(segment);
+                }
+            }
+            for segment in type_path.path.segments.iter_mut() {
+                if let PathArguments::AngleBracketed(pa) = &mut segment.arguments {
+                    for arg in pa.args.iter_mut() {
+                        if let GenericArgument::Type(t) = arg {
+                            resolve_in_place(t);
+                        }
+                    }
+                }
+            }
+        }
+        Type::Tuple(t) => t.elems.iter_mut().for_each(resolve_in_place),
+        Type::Array(t) => resolve_in_place(&mut t.elem),
+        // types passed through macro_rules
+        Type::Group(t) => {
+            *ty = (*t.elem).clone();
+            resolve_in_place(ty);
+        }
+        _ => {}
+    }
+}
+
+/// Last segment of a std type named with its full path, like `::std::vec::Vec<T>`
+fn std_segment(path: &Path) -> Option<PathSegment> {
+    let segments: Vec<_> = path.segments.iter().collect();
+    if segments.len() != 3 || !["std", "core", "alloc"].iter().any(|c| segments[0].ident == c) {
+        return None;
+    }
+    let (module, name) = (&segments[1].ident, &segments[2].ident);
+    let known = STD_TYPES.iter().any(|(m, n)| module == m && name == n)
+        || (module == "primitive" && primitive_type(name));
+    known.then(|| segments[2].clone())
+}
+
 /// We could use `core::mem::size_of` but this is more readable when debugging generated code
 fn primitive_size(ty: &Ident) -> LitInt {
     [
@@ -90,6 +177,92 @@ fn primitive_function(endianness: Endianness) -> (Ident, Ident) {
     )
 }
 
+/// Context of a nested type from the context `ctx` of its container, passed as is if it has the
+/// same type, `&()` if the nested type has none, and converted with `into()` otherwise
+fn pass_context(ty: &impl quote::ToTokens, ctx: &TokenStream) -> TokenStream {
+    quote! {
+        {
+            #[allow(unused_imports)]
+            use plod::context::{PassInto as _, PassUnit as _};
+            (&plod::context::Pass::<<#ty as plod::Plod>::Context, _>::new(#ctx)).get()
+        }
+    }
+}
+
+/// Context of a nested type like `pass_context`, or converted with `TryFrom` for a `try_context`
+/// field, the conversion error is returned
+fn nested_context(ty: &impl quote::ToTokens, ctx: &TokenStream, attributes: &Attributes) -> TokenStream {
+    if attributes.try_context {
+        quote! { &plod::context::try_context::<<#ty as plod::Plod>::Context, _>(#ctx)? }
+    } else {
+        pass_context(ty, ctx)
+    }
+}
+
+/// Is this item read and written by its own `Plod` implementation without any attribute changing
+/// how, so that the items of a `Vec` of it can be handled by shared runtime helpers instead of a
+/// loop generated for each field
+fn plain_item(ty: &Type, attributes: &Attributes) -> bool {
+    let type_path = match resolve_std_types(ty) {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path,
+        _ => return false,
+    };
+    let special = match type_path.path.segments.first() {
+        Some(first) => {
+            primitive_type(&first.ident) || ["Vec", "Option", "Box"].iter().any(|name| first.ident == name)
+        }
+        None => true,
+    };
+    !special
+        && bytes_or_text(ty).is_none()
+        && !is_blob(ty)
+        && integer_storage(ty).is_none()
+        && !is_phantom(ty)
+        && !attributes.seek
+        && !attributes.flatten
+        && !attributes.record_offset
+        && !attributes.skip
+        && !attributes.if_remaining
+        && !attributes.rest
+        && !attributes.bitmap
+        && !attributes.size_after
+        && !attributes.bstr
+        && attributes.checksum.is_none()
+        && attributes.as_type.is_none()
+        && attributes.equals.is_none()
+        && attributes.encoding.is_none()
+}
+
+/// Observer of the context of the type being read, `None` if the context doesn't implement
+/// `plod::Observer`
+fn observer() -> TokenStream {
+    quote! {
+        {
+            #[allow(unused_imports)]
+            use plod::observer::{ObserveImpl as _, ObserveNone as _};
+            (&plod::observer::Observe(ctx)).observer()
+        }
+    }
+}
+
+/// Method used to read nested types
+fn read_method(attributes: &Attributes) -> Ident {
+    if attributes.seek {
+        Ident::new("impl_read_from_seek", Span::call_site())
+    } else {
+        Ident::new("impl_read_from", Span::call_site())
+    }
+}
+
+/// Method used to write nested types
+fn write_method(attributes: &Attributes) -> Ident {
+    if attributes.seek {
+        Ident::new("impl_write_to_seek", Span::call_site())
+    } else {
+        Ident::new("impl_write_to", Span::call_site())
+    }
+}
+
 fn syn_error<S: Spanned, T>(span: &S, message: &str) -> Result<T> {
     Err(syn::Error::new(span.span(), message))
 }
@@ -100,26 +273,120 @@ fn syn_error<S: Spanned, T>(span: &S, message: &str) -> Result<T> {
 /// Attributes can be inherited, which means that if you define a `#[plod(size_type(u8))]` attribute
 /// on a struct, all `Vec` inside this struct will have their size stored as a `u8`;
 ///
+/// Std types are recognized with or without their full path (`std::vec::Vec<u8>`,
+/// `core::primitive::u32`). A `Box<T>` is stored as a `T`, so types can be
+/// recursive, like `Cons(u16, Box<List>)`. A type with a `Box` of a non primitive type has no
+/// `FIXED_SIZE`.
+///
 /// Per type attributes:
 /// - `#[plod(<endianness>)]` (default: `native_endian`), available values: `native_endian`,
-///   `big_endian`, `little_endian`.
-/// - `#[plod(<context_type>)]` (default: `()`): the associated type to use when reading and writing data.
-///   A context can help when reading and writing data structures.
+///   `big_endian`, `little_endian`. It can be overridden by a variant or a field, the tag of an enum
+///   always uses the endianness of the enum, including tags kept in a variant field.
+/// - `#[plod(tag_endianness(<endianness>))]` on an enum: the tag is stored with this endianness
+///   instead of the one of the enum.
+/// - `#[plod(context = <context_type>)]` (default: `()`): the associated type to use when reading and writing data.
+///   A context can help when reading and writing data structures. It can be a type parameter, eg:
+///   `#[plod(context = C)] struct Tlv<C> { ..., marker: PhantomData<C> }` for a structure reused
+///   under several contexts, `PhantomData` fields are not stored.
+///   A context that implements `plod::Observer` is told about unknown tags, unexpected padding and
+///   missing `if_remaining` fields, see `plod::observer`.
 /// - `#[plod(no_pos)]` (default: `false`): do no generate position handling code used for alignment
 /// and padding, it makes slightly shorter code but padding in inner types won't work.
+/// - `#[plod(length_prefixed(<type>))]`: the struct or enum is written after its total length in
+///   bytes (without the length itself) stored as a `<type>`. Reading consumes exactly that many
+///   bytes, unknown trailing bytes are skipped.
+/// - `#[plod(preserve_unknown=<field>)]` on a struct: the bytes of `reserved` regions and the unknown
+///   trailing bytes of a `length_prefixed` struct are kept in `<field>`, a `plod::Preserved` that is
+///   not stored itself, and written back as they were read, see `plod::preserve`.
+/// - `#[plod(length_footer(<type>))]`: the struct or enum is followed by its total length in bytes
+///   (the footer included) stored as a `<type>`, so that records can be scanned backward. Reading
+///   fails with `InvalidData` if the length differs from the bytes read. With
+///   `#[plod(length_footer(<type>, checksum(<checksum_type>, <algorithm>)))]` the length is followed
+///   by a checksum of all the bytes before it, computed like `checksum` fields. `describe` returns
+///   an opaque layout.
+/// - `#[plod(variant_size=<N>)]` on an enum: every variant takes exactly N bytes after the tag, like
+///   a C union. Shorter variants are padded with zeroes, longer ones cannot be written.
+/// - `#[plod(expected_size=<N>)]`: the size at rest must be N bytes, a fixed size type with another
+///   size doesn't compile and other values are checked by `debug_assert` when written.
+/// - `#[plod(align_size(<N>))]`: the size of the struct or enum is padded with zeroes to a multiple of N
+///   bytes, the padding is skipped on read.
+/// - `#[plod(after_read=<method>)]`: call the inherent method `fn(&mut self, &Context) -> plod::Result<()>`
+///   on the value once it has been read, to normalize or validate it.
+/// - `#[plod(construct_with=<path>)]` on a struct: build the value read with the function
+///   `fn(<fields>...) -> Result<Self, E>` instead of a struct literal, for types whose invariants
+///   are enforced by a constructor. It gets all fields in declaration order, skipped ones included.
+///   An `E` that is not a `std::io::Error` fails the read with an error of kind `InvalidData`.
+/// - `#[plod(before_write=<method>)]`: call the inherent method `fn(&self, &Context) -> plod::Result<()>`
+///   before the value is written, an error aborts the write. Since writing only borrows the value,
+///   this method cannot modify it.
+/// - `#[plod(arbitrary)]` (default: `false`, requires the `arbitrary` feature of plod): also implement
+///   `arbitrary::Arbitrary`, generating only values that can be written and read back (no skipped
+///   variant, kept tags matching their variant, `Vec` sizes fitting in their `size_type`).
+/// - `#[plod(debug_derive)]`: print the generated code to stderr during compilation, to debug a
+///   layout without expanding the whole crate.
+/// - `#[plod(pod_cast)]` on a `#[repr(C)]` struct of numbers, arrays and other `pod_cast` structs:
+///   implement `plod::pod::Pod` so that values are read and written with a single copy when the
+///   endianness is the one of the target. A struct with padding is rejected at compile time, as
+///   well as any attribute other than the endianness that would change the bytes or check values.
+/// - `#[plod(constructor)]` on a struct with named fields: generate `new` taking the fields in
+///   order, except the ones the format manages: skipped fields get their default value, `len_of`
+///   and `columnar` count fields are set when written and a kept magic gets the written magic.
+/// - `#[plod(field_sizes)]`: generate `fn field_sizes(&self) -> Vec<(&'static str, usize)>`, the
+///   bytes taken by each field in order, see `plod::dump::field_sizes`.
 ///
 /// Enum specific attributes:
-/// - `#[plod(tag_type(<tag_type>))]` defines the type used to store the enum discriminant. This must be a
-///   primitive type like `u16`, and is stored as the first item of the binary format.
+/// - `#[plod(tag_type(<tag_type>))]` defines the type used to store the enum discriminant. This must be an
+///   integer type like `u16`, and is stored as the first item of the binary format. Negative tags of
+///   signed types are matched like any other value.
+///   It can also be a type implementing `Plod` and `PartialEq`, for composite keys like a class and
+///   a method: the tag is read once with its own implementation and compared with `==` to the
+///   `tag` of each variant, which must then be a single value, eg: `#[plod(tag=Key { class: 1, method: 3 })]`
+///   or a constant. Such enums describe themselves as opaque and don't support `variant_size`.
+/// - `#[plod(tag_fields(<name>: <type>, ...))]` instead of `tag_type`: the discriminant is made of
+///   several primitive fields, read as a tuple and matched with tuple patterns, eg:
+///   `#[plod(tag_fields(class: u8, method: u8))]` on the enum and `#[plod(tag=(1, 3))]` or
+///   `#[plod(tag=(2, _), keep_tag)]` on variants. A kept tag is a tuple of the field types.
+///   Such enums describe themselves as opaque.
+/// - `#[plod(common_fields(<name>: <type>, ...))]` on an enum: primitive fields shared by all
+///   variants, stored once before the tag, eg: a message length and flags. Every variant must have
+///   named fields with these names and types. They are read before the tag, so a guard of a `tag` can
+///   use them, and moved into the variant. Such enums describe themselves as opaque and don't support
+///   `variant_size`.
+/// - `#[plod(dispatch_on_magic)]` instead of `tag_type`: each variant has a `magic` of the same type,
+///   which is read with the endianness of the enum to select the variant and is its tag, eg: chunks
+///   starting with their signature. The first accepted value of a magic is written, and
+///   `keep_magic` keeps the value read in the first field like `keep_tag`.
+/// - `#[plod(tag_names)]` on an enum with an integer `tag_type`: generate
+///   `fn tag_name(tag) -> Option<&'static str>`, the name of the variant a tag selects, eg: for error
+///   messages and logs. Guards are not evaluated, and a variant without `tag` matches every tag.
+/// - `#[plod(tag_consts)]` on an enum with an integer `tag_type`: generate a constant with the tag
+///   written for each variant that has a single tag value, named after the variant, eg:
+///   `Message::PING_TAG` for `Ping`, for routing tables that must agree with the format.
+/// - `#[plod(reserved_tags = <pattern>)]` on an enum: tags matching the pattern, like
+///   `0x40..=0x4F`, are reserved for future records. They are read as the variant
+///   `Reserved { tag, payload: Vec<u8> }`, which must be declared without a `tag`, its payload being
+///   the rest of the region given by `length_prefixed` or `variant_size`.
 /// - `#[plod(skip)]` (default false), the field will be skipped on serialization, but it must implement `Default`
 ///   on deserialization.
 ///q
 /// Variant specific attributes:
 /// - `#[plod(tag=<tag_value>)]` (implies `keep_tag`, see below) defines a value of type `<tag_type>` used
-///   to differentiate each variant. This value can be a match arm (instead of a single value).
+///   to differentiate each variant. This value can be a match arm (instead of a single value), and
+///   a constant of the tag type, eg: `#[plod(tag=OPCODE_PING)]` or `#[plod(tag=Opcode::PING)]`.
+///   It can be followed by a guard on the context, eg: `#[plod(tag=0x80..=0xFF if ctx.extended)]`
+///   selects the variant only if the guard also holds when reading. Layout based tools (`skip_from`,
+///   `convert`...) cannot evaluate guards and return an error of kind Unsupported for these variants.
+///   A variant whose literal tags are all matched by earlier variants without guard is an error.
+/// - `#[plod(tag_aliases(<value>, ...))]` with a `tag` value: other tags also read as this variant,
+///   for formats that renumbered their records. The variant is still written with its `tag`, so
+///   unlike a `tag` pattern this doesn't need `keep_tag`.
+/// - `#[plod(write_tag = <expr>)]` with a `tag` pattern, eg: `#[plod(tag = 0x80.., write_tag = 0x80)]`:
+///   the tag written for this variant when it doesn't keep its tag, for formats where any tag of a
+///   range is read the same. The expression can use the context, writing fails with `InvalidInput`
+///   if its value doesn't match the `tag`.
 /// - `#[plod(keep_tag)]` means that the first field of this variant is used to retain the values
 ///   that was used as a discriminant. It will be equal to `<tag_value>` if a simple value was
-///   provided.
+///   provided. `#[plod(is_tag)]` on another field of the variant retains it in that field instead.
 /// - `#[plod(keep_diff=<integer>)]` (implies `keep_tag`) means that the tag also conveys a value,
 ///   the value is stored after subtracting `<integer>` from the tag. This is especially useful in
 ///   combination with a tag value that is a range. Eg: `#[plod(tag=6..=8, keep_diff=6)]` will
@@ -128,21 +395,172 @@ fn syn_error<S: Spanned, T>(span: &S, message: &str) -> Result<T> {
 /// - `#[plod(skip)]` the variant is ignored, it is not created and produces an error of kind Other
 ///   if encountered during write
 ///
+/// Variant and field attributes:
+/// - `#[plod(no_inherit)]`: attributes of the enclosing type or enum are not inherited, the
+///   variant or field starts from the defaults (`native_endian`, no `size_type`...) before its own
+///   attributes apply, eg: `#[plod(no_inherit, size_type(u32))]` on a `Vec` of a `size_type(u8)`
+///   struct. The context and the tag type are kept.
+///
 /// Field item specific attributes:
 /// - `#[plod(magic(<type>=<value>))]` the field will be prefixed by a magic value. This value must be present
 ///   at rest. It is written with `write_to` and its presence is checked by `read_from` but not stored.
+///   Several values can be accepted with `|`, eg: `#[plod(magic(u32=0xa1b2c3d4|0xd4c3b2a1))]`, the
+///   first one is written. Values can also be constants, eg: `#[plod(magic(u32=HEADER_MAGIC))]`.
+/// - `#[plod(keep_magic)]` with `magic`: the first field, of the magic type, retains the magic value
+///   that was read instead of it being discarded. It is written in place of the magic and must be
+///   one of the accepted values.
+/// - `#[plod(trailer_magic(<type>=<value>))]` on a struct or variant: like `magic`, but the value
+///   comes after the fields, eg: a record bracketed by start and end markers. It is checked after
+///   the fields are read, and cannot follow a `rest` field.
+/// - `#[plod(endian_marker(<type>, big = <value>, little = <value>))]` on a struct: the first field,
+///   of the marker type, tells the endianness of the struct, eg: TIFF starts with `0x4D4D` for big
+///   endian and `0x4949` for little endian. It is read as big endian first, then as little endian,
+///   and other values are an error. The value of the field chooses the endianness of `write_to`.
+///   Nested types would keep their own endianness, so fields can only be primitives, or arrays,
+///   tuples, `Option`, `Box` or `Vec` of them, which is checked at compile time. `describe`
+///   returns an opaque layout.
+/// - `#[plod(pad_to_offset=<offset>)]`: the field starts at the absolute position `<offset>`, a
+///   constant expression. The bytes before it are skipped by `read_from` and written by `write_to`
+///   with the value of `#[plod(fill=<byte>)]` (default: 0). Reaching the field past this offset is
+///   an error. Only for named fields of structs, and `size_at_rest` assumes the value starts at
+///   offset 0, so it is meant for the top level type of a file or image.
+/// - `#[plod(equals = <constant>)]` on a `[u8; N]` field: the field always holds the constant, a
+///   `&[u8; N]` like `b"IEND"`, eg: a signature in the middle of a struct. Other bytes fail to read
+///   with an error of kind `InvalidData`, and the constant is written whatever the field holds.
+/// - `#[plod(reserved(<N>))]` N reserved bytes are stored before the field without being part of the
+///   struct, they are ignored by `read_from` and written as zeroes by `write_to`.
+/// - `#[plod(rest)]` on a last `Vec<u8>` field: it holds all the remaining bytes of the reader, or of
+///   the enclosing `length_prefixed` type, and they are written back as is. The type can then only be
+///   read last, or inside a length prefixed region, and it cannot be padded by `align_size` or
+///   followed by a `length_footer`.
+/// - `#[plod(size_after)]` on a last `Vec` field: its size, stored as its `size_type`, follows its
+///   items at the end of the reader, or of the enclosing `length_prefixed` type. The remaining bytes
+///   are read at once to find it, no more than `max_len` of `plod::fuzz::Limits` while limits are
+///   active, and the type can then only be read last, or inside a length prefixed region. It cannot
+///   be padded by `align_size` or followed by a `length_footer`.
+/// - `#[plod(bitmap)]` on a `Vec<bool>` field: the booleans are packed 8 per byte, the first one in
+///   the lowest bit of the first byte, and unused bits of the last byte are written as 0. The size
+///   counts bits, or bytes with `byte_sized`, in which case all the bits of the bytes are read.
+///   `#[plod(bit_order = msb_first)]` packs the first one in the highest bit instead, the default
+///   being `lsb_first`.
+/// - `#[plod(if_remaining)]` on trailing `Option<T>` fields: the field is `Some` if bytes remain in the
+///   reader, or in the enclosing `length_prefixed` type, and `None` otherwise. It is only written when
+///   `Some`, so fields after a `None` must be `None` too, writing fails with `InvalidInput` otherwise.
+///   Only `if_remaining` fields can follow one, and the type cannot be padded by `align_size` or
+///   followed by a `length_footer`.
+/// - `#[plod(seek_to=<offset>)]`: the field is not stored inline but at the absolute position `<offset>`,
+///   an expression that can use previous fields, eg: `#[plod(seek_to=data_offset)]`. Reading goes on
+///   after the previous field. The type must be read with `read_from_seek` and written with
+///   `write_to_seek` (outside of `length_prefixed` types), other methods return an error.
+/// - `#[plod(checksum(<algorithm>))]` on an integer field: the field is the checksum of the bytes of
+///   the previous fields of the struct or variant, without the magic and the tag. It is computed on
+///   write, the value of the field is ignored, and checked on read. `<algorithm>` implements
+///   `plod::checksum::Checksum`, with the content of `plod::checksum` in scope,
+///   eg: `#[plod(checksum(Crc::CRC16_MODBUS))]` or `#[plod(checksum(Crc::new(16, 0x8bb7)))]`.
+/// - `#[plod(skip_unknown)]` on a `Vec` of `length_prefixed` items: an item containing an unknown enum
+///   tag is skipped instead of failing the whole read, see `plod::lenient`.
 /// - `#[plod(skip)]` (default: false), the field will be skipped on serialization, but it must implement `Default`
 ///   to be created on deserialization.
+/// - `#[plod(as=<type>)]` or `#[plod(as="<type>")]`: the field is handled as if it was declared
+///   with `<type>`, for type aliases and newtypes the derive cannot see through, eg: `#[plod(as=Vec<u8>)]`
+///   on a field of type `Payload` with `type Payload = Vec<u8>;`. A newtype must implement
+///   `From<type>` or `TryFrom<type>` to be read, a conversion error is an error of kind `InvalidData`,
+///   and `Borrow<type>` to be written.
+///   `#[plod(as_vec)]` is a shortcut for `as=Vec<u8>` and `#[plod(as_primitive(<type>))]` for `as=<type>`.
+///   Durations like `plod::Millis<u32>` and `plod::Bounded<u8, 1, 12>` are implicitly handled `as`
+///   their integer.
+/// - `#[plod(record_offset)]` on a `usize` field: the field is not stored, it is set by `read_from`
+///   to the position at which it would be read, counted from the start of the outermost value like
+///   `pad_to_offset`, or of the first record for `plod::iter`. As a first field it tells where a
+///   record came from in a file, after its magic or tag.
 /// - `#[plod(is_context)]` (default: false): this field will be used as the context for all next fields
-///   encountered in this structure.
+///   encountered in this structure. Fields whose context is the type of this field or `()` get it
+///   without any conversion, others need a `From<&<field type>>` for their context.
+/// - `#[plod(try_context)]` on a field whose type has another context: the context is an owned
+///   value built with `TryFrom<&<current context>>`, eg: a lookup in a table of the outer context. A
+///   failed conversion makes reading or writing the field fail, see `plod::context`.
+/// - `#[plod(is_tag)]` on a field of an enum variant: the field retains the tag like `keep_tag` does with
+///   the first field, eg: `Data { len: u16, #[plod(is_tag)] kind: u8 }`. The tag is still stored first.
+/// - `#[plod(flatten)]` on a field whose type implements `Plod`: its fields are part of the current
+///   struct. They are read and written with the current context, without conversion, so the type
+///   must use the same context, and its layout lists them among the fields of the current struct.
+/// - `#[plod(len_of=<field>)]` on an integer field: its value is ignored on write and replaced with the
+///   size of the later `Vec` field `<field>` (in items, or in bytes if it is `byte_sized`). On read,
+///   it is the size of `<field>`, which has no size prefix of its own.
+/// - `#[plod(tag_for=<field>)]` on an integer field and `#[plod(external_tag)]` on the later enum field
+///   `<field>`: the tag of the enum is stored in the integer field instead of before the enum, eg: a
+///   header with `msg_type: u8` and `payload: Body`. The integer has the size and the byte order of
+///   the tag of the enum, its value is ignored on write and replaced with the tag of `<field>`. No
+///   context field can be between them. Such structs describe themselves as opaque. The enum must
+///   store its tag first, without `common_fields`, `magic` or `length_prefixed`, and the integer
+///   must have the size of the tag, which is checked at compile time.
+/// - `#[plod(keep_raw=<field>)]` on a named field: the bytes it is read from are also kept in the
+///   field `<field>`, of a type implementing `From<Vec<u8>>` like `Vec<u8>`, to check a signature or
+///   to copy a section exactly as it was. `<field>` is not stored: it is ignored on write and empty
+///   (`Default`) when the value is not read.
+/// - `#[plod(compress=<algorithm>)]` (requires `size_type`, and the feature of the same name of plod):
+///   the field, a `Vec<u8>` or a type implementing `Plod`, is stored compressed, prefixed with the
+///   compressed size. Available algorithms: `zlib`, `zstd`, `lz4`, a missing feature is a compile
+///   error naming it. `size_at_rest` needs the compressed value, the last few compressions are
+///   reused, see `plod::compress`, and it uses a default context for nested types.
+/// - `#[plod(transform=<path>)]` (requires `size_type`): like `compress`, but the region is encoded
+///   with `<path>::encode(&[u8]) -> Vec<u8>` and decoded with
+///   `<path>::decode(&[u8]) -> plod::Result<Vec<u8>>`, for obfuscation, encryption or custom encodings.
+/// - `#[cfg(...)]` on a named field: the field is read, written and described only when the
+///   configuration predicate holds, so layouts can depend on features or platforms.
 ///
 /// Vec field specific attributes:
 /// - `#[plod(size_type(<size_type>))]` defines the type used to store the `Vec` size. This must
-///   be an integer type. The default is to store the number of items as the _size_.
+///   be an unsigned integer type. The default is to store the number of items as the _size_.
+///   Nested `Vec` use the same type unless one type per level is given, outermost first:
+///   `#[plod(size_type(u32, u16))]` on a `Vec<Vec<u8>>`. The last type is used by deeper levels.
+///   A `plod::Blob` field is stored like a `Vec<u8>` with its `size_type`, but its content is
+///   skipped on read and streamed on write instead of being held in memory.
+///   A `Cow<[u8]>`, `Cow<str>` or `String` field is also stored like a `Vec<u8>`. It is written
+///   from borrowed or owned data and always read as owned data: values are read from any
+///   `std::io::Read`, which cannot lend its bytes, so a `Cow` is not read without a copy, even from
+///   a slice. A `&'a [u8]` or `&'a str` field is stored the same way and read into the arena of the
+///   context, which avoids an allocation per field, see `plod::arena`. A
+///   `plod::InlineBytes<N>` or `plod::InlineStr<N>` field too, it holds at most `N` bytes in
+///   place, see `plod::inline`.
+/// - `#[plod(encoding = <encoding>)]` on a `String` or `Cow<str>` field, or on a type for all its
+///   text fields: how characters are stored, see `plod::text`. Available encodings: `utf8_strict`
+///   (default), `utf8_lossy`, `latin1`, `ascii`, `utf16le` and `utf16be`. Invalid text fails to read with `InvalidData`
+///   telling its offset, characters that cannot be encoded fail to write with `InvalidInput`.
+/// - `#[plod(varint = <flavor>)]` on an unsigned integer field, or a `Vec` of them: the integer is
+///   stored in as few bytes as its value needs, see `plod::varint`. Flavors: `leb128` (or
+///   `protobuf`), `midi_vlq`, `git_vlq` and `sqlite`. Such fields have no fixed size.
+/// - `#[plod(bstr)]` on a `String` or `Cow<str>` field: a Windows `BSTR`, its size in bytes as a
+///   little endian `u32`, its UTF-16LE characters, then a NUL character not counted in the size.
+///   Other length prefixed wide strings use `encoding = utf16le` with their `size_type`, and
+///   `size_unit = 2` to count UTF-16 code units instead of bytes.
 /// - `#[plod(bytes_sized)]` means that the size stored is the number of bytes instead of the numer
-///   of items in the `Vec`
+///   of items in the `Vec`. Reading fails with `InvalidData` if an item crosses the end of this size.
+/// - `#[plod(patched_size)]` (with `byte_sized`) when writing with `write_to_seek`, the size is
+///   written after the items by seeking back instead of computing it beforehand. This can also
+///   be used with `length_prefixed` on a type.
 /// - `#[plod(size_is_next)]` means that the bytes used to store the `Vec` size contains the place
 ///   for the next entry instead of the length of the vector ie: n+1
+/// - `#[plod(signed_size)]` accepts a signed `size_type`, as in formats where a size of -1 means an
+///   absent collection: a negative size is read as an empty collection, which is written back with
+///   a size of 0.
+/// - `#[plod(size_adjust = <N>)]` generalizes `size_is_next`: the size stored is the actual size
+///   plus N, which may be negative, like lengths that include or exclude header bytes. Reading fails
+///   with `InvalidData` if the stored size is less than N, writing fails with `InvalidInput` if the
+///   stored size would be negative. The size is counted in `size_unit` before being adjusted.
+/// - `#[plod(size_unit = <N>)]` means that the size stored counts units of N items, or N bytes if it
+///   is `byte_sized`, like lengths in 4 bytes words or in 512 bytes sectors. It also applies to the
+///   size written by a `len_of` field. Writing fails with `InvalidInput` if the size is not a
+///   multiple of N.
+/// - `#[plod(item_align(<N>))]` on a `Vec` or an array: each item starts at a multiple of N bytes
+///   from the first one, the padding between items is skipped on read, written as zeroes and
+///   counted by `byte_sized` sizes.
+/// - `#[plod(columnar)]` on a `Vec` of structs: the items are stored column by column, the first
+///   field of every item, then the second field of every item, and so on. With
+///   `#[plod(columnar(count = <field>))]` the item count is the earlier integer field `<field>`, as
+///   with `len_of`. Item types must be plain structs whose fields do not depend on each other
+///   (no magic, length prefix, hook, `construct_with`, `len_of`, `keep_raw`, `seek_to`, checksum or
+///   context field), which is checked at compile time.
 ///
 #[proc_macro_derive(Plod, attributes(plod))]
 pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -150,51 +568,355 @@ pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     // get main attributes
-    let attributes = unwrap!(Attributes::parse(&input.attrs));
+    let mut attributes = unwrap!(Attributes::parse(&input.attrs));
+    unwrap!(attributes.magic_tag_type(&input));
 
     // generate everything
     let plod_impl = unwrap!(plod_impl(&input, &attributes));
+    let describe_impl = unwrap!(describe_impl(&input, &attributes));
+    let layout_doc = unwrap!(layout_doc(&input, &attributes));
+    // apart from reading and writing, a type with an endian marker is handled as a big endian one
+    let marked_attributes = attributes.with_marker(Endianness::Big);
+    let spans_impl = unwrap!(spans_impl(&input, &marked_attributes));
+    let fixed_size = unwrap!(fixed_size_impl(&input, &marked_attributes));
+    let offsets_impl = unwrap!(offsets_impl(&input, &marked_attributes));
+    let arbitrary_impl = if attributes.arbitrary {
+        unwrap!(arbitrary_impl(&input, &marked_attributes))
+    } else {
+        TokenStream::new()
+    };
+    let pod_impl = if attributes.pod_cast {
+        unwrap!(pod_impl(&input, &marked_attributes))
+    } else {
+        TokenStream::new()
+    };
+    let constructor_impl = if attributes.constructor {
+        unwrap!(constructor_impl(&input, &attributes))
+    } else {
+        TokenStream::new()
+    };
+    let field_sizes_impl = if attributes.field_sizes {
+        field_sizes_impl(&input)
+    } else {
+        TokenStream::new()
+    };
+    let tag_names_impl = if attributes.tag_names {
+        unwrap!(tag_names_impl(&input, &attributes))
+    } else {
+        TokenStream::new()
+    };
+    let leading_tag_size = leading_tag_size(&input, &attributes);
+    let field_type_checks = unwrap!(field_type_checks(&input, &attributes));
+    let tag_consts_impl = if attributes.tag_consts {
+        unwrap!(tag_consts_impl(&input, &attributes))
+    } else {
+        TokenStream::new()
+    };
 
     // thing for generation
     let name = input.ident;
-    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
-    let type_params = input.generics.type_params();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
     // define endianness generic
     let ctx_ty = attributes.context_type;
 
+    // a fixed size is usable in const contexts
+    let (fixed_size_const, size_impl) = match fixed_size {
+        Some(fixed_size) => {
+            let message = format!("{} has no fixed size", name);
+            let size_impl = if input.generics.params.is_empty() {
+                let expected_size_check = match attributes.expected_size {
+                    Some(expected) => {
+                        let message = format!("{} is not {} bytes at rest", name, expected);
+                        quote! {
+                            const _: () = match <#name as plod::Plod>::FIXED_SIZE {
+                                Some(size) if size != #expected => panic!(#message),
+                                _ => {}
+                            };
+                        }
+                    }
+                    None => TokenStream::new(),
+                };
+                quote! {
+                    impl #name {
+                        /// Size at rest, a compile time error if it depends on the value
+                        pub const SIZE: usize = match <Self as plod::Plod>::FIXED_SIZE {
+                            Some(size) => size,
+                            None => panic!(#message),
+                        };
+                        #offsets_impl
+                    }
+                    #expected_size_check
+                }
+            } else {
+                TokenStream::new()
+            };
+            (quote! { const FIXED_SIZE: Option<usize> = #fixed_size; }, size_impl)
+        }
+        None => (TokenStream::new(), TokenStream::new()),
+    };
+
     // Build the output
     let expanded = quote! {
         // The generated impl.
         #[automatically_derived]
-        impl <#(#type_params),*> plod::Plod for #name #ty_generics #where_clause {
+        #[doc = #layout_doc]
+        // wide tuple fields are spelled out in local variables
+        #[allow(clippy::type_complexity)]
+        impl #impl_generics plod::Plod for #name #ty_generics #where_clause {
             type Context= #ctx_ty;
+            const LAYOUT_DOC: &'static str = #layout_doc;
+            #fixed_size_const
+            #leading_tag_size
             #plod_impl
+            #describe_impl
+            #spans_impl
         }
+        #size_impl
+        #arbitrary_impl
+        #pod_impl
+        #constructor_impl
+        #field_sizes_impl
+        #tag_names_impl
+        #tag_consts_impl
+        #field_type_checks
     };
+    if attributes.debug_derive {
+        debug::print_expansion(&name.to_string(), &expanded);
+    }
 
     // Hand the output tokens back to the compiler
     proc_macro::TokenStream::from(expanded)
 }
 
+/// `LEADING_TAG_SIZE` of an enum whose integer tag is the first thing stored, nothing otherwise
+fn leading_tag_size(input: &DeriveInput, attributes: &Attributes) -> TokenStream {
+    match (&input.data, &attributes.tag_type) {
+        (Data::Enum(_), Some(tag_type))
+            if integer_type(tag_type)
+                && attributes.tag_fields.is_empty()
+                && attributes.common_fields.is_empty()
+                && attributes.magic.is_none()
+                && !attributes.dispatch_on_magic
+                && attributes.length_prefix.is_none()
+                && attributes.endian_marker.is_none() =>
+        {
+            let size = primitive_size(tag_type);
+            quote! { const LEADING_TAG_SIZE: Option<usize> = Some(#size); }
+        }
+        _ => TokenStream::new(),
+    }
+}
+
+/// Assertion that `ty` is an enum whose tag of `tag_size` bytes is stored first, so that its
+/// container can store the tag elsewhere
+fn leading_tag_assert(ty: &Type, tag_size: &LitInt) -> TokenStream {
+    let message = format!(
+        "{} must be an enum storing first a tag as large as its tag_for field, {} byte(s), without common_fields, magic or length_prefixed",
+        quote!(#ty),
+        tag_size.base10_digits()
+    );
+    quote! {
+        assert!(matches!(<#ty as plod::Plod>::LEADING_TAG_SIZE, Some(#tag_size)), #message)
+    }
+}
+
+/// Assertion that the items of a `columnar` vector can be stored in columns
+fn columnar_assert(item: &Type) -> TokenStream {
+    let message = format!(
+        "{} cannot be stored in columns, it must be a struct of independent fields without magic, length prefix, hook or construct_with",
+        quote!(#item)
+    );
+    quote! {
+        assert!(<#item as plod::Plod>::COLUMNAR, #message)
+    }
+}
+
+/// Checks of the types of `external_tag` and `columnar` fields of a type without generics,
+/// evaluated even if the type is never read, types with generics check them when they are read
+fn field_type_checks(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Ok(TokenStream::new());
+    }
+    let all_fields = match &input.data {
+        Data::Struct(data) => vec![(&data.fields, attributes.clone())],
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|v| Ok((&v.fields, attributes.variant(v)?)))
+            .collect::<Result<_>>()?,
+        Data::Union(_) => Vec::new(),
+    };
+    let mut checks = TokenStream::new();
+    for (fields, attributes) in all_fields {
+        for (field, field_attributes) in fields.iter().zip(fields_attributes(fields, &attributes)?) {
+            if let Some((_, tag_ty, _)) = &field_attributes.tag_source {
+                let check = leading_tag_assert(&field.ty, &primitive_size(tag_ty));
+                checks.extend(quote! { const _: () = #check; });
+            }
+            if let (Type::Path(type_path), true) = (resolve_std_types(&field.ty), field_attributes.columnar) {
+                if let Some(item) = vec_item(&type_path) {
+                    let check = columnar_assert(item);
+                    checks.extend(quote! { const _: () = #check; });
+                }
+            }
+        }
+    }
+    Ok(checks)
+}
+
 /// Generate implementation for a given input type (struct or enum)
 fn plod_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let (size_impl, read_impl, write_impl) = generate_impls(input, attributes)?;
+    // the same code, but for seekable readers and writers
+    let mut seek_attributes = attributes.clone();
+    seek_attributes.seek = true;
+    let (_, seek_read_impl, seek_write_impl) = generate_impls(input, &seek_attributes)?;
+    // a length prefixed or fixed size region is not seekable
+    let seek_read_impl = if attributes.length_prefix.is_some() || attributes.variant_size.is_some() {
+        read_impl.clone()
+    } else {
+        seek_read_impl
+    };
+    // sizes that cannot be checked at compile time are checked by debug builds
+    let size_check = match attributes.expected_size {
+        Some(expected) => {
+            let message = format!("{} is not {} bytes at rest", input.ident, expected);
+            quote! { debug_assert_eq!(self.size_at_rest(), #expected, #message); }
+        }
+        None => TokenStream::new(),
+    };
+    // a single copy when the in memory representation is the at rest one
+    let (pod_read, pod_write) = if attributes.pod_cast {
+        (
+            quote! {
+                if <Self as plod::pod::Pod>::NATIVE {
+                    return plod::pod::read_value(from);
+                }
+            },
+            quote! {
+                if <Self as plod::pod::Pod>::NATIVE {
+                    return plod::pod::write_value(self, to);
+                }
+            },
+        )
+    } else {
+        (TokenStream::new(), TokenStream::new())
+    };
+
+    let columns = columns_impl(input, attributes)?;
+
+    // without anything specific to seekable streams, the default implementations of the seek
+    // methods call the other ones instead of duplicating them
+    let read_seek = if seek_read_impl.to_string() != read_impl.to_string() {
+        quote! {
+            fn impl_read_from_seek<R: std::io::Read + std::io::Seek>(from: &mut R, ctx: &Self::Context, mut _pos: usize) -> plod::Result<Self> {
+                let _depth = plod::fuzz::enter()?;
+                let start = _pos;
+                plod::trace::read_start::<Self>(start);
+                let result = (|| -> plod::Result<Self> {
+                    #pod_read
+                    #seek_read_impl
+                })();
+                plod::trace::read_end(start, &result);
+                result
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+    let write_seek = if seek_write_impl.to_string() != write_impl.to_string() {
+        quote! {
+            fn impl_write_to_seek<W: std::io::Write + std::io::Seek>(&self, to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
+                #size_check
+                let start = _pos;
+                plod::trace::write_start(self, start);
+                let result = (|| -> plod::Result<()> {
+                    #pod_write
+                    #seek_write_impl
+                })();
+                plod::trace::write_end(self, start, &result);
+                result
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+
+    Ok(quote! {
+        fn size_at_rest(&self) -> usize {
+            #size_impl
+        }
+
+        #columns
+
+        fn impl_read_from<R: std::io::Read>(from: &mut R, ctx: &Self::Context, mut _pos: usize) -> plod::Result<Self> {
+            let _depth = plod::fuzz::enter()?;
+            let start = _pos;
+            plod::trace::read_start::<Self>(start);
+            let result = (|| -> plod::Result<Self> {
+                #pod_read
+                #read_impl
+            })();
+            plod::trace::read_end(start, &result);
+            result
+        }
+
+        #read_seek
+
+        fn impl_write_to<W: std::io::Write>(&self, to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
+            #size_check
+            let start = _pos;
+            plod::trace::write_start(self, start);
+            let result = (|| -> plod::Result<()> {
+                #pod_write
+                #write_impl
+            })();
+            plod::trace::write_end(self, start, &result);
+            result
+        }
+
+        #write_seek
+    })
+}
+
+/// Generate size, read and write code for a given input type (struct or enum)
+fn generate_impls(
+    input: &DeriveInput,
+    attributes: &Attributes,
+) -> Result<(TokenStream, TokenStream, TokenStream)> {
     let self_name = &input.ident;
 
     let (size_impl, read_impl, write_impl) = match &input.data {
+        Data::Struct(_) if attributes.endian_marker.is_some() && attributes.construct_with.is_some() => {
+            return syn_error(self_name, "construct_with doesn't work with endian_marker")
+        }
+        Data::Struct(data) if attributes.endian_marker.is_some() => endian_marked(input, &data.fields, attributes)?,
         Data::Struct(data) => {
             // generate for all fields
             let (size_code, read_code, write_code, field_list) = generate_for_fields(
                 &data.fields,
                 Some(&quote! { self. }),
                 &input.ident,
+                &input.ident.to_string(),
                 &attributes,
             )?;
+            let construct = match &attributes.construct_with {
+                // fields are passed in declaration order
+                Some(function) => {
+                    let args = data.fields.iter().enumerate().map(|(i, field)| match &field.ident {
+                        Some(ident) => ident.clone(),
+                        None => Ident::new(&format!("field_{}", i), field.span()),
+                    });
+                    quote! { plod::helpers::constructed(#function(#(#args),*)) }
+                }
+                None => quote! { Ok(#self_name #field_list) },
+            };
             (
                 size_code,
                 quote! {
                     #read_code
-                    Ok(#self_name #field_list)
+                    #construct
                 },
                 quote! {
                     #write_code
@@ -202,6 +924,15 @@ fn plod_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream
                 },
             )
         }
+        Data::Enum(_) if attributes.construct_with.is_some() => {
+            return syn_error(self_name, "construct_with only works with structs")
+        }
+        Data::Enum(_) if attributes.endian_marker.is_some() => {
+            return syn_error(self_name, "endian_marker only works with structs")
+        }
+        Data::Enum(_) if attributes.preserve_unknown.is_some() => {
+            return syn_error(self_name, "preserve_unknown only works with structs, keep unknown variants with a catch-all variant")
+        }
         Data::Enum(data) => enum_impl(self_name, data, attributes)?,
         Data::Union(u) => {
             return Err(syn::Error::new(
@@ -211,19 +942,409 @@ fn plod_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream
         }
     };
 
-    Ok(quote! {
-        fn size_at_rest(&self) -> usize {
-            #size_impl
+    let read_impl = match &attributes.after_read {
+        Some(method) => quote! {
+            let mut value = (|| -> plod::Result<Self> { #read_impl })()?;
+            value.#method(ctx)?;
+            Ok(value)
+        },
+        None => read_impl,
+    };
+    let write_impl = match &attributes.before_write {
+        Some(method) => quote! {
+            self.#method(ctx)?;
+            #write_impl
+        },
+        None => write_impl,
+    };
+    let (size_impl, read_impl, write_impl) = match attributes.variant_size {
+        Some(variant_size) => {
+            let tag_size = match (&input.data, &attributes.tag_type) {
+                (Data::Enum(_), Some(tag_type)) if primitive_type(tag_type) => primitive_size(tag_type),
+                (Data::Enum(_), Some(tag_type)) => {
+                    return syn_error(tag_type, "variant_size only works with primitive tag types")
+                }
+                (Data::Enum(_), None) if !attributes.tag_fields.is_empty() => {
+                    LitInt::new(&attributes.tag_fields_size().to_string(), Span::call_site())
+                }
+                _ => return syn_error(self_name, "variant_size only works with enums"),
+            };
+            variant_sized(variant_size, tag_size, size_impl, read_impl, write_impl)
+        }
+        None => (size_impl, read_impl, write_impl),
+    };
+    trailing_fields(input, attributes)?;
+    let (size_impl, read_impl, write_impl) = match attributes.align_size {
+        Some(0) => return syn_error(self_name, "align_size must not be 0"),
+        Some(align) => size_aligned(align, size_impl, read_impl, write_impl),
+        None => (size_impl, read_impl, write_impl),
+    };
+    let (size_impl, read_impl, write_impl) = match &attributes.length_footer {
+        Some((length_ty, checksum)) => {
+            length_footed(length_ty, checksum.as_ref(), attributes, size_impl, read_impl, write_impl)?
         }
+        None => (size_impl, read_impl, write_impl),
+    };
+    let (size_impl, read_impl, write_impl) = match &attributes.length_prefix {
+        Some(length_ty) => length_prefixed(length_ty, attributes, size_impl, read_impl, write_impl)?,
+        None => (size_impl, read_impl, write_impl),
+    };
+    Ok((size_impl, read_impl, write_impl))
+}
 
-        fn impl_read_from<R: std::io::Read>(from: &mut R, ctx: &Self::Context, mut _pos: usize) -> plod::Result<Self> {
+/// Reject fields that read up to the end of the data when the type is followed by bytes of its own
+fn trailing_fields(input: &DeriveInput, attributes: &Attributes) -> Result<()> {
+    let trailer = match (attributes.align_size, &attributes.length_footer) {
+        (Some(_), _) => "align_size padding",
+        (None, Some(_)) => "a length_footer",
+        (None, None) => return Ok(()),
+    };
+    let all_fields = match &input.data {
+        Data::Struct(data) => vec![(&data.fields, attributes.clone())],
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| Ok((&variant.fields, attributes.variant(variant)?)))
+            .collect::<Result<Vec<_>>>()?,
+        Data::Union(_) => return Ok(()),
+    };
+    for (fields, attributes) in all_fields {
+        for (field, field_attributes) in fields.iter().zip(fields_attributes(fields, &attributes)?) {
+            if field_attributes.rest {
+                return syn_error(field, &format!("#[plod(rest)] cannot be followed by {}", trailer));
+            }
+            if field_attributes.if_remaining {
+                return syn_error(field, &format!("#[plod(if_remaining)] cannot be followed by {}", trailer));
+            }
+            if field_attributes.size_after {
+                return syn_error(field, &format!("#[plod(size_after)] cannot be followed by {}", trailer));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Generate size, read and write code for a struct whose endianness is given by its first field
+fn endian_marked(
+    input: &DeriveInput,
+    fields: &Fields,
+    attributes: &Attributes,
+) -> Result<(TokenStream, TokenStream, TokenStream)> {
+    let self_name = &input.ident;
+    // checked by the caller
+    let (ty, big, little) = attributes.endian_marker.as_ref().unwrap();
+    if attributes.magic.is_some() || attributes.keep_magic {
+        return syn_error(ty, "endian_marker cannot be used with magic");
+    }
+    if !primitive_type(ty) {
+        return syn_error(ty, "endian_marker only works with primitive types");
+    }
+    // nested types have their endianness fixed at compile time
+    for (field, field_attributes) in fields.iter().zip(fields_attributes(fields, attributes)?) {
+        if !field_attributes.skip && !is_phantom(&field.ty) && !primitive_items(&field.ty) {
+            return syn_error(
+                &field.ty,
+                "endian_marker only works with primitive fields, and arrays, tuples, Option, Box or Vec of them",
+            );
+        }
+    }
+    let ty_size = primitive_size(ty);
+    // each endianness has its own code, the marker is a kept magic in both
+    let mut branches = Vec::new();
+    for endianness in [Endianness::Big, Endianness::Little] {
+        branches.push(generate_for_fields(
+            fields,
+            Some(&quote! { self. }),
+            self_name,
+            &self_name.to_string(),
+            &attributes.with_marker(endianness),
+        )?);
+    }
+    let (size_code, big_read, big_write, field_list) = &branches[0];
+    let (_, little_read, little_write, _) = &branches[1];
+    let marker_field = match fields.iter().next().and_then(|f| f.ident.as_ref()) {
+        Some(ident) => quote! { self.#ident },
+        None => quote! { self.0 },
+    };
+    let text = format!("{} or {}", quote!(#big), quote!(#little));
+    Ok((
+        size_code.clone(),
+        quote! {
+            let mut marker: [u8; #ty_size] = [0; #ty_size];
+            from.read_exact(&mut marker)?;
+            if matches!(#ty::from_be_bytes(marker), #big) {
+                #big_read
+                Ok(#self_name #field_list)
+            } else if matches!(#ty::from_le_bytes(marker), #little) {
+                #little_read
+                Ok(#self_name #field_list)
+            } else {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Endian marker {} expected, found {:?}", #text, marker),
+                ))
+            }
+        },
+        quote! {
+            if matches!(#marker_field, #big) {
+                #big_write
+            } else {
+                #little_write
+            }
+            Ok(())
+        },
+    ))
+}
+
+/// Wrap generated code to handle the total length written before the value
+fn length_prefixed(
+    length_ty: &Ident,
+    attributes: &Attributes,
+    size_impl: TokenStream,
+    read_impl: TokenStream,
+    write_impl: TokenStream,
+) -> Result<(TokenStream, TokenStream, TokenStream)> {
+    if !unsigned_type(length_ty) {
+        return syn_error(length_ty, "length_prefixed only works with unsigned integer types");
+    }
+    let ty_size = primitive_size(length_ty);
+    let (from_method, to_method) = primitive_function(attributes.endianness);
+    // unknown trailing bytes are skipped, or kept to be written back
+    let (size_impl, keep_trailing, write_impl) = match &attributes.preserve_unknown {
+        Some(holder) => (
+            quote! { ({ #size_impl }) + self.#holder.trailing().len() },
+            quote! {
+                let mut trailing = Vec::new();
+                std::io::Read::read_to_end(&mut region, &mut trailing)?;
+                value.#holder.keep_trailing(trailing);
+            },
+            quote! {
+                (|| -> plod::Result<()> { #write_impl })()?;
+                to.write_all(self.#holder.trailing())?;
+                Ok(())
+            },
+        ),
+        None => (size_impl, TokenStream::new(), write_impl),
+    };
+    let size_impl = quote! {
+        #ty_size + { #size_impl }
+    };
+    // read within the announced length
+    let read_impl = quote! {
+        let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+        _pos += #ty_size;
+        let length = #length_ty::#from_method(buffer) as u64;
+        let mut region = std::io::Read::take(&mut *from, length);
+        #[allow(unused_mut)]
+        let mut value = (|from: &mut std::io::Take<&mut R>| -> plod::Result<Self> {
+            // methods of a concrete reader need the trait in scope
+            #[allow(unused_imports)]
+            use std::io::Read as _;
             #read_impl
+        })(&mut region)
+            .map_err(|e| plod::lenient::skip_region(e, &mut region, #ty_size + length as usize))?;
+        #keep_trailing
+        let trailing = region.limit();
+        if std::io::copy(&mut region, &mut std::io::sink())? != trailing {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
         }
+        Ok(value)
+    };
+    if attributes.patched_size && attributes.seek {
+        let write_impl = quote! {
+            let length_position = std::io::Seek::stream_position(to)?;
+            to.write_all(&[0_u8; #ty_size])?;
+            _pos += #ty_size;
+            (|| -> plod::Result<()> { #write_impl })()?;
+            let end = std::io::Seek::stream_position(to)?;
+            let length = (end - length_position) as usize - #ty_size;
+            if length > #length_ty::MAX as usize {
+                return Err(std::io::Error::other(format!("Length {} does not fit in {}", length, stringify!(#length_ty))));
+            }
+            std::io::Seek::seek(to, std::io::SeekFrom::Start(length_position))?;
+            to.write_all(&(length as #length_ty).#to_method())?;
+            std::io::Seek::seek(to, std::io::SeekFrom::Start(end))?;
+            Ok(())
+        };
+        return Ok((size_impl, read_impl, write_impl));
+    }
+    let write_impl = quote! {
+        let length = plod::Plod::size_at_rest(self) - #ty_size;
+        if length > #length_ty::MAX as usize {
+            return Err(std::io::Error::other(format!("Length {} does not fit in {}", length, stringify!(#length_ty))));
+        }
+        let buffer: [u8; #ty_size] = (length as #length_ty).#to_method();
+        to.write_all(&buffer)?;
+        _pos += #ty_size;
+        #write_impl
+    };
+    Ok((size_impl, read_impl, write_impl))
+}
 
-        fn impl_write_to<W: std::io::Write>(&self, to: &mut W, ctx: &Self::Context, mut _pos: usize) -> plod::Result<()> {
-            #write_impl
+/// Wrap generated code to handle the total length, and the checksum, written after the value
+fn length_footed(
+    length_ty: &Ident,
+    checksum: Option<&(Ident, Expr)>,
+    attributes: &Attributes,
+    size_impl: TokenStream,
+    read_impl: TokenStream,
+    write_impl: TokenStream,
+) -> Result<(TokenStream, TokenStream, TokenStream)> {
+    if !unsigned_type(length_ty) {
+        return syn_error(length_ty, "length_footer only works with unsigned integer types");
+    }
+    let ty_size = primitive_size(length_ty);
+    let (from_method, to_method) = primitive_function(attributes.endianness);
+    let (mut read_checksum, mut write_checksum) = (TokenStream::new(), TokenStream::new());
+    let (mut read_recorder, mut write_recorder) = (TokenStream::new(), TokenStream::new());
+    let mut checksum_size = quote! { 0 };
+    if let Some((checksum_ty, algorithm)) = checksum {
+        if !primitive_type(checksum_ty) || checksum_ty == "f32" || checksum_ty == "f64" {
+            return syn_error(checksum_ty, "A checksum must be an integer");
         }
-    })
+        let size = primitive_size(checksum_ty);
+        checksum_size = quote! { #size };
+        // the checksum covers the value and the length
+        read_recorder = quote! {
+            #[allow(unused_imports)]
+            use std::io::{Read as _, Seek as _};
+            let mut footer_recorder = plod::checksum::Recorder::new(&mut *from);
+            let from = &mut footer_recorder;
+        };
+        write_recorder = quote! {
+            #[allow(unused_imports)]
+            use std::io::{Seek as _, Write as _};
+            let mut footer_recorder = plod::checksum::Recorder::new(&mut *to);
+            let to = &mut footer_recorder;
+        };
+        read_checksum = quote! {
+            let computed_checksum = {
+                use plod::checksum::*;
+                Checksum::checksum(&(#algorithm), from.recorded()) as #checksum_ty
+            };
+            let buffer: [u8; #size] = plod::helpers::read_bytes(from)?;
+            _pos += #size;
+            let checksum = #checksum_ty::#from_method(buffer);
+            if checksum != computed_checksum {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Checksum {:#x} expected, found {:#x}", computed_checksum, checksum),
+                ));
+            }
+        };
+        write_checksum = quote! {
+            let computed_checksum = {
+                use plod::checksum::*;
+                Checksum::checksum(&(#algorithm), to.recorded()) as #checksum_ty
+            };
+            to.write_all(&computed_checksum.#to_method())?;
+            _pos += #size;
+        };
+    }
+    let size_impl = quote! {
+        #ty_size + #checksum_size + { #size_impl }
+    };
+    // the length read is compared with the bytes actually read
+    let read_impl = quote! {
+        let footer_start = _pos;
+        #read_recorder
+        let value = (|| -> plod::Result<Self> { #read_impl })()?;
+        let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+        _pos += #ty_size;
+        let length = #length_ty::#from_method(buffer) as usize;
+        let read = _pos - footer_start + #checksum_size;
+        if length != read {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Length {} found in the footer, {} bytes were read", length, read),
+            ));
+        }
+        #read_checksum
+        Ok(value)
+    };
+    let write_impl = quote! {
+        let length = { #size_impl };
+        if length > #length_ty::MAX as usize {
+            return Err(std::io::Error::other(format!("Length {} does not fit in {}", length, stringify!(#length_ty))));
+        }
+        #write_recorder
+        (|| -> plod::Result<()> { #write_impl })()?;
+        to.write_all(&(length as #length_ty).#to_method())?;
+        _pos += #ty_size;
+        #write_checksum
+        Ok(())
+    };
+    Ok((size_impl, read_impl, write_impl))
+}
+
+/// Wrap size, read and write code so that the size is padded to a multiple of `align`
+fn size_aligned(
+    align: usize,
+    size_impl: TokenStream,
+    read_impl: TokenStream,
+    write_impl: TokenStream,
+) -> (TokenStream, TokenStream, TokenStream) {
+    let aligned_size_impl = quote! {
+        {
+            let content = { #size_impl };
+            content + (#align - content % #align) % #align
+        }
+    };
+    let read_impl = quote! {
+        let align_start = _pos;
+        let value = (|| -> plod::Result<Self> { #read_impl })()?;
+        let padding = (#align - (_pos - align_start) % #align) % #align;
+        let mut buffer: [u8; #align] = [0; #align];
+        from.read_exact(&mut buffer[..padding])?;
+        Ok(value)
+    };
+    let write_impl = quote! {
+        let content = { #size_impl };
+        (|| -> plod::Result<()> { #write_impl })()?;
+        let padding = (#align - content % #align) % #align;
+        to.write_all(&[0_u8; #align][..padding])?;
+        Ok(())
+    };
+    (aligned_size_impl, read_impl, write_impl)
+}
+
+/// Wrap size, read and write code of an enum so that all variants take the same size
+fn variant_sized(
+    variant_size: usize,
+    tag_size: LitInt,
+    size_impl: TokenStream,
+    read_impl: TokenStream,
+    write_impl: TokenStream,
+) -> (TokenStream, TokenStream, TokenStream) {
+    let fixed_size_impl = quote! {
+        #tag_size + #variant_size
+    };
+    // read within the variant size, unused bytes are skipped
+    let read_impl = quote! {
+        let mut region = std::io::Read::take(&mut *from, (#tag_size + #variant_size) as u64);
+        let value = (|from: &mut std::io::Take<&mut R>| -> plod::Result<Self> {
+            // methods of a concrete reader need the trait in scope
+            #[allow(unused_imports)]
+            use std::io::Read as _;
+            #read_impl
+        })(&mut region)?;
+        let trailing = region.limit();
+        if std::io::copy(&mut region, &mut std::io::sink())? != trailing {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        }
+        Ok(value)
+    };
+    let write_impl = quote! {
+        let content = { #size_impl } - #tag_size;
+        if content > #variant_size {
+            return Err(std::io::Error::other(format!("Variant size {} is larger than {}", content, #variant_size)));
+        }
+        (|| -> plod::Result<()> { #write_impl })()?;
+        let padding = (#variant_size - content) as u64;
+        std::io::copy(&mut std::io::Read::take(std::io::repeat(0), padding), to)?;
+        Ok(())
+    };
+    (fixed_size_impl, read_impl, write_impl)
 }
 
 /// Generate code for all variants of an enum
@@ -241,27 +1362,93 @@ fn enum_impl(
     //   the exact value before reading the variant.
 
     // check enum attributes
+    // with tag_fields, the tag is a tuple of primitives matched with the patterns of the variants
+    let tag_fields = &attributes.tag_fields;
     let tag_type = match &attributes.tag_type {
+        Some(t) if !tag_fields.is_empty() => {
+            return syn_error(t, "tag_type and tag_fields cannot be used together")
+        }
+        Some(t) if primitive_type(t) && !integer_type(t) => {
+            return syn_error(t, "tag_type must be an integer type, eg: #[plod(tag_type(u8))]")
+        }
         Some(t) => t,
-        None => return syn_error(self_name, "#[plod(tag_type(<type>)] is mandatory for enum"),
+        // only used to report errors
+        None if !tag_fields.is_empty() => self_name,
+        None => {
+            return syn_error(
+                self_name,
+                "#[plod(tag_type(<type>))] is mandatory for enum, eg: #[plod(tag_type(u8))], accepted types are u8, u16, u32, u64, u128, i8, i16, i32, i64 and i128",
+            )
+        }
     };
-    if !primitive_type(tag_type) {
-        return syn_error(
-            &tag_type,
-            "#[plod(tag_type(<type>)] tag only works with primitive types",
-        );
+    if attributes.reserved_tags.is_some() {
+        if attributes.length_prefix.is_none() && attributes.variant_size.is_none() {
+            return syn_error(self_name, "reserved_tags needs the payload size given by length_prefixed or variant_size");
+        }
+        if !data.variants.iter().any(|v| v.ident == "Reserved") {
+            return syn_error(self_name, "reserved_tags needs a `Reserved { tag, payload: Vec<u8> }` variant");
+        }
+    }
+    // other tag types are read with their Plod implementation and compared with ==
+    let composite = tag_fields.is_empty() && !primitive_type(tag_type);
+    if !composite && tag_fields.is_empty() {
+        check_shadowed_variants(data, tag_type, attributes)?;
+    }
+    let tag_size = if composite {
+        quote! { <#tag_type as plod::Plod>::size_at_rest(&discriminant) }
+    } else if !tag_fields.is_empty() {
+        let size = attributes.tag_fields_size();
+        quote! { #size }
+    } else {
+        let size = primitive_size(tag_type);
+        quote! { #size }
+    };
+    // variants may override the endianness of their fields, not the one of the tag
+    let tag_endianness = attributes.tag_endianness.unwrap_or(attributes.endianness);
+    let (from_method, to_method) = primitive_function(tag_endianness);
+
+    // common fields are read before the tag and moved into the variant, which writes them
+    let mut common_read = TokenStream::new();
+    let mut common_write = TokenStream::new();
+    if !attributes.common_fields.is_empty() && attributes.variant_size.is_some() {
+        return syn_error(self_name, "common_fields cannot be used with variant_size");
+    }
+    for (name, ty) in attributes.common_fields.iter() {
+        let ty_size = primitive_size(ty);
+        let (from_method, to_method) = primitive_function(attributes.endianness);
+        in_field(
+            name,
+            &self_name.to_string(),
+            &name.to_string(),
+            attributes,
+            quote! {
+                let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+                let #name = #ty::#from_method(buffer);
+                _pos += #ty_size;
+            },
+            quote! {
+                to.write_all(&#name.#to_method())?;
+                _pos += #ty_size;
+            },
+            &mut common_read,
+            &mut common_write,
+        );
     }
-    let tag_size = primitive_size(tag_type);
-    let (from_method, to_method) = primitive_function(attributes.endianness);
 
     // iterate over variants
     let mut default_done = false;
+    let mut expected = String::new();
     for variant in data.variants.iter() {
         let ident = &variant.ident;
 
         // check variant attributes
-        let variant_attributes = attributes.extend(&variant.attrs)?;
-        let tag_value = &variant_attributes.tag;
+        let mut variant_attributes = attributes.variant(variant)?;
+        variant_attributes.tag_endianness = Some(tag_endianness);
+        // aliases are read as the variant, which is written with its tag
+        let tag_value = &variant_attributes.tag_pattern();
+        if variant_attributes.tag.is_none() && !variant_attributes.tag_aliases.is_empty() {
+            return syn_error(ident, "tag_aliases needs a tag");
+        }
 
         // handle skipped values, no size code, no read code, error on write
         if variant_attributes.skip {
@@ -283,6 +1470,14 @@ fn enum_impl(
             continue;
         }
 
+        // every variant holds the common fields
+        let missing = attributes.common_fields.iter().find(|(name, _)| {
+            !variant.fields.iter().any(|f| f.ident.as_ref() == Some(name))
+        });
+        if let Some((name, _)) = missing {
+            return syn_error(&variant.ident, &format!("The variant must have the common field `{}`", name));
+        }
+
         // handle default value
         if default_done {
             return syn_error(
@@ -291,14 +1486,38 @@ fn enum_impl(
             );
         }
 
+        // accepted tags, listed by unknown tag errors
+        if let Some(value) = tag_value {
+            if !expected.is_empty() {
+                expected.push_str(", ");
+            }
+            expected.push_str(&quote!(#value).to_string().replace(' ', ""));
+        }
+
         // generate for all fields
+        let type_name = format!("{}::{}", self_name, ident);
         let (size_code, read_code, write_code, field_list) =
-            generate_for_fields(&variant.fields, None, &variant.ident, &variant_attributes)?;
+            generate_for_fields(&variant.fields, None, &variant.ident, &type_name, &variant_attributes)?;
 
         // code for reading variant
+        let guard = variant_attributes
+            .tag_guard
+            .as_ref()
+            .map(|guard| quote! { if #guard });
         match &tag_value {
+            Some(_) if composite => {
+                let value = composite_tag(variant_attributes.tag.as_ref().unwrap())?;
+                let aliases = variant_attributes.tag_aliases.iter().map(composite_tag).collect::<Result<Vec<_>>>()?;
+                let guard = variant_attributes.tag_guard.as_ref().map(|guard| quote! { && (#guard) });
+                read_impl.extend(quote! {
+                    _ if (discriminant == #value #(|| discriminant == #aliases)*) #guard => {
+                        #read_code
+                        Ok(#self_name::#ident #field_list)
+                    }
+                })
+            }
             Some(value) => read_impl.extend(quote! {
-                #value => {
+                #value #guard => {
                     #read_code
                     Ok(#self_name::#ident #field_list)
                 }
@@ -315,7 +1534,47 @@ fn enum_impl(
         }
 
         // code for writing variant
-        let add_tag = if variant_attributes.keep_tag {
+        let add_tag = if let Some(write_tag) = &variant_attributes.write_tag {
+            if variant_attributes.keep_tag {
+                return syn_error(write_tag, "write_tag is not used with keep_tag, the kept tag is written");
+            }
+            if !tag_fields.is_empty() {
+                return syn_error(write_tag, "write_tag needs a single tag_type");
+            }
+            // a tag that would not be read back as this variant is refused
+            let message = format!("tag written for {}::{} doesn't match its tag", self_name, ident);
+            let matching = match &variant_attributes.tag {
+                Some(tag) if composite => {
+                    let value = composite_tag(tag)?;
+                    let aliases = variant_attributes.tag_aliases.iter().map(composite_tag).collect::<Result<Vec<_>>>()?;
+                    Some(quote! { discriminant == #value #(|| discriminant == #aliases)* })
+                }
+                Some(_) => {
+                    let pattern = variant_attributes.tag_pattern();
+                    Some(quote! { matches!(discriminant, #pattern) })
+                }
+                None => None,
+            };
+            let check = matching.map(|matching| {
+                quote! {
+                    if !(#matching) {
+                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, #message));
+                    }
+                }
+            });
+            let write = if composite {
+                let context = pass_context(tag_type, &quote! { ctx });
+                quote! { <#tag_type as plod::Plod>::impl_write_to(&discriminant, to, #context, _pos)?; }
+            } else {
+                quote! { to.write_all(&discriminant.#to_method())?; }
+            };
+            quote! {
+                let discriminant: #tag_type = #write_tag;
+                #check
+                #write
+                _pos += #tag_size;
+            }
+        } else if variant_attributes.keep_tag {
             TokenStream::new()
         } else {
             let tag_pattern = match &variant_attributes.tag {
@@ -324,19 +1583,46 @@ fn enum_impl(
                     return syn_error(ident, "#[plod(tag(<value>))] is mandatory without keep_tag")
                 }
             };
-            let tag_value = match tag_pattern {
-                Pat::Lit(expr) => expr,
-                _ => {
-                    return syn_error(tag_type, "#[plod(keep_tag)] is mandatory with tag patterns")
+            if composite {
+                let tag_value = composite_tag(tag_pattern)?;
+                let context = pass_context(tag_type, &quote! { ctx });
+                quote! {
+                    let discriminant: #tag_type = #tag_value;
+                    <#tag_type as plod::Plod>::impl_write_to(&discriminant, to, #context, _pos)?;
+                    _pos += #tag_size;
+                }
+            } else if !tag_fields.is_empty() {
+                let values = match tag_pattern {
+                    Pat::Tuple(t) if t.elems.len() == tag_fields.len() && t.elems.iter().all(is_value) => &t.elems,
+                    _ => return syn_error(tag_pattern, "#[plod(keep_tag)] is mandatory with tag patterns, a tag value has one value per tag field"),
+                };
+                let mut code = TokenStream::new();
+                for (value, (_, ty)) in values.iter().zip(tag_fields) {
+                    code.extend(quote! {
+                        to.write_all(&(#value as #ty).#to_method())?;
+                    });
+                }
+                quote! {
+                    #code
+                    _pos += #tag_size;
+                }
+            } else {
+                // the first accepted magic is written
+                let tag_value = match tag_pattern {
+                    Pat::Or(or) if attributes.dispatch_on_magic => &or.cases[0],
+                    pattern if is_value(pattern) => pattern,
+                    _ => return syn_error(tag_type, "#[plod(keep_tag)] or #[plod(write_tag = <value>)] is mandatory with tag patterns"),
+                };
+                quote! {
+                    let buffer: [u8; #tag_size] = (#tag_value as #tag_type).#to_method();
+                    to.write_all(&buffer)?;
+                    _pos += #tag_size;
                 }
-            };
-            quote! {
-                let buffer: [u8; #tag_size] = (#tag_value as #tag_type).#to_method();
-                to.write_all(&buffer)?;
             }
         };
         write_impl.extend(quote! {
             #self_name::#ident #field_list => {
+                #common_write
                 #add_tag
                 #write_code
             }
@@ -354,12 +1640,50 @@ fn enum_impl(
         }
     };
     // finalize read_impl
+    let (read_tag, unknown_tag) = if composite {
+        let context = pass_context(tag_type, &quote! { ctx });
+        (
+            quote! {
+                let discriminant = <#tag_type as plod::Plod>::impl_read_from(from, #context, _pos)?;
+                _pos += #tag_size;
+            },
+            quote! { 0 },
+        )
+    } else if !tag_fields.is_empty() {
+        let mut code = TokenStream::new();
+        let mut values = TokenStream::new();
+        for (i, (_, ty)) in tag_fields.iter().enumerate() {
+            let ty_size = primitive_size(ty);
+            let value = Ident::new(&format!("tag_{}", i), Span::call_site());
+            code.extend(quote! {
+                let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+                let #value = #ty::#from_method(buffer);
+            });
+            values.extend(quote! { #value, });
+        }
+        (
+            quote! {
+                #code
+                let discriminant = (#values);
+                _pos += #tag_size;
+            },
+            quote! { 0 },
+        )
+    } else {
+        (
+            quote! {
+                let buffer: [u8; #tag_size] = plod::helpers::read_bytes(from)?;
+                let discriminant = #tag_type::#from_method(buffer);
+                _pos += #tag_size;
+            },
+            quote! { discriminant as i128 },
+        )
+    };
     let read_tag = quote! {
-        let mut buffer: [u8; #tag_size] = [0; #tag_size];
-        from.read_exact(&mut buffer)?;
-        let discriminant = #tag_type::#from_method(buffer);
-        _pos += #tag_size;
+        #common_read
+        #read_tag
     };
+    let observer = observer();
     if default_done {
         read_impl = quote! {
             #read_tag
@@ -372,7 +1696,19 @@ fn enum_impl(
             #read_tag
             match discriminant {
                 #read_impl
-                _ => return Err(std::io::Error::other(format!("Tag value {} not found", discriminant))),
+                _ => {
+                    let unknown = plod::lenient::UnknownTag {
+                        type_name: std::any::type_name::<Self>(),
+                        tag: #unknown_tag,
+                        expected: #expected,
+                        position: _pos - #tag_size,
+                        skipped: None,
+                    };
+                    if let Some(observer) = #observer {
+                        observer.on_unknown_tag(&unknown);
+                    }
+                    return Err(std::io::Error::other(unknown));
+                }
             }
         };
     }
@@ -386,11 +1722,144 @@ fn enum_impl(
     Ok((size_impl, read_impl, write_impl))
 }
 
+/// Check that the field retaining the tag of a variant can hold it, other types would silently be
+/// read from the data following the tag
+fn check_kept_tag(field_type: &Type, attributes: &Attributes) -> Result<()> {
+    let type_path = match field_type {
+        Type::Path(type_path) => type_path,
+        _ => return syn_error(field_type, "The field retaining the tag must have the type of the tag"),
+    };
+    let collection = type_path.path.segments.first().is_some_and(|s| s.ident == "Vec" || s.ident == "Option");
+    if collection || bytes_or_text(field_type).is_some() || is_blob(field_type) {
+        return syn_error(field_type, "The field retaining the tag must have the type of the tag");
+    }
+    if attributes.keep_diff.is_some() && box_item(type_path).is_none() && !type_path.path.get_ident().is_some_and(integer_type) {
+        return syn_error(field_type, "keep_diff needs an integer field to retain the tag");
+    }
+    Ok(())
+}
+
+/// Report variants whose tags are all read as earlier variants, they could never be read. Only
+/// tags made of integer literals are checked, and variants with a guard don't hide later ones.
+fn check_shadowed_variants(data: &DataEnum, tag_type: &Ident, attributes: &Attributes) -> Result<()> {
+    let mut read: Vec<(i128, i128)> = Vec::new();
+    let mut names: Vec<(String, Vec<(i128, i128)>)> = Vec::new();
+    for variant in data.variants.iter() {
+        let variant_attributes = attributes.variant(variant)?;
+        if variant_attributes.skip {
+            continue;
+        }
+        let ranges = match variant_attributes.tag_pattern().and_then(|tag| literal_tag_ranges(&tag, tag_type)) {
+            Some(ranges) => ranges,
+            None => continue,
+        };
+        if ranges.iter().all(|range| covered(range, &read)) {
+            let first = names.iter().find(|(_, earlier)| ranges.iter().any(|r| earlier.iter().any(|e| e.0 <= r.1 && r.0 <= e.1)));
+            let message = match first {
+                Some((name, _)) => format!("The tags of {} are all read as earlier variants, like {}", variant.ident, name),
+                None => format!("The tags of {} are all read as earlier variants", variant.ident),
+            };
+            return syn_error(&variant.ident, &message);
+        }
+        if variant_attributes.tag_guard.is_none() {
+            read.extend(ranges.iter().copied());
+            names.push((variant.ident.to_string(), ranges));
+        }
+    }
+    Ok(())
+}
+
+/// Is the inclusive range `range` within the union of `ranges`
+fn covered(range: &(i128, i128), ranges: &[(i128, i128)]) -> bool {
+    let mut sorted = ranges.to_vec();
+    sorted.sort();
+    let mut next = range.0;
+    for (start, end) in sorted {
+        if start > next {
+            break;
+        }
+        if end >= next {
+            if end >= range.1 {
+                return true;
+            }
+            next = end + 1;
+        }
+    }
+    false
+}
+
+/// Inclusive ranges of the values of a tag pattern made of integer literals, `None` if it uses
+/// anything else, like a constant
+fn literal_tag_ranges(pattern: &Pat, tag_type: &Ident) -> Option<Vec<(i128, i128)>> {
+    let (min, max) = integer_bounds(tag_type)?;
+    match pattern {
+        Pat::Or(or) => {
+            let mut ranges = Vec::new();
+            for case in or.cases.iter() {
+                ranges.extend(literal_tag_ranges(case, tag_type)?);
+            }
+            Some(ranges)
+        }
+        Pat::Paren(paren) => literal_tag_ranges(&paren.pat, tag_type),
+        Pat::Wild(_) => Some(vec![(min, max)]),
+        Pat::Range(range) => {
+            let start = match &range.start {
+                Some(start) => literal_value(&quote! { #start })?,
+                None => min,
+            };
+            let end = match (&range.end, &range.limits) {
+                (Some(end), syn::RangeLimits::Closed(_)) => literal_value(&quote! { #end })?,
+                (Some(end), syn::RangeLimits::HalfOpen(_)) => literal_value(&quote! { #end })? - 1,
+                (None, _) => max,
+            };
+            (start <= end).then(|| vec![(start, end)])
+        }
+        Pat::Lit(_) => literal_value(&quote! { #pattern }).map(|value| vec![(value, value)]),
+        _ => None,
+    }
+}
+
+/// Value of an integer literal, possibly negative and with a type suffix
+fn literal_value(tokens: &TokenStream) -> Option<i128> {
+    let text = tokens.to_string().replace(' ', "");
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text.to_string()),
+        None => (false, text),
+    };
+    let value: i128 = syn::parse_str::<LitInt>(&text).ok()?.base10_parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Smallest and largest values of an integer type, as far as an i128 holds them
+fn integer_bounds(ty: &Ident) -> Option<(i128, i128)> {
+    if !integer_type(ty) {
+        return None;
+    }
+    let bits = primitive_size(ty).base10_parse::<u32>().ok()? * 8;
+    let signed = ty.to_string().starts_with('i');
+    Some(match bits {
+        128 if signed => (i128::MIN, i128::MAX),
+        128 => (0, i128::MAX),
+        bits if signed => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1),
+        bits => (0, (1 << bits) - 1),
+    })
+}
+
+/// Expression of the tag of a variant when the tag type is not a primitive, it is compared with
+/// the tag read with `==` so it must be a single value
+fn composite_tag(pattern: &Pat) -> Result<Expr> {
+    if matches!(pattern, Pat::Or(_) | Pat::Range(_) | Pat::Wild(_) | Pat::Rest(_)) {
+        return syn_error(pattern, "Tags of a non primitive tag_type must be a single value");
+    }
+    syn::parse2(quote! { #pattern })
+}
+
 /// generate code for all fields of a struct / enum variant
 fn generate_for_fields(
     fields: &Fields,
     field_prefix: Option<&TokenStream>,
     ident: &Ident,
+    type_name: &str,
     attributes: &Attributes,
 ) -> Result<(TokenStream, TokenStream, TokenStream, TokenStream)> {
     let mut size_code = TokenStream::new();
@@ -399,37 +1868,134 @@ fn generate_for_fields(
     let mut field_list = TokenStream::new();
     let mut context_val = quote! { ctx };
     let mut prefixed_context_val = quote! { ctx };
-    if let Some((ty, value)) = &attributes.magic {
-        let (from_method, to_method) = primitive_function(attributes.endianness);
+    if let Some((ty, values)) = &attributes.magic {
+        let (from_method, _) = primitive_function(attributes.endianness);
         if !primitive_type(ty) {
             return syn_error(ty, "magic only works with primitive types");
         }
         let ty_size = primitive_size(ty);
+        let text = quote!(#(#values)|*).to_string();
 
         // size code
         size_code.extend(quote! {
             #ty_size +
         });
+        let read_buffer = if attributes.endian_marker.is_some() {
+            // already read to find the endianness
+            quote! { let buffer = marker; }
+        } else {
+            quote! {
+                let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+            }
+        };
         read_code.extend(quote! {
-            let mut buffer: [u8; #ty_size] = [0; #ty_size];
-            from.read_exact(&mut buffer)?;
+            #read_buffer
             let magic = #ty::#from_method(buffer);
-            if magic != #value {
-                return Err(std::io::Error::other(format!("Magic value {} expected, found {}", #value, magic)));
+            if !matches!(magic, #(#values)|*) {
+                return Err(std::io::Error::other(format!("Magic value {} expected, found {}", #text, magic)));
             }
             _pos += #ty_size;
         });
+        if !attributes.keep_magic {
+            // the first value is written
+            let value = &values[0];
+            write_code.extend(magic_write(ty, &quote! { (#value as #ty) }, attributes));
+        }
+    }
+    if attributes.keep_magic {
+        let magic_ty = match &attributes.magic {
+            Some((ty, _)) => ty,
+            None => return syn_error(ident, "#[plod(keep_magic)] needs a #[plod(magic(...))]"),
+        };
+        if attributes.keep_tag {
+            return syn_error(ident, "Cannot keep both the tag and the magic in the first field");
+        }
+        match fields.iter().next() {
+            Some(field) if matches!(&field.ty, Type::Path(p) if p.path.is_ident(magic_ty)) => {}
+            Some(field) => return syn_error(&field.ty, "The first field must have the type of the kept magic"),
+            None => return syn_error(ident, "Cannot keep magic without a field"),
+        }
+    }
+    let all_attributes = fields_attributes(fields, attributes)?;
+    let kept_field = attributes.kept_field();
+    // fields_attributes checked that the holder is a named field
+    let preserved_holder = match (&attributes.preserve_unknown, field_prefix) {
+        (Some(holder), Some(prefix)) => {
+            read_code.extend(quote! { let mut preserved_unknown = plod::Preserved::default(); });
+            Some(quote! { #prefix #holder })
+        }
+        (Some(holder), None) => return syn_error(holder, "preserve_unknown only works with structs"),
+        (None, _) => None,
+    };
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).find(|(_, a)| a.is_tag && !attributes.keep_tag) {
+        return syn_error(field, "#[plod(is_tag)] is only supported on fields of enum variants");
+    }
+    if let Some((field, _)) = kept_field.and_then(|i| fields.iter().zip(&all_attributes).nth(i)).filter(|(_, a)| {
+        a.reserved > 0 || a.pad_to_offset.is_some() || a.len_of.is_some() || a.seek_to.is_some() || a.cfg.is_some()
+    }) {
+        return syn_error(field, "A kept tag or magic cannot be reserved, padded, conditional, a len_of or a seek_to field");
+    }
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).rev().skip(1).find(|(_, a)| a.size_after) {
+        return syn_error(field, "#[plod(size_after)] must be on the last field");
+    }
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).find(|(_, a)| a.size_after && attributes.trailer_magic.is_some()) {
+        return syn_error(field, "#[plod(size_after)] cannot be followed by a trailer_magic");
+    }
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).rev().skip(1).find(|(_, a)| a.rest) {
+        return syn_error(field, "#[plod(rest)] must be on the last field");
+    }
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).find(|(_, a)| a.rest && attributes.trailer_magic.is_some()) {
+        return syn_error(field, "#[plod(rest)] cannot be followed by a trailer_magic");
+    }
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).find(|(_, a)| {
+        (a.tag_for.is_some() || a.external_tag) && (a.cfg.is_some() || a.skip || a.seek_to.is_some() || a.as_type.is_some())
+    }) {
+        return syn_error(field, "A tag_for or external_tag field cannot be conditional, skipped, a seek_to or an as field");
+    }
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).find(|(_, a)| a.fill.is_some() && a.pad_to_offset.is_none()) {
+        return syn_error(field, "#[plod(fill)] needs a #[plod(pad_to_offset)]");
+    }
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).find(|(_, a)| a.checksum.is_some() && (a.len_of.is_some() || a.seek_to.is_some() || a.skip)) {
+        return syn_error(field, "A checksum field cannot be skipped, a len_of or a seek_to field");
+    }
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).find(|(_, a)| {
+        a.keep_raw.is_some()
+            && (a.skip || a.common || a.len_of.is_some() || a.tag_for.is_some() || a.external_tag || a.seek_to.is_some())
+    }) {
+        return syn_error(field, "keep_raw is not supported on skipped, common, len_of, tag_for, external_tag or seek_to fields");
+    }
+    if let Some((field, _)) = kept_field.and_then(|i| fields.iter().zip(&all_attributes).nth(i)).filter(|(_, a)| a.keep_raw.is_some()) {
+        return syn_error(field, "keep_raw is not supported on a kept tag or magic");
+    }
+    if all_attributes.iter().any(|a| a.checksum.is_some()) {
+        // the bytes of the fields are recorded to compute checksums
+        read_code.extend(quote! {
+            #[allow(unused_imports)]
+            use std::io::{Read as _, Seek as _};
+            let mut checksum_recorder = plod::checksum::Recorder::new(&mut *from);
+            let from = &mut checksum_recorder;
+        });
         write_code.extend(quote! {
-            let buffer: [u8; #ty_size] = (#value as #ty).#to_method();
-            to.write_all(&buffer)?;
-            _pos += #ty_size;
+            #[allow(unused_imports)]
+            use std::io::{Seek as _, Write as _};
+            let mut checksum_recorder = plod::checksum::Recorder::new(&mut *to);
+            let to = &mut checksum_recorder;
         });
     }
+    // the kept tag has been read by enum_impl before anything else, so it is written first
+    let mut tag_write = TokenStream::new();
+    let optional = all_attributes.iter().position(|a| a.if_remaining).unwrap_or(all_attributes.len());
+    if let Some((field, _)) = fields.iter().zip(&all_attributes).skip(optional).find(|(_, a)| !a.if_remaining && !a.skip) {
+        return syn_error(field, "Only #[plod(if_remaining)] fields can follow an #[plod(if_remaining)] field");
+    }
+    if optional < all_attributes.len() {
+        // set by the first None, after which nothing can be written
+        write_code.extend(quote! { let mut _remaining_none = false; });
+    }
     match fields {
         Fields::Named(fields) => {
-            let mut i = 0;
-            for field in fields.named.iter() {
-                let field_attributes = attributes.extend(&field.attrs)?;
+            for (i, field) in fields.named.iter().enumerate() {
+                let field_attributes = &all_attributes[i];
                 // all named fields have an ident
                 let field_ident = field.ident.as_ref().unwrap();
                 let (prefixed_field_ref, prefixed_field_dotted) = match field_prefix {
@@ -439,34 +2005,198 @@ fn generate_for_fields(
                         quote! {  #prefix #field_ident . },
                     ),
                 };
-                generate_for_item(
-                    &field_ident,
-                    &field.ty,
-                    &prefixed_field_ref,
-                    &prefixed_field_dotted,
-                    // TODO field_attributes keep tag ?
-                    i == 0 && attributes.keep_tag,
-                    &field_attributes,
-                    &mut size_code,
+                let mut field_size = TokenStream::new();
+                let mut field_read = TokenStream::new();
+                let mut field_write = TokenStream::new();
+                let mut raw_read = TokenStream::new();
+                if field_attributes.common {
+                    if kept_field == Some(i) {
+                        return syn_error(field, "A common field cannot be a kept tag or magic");
+                    }
+                    // read and written by enum_impl before the tag, only its size is counted here
+                    generate_for_item(
+                        field_ident,
+                        &field.ty,
+                        &prefixed_field_ref,
+                        &prefixed_field_dotted,
+                        false,
+                        field_attributes,
+                        &mut size_code,
+                        &mut TokenStream::new(),
+                        &mut TokenStream::new(),
+                        &context_val,
+                        &prefixed_context_val,
+                    )?;
+                    field_list.extend(quote! { #field_ident, });
+                    continue;
+                }
+                if field_attributes.raw_of {
+                    // read with the field it keeps the bytes of, never written
+                    field_list.extend(quote! { #field_ident, });
+                    continue;
+                }
+                if field_attributes.pad_to_offset.is_some() && (field_prefix.is_none() || attributes.length_prefix.is_some()) {
+                    return syn_error(field, "pad_to_offset is only supported in structs that are not length_prefixed");
+                }
+                if field_attributes.pad_to_offset.is_some() && field_attributes.cfg.is_some() {
+                    return syn_error(field, "pad_to_offset is not supported on #[cfg] fields");
+                }
+                // the padding depends on the size of all previous fields
+                generate_for_pad_to_offset(field_attributes, &mut size_code, &mut field_read, &mut field_write);
+                let name = field_ident.to_string();
+                generate_for_reserved(
+                    field_attributes,
+                    preserved_holder.as_ref().map(|holder| (holder, name.as_str())),
+                    &mut field_size,
+                    &mut field_read,
+                    &mut field_write,
+                );
+                if let Some(target) = &field_attributes.len_of {
+                    // fields_attributes checked that the target exists
+                    let j = fields.named.iter().position(|f| f.ident.as_ref() == Some(target)).unwrap();
+                    let target_dotted = match field_prefix {
+                        None => quote! { #target . },
+                        Some(prefix) => quote! { #prefix #target . },
+                    };
+                    let length = collection_size(
+                        &fields.named[j].ty,
+                        &target_dotted,
+                        &all_attributes[j],
+                        &context_val,
+                        &prefixed_context_val,
+                    )?;
+                    generate_for_length(
+                        field_ident,
+                        &field.ty,
+                        &length,
+                        field_attributes,
+                        &mut field_size,
+                        &mut field_read,
+                        &mut field_write,
+                    )?;
+                } else if let Some(target) = &field_attributes.tag_for {
+                    // fields_attributes checked that the target exists
+                    let j = fields.named.iter().position(|f| f.ident.as_ref() == Some(target)).unwrap();
+                    let target_ref = match field_prefix {
+                        None => quote! { #target },
+                        Some(prefix) => quote! { (& #prefix #target) },
+                    };
+                    generate_for_tag(
+                        field_ident,
+                        &field.ty,
+                        &fields.named[j].ty,
+                        &target_ref,
+                        field_attributes,
+                        &mut field_size,
+                        &mut field_read,
+                        &mut field_write,
+                        &prefixed_context_val,
+                    )?;
+                } else if let Some(source) = &field_attributes.tag_source {
+                    generate_for_external_tag(
+                        field_ident,
+                        &field.ty,
+                        source,
+                        &prefixed_field_ref,
+                        field_attributes,
+                        &mut field_size,
+                        &mut field_read,
+                        &mut field_write,
+                        &context_val,
+                        &prefixed_context_val,
+                    )?;
+                } else if let Some(offset) = &field_attributes.seek_to {
+                    let previous = fields.named.iter().take(i).map(|f| {
+                        let ident = f.ident.as_ref().unwrap();
+                        match field_prefix {
+                            None => (ident.clone(), quote! { #ident }),
+                            Some(prefix) => (ident.clone(), quote! { (& #prefix #ident) }),
+                        }
+                    });
+                    generate_for_seek_to(
+                        offset,
+                        &previous.collect::<Vec<_>>(),
+                        field_ident,
+                        &field.ty,
+                        &prefixed_field_ref,
+                        &prefixed_field_dotted,
+                        field_attributes,
+                        &mut field_read,
+                        &mut field_write,
+                        &context_val,
+                        &prefixed_context_val,
+                    )?;
+                } else if i == 0 && attributes.keep_magic {
+                    generate_for_kept_magic(field_ident, &prefixed_field_ref, attributes, &mut field_read, &mut field_write);
+                } else {
+                    let mut item_read = TokenStream::new();
+                    generate_for_item(
+                        &field_ident,
+                        &field.ty,
+                        &prefixed_field_ref,
+                        &prefixed_field_dotted,
+                        attributes.keep_tag && i == attributes.tag_field,
+                        field_attributes,
+                        &mut field_size,
+                        &mut item_read,
+                        &mut field_write,
+                        &context_val,
+                        &prefixed_context_val,
+                    )?;
+                    if let Some(raw) = &field_attributes.keep_raw {
+                        // the bytes are recorded while the item is read
+                        let raw_bytes = Ident::new(&format!("raw_bytes_{}", i), Span::call_site());
+                        read_code.extend(quote! { let mut #raw_bytes = Vec::new(); });
+                        item_read = quote! {
+                            let mut raw_recorder = plod::checksum::Recorder::new(&mut *from);
+                            let #field_ident = {
+                                let from = &mut raw_recorder;
+                                #item_read
+                                #field_ident
+                            };
+                            #raw_bytes = raw_recorder.recorded().to_vec();
+                        };
+                        raw_read.extend(quote! { let #raw = std::convert::From::from(#raw_bytes); });
+                    }
+                    field_read.extend(item_read);
+                }
+                if field_attributes.cfg.is_some() {
+                    let field_size = field_attributes.cfg_value(quote! { #field_size 0 }, quote! { 0 });
+                    size_code.extend(quote! { #field_size + });
+                } else {
+                    size_code.extend(field_size);
+                }
+                in_field(
+                    field_ident,
+                    type_name,
+                    &field_ident.to_string(),
+                    field_attributes,
+                    field_read,
+                    field_write,
                     &mut read_code,
-                    &mut write_code,
-                    &context_val,
-                    &prefixed_context_val,
-                )?;
-                if field_attributes.is_context {
+                    if attributes.keep_tag && i == attributes.tag_field { &mut tag_write } else { &mut write_code },
+                );
+                read_code.extend(raw_read);
+                let plain = field_attributes.len_of.is_none()
+                    && field_attributes.seek_to.is_none()
+                    && !(i == 0 && attributes.keep_magic);
+                if field_attributes.is_context && plain {
                     context_val = quote! { (&#field_ident) };
                     prefixed_context_val = prefixed_field_ref;
                 }
+                let cfg = field_attributes.cfg_attr();
                 field_list.extend(quote! {
-                    #field_ident,
+                    #cfg #field_ident,
                 });
-                i += 1;
+            }
+            if let Some(holder) = &attributes.preserve_unknown {
+                read_code.extend(quote! { let #holder = preserved_unknown; });
             }
             field_list = quote! { { #field_list } };
         }
         Fields::Unnamed(fields) => {
             for (i, field) in fields.unnamed.iter().enumerate() {
-                let field_attributes = attributes.extend(&field.attrs)?;
+                let field_attributes = &all_attributes[i];
                 let field_ident = Ident::new(&format!("field_{}", i), field.span());
                 let (prefixed_field_ref, prefixed_field_dotted) = match field_prefix {
                     None => (quote! { #field_ident }, quote! { #field_ident .}),
@@ -475,20 +2205,46 @@ fn generate_for_fields(
                         (quote! {  ( & #prefix #i ) }, quote! {  #prefix #i . })
                     }
                 };
-                generate_for_item(
+                if field_attributes.seek_to.is_some() {
+                    return syn_error(field, "seek_to is only supported on named fields");
+                }
+                if field_attributes.cfg.is_some() {
+                    return syn_error(field, "#[cfg] is only supported on named fields");
+                }
+                if field_attributes.pad_to_offset.is_some() {
+                    return syn_error(field, "pad_to_offset is only supported on named fields");
+                }
+                let mut field_read = TokenStream::new();
+                let mut field_write = TokenStream::new();
+                generate_for_reserved(field_attributes, None, &mut size_code, &mut field_read, &mut field_write);
+                if i == 0 && attributes.keep_magic {
+                    generate_for_kept_magic(&field_ident, &prefixed_field_ref, attributes, &mut field_read, &mut field_write);
+                } else {
+                    generate_for_item(
+                        &field_ident,
+                        &field.ty,
+                        &prefixed_field_ref,
+                        &prefixed_field_dotted,
+                        attributes.keep_tag && i == attributes.tag_field,
+                        field_attributes,
+                        &mut size_code,
+                        &mut field_read,
+                        &mut field_write,
+                        &context_val,
+                        &prefixed_context_val,
+                    )?;
+                }
+                in_field(
                     &field_ident,
-                    &field.ty,
-                    &prefixed_field_ref,
-                    &prefixed_field_dotted,
-                    i == 0 && attributes.keep_tag,
-                    &field_attributes,
-                    &mut size_code,
+                    type_name,
+                    &i.to_string(),
+                    field_attributes,
+                    field_read,
+                    field_write,
                     &mut read_code,
-                    &mut write_code,
-                    &context_val,
-                    &prefixed_context_val,
-                )?;
-                if field_attributes.is_context {
+                    if attributes.keep_tag && i == attributes.tag_field { &mut tag_write } else { &mut write_code },
+                );
+                if field_attributes.is_context && !(i == 0 && attributes.keep_magic) {
                     context_val = quote! { (&#field_ident) };
                     prefixed_context_val = quote! { #prefixed_field_ref };
                 }
@@ -505,12 +2261,43 @@ fn generate_for_fields(
             }
         }
     };
+    write_code = quote! { #tag_write #write_code };
+    if let Some((ty, values)) = &attributes.trailer_magic {
+        if !primitive_type(ty) {
+            return syn_error(ty, "trailer_magic only works with primitive types");
+        }
+        let (from_method, _) = primitive_function(attributes.endianness);
+        let ty_size = primitive_size(ty);
+        let text = quote!(#(#values)|*).to_string();
+        let value = &values[0];
+        size_code.extend(quote! {
+            #ty_size +
+        });
+        read_code.extend(quote! {
+            let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+            let trailer = #ty::#from_method(buffer);
+            if !matches!(trailer, #(#values)|*) {
+                return Err(std::io::Error::other(format!("Trailer magic value {} expected, found {}", #text, trailer)));
+            }
+            _pos += #ty_size;
+        });
+        write_code.extend(magic_write(ty, &quote! { (#value as #ty) }, attributes));
+    }
     // final part of size for the tag
     if attributes.keep_tag {
         size_code.extend(quote! { 0 });
     } else {
         match &attributes.tag_type {
+            None if !attributes.tag_fields.is_empty() => {
+                let size = attributes.tag_fields_size();
+                size_code.extend(quote! { #size });
+            }
             None => size_code.extend(quote! { 0 }),
+            Some(ty) if !primitive_type(ty) => {
+                // checked by enum_impl, a variant without keep_tag has a tag
+                let tag = composite_tag(attributes.tag.as_ref().unwrap())?;
+                size_code.extend(quote! { <#ty as plod::Plod>::size_at_rest(&#tag) });
+            }
             Some(ty) => {
                 let ty_size = primitive_size(ty);
                 size_code.extend(quote! { #ty_size });
@@ -520,13 +2307,124 @@ fn generate_for_fields(
     Ok((size_code, read_code, write_code, field_list))
 }
 
-/// Generate code for a single item of a variant or a struct
-fn generate_for_item(
+/// Add the read and write code of a field, with errors wrapped to tell which field failed
+#[allow(clippy::too_many_arguments)]
+fn in_field(
+    field_ident: &Ident,
+    type_name: &str,
+    field_name: &str,
+    attributes: &Attributes,
+    field_read: TokenStream,
+    field_write: TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) {
+    let cfg = attributes.cfg_attr();
+    if attributes.skip {
+        // nothing can fail
+        if attributes.cfg.is_some() {
+            read_code.extend(quote! {
+                #cfg
+                let #field_ident = {
+                    #field_read
+                    #field_ident
+                };
+            });
+            write_code.extend(quote! { #cfg { #field_write } });
+        } else {
+            read_code.extend(field_read);
+            write_code.extend(field_write);
+        }
+        return;
+    }
+    read_code.extend(quote! {
+        #cfg
+        let #field_ident = (|| -> plod::Result<_> {
+            #field_read
+            Ok(#field_ident)
+        })()
+        .map_err(|e| plod::error::reading(e, #type_name, #field_name))?;
+    });
+    write_code.extend(quote! {
+        #cfg
+        (|| -> plod::Result<()> {
+            #field_write
+            Ok(())
+        })()
+        .map_err(|e| plod::error::writing(e, #type_name, #field_name))?;
+    });
+}
+
+/// Generate code for the fill bytes before a field starting at an absolute offset, the size at
+/// rest is computed as if the value started at offset 0
+fn generate_for_pad_to_offset(
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) {
+    let offset = match &attributes.pad_to_offset {
+        Some(offset) => offset,
+        None => return,
+    };
+    let fill = attributes.fill.unwrap_or(0);
+    let observer = observer();
+    *size_code = quote! {
+        std::cmp::max(#size_code 0, (#offset) as usize) +
+    };
+    read_code.extend(quote! {
+        _pos += plod::observer::read_padding_to(from, _pos, (#offset) as usize, #fill, #observer)?;
+    });
+    write_code.extend(quote! {
+        _pos += plod::helpers::write_padding_to(to, _pos, (#offset) as usize, #fill)?;
+    });
+}
+
+/// Generate code for the reserved bytes before a field
+fn generate_for_reserved(
+    attributes: &Attributes,
+    preserved: Option<(&TokenStream, &str)>,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) {
+    let reserved = attributes.reserved;
+    if reserved == 0 {
+        return;
+    }
+    let observer = observer();
+    size_code.extend(quote! {
+        #reserved +
+    });
+    read_code.extend(quote! {
+        let buffer: [u8; #reserved] = plod::helpers::read_bytes(from)?;
+        plod::observer::check_reserved(#observer, _pos, &buffer);
+        _pos += #reserved;
+    });
+    match preserved {
+        // the bytes read are written back
+        Some((holder, name)) => {
+            read_code.extend(quote! { preserved_unknown.keep_reserved(#name, &buffer); });
+            write_code.extend(quote! {
+                to.write_all(&#holder.reserved_bytes::<#reserved>(#name))?;
+                _pos += #reserved;
+            });
+        }
+        None => write_code.extend(quote! {
+            to.write_all(&[0_u8; #reserved])?;
+            _pos += #reserved;
+        }),
+    }
+}
+
+/// Generate code for a last `Vec` whose size is stored after its items: the remaining bytes are
+/// read at once, the size is at their end and the items before it
+#[allow(clippy::too_many_arguments)]
+fn generate_for_size_after(
     field_ident: &Ident,
     field_type: &Type,
     prefixed_field_ref: &TokenStream,
     prefixed_field_dotted: &TokenStream,
-    is_tag: bool,
     attributes: &Attributes,
     size_code: &mut TokenStream,
     read_code: &mut TokenStream,
@@ -534,45 +2432,648 @@ fn generate_for_item(
     context_val: &TokenStream,
     prefixed_context_val: &TokenStream,
 ) -> Result<()> {
-    if attributes.skip {
-        // no size code, no write code
-        // default on read
-        read_code.extend(quote! {
-            let #field_ident = <#field_type as std::default::Default>::default();
-        });
-        return Ok(());
+    let size_ty = match (&resolve_std_types(field_type), &attributes.size_type) {
+        (Type::Path(p), Some(size_ty)) if p.path.segments.first().is_some_and(|s| s.ident == "Vec") => size_ty,
+        _ => return syn_error(field_type, "#[plod(size_after)] only works with a Vec with a size_type"),
+    };
+    if !unsigned_type(size_ty) || attributes.columnar {
+        return syn_error(field_type, "#[plod(size_after)] needs an unsigned size_type, without columnar");
     }
-    match field_type {
-        Type::Path(type_path) => {
-            let mut is_vec = false;
-            let mut is_primitive = false;
-            if let Some(id) = type_path.path.segments.first() {
-                is_vec = id.ident == "Vec";
-                // TODO we should probably make sure there is only one segment
-                is_primitive = primitive_type(&id.ident);
-            };
-            if is_vec {
-                generate_for_vec(
-                    type_path,
-                    field_ident,
-                    prefixed_field_dotted,
-                    attributes,
-                    size_code,
-                    read_code,
-                    write_code,
-                    context_val,
-                    prefixed_context_val,
-                )?;
-            } else if is_primitive {
-                let ty = type_path.path.get_ident().unwrap();
-                let ty_size = primitive_size(ty);
-                let (from_method, to_method) = primitive_function(attributes.endianness);
-                size_code.extend(quote! {
-                    #ty_size +
-                });
-                if is_tag {
-                    // TODO, tag should always be read/written by enum_impl, this would be easier
-                    if let Some(diff) = &attributes.keep_diff {
+    let ty_size = primitive_size(size_ty);
+    let (from_method, to_method) = primitive_function(attributes.endianness);
+    // the items are those of a Vec whose size has already been read
+    let count = Ident::new("size_after_count", Span::call_site());
+    let mut inner = attributes.clone();
+    inner.size_after = false;
+    inner.length_field = Some(count.clone());
+    let mut items_size = TokenStream::new();
+    let mut items_read = TokenStream::new();
+    let mut items_write = TokenStream::new();
+    generate_for_item(
+        field_ident,
+        field_type,
+        prefixed_field_ref,
+        prefixed_field_dotted,
+        false,
+        &inner,
+        &mut items_size,
+        &mut items_read,
+        &mut items_write,
+        context_val,
+        prefixed_context_val,
+    )?;
+    let size = if attributes.byte_sized {
+        quote! { #items_size 0 }
+    } else {
+        quote! { #prefixed_field_dotted len() }
+    };
+    let stored = stored_size_as(stored_size(quote! { size }, attributes), size_ty);
+    size_code.extend(quote! { #items_size #ty_size + });
+    read_code.extend(quote! {
+        let size_after_bytes = plod::fuzz::read_to_end(&mut *from)?;
+        if size_after_bytes.len() < #ty_size {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        }
+        let (size_after_items, size_after_size) = size_after_bytes.split_at(size_after_bytes.len() - #ty_size);
+        let mut size_after_array: [u8; #ty_size] = [0; #ty_size];
+        size_after_array.copy_from_slice(size_after_size);
+        let #count = #size_ty::#from_method(size_after_array);
+        let size_after_len = size_after_items.len();
+        let mut size_after_reader = std::io::Cursor::new(size_after_items);
+        let #field_ident = {
+            // methods of a concrete reader need the trait in scope
+            #[allow(unused_imports)]
+            use std::io::Read as _;
+            let from = &mut size_after_reader;
+            #items_read
+            #field_ident
+        };
+        if size_after_reader.position() as usize != size_after_len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{} bytes between the items and their size",
+                    size_after_len - size_after_reader.position() as usize
+                ),
+            ));
+        }
+        _pos += #ty_size;
+    });
+    write_code.extend(quote! {
+        #items_write
+        let size = #size;
+        let buffer: [u8; #ty_size] = #stored.#to_method();
+        to.write_all(&buffer)?;
+        _pos += #ty_size;
+    });
+    Ok(())
+}
+
+/// Generate code for a `Vec<bool>` packed 8 items per byte, the first item in the lowest bit
+/// unless `bit_order` is `msb_first`
+fn generate_for_bitmap(
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_dotted: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) -> Result<()> {
+    if !is_vec_bool(&resolve_std_types(field_type)) {
+        return syn_error(field_type, "#[plod(bitmap)] only works with Vec<bool>");
+    }
+    let size_ty = match &attributes.size_type {
+        Some(size_ty) if unsigned_type(size_ty) => size_ty,
+        _ => return syn_error(field_type, "#[plod(bitmap)] needs an unsigned size_type, eg: #[plod(size_type(u16))]"),
+    };
+    if attributes.columnar || attributes.item_align.is_some() {
+        return syn_error(field_type, "#[plod(bitmap)] cannot be columnar or have an item_align");
+    }
+    // the size is stored in a previous field instead of prefixing the bytes
+    let ty_size = match attributes.length_field {
+        Some(_) => LitInt::new("0", Span::call_site()),
+        None => primitive_size(size_ty),
+    };
+    let (from_method, to_method) = primitive_function(attributes.endianness);
+    size_code.extend(quote! {
+        #ty_size + #prefixed_field_dotted len().div_ceil(8) +
+    });
+    let stored = match &attributes.length_field {
+        Some(length_field) => quote! { #length_field as usize },
+        None => quote! { #size_ty::#from_method(buffer) as usize },
+    };
+    let size = actual_size(stored, attributes);
+    let (pack, unpack) = if attributes.msb_first {
+        (quote! { pack_bits_msb_first }, quote! { unpack_bits_msb_first })
+    } else {
+        (quote! { pack_bits }, quote! { unpack_bits })
+    };
+    // byte_sized bitmaps hold all the bits of their bytes
+    let (bits, bytes) = if attributes.byte_sized {
+        (quote! { size.saturating_mul(8) }, quote! { size })
+    } else {
+        (quote! { size }, quote! { size.div_ceil(8) })
+    };
+    if attributes.length_field.is_none() {
+        read_code.extend(quote! {
+            let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+            _pos += #ty_size;
+        });
+    }
+    read_code.extend(quote! {
+        let size = #size;
+        plod::fuzz::check_len(size)?;
+        let mut packed = vec![0_u8; #bytes];
+        from.read_exact(&mut packed)?;
+        _pos += packed.len();
+        let #field_ident = plod::helpers::#unpack(&packed, #bits);
+    });
+    write_code.extend(quote! {
+        let packed = plod::helpers::#pack(#prefixed_field_dotted as_slice());
+    });
+    if attributes.length_field.is_none() {
+        let size = if attributes.byte_sized {
+            quote! { packed.len() }
+        } else {
+            quote! { #prefixed_field_dotted len() }
+        };
+        let stored = stored_size_as(stored_size(quote! { size }, attributes), size_ty);
+        write_code.extend(quote! {
+            let size = #size;
+            let buffer: [u8; #ty_size] = #stored.#to_method();
+            to.write_all(&buffer)?;
+            _pos += #ty_size;
+        });
+    }
+    write_code.extend(quote! {
+        to.write_all(&packed)?;
+        _pos += packed.len();
+    });
+    Ok(())
+}
+
+/// Generate code for an unsigned integer stored as a varint, see `plod::varint`
+fn generate_for_varint(
+    ty: &Ident,
+    field_ident: &Ident,
+    prefixed_field_ref: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) -> Result<()> {
+    if !unsigned_type(ty) || ty == "u128" {
+        return syn_error(ty, "#[plod(varint)] only works with unsigned integers up to u64");
+    }
+    let varint = match &attributes.varint {
+        Some(flavor) => varint_flavor(flavor),
+        None => return syn_error(ty, "#[plod(varint)] needs a flavor"),
+    };
+    size_code.extend(quote! {
+        #varint.size(*#prefixed_field_ref as u64) +
+    });
+    read_code.extend(quote! {
+        let (value, size) = #varint.read(from)?;
+        let #field_ident = match #ty::try_from(value) {
+            Ok(value) => value,
+            Err(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Varint {} does not fit in {}", value, stringify!(#ty)),
+                ))
+            }
+        };
+        _pos += size;
+    });
+    write_code.extend(quote! {
+        _pos += #varint.write(to, *#prefixed_field_ref as u64)?;
+    });
+    Ok(())
+}
+
+/// Identifiers used in a token stream
+fn used_idents(tokens: TokenStream, idents: &mut Vec<Ident>) {
+    for token in tokens {
+        match token {
+            proc_macro2::TokenTree::Ident(ident) => idents.push(ident),
+            proc_macro2::TokenTree::Group(group) => used_idents(group.stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+/// Generate code for an item stored at an absolute offset, it is not part of the size at rest
+fn generate_for_seek_to(
+    offset: &syn::Expr,
+    previous: &[(Ident, TokenStream)],
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    prefixed_field_dotted: &TokenStream,
+    attributes: &Attributes,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+) -> Result<()> {
+    if !attributes.seek {
+        read_code.extend(quote! {
+            let #field_ident: #field_type = plod::not_seekable()?;
+        });
+        write_code.extend(quote! {
+            plod::not_seekable::<()>()?;
+        });
+        return Ok(());
+    }
+    let mut inner = attributes.clone();
+    inner.seek_to = None;
+    let mut item_read_code = TokenStream::new();
+    let mut item_write_code = TokenStream::new();
+    generate_for_item(
+        field_ident,
+        field_type,
+        prefixed_field_ref,
+        prefixed_field_dotted,
+        false,
+        &inner,
+        &mut TokenStream::new(),
+        &mut item_read_code,
+        &mut item_write_code,
+        context_val,
+        prefixed_context_val,
+    )?;
+    // on write, previous fields used by the offset expression are bound by value as on read
+    let mut idents = Vec::new();
+    used_idents(quote! { #offset }, &mut idents);
+    let bindings = previous
+        .iter()
+        .filter(|(ident, _)| idents.contains(ident))
+        .map(|(ident, field_ref)| quote! { let #ident = #field_ref.clone(); });
+    read_code.extend(quote! {
+        let #field_ident = {
+            let seek_target = (#offset) as u64;
+            let seek_back = std::io::Seek::stream_position(from)?;
+            let pos_back = _pos;
+            std::io::Seek::seek(from, std::io::SeekFrom::Start(seek_target))?;
+            _pos = seek_target as usize;
+            #item_read_code
+            std::io::Seek::seek(from, std::io::SeekFrom::Start(seek_back))?;
+            _pos = pos_back;
+            #field_ident
+        };
+    });
+    write_code.extend(quote! {
+        {
+            #(#bindings)*
+            let seek_target = (#offset) as u64;
+            let seek_back = std::io::Seek::stream_position(to)?;
+            let pos_back = _pos;
+            std::io::Seek::seek(to, std::io::SeekFrom::Start(seek_target))?;
+            _pos = seek_target as usize;
+            #item_write_code
+            std::io::Seek::seek(to, std::io::SeekFrom::Start(seek_back))?;
+            _pos = pos_back;
+        }
+    });
+    Ok(())
+}
+
+/// Write code of a magic value
+fn magic_write(ty: &Ident, value: &TokenStream, attributes: &Attributes) -> TokenStream {
+    let ty_size = primitive_size(ty);
+    let (_, to_method) = primitive_function(attributes.endianness);
+    quote! {
+        let buffer: [u8; #ty_size] = #value.#to_method();
+        to.write_all(&buffer)?;
+        _pos += #ty_size;
+    }
+}
+
+/// Generate code for the first field retaining the magic, it has already been read and its size counted
+fn generate_for_kept_magic(
+    field_ident: &Ident,
+    prefixed_field_ref: &TokenStream,
+    attributes: &Attributes,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) {
+    // keep_magic has been checked by generate_for_fields
+    let (ty, values) = attributes.magic.as_ref().unwrap();
+    let text = quote!(#(#values)|*).to_string();
+    read_code.extend(quote! {
+        let #field_ident = magic;
+    });
+    write_code.extend(quote! {
+        let magic: #ty = *#prefixed_field_ref;
+        if !matches!(magic, #(#values)|*) {
+            return Err(std::io::Error::other(format!("Magic value {} expected, found {}", #text, magic)));
+        }
+    });
+    write_code.extend(magic_write(ty, &quote! { magic }, attributes));
+}
+
+/// Generate code for a single item of a variant or a struct
+fn generate_for_item(
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    prefixed_field_dotted: &TokenStream,
+    is_tag: bool,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+) -> Result<()> {
+    if attributes.record_offset {
+        if !matches!(field_type, Type::Path(p) if p.path.is_ident("usize")) {
+            return syn_error(field_type, "A record_offset field must be a usize");
+        }
+        // no size code, no write code
+        read_code.extend(quote! {
+            let #field_ident: usize = _pos;
+        });
+        return Ok(());
+    }
+    if attributes.skip {
+        // no size code, no write code
+        // default on read
+        read_code.extend(quote! {
+            let #field_ident = <#field_type as std::default::Default>::default();
+        });
+        return Ok(());
+    }
+    if let Some(algorithm) = &attributes.checksum {
+        // the value is computed from the bytes recorded since the first field
+        let ty = match field_type {
+            Type::Path(p) if p.path.get_ident().is_some_and(|i| primitive_type(i) && i != "f32" && i != "f64") => {
+                p.path.get_ident().unwrap()
+            }
+            _ => return syn_error(field_type, "A checksum field must be an integer"),
+        };
+        if is_tag || attributes.as_type.is_some() {
+            return syn_error(field_type, "A checksum field cannot be a kept tag or use `as`");
+        }
+        let mut attributes = attributes.clone();
+        attributes.checksum = None;
+        let computed = |recorder: TokenStream| {
+            quote! {
+                {
+                    use plod::checksum::*;
+                    Checksum::checksum(&(#algorithm), #recorder.recorded()) as #ty
+                }
+            }
+        };
+        let (read_computed, write_computed) = (computed(quote! { from }), computed(quote! { to }));
+        let mut item_read = TokenStream::new();
+        let mut item_write = TokenStream::new();
+        generate_for_item(
+            field_ident,
+            field_type,
+            &quote! { (&computed_checksum) },
+            &quote! { computed_checksum. },
+            false,
+            &attributes,
+            size_code,
+            &mut item_read,
+            &mut item_write,
+            context_val,
+            prefixed_context_val,
+        )?;
+        read_code.extend(quote! {
+            let computed_checksum = #read_computed;
+            #item_read
+            if #field_ident != computed_checksum {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Checksum {:#x} expected, found {:#x}", computed_checksum, #field_ident),
+                ));
+            }
+        });
+        write_code.extend(quote! {
+            let computed_checksum = #write_computed;
+            #item_write
+        });
+        return Ok(());
+    }
+    if is_tag && !attributes.tag_fields.is_empty() {
+        // the tuple of tag fields has already been read by enum_impl
+        let arity = match field_type {
+            Type::Tuple(t) => t.elems.len(),
+            _ => 0,
+        };
+        if arity != attributes.tag_fields.len() || attributes.keep_diff.is_some() {
+            return syn_error(field_type, "A kept tag of tag_fields is a tuple of their types, without keep_diff");
+        }
+        let (_, to_method) = primitive_function(attributes.tag_endianness.unwrap_or(attributes.endianness));
+        let size = attributes.tag_fields_size();
+        size_code.extend(quote! { #size + });
+        read_code.extend(quote! {
+            let #field_ident = discriminant;
+        });
+        for (i, (_, ty)) in attributes.tag_fields.iter().enumerate() {
+            let index = syn::Index::from(i);
+            write_code.extend(quote! {
+                to.write_all(&(#prefixed_field_ref.#index as #ty).#to_method())?;
+            });
+        }
+        write_code.extend(quote! { _pos += #size; });
+        return Ok(());
+    }
+    if attributes.flatten {
+        let is_struct = match &resolve_std_types(field_type) {
+            Type::Path(type_path) => {
+                let ident = &type_path.path.segments.last().unwrap().ident;
+                !primitive_type(ident) && !["Vec", "Option", "Box"].iter().any(|n| ident == n)
+            }
+            _ => false,
+        };
+        if !is_struct || attributes.as_type.is_some() || attributes.rest || attributes.encoding.is_some() {
+            return syn_error(field_type, "#[plod(flatten)] only works with a plain type implementing Plod");
+        }
+    }
+    if let Some(as_type) = &attributes.as_type {
+        // the value is borrowed as the other type to be written, and converted from it once read
+        let mut attributes = attributes.clone();
+        attributes.as_type = None;
+        let borrowed = quote! { <#field_type as std::borrow::Borrow<#as_type>>::borrow(#prefixed_field_ref) };
+        generate_for_item(
+            field_ident,
+            as_type,
+            &quote! { (#borrowed) },
+            &quote! { (#borrowed). },
+            is_tag,
+            &attributes,
+            size_code,
+            read_code,
+            write_code,
+            context_val,
+            prefixed_context_val,
+        )?;
+        read_code.extend(quote! {
+            let #field_ident = <#field_type as std::convert::TryFrom<#as_type>>::try_from(#field_ident)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        });
+        return Ok(());
+    }
+    let field_type = &resolve_std_types(field_type);
+    if is_tag {
+        check_kept_tag(field_type, attributes)?;
+    }
+    if attributes.if_remaining {
+        return generate_for_remaining(
+            field_ident,
+            field_type,
+            prefixed_field_ref,
+            attributes,
+            size_code,
+            read_code,
+            write_code,
+            context_val,
+            prefixed_context_val,
+        );
+    }
+    if let Some(expected) = &attributes.equals {
+        return generate_for_equals(field_ident, field_type, expected, size_code, read_code, write_code);
+    }
+    if attributes.bstr && bytes_or_text(field_type) != Some(true) {
+        return syn_error(field_type, "#[plod(bstr)] only works with String and Cow<str>");
+    }
+    if attributes.rest {
+        if !is_vec_u8(field_type) {
+            return syn_error(field_type, "#[plod(rest)] only works with Vec<u8>");
+        }
+        size_code.extend(quote! {
+            #prefixed_field_ref.len() +
+        });
+        read_code.extend(quote! {
+            let mut #field_ident = Vec::new();
+            from.read_to_end(&mut #field_ident)?;
+            _pos += #field_ident.len();
+        });
+        write_code.extend(quote! {
+            to.write_all(#prefixed_field_ref)?;
+            _pos += #prefixed_field_ref.len();
+        });
+        return Ok(());
+    }
+    if attributes.bitmap {
+        return generate_for_bitmap(
+            field_ident,
+            field_type,
+            prefixed_field_dotted,
+            attributes,
+            size_code,
+            read_code,
+            write_code,
+        );
+    }
+    if attributes.size_after {
+        return generate_for_size_after(
+            field_ident,
+            field_type,
+            prefixed_field_ref,
+            prefixed_field_dotted,
+            attributes,
+            size_code,
+            read_code,
+            write_code,
+            context_val,
+            prefixed_context_val,
+        );
+    }
+    if let Some(encoding) = &attributes.encoding {
+        return generate_for_encoded(
+            field_ident,
+            field_type,
+            prefixed_field_ref,
+            encoding,
+            attributes,
+            size_code,
+            read_code,
+            write_code,
+            context_val,
+            prefixed_context_val,
+        );
+    }
+    match field_type {
+        Type::Path(type_path) => {
+            let mut is_vec = false;
+            let mut is_primitive = false;
+            if let Some(id) = type_path.path.segments.first() {
+                is_vec = id.ident == "Vec";
+                // TODO we should probably make sure there is only one segment
+                is_primitive = primitive_type(&id.ident);
+            };
+            if let Some(inner_type) = box_item(type_path) {
+                // boxes are transparent
+                generate_for_item(
+                    field_ident,
+                    inner_type,
+                    &quote! { (&**#prefixed_field_ref) },
+                    prefixed_field_dotted,
+                    is_tag,
+                    attributes,
+                    size_code,
+                    read_code,
+                    write_code,
+                    context_val,
+                    prefixed_context_val,
+                )?;
+                read_code.extend(quote! {
+                    let #field_ident = Box::new(#field_ident);
+                });
+            } else if type_path.path.segments.len() == 1
+                && type_path.path.segments[0].ident == "Option"
+            {
+                return syn_error(
+                    field_type,
+                    "Option has no plain old data representation, use #[plod(skip)], #[plod(if_remaining)] or an enum",
+                );
+            } else if let Some(is_text) = bytes_or_text(field_type) {
+                generate_for_bytes_or_text(
+                    field_ident,
+                    field_type,
+                    is_text,
+                    prefixed_field_ref,
+                    attributes,
+                    size_code,
+                    read_code,
+                    write_code,
+                    context_val,
+                    prefixed_context_val,
+                )?;
+            } else if is_blob(field_type) {
+                generate_for_blob(
+                    field_type,
+                    field_ident,
+                    prefixed_field_dotted,
+                    attributes,
+                    size_code,
+                    read_code,
+                    write_code,
+                )?;
+            } else if is_vec {
+                generate_for_vec(
+                    type_path,
+                    field_ident,
+                    prefixed_field_dotted,
+                    attributes,
+                    size_code,
+                    read_code,
+                    write_code,
+                    context_val,
+                    prefixed_context_val,
+                )?;
+            } else if is_primitive && attributes.varint.is_some() {
+                if is_tag {
+                    return syn_error(field_type, "A kept tag cannot be a varint");
+                }
+                generate_for_varint(
+                    type_path.path.get_ident().unwrap(),
+                    field_ident,
+                    prefixed_field_ref,
+                    attributes,
+                    size_code,
+                    read_code,
+                    write_code,
+                )?;
+            } else if is_primitive {
+                let ty = type_path.path.get_ident().unwrap();
+                let ty_size = primitive_size(ty);
+                let endianness = match attributes.tag_endianness {
+                    Some(tag_endianness) if is_tag => tag_endianness,
+                    _ => attributes.endianness,
+                };
+                let (from_method, to_method) = primitive_function(endianness);
+                size_code.extend(quote! {
+                    #ty_size +
+                });
+                if is_tag {
+                    // TODO, tag should always be read/written by enum_impl, this would be easier
+                    if let Some(diff) = &attributes.keep_diff {
                         read_code.extend(quote! {
                             let #field_ident = discriminant as #ty - #diff;
                         });
@@ -583,287 +3084,1450 @@ fn generate_for_item(
                     }
                 } else {
                     read_code.extend(quote! {
-                        let mut buffer: [u8; #ty_size] = [0; #ty_size];
-                        from.read_exact(&mut buffer)?;
+                        let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
                         let #field_ident = #ty::#from_method(buffer);
                         _pos += #ty_size;
                     });
                 }
-                let diff = if is_tag && attributes.keep_diff.is_some() {
-                    let diff = attributes.keep_diff.as_ref().unwrap();
-                    quote! { + #diff }
-                } else {
-                    TokenStream::new()
+                let diff = if is_tag && attributes.keep_diff.is_some() {
+                    let diff = attributes.keep_diff.as_ref().unwrap();
+                    quote! { + #diff }
+                } else {
+                    TokenStream::new()
+                };
+                write_code.extend(quote! {
+                    let buffer: [u8; #ty_size] = (#prefixed_field_ref #diff). #to_method();
+                    to.write_all(&buffer)?;
+                    _pos += #ty_size;
+                });
+            } else {
+                let read_method = read_method(attributes);
+                let write_method = write_method(attributes);
+                // a flattened type shares the current context instead of converting it
+                let (context_val, prefixed_context_val) = if attributes.flatten {
+                    (context_val.clone(), prefixed_context_val.clone())
+                } else {
+                    (nested_context(type_path, context_val, attributes), nested_context(type_path, prefixed_context_val, attributes))
+                };
+                size_code.extend(quote! {
+                    <#type_path as plod::Plod>::size_at_rest(#prefixed_field_ref) +
+                });
+                if is_tag {
+                    // a non primitive tag has already been read by enum_impl
+                    if attributes.keep_diff.is_some() {
+                        return syn_error(field_type, "keep_diff only works with primitive tag types");
+                    }
+                    read_code.extend(quote! {
+                        let #field_ident: #type_path = discriminant;
+                    });
+                } else {
+                    read_code.extend(quote! {
+                        let #field_ident = <#type_path as plod::Plod>::#read_method(from, #context_val, _pos)?;
+                        _pos += <#type_path as plod::Plod>::size_at_rest(&#field_ident);
+                    });
+                }
+                if attributes.seek {
+                    // the position tells what was actually written, without computing sizes again
+                    write_code.extend(quote! {
+                        let start = std::io::Seek::stream_position(to)?;
+                        <#type_path as plod::Plod>::#write_method(#prefixed_field_ref, to, #prefixed_context_val, _pos)?;
+                        _pos += (std::io::Seek::stream_position(to)? - start) as usize;
+                    });
+                } else {
+                    write_code.extend(quote! {
+                        <#type_path as plod::Plod>::#write_method(#prefixed_field_ref, to, #prefixed_context_val, _pos)?;
+                        _pos += <#type_path as plod::Plod>::size_at_rest(#prefixed_field_ref);
+                    });
+                }
+            }
+        }
+        Type::Tuple(t) => {
+            let mut field_list = TokenStream::new();
+            for (i, field_ty) in t.elems.iter().enumerate() {
+                let field_ident = Ident::new(&format!("infield_{}", i), field_ty.span());
+                let (prefixed_field_ref, prefixed_field_dotted) = {
+                    let i = syn::Index::from(i);
+                    (
+                        quote! {  ( & #prefixed_field_dotted #i ) },
+                        quote! {  #prefixed_field_dotted #i . },
+                    )
+                };
+                generate_for_item(
+                    &field_ident,
+                    field_ty,
+                    &prefixed_field_ref,
+                    &prefixed_field_dotted,
+                    false,
+                    attributes,
+                    size_code,
+                    read_code,
+                    write_code,
+                    context_val,
+                    prefixed_context_val,
+                )?;
+                field_list.extend(quote! {
+                    #field_ident,
+                });
+            }
+            read_code.extend(quote! {
+                let #field_ident = (#field_list);
+            });
+        }
+        Type::Array(t) => {
+            let n = &t.len;
+            let ty_ = &t.elem;
+            // u8 special case
+            let mut vec_u8 = false;
+            let mut primitive_item = None;
+            if let Type::Path(type_path) = ty_.as_ref() {
+                if let Some(id) = type_path.path.segments.first() {
+                    vec_u8 = id.ident == "u8";
+                }
+                primitive_item = type_path.path.get_ident().filter(|id| primitive_type(id));
+            }
+            // padded items are handled one by one
+            if attributes.item_align.is_some() {
+                vec_u8 = false;
+                primitive_item = None;
+            }
+
+            if vec_u8 {
+                size_code.extend(quote! {
+                    #prefixed_field_dotted len() +
+                });
+                read_code.extend(quote! {
+                    let mut #field_ident: #t = [0; #n];
+                    from.read_exact(&mut #field_ident)?;
+                });
+                write_code.extend(quote! {
+                    to.write_all(#prefixed_field_dotted as_slice())?;
+                });
+            } else if let Some(ty) = primitive_item {
+                // bytes are converted by blocks instead of reading items one by one
+                let ty_size = primitive_size(ty);
+                let (from_method, to_method) = primitive_function(attributes.endianness);
+                size_code.extend(quote! {
+                    #ty_size * #n +
+                });
+                read_code.extend(quote! {
+                    let mut #field_ident: #t = [0 as #ty; #n];
+                    for items in #field_ident.chunks_mut(1024 / #ty_size) {
+                        let mut buffer = [0_u8; 1024];
+                        let bytes = &mut buffer[..items.len() * #ty_size];
+                        from.read_exact(bytes)?;
+                        for (item, chunk) in items.iter_mut().zip(bytes.chunks_exact(#ty_size)) {
+                            *item = #ty::#from_method(chunk.try_into().unwrap());
+                        }
+                    }
+                    _pos += #ty_size * #n;
+                });
+                write_code.extend(quote! {
+                    for items in #prefixed_field_dotted chunks(1024 / #ty_size) {
+                        let mut buffer = [0_u8; 1024];
+                        let bytes = &mut buffer[..items.len() * #ty_size];
+                        for (chunk, item) in bytes.chunks_exact_mut(#ty_size).zip(items) {
+                            chunk.copy_from_slice(&item.#to_method());
+                        }
+                        to.write_all(bytes)?;
+                    }
+                    _pos += #ty_size * #n;
+                });
+            } else {
+                let mut item_size_code = TokenStream::new();
+                let mut item_read_code = TokenStream::new();
+                let mut item_write_code = TokenStream::new();
+                let item_name = Ident::new("item", field_ident.span());
+                generate_for_item(
+                    &item_name,
+                    ty_,
+                    &quote! { #item_name },
+                    &quote! { #item_name . },
+                    false,
+                    &attributes.array_item(),
+                    &mut item_size_code,
+                    &mut item_read_code,
+                    &mut item_write_code,
+                    context_val,
+                    prefixed_context_val,
+                )?;
+                let (size_padding, read_padding, write_padding, item_offset) = item_padding(attributes);
+                let (read_offset_update, write_offset_update) = match attributes.item_align {
+                    Some(_) => (
+                        quote! {
+                            offset += {
+                                #[allow(unused_variables)]
+                                let item = &item;
+                                #item_size_code 0
+                            };
+                        },
+                        quote! { offset += #item_size_code 0; },
+                    ),
+                    None => Default::default(),
                 };
+                size_code.extend(quote! {
+                    #prefixed_field_dotted iter().fold(0, |n, item| n + #size_padding #item_size_code 0) +
+                });
+                read_code.extend(quote! {
+                    let mut vec = Vec::new();
+                    #item_offset
+                    for _ in 0..#n {
+                        #read_padding
+                        #item_read_code
+                        #read_offset_update
+                        vec.push(item);
+                    }
+                    let #field_ident: #t = match vec.try_into() {
+                        Ok(array) => array,
+                        Err(_) => unreachable!("exactly N items have been read"),
+                    };
+               });
                 write_code.extend(quote! {
-                    let buffer: [u8; #ty_size] = (#prefixed_field_ref #diff). #to_method();
-                    to.write_all(&buffer)?;
-                    _pos += #ty_size;
+                    #item_offset
+                    for item in #prefixed_field_dotted iter() {
+                        #write_padding
+                        #item_write_code
+                        #write_offset_update
+                    }
                 });
+            }
+        }
+        Type::Reference(_) if bytes_or_text(field_type).is_some() => {
+            generate_for_bytes_or_text(
+                field_ident,
+                field_type,
+                bytes_or_text(field_type) == Some(true),
+                prefixed_field_ref,
+                attributes,
+                size_code,
+                read_code,
+                write_code,
+                context_val,
+                prefixed_context_val,
+            )?;
+        }
+        _ => {
+            return syn_error(field_ident, "Unsupported type for Plod");
+        }
+    }
+    Ok(())
+}
+
+fn generate_for_vec(
+    type_path: &TypePath,
+    field_ident: &Ident,
+    prefixed_field_dotted: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &     TokenStream,
+) -> Result<()> {
+    let size_ty = match &attributes.size_type {
+        Some(ty) => ty,
+        None => {
+            return syn_error(
+                type_path,
+                "Vec needs a size storage, add #[plod(size_type(<type>))] to the field, or to the type for all its Vec, eg: #[plod(size_type(u16))]",
+            );
+        }
+    };
+    if !unsigned_type(size_ty) && !(attributes.signed_size && integer_type(size_ty)) {
+        return syn_error(
+            size_ty,
+            "#[plod(size_type(<type>))] only works with unsigned integer types, add #[plod(signed_size)] to read negative sizes as empty",
+        );
+    }
+    // the size is stored in a previous field instead of prefixing items
+    let ty_size = match attributes.length_field {
+        Some(_) => LitInt::new("0", Span::call_site()),
+        None => primitive_size(size_ty),
+    };
+
+    let (from_method, to_method) = primitive_function(attributes.endianness);
+    // we can unwrap because it's how we know we are in a vec
+    let vec_generic = match &type_path.path.segments.first().unwrap().arguments {
+        PathArguments::AngleBracketed(pa) => {
+            if pa.args.len() != 1 {
+                return syn_error(
+                    type_path,
+                    "Plod only support regular Vec<Type>: unknown type Vec<X,Y,...>",
+                );
+            }
+            match pa.args.first().unwrap() {
+                GenericArgument::Type(t) => t,
+                _ => {
+                    return syn_error(
+                        type_path,
+                        "Plod only support regular Vec<Type>: unknown Vec<...>",
+                    )
+                }
+            }
+        }
+        _ => {
+            return syn_error(
+                type_path,
+                "Plod only support regular Vec<Type>: unknown Vec...",
+            );
+        }
+    };
+    // u8 special case
+    let mut vec_u8 = false;
+    if let Type::Path(type_path) = vec_generic {
+        if let Some(id) = type_path.path.segments.first() {
+            vec_u8 = id.ident == "u8" && attributes.item_align.is_none();
+        }
+    }
+    let (size_padding, read_padding, write_padding, item_offset) = item_padding(attributes);
+
+    let mut item_size_code = TokenStream::new();
+    let mut item_read_code = TokenStream::new();
+    let mut item_write_code = TokenStream::new();
+    let item_name = Ident::new("item", field_ident.span());
+    let it_name = Ident::new("it", field_ident.span());
+
+    // zero sized items cannot fill a size in bytes
+    let zero_sized = match vec_generic {
+        Type::Tuple(tuple) => tuple.elems.is_empty(),
+        Type::Array(array) => matches!(&array.len, Expr::Lit(lit) if matches!(&lit.lit, syn::Lit::Int(n) if n.base10_digits() == "0")),
+        ty => is_phantom(ty),
+    };
+    if attributes.byte_sized && zero_sized {
+        return syn_error(vec_generic, "byte_sized needs items that are stored in at least one byte");
+    }
+    // items without padding nor skipping are read and written by shared helpers
+    let plain = !vec_u8
+        && attributes.item_align.is_none()
+        && !attributes.skip_unknown
+        && !attributes.byte_sized
+        && plain_item(vec_generic, &attributes.vec_item());
+    if vec_u8 {
+        size_code.extend(quote! {
+            #ty_size + #prefixed_field_dotted len() +
+        });
+    } else if plain {
+        size_code.extend(quote! {
+            #ty_size + plod::helpers::vec_size(#prefixed_field_dotted as_slice()) +
+        });
+    } else {
+        generate_for_item(
+            &item_name,
+            vec_generic,
+            &quote! { # it_name },
+            &quote! { #it_name . },
+            false,
+            &attributes.vec_item(),
+            &mut item_size_code,
+            &mut item_read_code,
+            &mut item_write_code,
+            context_val,
+            prefixed_context_val,
+        )?;
+
+        // it_name may or may not be used by item_size_code
+        size_code.extend(quote! {
+            #ty_size + #prefixed_field_dotted iter().fold(0, #[allow(unused_variables)] |n, #it_name| n + #size_padding #item_size_code 0) +
+        });
+    }
+    let stored = stored_size_as(stored_size(quote! { size }, attributes), size_ty);
+    // the memory taken by items is counted against limits
+    let check_len = if attributes.byte_sized {
+        quote! { plod::fuzz::check_len(size)?; }
+    } else {
+        quote! { plod::fuzz::check_items::<#vec_generic>(size)?; }
+    };
+    if let Some(length_field) = &attributes.length_field {
+        let size = actual_size(quote! { #length_field as usize }, attributes);
+        read_code.extend(quote! {
+            let mut size = #size;
+            #check_len
+        });
+    } else {
+        let mut size = actual_size(quote! { #size_ty::#from_method(buffer) as usize }, attributes);
+        if !unsigned_type(size_ty) {
+            // negative sizes are empty collections
+            let actual = actual_size(quote! { stored as usize }, attributes);
+            size = quote! {
+                {
+                    let stored = #size_ty::#from_method(buffer);
+                    if stored < 0 { 0 } else { #actual }
+                }
+            };
+        }
+        read_code.extend(quote! {
+            let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+            _pos += #ty_size;
+            let mut size = #size;
+            #check_len
+        });
+    }
+    if attributes.length_field.is_some() {
+        // already written by the length field
+        write_code.extend(quote! {
+            let size = #prefixed_field_dotted len();
+        });
+    } else if attributes.byte_sized && attributes.patched_size && attributes.seek {
+        // placeholder patched once items are written
+        write_code.extend(quote! {
+            let size_position = std::io::Seek::stream_position(to)?;
+            to.write_all(&[0_u8; #ty_size])?;
+            _pos += #ty_size;
+        });
+    } else if attributes.byte_sized {
+        write_code.extend(quote! {
+            let size = #prefixed_field_dotted iter().fold(0, #[allow(unused_variables)] |n, #it_name| n + #size_padding #item_size_code 0);
+            let buffer: [u8; #ty_size] = #stored.#to_method();
+            to.write_all(&buffer)?;
+            _pos += #ty_size;
+        });
+    } else {
+        write_code.extend(quote! {
+            let size = #prefixed_field_dotted len();
+            let buffer: [u8; #ty_size] = #stored.#to_method();
+            to.write_all(&buffer)?;
+            _pos += #ty_size;
+        });
+    }
+    if attributes.columnar {
+        let primitive = matches!(vec_generic, Type::Path(p) if p.path.get_ident().is_some_and(primitive_type));
+        if primitive || attributes.byte_sized || attributes.item_align.is_some() || attributes.skip_unknown {
+            return syn_error(type_path, "columnar needs a Vec of structs counted in items, without item_align or skip_unknown");
+        }
+        // the item type reads and writes its fields one column after the other
+        let check = columnar_assert(vec_generic);
+        let item_context = nested_context(vec_generic, context_val, attributes);
+        let prefixed_item_context = nested_context(vec_generic, prefixed_context_val, attributes);
+        read_code.extend(quote! {
+            const { #check };
+            let #field_ident = <#vec_generic as plod::Plod>::impl_read_columns(from, #item_context, _pos, size)?;
+            _pos += #field_ident.iter().map(plod::Plod::size_at_rest).sum::<usize>();
+            plod::progress::tick(_pos);
+        });
+        write_code.extend(quote! {
+            <#vec_generic as plod::Plod>::impl_write_columns(#prefixed_field_dotted as_slice(), to, #prefixed_item_context, _pos)?;
+            _pos += #prefixed_field_dotted iter().map(plod::Plod::size_at_rest).sum::<usize>();
+        });
+        return Ok(());
+    }
+    // Vec<u8> can be read and written all at once
+    if let (true, Some(inline)) = (vec_u8, &attributes.inline) {
+        read_code.extend(quote! {
+            let #field_ident = <#inline>::read_sized(from, size, _pos)?;
+            _pos += size;
+        });
+        write_code.extend(quote! {
+            to.write_all(#prefixed_field_dotted as_ref())?;
+            _pos += size;
+        });
+    } else if vec_u8 && attributes.arena {
+        read_code.extend(quote! {
+            let #field_ident: &mut [u8] = plod::arena::Arena::alloc_bytes(#context_val, size);
+            from.read_exact(#field_ident)?;
+            let #field_ident: &[u8] = #field_ident;
+            _pos += size;
+            plod::progress::tick(_pos);
+        });
+        write_code.extend(quote! {
+            to.write_all(#prefixed_field_dotted as_ref())?;
+            _pos += size;
+        });
+    } else if plain {
+        let item_attributes = attributes.vec_item();
+        let item_context = nested_context(vec_generic, context_val, &item_attributes);
+        let prefixed_item_context = nested_context(vec_generic, prefixed_context_val, &item_attributes);
+        read_code.extend(quote! {
+            let (#field_ident, end) = plod::helpers::read_items::<#vec_generic, _>(from, #item_context, _pos, size)?;
+            _pos = end;
+        });
+        write_code.extend(quote! {
+            _pos = plod::helpers::write_items(to, #prefixed_field_dotted as_slice(), #prefixed_item_context, _pos)?;
+        });
+    } else if vec_u8 {
+        // byte size == count size for Vec<u8>
+        read_code.extend(quote! {
+            let mut #field_ident = vec![0_u8; size];
+            from.read_exact(&mut #field_ident)?;
+            _pos += size;
+            plod::progress::tick(_pos);
+        });
+        write_code.extend(quote! {
+            to.write_all(#prefixed_field_dotted as_ref())?;
+            _pos += size;
+        });
+    } else {
+        if attributes.skip_unknown {
+            // items containing an unknown tag are skipped
+            let mut skip = if attributes.byte_sized {
+                quote! {
+                    size = size.checked_sub(skipped).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Vec item exceeds the declared byte size"))?;
+                }
             } else {
-                size_code.extend(quote! {
-                    <#type_path as plod::Plod>::size_at_rest(#prefixed_field_ref) +
-                });
-                read_code.extend(quote! {
-                    let #field_ident = <#type_path as plod::Plod>::impl_read_from(from, #context_val.into(), _pos)?;
-                    _pos += <#type_path as plod::Plod>::size_at_rest(&#field_ident);
-                });
-                write_code.extend(quote! {
-                    <#type_path as plod::Plod>::impl_write_to(#prefixed_field_ref, to, #prefixed_context_val.into(), _pos)?;
-                    _pos += <#type_path as plod::Plod>::size_at_rest(#prefixed_field_ref);
-                });
+                TokenStream::new()
+            };
+            if attributes.item_align.is_some() {
+                skip.extend(quote! { offset += skipped; });
             }
-        }
-        Type::Tuple(t) => {
-            let mut field_list = TokenStream::new();
-            for (i, field_ty) in t.elems.iter().enumerate() {
-                let field_ident = Ident::new(&format!("infield_{}", i), field_ty.span());
-                let (prefixed_field_ref, prefixed_field_dotted) = {
-                    let i = syn::Index::from(i);
-                    (
-                        quote! {  ( & #prefixed_field_dotted #i ) },
-                        quote! {  #prefixed_field_dotted #i . },
-                    )
+            item_read_code = quote! {
+                let read = (|| -> plod::Result<_> {
+                    #item_read_code
+                    Ok(#item_name)
+                })();
+                let #item_name = match read {
+                    Ok(item) => item,
+                    Err(e) => {
+                        let skipped = plod::lenient::skipped(e)?;
+                        _pos += skipped;
+                        #skip
+                        continue;
+                    }
                 };
-                generate_for_item(
-                    &field_ident,
-                    field_ty,
-                    &prefixed_field_ref,
-                    &prefixed_field_dotted,
-                    false,
-                    attributes,
-                    size_code,
-                    read_code,
-                    write_code,
-                    context_val,
-                    prefixed_context_val,
-                )?;
-                field_list.extend(quote! {
-                    #field_ident,
-                });
-            }
+            };
+        }
+        let item_offset_update = match attributes.item_align {
+            Some(_) => quote! { offset += #item_size_code 0; },
+            None => TokenStream::new(),
+        };
+        let read_offset_update = match attributes.item_align {
+            Some(_) => quote! {
+                let #it_name = &#item_name;
+                #item_offset_update
+            },
+            None => TokenStream::new(),
+        };
+        // items are read in their own block so that nested Vec don't clash with this one
+        if attributes.byte_sized {
+            // padding is counted in the size
+            let read_padding = if attributes.item_align.is_some() {
+                quote! {
+                    #read_padding
+                    size = size.checked_sub(padding).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Item padding exceeds the Vec size"))?;
+                }
+            } else {
+                read_padding
+            };
             read_code.extend(quote! {
-                let #field_ident = (#field_list);
+                let mut items = Vec::new();
+                #item_offset
+                while size > 0 {
+                    #read_padding
+                    let #item_name = { #item_read_code #item_name };
+                    let #it_name = &#item_name;
+                    // an item crossing the end of the declared size is an error, so the loop ends
+                    // exactly at this end, and an empty item would never reach it
+                    let item_size = #item_size_code 0;
+                    if item_size == 0 {
+                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Empty Vec item in a byte sized Vec"));
+                    }
+                    size = size.checked_sub(item_size).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Vec item exceeds the declared byte size"))?;
+                    #item_offset_update
+                    items.push(#item_name);
+                    plod::progress::tick(_pos);
+                    plod::cancel::check()?;
+                }
+                let #field_ident = items;
             });
-        }
-        Type::Array(t) => {
-            let n = &t.len;
-            let ty_ = &t.elem;
-            // u8 special case
-            let mut vec_u8 = false;
-            if let Type::Path(type_path) = ty_.as_ref() {
-                if let Some(id) = type_path.path.segments.first() {
-                    vec_u8 = id.ident == "u8";
+        } else {
+            read_code.extend(quote! {
+                let mut items = Vec::with_capacity(plod::fuzz::capacity::<#vec_generic>(size));
+                #item_offset
+                for _ in 0..size {
+                    #read_padding
+                    let #item_name = { #item_read_code #item_name };
+                    #read_offset_update
+                    items.push(#item_name);
+                    plod::progress::tick(_pos);
+                    plod::cancel::check()?;
                 }
+                let #field_ident = items;
+            });
+        }
+        write_code.extend(quote! {
+            #item_offset
+            for #it_name in #prefixed_field_dotted iter() {
+                #write_padding
+                #item_write_code
+                #item_offset_update
             }
+        });
+        if attributes.byte_sized
+            && attributes.patched_size
+            && attributes.seek
+            && attributes.length_field.is_none()
+        {
+            write_code.extend(quote! {
+                let end = std::io::Seek::stream_position(to)?;
+                let size = (end - size_position) as usize - #ty_size;
+                let buffer: [u8; #ty_size] = #stored.#to_method();
+                std::io::Seek::seek(to, std::io::SeekFrom::Start(size_position))?;
+                to.write_all(&buffer)?;
+                std::io::Seek::seek(to, std::io::SeekFrom::Start(end))?;
+            });
+        }
+    }
+    Ok(())
+}
 
-            if vec_u8 {
-                size_code.extend(quote! {
-                    #prefixed_field_dotted len() +
-                });
-                read_code.extend(quote! {
-                    let mut #field_ident: #t = [0; #n];
-                    from.read_exact(&mut #field_ident)?;
-                });
-                write_code.extend(quote! {
-                    to.write_all(#prefixed_field_dotted as_slice())?;
-                });
-            } else {
-                let mut item_size_code = TokenStream::new();
-                let mut item_read_code = TokenStream::new();
-                let mut item_write_code = TokenStream::new();
-                let item_name = Ident::new("item", field_ident.span());
-                generate_for_item(
-                    &item_name,
-                    ty_,
-                    &quote! { #item_name },
-                    &quote! { #item_name . },
-                    false,
-                    attributes,
-                    &mut item_size_code,
-                    &mut item_read_code,
-                    &mut item_write_code,
-                    context_val,
-                    prefixed_context_val,
-                )?;
-                size_code.extend(quote! {
-                    #prefixed_field_dotted iter().fold(0, |n, item| n + #item_size_code 0) +
-                });
-                read_code.extend(quote! {
-                    let mut vec = Vec::new();
-                    for _ in 0..#n {
-                        #item_read_code
-                        vec.push(item);
-                    }
-                    let #field_ident: #t = vec.try_into().unwrap();
-               });
-                write_code.extend(quote! {
-                    for item in #prefixed_field_dotted iter() {
-                        #item_write_code
-                    }
-                });
+/// Generate code for a byte array that always holds the constant `expected`, a `&[u8; N]`
+fn generate_for_equals(
+    field_ident: &Ident,
+    field_type: &Type,
+    expected: &Expr,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) -> Result<()> {
+    let len = match field_type {
+        Type::Array(array) if matches!(&*array.elem, Type::Path(p) if p.path.is_ident("u8")) => &array.len,
+        _ => return syn_error(field_type, "#[plod(equals)] only works with [u8; N]"),
+    };
+    size_code.extend(quote! {
+        #len +
+    });
+    // the constant must have the size of the array
+    read_code.extend(quote! {
+        let expected: &[u8; #len] = #expected;
+        let mut #field_ident = [0_u8; #len];
+        from.read_exact(&mut #field_ident)?;
+        if #field_ident != *expected {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{:02x?} expected at offset {}, found {:02x?}", expected, _pos, #field_ident),
+            ));
+        }
+        _pos += #len;
+    });
+    write_code.extend(quote! {
+        let expected: &[u8; #len] = #expected;
+        to.write_all(expected)?;
+        _pos += #len;
+    });
+    Ok(())
+}
+
+/// Generate code for a `Cow<[u8]>` or a text: stored like a `Vec<u8>` of the encoded text, written
+/// from the borrowed or owned bytes and always read as an owned value
+#[allow(clippy::too_many_arguments)]
+fn generate_for_bytes_or_text(
+    field_ident: &Ident,
+    field_type: &Type,
+    is_text: bool,
+    prefixed_field_ref: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+) -> Result<()> {
+    let encoding = text_encoding(attributes);
+    let bytes = if is_text {
+        // the encoded text is borrowed unless it differs from the UTF-8 one
+        write_code.extend(quote! { #encoding.check(#prefixed_field_ref)?; });
+        quote! { (&*#encoding.encode(#prefixed_field_ref)) }
+    } else {
+        quote! { (std::convert::AsRef::<[u8]>::as_ref(&**#prefixed_field_ref)) }
+    };
+    // references are read in the arena of the context, as UTF-8 for a text
+    let arena = matches!(field_type, Type::Reference(_));
+    if arena && is_text && (attributes.bstr || attributes.text_encoding.as_ref().is_some_and(|e| e != "utf8_strict")) {
+        return syn_error(field_type, "&str is only read as UTF-8 text, use String for other encodings");
+    }
+    // inline bytes are read in place, like a reference
+    let inline = inline_bytes(field_type).map(|(bytes_type, _)| bytes_type);
+    if inline.is_some() && is_text && attributes.text_encoding.as_ref().is_some_and(|e| e != "utf8_strict") {
+        return syn_error(field_type, "InlineStr is only read as UTF-8 text, use String for other encodings");
+    }
+    // the encoded text is a Vec<u8>
+    let mut bytes_attributes = attributes.clone();
+    bytes_attributes.bstr = false;
+    bytes_attributes.arena = arena;
+    bytes_attributes.inline = inline.clone();
+    generate_for_item(
+        field_ident,
+        &blob_as_vec(),
+        &bytes,
+        &quote! { #bytes . },
+        false,
+        &bytes_attributes,
+        size_code,
+        read_code,
+        write_code,
+        context_val,
+        prefixed_context_val,
+    )?;
+    if is_text && arena {
+        read_code.extend(quote! {
+            let #field_ident = plod::arena::text(#field_ident, _pos - #field_ident.len())?;
+        });
+    } else if is_text && inline.is_some() {
+        read_code.extend(quote! {
+            let #field_ident = plod::inline::InlineStr::from_utf8(#field_ident, _pos - #field_ident.len())?;
+        });
+    } else if is_text {
+        read_code.extend(quote! {
+            let text_pos = _pos - #field_ident.len();
+            let #field_ident = #encoding.decode(#field_ident, text_pos)?;
+        });
+    }
+    if attributes.bstr {
+        // the terminating NUL character is not part of the size
+        size_code.extend(quote! { 2 + });
+        read_code.extend(quote! {
+            let mut nul = [0_u8; 2];
+            from.read_exact(&mut nul)?;
+            if nul != [0, 0] {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("BSTR not terminated by a NUL character at offset {}", _pos),
+                ));
+            }
+            _pos += 2;
+        });
+        write_code.extend(quote! {
+            to.write_all(&[0_u8; 2])?;
+            _pos += 2;
+        });
+    }
+    if !arena && inline.is_none() && !matches!(field_type, Type::Path(p) if p.path.is_ident("String")) {
+        read_code.extend(quote! {
+            let #field_ident = std::borrow::Cow::Owned(#field_ident);
+        });
+    }
+    Ok(())
+}
+
+/// Encoding of a text, `plod::text::TextEncoding::Utf8Strict` by default
+fn text_encoding(attributes: &Attributes) -> TokenStream {
+    let variant = match attributes.text_encoding.as_ref().map(|e| e.to_string()).as_deref() {
+        Some("utf8_lossy") => quote! { Utf8Lossy },
+        Some("latin1") => quote! { Latin1 },
+        Some("ascii") => quote! { Ascii },
+        Some("utf16le") => quote! { Utf16Le },
+        Some("utf16be") => quote! { Utf16Be },
+        _ => quote! { Utf8Strict },
+    };
+    quote! { plod::text::TextEncoding::#variant }
+}
+
+/// The `plod::varint::Varint` of a flavor name
+fn varint_flavor(flavor: &Ident) -> TokenStream {
+    let variant = match flavor.to_string().as_str() {
+        "midi_vlq" => quote! { MidiVlq },
+        "git_vlq" => quote! { GitVlq },
+        "sqlite" => quote! { Sqlite },
+        _ => quote! { Leb128 },
+    };
+    quote! { plod::varint::Varint::#variant }
+}
+
+/// Is the encoding of a text one byte per character
+fn single_byte_text(attributes: &Attributes) -> bool {
+    attributes.text_encoding.as_ref().is_some_and(|e| e == "latin1" || e == "ascii")
+}
+
+/// Is the encoding of a text two bytes per UTF-16 code unit
+fn utf16_text(attributes: &Attributes) -> bool {
+    attributes.text_encoding.as_ref().is_some_and(|e| e == "utf16le" || e == "utf16be")
+}
+
+/// Generate code for a `Blob`: its size prefix, then its content skipped on read and copied
+/// from its source on write
+fn generate_for_blob(
+    field_type: &Type,
+    field_ident: &Ident,
+    prefixed_field_dotted: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) -> Result<()> {
+    let size_ty = match &attributes.size_type {
+        Some(ty) if unsigned_type(ty) => ty,
+        _ => return syn_error(field_type, "Blob needs an unsigned integer size storage, eg: #[plod(size_type(u32))]"),
+    };
+    if attributes.length_field.is_some()
+        || attributes.size_adjust != 0
+        || attributes.size_unit.is_some()
+        || attributes.encoding.is_some()
+    {
+        return syn_error(field_type, "Blob only supports a size_type prefix");
+    }
+    let ty_size = primitive_size(size_ty);
+    let (from_method, to_method) = primitive_function(attributes.endianness);
+    // content is sought over when the reader allows it
+    let skip = if attributes.seek {
+        quote! { read_seeking }
+    } else {
+        quote! { read_skipping }
+    };
+    size_code.extend(quote! {
+        #ty_size + #prefixed_field_dotted len() +
+    });
+    read_code.extend(quote! {
+        let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+        _pos += #ty_size;
+        let size = #size_ty::#from_method(buffer) as usize;
+        let #field_ident = plod::Blob::#skip(from, _pos, size)?;
+        _pos += size;
+        plod::progress::tick(_pos);
+    });
+    write_code.extend(quote! {
+        let size = #prefixed_field_dotted len();
+        if size > #size_ty::MAX as usize {
+            return Err(std::io::Error::other(format!("Size {} does not fit in {}", size, stringify!(#size_ty))));
+        }
+        to.write_all(&(size as #size_ty).#to_method())?;
+        _pos += #ty_size;
+        #prefixed_field_dotted write_content(to)?;
+        _pos += size;
+    });
+    Ok(())
+}
+
+/// Code handling the padding before the items of a collection with `item_align`: size code to
+/// add after `n` bytes of items, read and write code after `offset` bytes of items, and the
+/// declaration of `offset`
+fn item_padding(attributes: &Attributes) -> (TokenStream, TokenStream, TokenStream, TokenStream) {
+    match attributes.item_align {
+        Some(align) => (
+            quote! { plod::helpers::padding(n, #align) + },
+            quote! {
+                let padding = plod::helpers::read_padding(from, offset, #align)?;
+                offset += padding;
+                _pos += padding;
+            },
+            quote! {
+                let padding = plod::helpers::write_padding(to, offset, #align)?;
+                offset += padding;
+                _pos += padding;
+            },
+            quote! { let mut offset = 0; },
+        ),
+        None => Default::default(),
+    }
+}
+
+/// Is this type made of primitives only, which are read with the endianness of their container
+fn primitive_items(ty: &Type) -> bool {
+    match ty {
+        Type::Path(p) => match p.path.get_ident() {
+            Some(ident) => primitive_type(ident),
+            None => {
+                let item = if is_vec(ty) { vec_item(p) } else { option_item(p).or_else(|| box_item(p)) };
+                item.is_some_and(primitive_items)
+            }
+        },
+        Type::Array(a) => primitive_items(&a.elem),
+        Type::Tuple(t) => t.elems.iter().all(primitive_items),
+        _ => false,
+    }
+}
+
+/// Is this type a `Vec`, once std types are resolved
+fn is_vec(ty: &Type) -> bool {
+    matches!(ty, Type::Path(p) if p.path.segments.first().is_some_and(|s| s.ident == "Vec"))
+}
+
+/// Item type of a `Vec<T>`
+fn vec_item(type_path: &TypePath) -> Option<&Type> {
+    match &type_path.path.segments.first()?.arguments {
+        PathArguments::AngleBracketed(pa) => match pa.args.first() {
+            Some(GenericArgument::Type(t)) => Some(t),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Item type of a `Box<T>`
+fn box_item(type_path: &TypePath) -> Option<&Type> {
+    wrapped_item(type_path, "Box")
+}
+
+/// Item type of an `Option<T>`
+fn option_item(type_path: &TypePath) -> Option<&Type> {
+    wrapped_item(type_path, "Option")
+}
+
+/// Item type of a `<wrapper><T>` standard type
+fn wrapped_item<'a>(type_path: &'a TypePath, wrapper: &str) -> Option<&'a Type> {
+    let segment = type_path.path.segments.first()?;
+    if type_path.path.segments.len() != 1 || segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(pa) => match pa.args.first() {
+            Some(GenericArgument::Type(t)) => Some(t),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Is this type `plod::Blob`, stored like a `Vec<u8>` without being held in memory, the path is
+/// required so that user types named `Blob` are left alone
+fn is_blob(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => {
+            let segments = &type_path.path.segments;
+            type_path.qself.is_none()
+                && segments.last().is_some_and(|s| s.ident == "Blob" && s.arguments.is_none())
+                && segments.len() == 2
+                && segments[0].ident == "plod"
+        }
+        _ => false,
+    }
+}
+
+/// Integer type of a duration like `plod::Millis<u32>` or of a `plod::Bounded<u8, 1, 12>`, stored as
+/// this integer with the endianness of the field, the path is required like for `Blob`
+fn integer_storage(ty: &Type) -> Option<Type> {
+    let type_path = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path,
+        _ => return None,
+    };
+    let segments: Vec<_> = type_path.path.segments.iter().collect();
+    let last = segments.last()?;
+    let (module, arity) = if last.ident == "Bounded" {
+        ("bounded", 3)
+    } else if ["Seconds", "Millis", "Micros", "Nanos"].iter().any(|name| last.ident == name) {
+        ("time", 1)
+    } else {
+        return None;
+    };
+    let in_plod = match segments.len() {
+        2 => segments[0].ident == "plod",
+        3 => segments[0].ident == "plod" && segments[1].ident == module,
+        _ => false,
+    };
+    if !in_plod {
+        return None;
+    }
+    match &last.arguments {
+        PathArguments::AngleBracketed(pa) if pa.args.len() == arity => match pa.args.first() {
+            Some(GenericArgument::Type(Type::Path(p))) if p.path.get_ident().is_some_and(primitive_type) => {
+                Some(Type::Path(p.clone()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Is this type a `plod::Bounded`, recognized like by `integer_storage`
+fn is_bounded(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) => {
+            type_path.path.segments.last().is_some_and(|s| s.ident == "Bounded") && integer_storage(ty).is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Is this type a `PhantomData`, which is not stored
+fn is_phantom(ty: &Type) -> bool {
+    match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let segments = &type_path.path.segments;
+            segments.last().is_some_and(|s| s.ident == "PhantomData")
+                && (segments.len() == 1
+                    || (segments.len() == 3
+                        && ["std", "core"].iter().any(|c| segments[0].ident == c)
+                        && segments[1].ident == "marker"))
+        }
+        _ => false,
+    }
+}
+
+/// `Vec<u8>`, the type a `Blob` or a `Cow` of bytes is described as
+fn blob_as_vec() -> Type {
+    syn::parse_quote! { Vec<u8> }
+}
+
+/// Is this type a `Cow<[u8]>` or a text (`Cow<str>` or `String`), all stored like a `Vec<u8>`,
+/// and is it a text. References to `[u8]` and `str` are also stored like this, they are allocated
+/// in the arena of the context. `plod::InlineBytes` and `plod::InlineStr` are stored like this too,
+/// but hold their bytes in place, without allocation.
+fn bytes_or_text(ty: &Type) -> Option<bool> {
+    if let Type::Reference(reference) = ty {
+        return match &*reference.elem {
+            Type::Slice(slice) if matches!(&*slice.elem, Type::Path(p) if p.path.is_ident("u8")) => Some(false),
+            Type::Path(p) if p.path.is_ident("str") => Some(true),
+            _ => None,
+        };
+    }
+    if let Some((_, is_text)) = inline_bytes(ty) {
+        return Some(is_text);
+    }
+    let segment = match &resolve_std_types(ty) {
+        Type::Path(type_path) if type_path.qself.is_none() && type_path.path.segments.len() == 1 => {
+            type_path.path.segments[0].clone()
+        }
+        _ => return None,
+    };
+    if segment.ident == "String" && segment.arguments.is_none() {
+        return Some(true);
+    }
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(pa) if segment.ident == "Cow" => &pa.args,
+        _ => return None,
+    };
+    // the lifetime comes first
+    args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(Type::Slice(slice)) if matches!(&*slice.elem, Type::Path(p) if p.path.is_ident("u8")) => Some(false),
+        GenericArgument::Type(Type::Path(p)) if p.path.is_ident("str") => Some(true),
+        _ => None,
+    })
+}
+
+/// The `plod::InlineBytes<N>` holding the bytes of a `plod::InlineBytes<N>` or `plod::InlineStr<N>`,
+/// and is it a text, the path is required like for `Blob`
+fn inline_bytes(ty: &Type) -> Option<(Type, bool)> {
+    let type_path = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path,
+        _ => return None,
+    };
+    let segments: Vec<_> = type_path.path.segments.iter().collect();
+    let last = segments.last()?;
+    let is_text = match last.ident.to_string().as_str() {
+        "InlineBytes" => false,
+        "InlineStr" => true,
+        _ => return None,
+    };
+    let in_plod = match segments.len() {
+        2 => segments[0].ident == "plod",
+        3 => segments[0].ident == "plod" && segments[1].ident == "inline",
+        _ => false,
+    };
+    match &last.arguments {
+        PathArguments::AngleBracketed(pa) if in_plod && pa.args.len() == 1 => {
+            let len = &pa.args;
+            Some((syn::parse_quote! { plod::inline::InlineBytes<#len> }, is_text))
+        }
+        _ => None,
+    }
+}
+
+/// Is this type exactly `Vec<u8>`
+fn is_vec_u8(ty: &Type) -> bool {
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.first() {
+            if let PathArguments::AngleBracketed(pa) = &segment.arguments {
+                if let Some(GenericArgument::Type(Type::Path(item))) = pa.args.first() {
+                    return segment.ident == "Vec" && item.path.is_ident("u8");
+                }
             }
         }
-        _ => {
-            return syn_error(field_ident, "Unsupported type for Plod");
+    }
+    false
+}
+
+/// Is this type exactly `Vec<bool>`
+fn is_vec_bool(ty: &Type) -> bool {
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.first() {
+            if let PathArguments::AngleBracketed(pa) = &segment.arguments {
+                if let Some(GenericArgument::Type(Type::Path(item))) = pa.args.first() {
+                    return segment.ident == "Vec" && item.path.is_ident("bool");
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Expression producing the encoded bytes of an item, `access` is a reference to the item.
+/// This is used where no context is available, so nested types use a default context.
+fn encoded_bytes(ty: &Type, access: &TokenStream, encoding: &Encoding) -> TokenStream {
+    let encode = &encoding.encode;
+    if is_vec_u8(ty) {
+        quote! { #encode(#access.as_slice()) }
+    } else {
+        quote! {
+            {
+                let mut decoded = Vec::new();
+                let _ = <#ty as plod::Plod>::write_to(#access, &mut decoded);
+                #encode(&decoded)
+            }
         }
     }
+}
+
+/// Generate code for an `Option` item present only if bytes remain in the reader
+fn generate_for_remaining(
+    field_ident: &Ident,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+) -> Result<()> {
+    let inner_type = match field_type {
+        Type::Path(type_path) => option_item(type_path),
+        _ => None,
+    };
+    let inner_type = match inner_type {
+        Some(t) => t,
+        None => return syn_error(field_type, "#[plod(if_remaining)] only works with Option<T>"),
+    };
+    // the item is read from a chained reader, which is not seekable
+    let mut inner = attributes.clone();
+    inner.if_remaining = false;
+    inner.seek = false;
+    let item = Ident::new("item", field_ident.span());
+    let mut item_size_code = TokenStream::new();
+    let mut item_read_code = TokenStream::new();
+    let mut item_write_code = TokenStream::new();
+    generate_for_item(
+        &item,
+        inner_type,
+        &quote! { #item },
+        &quote! { #item . },
+        false,
+        &inner,
+        &mut item_size_code,
+        &mut item_read_code,
+        &mut item_write_code,
+        context_val,
+        prefixed_context_val,
+    )?;
+    size_code.extend(quote! {
+        match #prefixed_field_ref {
+            Some(#item) => #item_size_code 0,
+            None => 0,
+        } +
+    });
+    let name = field_ident.to_string();
+    let observer = observer();
+    // the first byte tells if there is anything left, it is then read again as part of the item
+    read_code.extend(quote! {
+        let mut first = [0_u8; 1];
+        let #field_ident = if plod::helpers::read_some(from, &mut first)? == 0 {
+            if let Some(observer) = #observer {
+                observer.on_truncated(_pos, #name);
+            }
+            None
+        } else {
+            #[allow(unused_imports)]
+            use std::io::Read as _;
+            let from = &mut std::io::Read::chain(first.as_slice(), &mut *from);
+            #item_read_code
+            Some(#item)
+        };
+    });
+    let message = format!("{} cannot be Some after an if_remaining field that is None", name);
+    write_code.extend(quote! {
+        match #prefixed_field_ref {
+            Some(_) if _remaining_none => {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, #message));
+            }
+            Some(#item) => { #item_write_code }
+            None => _remaining_none = true,
+        }
+    });
     Ok(())
 }
 
-fn generate_for_vec(
-    type_path: &TypePath,
+/// Generate code for an item stored encoded in a size prefixed region
+fn generate_for_encoded(
     field_ident: &Ident,
-    prefixed_field_dotted: &TokenStream,
+    field_type: &Type,
+    prefixed_field_ref: &TokenStream,
+    encoding: &Encoding,
     attributes: &Attributes,
     size_code: &mut TokenStream,
     read_code: &mut TokenStream,
     write_code: &mut TokenStream,
     context_val: &TokenStream,
-    prefixed_context_val: &     TokenStream,
+    prefixed_context_val: &TokenStream,
 ) -> Result<()> {
+    let raw = is_vec_u8(field_type);
+    let is_plod_type = match field_type {
+        Type::Path(type_path) => match type_path.path.segments.first() {
+            Some(segment) => segment.ident != "Vec" && !primitive_type(&segment.ident),
+            None => false,
+        },
+        _ => false,
+    };
+    if !raw && !is_plod_type {
+        return syn_error(
+            field_type,
+            "Encoded fields must be a Vec<u8> or a type implementing Plod",
+        );
+    }
     let size_ty = match &attributes.size_type {
-        Some(ty) => ty,
-        None => {
+        Some(ty) if unsigned_type(ty) => ty,
+        _ => {
             return syn_error(
-                type_path,
-                "#[plod(size_type(<value>))] is mandatory for Vec<type>",
-            );
+                field_type,
+                "#[plod(size_type(<type>))] with an unsigned integer type is mandatory for encoded fields",
+            )
         }
     };
-    if !primitive_type(size_ty) {
-        return syn_error(size_ty, "vec length magic only works with primitive types");
-    }
     let ty_size = primitive_size(size_ty);
-
     let (from_method, to_method) = primitive_function(attributes.endianness);
-    // we can unwrap because it's how we know we are in a vec
-    let vec_generic = match &type_path.path.segments.first().unwrap().arguments {
-        PathArguments::AngleBracketed(pa) => {
-            if pa.args.len() != 1 {
-                return syn_error(
-                    type_path,
-                    "Plod only support regular Vec<Type>: unknown type Vec<X,Y,...>",
-                );
-            }
-            match pa.args.first().unwrap() {
-                GenericArgument::Type(t) => t,
-                _ => {
-                    return syn_error(
-                        type_path,
-                        "Plod only support regular Vec<Type>: unknown Vec<...>",
-                    )
-                }
+    let encode = &encoding.encode;
+    let decode = &encoding.decode;
+
+    let encoded = encoded_bytes(field_type, prefixed_field_ref, encoding);
+    size_code.extend(quote! {
+        #ty_size + #encoded.len() +
+    });
+    let value = if raw {
+        quote! { decoded }
+    } else {
+        let context = nested_context(field_type, context_val, attributes);
+        quote! { <#field_type as plod::Plod>::impl_read_from(&mut decoded.as_slice(), #context, 0)? }
+    };
+    if let Some(check) = &encoding.check {
+        // a compression algorithm needs the feature of the same name
+        read_code.extend(quote! { const _: () = #check; });
+    }
+    read_code.extend(quote! {
+        let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+        _pos += #ty_size;
+        let size = #size_ty::#from_method(buffer) as usize;
+        plod::fuzz::check_len(size)?;
+        let mut encoded = vec![0_u8; size];
+        from.read_exact(&mut encoded)?;
+        _pos += size;
+        let decoded = #decode(&encoded)?;
+        let #field_ident = #value;
+    });
+    let encoded = if raw {
+        quote! { #encode(#prefixed_field_ref.as_slice()) }
+    } else {
+        let prefixed_context = nested_context(field_type, prefixed_context_val, attributes);
+        quote! {
+            {
+                let mut decoded = Vec::new();
+                <#field_type as plod::Plod>::impl_write_to(#prefixed_field_ref, &mut decoded, #prefixed_context, 0)?;
+                #encode(&decoded)
             }
         }
-        _ => {
-            return syn_error(
-                type_path,
-                "Plod only support regular Vec<Type>: unknown Vec...",
-            );
-        }
     };
-    // u8 special case
-    let mut vec_u8 = false;
-    if let Type::Path(type_path) = vec_generic {
-        if let Some(id) = type_path.path.segments.first() {
-            vec_u8 = id.ident == "u8";
+    write_code.extend(quote! {
+        let encoded = #encoded;
+        if encoded.len() > #size_ty::MAX as usize {
+            return Err(std::io::Error::other(format!("Encoded size {} does not fit in {}", encoded.len(), stringify!(#size_ty))));
         }
-    }
+        let buffer: [u8; #ty_size] = (encoded.len() as #size_ty).#to_method();
+        to.write_all(&buffer)?;
+        to.write_all(&encoded)?;
+        _pos += #ty_size + encoded.len();
+    });
+    Ok(())
+}
 
+/// Expression of the size stored for a collection, `dotted` gives access to the collection
+fn collection_size(
+    ty: &Type,
+    dotted: &TokenStream,
+    attributes: &Attributes,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+) -> Result<TokenStream> {
+    // single byte encodings store one byte per character
+    if bytes_or_text(ty) == Some(true) && single_byte_text(attributes) {
+        return Ok(stored_size(quote! { #dotted chars().count() }, attributes));
+    }
+    if bytes_or_text(ty) == Some(true) && utf16_text(attributes) {
+        return Ok(stored_size(quote! { #dotted encode_utf16().count() * 2 }, attributes));
+    }
+    if attributes.bitmap && attributes.byte_sized {
+        return Ok(stored_size(quote! { #dotted len().div_ceil(8) }, attributes));
+    }
+    if !attributes.byte_sized || is_vec_u8(ty) || bytes_or_text(ty).is_some() {
+        return Ok(stored_size(quote! { #dotted len() }, attributes));
+    }
+    let item_ty = match ty {
+        Type::Path(type_path) => match &type_path.path.segments.first().unwrap().arguments {
+            PathArguments::AngleBracketed(pa) => match pa.args.first() {
+                Some(GenericArgument::Type(t)) => t,
+                _ => return syn_error(ty, "Plod only support regular Vec<Type>"),
+            },
+            _ => return syn_error(ty, "Plod only support regular Vec<Type>"),
+        },
+        _ => return syn_error(ty, "Plod only support regular Vec<Type>"),
+    };
+    let it_name = Ident::new("it", Span::call_site());
     let mut item_size_code = TokenStream::new();
-    let mut item_read_code = TokenStream::new();
-    let mut item_write_code = TokenStream::new();
-    let item_name = Ident::new("item", field_ident.span());
-    let it_name = Ident::new("it", field_ident.span());
+    generate_for_item(
+        &Ident::new("item", Span::call_site()),
+        item_ty,
+        &quote! { #it_name },
+        &quote! { #it_name . },
+        false,
+        attributes,
+        &mut item_size_code,
+        &mut TokenStream::new(),
+        &mut TokenStream::new(),
+        context_val,
+        prefixed_context_val,
+    )?;
+    Ok(stored_size(quote! {
+        #dotted iter().fold(0, #[allow(unused_variables)] |n, #it_name| n + #item_size_code 0)
+    }, attributes))
+}
 
-    if vec_u8 {
-        size_code.extend(quote! {
-            #ty_size + #prefixed_field_dotted len() +
-        });
-    } else {
-        generate_for_item(
-            &item_name,
-            vec_generic,
-            &quote! { # it_name },
-            &quote! { #it_name . },
-            false,
-            attributes,
-            &mut item_size_code,
-            &mut item_read_code,
-            &mut item_write_code,
-            context_val,
-            prefixed_context_val,
-        )?;
+/// Expression of the size stored for an actual `size` of a collection: counted in `size_unit`
+/// and adjusted by `size_adjust`
+fn stored_size(size: TokenStream, attributes: &Attributes) -> TokenStream {
+    let size = match attributes.size_unit {
+        Some(unit) => quote! { plod::size::in_units(#size, #unit)? },
+        None => size,
+    };
+    match attributes.size_adjust {
+        0 => size,
+        adjust => quote! { plod::size::adjusted(#size, #adjust)? },
+    }
+}
 
-        // it_name may or may not be used by item_size_code
-        size_code.extend(quote! {
-            #ty_size + #prefixed_field_dotted iter().fold(0, #[allow(unused_variables)] |n, #it_name| n + #item_size_code 0) +
-        });
+/// Expression of a `stored` size as a `size_ty`, returning an error of kind `InvalidInput` if it
+/// doesn't fit
+fn stored_size_as(stored: TokenStream, size_ty: &Ident) -> TokenStream {
+    quote! {
+        {
+            let stored = #stored;
+            if stored > #size_ty::MAX as usize {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Size {} does not fit in {}", stored, stringify!(#size_ty)),
+                ));
+            }
+            stored as #size_ty
+        }
     }
-    let (plus_one, minus_one) = if attributes.size_is_next {
-        (quote! { + 1 }, quote! { - 1 })
-    } else {
-        (quote! {}, quote! {})
+}
+
+/// Expression of the actual size of a collection from the `stored` one, the opposite of
+/// `stored_size`
+fn actual_size(stored: TokenStream, attributes: &Attributes) -> TokenStream {
+    let size = match attributes.size_adjust {
+        0 => stored,
+        adjust => quote! { plod::size::unadjusted(#stored, #adjust)? },
+    };
+    match attributes.size_unit {
+        Some(unit) => quote! { (#size).saturating_mul(#unit) },
+        None => size,
+    }
+}
+
+/// Generate code for an integer field storing the tag of a later `external_tag` enum, its value
+/// is ignored on write and replaced with the tag of the enum
+#[allow(clippy::too_many_arguments)]
+fn generate_for_tag(
+    field_ident: &Ident,
+    field_type: &Type,
+    target_type: &Type,
+    target_ref: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    prefixed_context_val: &TokenStream,
+) -> Result<()> {
+    // fields_attributes checked that the field is an integer
+    let ty = match resolve_std_types(field_type) {
+        Type::Path(type_path) => type_path.path.get_ident().unwrap().clone(),
+        _ => unreachable!(),
     };
+    let ty_size = primitive_size(&ty);
+    let (from_method, _) = primitive_function(attributes.endianness);
+    size_code.extend(quote! {
+        #ty_size +
+    });
     read_code.extend(quote! {
-        let mut buffer: [u8; #ty_size] = [0; #ty_size];
-        from.read_exact(&mut buffer)?;
+        let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+        let #field_ident = #ty::#from_method(buffer);
         _pos += #ty_size;
-        let mut size = #size_ty::#from_method(buffer) as usize #minus_one;
     });
-    if attributes.byte_sized {
-        write_code.extend(quote! {
-            let size = #prefixed_field_dotted iter().fold(0, #[allow(unused_variables)] |n, #it_name| n + #item_size_code 0);
-            let buffer: [u8; #ty_size] = (size as #size_ty #plus_one).#to_method();
-            to.write_all(&buffer)?;
-            _pos += #ty_size;
-        });
-    } else {
-        write_code.extend(quote! {
-            let size = #prefixed_field_dotted len();
-            let buffer: [u8; #ty_size] = (size as #size_ty #plus_one).#to_method();
-            to.write_all(&buffer)?;
-            _pos += #ty_size;
-        });
+    let prefixed_context = pass_context(target_type, prefixed_context_val);
+    write_code.extend(quote! {
+        plod::helpers::write_tag::<#target_type, _>(to, #target_ref, #ty_size, #prefixed_context, _pos)?;
+        _pos += #ty_size;
+    });
+    Ok(())
+}
+
+/// Generate code for an enum stored without its tag, which is stored by a previous field
+#[allow(clippy::too_many_arguments)]
+fn generate_for_external_tag(
+    field_ident: &Ident,
+    field_type: &Type,
+    source: &(Ident, Ident, Endianness),
+    prefixed_field_ref: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+    context_val: &TokenStream,
+    prefixed_context_val: &TokenStream,
+) -> Result<()> {
+    if !matches!(field_type, Type::Path(_)) {
+        return syn_error(field_type, "An external_tag field must be an enum implementing Plod");
     }
-    // Vec<u8> can be read and written all at once
-    if vec_u8 {
-        // byte size == count size for Vec<u8>
-        read_code.extend(quote! {
-            let mut #field_ident = vec![0_u8; size];
-            from.read_exact(&mut #field_ident)?;
-            _pos += size;
-        });
-        write_code.extend(quote! {
-            to.write_all(#prefixed_field_dotted as_slice())?;
-            _pos += size;
-        });
-    } else {
-        if attributes.byte_sized {
-            read_code.extend(quote! {
-                let mut #field_ident = Vec::new();
-                while size > 0 {
-                    #item_read_code
-                    let #it_name = &#item_name;
-                    size -= #item_size_code 0;
-                    #field_ident.push(item);
-                }
-            });
-        } else {
-            read_code.extend(quote! {
-                let mut #field_ident = Vec::new();
-                for _ in 0..size {
-                    #item_read_code
-                    #field_ident.push(#item_name);
-                }
-            });
+    let (tag_ident, tag_ty, endianness) = source;
+    let tag_size = primitive_size(tag_ty);
+    let (_, to_method) = primitive_function(*endianness);
+    size_code.extend(quote! {
+        <#field_type as plod::Plod>::size_at_rest(#prefixed_field_ref).saturating_sub(#tag_size) +
+    });
+    let (context, prefixed_context) = (nested_context(field_type, context_val, attributes), nested_context(field_type, prefixed_context_val, attributes));
+    let check = leading_tag_assert(field_type, &tag_size);
+    // the bytes of the tag are read again by the enum
+    read_code.extend(quote! {
+        const { #check };
+        let tag: [u8; #tag_size] = #tag_ident.#to_method();
+        let #field_ident: #field_type = plod::helpers::read_untagged(from, &tag, #context, _pos)?;
+        _pos += <#field_type as plod::Plod>::size_at_rest(&#field_ident).saturating_sub(#tag_size);
+    });
+    write_code.extend(quote! {
+        plod::helpers::write_untagged(to, #prefixed_field_ref, #tag_size, #prefixed_context, _pos)?;
+        _pos += <#field_type as plod::Plod>::size_at_rest(#prefixed_field_ref).saturating_sub(#tag_size);
+    });
+    Ok(())
+}
+
+/// Generate code for an integer field storing the size of a later collection
+fn generate_for_length(
+    field_ident: &Ident,
+    field_type: &Type,
+    length: &TokenStream,
+    attributes: &Attributes,
+    size_code: &mut TokenStream,
+    read_code: &mut TokenStream,
+    write_code: &mut TokenStream,
+) -> Result<()> {
+    let ty = match field_type {
+        Type::Path(type_path) => match type_path.path.get_ident() {
+            Some(ty) if primitive_type(ty) => ty,
+            _ => return syn_error(field_type, "len_of field must be an integer"),
+        },
+        _ => return syn_error(field_type, "len_of field must be an integer"),
+    };
+    let ty_size = primitive_size(ty);
+    let (from_method, to_method) = primitive_function(attributes.endianness);
+    size_code.extend(quote! {
+        #ty_size +
+    });
+    read_code.extend(quote! {
+        let buffer: [u8; #ty_size] = plod::helpers::read_bytes(from)?;
+        let #field_ident = #ty::#from_method(buffer);
+        _pos += #ty_size;
+    });
+    // the value of the field is ignored
+    write_code.extend(quote! {
+        let length = #length;
+        if length > #ty::MAX as usize {
+            return Err(std::io::Error::other(format!("Length {} does not fit in {}", length, stringify!(#ty))));
         }
-        write_code.extend(quote! {
-            for #it_name in #prefixed_field_dotted iter() {
-                #item_write_code
-            }
-        });
-    }
+        let buffer: [u8; #ty_size] = (length as #ty).#to_method();
+        to.write_all(&buffer)?;
+        _pos += #ty_size;
+    });
     Ok(())
 }
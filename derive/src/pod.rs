@@ -0,0 +1,169 @@
+//! Checks and generation of `plod::pod::Pod` implementations for `#[plod(pod_cast)]`
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::Result;
+use syn::spanned::Spanned;
+use proc_macro2::Ident;
+use syn::{Attribute, Data, DeriveInput, LitStr, Type};
+
+use crate::attributes::{fields_attributes, Attributes, Endianness};
+use crate::{primitive_size, primitive_type, resolve_std_types};
+
+/// Generate the `Pod` implementation after checking that the type qualifies
+pub fn pod_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let self_name = &input.ident;
+    let data = match &input.data {
+        Data::Struct(data) => data,
+        _ => {
+            return Err(syn::Error::new(
+                self_name.span(),
+                "pod_cast only works with structs",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "pod_cast doesn't support generic types",
+        ));
+    }
+    if !has_c_repr(input)? {
+        return Err(syn::Error::new(
+            self_name.span(),
+            "pod_cast needs #[repr(C)] or #[repr(transparent)]",
+        ));
+    }
+    if let Some(key) = unknown_key(&input.attrs, POD_KEYS)? {
+        return Err(syn::Error::new(
+            key.span(),
+            format!("pod_cast is not compatible with {}", key),
+        ));
+    }
+
+    let mut native = match attributes.endianness {
+        Endianness::Big => quote! { cfg!(target_endian = "big") },
+        Endianness::Little => quote! { cfg!(target_endian = "little") },
+        Endianness::Native => quote! { true },
+    };
+    let mut sizes = Vec::new();
+    let all_attributes = fields_attributes(&data.fields, attributes)?;
+    for (field, field_attributes) in data.fields.iter().zip(all_attributes) {
+        if let Some(key) = unknown_key(&field.attrs, POD_FIELD_KEYS)? {
+            return Err(syn::Error::new(
+                key.span(),
+                format!("pod_cast fields are not compatible with {}", key),
+            ));
+        }
+        if field_attributes.endianness != attributes.endianness {
+            return Err(syn::Error::new(
+                field.span(),
+                "pod_cast fields are not compatible with another endianness",
+            ));
+        }
+        let (size, field_native) = pod_field(&resolve_std_types(&field.ty))?;
+        sizes.push(size);
+        native.extend(quote! { && #field_native });
+    }
+
+    let message = LitStr::new(
+        &format!("{} has padding, it cannot be pod_cast", self_name),
+        self_name.span(),
+    );
+    Ok(quote! {
+        unsafe impl plod::pod::Pod for #self_name {
+            const NATIVE: bool = #native;
+        }
+        const _: () = assert!(std::mem::size_of::<#self_name>() == 0 #(+ #sizes)*, #message);
+    })
+}
+
+/// Keys allowed on a `pod_cast` struct, any other key may change the bytes at rest or what is done
+/// with the values, which a copy of the memory would skip
+const POD_KEYS: &[&str] = &[
+    "pod_cast", "big_endian", "little_endian", "native_endian", "no_pos", "context", "expected_size",
+    "arbitrary", "debug_derive", "constructor", "field_sizes",
+];
+
+/// Keys allowed on the fields of a `pod_cast` struct
+const POD_FIELD_KEYS: &[&str] = &["big_endian", "little_endian", "native_endian"];
+
+/// The first key of the `#[plod(...)]` attributes that is not in `allowed`
+fn unknown_key(attrs: &[Attribute], allowed: &[&str]) -> Result<Option<Ident>> {
+    let mut unknown = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("plod")) {
+        attr.parse_nested_meta(|meta| {
+            match meta.path.get_ident() {
+                Some(ident) if allowed.iter().any(|key| ident == key) => (),
+                _ => {
+                    if unknown.is_none() {
+                        unknown = meta.path.get_ident().cloned();
+                    }
+                }
+            }
+            // skip the value, only keys matter
+            while !meta.input.is_empty() && !meta.input.peek(syn::Token![,]) {
+                meta.input.parse::<proc_macro2::TokenTree>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(unknown)
+}
+
+/// Is the struct `#[repr(C)]` or `#[repr(transparent)]`
+fn has_c_repr(input: &DeriveInput) -> Result<bool> {
+    let mut found = false;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+                found = true;
+            }
+            // skip arguments like align(N)
+            if meta.input.peek(syn::token::Paren) {
+                let _content;
+                syn::parenthesized!(_content in meta.input);
+            }
+            Ok(())
+        })?;
+    }
+    Ok(found)
+}
+
+/// Size at rest of a field and whether its byte order is the target one
+fn pod_field(ty: &Type) -> Result<(TokenStream, TokenStream)> {
+    match ty {
+        Type::Path(type_path) => match type_path.path.get_ident() {
+            Some(ident) if primitive_type(ident) => {
+                let size = primitive_size(ident);
+                Ok((quote! { #size }, quote! { true }))
+            }
+            _ if type_path.qself.is_none()
+                && type_path
+                    .path
+                    .segments
+                    .iter()
+                    .all(|s| s.arguments.is_empty()) =>
+            {
+                Ok((
+                    quote! { std::mem::size_of::<#ty>() },
+                    quote! { <#ty as plod::pod::Pod>::NATIVE },
+                ))
+            }
+            _ => Err(pod_field_error(ty)),
+        },
+        Type::Array(array) => {
+            let (size, native) = pod_field(&array.elem)?;
+            let len = &array.len;
+            Ok((quote! { (#size) * (#len) }, native))
+        }
+        _ => Err(pod_field_error(ty)),
+    }
+}
+
+fn pod_field_error(ty: &Type) -> syn::Error {
+    syn::Error::new(
+        ty.span(),
+        "pod_cast fields must be numbers, arrays or other pod_cast types",
+    )
+}
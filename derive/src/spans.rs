@@ -0,0 +1,576 @@
+//! Generation of `Plod::impl_spans()`, the labeled byte ranges of a value at rest
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::parse::Result;
+use syn::{Data, DeriveInput, Fields, GenericArgument, PathArguments, Type};
+
+use crate::attributes::{fields_attributes, Attributes};
+use crate::{blob_as_vec, box_item, bytes_or_text, is_blob, option_item, encoded_bytes, primitive_size, primitive_type, resolve_std_types, text_encoding, varint_flavor};
+
+/// Generate the `impl_spans` method for a given input type (struct or enum)
+pub fn spans_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let self_name = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let mut body = spans_magic(attributes);
+            let all_attributes = fields_attributes(&data.fields, attributes)?;
+            for (i, (field, field_attributes)) in data.fields.iter().zip(all_attributes).enumerate() {
+                let mut field_body = spans_pad_to_offset(&field_attributes);
+                field_body.extend(spans_reserved(&field_attributes));
+                // the kept magic is covered by the magic span
+                if !(field_attributes.skip
+                    || field_attributes.seek_to.is_some()
+                    || (i == 0 && attributes.keep_magic))
+                {
+                    let (access, name) = match &field.ident {
+                        Some(ident) => (quote! { (&self.#ident) }, ident.to_string()),
+                        None => {
+                            let index = syn::Index::from(i);
+                            (quote! { (&self.#index) }, i.to_string())
+                        }
+                    };
+                    // flattened fields are named as fields of this struct
+                    let path = if field_attributes.flatten {
+                        quote! { path.to_string() }
+                    } else {
+                        quote! { format!("{}.{}", path, #name) }
+                    };
+                    field_body.extend(spans_item(&field.ty, &field_attributes, &access, &path));
+                }
+                body.extend(cfg_block(&field_attributes, field_body));
+            }
+            body.extend(spans_trailer(attributes));
+            body
+        }
+        Data::Enum(data) => {
+            let tag_type = match &attributes.tag_type {
+                Some(ty) => ty,
+                None if !attributes.tag_fields.is_empty() => &input.ident,
+                None => return Ok(TokenStream::new()),
+            };
+            // the size of a non primitive tag depends on the variant
+            let composite = attributes.tag_fields.is_empty() && !primitive_type(tag_type);
+            let tag_size = if composite {
+                TokenStream::new()
+            } else if !attributes.tag_fields.is_empty() {
+                let size = attributes.tag_fields_size();
+                quote! { #size }
+            } else {
+                let size = primitive_size(tag_type);
+                quote! { #size }
+            };
+            let mut arms = TokenStream::new();
+            for variant in data.variants.iter() {
+                let variant_attributes = attributes.variant(variant)?;
+                let ident = &variant.ident;
+                let name = ident.to_string();
+                if variant_attributes.skip {
+                    let fields = match variant.fields {
+                        Fields::Unit => TokenStream::new(),
+                        Fields::Named(_) => quote! { { .. } },
+                        Fields::Unnamed(_) => quote! { (..) },
+                    };
+                    arms.extend(quote! { #self_name::#ident #fields => {} });
+                    continue;
+                }
+                let mut code = spans_magic(&variant_attributes);
+                let mut common_code = TokenStream::new();
+                let mut bindings = Vec::new();
+                let all_attributes = fields_attributes(&variant.fields, &variant_attributes)?;
+                for (i, (field, field_attributes)) in
+                    variant.fields.iter().zip(all_attributes).enumerate()
+                {
+                    let mut field_code = spans_reserved(&field_attributes);
+                    let (binding, field_name) = match &field.ident {
+                        Some(ident) => (ident.clone(), ident.to_string()),
+                        None => (
+                            Ident::new(&format!("field_{}", i), Span::call_site()),
+                            i.to_string(),
+                        ),
+                    };
+                    // common fields come before the tag and are named as fields of the enum
+                    if field_attributes.common {
+                        common_code.extend(spans_item(
+                            &field.ty,
+                            &field_attributes,
+                            &quote! { #binding },
+                            &quote! { format!("{}.{}", path, #field_name) },
+                        ));
+                        bindings.push(quote! { #binding });
+                        continue;
+                    }
+                    // the kept tag or magic is covered by its own span
+                    let kept = variant_attributes.kept_field() == Some(i);
+                    if kept && composite && variant_attributes.keep_tag {
+                        bindings.push(match &field.ident {
+                            Some(ident) => quote! { #ident: kept_tag },
+                            None => quote! { kept_tag },
+                        });
+                        continue;
+                    }
+                    if field_attributes.skip || field_attributes.seek_to.is_some() || kept {
+                        if field.ident.is_none() {
+                            bindings.push(quote! { _ });
+                        }
+                        code.extend(cfg_block(&field_attributes, field_code));
+                        continue;
+                    }
+                    let path = if field_attributes.flatten {
+                        quote! { format!("{}::{}", path, #name) }
+                    } else {
+                        quote! { format!("{}::{}.{}", path, #name, #field_name) }
+                    };
+                    field_code.extend(spans_item(
+                        &field.ty,
+                        &field_attributes,
+                        &quote! { #binding },
+                        &path,
+                    ));
+                    code.extend(cfg_block(&field_attributes, field_code));
+                    let cfg = field_attributes.cfg_attr();
+                    bindings.push(quote! { #cfg #binding });
+                }
+                code.extend(spans_trailer(&variant_attributes));
+                let pattern = match variant.fields {
+                    Fields::Unit => TokenStream::new(),
+                    Fields::Named(_) => quote! { { #(#bindings,)* .. } },
+                    Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+                };
+                let tag_size = match &variant_attributes.tag {
+                    _ if !composite => tag_size.clone(),
+                    _ if variant_attributes.keep_tag => quote! { plod::Plod::size_at_rest(kept_tag) },
+                    Some(tag) => {
+                        let tag = crate::composite_tag(tag)?;
+                        quote! { <#tag_type as plod::Plod>::size_at_rest(&#tag) }
+                    }
+                    // reported by enum_impl
+                    None => return Ok(TokenStream::new()),
+                };
+                arms.extend(quote! {
+                    #self_name::#ident #pattern => {
+                        #common_code
+                        spans.push(plod::dump::Span::new(format!("{}.<tag>", path), _pos, #tag_size));
+                        _pos += #tag_size;
+                        #code
+                    }
+                });
+            }
+            match attributes.variant_size {
+                Some(variant_size) => quote! {
+                    let variant_end = _pos + #tag_size + #variant_size;
+                    match self {
+                        #arms
+                    }
+                    if _pos < variant_end {
+                        spans.push(plod::dump::Span::new(format!("{}.<padding>", path), _pos, variant_end - _pos));
+                        _pos = variant_end;
+                    }
+                },
+                None => quote! {
+                    match self {
+                        #arms
+                    }
+                },
+            }
+        }
+        // already reported by plod_impl
+        Data::Union(_) => return Ok(TokenStream::new()),
+    };
+    let body = match attributes.align_size {
+        Some(align) => quote! {
+            let align_start = _pos;
+            #body
+            let padding = (#align - (_pos - align_start) % #align) % #align;
+            if padding > 0 {
+                spans.push(plod::dump::Span::new(format!("{}.<padding>", path), _pos, padding));
+                _pos += padding;
+            }
+        },
+        None => body,
+    };
+    let body = match &attributes.length_footer {
+        Some((length_ty, checksum)) => {
+            let size = primitive_size(length_ty);
+            let checksum = match checksum {
+                Some((checksum_ty, _)) => {
+                    let size = primitive_size(checksum_ty);
+                    quote! {
+                        spans.push(plod::dump::Span::new(format!("{}.<checksum>", path), _pos, #size));
+                        _pos += #size;
+                    }
+                }
+                None => TokenStream::new(),
+            };
+            quote! {
+                #body
+                spans.push(plod::dump::Span::new(format!("{}.<len>", path), _pos, #size));
+                _pos += #size;
+                #checksum
+            }
+        }
+        None => body,
+    };
+    let body = match &attributes.length_prefix {
+        Some(length_ty) => {
+            let size = primitive_size(length_ty);
+            let trailing = match &attributes.preserve_unknown {
+                Some(holder) => quote! {
+                    let trailing = self.#holder.trailing().len();
+                    if trailing > 0 {
+                        spans.push(plod::dump::Span::new(format!("{}.<trailing>", path), _pos, trailing));
+                    }
+                },
+                None => TokenStream::new(),
+            };
+            quote! {
+                spans.push(plod::dump::Span::new(format!("{}.<len>", path), _pos, #size));
+                _pos += #size;
+                #body
+                #trailing
+            }
+        }
+        None => body,
+    };
+    Ok(quote! {
+        #[allow(unused_variables, unused_assignments)]
+        fn impl_spans(&self, path: &str, mut _pos: usize, spans: &mut Vec<plod::dump::Span>) {
+            #body
+        }
+    })
+}
+
+/// Generate the inherent `field_sizes` method, which names fields with static strings
+pub fn field_sizes_impl(input: &DeriveInput) -> TokenStream {
+    let field_names = |fields: &Fields, prefix: &str| -> Vec<String> {
+        fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| match &f.ident {
+                Some(ident) => format!("{}{}", prefix, ident),
+                None => format!("{}{}", prefix, i),
+            })
+            .collect()
+    };
+    let names = match &input.data {
+        Data::Struct(data) => field_names(&data.fields, ""),
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|v| field_names(&v.fields, &format!("{}.", v.ident)))
+            .collect(),
+        Data::Union(_) => Vec::new(),
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Bytes taken by each field at rest, in order, see `plod::dump::field_sizes`
+            pub fn field_sizes(&self) -> Vec<(&'static str, usize)> {
+                plod::dump::static_field_sizes(self, &[#(#names),*])
+            }
+        }
+    }
+}
+
+/// Spans of a field that may not exist are only computed when it does
+fn cfg_block(attributes: &Attributes, code: TokenStream) -> TokenStream {
+    match attributes.cfg {
+        Some(_) => {
+            let cfg = attributes.cfg_attr();
+            quote! { #cfg { #code } }
+        }
+        None => code,
+    }
+}
+
+fn spans_magic(attributes: &Attributes) -> TokenStream {
+    match &attributes.magic {
+        Some((ty, _)) => {
+            let size = primitive_size(ty);
+            quote! {
+                spans.push(plod::dump::Span::new(format!("{}.<magic>", path), _pos, #size));
+                _pos += #size;
+            }
+        }
+        None => TokenStream::new(),
+    }
+}
+
+fn spans_trailer(attributes: &Attributes) -> TokenStream {
+    match &attributes.trailer_magic {
+        Some((ty, _)) => {
+            let size = primitive_size(ty);
+            quote! {
+                spans.push(plod::dump::Span::new(format!("{}.<trailer>", path), _pos, #size));
+                _pos += #size;
+            }
+        }
+        None => TokenStream::new(),
+    }
+}
+
+fn spans_pad_to_offset(attributes: &Attributes) -> TokenStream {
+    let offset = match &attributes.pad_to_offset {
+        Some(offset) => offset,
+        None => return TokenStream::new(),
+    };
+    quote! {
+        let offset = (#offset) as usize;
+        if offset > _pos {
+            spans.push(plod::dump::Span::new(format!("{}.<padding>", path), _pos, offset - _pos));
+            _pos = offset;
+        }
+    }
+}
+
+fn spans_reserved(attributes: &Attributes) -> TokenStream {
+    let reserved = attributes.reserved;
+    if reserved == 0 {
+        return TokenStream::new();
+    }
+    quote! {
+        spans.push(plod::dump::Span::new(format!("{}.<reserved>", path), _pos, #reserved));
+        _pos += #reserved;
+    }
+}
+
+/// Generate spans of a single item, `access` is a reference to the item and `path` an
+/// expression producing its path
+fn spans_item(
+    ty: &Type,
+    attributes: &Attributes,
+    access: &TokenStream,
+    path: &TokenStream,
+) -> TokenStream {
+    // a blob or a Cow has the same len() and is_empty() as a Vec<u8>
+    if is_blob(ty) || bytes_or_text(ty) == Some(false) {
+        return spans_item(&blob_as_vec(), attributes, access, path);
+    }
+    if bytes_or_text(ty) == Some(true) {
+        let encoding = text_encoding(attributes);
+        let bytes = quote! { (&*#encoding.encode(#access)) };
+        if !attributes.bstr {
+            return spans_item(&blob_as_vec(), attributes, &bytes, path);
+        }
+        let text = spans_item(&blob_as_vec(), attributes, &bytes, &quote! { item_path.clone() });
+        return quote! {
+            {
+                let item_path: String = #path;
+                #text
+                spans.push(plod::dump::Span::new(format!("{}.<nul>", item_path), _pos, 2));
+                _pos += 2;
+            }
+        };
+    }
+    if let Some(as_type) = &attributes.as_type {
+        let mut attributes = attributes.clone();
+        attributes.as_type = None;
+        let borrowed = quote! { (<#ty as std::borrow::Borrow<#as_type>>::borrow(#access)) };
+        return spans_item(as_type, &attributes, &borrowed, path);
+    }
+    let ty = &resolve_std_types(ty);
+    if let Some(inner_type) = match ty {
+        Type::Path(type_path) => box_item(type_path),
+        _ => None,
+    } {
+        return spans_item(inner_type, attributes, &quote! { (&**#access) }, path);
+    }
+    if attributes.if_remaining {
+        let mut inner = attributes.clone();
+        inner.if_remaining = false;
+        return match ty {
+            Type::Path(type_path) => match option_item(type_path) {
+                Some(item_ty) => {
+                    let item = spans_item(item_ty, &inner, &quote! { item }, path);
+                    quote! {
+                        if let Some(item) = #access {
+                            #item
+                        }
+                    }
+                }
+                None => TokenStream::new(),
+            },
+            _ => TokenStream::new(),
+        };
+    }
+    let code = match ty {
+        // the tag of the enum is covered by its own field
+        _ if attributes.tag_source.is_some() => {
+            let tag_size = primitive_size(&attributes.tag_source.as_ref().unwrap().1);
+            quote! {
+                let size = plod::Plod::size_at_rest(#access).saturating_sub(#tag_size);
+                if size > 0 {
+                    spans.push(plod::dump::Span::new(item_path, _pos, size));
+                    _pos += size;
+                }
+            }
+        }
+        _ if attributes.rest => quote! {
+            if !#access.is_empty() {
+                spans.push(plod::dump::Span::new(item_path, _pos, #access.len()));
+                _pos += #access.len();
+            }
+        },
+        _ if attributes.bitmap => {
+            // the size is stored in a previous field or prefixes the packed bytes
+            let len = match (&attributes.length_field, &attributes.size_type) {
+                (Some(_), _) => TokenStream::new(),
+                (None, Some(size_ty)) => {
+                    let size = primitive_size(size_ty);
+                    quote! {
+                        spans.push(plod::dump::Span::new(format!("{}.<len>", item_path), _pos, #size));
+                        _pos += #size;
+                    }
+                }
+                (None, None) => return TokenStream::new(),
+            };
+            quote! {
+                #len
+                let packed_size = #access.len().div_ceil(8);
+                if packed_size > 0 {
+                    spans.push(plod::dump::Span::new(item_path, _pos, packed_size));
+                    _pos += packed_size;
+                }
+            }
+        }
+        _ if attributes.encoding.is_some() => {
+            let size = match &attributes.size_type {
+                Some(size_ty) => primitive_size(size_ty),
+                None => return TokenStream::new(),
+            };
+            let encoded = encoded_bytes(ty, access, attributes.encoding.as_ref().unwrap());
+            quote! {
+                spans.push(plod::dump::Span::new(format!("{}.<len>", item_path), _pos, #size));
+                _pos += #size;
+                let encoded_size = #encoded.len();
+                spans.push(plod::dump::Span::new(item_path, _pos, encoded_size));
+                _pos += encoded_size;
+            }
+        }
+        Type::Path(type_path) => {
+            let first = type_path.path.segments.first();
+            if let Some(segment) = first.filter(|s| s.ident == "Vec") {
+                let item_ty = match &segment.arguments {
+                    PathArguments::AngleBracketed(pa) => match pa.args.first() {
+                        Some(GenericArgument::Type(t)) => t,
+                        _ => return TokenStream::new(),
+                    },
+                    _ => return TokenStream::new(),
+                };
+                let size = match &attributes.size_type {
+                    Some(size_ty) => primitive_size(size_ty),
+                    None => return TokenStream::new(),
+                };
+                let items = if attributes.columnar {
+                    // items are interleaved, they are a single span
+                    quote! {
+                        let size = #access.iter().map(plod::Plod::size_at_rest).sum::<usize>();
+                        if size > 0 {
+                            spans.push(plod::dump::Span::new(item_path.clone(), _pos, size));
+                            _pos += size;
+                        }
+                    }
+                } else {
+                    spans_items(item_ty, &attributes.vec_item(), access, attributes.item_align)
+                };
+                if attributes.length_field.is_some() {
+                    items
+                } else if attributes.size_after {
+                    quote! {
+                        #items
+                        spans.push(plod::dump::Span::new(format!("{}.<len>", item_path), _pos, #size));
+                        _pos += #size;
+                    }
+                } else {
+                    quote! {
+                        spans.push(plod::dump::Span::new(format!("{}.<len>", item_path), _pos, #size));
+                        _pos += #size;
+                        #items
+                    }
+                }
+            } else if let (Some(_), Some(flavor)) = (type_path.path.get_ident().filter(|i| primitive_type(i)), &attributes.varint) {
+                let varint = varint_flavor(flavor);
+                quote! {
+                    let size = #varint.size(*#access as u64);
+                    spans.push(plod::dump::Span::new(item_path, _pos, size));
+                    _pos += size;
+                }
+            } else if let Some(ident) = type_path.path.get_ident().filter(|i| primitive_type(i)) {
+                let size = primitive_size(ident);
+                quote! {
+                    spans.push(plod::dump::Span::new(item_path, _pos, #size));
+                    _pos += #size;
+                }
+            } else {
+                quote! {
+                    <#type_path as plod::Plod>::impl_spans(#access, &item_path, _pos, spans);
+                    _pos += <#type_path as plod::Plod>::size_at_rest(#access);
+                }
+            }
+        }
+        Type::Tuple(t) => {
+            let mut code = TokenStream::new();
+            for (i, ty) in t.elems.iter().enumerate() {
+                let index = syn::Index::from(i);
+                let name = i.to_string();
+                code.extend(spans_item(
+                    ty,
+                    attributes,
+                    &quote! { (&#access.#index) },
+                    &quote! { format!("{}.{}", item_path, #name) },
+                ));
+            }
+            code
+        }
+        Type::Array(t) => {
+            spans_items(&t.elem, &attributes.array_item(), access, attributes.item_align)
+        }
+        _ => TokenStream::new(),
+    };
+    quote! {
+        {
+            let item_path: String = #path;
+            #code
+        }
+    }
+}
+
+/// Spans of the items of a collection, `Vec<u8>` and `[u8; N]` are a single span
+fn spans_items(
+    item_ty: &Type,
+    attributes: &Attributes,
+    access: &TokenStream,
+    item_align: Option<usize>,
+) -> TokenStream {
+    if let (Type::Path(type_path), None) = (item_ty, item_align) {
+        if type_path.path.is_ident("u8") {
+            return quote! {
+                if !#access.is_empty() {
+                    spans.push(plod::dump::Span::new(item_path.clone(), _pos, #access.len()));
+                    _pos += #access.len();
+                }
+            };
+        }
+    }
+    let item = spans_item(
+        item_ty,
+        attributes,
+        &quote! { it },
+        &quote! { format!("{}[{}]", item_path, i) },
+    );
+    let (start, padding) = match item_align {
+        Some(align) => (
+            quote! { let start = _pos; },
+            quote! { _pos += plod::helpers::padding(_pos - start, #align); },
+        ),
+        None => Default::default(),
+    };
+    quote! {
+        #start
+        for (i, it) in #access.iter().enumerate() {
+            #padding
+            #item
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Generation of the `tag_name` lookup of `#[plod(tag_names)]` enums, which maps a tag to the name
+//! of the variant it selects, and of the tag constants of `#[plod(tag_consts)]` enums
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::Result;
+use syn::{Data, DeriveInput};
+
+use crate::attributes::{is_value, Attributes};
+use crate::{integer_type, syn_error};
+
+/// Tag type of an enum whose tags can be looked up, a single integer
+fn integer_tag_type<'a>(input: &DeriveInput, attributes: &'a Attributes, option: &str) -> Result<&'a syn::Ident> {
+    match &attributes.tag_type {
+        Some(ty) if attributes.tag_fields.is_empty() && integer_type(ty) => Ok(ty),
+        _ => syn_error(&input.ident, &format!("{} needs an integer tag_type", option)),
+    }
+}
+
+/// Generate an inherent `tag_name` function for an enum with an integer tag
+pub fn tag_names_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => return syn_error(&input.ident, "tag_names only works with enums"),
+    };
+    let tag_type = integer_tag_type(input, attributes, "tag_names")?;
+    let mut arms = TokenStream::new();
+    let mut catch_all = false;
+    for variant in data.variants.iter() {
+        let variant_attributes = attributes.variant(variant)?;
+        // skipped variants are never read
+        if variant_attributes.skip {
+            continue;
+        }
+        let name = variant.ident.to_string();
+        match &variant_attributes.tag_pattern() {
+            Some(tag) => arms.extend(quote! { #tag => Some(#name), }),
+            None => {
+                arms.extend(quote! { _ => Some(#name), });
+                catch_all = true;
+            }
+        }
+    }
+    if !catch_all {
+        arms.extend(quote! { _ => None, });
+    }
+    let name = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let doc = format!(
+        "Name of the `{}` variant read for `tag`, `None` if no variant has this tag",
+        name
+    );
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc = #doc]
+            // variant patterns may overlap
+            #[allow(unreachable_patterns)]
+            #vis fn tag_name(tag: #tag_type) -> Option<&'static str> {
+                match tag {
+                    #arms
+                }
+            }
+        }
+    })
+}
+
+/// Name of the constant of the tag of a variant, eg: `DataChunk` -> `DATA_CHUNK_TAG`
+fn const_name(variant: &syn::Ident) -> syn::Ident {
+    let mut name = String::new();
+    let mut previous_lower = false;
+    for c in variant.to_string().trim_start_matches("r#").chars() {
+        if c.is_uppercase() && previous_lower {
+            name.push('_');
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        name.extend(c.to_uppercase());
+    }
+    format_ident!("{}_TAG", name)
+}
+
+/// Generate an associated constant with the tag of each variant that has a single tag value
+pub fn tag_consts_impl(input: &DeriveInput, attributes: &Attributes) -> Result<TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => return syn_error(&input.ident, "tag_consts only works with enums"),
+    };
+    let tag_type = integer_tag_type(input, attributes, "tag_consts")?;
+    let mut consts = TokenStream::new();
+    for variant in data.variants.iter() {
+        let variant_attributes = attributes.variant(variant)?;
+        if variant_attributes.skip {
+            continue;
+        }
+        // the written tag, patterns don't have a single value
+        let tag = match &variant_attributes.tag {
+            Some(syn::Pat::Or(or)) if attributes.dispatch_on_magic => &or.cases[0],
+            Some(tag) if is_value(tag) => tag,
+            _ => continue,
+        };
+        let const_name = const_name(&variant.ident);
+        let doc = format!("Tag of the `{}` variant", variant.ident);
+        consts.extend(quote! {
+            #[doc = #doc]
+            pub const #const_name: #tag_type = #tag;
+        });
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #consts
+        }
+    })
+}
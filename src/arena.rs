@@ -0,0 +1,61 @@
+//! Values borrowing from an arena
+//!
+//! Parsing millions of small records that are dropped together spends most of its time in the
+//! allocator. Fields of type `&'a [u8]` and `&'a str` are stored like `Vec<u8>` and `String`, but
+//! their bytes are read into memory obtained from the context, which must implement [`Arena`].
+//! A context holding an arena lives as long as the values read with it, so it is usually a
+//! reference, like `&'a bumpalo::Bump` with the `bumpalo` feature, or a struct containing one.
+//!
+//! A `&'a str` is always UTF-8 text, invalid text fails to read with an error of kind
+//! `InvalidData`.
+//!
+//! `Vec` and `String` fields are not read into the arena: on stable Rust they always use the
+//! global allocator, choosing another one needs the unstable `allocator_api`. Arena aware
+//! collections like `bumpalo::collections::Vec` are not supported either, as a field type they
+//! would tie every read to a single arena implementation. Bytes and text are what most small
+//! records hold, they are read into the arena when declared as `&'a [u8]` and `&'a str`.
+//!
+//! ```
+//! # #[cfg(feature = "bumpalo")] {
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! #[plod(context = &'a bumpalo::Bump, size_type(u8))]
+//! struct Entry<'a> {
+//!     name: &'a str,
+//!     data: &'a [u8],
+//! }
+//!
+//! let bump = bumpalo::Bump::new();
+//! let entry = Entry::read_with(&mut [2_u8, b'i', b'd', 1, 7].as_slice(), &&bump).unwrap();
+//! assert_eq!(entry, Entry { name: "id", data: &[7] });
+//! # }
+//! ```
+
+use std::io::{Error, ErrorKind};
+
+use crate::Result;
+
+/// Memory for the bytes of values read, that lives as long as the values
+pub trait Arena<'a> {
+    /// Allocate `len` bytes, their content is overwritten by the caller
+    fn alloc_bytes(&self, len: usize) -> &'a mut [u8];
+}
+
+#[cfg(feature = "bumpalo")]
+impl<'a> Arena<'a> for &'a bumpalo::Bump {
+    fn alloc_bytes(&self, len: usize) -> &'a mut [u8] {
+        self.alloc_slice_fill_copy(len, 0)
+    }
+}
+
+/// UTF-8 text of bytes read at `pos`, called by derived code
+#[doc(hidden)]
+pub fn text(bytes: &[u8], pos: usize) -> Result<&str> {
+    std::str::from_utf8(bytes).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid utf8_strict text at offset {}", pos + e.valid_up_to()),
+        )
+    })
+}
@@ -0,0 +1,96 @@
+//! `Plod` for arrays of types implementing it
+//!
+//! Derived types handle array fields themselves, this implementation is for manual
+//! implementations and generic code. Items are read and written one after the other with the
+//! context of the array.
+
+use std::io::{Read, Seek, Write};
+
+use crate::layout::Layout;
+use crate::{dump, Plod, Result};
+
+// the items are read in a Vec first since arrays cannot be built from a fallible iterator
+fn into_array<T, const N: usize>(items: Vec<T>) -> [T; N] {
+    match items.try_into() {
+        Ok(array) => array,
+        Err(_) => unreachable!("exactly N items have been read"),
+    }
+}
+
+impl<T: Plod, const N: usize> Plod for [T; N] {
+    type Context = T::Context;
+
+    const FIXED_SIZE: Option<usize> = match T::FIXED_SIZE {
+        Some(size) => Some(size * N),
+        None => None,
+    };
+
+    fn size_at_rest(&self) -> usize {
+        self.iter().map(Plod::size_at_rest).sum()
+    }
+
+    fn impl_read_from<R: Read>(from: &mut R, ctx: &Self::Context, mut pos: usize) -> Result<Self> {
+        let mut items = Vec::with_capacity(N);
+        for _ in 0..N {
+            let item = T::impl_read_from(from, ctx, pos)?;
+            pos += item.size_at_rest();
+            items.push(item);
+        }
+        Ok(into_array(items))
+    }
+
+    fn impl_read_from_seek<R: Read + Seek>(
+        from: &mut R,
+        ctx: &Self::Context,
+        mut pos: usize,
+    ) -> Result<Self> {
+        let mut items = Vec::with_capacity(N);
+        for _ in 0..N {
+            let item = T::impl_read_from_seek(from, ctx, pos)?;
+            pos += item.size_at_rest();
+            items.push(item);
+        }
+        Ok(into_array(items))
+    }
+
+    fn impl_write_to<W: Write>(
+        &self,
+        to: &mut W,
+        ctx: &Self::Context,
+        mut pos: usize,
+    ) -> Result<()> {
+        for item in self.iter() {
+            item.impl_write_to(to, ctx, pos)?;
+            pos += item.size_at_rest();
+        }
+        Ok(())
+    }
+
+    fn impl_write_to_seek<W: Write + Seek>(
+        &self,
+        to: &mut W,
+        ctx: &Self::Context,
+        mut pos: usize,
+    ) -> Result<()> {
+        for item in self.iter() {
+            item.impl_write_to_seek(to, ctx, pos)?;
+            pos += item.size_at_rest();
+        }
+        Ok(())
+    }
+
+    fn describe() -> Layout {
+        Layout::Array {
+            len: N,
+            item: Box::new(T::describe()),
+            item_align: None,
+        }
+    }
+
+    fn impl_spans(&self, path: &str, mut pos: usize, spans: &mut Vec<dump::Span>) {
+        for (i, item) in self.iter().enumerate() {
+            item.impl_spans(&format!("{}[{}]", path, i), pos, spans);
+            pos += item.size_at_rest();
+        }
+    }
+}
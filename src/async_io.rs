@@ -0,0 +1,123 @@
+//! `futures_io` adapters, available with the `futures-io` feature
+//!
+//! [`AsyncPlodReader`] reads values of derived types from any `futures_io::AsyncRead`, so they
+//! can be awaited with async-std, smol or any other runtime. As with the
+//! [`codec`](crate::codec) adapters, a value is complete once it can be read: the reader parses
+//! the bytes it has buffered and reads more from the stream when the parse stops at the end of the
+//! buffer. Collection lengths are checked against a maximum value length (see
+//! [`crate::fuzz::Limits`]) to avoid buffering and allocating for a hostile length prefix.
+//!
+//! [`write_to_async`] writes a value to any `futures_io::AsyncWrite`.
+
+use std::future::poll_fn;
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::fuzz::{with_limits, Limits};
+use crate::slice::SliceReader;
+use crate::{Plod, Result};
+
+/// Read values from an async byte stream
+pub struct AsyncPlodReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    max_length: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncPlodReader<R> {
+    /// Create a reader with the default maximum value length of [`Limits`]
+    pub fn new(inner: R) -> Self {
+        Self::with_max_length(inner, Limits::default().max_len)
+    }
+
+    /// Create a reader that fails when a value or one of its collections is longer than
+    /// `max_length` bytes
+    pub fn with_max_length(inner: R, max_length: usize) -> Self {
+        AsyncPlodReader {
+            inner,
+            buffer: Vec::new(),
+            max_length,
+        }
+    }
+
+    /// Bytes read from the stream that are not part of a value yet
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Get back the underlying stream, bytes in the buffer are lost
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read the next value, returns an error of kind `UnexpectedEof` if the stream ends first
+    pub async fn read<T: Plod>(&mut self) -> Result<T>
+    where
+        T::Context: Default,
+    {
+        let limits = Limits {
+            max_len: self.max_length,
+            ..Limits::default()
+        };
+        loop {
+            let mut reader = SliceReader::new(&self.buffer);
+            match with_limits(limits, || {
+                T::impl_read_from(&mut reader, &T::Context::default(), 0)
+            }) {
+                Ok(value) => {
+                    let size = reader.position();
+                    self.buffer.drain(..size);
+                    return Ok(value);
+                }
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    if self.buffer.len() > self.max_length {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("Value longer than {} bytes", self.max_length),
+                        ));
+                    }
+                    if self.fill().await? == 0 {
+                        return Err(e);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // read more bytes from the stream, returns 0 at the end of the stream
+    async fn fill(&mut self) -> Result<usize> {
+        let mut chunk = [0_u8; 4096];
+        let n = loop {
+            match poll_fn(|cx| Pin::new(&mut self.inner).poll_read(cx, &mut chunk)).await {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+        self.buffer.extend_from_slice(&chunk[..n]);
+        Ok(n)
+    }
+}
+
+/// Write a value to an async byte stream, the stream is not flushed
+pub async fn write_to_async<T: Plod, W: AsyncWrite + Unpin>(value: &T, writer: &mut W) -> Result<()>
+where
+    T::Context: Default,
+{
+    let mut data = Vec::with_capacity(value.size_at_rest());
+    value.write_to(&mut data)?;
+    let mut written = 0;
+    while written < data.len() {
+        let n = match poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, &data[written..])).await {
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            result => result?,
+        };
+        if n == 0 {
+            return Err(Error::from(ErrorKind::WriteZero));
+        }
+        written += n;
+    }
+    Ok(())
+}
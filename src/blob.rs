@@ -0,0 +1,209 @@
+//! Payloads streamed instead of buffered
+//!
+//! A [`Blob`] field is stored like a `Vec<u8>`, its size prefixed with the `size_type` of the
+//! field, but its content never has to fit in memory. Reading a blob skips its bytes and records
+//! where they are, they can be read later with [`Blob::open`]. A blob built with
+//! [`Blob::from_reader`] pulls its bytes from the reader when it is written. Headers around
+//! multi-gigabyte payloads can then stay derived. The field type must be spelled `plod::Blob`.
+//!
+//! ```
+//! use std::io::{Cursor, Read};
+//! use plod::{Blob, Plod};
+//!
+//! #[derive(Plod, Debug)]
+//! #[plod(big_endian)]
+//! struct Archive {
+//!     version: u8,
+//!     #[plod(size_type(u32))]
+//!     payload: plod::Blob,
+//!     crc: u16,
+//! }
+//!
+//! let archive = Archive {
+//!     version: 1,
+//!     payload: Blob::from_reader(Cursor::new(b"large content"), 13),
+//!     crc: 7,
+//! };
+//! let mut file = Cursor::new(Vec::new());
+//! archive.write_to(&mut file).unwrap();
+//!
+//! file.set_position(0);
+//! let archive = Archive::read_from_seek(&mut file).unwrap();
+//! assert_eq!(archive.crc, 7);
+//! assert_eq!((archive.payload.offset(), archive.payload.len()), (Some(5), 13));
+//! let mut content = String::new();
+//! archive.payload.open(&mut file, 0).unwrap().read_to_string(&mut content).unwrap();
+//! assert_eq!(content, "large content");
+//! ```
+//!
+//! With [`Plod::read_from_seek`](crate::Plod::read_from_seek) the content is sought over,
+//! other readers read it and drop it. The offset is relative to the start of the value read
+//! first.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Take, Write};
+
+use crate::Result;
+
+enum Content {
+    /// skipped on read, at this offset
+    Stored(usize),
+    /// pulled on write
+    Source(RefCell<Box<dyn Read>>),
+    /// held in memory
+    Bytes(Vec<u8>),
+}
+
+/// A size prefixed payload that is not held in memory
+pub struct Blob {
+    len: usize,
+    content: Content,
+}
+
+impl Blob {
+    /// A blob of `len` bytes read from `reader` when it is written, writing fails if the reader
+    /// ends before. The reader is consumed by the first write.
+    pub fn from_reader<R: Read + 'static>(reader: R, len: usize) -> Self {
+        Blob {
+            len,
+            content: Content::Source(RefCell::new(Box::new(reader))),
+        }
+    }
+
+    /// A blob of bytes in memory, for small payloads and tests
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Blob {
+            len: bytes.len(),
+            content: Content::Bytes(bytes),
+        }
+    }
+
+    /// Number of bytes of the content
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Tell if the content is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Offset of the content of a blob that was read, relative to the start of the value read
+    /// first, `None` for other blobs
+    pub fn offset(&self) -> Option<usize> {
+        match self.content {
+            Content::Stored(offset) => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// Content of a blob built from bytes
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match &self.content {
+            Content::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Reader of the content of a blob that was read, `from` is the reader the enclosing value
+    /// was read from and `start` the position where the value read first started
+    pub fn open<R: Read + Seek>(&self, mut from: R, start: u64) -> Result<Take<R>> {
+        let offset = match self.content {
+            Content::Stored(offset) => offset,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Only blobs that were read can be opened",
+                ))
+            }
+        };
+        from.seek(SeekFrom::Start(start + offset as u64))?;
+        Ok(from.take(self.len as u64))
+    }
+
+    /// Skip the content of a blob of `len` bytes at `pos`, called by derived code
+    #[doc(hidden)]
+    pub fn read_skipping<R: Read>(from: &mut R, pos: usize, len: usize) -> Result<Self> {
+        let skipped = std::io::copy(&mut from.take(len as u64), &mut std::io::sink())?;
+        if skipped < len as u64 {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+        Ok(Blob {
+            len,
+            content: Content::Stored(pos),
+        })
+    }
+
+    /// Seek over the content of a blob of `len` bytes at `pos`, called by derived code
+    #[doc(hidden)]
+    pub fn read_seeking<R: Read + Seek>(from: &mut R, pos: usize, len: usize) -> Result<Self> {
+        // seeking past the end succeeds, the content must be there
+        let start = from.stream_position()?;
+        let end = from.seek(SeekFrom::End(0))?;
+        if end.saturating_sub(start) < len as u64 {
+            from.seek(SeekFrom::Start(start))?;
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+        from.seek(SeekFrom::Start(start + len as u64))?;
+        Ok(Blob {
+            len,
+            content: Content::Stored(pos),
+        })
+    }
+
+    /// Write the content, called by derived code
+    #[doc(hidden)]
+    pub fn write_content<W: Write>(&self, to: &mut W) -> Result<()> {
+        match &self.content {
+            Content::Bytes(bytes) => to.write_all(bytes),
+            Content::Source(source) => {
+                let mut source = source.borrow_mut();
+                let copied = std::io::copy(&mut (&mut *source).take(self.len as u64), to)?;
+                if copied < self.len as u64 {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        format!("Blob source ended after {} of {} bytes", copied, self.len),
+                    ));
+                }
+                Ok(())
+            }
+            Content::Stored(_) => Err(Error::new(
+                ErrorKind::Unsupported,
+                "A blob that was read cannot be written, its content was skipped",
+            )),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Blob {
+    fn from(bytes: Vec<u8>) -> Self {
+        Blob::from_bytes(bytes)
+    }
+}
+
+impl fmt::Debug for Blob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Blob");
+        debug.field("len", &self.len);
+        match &self.content {
+            Content::Stored(offset) => debug.field("offset", offset),
+            Content::Source(_) => debug.field("source", &"<reader>"),
+            Content::Bytes(bytes) => debug.field("bytes", bytes),
+        };
+        debug.finish()
+    }
+}
+
+/// Blobs are equal if they were read at the same place or hold the same bytes, blobs pulled
+/// from a reader are never equal
+impl PartialEq for Blob {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && match (&self.content, &other.content) {
+                (Content::Stored(a), Content::Stored(b)) => a == b,
+                (Content::Bytes(a), Content::Bytes(b)) => a == b,
+                _ => false,
+            }
+    }
+}
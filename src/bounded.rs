@@ -0,0 +1,85 @@
+//! Integers restricted to a range, checked when read
+//!
+//! A [`Bounded`] integer is stored like its integer, with the endianness of the field, and a
+//! derived type fails to read a value outside of `MIN..=MAX` with an error of kind `InvalidData`.
+//! The derive recognizes it by its path, which must start with `plod::` like for durations.
+//!
+//! ```
+//! use plod::{Bounded, Plod};
+//!
+//! #[derive(Plod, Debug)]
+//! #[plod(big_endian)]
+//! struct Date {
+//!     year: u16,
+//!     month: plod::Bounded<u8, 1, 12>,
+//!     day: plod::Bounded<u8, 1, 31>,
+//! }
+//!
+//! let date = Date::read_from(&mut [0x07, 0xe4, 5, 17].as_slice()).unwrap();
+//! assert_eq!(date.month.get(), 5);
+//! assert!(Date::read_from(&mut [0x07, 0xe4, 13, 17].as_slice()).is_err());
+//! assert!(Bounded::<u8, 1, 12>::try_from(0).is_err());
+//! ```
+
+use std::borrow::Borrow;
+use std::io::{Error, ErrorKind};
+
+use crate::Result;
+
+/// An integer of type `T` between `MIN` and `MAX` included, built with `TryFrom<T>`
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Bounded<T, const MIN: i128, const MAX: i128>(T);
+
+impl<T: Copy, const MIN: i128, const MAX: i128> Bounded<T, MIN, MAX> {
+    /// The integer value
+    pub fn get(&self) -> T {
+        self.0
+    }
+}
+
+impl<T, const MIN: i128, const MAX: i128> Borrow<T> for Bounded<T, MIN, MAX> {
+    fn borrow(&self) -> &T {
+        &self.0
+    }
+}
+
+macro_rules! bounded_integer {
+    ($($ty:ident)*) => {
+        $(
+            impl<const MIN: i128, const MAX: i128> Bounded<$ty, MIN, MAX> {
+                /// The closest value in range, panics if the range is empty or doesn't fit in the
+                /// integer type
+                pub fn saturating(value: $ty) -> Self {
+                    let value = i128::try_from(value).unwrap_or(i128::MAX).clamp(MIN, MAX);
+                    match $ty::try_from(value) {
+                        Ok(value) => Bounded(value),
+                        Err(_) => panic!("{}..={} doesn't fit in {}", MIN, MAX, stringify!($ty)),
+                    }
+                }
+            }
+
+            /// Returns an error of kind `InvalidInput` if the value is out of range
+            impl<const MIN: i128, const MAX: i128> TryFrom<$ty> for Bounded<$ty, MIN, MAX> {
+                type Error = Error;
+
+                fn try_from(value: $ty) -> Result<Self> {
+                    match i128::try_from(value) {
+                        Ok(v) if (MIN..=MAX).contains(&v) => Ok(Bounded(value)),
+                        _ => Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("{} is out of range {}..={}", value, MIN, MAX),
+                        )),
+                    }
+                }
+            }
+
+            impl<const MIN: i128, const MAX: i128> From<Bounded<$ty, MIN, MAX>> for $ty {
+                fn from(value: Bounded<$ty, MIN, MAX>) -> Self {
+                    value.0
+                }
+            }
+        )*
+    };
+}
+
+bounded_integer!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
@@ -0,0 +1,45 @@
+//! `Plod` for boxes of types implementing it
+//!
+//! Derived types handle `Box` fields themselves, this implementation is for manual
+//! implementations and generic code. A box is stored like its content.
+
+use std::io::{Read, Seek, Write};
+
+use crate::layout::Layout;
+use crate::{dump, Plod, Result};
+
+impl<T: Plod> Plod for Box<T> {
+    type Context = T::Context;
+
+    const LAYOUT_DOC: &'static str = T::LAYOUT_DOC;
+
+    const FIXED_SIZE: Option<usize> = T::FIXED_SIZE;
+
+    fn size_at_rest(&self) -> usize {
+        (**self).size_at_rest()
+    }
+
+    fn impl_read_from<R: Read>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self> {
+        T::impl_read_from(from, ctx, pos).map(Box::new)
+    }
+
+    fn impl_read_from_seek<R: Read + Seek>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self> {
+        T::impl_read_from_seek(from, ctx, pos).map(Box::new)
+    }
+
+    fn impl_write_to<W: Write>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+        (**self).impl_write_to(to, ctx, pos)
+    }
+
+    fn impl_write_to_seek<W: Write + Seek>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+        (**self).impl_write_to_seek(to, ctx, pos)
+    }
+
+    fn describe() -> Layout {
+        T::describe()
+    }
+
+    fn impl_spans(&self, path: &str, pos: usize, spans: &mut Vec<dump::Span>) {
+        (**self).impl_spans(path, pos, spans)
+    }
+}
@@ -0,0 +1,87 @@
+//! Cooperative cancellation of long reads
+//!
+//! While [`with_cancel`] runs, derived readers check a flag after each item of a `Vec` and after
+//! each record of [`iter`](crate::iter()), and stop with an error containing [`Cancelled`] once it
+//! is set. Another thread, a signal handler or a progress callback (see
+//! [`progress`](crate::progress)) sets the flag to interrupt a parse cleanly.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//! use std::sync::Arc;
+//!
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! struct Samples {
+//!     #[plod(size_type(u32))]
+//!     values: Vec<u16>,
+//! }
+//!
+//! let data = [2, 0, 0, 0, 1, 0, 2, 0];
+//! let flag = Arc::new(AtomicBool::new(true));
+//! match plod::cancel::with_cancel(flag, || Samples::read_from(&mut data.as_slice())) {
+//!     Err(error) => assert!(plod::cancel::is_cancelled(&error)),
+//!     Ok(_) => panic!("the read should have been cancelled"),
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::Result;
+
+/// Error payload of a cancelled read, available with `std::io::Error::get_ref` on errors of kind
+/// `Other`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Read cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+thread_local! {
+    static FLAG: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous flag even if the closure panics
+struct Restore(Option<Arc<AtomicBool>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        FLAG.with(|f| *f.borrow_mut() = previous);
+    }
+}
+
+/// Run `f` with reads of derived types on the current thread stopped as soon as `flag` is set
+pub fn with_cancel<T, F: FnOnce() -> T>(flag: Arc<AtomicBool>, f: F) -> T {
+    let _restore = Restore(FLAG.with(|f| f.borrow_mut().replace(flag)));
+    f()
+}
+
+/// Tell if an error comes from a cancelled read
+pub fn is_cancelled(error: &Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<Cancelled>())
+}
+
+/// Return an error if the current read was cancelled, called by derived code
+#[doc(hidden)]
+#[inline]
+pub fn check() -> Result<()> {
+    let cancelled = FLAG.with(|f| {
+        f.borrow()
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    });
+    if cancelled {
+        return Err(Error::other(Cancelled));
+    }
+    Ok(())
+}
@@ -0,0 +1,306 @@
+//! Checksum algorithms for checksum fields
+//!
+//! An integer field marked with `#[plod(checksum(<algorithm>))]` holds the checksum of the bytes
+//! of the fields that precede it in its struct or variant. The magic and the tag are not
+//! included. The value is computed when writing, whatever the field contains, and checked when
+//! reading, with an error of kind `InvalidData` on a mismatch. The algorithm is any expression
+//! implementing [`Checksum`], with the content of this module in scope. The checksum is
+//! truncated to the field type.
+//!
+//! Embedded and legacy formats rarely agree on the variant, so [`Crc`] takes its width,
+//! polynomial, initial value, reflection and final xor as parameters. The most common ones are
+//! available as constants.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod, PartialEq, Debug)]
+//! #[plod(big_endian)]
+//! struct Frame {
+//!     address: u8,
+//!     command: u16,
+//!     #[plod(checksum(Crc::CRC16_XMODEM))]
+//!     crc: u16,
+//!     #[plod(checksum(Fletcher16))]
+//!     fletcher: u16,
+//! }
+//!
+//! let frame = Frame { address: 1, command: 0x203, crc: 0, fletcher: 0 };
+//! let mut data = Vec::new();
+//! frame.write_to(&mut data).unwrap();
+//! let read = Frame::read_from(&mut data.as_slice()).unwrap();
+//! assert_eq!(read.crc, 0x6131);
+//!
+//! data[1] ^= 1;
+//! assert!(Frame::read_from(&mut data.as_slice()).is_err());
+//! ```
+//!
+//! Bytes are recorded in the order they are read or written. With `seek_to` fields, the bytes
+//! at the other offset are included where the field is, not where they are stored.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// A checksum algorithm
+pub trait Checksum {
+    /// Checksum of `data`
+    fn checksum(&self, data: &[u8]) -> u64;
+}
+
+/// A cyclic redundancy check of 8 to 64 bits, with its Rocksoft parameters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Crc {
+    width: u32,
+    polynomial: u64,
+    init: u64,
+    reflect: bool,
+    xor_out: u64,
+}
+
+impl Crc {
+    /// CRC-8 (SMBus)
+    pub const CRC8: Crc = Crc::new(8, 0x07);
+    /// CRC-8/MAXIM (1-Wire)
+    pub const CRC8_MAXIM: Crc = Crc::new(8, 0x31).reflected();
+    /// CRC-16/ARC (IBM)
+    pub const CRC16_ARC: Crc = Crc::new(16, 0x8005).reflected();
+    /// CRC-16/MODBUS
+    pub const CRC16_MODBUS: Crc = Crc::new(16, 0x8005).init(0xffff).reflected();
+    /// CRC-16/XMODEM
+    pub const CRC16_XMODEM: Crc = Crc::new(16, 0x1021);
+    /// CRC-16/CCITT-FALSE
+    pub const CRC16_CCITT_FALSE: Crc = Crc::new(16, 0x1021).init(0xffff);
+    /// CRC-16/KERMIT
+    pub const CRC16_KERMIT: Crc = Crc::new(16, 0x1021).reflected();
+    /// CRC-32 (ISO-HDLC, as used by zip, png and ethernet)
+    pub const CRC32: Crc = Crc::new(32, 0x04c1_1db7)
+        .init(0xffff_ffff)
+        .reflected()
+        .xor_out(0xffff_ffff);
+    /// CRC-32C (Castagnoli)
+    pub const CRC32C: Crc = Crc::new(32, 0x1edc_6f41)
+        .init(0xffff_ffff)
+        .reflected()
+        .xor_out(0xffff_ffff);
+    /// CRC-32/MPEG-2
+    pub const CRC32_MPEG2: Crc = Crc::new(32, 0x04c1_1db7).init(0xffff_ffff);
+
+    /// A CRC of `width` bits with the (non reflected) `polynomial`, an initial value and a final
+    /// xor of 0, without reflection
+    ///
+    /// Panics if `width` is not between 8 and 64.
+    pub const fn new(width: u32, polynomial: u64) -> Self {
+        assert!(
+            width >= 8 && width <= 64,
+            "CRC width must be between 8 and 64"
+        );
+        Crc {
+            width,
+            polynomial,
+            init: 0,
+            reflect: false,
+            xor_out: 0,
+        }
+    }
+
+    /// Start with this value
+    pub const fn init(self, init: u64) -> Self {
+        Crc { init, ..self }
+    }
+
+    /// Process bytes least significant bit first, and reflect the result
+    pub const fn reflected(self) -> Self {
+        Crc {
+            reflect: true,
+            ..self
+        }
+    }
+
+    /// Xor the result with this value
+    pub const fn xor_out(self, xor_out: u64) -> Self {
+        Crc { xor_out, ..self }
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.width)
+    }
+}
+
+impl Checksum for Crc {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        let mask = self.mask();
+        let top = 1_u64 << (self.width - 1);
+        let mut crc = self.init & mask;
+        for &byte in data {
+            let byte = if self.reflect {
+                byte.reverse_bits()
+            } else {
+                byte
+            };
+            crc ^= (byte as u64) << (self.width - 8);
+            for _ in 0..8 {
+                crc = if crc & top != 0 {
+                    (crc << 1) ^ self.polynomial
+                } else {
+                    crc << 1
+                } & mask;
+            }
+        }
+        if self.reflect {
+            crc = crc.reverse_bits() >> (64 - self.width);
+        }
+        (crc ^ self.xor_out) & mask
+    }
+}
+
+/// Adler-32, as used by zlib
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Adler32;
+
+impl Checksum for Adler32 {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        let (mut a, mut b) = (1_u64, 0_u64);
+        for &byte in data {
+            a = (a + byte as u64) % 65521;
+            b = (b + a) % 65521;
+        }
+        (b << 16) | a
+    }
+}
+
+/// Fletcher-16, over bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fletcher16;
+
+impl Checksum for Fletcher16 {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        let (mut a, mut b) = (0_u64, 0_u64);
+        for &byte in data {
+            a = (a + byte as u64) % 255;
+            b = (b + a) % 255;
+        }
+        (b << 8) | a
+    }
+}
+
+/// Fletcher-32, over little endian 16 bit words, an odd last byte is padded with zero
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fletcher32;
+
+impl Checksum for Fletcher32 {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        let (mut a, mut b) = (0_u64, 0_u64);
+        for word in data.chunks(2) {
+            let word = word[0] as u64 | (*word.get(1).unwrap_or(&0) as u64) << 8;
+            a = (a + word) % 65535;
+            b = (b + a) % 65535;
+        }
+        (b << 16) | a
+    }
+}
+
+/// Sum of the bytes, wrapping at 8 bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sum8;
+
+impl Checksum for Sum8 {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        data.iter().fold(0_u8, |sum, &byte| sum.wrapping_add(byte)) as u64
+    }
+}
+
+/// Sum of the bytes, wrapping at 16 bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sum16;
+
+impl Checksum for Sum16 {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        data.iter()
+            .fold(0_u16, |sum, &byte| sum.wrapping_add(byte as u16)) as u64
+    }
+}
+
+/// Sum of the bytes, wrapping at 32 bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sum32;
+
+impl Checksum for Sum32 {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        data.iter()
+            .fold(0_u32, |sum, &byte| sum.wrapping_add(byte as u32)) as u64
+    }
+}
+
+/// Two's complement of the 8 bit sum, so that the sum of the bytes and the checksum is 0, as in
+/// Intel HEX
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TwosComplement8;
+
+impl Checksum for TwosComplement8 {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        (Sum8.checksum(data) as u8).wrapping_neg() as u64
+    }
+}
+
+/// Xor of the bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Xor8;
+
+impl Checksum for Xor8 {
+    fn checksum(&self, data: &[u8]) -> u64 {
+        data.iter().fold(0, |xor, &byte| xor ^ byte) as u64
+    }
+}
+
+/// A reader or writer keeping a copy of the bytes that go through it, used by derived code for
+/// checksum fields
+#[derive(Debug)]
+pub struct Recorder<T> {
+    inner: T,
+    recorded: Vec<u8>,
+}
+
+impl<T> Recorder<T> {
+    /// Record the bytes read from or written to `inner`
+    pub fn new(inner: T) -> Self {
+        Recorder {
+            inner,
+            recorded: Vec::new(),
+        }
+    }
+
+    /// Bytes read or written so far
+    pub fn recorded(&self) -> &[u8] {
+        &self.recorded
+    }
+
+    /// Get back the inner reader or writer
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: Read> Read for Recorder<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.recorded.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+}
+
+impl<T: Write> Write for Recorder<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let size = self.inner.write(buf)?;
+        self.recorded.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<T: Seek> Seek for Recorder<T> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
@@ -0,0 +1,163 @@
+//! `tokio_util` codec adapters, available with the `codec` feature
+//!
+//! Derived types know their own framing: a value is complete once it can be read. So
+//! [`PlodDecoder`] simply tries to read a value from the buffered bytes and waits for more data
+//! when the read stops at the end of the buffer. [`PlodCodec`] combines the decoder and the
+//! encoder to be used with `tokio_util::codec::Framed`.
+//!
+//! Since a frame is parsed again each time new bytes arrive, collection lengths are checked
+//! against a maximum frame length (see [`crate::fuzz::Limits`]) to avoid buffering and allocating
+//! for a hostile length prefix.
+
+use std::io::{Error, ErrorKind};
+use std::marker::PhantomData;
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::fuzz::{with_limits, Limits};
+use crate::slice::SliceReader;
+use crate::Plod;
+
+/// Decode values of type `T` from a byte stream
+pub struct PlodDecoder<T> {
+    max_length: usize,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> PlodDecoder<T> {
+    /// Create a decoder with the default maximum frame length of [`Limits`]
+    pub fn new() -> Self {
+        Self::with_max_length(Limits::default().max_len)
+    }
+
+    /// Create a decoder that fails when a frame or one of its collections is longer than
+    /// `max_length` bytes
+    pub fn with_max_length(max_length: usize) -> Self {
+        PlodDecoder {
+            max_length,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for PlodDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Plod> Decoder for PlodDecoder<T>
+where
+    T::Context: Default,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, Error> {
+        let limits = Limits {
+            max_len: self.max_length,
+            ..Limits::default()
+        };
+        let mut reader = SliceReader::new(src);
+        match with_limits(limits, || {
+            T::impl_read_from(&mut reader, &T::Context::default(), 0)
+        }) {
+            Ok(value) => {
+                let size = reader.position();
+                src.advance(size);
+                Ok(Some(value))
+            }
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                if src.len() > self.max_length {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Frame longer than {} bytes", self.max_length),
+                    ));
+                }
+                src.reserve(1);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Encode values of type `T` to a byte stream
+pub struct PlodEncoder<T> {
+    phantom: PhantomData<fn(T)>,
+}
+
+impl<T> PlodEncoder<T> {
+    /// Create an encoder
+    pub fn new() -> Self {
+        PlodEncoder {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for PlodEncoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Plod> Encoder<T> for PlodEncoder<T>
+where
+    T::Context: Default,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Error> {
+        dst.reserve(item.size_at_rest());
+        item.write_to_buf(dst)
+    }
+}
+
+/// Both a [`PlodDecoder`] and a [`PlodEncoder`], for `Framed` streams
+pub struct PlodCodec<T> {
+    /// decoding half
+    pub decoder: PlodDecoder<T>,
+    /// encoding half
+    pub encoder: PlodEncoder<T>,
+}
+
+impl<T> PlodCodec<T> {
+    /// Create a codec with the default maximum frame length
+    pub fn new() -> Self {
+        PlodCodec {
+            decoder: PlodDecoder::new(),
+            encoder: PlodEncoder::new(),
+        }
+    }
+}
+
+impl<T> Default for PlodCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Plod> Decoder for PlodCodec<T>
+where
+    T::Context: Default,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, Error> {
+        self.decoder.decode(src)
+    }
+}
+
+impl<T: Plod> Encoder<T> for PlodCodec<T>
+where
+    T::Context: Default,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Error> {
+        self.encoder.encode(item, dst)
+    }
+}
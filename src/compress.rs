@@ -0,0 +1,175 @@
+//! Compressed regions, used by `#[plod(compress = <algorithm>)]`
+//!
+//! Each algorithm is a module with a `compress` and a `decompress` function, it is available
+//! when the feature of the same name is enabled: `zlib`, `zstd` or `lz4` (frame format). A field
+//! compressed with an algorithm whose feature is not enabled fails to compile with an error
+//! naming the feature.
+//!
+//! The last few compressions of each thread are kept with their input, so that computing the
+//! size at rest of a value and writing it don't compress the same bytes again.
+//!
+//! Decompression is bounded by the current [`Limits`](crate::fuzz::Limits) when they are active,
+//! so that a small hostile blob cannot expand to gigabytes.
+//!
+//! Compressing again the same data is not guaranteed to produce the bytes that were read, so
+//! [`check_read`](crate::fuzz::check_read) is not suitable for types with compressed fields.
+
+#[cfg(any(feature = "zlib", feature = "zstd", feature = "lz4"))]
+use std::cell::RefCell;
+#[cfg(any(feature = "zlib", feature = "zstd", feature = "lz4"))]
+use std::io::Read;
+use std::marker::PhantomData;
+
+#[cfg(any(feature = "zlib", feature = "zstd", feature = "lz4"))]
+use crate::Result;
+
+/// Number of compressions kept per thread
+#[cfg(any(feature = "zlib", feature = "zstd", feature = "lz4"))]
+const CACHED: usize = 4;
+
+/// A compression: algorithm, input and compressed bytes
+#[cfg(any(feature = "zlib", feature = "zstd", feature = "lz4"))]
+type Compression = (&'static str, Vec<u8>, Vec<u8>);
+
+#[cfg(any(feature = "zlib", feature = "zstd", feature = "lz4"))]
+thread_local! {
+    /// last compressions, most recent first
+    static LAST: RefCell<Vec<Compression>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Compress `data` with `compress` unless it is one of the last inputs of `algorithm`
+#[cfg(any(feature = "zlib", feature = "zstd", feature = "lz4"))]
+fn cached(algorithm: &'static str, data: &[u8], compress: impl FnOnce(&[u8]) -> Vec<u8>) -> Vec<u8> {
+    LAST.with(|last| {
+        let mut last = last.borrow_mut();
+        let position = last.iter().position(|(a, input, _)| *a == algorithm && input == data);
+        let entry = match position {
+            Some(i) => last.remove(i),
+            None => (algorithm, data.to_vec(), compress(data)),
+        };
+        let compressed = entry.2.clone();
+        last.insert(0, entry);
+        last.truncate(CACHED);
+        compressed
+    })
+}
+
+/// Checked at compile time by derived code, for an algorithm whose feature is enabled
+#[doc(hidden)]
+pub struct Enabled<T>(PhantomData<T>);
+
+impl<T> Enabled<T> {
+    pub const CHECK: () = ();
+}
+
+/// Module of an algorithm whose feature is not enabled, derived code using it fails to compile
+#[allow(unused_macros)]
+macro_rules! missing {
+    ($algorithm:ident, $message:literal) => {
+        #[doc(hidden)]
+        pub mod $algorithm {
+            use std::marker::PhantomData;
+
+            /// Checked at compile time by derived code
+            pub struct Feature<T>(PhantomData<T>);
+
+            impl<T> Feature<T> {
+                pub const CHECK: () = panic!($message);
+            }
+
+            pub fn compress(_data: &[u8]) -> Vec<u8> {
+                unreachable!($message)
+            }
+
+            pub fn decompress(_data: &[u8]) -> crate::Result<Vec<u8>> {
+                unreachable!($message)
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "zlib"))]
+missing!(zlib, "compress = zlib needs the zlib feature of plod");
+#[cfg(not(feature = "zstd"))]
+missing!(zstd, "compress = zstd needs the zstd feature of plod");
+#[cfg(not(feature = "lz4"))]
+missing!(lz4, "compress = lz4 needs the lz4 feature of plod");
+
+/// Read all decompressed bytes, within current limits
+#[cfg(any(feature = "zlib", feature = "zstd", feature = "lz4"))]
+fn read_limited<R: Read>(reader: R) -> Result<Vec<u8>> {
+    crate::fuzz::read_to_end(reader)
+}
+
+/// zlib (RFC 1950) compression
+#[cfg(feature = "zlib")]
+pub mod zlib {
+    use std::io::Write;
+
+    use crate::Result;
+
+    #[doc(hidden)]
+    pub use super::Enabled as Feature;
+
+    /// Compress `data` with the default compression level
+    pub fn compress(data: &[u8]) -> Vec<u8> {
+        super::cached("zlib", data, |data| {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            // writing to a Vec cannot fail
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        })
+    }
+
+    /// Decompress `data`
+    pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+        super::read_limited(flate2::read::ZlibDecoder::new(data))
+    }
+}
+
+/// zstd compression
+#[cfg(feature = "zstd")]
+pub mod zstd {
+    use crate::Result;
+
+    #[doc(hidden)]
+    pub use super::Enabled as Feature;
+
+    /// Compress `data` with the default compression level
+    pub fn compress(data: &[u8]) -> Vec<u8> {
+        // writing to a Vec cannot fail
+        super::cached("zstd", data, |data| ::zstd::stream::encode_all(data, 0).unwrap())
+    }
+
+    /// Decompress `data`
+    pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+        super::read_limited(::zstd::stream::Decoder::new(data)?)
+    }
+}
+
+/// lz4 compression, using the lz4 frame format
+#[cfg(feature = "lz4")]
+pub mod lz4 {
+    use std::io::Write;
+
+    use crate::Result;
+
+    #[doc(hidden)]
+    pub use super::Enabled as Feature;
+
+    /// Compress `data`
+    pub fn compress(data: &[u8]) -> Vec<u8> {
+        super::cached("lz4", data, |data| {
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+            // writing to a Vec cannot fail
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        })
+    }
+
+    /// Decompress `data`
+    pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+        super::read_limited(lz4_flex::frame::FrameDecoder::new(data))
+    }
+}
@@ -0,0 +1,133 @@
+//! Conversions between contexts
+//!
+//! The context of a value is passed to its derived fields as is if their context has the same
+//! type, as `&()` if they have no context, and with `into()` on `&Context` otherwise, so only field
+//! types with another context need a `From<&Outer> for &Inner` implementation. This also applies
+//! to the value of an `is_context` field, which can then be of any type, even a primitive.
+//! The [`context!`](crate::context!) macro generates these conversions: `()` for the unit
+//! conversion, needed by manual implementations, and `Inner: path.to.field` to borrow a field of
+//! the outer context.
+//!
+//! A field with `#[plod(try_context)]` gets instead an owned context built with `TryFrom<&Outer>`,
+//! for conversions that can fail like a lookup in a table. A failed conversion is an error of kind
+//! `InvalidData`, or the error itself when it is a `std::io::Error`.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Default)]
+//! struct Version {
+//!     minor: u8,
+//! }
+//!
+//! #[derive(Default)]
+//! struct Session {
+//!     version: Version,
+//! }
+//!
+//! plod::context!(Session => (), Version: version);
+//!
+//! #[derive(Plod)]
+//! #[plod(context = Version)]
+//! struct Payload {
+//!     size: u16,
+//! }
+//!
+//! #[derive(Plod)]
+//! #[plod(context = Session)]
+//! struct Message {
+//!     kind: u8,
+//!     payload: Payload,
+//! }
+//!
+//! let message = Message::read_from(&mut [1_u8, 2, 0].as_slice()).unwrap();
+//! assert_eq!(message.payload.size, 2);
+//! ```
+
+use std::marker::PhantomData;
+
+use crate::Result;
+
+/// Implement the conversions from `&Outer` to the contexts of its fields.
+///
+/// `plod::context!(Outer => (), Inner: inner, Deep: sub.deep)` implements
+/// `From<&Outer> for &()`, `From<&Outer> for &Inner` returning `&outer.inner` and
+/// `From<&Outer> for &Deep` returning `&outer.sub.deep`.
+#[macro_export]
+macro_rules! context {
+    (@impl $outer:ty;) => {};
+    (@impl $outer:ty; () $(, $($rest:tt)*)?) => {
+        impl<'a> ::std::convert::From<&'a $outer> for &'a () {
+            fn from(_: &'a $outer) -> Self {
+                &()
+            }
+        }
+        $crate::context!(@impl $outer; $($($rest)*)?);
+    };
+    (@impl $outer:ty; $inner:ty : $($field:tt).+ $(, $($rest:tt)*)?) => {
+        impl<'a> ::std::convert::From<&'a $outer> for &'a $inner {
+            fn from(outer: &'a $outer) -> Self {
+                &outer.$($field).+
+            }
+        }
+        $crate::context!(@impl $outer; $($($rest)*)?);
+    };
+    ($outer:ty => $($rest:tt)*) => {
+        $crate::context!(@impl $outer; $($rest)*);
+    };
+}
+
+/// Context of a nested type `C` borrowed from the context `S` of its container, used by the
+/// derive: [`Pass::get`] returns it as is when `C` is `S`, [`PassUnit`] returns `&()` when `C` is
+/// `()` and [`PassInto`] converts it with `into()` otherwise. The first one that applies is
+/// chosen at compile time, so only the other conversions need a `From` implementation.
+#[doc(hidden)]
+pub struct Pass<'a, C, S>(&'a S, PhantomData<C>);
+
+impl<'a, C, S> Pass<'a, C, S> {
+    pub fn new(ctx: &'a S) -> Self {
+        Pass(ctx, PhantomData)
+    }
+}
+
+impl<'a, C> Pass<'a, C, C> {
+    pub fn get(&self) -> &'a C {
+        self.0
+    }
+}
+
+#[doc(hidden)]
+pub trait PassUnit<'a> {
+    fn get(&self) -> &'a ();
+}
+
+impl<'a, S> PassUnit<'a> for Pass<'a, (), S> {
+    fn get(&self) -> &'a () {
+        &()
+    }
+}
+
+#[doc(hidden)]
+pub trait PassInto<'a, C> {
+    fn get(&self) -> &'a C;
+}
+
+impl<'a, C: 'a, S> PassInto<'a, C> for &Pass<'a, C, S>
+where
+    &'a S: Into<&'a C>,
+{
+    fn get(&self) -> &'a C {
+        self.0.into()
+    }
+}
+
+/// Context of a `try_context` field converted with `TryFrom` from the context `S` of its
+/// container, used by the derive
+#[doc(hidden)]
+pub fn try_context<'a, C, S>(ctx: &'a S) -> Result<C>
+where
+    C: TryFrom<&'a S>,
+    C::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    crate::helpers::constructed(C::try_from(ctx))
+}
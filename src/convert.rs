@@ -0,0 +1,31 @@
+//! Endianness conversion of serialized values
+//!
+//! The endianness of a derived type is fixed by its attributes. [`convert`] walks the
+//! [`Layout`] of a type instead of its generated code, so a value can be read with any byte order
+//! and written with another one without declaring the type twice.
+//! Every number is converted, including tags, magics and sizes. Fields marked with `seek_to`
+//! are not part of the layout so they are not supported, neither are compressed or transformed
+//! fields nor manual implementations of `Plod`, those return an error of kind `Unsupported`.
+
+use std::io::{Read, Write};
+
+use crate::layout::Endianness;
+use crate::walk::Walker;
+use crate::{Plod, Result};
+
+/// Read a value of type `T` whose numbers are stored with the `source` endianness and write it
+/// with the `target` endianness, whatever the endianness declared by `T`.
+pub fn convert<T: Plod, R: Read, W: Write>(
+    from: &mut R,
+    to: &mut W,
+    source: Endianness,
+    target: Endianness,
+) -> Result<()> {
+    let walker = Walker {
+        source: Some(source),
+        swap: source.resolve() != target.resolve(),
+        check_magic: false,
+    };
+    walker.walk(&T::describe(), from, to)?;
+    Ok(())
+}
@@ -0,0 +1,136 @@
+//! Incremental decoding without IO
+//!
+//! A [`Decoder`] is fed the bytes of a stream as they arrive, in chunks of any size, and produces
+//! values once they are complete. It never blocks and never reads by itself, so it can be driven
+//! by an event loop, a network stack or an interrupt handler that receives partial frames.
+//!
+//! As with the [`codec`](crate::codec) adapters, a value is complete once it can be read. The
+//! decoder buffers the bytes it is given and parses them again when enough bytes have arrived:
+//! a fixed size type waits for its size, other types wait for the bytes the previous parse
+//! stopped at. Collection lengths are checked against a maximum value length (see
+//! [`crate::fuzz::Limits`]) to avoid buffering and allocating for a hostile length prefix.
+//!
+//! ```
+//! use std::task::Poll;
+//! use plod::decoder::Decoder;
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! #[plod(big_endian)]
+//! struct Frame {
+//!     kind: u8,
+//!     #[plod(size_type(u16))]
+//!     payload: Vec<u8>,
+//! }
+//!
+//! let mut decoder = Decoder::<Frame>::new();
+//! assert!(decoder.push(&[1, 0]).is_pending());
+//! assert!(decoder.push(&[2, 0xaa]).is_pending());
+//! let frame = match decoder.push(&[0xbb, 2]) {
+//!     Poll::Ready(frame) => frame.unwrap(),
+//!     Poll::Pending => unreachable!(),
+//! };
+//! assert_eq!(frame, Frame { kind: 1, payload: vec![0xaa, 0xbb] });
+//! // the start of the next frame is kept
+//! assert_eq!(decoder.buffer(), &[2]);
+//! ```
+
+use std::io::{Error, ErrorKind};
+use std::marker::PhantomData;
+use std::task::Poll;
+
+use crate::fuzz::{with_limits, Limits};
+use crate::slice::{OutOfBounds, SliceReader};
+use crate::{Plod, Result};
+
+/// Decode values of type `T` from bytes pushed as they arrive
+pub struct Decoder<T> {
+    buffer: Vec<u8>,
+    /// the buffer is not parsed before it has this many bytes
+    wanted: usize,
+    max_length: usize,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Decoder<T> {
+    /// Create a decoder with the default maximum value length of [`Limits`]
+    pub fn new() -> Self {
+        Self::with_max_length(Limits::default().max_len)
+    }
+
+    /// Create a decoder that fails when a value or one of its collections is longer than
+    /// `max_length` bytes
+    pub fn with_max_length(max_length: usize) -> Self {
+        Decoder {
+            buffer: Vec::new(),
+            wanted: 0,
+            max_length,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Bytes pushed that are not part of a value yet
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Tell if no byte is waiting, ie the stream can end here
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+}
+
+impl<T> Default for Decoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Plod> Decoder<T>
+where
+    T::Context: Default,
+{
+    /// Add bytes and decode the next value if it is complete. When several values are complete,
+    /// the next ones are decoded by pushing an empty chunk. After an error, the buffer is left as
+    /// is and the decoder should not be used anymore.
+    pub fn push(&mut self, chunk: &[u8]) -> Poll<Result<T>> {
+        self.buffer.extend_from_slice(chunk);
+        let wanted = match T::FIXED_SIZE {
+            Some(size) => size.max(self.wanted),
+            None => self.wanted,
+        };
+        if self.buffer.len() < wanted || self.buffer.is_empty() {
+            return Poll::Pending;
+        }
+        let limits = Limits {
+            max_len: self.max_length,
+            ..Limits::default()
+        };
+        let mut reader = SliceReader::new(&self.buffer);
+        match with_limits(limits, || {
+            T::impl_read_from(&mut reader, &T::Context::default(), 0)
+        }) {
+            Ok(value) => {
+                let size = reader.position();
+                self.buffer.drain(..size);
+                self.wanted = 0;
+                Poll::Ready(Ok(value))
+            }
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                if self.buffer.len() > self.max_length {
+                    return Poll::Ready(Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Value longer than {} bytes", self.max_length),
+                    )));
+                }
+                // the parse can only go further once the missing bytes are there
+                self.wanted = match e.get_ref().and_then(|p| p.downcast_ref::<OutOfBounds>()) {
+                    Some(missing) => missing.offset + missing.needed,
+                    None => self.buffer.len() + 1,
+                };
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
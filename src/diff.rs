@@ -0,0 +1,100 @@
+//! Field by field comparison of values at rest
+//!
+//! [`diff`] serializes two values and compares the bytes of each field, labeled with the same
+//! paths as [`dump`](crate::dump). This tells which field of an encoder output differs from a
+//! golden file instead of just the offset of the first different byte.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::dump::{spans, Span};
+use crate::{Plod, Result};
+
+/// A field whose bytes at rest differ between two values
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    /// path of the field, see [`Span`]
+    pub path: String,
+    /// position and bytes of the field in the first value, `None` if it doesn't have this field
+    pub left: Option<(usize, Vec<u8>)>,
+    /// position and bytes of the field in the second value, `None` if it doesn't have this field
+    pub right: Option<(usize, Vec<u8>)>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn side(side: &Option<(usize, Vec<u8>)>) -> String {
+            match side {
+                Some((start, bytes)) => format!("{:02x?} at {}", bytes, start),
+                None => "missing".to_string(),
+            }
+        }
+        write!(
+            f,
+            "{}: {} != {}",
+            self.path,
+            side(&self.left),
+            side(&self.right)
+        )
+    }
+}
+
+/// Compare two values field by field, the result lists the fields that differ in the order of
+/// the first value then those only present in the second one. It is empty when both values
+/// have the same representation at rest.
+pub fn diff<T: Plod>(left: &T, right: &T) -> Result<Vec<Difference>>
+where
+    T::Context: Default,
+{
+    let mut left_bytes = Vec::new();
+    left.write_to(&mut left_bytes)?;
+    let mut right_bytes = Vec::new();
+    right.write_to(&mut right_bytes)?;
+    Ok(diff_spans(
+        &left_bytes,
+        &spans(left),
+        &right_bytes,
+        &spans(right),
+    ))
+}
+
+/// Compare the fields of two serialized values given their spans
+pub fn diff_spans(
+    left_bytes: &[u8],
+    left_spans: &[Span],
+    right_bytes: &[u8],
+    right_spans: &[Span],
+) -> Vec<Difference> {
+    let field = |bytes: &[u8], span: &Span| {
+        let end = span.end().min(bytes.len());
+        (span.start, bytes[span.start.min(end)..end].to_vec())
+    };
+    let right_fields: HashMap<&str, &Span> =
+        right_spans.iter().map(|s| (s.path.as_str(), s)).collect();
+    let mut differences = Vec::new();
+    for span in left_spans {
+        let left = field(left_bytes, span);
+        let right = right_fields
+            .get(span.path.as_str())
+            .map(|s| field(right_bytes, s));
+        if right.as_ref().map(|(_, bytes)| bytes) != Some(&left.1) {
+            differences.push(Difference {
+                path: span.path.clone(),
+                left: Some(left),
+                right,
+            });
+        }
+    }
+    let left_fields: HashMap<&str, &Span> =
+        left_spans.iter().map(|s| (s.path.as_str(), s)).collect();
+    for span in right_spans {
+        if !left_fields.contains_key(span.path.as_str()) {
+            differences.push(Difference {
+                path: span.path.clone(),
+                left: None,
+                right: Some(field(right_bytes, span)),
+            });
+        }
+    }
+    differences
+}
@@ -0,0 +1,63 @@
+//! Content digest over the at-rest representation
+//!
+//! [`digest()`] hashes the bytes a value would be written as, streaming them into the hasher
+//! instead of materializing a buffer. Two values with the same representation at rest have the
+//! same digest, whatever their in-memory differences (padding, ignored fields, etc.), which makes
+//! it usable as a content address or a cache key. Any [`Digest`] implementation can be used.
+
+use std::io::Write;
+
+use ::digest::{Digest, Output};
+
+use crate::{Plod, Result};
+
+/// A writer feeding the bytes written to a hasher
+#[derive(Debug, Default)]
+pub struct DigestWriter<D> {
+    hasher: D,
+}
+
+impl<D: Digest> DigestWriter<D> {
+    /// A writer feeding a new hasher
+    pub fn new() -> Self {
+        DigestWriter { hasher: D::new() }
+    }
+
+    /// Feed the bytes written to `hasher`
+    pub fn with_hasher(hasher: D) -> Self {
+        DigestWriter { hasher }
+    }
+
+    /// Get back the hasher
+    pub fn into_inner(self) -> D {
+        self.hasher
+    }
+
+    /// Digest of the bytes written so far
+    pub fn finalize(self) -> Output<D> {
+        self.hasher.finalize()
+    }
+}
+
+impl<D: Digest> Write for DigestWriter<D> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash the representation at rest of `value` with `D`
+///
+/// The error is the one of [`Plod::write_to`], nothing is written anywhere else.
+pub fn digest<T: Plod, D: Digest>(value: &T) -> Result<Output<D>>
+where
+    T::Context: Default,
+{
+    let mut writer = DigestWriter::<D>::new();
+    value.impl_write_to(&mut writer, &T::Context::default(), 0)?;
+    Ok(writer.finalize())
+}
@@ -0,0 +1,128 @@
+//! Annotated hexdump of values
+//!
+//! Each value can list the byte ranges it occupies at rest with [`Plod::impl_spans`], every range
+//! being labeled with the path of the field that produced it. This is what [`dump`] uses to
+//! explain a serialized value.
+
+use std::fmt::Write as _;
+
+use crate::layout::short_name;
+use crate::{Plod, Result};
+
+/// Number of bytes displayed per line of dump
+const BYTES_PER_LINE: usize = 16;
+
+/// A labeled range of bytes at rest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// path of the field, like `Header.records[2]::Point.x`,
+    /// pseudo fields `<magic>`, `<trailer>`, `<tag>`, `<checksum>`, `<reserved>`, `<padding>`, `<trailing>` (kept unknown bytes), `<nul>` (ending a `BSTR`) and `<len>` (of a collection, a length prefixed type or a footer)
+    /// are used for data that is not stored in a field
+    pub path: String,
+    /// position of the first byte, relative to the start of the top level value
+    pub start: usize,
+    /// number of bytes
+    pub size: usize,
+}
+
+impl Span {
+    /// Create a new span
+    pub fn new(path: String, start: usize, size: usize) -> Self {
+        Span { path, start, size }
+    }
+
+    /// Position after the last byte
+    pub fn end(&self) -> usize {
+        self.start + self.size
+    }
+}
+
+/// List the byte ranges of a value at rest, the root path is the name of the type
+pub fn spans<T: Plod>(value: &T) -> Vec<Span> {
+    let mut spans = Vec::new();
+    value.impl_spans(&short_name(std::any::type_name::<T>()), 0, &mut spans);
+    spans
+}
+
+/// Bytes taken by each top level field of a value, in order, computed from its spans. Bytes that
+/// are not stored in a field keep their pseudo field name, like `<magic>` or `<tag>`, so that the
+/// sizes add up to `size_at_rest()`. Fields of an enum are named after their variant, like
+/// `Data.value`.
+pub fn field_sizes<T: Plod>(value: &T) -> Vec<(String, usize)> {
+    let mut spans = Vec::new();
+    value.impl_spans("", 0, &mut spans);
+    let mut sizes: Vec<(String, usize)> = Vec::new();
+    for span in spans {
+        let name = top_level_field(&span.path);
+        match sizes.last_mut() {
+            Some((last, size)) if last == name => *size += span.size,
+            _ => sizes.push((name.to_string(), span.size)),
+        }
+    }
+    sizes
+}
+
+/// Same as [`field_sizes`] with the names taken from `names`, called by the method generated for
+/// `#[plod(field_sizes)]`. Names that are not in the list, like the fields of a flattened type,
+/// are reported as `<other>`.
+#[doc(hidden)]
+pub fn static_field_sizes<T: Plod>(value: &T, names: &[&'static str]) -> Vec<(&'static str, usize)> {
+    let known = |name: &str| names.iter().chain(PSEUDO_FIELDS).find(|n| **n == name).copied();
+    field_sizes(value)
+        .into_iter()
+        .map(|(name, size)| (known(&name).unwrap_or("<other>"), size))
+        .collect()
+}
+
+/// Names of the data that is not stored in a field
+const PSEUDO_FIELDS: &[&str] =
+    &["<magic>", "<trailer>", "<tag>", "<checksum>", "<reserved>", "<padding>", "<len>"];
+
+/// Top level field of a path relative to the value: `.records[2].x` is in `records` and
+/// `::Data.value.<len>` is in `Data.value`
+fn top_level_field(path: &str) -> &str {
+    let (rest, start) = match path.strip_prefix("::") {
+        // the variant name is part of the field name
+        Some(rest) => (rest, rest.find('.').map_or(rest.len(), |i| i + 1)),
+        None => (path.strip_prefix('.').unwrap_or(path), 0),
+    };
+    let end = rest[start..].find(['.', '[', ':']).map_or(rest.len(), |i| start + i);
+    &rest[..end]
+}
+
+/// Serialize a value and produce a hexdump where each line is labeled with the field that
+/// produced its bytes
+pub fn dump<T: Plod>(value: &T) -> Result<String>
+where
+    T::Context: Default,
+{
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes)?;
+    Ok(hexdump(&bytes, &spans(value)))
+}
+
+/// Produce a labeled hexdump of `bytes`, bytes not covered by a span are labeled `?`
+pub fn hexdump(bytes: &[u8], spans: &[Span]) -> String {
+    let mut out = String::new();
+    let mut pos = 0;
+    let mut spans = spans.iter().filter(|s| s.size > 0).peekable();
+    while pos < bytes.len() {
+        let (end, label) = match spans.peek() {
+            Some(span) if span.start <= pos => {
+                let span = spans.next().unwrap();
+                (span.end().min(bytes.len()), span.path.as_str())
+            }
+            Some(span) => (span.start.min(bytes.len()), "?"),
+            None => (bytes.len(), "?"),
+        };
+        for (i, line) in bytes[pos..end].chunks(BYTES_PER_LINE).enumerate() {
+            let hex: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+            let label = if i == 0 { label } else { "" };
+            let offset = pos + i * BYTES_PER_LINE;
+            let width = BYTES_PER_LINE * 3 - 1;
+            let _ = writeln!(out, "{:08x}  {:width$}  {}", offset, hex.join(" "), label);
+        }
+        pos = end.max(pos + 1);
+    }
+    out
+}
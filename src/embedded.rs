@@ -0,0 +1,85 @@
+//! `embedded_io` adapters, available with the `embedded-io` feature
+//!
+//! HALs and drivers often expose their buses and radios through the blocking
+//! `embedded_io::{Read, Write}` traits. [`EmbeddedReader`] and [`EmbeddedWriter`] present them as
+//! `std::io` streams so that register blobs and packets can be read and written with derived
+//! types directly. Errors are converted to `std::io::Error` with the matching `ErrorKind`.
+//!
+//! plod itself still needs `std`, this is meant for targets that have it, or for host side tools
+//! and tests sharing drivers with the firmware.
+
+use std::io::{Error, Read, Write};
+
+use crate::{Plod, Result};
+
+fn convert<E: embedded_io::Error>(error: E) -> Error {
+    Error::new(error.kind().into(), format!("{:?}", error))
+}
+
+/// A `std::io::Read` over an `embedded_io::Read`
+#[derive(Debug)]
+pub struct EmbeddedReader<R> {
+    inner: R,
+}
+
+impl<R: embedded_io::Read> EmbeddedReader<R> {
+    /// Wrap an `embedded_io` reader
+    pub fn new(inner: R) -> Self {
+        EmbeddedReader { inner }
+    }
+
+    /// Get back the underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: embedded_io::Read> Read for EmbeddedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf).map_err(convert)
+    }
+}
+
+/// A `std::io::Write` over an `embedded_io::Write`
+#[derive(Debug)]
+pub struct EmbeddedWriter<W> {
+    inner: W,
+}
+
+impl<W: embedded_io::Write> EmbeddedWriter<W> {
+    /// Wrap an `embedded_io` writer
+    pub fn new(inner: W) -> Self {
+        EmbeddedWriter { inner }
+    }
+
+    /// Get back the underlying writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: embedded_io::Write> Write for EmbeddedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.inner.write(buf).map_err(convert)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush().map_err(convert)
+    }
+}
+
+/// Read a value from an `embedded_io` reader
+pub fn read_from_embedded<T: Plod, R: embedded_io::Read>(reader: &mut R) -> Result<T>
+where
+    T::Context: Default,
+{
+    T::read_from(&mut EmbeddedReader::new(reader))
+}
+
+/// Write a value to an `embedded_io` writer, the writer is not flushed
+pub fn write_to_embedded<T: Plod, W: embedded_io::Write>(value: &T, writer: &mut W) -> Result<()>
+where
+    T::Context: Default,
+{
+    value.write_to(&mut EmbeddedWriter::new(writer))
+}
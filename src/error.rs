@@ -0,0 +1,99 @@
+//! Locating errors in nested values
+//!
+//! An error raised while reading or writing a field of a derived type is wrapped in a
+//! [`FieldError`] naming the field, and the types containing it add their own field on the way
+//! up. A truncated input then reports `reading Header.flags.mode: failed to fill whole buffer`
+//! instead of the bare io error. The `ErrorKind` of the original error is kept.
+//!
+//! Errors carrying a [`LimitExceeded`](crate::fuzz::LimitExceeded), an
+//! [`UnknownTag`](crate::lenient::UnknownTag), an [`OutOfBounds`](crate::slice::OutOfBounds) or
+//! [`Cancelled`](crate::cancel::Cancelled) are left untouched, so that their payload can still be
+//! reached with `std::io::Error::get_ref`.
+
+use std::fmt;
+use std::io::Error;
+
+use crate::cancel::Cancelled;
+use crate::fuzz::LimitExceeded;
+use crate::lenient::UnknownTag;
+use crate::slice::OutOfBounds;
+
+/// Error payload of a failed field read or write, available with `std::io::Error::get_ref` on
+/// errors of the same kind as the original one
+#[derive(Debug)]
+pub struct FieldError {
+    /// true for a read, false for a write
+    pub reading: bool,
+    /// name of the outermost type, followed by the fields down to the one that failed, enum
+    /// variants are named `Type::Variant`
+    pub path: Vec<&'static str>,
+    /// the original error
+    pub error: Error,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = if self.reading { "reading" } else { "writing" };
+        write!(f, "{} {}: {}", action, self.path.join("."), self.error)
+    }
+}
+
+impl std::error::Error for FieldError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Wrap an error raised while reading `field` of `type_name`, called by derived code
+#[doc(hidden)]
+pub fn reading(error: Error, type_name: &'static str, field: &'static str) -> Error {
+    in_field(error, true, type_name, field)
+}
+
+/// Wrap an error raised while writing `field` of `type_name`, called by derived code
+#[doc(hidden)]
+pub fn writing(error: Error, type_name: &'static str, field: &'static str) -> Error {
+    in_field(error, false, type_name, field)
+}
+
+fn in_field(error: Error, reading: bool, type_name: &'static str, field: &'static str) -> Error {
+    let kind = error.kind();
+    let nested = match error.get_ref() {
+        Some(p)
+            if p.is::<LimitExceeded>()
+                || p.is::<UnknownTag>()
+                || p.is::<OutOfBounds>()
+                || p.is::<Cancelled>() =>
+        {
+            return error
+        }
+        Some(p) => p.is::<FieldError>(),
+        None => false,
+    };
+    let field_error = if nested {
+        // checked above
+        let inner = *error.into_inner().unwrap().downcast::<FieldError>().unwrap();
+        if inner.reading == reading {
+            // the inner type name is replaced by the field holding it
+            let path = [type_name, field].into_iter().chain(inner.path.into_iter().skip(1));
+            FieldError {
+                reading,
+                path: path.collect(),
+                error: inner.error,
+            }
+        } else {
+            FieldError {
+                reading,
+                path: vec![type_name, field],
+                error: Error::new(kind, inner),
+            }
+        }
+    } else {
+        FieldError {
+            reading,
+            path: vec![type_name, field],
+            error,
+        }
+    };
+    Error::new(kind, field_error)
+}
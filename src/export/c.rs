@@ -0,0 +1,250 @@
+//! C99 header generation
+
+use std::collections::HashSet;
+use std::io::{Error, ErrorKind};
+
+use crate::layout::{aligned, snake_case, Endianness, Field, Layout, Magic, Primitive};
+use crate::{Plod, Result};
+
+/// Generate a C99 header with packed structs matching the at rest layout of a derived type,
+/// plus prototypes of read and write helpers for each struct.
+///
+/// Only fixed size layouts can be represented: an error of kind `Unsupported` is returned if the
+/// type contains a `Vec`, a type with a manual implementation or an enum whose variants don't
+/// all have the same size.
+/// Enums are represented as a tag followed by a union of their variants.
+/// Multibyte fields are annotated with their endianness, byte swapping is left to the helpers.
+pub fn c_header<T: Plod>() -> Result<String> {
+    let root = T::describe().resolve();
+    let root_id = match root.name() {
+        Some(name) => snake_case(name),
+        None => return unsupported("only structs and enums can be exported to C"),
+    };
+    let mut c = CHeader::default();
+    c.declare(&root)?;
+
+    let guard = format!("{}_H", root_id.to_uppercase());
+    let mut out = format!("#ifndef {}\n#define {}\n\n", guard, guard);
+    out.push_str("#include <stddef.h>\n#include <stdint.h>\n\n#pragma pack(push, 1)\n\n");
+    for declaration in c.declarations.iter() {
+        out.push_str(declaration);
+        out.push('\n');
+    }
+    out.push_str("#pragma pack(pop)\n\n");
+    for (id, size) in c.sizes.iter() {
+        out.push_str(&format!("#define {}_SIZE {}\n", id.to_uppercase(), size));
+        out.push_str(&format!(
+            "int {}_read(const uint8_t *buf, size_t len, struct {} *out);\n",
+            id, id
+        ));
+        out.push_str(&format!(
+            "int {}_write(const struct {} *in, uint8_t *buf, size_t len);\n\n",
+            id, id
+        ));
+    }
+    out.push_str(&format!("#endif /* {} */\n", guard));
+    Ok(out)
+}
+
+fn unsupported<T>(message: &str) -> Result<T> {
+    Err(Error::new(ErrorKind::Unsupported, message))
+}
+
+#[derive(Default)]
+struct CHeader {
+    /// type declarations, dependencies first
+    declarations: Vec<String>,
+    /// struct ids and their size at rest
+    sizes: Vec<(String, usize)>,
+    /// types already declared or being declared
+    done: HashSet<String>,
+}
+
+impl CHeader {
+    /// Declare a struct or an enum and all the types it depends on, return its C id
+    fn declare(&mut self, layout: &Layout) -> Result<String> {
+        let name = layout.name().unwrap_or("anonymous");
+        let id = snake_case(name);
+        if self.done.contains(&id) {
+            return Ok(id);
+        }
+        self.done.insert(id.clone());
+        let size = match layout.fixed_size() {
+            Some(size) => size,
+            None => return unsupported(&format!("{} doesn't have a fixed size", name)),
+        };
+        let mut body = String::new();
+        match layout {
+            Layout::Struct(s) => {
+                length_member(&s.length, &mut body);
+                magic_member("magic", &s.magic, "    ", &mut body);
+                self.members(s.stored_fields(), "    ", &mut body)?;
+                magic_member("trailer", &s.trailer, "    ", &mut body);
+            }
+            Layout::Enum(e) => {
+                length_member(&e.length, &mut body);
+                body.push_str(&member(&e.tag, "tag", "", "    "));
+                let mut variants = String::new();
+                let mut tags = String::new();
+                for variant in e.variants.iter().filter(|v| !v.skip) {
+                    let variant_id = snake_case(variant.name);
+                    let fields = variant.stored_fields();
+                    if fields.is_empty() && variant.magic.is_none() && variant.trailer.is_none() {
+                        continue;
+                    }
+                    variants.push_str("        struct {\n");
+                    magic_member("magic", &variant.magic, "            ", &mut variants);
+                    self.members(&fields, "            ", &mut variants)?;
+                    magic_member("trailer", &variant.trailer, "            ", &mut variants);
+                    variants.push_str(&format!("        }} {};\n", variant_id));
+                    if let Some(tag) = variant.tag.as_ref().and_then(|t| t.single()) {
+                        tags.push_str(&format!(
+                            "#define {}_{}_TAG {}\n",
+                            id.to_uppercase(),
+                            variant_id.to_uppercase(),
+                            tag
+                        ));
+                    }
+                }
+                if let Some(variant_size) = e.variant_size {
+                    // forces the size of the union
+                    variants.push_str(&format!("        uint8_t slot[{}];\n", variant_size));
+                }
+                if !variants.is_empty() {
+                    body.push_str(&format!("    union {{\n{}    }} body;\n", variants));
+                }
+                if !tags.is_empty() {
+                    self.declarations.push(tags);
+                }
+            }
+            _ => return unsupported(&format!("{} is not a struct or an enum", name)),
+        }
+        padding_member(layout, size, &mut body);
+        self.declarations
+            .push(format!("struct {} {{\n{}}};\n", id, body));
+        self.sizes.push((id.clone(), size));
+        Ok(id)
+    }
+
+    fn members(&mut self, fields: &[Field], indent: &str, out: &mut String) -> Result<()> {
+        for field in fields.iter() {
+            self.member(&snake_case(field.name), &field.layout, "", indent, out)?;
+        }
+        Ok(())
+    }
+
+    /// Produce a member declaration, `dims` are the array dimensions already found
+    fn member(
+        &mut self,
+        id: &str,
+        layout: &Layout,
+        dims: &str,
+        indent: &str,
+        out: &mut String,
+    ) -> Result<()> {
+        match layout {
+            Layout::Primitive(p) => out.push_str(&member(p, id, dims, indent)),
+            Layout::Tuple(items) => {
+                if !dims.is_empty() && !items.is_empty() {
+                    return unsupported("arrays of tuples cannot be represented in C");
+                }
+                for (i, item) in items.iter().enumerate() {
+                    self.member(&format!("{}_{}", id, i), item, dims, indent, out)?;
+                }
+            }
+            Layout::Array {
+                len,
+                item,
+                item_align,
+            } => {
+                let size = item.fixed_size().unwrap_or(0);
+                if aligned(size, *item_align) != size {
+                    return unsupported(&format!("{}: padded items cannot be represented in C", id));
+                }
+                self.member(id, item, &format!("{}[{}]", dims, len), indent, out)?
+            }
+            Layout::Struct(_) | Layout::Enum(_) | Layout::Type { .. } => {
+                let type_id = self.declare(&layout.resolve())?;
+                out.push_str(&format!("{}struct {} {}{};\n", indent, type_id, id, dims));
+            }
+            Layout::Vec { .. } => {
+                return unsupported(&format!("{}: Vec cannot be represented in C", id))
+            }
+            Layout::Rest => {
+                // a flexible array member, rest is always the last field
+                out.push_str(&format!("{}uint8_t {}{}[]; /* remaining bytes */\n", indent, id, dims))
+            }
+            Layout::IfRemaining(_) => {
+                return unsupported(&format!("{}: optional fields cannot be represented in C", id))
+            }
+            Layout::PadTo { offset, .. } => {
+                return unsupported(&format!(
+                    "{}: padding to offset {} cannot be represented in C",
+                    id, offset
+                ))
+            }
+            Layout::Encoded { encoding, .. } => {
+                return unsupported(&format!(
+                    "{}: {} encoded data cannot be represented in C",
+                    id, encoding
+                ))
+            }
+            Layout::Opaque { name } => {
+                return unsupported(&format!("{}: {} has an unknown layout", id, name))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn length_member(length: &Option<Primitive>, out: &mut String) {
+    if let Some(length) = length {
+        out.push_str(&member(length, "length", "", "    "));
+        out.push_str("    /* byte length of the rest of this struct */\n");
+    }
+}
+
+fn padding_member(layout: &Layout, size: usize, out: &mut String) {
+    let mut unaligned = layout.clone();
+    match &mut unaligned {
+        Layout::Struct(s) => s.align_size = None,
+        Layout::Enum(e) => e.align_size = None,
+        _ => return,
+    }
+    match unaligned.fixed_size() {
+        Some(content) if content < size => {
+            out.push_str(&format!("    uint8_t padding[{}];\n", size - content))
+        }
+        _ => {}
+    }
+}
+
+fn magic_member(id: &str, magic: &Option<Magic>, indent: &str, out: &mut String) {
+    if let Some(magic) = magic {
+        out.push_str(&member(&magic.ty, id, "", indent));
+        out.push_str(&format!("{}/* {} must be {} */\n", indent, id, magic.value));
+    }
+}
+
+fn member(primitive: &Primitive, id: &str, dims: &str, indent: &str) -> String {
+    let ty = match primitive.ty {
+        "f32" => "float".to_string(),
+        "f64" => "double".to_string(),
+        // no 128 bits integers in C99
+        t if primitive.size > 8 => {
+            return format!("{}uint8_t {}{}[16]; /* {} */\n", indent, id, dims, t)
+        }
+        t => format!(
+            "{}int{}_t",
+            if primitive.is_signed() { "" } else { "u" },
+            &t[1..]
+        ),
+    };
+    let endianness = match primitive.endianness {
+        _ if primitive.size == 1 => "",
+        Endianness::Big => " /* big endian */",
+        Endianness::Little => " /* little endian */",
+        Endianness::Native => "",
+    };
+    format!("{}{} {}{};{}\n", indent, ty, id, dims, endianness)
+}
@@ -0,0 +1,364 @@
+//! Kaitai Struct `.ksy` generation
+
+use std::collections::HashSet;
+
+use crate::layout::{snake_case, Endianness, Field, Layout, Magic, Primitive, Variant};
+use crate::Plod;
+
+/// Maximum number of values a tag range can be expanded to in a `switch-on`
+const MAX_EXPANDED_TAGS: u128 = 256;
+
+/// Generate a Kaitai Struct description (`.ksy` YAML document) for a derived type.
+///
+/// Native endianness is resolved using the endianness of the generating target.
+/// Tag patterns that cannot be expressed as Kaitai `switch-on` cases are kept as a `doc` entry.
+pub fn kaitai<T: Plod>() -> String {
+    let mut ksy = Kaitai::default();
+    let root = T::describe().resolve();
+    let id = match root.name() {
+        Some(name) => snake_case(name),
+        None => "root".to_string(),
+    };
+    ksy.done.insert(id.clone());
+    let seq = ksy.type_seq(&id, &root);
+
+    let mut out = String::new();
+    out.push_str(&format!("meta:\n  id: {}\n", id));
+    write_seq(&mut out, 0, &seq);
+    if !ksy.types.is_empty() {
+        out.push_str("types:\n");
+        for (id, seq) in ksy.types.iter() {
+            out.push_str(&format!("  {}:\n", id));
+            write_seq(&mut out, 4, seq);
+        }
+    }
+    out
+}
+
+/// A seq entry is a list of `key: value` lines, the first being the id
+type Entry = Vec<String>;
+
+#[derive(Default)]
+struct Kaitai {
+    /// user types, in generation order
+    types: Vec<(String, Vec<Entry>)>,
+    /// user types already generated or being generated
+    done: HashSet<String>,
+}
+
+impl Kaitai {
+    /// Produce the seq of a user type from its layout
+    fn type_seq(&mut self, id: &str, layout: &Layout) -> Vec<Entry> {
+        let mut seq = Vec::new();
+        match layout {
+            Layout::Struct(s) => {
+                length_entry(&s.length, &mut seq);
+                magic_entry("magic", &s.magic, &mut seq);
+                self.fields(id, s.stored_fields(), &mut seq);
+                magic_entry("trailer", &s.trailer, &mut seq);
+                padding_entry(s.align_size, &mut seq);
+            }
+            Layout::Enum(e) => {
+                length_entry(&e.length, &mut seq);
+                seq.push(vec![
+                    "id: tag".to_string(),
+                    format!("type: {}", primitive_type(&e.tag)),
+                ]);
+                let mut cases = Vec::new();
+                let mut docs = Vec::new();
+                for variant in e.variants.iter().filter(|v| !v.skip) {
+                    let variant_id = format!("{}_{}", id, snake_case(variant.name));
+                    let variant_seq = self.variant_seq(&variant_id, variant);
+                    self.add_type(variant_id.clone(), variant_seq);
+                    match &variant.tag {
+                        None => cases.push(format!("    _: {}", variant_id)),
+                        Some(tag) if tag.count() > 0 && tag.count() <= MAX_EXPANDED_TAGS => {
+                            for value in tag.values.iter().cloned().flatten() {
+                                cases.push(format!("    {}: {}", value, variant_id));
+                            }
+                        }
+                        Some(tag) => {
+                            docs.push(format!("{} when tag matches {}", variant_id, tag.pattern))
+                        }
+                    }
+                }
+                let mut body = vec!["id: body".to_string()];
+                if let Some(variant_size) = e.variant_size {
+                    body.push(format!("size: {}", variant_size));
+                }
+                body.extend([
+                    "type:".to_string(),
+                    "  switch-on: tag".to_string(),
+                    "  cases:".to_string(),
+                ]);
+                body.extend(cases);
+                if !docs.is_empty() {
+                    body.push(format!("doc: '{}'", docs.join(", ")));
+                }
+                seq.push(body);
+                padding_entry(e.align_size, &mut seq);
+            }
+            l => self.item("value", id, l, &mut seq),
+        }
+        seq
+    }
+
+    fn variant_seq(&mut self, id: &str, variant: &Variant) -> Vec<Entry> {
+        let mut seq = Vec::new();
+        magic_entry("magic", &variant.magic, &mut seq);
+        self.fields(id, &variant.stored_fields(), &mut seq);
+        magic_entry("trailer", &variant.trailer, &mut seq);
+        seq
+    }
+
+    fn fields(&mut self, scope: &str, fields: &[Field], seq: &mut Vec<Entry>) {
+        for field in fields.iter() {
+            self.item(&snake_case(field.name), scope, &field.layout, seq);
+        }
+    }
+
+    /// Add entries for a single item named `id`, `scope` is the id of the enclosing type
+    fn item(&mut self, id: &str, scope: &str, layout: &Layout, seq: &mut Vec<Entry>) {
+        let item_align = match layout {
+            Layout::Array { item_align, .. } | Layout::Vec { item_align, .. } => *item_align,
+            _ => None,
+        };
+        match layout {
+            Layout::Tuple(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.item(&format!("{}_{}", id, i), scope, item, seq);
+                }
+            }
+            Layout::Array { len, item, .. } => {
+                if is_u8(item) {
+                    seq.push(vec![format!("id: {}", id), format!("size: {}", len)]);
+                } else {
+                    let ty = self.item_type(id, scope, item);
+                    seq.push(vec![
+                        format!("id: {}", id),
+                        format!("type: {}", ty),
+                        "repeat: expr".to_string(),
+                        format!("repeat-expr: {}", len),
+                    ]);
+                }
+            }
+            Layout::Vec {
+                size,
+                byte_sized,
+                size_adjust,
+                size_unit,
+                length_field,
+                item,
+                ..
+            } => {
+                let size_id = match length_field {
+                    Some(field) => snake_case(field),
+                    None => {
+                        let size_id = format!("{}_size", id);
+                        seq.push(vec![
+                            format!("id: {}", size_id),
+                            format!("type: {}", primitive_type(size)),
+                        ]);
+                        size_id
+                    }
+                };
+                let count = match size_adjust {
+                    0 => size_id,
+                    adjust if *adjust < 0 => format!("{} + {}", size_id, -adjust),
+                    adjust => format!("{} - {}", size_id, adjust),
+                };
+                let count = match size_unit {
+                    1 => count,
+                    unit => format!("({}) * {}", count, unit),
+                };
+                if is_u8(item) {
+                    seq.push(vec![format!("id: {}", id), format!("size: {}", count)]);
+                } else if *byte_sized {
+                    let ty = self.item_type(id, scope, item);
+                    let items_id = format!("{}_{}_items", scope, id);
+                    let items_seq = vec![vec![
+                        "id: items".to_string(),
+                        format!("type: {}", ty),
+                        "repeat: eos".to_string(),
+                    ]];
+                    self.add_type(items_id.clone(), items_seq);
+                    seq.push(vec![
+                        format!("id: {}", id),
+                        format!("size: {}", count),
+                        format!("type: {}", items_id),
+                    ]);
+                } else {
+                    let ty = self.item_type(id, scope, item);
+                    seq.push(vec![
+                        format!("id: {}", id),
+                        format!("type: {}", ty),
+                        "repeat: expr".to_string(),
+                        format!("repeat-expr: {}", count),
+                    ]);
+                }
+            }
+            Layout::Encoded {
+                encoding,
+                size,
+                layout,
+            } => {
+                let size_id = format!("{}_size", id);
+                seq.push(vec![
+                    format!("id: {}", size_id),
+                    format!("type: {}", primitive_type(size)),
+                ]);
+                let mut entry = vec![format!("id: {}", id), format!("size: {}", size_id)];
+                match *encoding {
+                    // the only encoding kaitai processes natively
+                    "zlib" => entry.push("process: zlib".to_string()),
+                    e => entry.push(format!("doc: '{} encoded'", e)),
+                }
+                if let Some(layout) = layout {
+                    let ty = self.item_type(id, scope, layout);
+                    entry.push(format!("type: {}", ty));
+                }
+                seq.push(entry);
+            }
+            Layout::Rest => seq.push(vec![format!("id: {}", id), "size-eos: true".to_string()]),
+            Layout::PadTo { offset, .. } => seq.push(vec![
+                format!("id: {}", id),
+                format!("size: {} - _io.pos", offset),
+            ]),
+            Layout::IfRemaining(item) => {
+                let mut item_seq = Vec::new();
+                self.item(id, scope, item, &mut item_seq);
+                for mut entry in item_seq {
+                    entry.push("if: not _io.eof".to_string());
+                    seq.push(entry);
+                }
+            }
+            Layout::Primitive(p) if p.size > 8 => {
+                // kaitai has no 128 bits integers
+                seq.push(vec![format!("id: {}", id), format!("size: {}", p.size)]);
+            }
+            Layout::Opaque { name } => seq.push(vec![
+                format!("id: {}", id),
+                "size-eos: true".to_string(),
+                format!("doc: 'opaque type {}'", name),
+            ]),
+            l => {
+                let ty = self.item_type(id, scope, l);
+                seq.push(vec![format!("id: {}", id), format!("type: {}", ty)]);
+            }
+        }
+        // kaitai cannot skip padding between items
+        if let (Some(align), Some(entry)) = (item_align, seq.last_mut()) {
+            entry.push(format!("doc: 'items start every multiple of {} bytes'", align));
+        }
+    }
+
+    /// Kaitai type name of a collection item, creating a helper type if needed
+    fn item_type(&mut self, id: &str, scope: &str, layout: &Layout) -> String {
+        match layout {
+            Layout::Primitive(p) if p.size <= 8 => primitive_type(p),
+            Layout::Struct(_) | Layout::Enum(_) | Layout::Type { .. } => {
+                let resolved = layout.resolve();
+                let type_id = match resolved.name() {
+                    Some(name) => snake_case(name),
+                    None => format!("{}_{}", scope, id),
+                };
+                if !self.done.contains(&type_id) {
+                    self.done.insert(type_id.clone());
+                    let seq = self.type_seq(&type_id, &resolved);
+                    self.types.push((type_id.clone(), seq));
+                }
+                type_id
+            }
+            l => {
+                let type_id = format!("{}_{}_item", scope, id);
+                let mut seq = Vec::new();
+                self.item("value", &type_id, l, &mut seq);
+                self.add_type(type_id.clone(), seq);
+                type_id
+            }
+        }
+    }
+
+    fn add_type(&mut self, id: String, seq: Vec<Entry>) {
+        if self.done.insert(id.clone()) {
+            self.types.push((id, seq));
+        }
+    }
+}
+
+fn is_u8(layout: &Layout) -> bool {
+    matches!(layout, Layout::Primitive(p) if p.ty == "u8")
+}
+
+fn magic_entry(id: &str, magic: &Option<Magic>, seq: &mut Vec<Entry>) {
+    if let Some(magic) = magic {
+        if magic.values.len() > 1 {
+            let values: Vec<String> = magic.values.iter().map(|v| v.to_string()).collect();
+            seq.push(vec![
+                format!("id: {}", id),
+                format!("type: {}", primitive_type(&magic.ty)),
+                format!("valid: {{ any-of: [{}] }}", values.join(", ")),
+            ]);
+            return;
+        }
+        let bytes: Vec<String> = magic.bytes.iter().map(|b| format!("0x{:02x}", b)).collect();
+        seq.push(vec![
+            format!("id: {}", id),
+            format!("contents: [{}]", bytes.join(", ")),
+        ]);
+    }
+}
+
+fn padding_entry(align_size: Option<usize>, seq: &mut Vec<Entry>) {
+    if let Some(align) = align_size {
+        seq.push(vec![
+            "id: padding".to_string(),
+            format!("size: ({} - _io.pos % {}) % {}", align, align, align),
+            "doc: 'assumes this type starts at an aligned position'".to_string(),
+        ]);
+    }
+}
+
+fn length_entry(length: &Option<Primitive>, seq: &mut Vec<Entry>) {
+    if let Some(length) = length {
+        seq.push(vec![
+            "id: length".to_string(),
+            format!("type: {}", primitive_type(length)),
+            "doc: 'byte length of the rest of this type'".to_string(),
+        ]);
+    }
+}
+
+/// Kaitai name of a primitive type
+fn primitive_type(primitive: &Primitive) -> String {
+    let kind = if primitive.is_float() {
+        "f"
+    } else if primitive.is_signed() {
+        "s"
+    } else {
+        "u"
+    };
+    if primitive.size == 1 {
+        return format!("{}1", kind);
+    }
+    let endian = match primitive.endianness.resolve() {
+        Endianness::Big => "be",
+        _ => "le",
+    };
+    format!("{}{}{}", kind, primitive.size, endian)
+}
+
+fn write_seq(out: &mut String, indent: usize, seq: &[Entry]) {
+    let pad = " ".repeat(indent);
+    if seq.is_empty() {
+        out.push_str(&format!("{}seq: []\n", pad));
+        return;
+    }
+    out.push_str(&format!("{}seq:\n", pad));
+    for entry in seq.iter() {
+        for (i, line) in entry.iter().enumerate() {
+            let bullet = if i == 0 { "- " } else { "  " };
+            out.push_str(&format!("{}  {}{}\n", pad, bullet, line));
+        }
+    }
+}
@@ -0,0 +1,12 @@
+//! Exporters that describe derived formats for other tools
+//!
+//! They are all based on the static [`Layout`](crate::layout::Layout) returned by
+//! [`Plod::describe`](crate::Plod::describe), so they never need a value.
+
+mod c;
+mod kaitai;
+mod pattern;
+
+pub use c::c_header;
+pub use kaitai::kaitai;
+pub use pattern::{imhex_pattern, template_010};
@@ -0,0 +1,511 @@
+//! ImHex pattern and 010 Editor binary template generation
+//!
+//! Both languages are C-like, they only differ in type names, endianness handling and
+//! dispatch syntax, so they share the same generator.
+
+use std::collections::HashSet;
+
+use crate::layout::{snake_case, Endianness, Field, Layout, Magic, Primitive, Variant};
+use crate::Plod;
+
+/// Generate an ImHex pattern (`.hexpat`) for a derived type, placed at offset 0.
+pub fn imhex_pattern<T: Plod>() -> String {
+    Pattern::new(Dialect::ImHex).generate(T::describe())
+}
+
+/// Generate a 010 Editor binary template (`.bt`) for a derived type.
+pub fn template_010<T: Plod>() -> String {
+    Pattern::new(Dialect::Editor010).generate(T::describe())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    ImHex,
+    Editor010,
+}
+
+impl Dialect {
+    /// Type name of a primitive, including its endianness for ImHex
+    fn primitive(self, primitive: &Primitive) -> String {
+        match self {
+            Dialect::ImHex => {
+                let ty = match primitive.ty {
+                    "f32" => "float".to_string(),
+                    "f64" => "double".to_string(),
+                    t if primitive.is_signed() => format!("s{}", &t[1..]),
+                    t => t.to_string(),
+                };
+                match primitive.endianness {
+                    _ if primitive.size == 1 => ty,
+                    Endianness::Big => format!("be {}", ty),
+                    Endianness::Little => format!("le {}", ty),
+                    Endianness::Native => ty,
+                }
+            }
+            Dialect::Editor010 => {
+                let ty = match primitive.size {
+                    1 => "byte",
+                    2 => "short",
+                    4 if primitive.is_float() => "float",
+                    4 => "int",
+                    8 if primitive.is_float() => "double",
+                    _ => "int64",
+                };
+                if primitive.is_signed() || primitive.is_float() {
+                    ty.to_string()
+                } else {
+                    format!("u{}", ty)
+                }
+            }
+        }
+    }
+
+    /// Reference to a user type
+    fn type_ref(self, id: &str) -> String {
+        match self {
+            Dialect::ImHex => id.to_string(),
+            Dialect::Editor010 => format!("struct {}", id),
+        }
+    }
+
+    fn forward(self, id: &str) -> String {
+        match self {
+            Dialect::ImHex => format!("using {};\n", id),
+            Dialect::Editor010 => format!("struct {};\n", id),
+        }
+    }
+
+    /// Condition matching a tag against a list of ranges
+    fn condition(self, tag: &str, values: &[std::ops::RangeInclusive<i128>]) -> String {
+        let conditions: Vec<String> = values
+            .iter()
+            .map(|r| match self {
+                Dialect::ImHex if r.start() == r.end() => format!("{}", r.start()),
+                Dialect::ImHex => format!("{} ... {}", r.start(), r.end()),
+                Dialect::Editor010 if r.start() == r.end() => format!("{} == {}", tag, r.start()),
+                Dialect::Editor010 => {
+                    format!("{} >= {} && {} <= {}", tag, r.start(), tag, r.end())
+                }
+            })
+            .collect();
+        match self {
+            Dialect::ImHex => conditions.join(" | "),
+            Dialect::Editor010 => conditions.join(" || "),
+        }
+    }
+}
+
+struct Pattern {
+    dialect: Dialect,
+    /// type declarations, dependencies first
+    declarations: Vec<String>,
+    /// types already declared or being declared
+    done: HashSet<String>,
+    /// types used before being declared
+    forwards: Vec<String>,
+    /// 010 Editor endianness is a global state, this is the last one set in the current body
+    endianness: Option<Endianness>,
+}
+
+impl Pattern {
+    fn new(dialect: Dialect) -> Self {
+        Pattern {
+            dialect,
+            declarations: Vec::new(),
+            done: HashSet::new(),
+            forwards: Vec::new(),
+            endianness: None,
+        }
+    }
+
+    fn generate(mut self, layout: Layout) -> String {
+        let root = self.item_type("root", "root", &layout);
+        let mut out = String::new();
+        for id in self.forwards.iter() {
+            out.push_str(&self.dialect.forward(id));
+        }
+        if !self.forwards.is_empty() {
+            out.push('\n');
+        }
+        for declaration in self.declarations.iter() {
+            out.push_str(declaration);
+            out.push('\n');
+        }
+        match self.dialect {
+            // ImHex needs a placement
+            Dialect::ImHex => out.push_str(&format!("{} root @ 0x00;\n", root)),
+            Dialect::Editor010 => out.push_str(&format!("{} root;\n", root)),
+        }
+        out
+    }
+
+    /// Declare a user type if needed and return its id
+    fn declare(&mut self, layout: &Layout) -> String {
+        let layout = layout.resolve();
+        let id = snake_case(layout.name().unwrap_or("anonymous"));
+        if self.done.contains(&id) {
+            let declared = format!("struct {} {{", id);
+            if !self.declarations.iter().any(|d| d.starts_with(&declared))
+                && !self.forwards.contains(&id)
+            {
+                self.forwards.push(id.clone());
+            }
+            return id;
+        }
+        self.done.insert(id.clone());
+        let saved = self.endianness.take();
+        let mut body = String::new();
+        match &layout {
+            Layout::Struct(s) => {
+                self.length(&s.length, &mut body);
+                self.magic("magic", &s.magic, "    ", &mut body);
+                self.fields(&id, s.stored_fields(), "    ", &mut body);
+                self.magic("trailer", &s.trailer, "    ", &mut body);
+            }
+            Layout::Enum(e) => {
+                self.length(&e.length, &mut body);
+                self.primitive("tag", &e.tag, "", "    ", &mut body);
+                let variants: Vec<&Variant> = e.variants.iter().filter(|v| !v.skip).collect();
+                match self.dialect {
+                    Dialect::ImHex => body.push_str("    match (tag) {\n"),
+                    Dialect::Editor010 => body.push_str("    "),
+                }
+                let indent = match self.dialect {
+                    Dialect::ImHex => "            ",
+                    Dialect::Editor010 => "        ",
+                };
+                for variant in variants.iter() {
+                    let variant_id = format!("{}_{}", id, snake_case(variant.name));
+                    let mut fields = String::new();
+                    self.magic("magic", &variant.magic, indent, &mut fields);
+                    self.fields(&variant_id, &variant.stored_fields(), indent, &mut fields);
+                    self.magic("trailer", &variant.trailer, indent, &mut fields);
+                    self.endianness = None;
+                    let condition = match &variant.tag {
+                        Some(tag) if tag.values.is_empty() => {
+                            let comment =
+                                format!("// {}: tag {} not supported", variant.name, tag.pattern);
+                            match self.dialect {
+                                Dialect::ImHex => body.push_str(&format!("        {}\n", comment)),
+                                Dialect::Editor010 => body.push_str(&format!("{}\n    ", comment)),
+                            }
+                            continue;
+                        }
+                        Some(tag) => Some(self.dialect.condition("tag", &tag.values)),
+                        None => None,
+                    };
+                    let name = variant.name;
+                    let arm = match (self.dialect, condition) {
+                        (Dialect::ImHex, Some(c)) => {
+                            format!("        ({}): {{ // {}\n{}        }}\n", c, name, fields)
+                        }
+                        (Dialect::ImHex, None) => {
+                            format!("        (_): {{ // {}\n{}        }}\n", name, fields)
+                        }
+                        (Dialect::Editor010, Some(c)) => {
+                            format!("if ({}) {{ // {}\n{}    }} else ", c, name, fields)
+                        }
+                        (Dialect::Editor010, None) => format!("{{ // {}\n{}    }}", name, fields),
+                    };
+                    body.push_str(&arm);
+                }
+                match self.dialect {
+                    Dialect::ImHex => body.push_str("    }\n"),
+                    Dialect::Editor010 if body.ends_with("else ") => {
+                        body.truncate(body.len() - 6);
+                        body.push('\n');
+                    }
+                    Dialect::Editor010 => body.push('\n'),
+                }
+                if let Some(variant_size) = e.variant_size {
+                    // skip the padding of shorter variants
+                    match self.dialect {
+                        Dialect::ImHex => body.push_str(&format!(
+                            "    $ = addressof(tag) + sizeof(tag) + {};\n",
+                            variant_size
+                        )),
+                        Dialect::Editor010 => body.push_str(&format!(
+                            "    FSeek(startof(tag) + sizeof(tag) + {});\n",
+                            variant_size
+                        )),
+                    }
+                }
+            }
+            l => self.field(&id, "value", l, "    ", &mut body),
+        }
+        let align_size = match &layout {
+            Layout::Struct(s) => s.align_size,
+            Layout::Enum(e) => e.align_size,
+            _ => None,
+        };
+        if let Some(align) = align_size {
+            match self.dialect {
+                Dialect::ImHex => body.push_str(&format!(
+                    "    padding[({} - ($ - addressof(this)) % {}) % {}];\n",
+                    align, align, align
+                )),
+                Dialect::Editor010 => body.push_str(&format!(
+                    "    FSeek(startof(this) + (FTell() - startof(this) + {} - 1) / {} * {});\n",
+                    align, align, align
+                )),
+            }
+        }
+        self.endianness = saved;
+        self.declarations
+            .push(format!("struct {} {{\n{}}};\n", id, body));
+        id
+    }
+
+    fn fields(&mut self, scope: &str, fields: &[Field], indent: &str, out: &mut String) {
+        for field in fields.iter() {
+            let id = snake_case(field.name);
+            self.field(scope, &id, &field.layout, indent, out);
+        }
+    }
+
+    fn field(&mut self, scope: &str, id: &str, layout: &Layout, indent: &str, out: &mut String) {
+        match layout {
+            Layout::Tuple(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.field(scope, &format!("{}_{}", id, i), item, indent, out);
+                }
+            }
+            Layout::Array {
+                item_align: Some(align),
+                ..
+            }
+            | Layout::Vec {
+                item_align: Some(align),
+                ..
+            } => {
+                // patterns have no padding between items
+                out.push_str(&format!(
+                    "{}// {}: items start every multiple of {} bytes\n",
+                    indent, id, align
+                ));
+                let mut unaligned = layout.clone();
+                if let Layout::Array { item_align, .. } | Layout::Vec { item_align, .. } =
+                    &mut unaligned
+                {
+                    *item_align = None;
+                }
+                self.field(scope, id, &unaligned, indent, out);
+            }
+            Layout::Array { len, item, .. } => {
+                let ty = self.item_type(scope, id, item);
+                self.typed(&ty, item, id, &format!("[{}]", len), indent, out);
+            }
+            Layout::Vec {
+                size,
+                byte_sized,
+                size_adjust,
+                size_unit,
+                length_field,
+                item,
+                ..
+            } => {
+                let size_id = match length_field {
+                    Some(field) => snake_case(field),
+                    None => {
+                        let size_id = format!("{}_size", id);
+                        self.primitive(&size_id, size, "", indent, out);
+                        size_id
+                    }
+                };
+                let count = match size_adjust {
+                    0 => size_id.clone(),
+                    adjust if *adjust < 0 => format!("{} + {}", size_id, -adjust),
+                    adjust => format!("{} - {}", size_id, adjust),
+                };
+                let count = match size_unit {
+                    1 => count,
+                    unit => format!("({}) * {}", count, unit),
+                };
+                let ty = self.item_type(scope, id, item);
+                if *byte_sized && !is_u8(item) {
+                    match self.dialect {
+                        Dialect::ImHex if length_field.is_some() => {
+                            out.push_str(&format!("{}// {}: {} bytes of {}\n", indent, id, count, ty));
+                            let byte = Primitive {
+                                ty: "u8",
+                                size: 1,
+                                endianness: Endianness::Native,
+                            };
+                            self.primitive(id, &byte, &format!("[{}]", count), indent, out);
+                        }
+                        Dialect::ImHex => {
+                            let end =
+                                format!("addressof({}) + sizeof({}) + {}", size_id, size_id, count);
+                            self.typed(
+                                &ty,
+                                item,
+                                id,
+                                &format!("[while($ < {})]", end),
+                                indent,
+                                out,
+                            );
+                        }
+                        Dialect::Editor010 => {
+                            out.push_str(&format!(
+                                "{}local int64 {}_end = FTell() + {};\n",
+                                indent, id, count
+                            ));
+                            out.push_str(&format!("{}while (FTell() < {}_end) {{\n", indent, id));
+                            self.typed(&ty, item, id, "", &format!("{}    ", indent), out);
+                            out.push_str(&format!("{}}}\n", indent));
+                        }
+                    }
+                } else {
+                    self.typed(&ty, item, id, &format!("[{}]", count), indent, out);
+                }
+            }
+            Layout::Encoded { encoding, size, .. } => {
+                let size_id = format!("{}_size", id);
+                self.primitive(&size_id, size, "", indent, out);
+                out.push_str(&format!("{}// {}: {} encoded\n", indent, id, encoding));
+                let byte = Primitive {
+                    ty: "u8",
+                    size: 1,
+                    endianness: Endianness::Native,
+                };
+                self.primitive(id, &byte, &format!("[{}]", size_id), indent, out);
+            }
+            Layout::Rest => {
+                let dims = match self.dialect {
+                    Dialect::ImHex => "[while(!std::mem::eof())]",
+                    Dialect::Editor010 => "[FileSize() - FTell()]",
+                };
+                let byte = Primitive {
+                    ty: "u8",
+                    size: 1,
+                    endianness: Endianness::Native,
+                };
+                self.primitive(id, &byte, dims, indent, out);
+            }
+            Layout::PadTo { offset, .. } => {
+                let dims = match self.dialect {
+                    Dialect::ImHex => format!("[{} - $]", offset),
+                    Dialect::Editor010 => format!("[{} - FTell()]", offset),
+                };
+                let byte = Primitive {
+                    ty: "u8",
+                    size: 1,
+                    endianness: Endianness::Native,
+                };
+                self.primitive(id, &byte, &dims, indent, out);
+            }
+            Layout::IfRemaining(item) => {
+                let condition = match self.dialect {
+                    Dialect::ImHex => "!std::mem::eof()",
+                    Dialect::Editor010 => "!FEof()",
+                };
+                out.push_str(&format!("{}if ({}) {{\n", indent, condition));
+                self.field(scope, id, item, &format!("{}    ", indent), out);
+                out.push_str(&format!("{}}}\n", indent));
+            }
+            Layout::Opaque { name } => {
+                out.push_str(&format!("{}// {}: opaque type {}\n", indent, id, name));
+            }
+            l => {
+                let ty = self.item_type(scope, id, l);
+                self.typed(&ty, l, id, "", indent, out);
+            }
+        }
+    }
+
+    /// Declare a field of type `ty`, handling 010 endianness for primitives
+    fn typed(
+        &mut self,
+        ty: &str,
+        layout: &Layout,
+        id: &str,
+        dims: &str,
+        indent: &str,
+        out: &mut String,
+    ) {
+        match layout {
+            Layout::Primitive(p) => self.primitive(id, p, dims, indent, out),
+            _ => {
+                out.push_str(&format!("{}{} {}{};\n", indent, ty, id, dims));
+                // nested types may have changed endianness
+                self.endianness = None;
+            }
+        }
+    }
+
+    fn primitive(
+        &mut self,
+        id: &str,
+        primitive: &Primitive,
+        dims: &str,
+        indent: &str,
+        out: &mut String,
+    ) {
+        if self.dialect == Dialect::Editor010 && primitive.size > 1 {
+            let endianness = primitive.endianness.resolve();
+            if self.endianness != Some(endianness) {
+                let call = match endianness {
+                    Endianness::Big => "BigEndian();",
+                    _ => "LittleEndian();",
+                };
+                out.push_str(&format!("{}{}\n", indent, call));
+                self.endianness = Some(endianness);
+            }
+            if primitive.size > 8 {
+                out.push_str(&format!(
+                    "{}ubyte {}{}[16]; // {}\n",
+                    indent, id, dims, primitive.ty
+                ));
+                return;
+            }
+        }
+        out.push_str(&format!(
+            "{}{} {}{};\n",
+            indent,
+            self.dialect.primitive(primitive),
+            id,
+            dims
+        ));
+    }
+
+    fn length(&mut self, length: &Option<Primitive>, out: &mut String) {
+        if let Some(length) = length {
+            self.primitive("length", length, "", "    ", out);
+            out.push_str("    // byte length of the rest of this struct\n");
+        }
+    }
+
+    fn magic(&mut self, id: &str, magic: &Option<Magic>, indent: &str, out: &mut String) {
+        if let Some(magic) = magic {
+            self.primitive(id, &magic.ty, "", indent, out);
+            out.push_str(&format!("{}// {} must be {}\n", indent, id, magic.value));
+        }
+    }
+
+    /// Type name of a collection item, creating a helper type if needed
+    fn item_type(&mut self, scope: &str, id: &str, layout: &Layout) -> String {
+        match layout {
+            Layout::Primitive(p) => self.dialect.primitive(p),
+            Layout::Struct(_) | Layout::Enum(_) | Layout::Type { .. } => {
+                let id = self.declare(layout);
+                self.dialect.type_ref(&id)
+            }
+            l => {
+                let type_id = format!("{}_{}_item", scope, id);
+                if self.done.insert(type_id.clone()) {
+                    let saved = self.endianness.take();
+                    let mut body = String::new();
+                    self.field(&type_id, "value", l, "    ", &mut body);
+                    self.endianness = saved;
+                    self.declarations
+                        .push(format!("struct {} {{\n{}}};\n", type_id, body));
+                }
+                self.dialect.type_ref(&type_id)
+            }
+        }
+    }
+}
+
+fn is_u8(layout: &Layout) -> bool {
+    matches!(layout, Layout::Primitive(p) if p.ty == "u8")
+}
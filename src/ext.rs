@@ -0,0 +1,192 @@
+//! Convenience methods for the simple cases
+//!
+//! [`PlodExt`] is implemented for every [`Plod`] type whose context has a default value, it
+//! avoids the `Cursor` and `File` plumbing when a whole buffer or file holds a single value.
+//! [`read_file`] and [`write_file`] do the same as functions, eg: `plod::read_file::<Header>(path)`.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Error, ErrorKind, Read, Seek, Write};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::size::{SizeReader, SizeWriter};
+use crate::{Plod, Result};
+
+/// Extension methods of [`Plod`] types
+pub trait PlodExt: Plod {
+    /// Size once serialized, same as `size_at_rest`
+    fn size(&self) -> usize;
+
+    /// Serialize this value to a new `Vec`
+    fn to_bytes(&self) -> Result<Vec<u8>>;
+
+    /// Read a value that takes exactly all of `bytes`
+    /// Returns an error of kind `std::io::ErrorKind::InvalidData` if bytes are left
+    fn from_bytes(bytes: &[u8]) -> Result<Self>;
+
+    /// Write this value to the file at `path`, it is created or truncated
+    fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()>;
+
+    /// Read a value that takes exactly all of the file at `path`
+    fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self>;
+
+    /// Read a value and return it with the number of bytes read
+    fn read_counted<R: Read>(from: &mut R) -> Result<(Self, usize)>;
+
+    /// Write this value and return the number of bytes written
+    fn write_counted<W: Write>(&self, to: &mut W) -> Result<usize>;
+}
+
+impl<T: Plod> PlodExt for T
+where
+    T::Context: Default,
+{
+    fn size(&self) -> usize {
+        self.size_at_rest()
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut cursor = Cursor::new(Vec::with_capacity(self.size_at_rest()));
+        self.write_to_seek(&mut cursor)?;
+        Ok(cursor.into_inner())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Farthest::new(Cursor::new(bytes));
+        let value = T::read_from_seek(&mut reader)?;
+        let left = bytes.len().saturating_sub(reader.farthest as usize);
+        if left > 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} bytes left after the value", left),
+            ));
+        }
+        Ok(value)
+    }
+
+    fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_file(path, self)
+    }
+
+    fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        read_file(path)
+    }
+
+    fn read_counted<R: Read>(from: &mut R) -> Result<(Self, usize)> {
+        let mut counter = SizeReader::new(from);
+        let value = T::read_from(&mut counter)?;
+        Ok((value, counter.size()))
+    }
+
+    fn write_counted<W: Write>(&self, to: &mut W) -> Result<usize> {
+        let mut counter = SizeWriter::with_writer(to);
+        self.write_to(&mut counter)?;
+        Ok(counter.size())
+    }
+}
+
+/// Reader remembering the farthest position read, values with `seek_to` fields end before the
+/// data they point to
+struct Farthest<R> {
+    reader: R,
+    position: u64,
+    farthest: u64,
+}
+
+impl<R> Farthest<R> {
+    /// `reader` is at the start of its input
+    fn new(reader: R) -> Self {
+        Farthest { reader, position: 0, farthest: 0 }
+    }
+}
+
+impl<R: Read> Read for Farthest<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.position += read as u64;
+        self.farthest = self.farthest.max(self.position);
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for Farthest<R> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> Result<u64> {
+        self.position = self.reader.seek(pos)?;
+        Ok(self.position)
+    }
+
+    fn stream_position(&mut self) -> Result<u64> {
+        Ok(self.position)
+    }
+}
+
+/// Buffer size of file readers and writers
+const FILE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Error payload of a failed [`read_file`] or [`write_file`], available with
+/// `std::io::Error::get_ref` on errors of the same kind as the original one
+#[derive(Debug)]
+pub struct FileError {
+    /// the file read or written
+    pub path: PathBuf,
+    /// the original error, whose own payload like a [`FieldError`](crate::error::FieldError) is
+    /// kept
+    pub error: Error,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The error of an operation on the file at `path`, with the path in its message
+fn file_error(path: &Path, error: Error) -> Error {
+    Error::new(error.kind(), FileError { path: path.to_path_buf(), error })
+}
+
+/// Read a value that takes exactly all of the file at `path`, through a buffered reader.
+/// Errors keep their kind and carry a [`FileError`] telling the path, a file with bytes left after
+/// the value is an error of kind `InvalidData`.
+pub fn read_file<T: Plod>(path: impl AsRef<Path>) -> Result<T>
+where
+    T::Context: Default,
+{
+    let path = path.as_ref();
+    let read = || {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut reader = Farthest::new(BufReader::with_capacity(FILE_BUFFER_SIZE, file));
+        let value = T::read_from_seek(&mut reader)?;
+        let left = len.saturating_sub(reader.farthest);
+        if left > 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} bytes left after the value", left),
+            ));
+        }
+        Ok(value)
+    };
+    read().map_err(|e| file_error(path, e))
+}
+
+/// Write a value to the file at `path`, created or truncated, through a buffered writer.
+/// Errors keep their kind and carry a [`FileError`] telling the path.
+pub fn write_file<T: Plod>(path: impl AsRef<Path>, value: &T) -> Result<()>
+where
+    T::Context: Default,
+{
+    let path = path.as_ref();
+    let write = || {
+        let mut writer = BufWriter::with_capacity(FILE_BUFFER_SIZE, File::create(path)?);
+        value.write_to_seek(&mut writer)?;
+        writer.flush()
+    };
+    write().map_err(|e| file_error(path, e))
+}
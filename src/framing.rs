@@ -0,0 +1,138 @@
+//! Framed records
+//!
+//! Applications that only need to append values to a log file can use [`write_framed`] and
+//! [`read_framed`] instead of designing a container. Each value is stored in a frame:
+//! - the bytes of [`Framing::magic`], if any,
+//! - the size of the value as a little endian `u32`,
+//! - the value,
+//! - if [`Framing::crc`] is set, the [`Crc::CRC32`] of the value as a little endian `u32`.
+//!
+//! A frame cut by a crash while appending is an error of kind `UnexpectedEof`, a wrong magic or
+//! checksum an error of kind `InvalidData`. The end of the input between two frames is not an
+//! error, [`read_framed`] returns `None`.
+//!
+//! ```
+//! use plod::framing::{read_framed, write_framed, Framing};
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! struct Entry {
+//!     id: u32,
+//!     #[plod(size_type(u8))]
+//!     name: String,
+//! }
+//!
+//! let framing = Framing::new().magic(b"LOG").crc();
+//! let mut log = Vec::new();
+//! write_framed(&mut log, &Entry { id: 1, name: "start".into() }, &framing).unwrap();
+//! write_framed(&mut log, &Entry { id: 2, name: "stop".into() }, &framing).unwrap();
+//!
+//! let mut reader = log.as_slice();
+//! let first: Entry = read_framed(&mut reader, &framing).unwrap().unwrap();
+//! assert_eq!(first.name, "start");
+//! assert_eq!(read_framed::<Entry, _>(&mut reader, &framing).unwrap().unwrap().id, 2);
+//! assert!(read_framed::<Entry, _>(&mut reader, &framing).unwrap().is_none());
+//! ```
+
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::checksum::{Checksum, Crc};
+use crate::{Plod, PlodWrite, Result};
+
+/// How values are framed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Framing {
+    /// Bytes at the start of each frame
+    pub magic: &'static [u8],
+    /// Whether a CRC-32 of the value follows it
+    pub crc: bool,
+}
+
+impl Framing {
+    /// Frames with only a size
+    pub const fn new() -> Self {
+        Framing { magic: &[], crc: false }
+    }
+
+    /// Start each frame with `magic`
+    pub const fn magic(self, magic: &'static [u8]) -> Self {
+        Framing { magic, ..self }
+    }
+
+    /// End each frame with a CRC-32 of the value
+    pub const fn crc(self) -> Self {
+        Framing { crc: true, ..self }
+    }
+}
+
+/// Write `value` in a frame, which can also be a slice of values, see [`PlodWrite`]
+pub fn write_framed<T: PlodWrite + ?Sized, W: Write>(writer: &mut W, value: &T, framing: &Framing) -> Result<()>
+where
+    T::Context: Default,
+{
+    let mut data = Vec::with_capacity(value.view_size());
+    value.write_view(&mut data)?;
+    let size = u32::try_from(data.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, format!("{} bytes don't fit in a frame", data.len())))?;
+    // a single write so that concurrent appends don't interleave their frames
+    let mut frame = Vec::with_capacity(framing.magic.len() + data.len() + 8);
+    frame.extend_from_slice(framing.magic);
+    frame.extend_from_slice(&size.to_le_bytes());
+    frame.extend_from_slice(&data);
+    if framing.crc {
+        frame.extend_from_slice(&(Crc::CRC32.checksum(&data) as u32).to_le_bytes());
+    }
+    writer.write_all(&frame)
+}
+
+/// Read a value from the next frame, `None` if the input ends before it
+pub fn read_framed<T: Plod, R: Read>(reader: &mut R, framing: &Framing) -> Result<Option<T>>
+where
+    T::Context: Default,
+{
+    let mut header = vec![0_u8; framing.magic.len() + 4];
+    let read = read_full(reader, &mut header)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if read < header.len() {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+    let (magic, size) = header.split_at(framing.magic.len());
+    if magic != framing.magic {
+        return Err(Error::new(ErrorKind::InvalidData, format!("bad frame magic {:02x?}", magic)));
+    }
+    let size = u32::from_le_bytes(size.try_into().unwrap()) as usize;
+    crate::fuzz::check_len(size)?;
+    // the size is not trusted to allocate the value
+    let mut data = Vec::new();
+    reader.take(size as u64).read_to_end(&mut data)?;
+    if data.len() < size {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+    if framing.crc {
+        let mut crc = [0_u8; 4];
+        reader.read_exact(&mut crc)?;
+        let expected = Crc::CRC32.checksum(&data) as u32;
+        let found = u32::from_le_bytes(crc);
+        if found != expected {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("frame CRC mismatch, expected {:#010x}, found {:#010x}", expected, found),
+            ));
+        }
+    }
+    T::from_bytes_exact(&data).map(Some)
+}
+
+/// Read until `buffer` is full or the input ends, returns the number of bytes read
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match crate::helpers::read_some(reader, &mut buffer[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
@@ -0,0 +1,275 @@
+//! Robustness helpers against malicious input
+//!
+//! Derived readers trust length prefixes, a 4 bytes size field is enough to request a 4GB
+//! allocation. While limits are active (see [`with_limits`]), collection lengths, the memory taken
+//! by all collections of a whole read and nesting depth are checked by generated code so that
+//! hostile input produces an error instead of an out of memory abort or a stack overflow. Such
+//! errors are of kind `InvalidData` and contain a [`LimitExceeded`].
+//!
+//! Vectors are allocated for the length read up front, but never for more than [`PREALLOCATION`]
+//! bytes, nor more than `max_len` items while limits are active: a longer collection grows as its
+//! items are actually read, so a hostile length fails at the end of the input before allocating.
+//!
+//! [`check_read`] runs a read under default limits and checks that what was read can be written
+//! back identically, it is meant to be the whole body of a `cargo fuzz` target:
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| {
+//!     plod::fuzz::check_read::<MyType>(data);
+//! });
+//! ```
+
+use std::cell::Cell;
+use std::fmt;
+use std::io::{Error, ErrorKind, Read};
+
+use crate::{Plod, Result};
+
+/// Limits enforced by derived readers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// maximum length of a single collection, in items or in bytes depending on its size storage
+    pub max_len: usize,
+    /// maximum memory taken by the items of all collections read within [`with_limits`], in bytes,
+    /// this bounds the total allocation of many collections that are each below `max_len`.
+    /// Collections counted in items count `size_of` their item for each, those sized in bytes
+    /// count their size at rest.
+    pub max_total_bytes: usize,
+    /// maximum nesting depth of derived types
+    pub max_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_len: 1 << 20,
+            max_total_bytes: 1 << 28,
+            max_depth: 64,
+        }
+    }
+}
+
+/// Error payload of a read stopped by a limit, available with `std::io::Error::get_ref` on errors
+/// of kind `InvalidData`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// a collection is longer than `max_len`
+    Length {
+        /// length read from input
+        len: usize,
+        /// the limit
+        max: usize,
+    },
+    /// collections take more than `max_total_bytes`
+    TotalBytes {
+        /// the limit
+        max: usize,
+    },
+    /// types are nested deeper than `max_depth`
+    Depth {
+        /// the limit
+        max: usize,
+    },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Length { len, max } => {
+                write!(f, "Collection length {} exceeds limit {}", len, max)
+            }
+            LimitExceeded::TotalBytes { max } => {
+                write!(f, "Total size of collections exceeds limit {} bytes", max)
+            }
+            LimitExceeded::Depth { max } => write!(f, "Nesting depth exceeds limit {}", max),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl From<LimitExceeded> for Error {
+    fn from(limit: LimitExceeded) -> Self {
+        Error::new(ErrorKind::InvalidData, limit)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    limits: Option<Limits>,
+    depth: usize,
+    total_bytes: usize,
+}
+
+thread_local! {
+    static STATE: Cell<State> = const { Cell::new(State { limits: None, depth: 0, total_bytes: 0 }) };
+}
+
+/// Restores the previous state even if the closure panics
+struct Restore(State);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        STATE.with(|s| s.set(self.0));
+    }
+}
+
+/// Run `f` with `limits` enforced by all reads of derived types on the current thread
+pub fn with_limits<T, F: FnOnce() -> T>(limits: Limits, f: F) -> T {
+    let _restore = Restore(STATE.with(|s| {
+        s.replace(State {
+            limits: Some(limits),
+            depth: 0,
+            total_bytes: 0,
+        })
+    }));
+    f()
+}
+
+/// Limits currently enforced on this thread, if any
+pub fn current_limits() -> Option<Limits> {
+    STATE.with(|s| s.get().limits)
+}
+
+/// Check the length of a collection of bytes read from input against current limits, called by
+/// derived code
+#[doc(hidden)]
+pub fn check_len(len: usize) -> Result<()> {
+    check(len, len)
+}
+
+/// Check the length of a collection of `T` read from input against current limits, called by
+/// derived code
+#[doc(hidden)]
+pub fn check_items<T>(len: usize) -> Result<()> {
+    check(len, len.saturating_mul(std::mem::size_of::<T>().max(1)))
+}
+
+/// Check a collection of `len` items taking `bytes` in memory
+fn check(len: usize, bytes: usize) -> Result<()> {
+    STATE.with(|s| {
+        let mut state = s.get();
+        let limits = match state.limits {
+            Some(limits) => limits,
+            None => return Ok(()),
+        };
+        if len > limits.max_len {
+            return Err(LimitExceeded::Length {
+                len,
+                max: limits.max_len,
+            }
+            .into());
+        }
+        state.total_bytes = state.total_bytes.saturating_add(bytes);
+        if state.total_bytes > limits.max_total_bytes {
+            return Err(LimitExceeded::TotalBytes {
+                max: limits.max_total_bytes,
+            }
+            .into());
+        }
+        s.set(state);
+        Ok(())
+    })
+}
+
+/// Read all the bytes left in `reader` as a collection, within current limits: no more than
+/// `max_len` bytes are buffered. Called by derived code
+#[doc(hidden)]
+pub fn read_to_end<R: Read>(reader: R) -> Result<Vec<u8>> {
+    let mut result = Vec::new();
+    match current_limits() {
+        Some(limits) => {
+            reader
+                .take(limits.max_len as u64 + 1)
+                .read_to_end(&mut result)?;
+            check_len(result.len())?;
+        }
+        None => {
+            let mut reader = reader;
+            reader.read_to_end(&mut result)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Largest allocation made for a collection length read from input, without limits
+pub const PREALLOCATION: usize = 1 << 20;
+
+/// Capacity to reserve for a collection of `len` items of type `T` read from input, called by
+/// derived code
+#[doc(hidden)]
+pub fn capacity<T>(len: usize) -> usize {
+    let len = len.min(PREALLOCATION / std::mem::size_of::<T>().max(1));
+    match current_limits() {
+        Some(limits) => len.min(limits.max_len),
+        None => len,
+    }
+}
+
+/// Guard returned by [`enter`], the depth is decreased when it is dropped
+#[doc(hidden)]
+pub struct DepthGuard(bool);
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        if self.0 {
+            STATE.with(|s| {
+                let mut state = s.get();
+                state.depth -= 1;
+                s.set(state);
+            });
+        }
+    }
+}
+
+/// Enter a nested read and check its depth against current limits, called by derived code
+#[doc(hidden)]
+pub fn enter() -> Result<DepthGuard> {
+    STATE.with(|s| {
+        let mut state = s.get();
+        let limits = match state.limits {
+            Some(limits) => limits,
+            None => return Ok(DepthGuard(false)),
+        };
+        if state.depth >= limits.max_depth {
+            return Err(LimitExceeded::Depth {
+                max: limits.max_depth,
+            }
+            .into());
+        }
+        state.depth += 1;
+        s.set(state);
+        Ok(DepthGuard(true))
+    })
+}
+
+/// Read a value from arbitrary bytes under default [`Limits`].
+///
+/// Returns `None` if the bytes are not a valid value.
+///
+/// # Panics
+///
+/// This is meant to be used as a fuzz target, so it panics when a value could be read but its
+/// `size_at_rest` doesn't match the bytes read, or when writing it doesn't produce the same bytes.
+pub fn check_read<T: Plod>(data: &[u8]) -> Option<T>
+where
+    T::Context: Default,
+{
+    let mut input = data;
+    let value = with_limits(Limits::default(), || T::read_from(&mut input)).ok()?;
+    let consumed = data.len() - input.len();
+    assert_eq!(
+        value.size_at_rest(),
+        consumed,
+        "size_at_rest differs from the number of bytes read"
+    );
+    let mut bytes = Vec::with_capacity(consumed);
+    value
+        .write_to(&mut bytes)
+        .expect("a value that was read must be writable");
+    assert_eq!(
+        bytes.as_slice(),
+        &data[..consumed],
+        "written bytes differ from bytes read"
+    );
+    Some(value)
+}
@@ -0,0 +1,427 @@
+//! Building blocks for manual implementations of [`Plod`]
+//!
+//! These functions do what the derive generates, with the same context and position handling,
+//! so that a hand written `impl Plod` only has to deal with what is really specific to its type.
+//! Collection lengths read from input are checked against current [`Limits`](crate::fuzz::Limits).
+//!
+//! ```
+//! use std::io::{Read, Write};
+//! use plod::{helpers, Plod, Result};
+//!
+//! #[derive(Plod)]
+//! struct Point { x: u16, y: u16 }
+//!
+//! /// points preceded by their count, on a single byte
+//! struct Shape(Vec<Point>);
+//!
+//! impl Plod for Shape {
+//!     type Context = ();
+//!     fn size_at_rest(&self) -> usize {
+//!         1 + helpers::vec_size(&self.0)
+//!     }
+//!     fn impl_read_from<R: Read>(from: &mut R, ctx: &(), pos: usize) -> Result<Self> {
+//!         let [count] = helpers::read_bytes::<1, _>(from)?;
+//!         Ok(Shape(helpers::read_vec_by_count(from, ctx, pos + 1, count as usize)?))
+//!     }
+//!     fn impl_write_to<W: Write>(&self, to: &mut W, ctx: &(), pos: usize) -> Result<()> {
+//!         to.write_all(&[self.0.len() as u8])?;
+//!         helpers::write_vec(to, &self.0, ctx, pos + 1)
+//!     }
+//! }
+//! ```
+
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::lenient::UnknownTag;
+use crate::{Plod, Result};
+
+/// Read exactly `N` bytes, typically a primitive to convert with `from_le_bytes` or a tag
+pub fn read_bytes<const N: usize, R: Read>(from: &mut R) -> Result<[u8; N]> {
+    let mut buffer = [0_u8; N];
+    from.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Read some bytes like `Read::read`, retrying when the read is interrupted by a signal.
+/// `read_exact` and `write_all` already retry, this is for the single reads that tell whether
+/// the input has ended.
+pub fn read_some<R: Read + ?Sized>(from: &mut R, buffer: &mut [u8]) -> Result<usize> {
+    loop {
+        match from.read(buffer) {
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            result => return result,
+        }
+    }
+}
+
+/// Read `count` items stored one after the other, `pos` is the position of the first one
+pub fn read_vec_by_count<T: Plod, R: Read>(
+    from: &mut R,
+    ctx: &T::Context,
+    mut pos: usize,
+    count: usize,
+) -> Result<Vec<T>> {
+    crate::fuzz::check_items::<T>(count)?;
+    let mut items = Vec::new();
+    for _ in 0..count {
+        let item = T::impl_read_from(from, ctx, pos)?;
+        pos += item.size_at_rest();
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Read items stored one after the other and taking `size` bytes in total, `pos` is the position
+/// of the first one
+pub fn read_vec_by_bytes<T: Plod, R: Read>(
+    from: &mut R,
+    ctx: &T::Context,
+    mut pos: usize,
+    mut size: usize,
+) -> Result<Vec<T>> {
+    crate::fuzz::check_len(size)?;
+    let mut items = Vec::new();
+    while size > 0 {
+        let item = T::impl_read_from(from, ctx, pos)?;
+        let item_size = item.size_at_rest();
+        if item_size == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Empty item in a Vec sized in bytes"));
+        }
+        if item_size > size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Item of {} bytes overflows the {} bytes left",
+                    item_size, size
+                ),
+            ));
+        }
+        pos += item_size;
+        size -= item_size;
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Write items one after the other, without their count or size
+pub fn write_vec<T: Plod, W: Write>(
+    to: &mut W,
+    items: &[T],
+    ctx: &T::Context,
+    mut pos: usize,
+) -> Result<()> {
+    for item in items {
+        item.impl_write_to(to, ctx, pos)?;
+        pos += item.size_at_rest();
+    }
+    Ok(())
+}
+
+/// Size in bytes of items written with [`write_vec`]
+pub fn vec_size<T: Plod>(items: &[T]) -> usize {
+    items.iter().map(|item| item.size_at_rest()).sum()
+}
+
+/// Read `count` items of a derived `Vec` field, reporting progress and checking cancellation after
+/// each of them, returns them with the position after them, called by derived code
+#[doc(hidden)]
+pub fn read_items<T: Plod, R: Read>(
+    from: &mut R,
+    ctx: &T::Context,
+    mut pos: usize,
+    count: usize,
+) -> Result<(Vec<T>, usize)> {
+    let mut items = Vec::with_capacity(crate::fuzz::capacity::<T>(count));
+    for _ in 0..count {
+        let item = T::impl_read_from(from, ctx, pos)?;
+        pos += item.size_at_rest();
+        items.push(item);
+        crate::progress::tick(pos);
+        crate::cancel::check()?;
+    }
+    Ok((items, pos))
+}
+
+/// Write the items of a derived `Vec` field, returns the position after them, called by derived
+/// code
+#[doc(hidden)]
+pub fn write_items<T: Plod, W: Write>(
+    to: &mut W,
+    items: &[T],
+    ctx: &T::Context,
+    mut pos: usize,
+) -> Result<usize> {
+    for item in items {
+        item.impl_write_to(to, ctx, pos)?;
+        pos += item.size_at_rest();
+    }
+    Ok(pos)
+}
+
+/// Read a magic value and check that it is the `expected` one
+pub fn check_magic<R: Read>(from: &mut R, expected: &[u8]) -> Result<()> {
+    let mut buffer = vec![0_u8; expected.len()];
+    from.read_exact(&mut buffer)?;
+    if buffer != expected {
+        return Err(Error::other(format!(
+            "Magic value {:02x?} expected, found {:02x?}",
+            expected, buffer
+        )));
+    }
+    Ok(())
+}
+
+/// The error of an enum tag that matches no variant, as returned by derived enums
+pub fn unknown_tag<T>(tag: i128, position: usize) -> Error {
+    Error::other(UnknownTag {
+        type_name: std::any::type_name::<T>(),
+        tag,
+        expected: "",
+        position,
+        skipped: None,
+    })
+}
+
+/// Read an enum whose `tag` bytes have already been read by its container, `pos` is the position
+/// of the bytes after the tag
+///
+/// The derive checks at compile time that the enum stores its tag first, with the size of the
+/// `tag_for` field:
+///
+/// ```compile_fail
+/// # use plod::Plod;
+/// #[derive(Plod)]
+/// #[plod(tag_type(u8), common_fields(length: u16))]
+/// enum Body {
+///     #[plod(tag = 1)]
+///     Ping { length: u16 },
+/// }
+///
+/// #[derive(Plod)]
+/// struct Header {
+///     #[plod(tag_for = payload)]
+///     msg_type: u8,
+///     #[plod(external_tag)]
+///     payload: Body,
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use plod::Plod;
+/// #[derive(Plod)]
+/// #[plod(tag_type(u8))]
+/// enum Body {
+///     #[plod(tag = 1)]
+///     Ping,
+/// }
+///
+/// #[derive(Plod)]
+/// struct Header {
+///     #[plod(tag_for = payload)]
+///     msg_type: u16,
+///     #[plod(external_tag)]
+///     payload: Body,
+/// }
+/// ```
+pub fn read_untagged<T: Plod, R: Read>(
+    from: &mut R,
+    tag: &[u8],
+    ctx: &T::Context,
+    pos: usize,
+) -> Result<T> {
+    let mut tagged = tag.chain(from);
+    T::impl_read_from(&mut tagged, ctx, pos.saturating_sub(tag.len()))
+}
+
+/// Bytes of an enum starting with a tag of `tag_size` bytes
+fn tagged_bytes<T: Plod>(value: &T, tag_size: usize, ctx: &T::Context, pos: usize) -> Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(value.size_at_rest());
+    value.impl_write_to(&mut buffer, ctx, pos)?;
+    if buffer.len() < tag_size {
+        return Err(Error::other(format!(
+            "{} has no tag of {} bytes",
+            std::any::type_name::<T>(),
+            tag_size
+        )));
+    }
+    Ok(buffer)
+}
+
+/// Write only the `tag_size` bytes of the tag of an enum, for its container to store it elsewhere
+pub fn write_tag<T: Plod, W: Write>(
+    to: &mut W,
+    value: &T,
+    tag_size: usize,
+    ctx: &T::Context,
+    pos: usize,
+) -> Result<()> {
+    let buffer = tagged_bytes(value, tag_size, ctx, pos)?;
+    to.write_all(&buffer[..tag_size])
+}
+
+/// Write an enum without the `tag_size` bytes of its tag, written with [`write_tag`], `pos` is the
+/// position of the bytes after the tag
+pub fn write_untagged<T: Plod, W: Write>(
+    to: &mut W,
+    value: &T,
+    tag_size: usize,
+    ctx: &T::Context,
+    pos: usize,
+) -> Result<()> {
+    let buffer = tagged_bytes(value, tag_size, ctx, pos.saturating_sub(tag_size))?;
+    to.write_all(&buffer[tag_size..])
+}
+
+/// Number of bytes needed after `pos` to reach a multiple of `align`
+pub fn padding(pos: usize, align: usize) -> usize {
+    (align - pos % align) % align
+}
+
+/// Skip the padding bytes after `pos` up to a multiple of `align`, returns the number of bytes
+/// skipped
+pub fn read_padding<R: Read>(from: &mut R, pos: usize, align: usize) -> Result<usize> {
+    let padding = padding(pos, align);
+    let skipped = std::io::copy(&mut from.take(padding as u64), &mut std::io::sink())?;
+    if skipped as usize != padding {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+    Ok(padding)
+}
+
+/// Write zeroes after `pos` up to a multiple of `align`, returns the number of bytes written
+pub fn write_padding<W: Write>(to: &mut W, pos: usize, align: usize) -> Result<usize> {
+    let padding = padding(pos, align);
+    std::io::copy(&mut std::io::repeat(0).take(padding as u64), to)?;
+    Ok(padding)
+}
+
+/// Number of bytes needed after `pos` to reach the absolute offset `target`, returns an error of
+/// kind `InvalidData` if `pos` is already past it
+pub fn padding_to(pos: usize, target: usize) -> Result<usize> {
+    target.checked_sub(pos).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Position {} is past the offset {} to pad to", pos, target),
+        )
+    })
+}
+
+/// Skip the bytes after `pos` up to the absolute offset `target`, returns the number of bytes
+/// skipped
+pub fn read_padding_to<R: Read>(from: &mut R, pos: usize, target: usize) -> Result<usize> {
+    let padding = padding_to(pos, target)?;
+    let skipped = std::io::copy(&mut from.take(padding as u64), &mut std::io::sink())?;
+    if skipped as usize != padding {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+    Ok(padding)
+}
+
+/// Write `fill` bytes after `pos` up to the absolute offset `target`, returns the number of bytes
+/// written
+pub fn write_padding_to<W: Write>(to: &mut W, pos: usize, target: usize, fill: u8) -> Result<usize> {
+    let padding = padding_to(pos, target)?;
+    std::io::copy(&mut std::io::repeat(fill).take(padding as u64), to)?;
+    Ok(padding)
+}
+
+/// Size of consecutive items, `None` if any of them doesn't have a fixed size.
+/// For [`Plod::FIXED_SIZE`], like the following helpers.
+pub const fn sum_sizes(sizes: &[Option<usize>]) -> Option<usize> {
+    let mut total = 0;
+    let mut i = 0;
+    while i < sizes.len() {
+        match sizes[i] {
+            Some(size) => total += size,
+            None => return None,
+        }
+        i += 1;
+    }
+    Some(total)
+}
+
+/// Size of `count` items of the same size
+pub const fn repeat_size(size: Option<usize>, count: usize) -> Option<usize> {
+    match size {
+        Some(size) => Some(size * count),
+        None => None,
+    }
+}
+
+/// Size of `count` items of the same size, each one after the first starting at a multiple of
+/// `align` from the first one
+pub const fn repeat_aligned_size(size: Option<usize>, count: usize, align: usize) -> Option<usize> {
+    match (size, count) {
+        (Some(_), 0) => Some(0),
+        (Some(size), count) => Some((count - 1) * size.div_ceil(align) * align + size),
+        (None, _) => None,
+    }
+}
+
+/// Size shared by all alternatives, like enum variants, `None` if they differ
+pub const fn same_size(sizes: &[Option<usize>]) -> Option<usize> {
+    if sizes.is_empty() {
+        return None;
+    }
+    let mut i = 1;
+    while i < sizes.len() {
+        match (sizes[0], sizes[i]) {
+            (Some(first), Some(size)) if first == size => {}
+            _ => return None,
+        }
+        i += 1;
+    }
+    sizes[0]
+}
+
+/// Size rounded up to a multiple of `align`
+pub const fn aligned_size(size: Option<usize>, align: usize) -> Option<usize> {
+    match size {
+        Some(size) => Some(size + (align - size % align) % align),
+        None => None,
+    }
+}
+
+/// Pack booleans 8 per byte, the first one in the lowest bit, unused bits of the last byte are 0
+pub fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let mut packed = vec![0_u8; bits.len().div_ceil(8)];
+    for (i, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+        packed[i / 8] |= 1 << (i % 8);
+    }
+    packed
+}
+
+/// Unpack `count` booleans packed by [`pack_bits`], missing bytes are read as 0
+pub fn unpack_bits(packed: &[u8], count: usize) -> Vec<bool> {
+    (0..count)
+        .map(|i| packed.get(i / 8).is_some_and(|byte| byte & (1 << (i % 8)) != 0))
+        .collect()
+}
+
+/// Pack booleans 8 per byte, the first one in the highest bit, unused bits of the last byte are 0
+pub fn pack_bits_msb_first(bits: &[bool]) -> Vec<u8> {
+    let mut packed = vec![0_u8; bits.len().div_ceil(8)];
+    for (i, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+        packed[i / 8] |= 0x80 >> (i % 8);
+    }
+    packed
+}
+
+/// Unpack `count` booleans packed by [`pack_bits_msb_first`], missing bytes are read as 0
+pub fn unpack_bits_msb_first(packed: &[u8], count: usize) -> Vec<bool> {
+    (0..count)
+        .map(|i| packed.get(i / 8).is_some_and(|byte| byte & (0x80 >> (i % 8)) != 0))
+        .collect()
+}
+
+/// Value returned by a fallible constructor, its error is returned as is if it is a
+/// `std::io::Error` and as an error of kind `InvalidData` otherwise
+pub fn constructed<T, E>(result: std::result::Result<T, E>) -> Result<T>
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    result.map_err(|e| match e.into().downcast::<Error>() {
+        Ok(error) => *error,
+        Err(error) => Error::new(ErrorKind::InvalidData, error),
+    })
+}
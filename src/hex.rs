@@ -0,0 +1,100 @@
+//! Hex strings of values at rest, available with the `hex` feature
+//!
+//! [`to_hex`] and [`from_hex`] turn values into lowercase hex strings and back, to embed wire
+//! values in test fixtures, configuration files or bug reports. Whitespace is ignored when
+//! parsing, so long values can be split in groups and lines.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod, PartialEq, Debug)]
+//! #[plod(big_endian)]
+//! struct Header {
+//!     version: u16,
+//!     flags: u8,
+//! }
+//!
+//! let header = Header { version: 2, flags: 0x80 };
+//! assert_eq!(plod::to_hex(&header).unwrap(), "000280");
+//! assert_eq!(plod::from_hex::<Header>("0002 80").unwrap(), header);
+//!
+//! let error = plod::from_hex::<Header>("00x280").unwrap_err();
+//! assert_eq!(error.to_string(), "Invalid hex digit 'x' at position 2");
+//! ```
+
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+use crate::{Plod, Result};
+
+/// Error payload of an invalid hex string, available with `std::io::Error::get_ref` on errors of
+/// kind `InvalidData`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidHex {
+    /// position of the faulty character in the string, in characters, or length of the string
+    /// if a last digit is missing
+    pub position: usize,
+    /// the faulty character, `None` if a last digit is missing
+    pub found: Option<char>,
+}
+
+impl fmt::Display for InvalidHex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.found {
+            Some(c) => write!(f, "Invalid hex digit {:?} at position {}", c, self.position),
+            None => write!(f, "Missing hex digit at position {}", self.position),
+        }
+    }
+}
+
+impl std::error::Error for InvalidHex {}
+
+/// Lowercase hex string of the bytes `value` is written as
+pub fn to_hex<T: Plod>(value: &T) -> Result<String>
+where
+    T::Context: Default,
+{
+    let mut data = Vec::new();
+    value.write_to(&mut data)?;
+    Ok(encode(&data))
+}
+
+/// Read a value from a hex string, all bytes must be used
+pub fn from_hex<T: Plod>(hex: &str) -> Result<T>
+where
+    T::Context: Default,
+{
+    let data = decode(hex)?;
+    T::from_bytes_exact(&data)
+}
+
+/// Lowercase hex string of `data`
+pub fn encode(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Bytes of a hex string, whitespace is ignored
+pub fn decode(hex: &str) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(hex.len() / 2);
+    let mut high = None;
+    let mut length = 0;
+    for (position, c) in hex.chars().enumerate() {
+        length = position + 1;
+        if c.is_whitespace() {
+            continue;
+        }
+        let nibble = c.to_digit(16).ok_or_else(|| invalid(position, Some(c)))? as u8;
+        match high.take() {
+            Some(high) => data.push(high << 4 | nibble),
+            None => high = Some(nibble),
+        }
+    }
+    if high.is_some() {
+        return Err(invalid(length, None));
+    }
+    Ok(data)
+}
+
+fn invalid(position: usize, found: Option<char>) -> Error {
+    Error::new(ErrorKind::InvalidData, InvalidHex { position, found })
+}
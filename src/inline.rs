@@ -0,0 +1,189 @@
+//! Short bytes and texts stored inline
+//!
+//! Names, keys and addresses of network packets are variable length but short, reading each of
+//! them in a `Vec<u8>` or a `String` costs an allocation. [`InlineBytes<N>`] and [`InlineStr<N>`]
+//! hold at most `N` bytes in the value itself. They are stored like a `Vec<u8>` and a `String`,
+//! with the same size attributes, and a stored size larger than `N` fails to read with an error
+//! of kind `InvalidData`. The derive recognizes them by their path, which must start with `plod::`
+//! like for `Blob`.
+//!
+//! An [`InlineStr`] is always UTF-8 text.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! #[plod(size_type(u8))]
+//! struct Packet {
+//!     key: plod::InlineStr<8>,
+//!     value: plod::InlineBytes<4>,
+//! }
+//!
+//! let packet = Packet::read_from(&mut [2_u8, b'i', b'd', 1, 7].as_slice()).unwrap();
+//! assert_eq!(&*packet.key, "id");
+//! assert_eq!(&*packet.value, [7]);
+//! assert!(Packet::read_from(&mut [9_u8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0].as_slice()).is_err());
+//! ```
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Read};
+use std::ops::Deref;
+
+use crate::Result;
+
+/// At most `N` bytes, stored like a `Vec<u8>`
+#[derive(Clone, Copy)]
+pub struct InlineBytes<const N: usize> {
+    len: usize,
+    data: [u8; N],
+}
+
+impl<const N: usize> InlineBytes<N> {
+    /// No bytes
+    pub const fn new() -> Self {
+        InlineBytes { len: 0, data: [0; N] }
+    }
+
+    /// The first `N` bytes of `bytes`
+    pub fn truncating(bytes: &[u8]) -> Self {
+        let mut result = InlineBytes::new();
+        result.len = bytes.len().min(N);
+        result.data[..result.len].copy_from_slice(&bytes[..result.len]);
+        result
+    }
+
+    /// Read `size` bytes stored at `pos`, called by derived code
+    #[doc(hidden)]
+    pub fn read_sized<R: Read>(from: &mut R, size: usize, pos: usize) -> Result<Self> {
+        if size > N {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} bytes at offset {} don't fit in InlineBytes<{}>", size, pos, N),
+            ));
+        }
+        let mut result = InlineBytes::new();
+        from.read_exact(&mut result.data[..size])?;
+        result.len = size;
+        Ok(result)
+    }
+}
+
+impl<const N: usize> Default for InlineBytes<N> {
+    fn default() -> Self {
+        InlineBytes::new()
+    }
+}
+
+impl<const N: usize> Deref for InlineBytes<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for InlineBytes<N> {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Returns an error of kind `InvalidInput` if there are more than `N` bytes
+impl<const N: usize> TryFrom<&[u8]> for InlineBytes<N> {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() > N {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} bytes don't fit in InlineBytes<{}>", bytes.len(), N),
+            ));
+        }
+        Ok(InlineBytes::truncating(bytes))
+    }
+}
+
+impl<const N: usize> PartialEq for InlineBytes<N> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<const N: usize> Eq for InlineBytes<N> {}
+
+impl<const N: usize> std::hash::Hash for InlineBytes<N> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<const N: usize> fmt::Debug for InlineBytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// A UTF-8 text of at most `N` bytes, stored like a `String`
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct InlineStr<const N: usize>(InlineBytes<N>);
+
+impl<const N: usize> InlineStr<N> {
+    /// An empty text
+    pub const fn new() -> Self {
+        InlineStr(InlineBytes::new())
+    }
+
+    /// The longest start of `text` that fits in `N` bytes without splitting a character
+    pub fn truncating(text: &str) -> Self {
+        let mut len = text.len().min(N);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        InlineStr(InlineBytes::truncating(&text.as_bytes()[..len]))
+    }
+
+    /// The text of bytes read at `pos`, called by derived code
+    #[doc(hidden)]
+    pub fn from_utf8(bytes: InlineBytes<N>, pos: usize) -> Result<Self> {
+        crate::arena::text(&bytes, pos)?;
+        Ok(InlineStr(bytes))
+    }
+}
+
+impl<const N: usize> Deref for InlineStr<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match std::str::from_utf8(&self.0) {
+            Ok(text) => text,
+            Err(_) => unreachable!("an InlineStr is built from UTF-8 text"),
+        }
+    }
+}
+
+impl<const N: usize> AsRef<str> for InlineStr<N> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+/// Returns an error of kind `InvalidInput` if the text is longer than `N` bytes
+impl<const N: usize> TryFrom<&str> for InlineStr<N> {
+    type Error = Error;
+
+    fn try_from(text: &str) -> Result<Self> {
+        InlineBytes::try_from(text.as_bytes()).map(InlineStr)
+    }
+}
+
+impl<const N: usize> fmt::Debug for InlineStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<const N: usize> fmt::Display for InlineStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
@@ -0,0 +1,143 @@
+//! Command line inspection of files, available with the `inspect` feature
+//!
+//! A layout only exists in the crate that derives it, so the inspector is a function generic
+//! over the top level type that your own binary calls:
+//! ```ignore
+//! fn main() -> std::process::ExitCode {
+//!     plod::inspect::main::<MyFormat>()
+//! }
+//! ```
+//! `my-inspect <file>` prints the parsed value as an annotated tree of fields with their offsets
+//! and `my-inspect <file> <field path>` writes the raw bytes of a single field to the standard
+//! output, paths are those of [`Span`], like `Header.records[2].name`.
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::process::ExitCode;
+
+use crate::dump::{spans, Span};
+use crate::{Plod, Result};
+
+/// Maximum number of bytes displayed for a field in a tree
+const MAX_BYTES: usize = 16;
+
+/// Run the inspector on the command line arguments, see the module documentation
+pub fn main<T: Plod>() -> ExitCode
+where
+    T::Context: Default,
+{
+    let args: Vec<String> = std::env::args().collect();
+    let (file, path) = match args.as_slice() {
+        [_, file] => (file, None),
+        [_, file, path] => (file, Some(path)),
+        _ => {
+            let program = args.first().map(String::as_str).unwrap_or("inspect");
+            eprintln!("Usage: {} <file> [<field path>]", program);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run::<T>(file, path.map(String::as_str)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {}", file, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run<T: Plod>(file: &str, path: Option<&str>) -> Result<()>
+where
+    T::Context: Default,
+{
+    let bytes = std::fs::read(file)?;
+    let (value, _) = T::read_from_slice(&bytes)?;
+    let spans = spans(&value);
+    let mut stdout = std::io::stdout().lock();
+    match path {
+        Some(path) => match extract(&bytes, &spans, path) {
+            Some(field) => stdout.write_all(field),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No field {}", path),
+            )),
+        },
+        None => stdout.write_all(tree(&bytes, &spans).as_bytes()),
+    }
+}
+
+/// Bytes of the field at `path`, without its length prefix if it has one. A field made of sub
+/// fields, like a struct, covers all of them. `None` if there is no such field.
+pub fn extract<'a>(bytes: &'a [u8], spans: &[Span], path: &str) -> Option<&'a [u8]> {
+    if let Some(span) = spans.iter().find(|s| s.path == path) {
+        return bytes.get(span.start..span.end());
+    }
+    let spans = spans.iter().filter(|s| {
+        s.path
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with(['.', '[', ':']))
+    });
+    let (start, end) = spans.fold(None, |range, s| match range {
+        None => Some((s.start, s.end())),
+        Some((start, end)) => Some((s.start.min(start), s.end().max(end))),
+    })?;
+    bytes.get(start..end)
+}
+
+/// Annotated tree of the fields of a value: one line per field with its offset, nested fields
+/// are indented under their parent
+pub fn tree(bytes: &[u8], spans: &[Span]) -> String {
+    let mut out = String::new();
+    let mut previous: Vec<&str> = Vec::new();
+    for span in spans.iter() {
+        let components = components(&span.path);
+        let (leaf, parents) = components.split_last().unwrap();
+        // print the parents that were not printed by the previous span
+        let common = previous
+            .iter()
+            .zip(parents)
+            .take_while(|(a, b)| a == b)
+            .count();
+        for (depth, parent) in parents.iter().enumerate().skip(common) {
+            let _ = writeln!(out, "{:08x}  {}{}", span.start, "  ".repeat(depth), parent);
+        }
+        let data = bytes
+            .get(span.start..span.end().min(bytes.len()))
+            .unwrap_or(&[]);
+        let mut hex: Vec<String> = data
+            .iter()
+            .take(MAX_BYTES)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        if data.len() > MAX_BYTES {
+            hex.push(format!("... ({} bytes)", data.len()));
+        }
+        let indent = "  ".repeat(parents.len());
+        let _ = writeln!(
+            out,
+            "{:08x}  {}{}: {}",
+            span.start,
+            indent,
+            leaf,
+            hex.join(" ")
+        );
+        previous = parents.to_vec();
+    }
+    out
+}
+
+/// Split a path into fields and indexes, a variant stays with what precedes it:
+/// `A.b[1]::C.d` becomes `A`, `b`, `[1]::C`, `d`
+fn components(path: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    for (i, c) in path.char_indices() {
+        if c == '.' || c == '[' {
+            if i > start {
+                result.push(&path[start..i]);
+            }
+            start = if c == '.' { i + 1 } else { i };
+        }
+    }
+    result.push(&path[start..]);
+    result
+}
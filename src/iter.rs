@@ -0,0 +1,216 @@
+//! Iteration over a stream of records
+//!
+//! Many formats are just the same record repeated until the end of the file. [`iter`] reads
+//! them one at a time, ending cleanly when the input ends between two records and producing an
+//! error of kind `UnexpectedEof` when it ends in the middle of one.
+//!
+//! When the number of records is known beforehand, [`read_n`] and [`write_all`] read and write
+//! them without a wrapper type. [`read_all`] reads the records of a whole file at once.
+//!
+//! [`iter_region`] reads the records of a region of known size in bytes, like the content of a
+//! `Vec` with a byte size, one at a time instead of collecting them.
+
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::size::SizeReader;
+use crate::{Plod, Result};
+
+/// Read exactly `count` records of type `T` stored one after the other in `reader`.
+/// The count is checked against current [`Limits`](crate::fuzz::Limits) since it usually comes
+/// from the input.
+pub fn read_n<T: Plod, R: Read>(reader: &mut R, count: usize) -> Result<Vec<T>>
+where
+    T::Context: Default,
+{
+    crate::fuzz::check_len(count)?;
+    let ctx = T::Context::default();
+    // positions are the bytes actually read, which the size at rest may not be
+    let mut from = SizeReader::new(reader);
+    let mut records = Vec::with_capacity(crate::fuzz::capacity::<T>(count));
+    for _ in 0..count {
+        let pos = from.size();
+        let record = T::impl_read_from(&mut from, &ctx, pos)?;
+        crate::progress::tick(from.size());
+        crate::cancel::check()?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Read all the records of type `T` stored one after the other in `reader`, until it ends
+/// between two records. A truncated last record is an error of kind `UnexpectedEof`.
+pub fn read_all<T: Plod, R: Read>(reader: R) -> Result<Vec<T>>
+where
+    T::Context: Default,
+{
+    iter(reader).collect()
+}
+
+/// Write all `records` one after the other to `writer`
+pub fn write_all<T: Plod, W: Write>(writer: &mut W, records: &[T]) -> Result<()>
+where
+    T::Context: Default,
+{
+    let ctx = T::Context::default();
+    let mut pos = 0;
+    for record in records {
+        record.impl_write_to(writer, &ctx, pos)?;
+        pos += record.size_at_rest();
+    }
+    Ok(())
+}
+
+/// Iterate over the records of type `T` stored one after the other in `reader`
+pub fn iter<T: Plod, R: Read>(reader: R) -> Records<T, R>
+where
+    T::Context: Default,
+{
+    Records::with_context(reader, T::Context::default())
+}
+
+/// Iterator over records read from a reader, created with [`iter`]
+pub struct Records<T: Plod, R: Read> {
+    reader: R,
+    ctx: T::Context,
+    pos: usize,
+    done: bool,
+}
+
+impl<T: Plod, R: Read> Records<T, R> {
+    /// Iterate over the records of `reader`, each one being read with `ctx`
+    pub fn with_context(reader: R, ctx: T::Context) -> Self {
+        Records {
+            reader,
+            ctx,
+            pos: 0,
+            done: false,
+        }
+    }
+
+    /// Number of bytes read so far
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Get back the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_next(&mut self) -> Option<Result<T>> {
+        // read a first byte to tell a clean end of input from a truncated record
+        let mut first = [0_u8; 1];
+        match crate::helpers::read_some(&mut self.reader, &mut first) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        let mut from = SizeReader::new(first.as_slice().chain(&mut self.reader));
+        let value = T::impl_read_from(&mut from, &self.ctx, self.pos);
+        if value.is_ok() {
+            self.pos += from.size();
+            crate::progress::tick(self.pos);
+            if let Err(e) = crate::cancel::check() {
+                return Some(Err(e));
+            }
+        }
+        Some(value)
+    }
+}
+
+impl<T: Plod, R: Read> Iterator for Records<T, R> {
+    type Item = Result<T>;
+
+    /// Read the next record, the iteration stops after the first error
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.read_next();
+        if !matches!(next, Some(Ok(_))) {
+            self.done = true;
+        }
+        next
+    }
+}
+
+/// Iterate over the records of type `T` stored one after the other in the next `size` bytes of
+/// `reader`
+pub fn iter_region<T: Plod, R: Read>(reader: &mut R, size: usize) -> RegionIter<'_, T, R>
+where
+    T::Context: Default,
+{
+    RegionIter::with_context(reader, size, T::Context::default())
+}
+
+/// Iterator over the records of a region of a reader, created with [`iter_region`]. A record
+/// overflowing the region is an error of kind `UnexpectedEof`.
+pub struct RegionIter<'r, T: Plod, R: Read> {
+    reader: &'r mut R,
+    ctx: T::Context,
+    pos: usize,
+    left: usize,
+    done: bool,
+}
+
+impl<'r, T: Plod, R: Read> RegionIter<'r, T, R> {
+    /// Iterate over the records of the next `size` bytes of `reader`, each one being read with `ctx`
+    pub fn with_context(reader: &'r mut R, size: usize, ctx: T::Context) -> Self {
+        RegionIter {
+            reader,
+            ctx,
+            pos: 0,
+            left: size,
+            done: false,
+        }
+    }
+
+    /// Number of bytes read so far
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of bytes of the region not read yet
+    pub fn remaining(&self) -> usize {
+        self.left
+    }
+
+    /// Skip the records that have not been read, so that the reader is at the end of the region,
+    /// returns the number of bytes skipped
+    pub fn finish(self) -> Result<usize> {
+        let left = self.left as u64;
+        let skipped = std::io::copy(&mut self.reader.take(left), &mut std::io::sink())?;
+        if skipped != left {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+        Ok(self.left)
+    }
+
+    fn read_next(&mut self) -> Result<T> {
+        let mut from = (&mut *self.reader).take(self.left as u64);
+        let value = T::impl_read_from(&mut from, &self.ctx, self.pos)?;
+        // the bytes actually read, the region limits them
+        let size = self.left - from.limit() as usize;
+        self.left -= size;
+        self.pos += size;
+        crate::progress::tick(self.pos);
+        crate::cancel::check()?;
+        Ok(value)
+    }
+}
+
+impl<T: Plod, R: Read> Iterator for RegionIter<'_, T, R> {
+    type Item = Result<T>;
+
+    /// Read the next record, the iteration stops at the end of the region or after the first error
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.left == 0 {
+            return None;
+        }
+        let next = self.read_next();
+        if next.is_err() {
+            self.done = true;
+        }
+        Some(next)
+    }
+}
@@ -0,0 +1,532 @@
+//! Static description of the at-rest layout of a type
+//!
+//! A [`Layout`] is generated by `#[derive(Plod)]` and returned by [`Plod::describe`](crate::Plod::describe).
+//! It describes the binary format without needing a value, this is what exporters like
+//! [`export::kaitai`](crate::export::kaitai) are built on.
+//!
+//! Nested types are not described inline but referenced through [`Layout::Type`], this keeps
+//! recursive data structures finite.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::ops::RangeInclusive;
+
+/// Endianness of a primitive at rest
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    /// Most significant byte first
+    Big,
+    /// Least significant byte first
+    Little,
+    /// Endianness of the target
+    Native,
+}
+
+impl Endianness {
+    /// Replace `Native` with the actual endianness of the target
+    pub fn resolve(self) -> Self {
+        match self {
+            Endianness::Native if cfg!(target_endian = "big") => Endianness::Big,
+            Endianness::Native => Endianness::Little,
+            e => e,
+        }
+    }
+}
+
+/// A primitive number stored at rest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Primitive {
+    /// rust name of the type (`u16`, `f32`, ...)
+    pub ty: &'static str,
+    /// size in bytes
+    pub size: usize,
+    /// byte order at rest
+    pub endianness: Endianness,
+}
+
+impl Primitive {
+    /// Is this primitive a signed integer
+    pub fn is_signed(&self) -> bool {
+        self.ty.starts_with('i')
+    }
+
+    /// Is this primitive a floating point number
+    pub fn is_float(&self) -> bool {
+        self.ty.starts_with('f')
+    }
+}
+
+/// A magic value that prefixes a struct or a variant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Magic {
+    /// type used to store the magic
+    pub ty: Primitive,
+    /// the value as written in the source code, alternatives are separated by `|`
+    pub value: &'static str,
+    /// the bytes at rest of the first value, the one written unless the magic is kept
+    pub bytes: Vec<u8>,
+    /// all accepted values
+    pub values: Vec<i128>,
+    /// the first field retains the magic instead of being read
+    pub keep: bool,
+}
+
+/// A named field of a struct or a variant, skipped fields are not listed
+#[derive(Clone, Debug)]
+pub struct Field {
+    /// field name, or its index for tuple structs and variants
+    pub name: &'static str,
+    /// field layout
+    pub layout: Layout,
+}
+
+impl Field {
+    /// Fields of a `#[plod(flatten)]` field, its own ones if it is a plain struct, without
+    /// length, magics or alignment, or itself otherwise
+    pub fn flatten(self) -> Vec<Field> {
+        match self.layout.resolve() {
+            Layout::Struct(Struct {
+                length: None,
+                magic: None,
+                trailer: None,
+                align_size: None,
+                fields,
+                ..
+            }) => fields,
+            _ => vec![self],
+        }
+    }
+}
+
+/// Description of a struct
+#[derive(Clone, Debug)]
+pub struct Struct {
+    /// full rust type name
+    pub name: &'static str,
+    /// type of the total length written before the struct
+    pub length: Option<Primitive>,
+    /// magic value written before fields
+    pub magic: Option<Magic>,
+    /// fields in at rest order
+    pub fields: Vec<Field>,
+    /// magic value written after fields
+    pub trailer: Option<Magic>,
+    /// the size is padded to a multiple of this
+    pub align_size: Option<usize>,
+}
+
+impl Struct {
+    /// Fields actually read from the stream (ie without the kept magic)
+    pub fn stored_fields(&self) -> &[Field] {
+        stored_fields(&self.fields, self.magic.as_ref().is_some_and(|m| m.keep))
+    }
+}
+
+/// Fields without the first one when it retains a tag or a magic
+fn stored_fields(fields: &[Field], keep: bool) -> &[Field] {
+    if keep && !fields.is_empty() {
+        &fields[1..]
+    } else {
+        fields
+    }
+}
+
+/// The tag pattern of a variant
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    /// the pattern as written in the source code
+    pub pattern: &'static str,
+    /// the values matched by the pattern, empty if they cannot be known
+    pub values: Vec<RangeInclusive<i128>>,
+    /// the guard on the context following the pattern, as written in the source code
+    pub guard: Option<&'static str>,
+}
+
+impl Tag {
+    /// Number of tag values matched by this pattern
+    pub fn count(&self) -> u128 {
+        self.values
+            .iter()
+            .map(|r| r.end().abs_diff(*r.start()) + 1)
+            .sum()
+    }
+
+    /// A single value if the pattern matches only one
+    pub fn single(&self) -> Option<i128> {
+        match self.values.as_slice() {
+            [r] if r.start() == r.end() => Some(*r.start()),
+            _ => None,
+        }
+    }
+}
+
+/// Description of an enum variant
+#[derive(Clone, Debug)]
+pub struct Variant {
+    /// variant name
+    pub name: &'static str,
+    /// tag pattern, `None` for the default variant
+    pub tag: Option<Tag>,
+    /// a field retains the tag instead of being read
+    pub keep_tag: bool,
+    /// index of the field retaining the tag when `keep_tag` is set, the first one unless `is_tag`
+    /// is set on another one
+    pub tag_field: usize,
+    /// value subtracted from the tag before storing it in the field retaining it
+    pub keep_diff: Option<i128>,
+    /// the variant is never read or written
+    pub skip: bool,
+    /// magic value written after the tag
+    pub magic: Option<Magic>,
+    /// fields in at rest order
+    pub fields: Vec<Field>,
+    /// magic value written after fields
+    pub trailer: Option<Magic>,
+}
+
+impl Variant {
+    /// Fields actually read from the stream (ie without the kept tag or magic)
+    pub fn stored_fields(&self) -> Cow<'_, [Field]> {
+        if self.keep_tag && self.tag_field > 0 && self.tag_field < self.fields.len() {
+            let mut fields = self.fields.clone();
+            fields.remove(self.tag_field);
+            return Cow::Owned(fields);
+        }
+        let keep_magic = self.magic.as_ref().is_some_and(|m| m.keep);
+        Cow::Borrowed(stored_fields(&self.fields, self.keep_tag || keep_magic))
+    }
+}
+
+/// Description of an enum
+#[derive(Clone, Debug)]
+pub struct Enum {
+    /// full rust type name
+    pub name: &'static str,
+    /// type of the total length written before the enum
+    pub length: Option<Primitive>,
+    /// type of the discriminant at rest
+    pub tag: Primitive,
+    /// size of every variant after the tag, shorter variants are padded
+    pub variant_size: Option<usize>,
+    /// all variants in declaration order
+    pub variants: Vec<Variant>,
+    /// the size is padded to a multiple of this
+    pub align_size: Option<usize>,
+}
+
+/// The at rest layout of a type
+#[derive(Clone, Debug)]
+pub enum Layout {
+    /// A number
+    Primitive(Primitive),
+    /// A tuple, `()` is an empty tuple
+    Tuple(Vec<Layout>),
+    /// A fixed size array
+    Array {
+        /// item count
+        len: usize,
+        /// item layout
+        item: Box<Layout>,
+        /// items start at a multiple of this from the first one, padded with zeroes
+        item_align: Option<usize>,
+    },
+    /// A size prefixed `Vec`
+    Vec {
+        /// type of the size prefix
+        size: Primitive,
+        /// size is counted in bytes instead of items
+        byte_sized: bool,
+        /// size stored minus the actual size, 1 for `size_is_next`
+        size_adjust: i64,
+        /// size stored counts units of this many items or bytes, 1 unless `size_unit` is set
+        size_unit: usize,
+        /// name of the previous field storing the size, `None` if the size prefixes the items
+        length_field: Option<&'static str>,
+        /// item layout
+        item: Box<Layout>,
+        /// items start at a multiple of this from the first one, padded with zeroes
+        item_align: Option<usize>,
+    },
+    /// A size prefixed region whose bytes are encoded at rest (compressed for example)
+    Encoded {
+        /// name of the encoding (`zlib`, `zstd`, `lz4` or the path of a transform)
+        encoding: &'static str,
+        /// type of the size prefix, counting encoded bytes
+        size: Primitive,
+        /// layout of the decoded bytes, `None` for raw bytes
+        layout: Option<Box<Layout>>,
+    },
+    /// A `Vec<u8>` holding all the remaining bytes of the input or of the enclosing length
+    /// prefixed type
+    Rest,
+    /// An `Option` item present only if bytes remain in the input or in the enclosing length
+    /// prefixed type
+    IfRemaining(Box<Layout>),
+    /// Fill bytes up to an absolute offset, counted from the start of the outermost value
+    PadTo {
+        /// offset of the next field
+        offset: usize,
+        /// value of the bytes written
+        fill: u8,
+    },
+    /// A derived struct
+    Struct(Struct),
+    /// A derived enum
+    Enum(Enum),
+    /// Reference to another type implementing `Plod`
+    Type {
+        /// full rust type name
+        name: &'static str,
+        /// function describing the type
+        layout: fn() -> Layout,
+        /// function reading a value of the type with a default context and dropping it, returns
+        /// its size at rest. It is how opaque types are skipped, `None` if the context of the
+        /// type has no `Default`.
+        read: Option<fn(&mut dyn std::io::Read) -> crate::Result<usize>>,
+    },
+    /// A type whose layout is unknown (manual implementation of `Plod`)
+    Opaque {
+        /// full rust type name
+        name: &'static str,
+    },
+}
+
+impl Layout {
+    /// Follow `Type` references until an actual layout is found
+    pub fn resolve(&self) -> Layout {
+        match self {
+            Layout::Type { layout, .. } => layout().resolve(),
+            l => l.clone(),
+        }
+    }
+
+    /// Fixed size at rest if this layout doesn't depend on the value
+    pub fn fixed_size(&self) -> Option<usize> {
+        fn fields_size(
+            magic: &Option<Magic>,
+            fields: &[Field],
+            trailer: &Option<Magic>,
+        ) -> Option<usize> {
+            let magic = [magic, trailer]
+                .into_iter()
+                .flatten()
+                .map(|m| m.ty.size)
+                .sum();
+            fields
+                .iter()
+                .try_fold(magic, |n, f| Some(n + f.layout.fixed_size()?))
+        }
+        match self {
+            Layout::Primitive(p) => Some(p.size),
+            Layout::Tuple(items) => items.iter().try_fold(0, |n, i| Some(n + i.fixed_size()?)),
+            Layout::Array {
+                len,
+                item,
+                item_align,
+            } => {
+                let size = item.fixed_size()?;
+                // no padding after the last item
+                Some(match len {
+                    0 => 0,
+                    len => (len - 1) * aligned(size, *item_align) + size,
+                })
+            }
+            Layout::Vec { .. }
+            | Layout::Encoded { .. }
+            | Layout::Rest
+            | Layout::IfRemaining(_)
+            | Layout::PadTo { .. }
+            | Layout::Opaque { .. } => None,
+            Layout::Struct(s) => {
+                let length = s.length.as_ref().map(|l| l.size).unwrap_or(0);
+                let content = fields_size(&s.magic, s.stored_fields(), &s.trailer)?;
+                Some(length + aligned(content, s.align_size))
+            }
+            Layout::Enum(e) => {
+                let length = e.length.as_ref().map(|l| l.size).unwrap_or(0);
+                if let Some(variant_size) = e.variant_size {
+                    return Some(length + aligned(e.tag.size + variant_size, e.align_size));
+                }
+                let mut sizes = e
+                    .variants
+                    .iter()
+                    .filter(|v| !v.skip)
+                    .map(|v| fields_size(&v.magic, &v.stored_fields(), &v.trailer));
+                let first = sizes.next()??;
+                if sizes.all(|s| s == Some(first)) {
+                    Some(length + aligned(e.tag.size + first, e.align_size))
+                } else {
+                    None
+                }
+            }
+            Layout::Type { name, layout, .. } => {
+                thread_local! {
+                    static SIZING: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+                }
+                // a type containing itself (through a `Box`) has no fixed size
+                if SIZING.with(|s| s.borrow().contains(name)) {
+                    return None;
+                }
+                SIZING.with(|s| s.borrow_mut().push(name));
+                let size = layout().fixed_size();
+                SIZING.with(|s| s.borrow_mut().pop());
+                size
+            }
+        }
+    }
+
+    /// Offset and layout of the field at `path`, relative to the start of this layout, if its
+    /// offset doesn't depend on the value. A path is made of field names separated by dots and of
+    /// array indexes, like `points[1].x`, tuple items are named by their index.
+    /// Fields within enums or after a field without a fixed size have no such offset.
+    pub fn field(&self, path: &str) -> Option<(usize, Layout)> {
+        let mut offset = 0;
+        let mut layout = self.resolve();
+        for component in path.split('.').filter(|c| !c.is_empty()) {
+            let (name, indexes) = match component.find('[') {
+                Some(i) => component.split_at(i),
+                None => (component, ""),
+            };
+            if !name.is_empty() {
+                let (field_offset, field) = layout.named_field(name)?;
+                offset += field_offset;
+                layout = field.resolve();
+            }
+            for index in indexes.split_terminator(']') {
+                let index: usize = index.strip_prefix('[')?.parse().ok()?;
+                let (item, item_align) = match layout {
+                    Layout::Array {
+                        len,
+                        item,
+                        item_align,
+                    } if index < len => (item.resolve(), item_align),
+                    _ => return None,
+                };
+                offset += index * aligned(item.fixed_size()?, item_align);
+                layout = item;
+            }
+        }
+        Some((offset, layout))
+    }
+
+    /// Offset and layout of a direct field of a struct or tuple
+    fn named_field(&self, name: &str) -> Option<(usize, Layout)> {
+        match self {
+            Layout::Struct(s) => {
+                let mut offset = s.length.as_ref().map(|l| l.size).unwrap_or(0);
+                offset += s.magic.as_ref().map(|m| m.ty.size).unwrap_or(0);
+                for field in s.stored_fields() {
+                    if field.name == name {
+                        return Some((offset, field.layout.clone()));
+                    }
+                    offset += field.layout.fixed_size()?;
+                }
+                None
+            }
+            Layout::Tuple(items) => {
+                let index: usize = name.parse().ok()?;
+                let mut offset = 0;
+                for item in items.get(..index)? {
+                    offset += item.fixed_size()?;
+                }
+                Some((offset, items.get(index)?.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Full rust type name of this layout, if any
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Layout::Struct(s) => Some(s.name),
+            Layout::Enum(e) => Some(e.name),
+            Layout::Type { name, .. } | Layout::Opaque { name } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Round `size` up to a multiple of `align`
+pub fn aligned(size: usize, align: Option<usize>) -> usize {
+    match align {
+        Some(align) => size.div_ceil(align) * align,
+        None => size,
+    }
+}
+
+/// Remove module paths from a full rust type name: `a::B<c::D>` becomes `B<D>`
+pub fn short_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut word = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+        } else if c == ':' {
+            word.clear();
+        } else {
+            result.push_str(&word);
+            word.clear();
+            result.push(c);
+        }
+    }
+    result.push_str(&word);
+    result
+}
+
+/// Convert a rust type or field name to a snake_case identifier usable by most tools,
+/// tuple field indexes are prefixed with `field_`
+pub fn snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut previous_lower = false;
+    for c in short_name(name).chars() {
+        if c.is_uppercase() {
+            if previous_lower {
+                result.push('_');
+            }
+            result.extend(c.to_lowercase());
+            previous_lower = false;
+        } else if c.is_alphanumeric() {
+            result.push(c);
+            previous_lower = true;
+        } else if !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+            previous_lower = false;
+        }
+    }
+    let result = result.trim_end_matches('_').to_string();
+    match result.chars().next() {
+        Some(c) if c.is_ascii_digit() => format!("field_{}", result),
+        _ => result,
+    }
+}
+
+/// Reader given to [`Layout::Type`] by the derive: [`DiscardDefault`] reads and drops a value of
+/// `T` when its context implements `Default`, [`DiscardNone`] gives `None` otherwise. The first
+/// one that applies is chosen at compile time.
+#[doc(hidden)]
+pub struct Discard<T>(pub std::marker::PhantomData<T>);
+
+#[doc(hidden)]
+pub trait DiscardDefault {
+    fn get(&self) -> Option<fn(&mut dyn std::io::Read) -> crate::Result<usize>>;
+}
+
+impl<T: crate::Plod> DiscardDefault for Discard<T>
+where
+    T::Context: Default,
+{
+    fn get(&self) -> Option<fn(&mut dyn std::io::Read) -> crate::Result<usize>> {
+        Some(|mut from| T::read_from(&mut from).map(|value| value.size_at_rest()))
+    }
+}
+
+#[doc(hidden)]
+pub trait DiscardNone {
+    fn get(&self) -> Option<fn(&mut dyn std::io::Read) -> crate::Result<usize>>;
+}
+
+impl<T> DiscardNone for &Discard<T> {
+    fn get(&self) -> Option<fn(&mut dyn std::io::Read) -> crate::Result<usize>> {
+        None
+    }
+}
@@ -0,0 +1,89 @@
+//! Lenient reading of collections containing unknown enum variants
+//!
+//! Real world files often contain vendor specific records. When the items of a `Vec` marked with
+//! `#[plod(skip_unknown)]` are `length_prefixed` and one of them contains an unknown enum tag, the
+//! bytes of this item are skipped and reading goes on with the next one. [`collect`] gives back
+//! what was skipped.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{Error, Read, Take};
+
+use crate::Result;
+
+/// Error payload of an unknown enum tag, available with `std::io::Error::get_ref` on errors of
+/// kind `Other`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownTag {
+    /// full rust type name of the enum
+    pub type_name: &'static str,
+    /// value of the tag, cast to i128, 0 for tag types that are not primitives
+    pub tag: i128,
+    /// patterns of the accepted tags separated by commas, empty if they are not known
+    pub expected: &'static str,
+    /// position of the tag
+    pub position: usize,
+    /// number of bytes skipped with the item containing the tag, `None` if it could not be skipped
+    pub skipped: Option<usize>,
+}
+
+impl fmt::Display for UnknownTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.expected.is_empty() {
+            write!(f, "{} tag {} not found", self.type_name, self.tag)?;
+        } else {
+            write!(f, "{} expected {}, found {}", self.type_name, self.expected, self.tag)?;
+        }
+        write!(f, " at offset {:#x}", self.position)
+    }
+}
+
+impl std::error::Error for UnknownTag {}
+
+thread_local! {
+    static SKIPPED: RefCell<Option<Vec<UnknownTag>>> = const { RefCell::new(None) };
+}
+
+/// Run `f` and return the unknown tags skipped by reads on the current thread in the meantime
+pub fn collect<T, F: FnOnce() -> T>(f: F) -> (T, Vec<UnknownTag>) {
+    let previous = SKIPPED.with(|s| s.replace(Some(Vec::new())));
+    let result = f();
+    let skipped = SKIPPED.with(|s| s.replace(previous)).unwrap_or_default();
+    (result, skipped)
+}
+
+/// Skip the rest of a length prefixed region after an unknown tag, called by derived code.
+/// `size` is the size of the whole region including its prefix.
+#[doc(hidden)]
+pub fn skip_region<R: Read>(error: Error, region: &mut Take<R>, size: usize) -> Error {
+    let tag = match error.get_ref().and_then(|e| e.downcast_ref::<UnknownTag>()) {
+        Some(tag) => *tag,
+        None => return error,
+    };
+    if let Err(e) = std::io::copy(region, &mut std::io::sink()) {
+        return e;
+    }
+    if region.limit() > 0 {
+        return Error::from(std::io::ErrorKind::UnexpectedEof);
+    }
+    Error::other(UnknownTag {
+        skipped: Some(size),
+        ..tag
+    })
+}
+
+/// Get the number of bytes of an item that was skipped, or the error back if it couldn't be,
+/// called by derived code
+#[doc(hidden)]
+pub fn skipped(error: Error) -> Result<usize> {
+    let tag = match error.get_ref().and_then(|e| e.downcast_ref::<UnknownTag>()) {
+        Some(tag) if tag.skipped.is_some() => *tag,
+        _ => return Err(error),
+    };
+    SKIPPED.with(|s| {
+        if let Some(skipped) = s.borrow_mut().as_mut() {
+            skipped.push(tag);
+        }
+    });
+    Ok(tag.skipped.unwrap())
+}
@@ -46,19 +46,20 @@
 //! Other reasons:
 //! * Plod is based on generic `Read`and `Write` traits.
 //! * Plod knows about endianness during serialization, it reorders bytes for you.
-//! * Plod doesn't use unsafe or transmute for read and write
+//! * Plod doesn't use unsafe or transmute for read and write, except for the opt-in
+//!   [`pod`] casts
 //! * Plod doesn't need you to play with `#[repr()]`
 //!
 //! Plod is for *plain old data*, which means that is well suited for known, existing, binary formats.
 //! *But*:
 //! - If you want a way to serialize *your own* data and be able to read it later, you should
-//! prefer serde which can serialize any data into many more formats that can be self describing.
+//!   prefer serde which can serialize any data into many more formats that can be self describing.
 //!
 //! - If your file format is *not binary* and not easily supported by serde, you may look at
-//! [`nom`] for parsing it.
+//!   [`nom`] for parsing it.
 //!
 //! - If your data only contains primary types and all you want is speed you may take a look at
-//! [`plain`], [`pod`] and [`nue`].
+//!   [`plain`], [`pod`] and [`nue`].
 //!
 //! # Special cases
 //!
@@ -67,6 +68,81 @@
 //! - `Vec` are represented with their size at the start (either in bytes or in item count)
 //! - Skipped values are not represented, they are ignored when written and replaced with `default()`when read.
 //!
+//! Fields that read up to the end of the data, like `#[plod(rest)]`, `#[plod(size_after)]` or
+//! `#[plod(if_remaining)]`, can only be followed by the end of the reader or of a length prefixed
+//! region. Types that add their own bytes after the fields reject them at compile time:
+//! ```compile_fail
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! #[plod(little_endian, align_size(4))]
+//! struct Padded {
+//!     x: u8,
+//!     #[plod(rest)]
+//!     z: Vec<u8>,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! #[plod(little_endian, align_size(4))]
+//! struct Padded {
+//!     x: u8,
+//!     #[plod(if_remaining)]
+//!     z: Option<u8>,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! #[plod(little_endian, length_footer(u16))]
+//! struct Footed {
+//!     x: u8,
+//!     #[plod(rest)]
+//!     z: Vec<u8>,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! #[plod(little_endian, length_footer(u16))]
+//! struct Footed {
+//!     x: u8,
+//!     #[plod(if_remaining)]
+//!     z: Option<u8>,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! #[plod(little_endian, align_size(4))]
+//! struct Padded {
+//!     x: u8,
+//!     #[plod(size_type(u8), size_after)]
+//!     z: Vec<u8>,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! #[plod(little_endian, length_footer(u16))]
+//! struct Footed {
+//!     x: u8,
+//!     #[plod(size_type(u8), size_after)]
+//!     z: Vec<u8>,
+//! }
+//! ```
+//!
 //! Document endianness and it inheritance
 //!
 //! How to call Plod trait methods
@@ -75,7 +151,90 @@
 //!
 #![deny(missing_docs)]
 
-use std::io::{Read, Write};
+// derived types of this crate refer to it by name
+extern crate self as plod;
+
+use std::io::{Read, Seek, Write};
+
+#[cfg(feature = "futures-io")]
+pub mod async_io;
+pub mod arena;
+mod array;
+pub mod blob;
+mod boxed;
+pub mod bounded;
+pub mod cancel;
+pub mod checksum;
+#[cfg(feature = "codec")]
+pub mod codec;
+pub mod compress;
+pub mod context;
+pub mod convert;
+pub mod decoder;
+pub mod diff;
+#[cfg(feature = "digest")]
+pub mod digest;
+pub mod dump;
+#[cfg(feature = "embedded-io")]
+pub mod embedded;
+pub mod error;
+pub mod export;
+pub mod ext;
+pub mod framing;
+pub mod fuzz;
+pub mod helpers;
+#[cfg(feature = "hex")]
+pub mod hex;
+#[cfg(feature = "inspect")]
+pub mod inspect;
+pub mod inline;
+pub mod iter;
+pub mod layout;
+pub mod lenient;
+pub mod migrate;
+pub mod observer;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod patch;
+pub mod pod;
+pub mod preserve;
+pub mod profile;
+pub mod progress;
+pub mod reader;
+pub mod size;
+pub mod slice;
+pub mod split;
+pub mod testing;
+pub mod text;
+pub mod time;
+pub mod trace;
+pub mod varint;
+pub mod verify;
+pub mod view;
+mod walk;
+
+pub use blob::Blob;
+pub use bounded::Bounded;
+pub use convert::convert;
+pub use diff::diff;
+#[cfg(feature = "digest")]
+pub use crate::digest::digest;
+pub use dump::dump;
+#[cfg(feature = "hex")]
+pub use crate::hex::{from_hex, to_hex};
+pub use ext::{read_file, write_file, PlodExt};
+pub use framing::{read_framed, write_framed, Framing};
+pub use fuzz::Limits;
+pub use inline::{InlineBytes, InlineStr};
+pub use iter::{iter, iter_region, read_all, read_n, write_all, RegionIter};
+pub use observer::Observer;
+pub use preserve::Preserved;
+pub use size::{SizeReader, SizeWriter};
+pub use split::{read_header_then, read_header_then_with, Payload};
+pub use time::{DosDateTime, Micros, Millis, Nanos, Seconds, Unix32Time, WindowsFiletime};
+pub use view::PlodWrite;
+#[cfg(feature = "rayon")]
+pub use parallel::par_read_records;
 
 /// plod results Result uses io errors
 pub type Result<T> = std::result::Result<T, std::io::Error>;
@@ -93,11 +252,28 @@ pub use plod_derive::Plod;
 pub trait Plod: Sized {
     /// Context passed to read and write methods, if you don't need one, just use `()`
     /// The context will be passed down to any sub method called by `read_from` and `write_to`
-    /// It is passed using `into()` on `&Context`. This means that `From<&Context>` should be implemented
-    /// for any other context used inside the current data structure. Especially, it implies that you
-    /// must `impl  From<&Context> for ()` since all primitive types use `()` as a context.
+    /// Derived types pass it as is to fields with the same context, `&()` to fields without
+    /// context and use `into()` on `&Context` otherwise. This means that `From<&Context>` should be
+    /// implemented for any other context used inside the current data structure.
+    /// The [`context!`] macro generates these implementations.
     type Context;
 
+    /// Description of the at rest layout in markdown, generated by the derive from its attributes
+    /// and also attached as documentation to the generated impl.
+    /// Empty for manual implementations unless they override it.
+    const LAYOUT_DOC: &'static str = "";
+
+    /// Size at rest when it is the same for every value, `None` otherwise.
+    /// Derived types also get an inherent `SIZE` constant, usable in array lengths, when all their
+    /// fields may have a fixed size. Derived structs then also get an `OFFSET_<FIELD>` constant per
+    /// stored field (`OFFSET_0` for tuple structs), its position from the start of the value.
+    const FIXED_SIZE: Option<usize> = None;
+
+    /// Size of the tag of a derived enum when it is the first thing stored, which is what
+    /// `tag_for` and `external_tag` fields split off, `None` otherwise.
+    #[doc(hidden)]
+    const LEADING_TAG_SIZE: Option<usize> = None;
+
     /// Size once serialized (including tag if any)
     // also used internally by byte sized Vec
     fn size_at_rest(&self) -> usize;
@@ -105,10 +281,82 @@ pub trait Plod: Sized {
     /// Read this structure from a reader
     /// Returns `std::io::Error` in case or error
     /// Returns an error of kind `std::io::ErrorKind::Other` if an unknown enum tag was found
+    /// Errors of derived types contain an [`error::FieldError`] naming the field that failed
+    /// Reads interrupted by a signal (`std::io::ErrorKind::Interrupted`) are retried
     fn read_from<R: Read>(from: &mut R) -> Result<Self>
         where Self::Context : Default
     { Self::impl_read_from(from, &Self::Context::default(), 0) }
 
+    /// Same as `read_from` with a given context, for contexts that have no default like the ones
+    /// borrowing data
+    fn read_with<R: Read>(from: &mut R, ctx: &Self::Context) -> Result<Self> {
+        Self::impl_read_from(from, ctx, 0)
+    }
+
+    /// Read this structure from the start of a slice and return it with the bytes left
+    /// Returns an error of kind `std::io::ErrorKind::UnexpectedEof` containing a
+    /// [`slice::OutOfBounds`] with the exact offset if the slice is too short
+    fn read_from_slice(data: &[u8]) -> Result<(Self, &[u8])>
+        where Self::Context : Default
+    {
+        let mut reader = slice::SliceReader::new(data);
+        let value = Self::impl_read_from(&mut reader, &Self::Context::default(), 0)?;
+        Ok((value, reader.remaining()))
+    }
+
+    /// Read this structure from a slice that contains nothing else
+    /// Returns an error of kind `std::io::ErrorKind::InvalidData` containing a
+    /// [`slice::TrailingBytes`] if bytes are left after the value, other errors are the same as
+    /// `read_from_slice`
+    fn from_bytes_exact(data: &[u8]) -> Result<Self>
+        where Self::Context : Default
+    {
+        let (value, rest) = Self::read_from_slice(data)?;
+        if !rest.is_empty() {
+            return Err(slice::trailing_bytes(data.len() - rest.len(), rest.len()));
+        }
+        Ok(value)
+    }
+
+    /// Read this structure from a reader that contains nothing else, the reader is read to its
+    /// end
+    /// Returns an error of kind `std::io::ErrorKind::InvalidData` containing a
+    /// [`slice::TrailingBytes`] if bytes are left after the value
+    fn read_exactly_from<R: Read>(from: &mut R) -> Result<Self>
+        where Self::Context : Default
+    {
+        let mut counter = SizeReader::new(&mut *from);
+        let value = Self::impl_read_from(&mut counter, &Self::Context::default(), 0)?;
+        let offset = counter.size();
+        let remaining = std::io::copy(from, &mut std::io::sink())?;
+        if remaining > 0 {
+            return Err(slice::trailing_bytes(offset, remaining as usize));
+        }
+        Ok(value)
+    }
+
+    /// Read this structure from the start of a slice and return it with the number of bytes it
+    /// occupied, the next value starts at this offset
+    /// Errors are the same as `read_from_slice`
+    fn read_prefix(data: &[u8]) -> Result<(Self, usize)>
+        where Self::Context : Default
+    {
+        let (value, rest) = Self::read_from_slice(data)?;
+        Ok((value, data.len() - rest.len()))
+    }
+
+    /// Read this structure from a seekable reader, fields marked with `#[plod(seek_to=<offset>)]`
+    /// are read at their absolute offset
+    fn read_from_seek<R: Read + Seek>(from: &mut R) -> Result<Self>
+        where Self::Context : Default
+    { Self::impl_read_from_seek(from, &Self::Context::default(), 0) }
+
+    /// Same as `read_from_seek` with all parameters, generated by the derive.
+    /// Manual implementations use `impl_read_from` unless they override it.
+    fn impl_read_from_seek<R: Read + Seek>(from: &mut R, ctx: &Self::Context, pos: usize) -> Result<Self> {
+        Self::impl_read_from(from, ctx, pos)
+    }
+
     /// Same as `read_from` with all parameters, you must implement this one.
     /// You should call this one if you are reading from a Plod implementation.
     /// `pos` is the position in bytes in the reader, it is used to handle padding and alignment.
@@ -116,14 +364,192 @@ pub trait Plod: Sized {
 
     /// Write this structure to a writer
     /// Returns `std::io::Error` in case or error
+    /// Errors of derived types contain an [`error::FieldError`] naming the field that failed
+    /// Debug builds panic if the number of bytes written is not `size_at_rest()`.
+    /// Within [`verify::with_verify`], the bytes are read back and checked before being written.
     fn write_to<W: Write>(&self, to: &mut W) -> Result<()>
         where Self::Context : Default
-    { self.impl_write_to(to, &mut Self::Context::default(), 0) }
+    {
+        if verify::active() {
+            return verify::write_verified(self, to);
+        }
+        self.write_with(to, &Self::Context::default())
+    }
+
+    /// Same as `write_to` with a given context, for contexts that have no default like the ones
+    /// borrowing data. Writes are not verified by [`verify::with_verify`], which needs a default
+    /// context to read the bytes back.
+    fn write_with<W: Write>(&self, to: &mut W, ctx: &Self::Context) -> Result<()> {
+        if cfg!(debug_assertions) {
+            let mut counter = size::SizeWriter::with_writer(to);
+            self.impl_write_to(&mut counter, ctx, 0)?;
+            let expected = self.size_at_rest();
+            assert!(counter.size() == expected, "{}", size::size_mismatch::<Self>(expected, counter.size()));
+            return Ok(());
+        }
+        self.impl_write_to(to, ctx, 0)
+    }
+
+    /// Write this structure at the start of a slice without allocating and return the number of
+    /// bytes written.
+    /// Returns an error of kind `std::io::ErrorKind::WriteZero` containing a
+    /// [`slice::OutOfBounds`] if the slice is too short, nothing is written in this case
+    fn write_to_slice(&self, buf: &mut [u8]) -> Result<usize>
+        where Self::Context : Default
+    {
+        let needed = self.size_at_rest();
+        if needed > buf.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                slice::OutOfBounds { offset: 0, needed, available: buf.len() },
+            ));
+        }
+        let available = buf.len();
+        let mut to = buf;
+        self.impl_write_to(&mut to, &Self::Context::default(), 0)?;
+        Ok(available - to.len())
+    }
 
     /// Same as `write_to` with all parameters, you must implement this one.
     /// You should call this one if you are writing from a Plod implementation.
     /// `pos` is the position in bytes in the writer, it is used to handle padding and alignment.
     fn impl_write_to<W: Write>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()>;
+
+    /// Write this structure to a seekable writer, sizes marked with `#[plod(patched_size)]` are
+    /// written after their content by seeking back instead of being computed beforehand and fields
+    /// marked with `#[plod(seek_to=<offset>)]` are written at their absolute offset
+    fn write_to_seek<W: Write + Seek>(&self, to: &mut W) -> Result<()>
+        where Self::Context : Default
+    { self.impl_write_to_seek(to, &Self::Context::default(), 0) }
+
+    /// Same as `write_to_seek` with all parameters, generated by the derive.
+    /// Manual implementations use `impl_write_to` unless they override it.
+    fn impl_write_to_seek<W: Write + Seek>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+        self.impl_write_to(to, ctx, pos)
+    }
+
+    /// Read this structure from a `bytes::Buf`, advancing it by the number of bytes read.
+    /// Returns an error of kind `std::io::ErrorKind::UnexpectedEof` if there are not enough bytes
+    #[cfg(feature = "bytes")]
+    fn read_from_buf<B: bytes::Buf>(buf: &mut B) -> Result<Self>
+        where Self::Context : Default
+    { Self::impl_read_from(&mut bytes::Buf::reader(buf), &Self::Context::default(), 0) }
+
+    /// Write this structure to a `bytes::BufMut`
+    /// Returns an error of kind `std::io::ErrorKind::WriteZero` if the buffer is full
+    #[cfg(feature = "bytes")]
+    fn write_to_buf<B: bytes::BufMut>(&self, buf: &mut B) -> Result<()>
+        where Self::Context : Default
+    { self.impl_write_to(&mut bytes::BufMut::writer(buf), &Self::Context::default(), 0) }
+
+    /// Static description of the at rest layout of this type, generated by the derive.
+    /// Manual implementations describe themselves as opaque unless they override it.
+    fn describe() -> layout::Layout {
+        layout::Layout::Opaque { name: std::any::type_name::<Self>() }
+    }
+
+    /// Offset of the field at `path` from the start of the value when it doesn't depend on the
+    /// value, see [`Layout::field`](layout::Layout::field) for the path syntax.
+    /// [`patch::patch_field`] uses it to rewrite a single field in place.
+    fn offset_of(path: &str) -> Option<usize> {
+        Self::describe().field(path).map(|(offset, _)| offset)
+    }
+
+    /// Go past a value without building it and return its size at rest. Collections are skipped
+    /// without being allocated, only sizes and enum tags are decoded.
+    /// Types whose layout is not known (manual implementations) are read instead with a default
+    /// context, this returns an error of kind `Unsupported` if the context of such a nested type
+    /// has no `Default`.
+    fn skip_from<R: Read>(from: &mut R) -> Result<usize>
+        where Self::Context : Default
+    {
+        walk_from::<Self, R>(from, false)
+    }
+
+    /// Check that a value could be read without building it, like [`skip_from`](Plod::skip_from)
+    /// but magic values are also checked.
+    /// Only the structure is checked: sizes, tags and magic values. Values are not, so checksums,
+    /// `equals`, `Bounded` ranges, `construct_with` and hooks like `after_read` are not checked,
+    /// except in types that are read because their layout is not known.
+    fn validate_from<R: Read>(from: &mut R) -> Result<()>
+        where Self::Context : Default
+    {
+        walk_from::<Self, R>(from, true).map(|_| ())
+    }
+
+    /// List the byte ranges of this value at rest labeled with their field path, generated by the derive.
+    /// `path` is the path of this value and `pos` its position relative to the top level value.
+    /// Manual implementations produce a single span unless they override it.
+    fn impl_spans(&self, path: &str, pos: usize, spans: &mut Vec<dump::Span>) {
+        spans.push(dump::Span::new(path.to_string(), pos, self.size_at_rest()))
+    }
+
+    /// The type implements [`impl_read_columns`](Plod::impl_read_columns) and
+    /// [`impl_write_columns`](Plod::impl_write_columns), checked at compile time by `columnar`
+    /// vectors.
+    #[doc(hidden)]
+    const COLUMNAR: bool = false;
+
+    /// Read `count` values stored column by column: the first field of every value, then the
+    /// second field of every value, and so on. Generated by the derive for `#[plod(columnar)]`
+    /// vectors, other types return an error of kind `Unsupported` and a `columnar` vector of them
+    /// doesn't compile:
+    ///
+    /// ```compile_fail
+    /// use plod::Plod;
+    ///
+    /// #[derive(Plod)]
+    /// #[plod(magic(u8 = 1))]
+    /// struct Marked {
+    ///     a: u8,
+    /// }
+    ///
+    /// #[derive(Plod)]
+    /// struct Marks {
+    ///     #[plod(columnar, size_type(u8))]
+    ///     marks: Vec<Marked>,
+    /// }
+    /// ```
+    fn impl_read_columns<R: Read>(from: &mut R, ctx: &Self::Context, pos: usize, count: usize) -> Result<Vec<Self>> {
+        let _ = (from, ctx, pos, count);
+        Err(not_columnar::<Self>())
+    }
+
+    /// Write values column by column, the counterpart of [`impl_read_columns`](Plod::impl_read_columns).
+    fn impl_write_columns<W: Write>(items: &[Self], to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+        let _ = (items, to, ctx, pos);
+        Err(not_columnar::<Self>())
+    }
+}
+
+/// Error of types that cannot be stored in columns
+fn not_columnar<T>() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("{} cannot be stored in columns", std::any::type_name::<T>()),
+    )
+}
+
+/// Walk a value from its layout, or read it if its layout is not known
+fn walk_from<T: Plod, R: Read>(from: &mut R, check_magic: bool) -> Result<usize>
+    where T::Context : Default
+{
+    let layout = T::describe();
+    if let layout::Layout::Opaque { .. } = layout {
+        return T::read_from(from).map(|value| value.size_at_rest());
+    }
+    let walker = walk::Walker { source: None, swap: false, check_magic };
+    walker.walk(&layout, from, &mut std::io::sink())
+}
+
+/// Error of types with `#[plod(seek_to=<offset>)]` fields read or written without `Seek`,
+/// called by derived code
+#[doc(hidden)]
+pub fn not_seekable<T>() -> Result<T> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "seek_to fields need read_from_seek and write_to_seek",
+    ))
 }
 
 // everything in this library is public and is tested via integration tests
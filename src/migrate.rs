@@ -0,0 +1,151 @@
+//! Reading old versions of a format
+//!
+//! Long lived formats store a version number before the data. Each version is its own derived
+//! type and old versions are upgraded with `From` conversions. [`Migrations`] reads the version
+//! number, reads the matching type and converts it to the latest one, while writing always
+//! produces the latest version. [`Migrations::impl_read_from`] and [`Migrations::impl_write_to`]
+//! take the position of the version number, for manual implementations of [`Plod`] that contain a
+//! versioned value.
+//!
+//! ```
+//! use plod::layout::Endianness;
+//! use plod::migrate::Migrations;
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! struct V1 { id: u8 }
+//!
+//! #[derive(Plod, PartialEq, Debug)]
+//! struct V2 { id: u16, flags: u8 }
+//!
+//! impl From<V1> for V2 {
+//!     fn from(v1: V1) -> Self {
+//!         V2 { id: v1.id as u16, flags: 0 }
+//!     }
+//! }
+//!
+//! let migrations = Migrations::<V2>::new(1, Endianness::Little, 2).version::<V1>(1);
+//! let v2 = migrations.read_from(&mut [1_u8, 7].as_slice()).unwrap();
+//! assert_eq!(v2, V2 { id: 7, flags: 0 });
+//! ```
+
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::layout::Endianness;
+use crate::{Plod, Result};
+
+/// Read a version at a position and upgrade it to the latest one
+type Upgrade<L> = Box<dyn Fn(&mut dyn Read, usize) -> Result<L>>;
+
+/// The known versions of a format whose latest version is `L`
+pub struct Migrations<L> {
+    size: usize,
+    endianness: Endianness,
+    current: u64,
+    versions: Vec<(u64, Upgrade<L>)>,
+}
+
+impl<L: Plod + 'static> Migrations<L>
+where
+    L::Context: Default,
+{
+    /// Version numbers are stored on `size` bytes (at most 8) before the data, `current` is the
+    /// version number of `L`
+    pub fn new(size: usize, endianness: Endianness, current: u64) -> Self {
+        assert!(size <= 8, "Version numbers are at most 8 bytes");
+        let latest: Upgrade<L> =
+            Box::new(|mut from, pos| L::impl_read_from(&mut from, &L::Context::default(), pos));
+        Migrations {
+            size,
+            endianness,
+            current,
+            versions: vec![(current, latest)],
+        }
+    }
+
+    /// Register the type of an older version
+    pub fn version<V: Plod + Into<L> + 'static>(mut self, number: u64) -> Self
+    where
+        V::Context: Default,
+    {
+        self.versions.push((
+            number,
+            Box::new(|mut from, pos| {
+                V::impl_read_from(&mut from, &V::Context::default(), pos).map(Into::into)
+            }),
+        ));
+        self
+    }
+
+    /// Register the type of an older version that is first upgraded to the intermediate version
+    /// `I`, this avoids writing a conversion from each version to the latest one
+    pub fn version_via<V, I>(mut self, number: u64) -> Self
+    where
+        V: Plod + Into<I> + 'static,
+        V::Context: Default,
+        I: Into<L> + 'static,
+    {
+        self.versions.push((
+            number,
+            Box::new(|mut from, pos| {
+                V::impl_read_from(&mut from, &V::Context::default(), pos)
+                    .map(|v| Into::<I>::into(v).into())
+            }),
+        ));
+        self
+    }
+
+    /// Read a value of any known version and upgrade it to the latest one.
+    /// Returns an error of kind `Other` containing an [`UnknownTag`](crate::lenient::UnknownTag)
+    /// if the version is not known
+    pub fn read_from<R: Read>(&self, from: &mut R) -> Result<L> {
+        self.impl_read_from(from, 0)
+    }
+
+    /// Like [`read_from`](Migrations::read_from), `pos` is the position of the version number
+    pub fn impl_read_from<R: Read>(&self, from: &mut R, pos: usize) -> Result<L> {
+        let mut buffer = [0_u8; 8];
+        from.read_exact(&mut buffer[..self.size])?;
+        let version = self.decode(&buffer[..self.size]);
+        match self.versions.iter().find(|(number, _)| *number == version) {
+            Some((_, upgrade)) => upgrade(from, pos + self.size),
+            None => Err(crate::helpers::unknown_tag::<L>(version as i128, pos)),
+        }
+    }
+
+    /// Write a value as the latest version, after its version number.
+    /// Returns an error of kind `InvalidInput` if the latest version number doesn't fit in its
+    /// size
+    pub fn write_to<W: Write>(&self, value: &L, to: &mut W) -> Result<()> {
+        self.impl_write_to(value, to, 0)
+    }
+
+    /// Like [`write_to`](Migrations::write_to), `pos` is the position of the version number
+    pub fn impl_write_to<W: Write>(&self, value: &L, to: &mut W, pos: usize) -> Result<()> {
+        if self.size < 8 && self.current >> (8 * self.size) != 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Version {} does not fit in {} bytes",
+                    self.current, self.size
+                ),
+            ));
+        }
+        let bytes = self.current.to_le_bytes();
+        let mut number = bytes[..self.size].to_vec();
+        if self.endianness.resolve() == Endianness::Big {
+            number.reverse();
+        }
+        to.write_all(&number)?;
+        value.impl_write_to(to, &L::Context::default(), pos + self.size)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> u64 {
+        let mut le = [0_u8; 8];
+        le[..bytes.len()].copy_from_slice(bytes);
+        if self.endianness.resolve() == Endianness::Big {
+            le[..bytes.len()].reverse();
+        }
+        u64::from_le_bytes(le)
+    }
+}
@@ -0,0 +1,116 @@
+//! Hooks on nonconforming data
+//!
+//! Forensic tools want to know what is odd about a file without failing to read it. When the
+//! context of a derived type implements [`Observer`], the generated code calls it:
+//! - [`Observer::on_unknown_tag`] when an enum tag matches no variant, before the error is
+//!   returned, so that it is also called for items skipped with `skip_unknown`,
+//! - [`Observer::on_padding`] when `reserved` bytes are not zero, or `pad_to_offset` bytes are not
+//!   the `fill` byte,
+//! - [`Observer::on_truncated`] when the data ends before an `if_remaining` field.
+//!
+//! Hooks take `&self`, so statistics are collected with interior mutability. The choice is made at
+//! compile time from the concrete context type: a type generic over its context never calls
+//! hooks, and a nested type only calls them if its own context implements [`Observer`].
+//!
+//! ```
+//! use plod::Plod;
+//! use std::cell::Cell;
+//!
+//! #[derive(Default)]
+//! struct Stats {
+//!     padding: Cell<usize>,
+//! }
+//!
+//! impl plod::Observer for Stats {
+//!     fn on_padding(&self, _position: usize, _bytes: &[u8]) {
+//!         self.padding.set(self.padding.get() + 1);
+//!     }
+//! }
+//!
+//! #[derive(Plod)]
+//! #[plod(context = Stats)]
+//! struct Header {
+//!     #[plod(reserved(2))]
+//!     version: u8,
+//! }
+//!
+//! let stats = Stats::default();
+//! Header::read_with(&mut [0_u8, 7, 1].as_slice(), &stats).unwrap();
+//! assert_eq!(stats.padding.get(), 1);
+//! ```
+
+use std::io::Read;
+
+use crate::lenient::UnknownTag;
+use crate::Result;
+
+/// Callbacks on nonconforming data, all of them do nothing by default
+pub trait Observer {
+    /// An enum tag matches no variant
+    fn on_unknown_tag(&self, _tag: &UnknownTag) {}
+
+    /// Padding at `position` doesn't have the expected value
+    fn on_padding(&self, _position: usize, _bytes: &[u8]) {}
+
+    /// The data ends at `position`, before the `if_remaining` field named `field`
+    fn on_truncated(&self, _position: usize, _field: &'static str) {}
+}
+
+/// Observer of a context, used by the derive: [`ObserveImpl`] returns the context itself when it
+/// implements [`Observer`] and [`ObserveNone`] returns `None` otherwise.
+#[doc(hidden)]
+pub struct Observe<'a, C>(pub &'a C);
+
+#[doc(hidden)]
+pub trait ObserveImpl<'a> {
+    fn observer(&self) -> Option<&'a dyn Observer>;
+}
+
+impl<'a, C: Observer> ObserveImpl<'a> for Observe<'a, C> {
+    fn observer(&self) -> Option<&'a dyn Observer> {
+        Some(self.0)
+    }
+}
+
+#[doc(hidden)]
+pub trait ObserveNone<'a> {
+    fn observer(&self) -> Option<&'a dyn Observer>;
+}
+
+impl<'a, C> ObserveNone<'a> for &Observe<'a, C> {
+    fn observer(&self) -> Option<&'a dyn Observer> {
+        None
+    }
+}
+
+/// Report reserved bytes that are not zero, called by derived code
+#[doc(hidden)]
+pub fn check_reserved(observer: Option<&dyn Observer>, position: usize, bytes: &[u8]) {
+    if let Some(observer) = observer {
+        if bytes.iter().any(|b| *b != 0) {
+            observer.on_padding(position, bytes);
+        }
+    }
+}
+
+/// Read padding after `pos` up to the absolute offset `target` and report it if it isn't made of
+/// `fill` bytes, returns the number of bytes read, called by derived code
+#[doc(hidden)]
+pub fn read_padding_to<R: Read>(
+    from: &mut R,
+    pos: usize,
+    target: usize,
+    fill: u8,
+    observer: Option<&dyn Observer>,
+) -> Result<usize> {
+    let observer = match observer {
+        Some(observer) => observer,
+        None => return crate::helpers::read_padding_to(from, pos, target),
+    };
+    let mut bytes = vec![0_u8; crate::helpers::padding_to(pos, target)?];
+    from.read_exact(&mut bytes)?;
+    if bytes.iter().any(|b| *b != fill) {
+        observer.on_padding(pos, &bytes);
+    }
+    Ok(bytes.len())
+}
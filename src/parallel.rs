@@ -0,0 +1,111 @@
+//! Parallel decoding of record collections, available with the `rayon` feature
+//!
+//! Decoding a buffer of records is sequential because the start of a record is only known once
+//! the previous one has been read. When records have a fixed size, or are prefixed with their
+//! length with `#[plod(length_prefixed(...))]`, their boundaries can be found without decoding
+//! them, so [`par_read_records`] splits the buffer first and decodes the records on the rayon
+//! thread pool.
+
+use std::io::{Error, ErrorKind};
+
+use rayon::prelude::*;
+
+use crate::layout::{Endianness, Layout, Primitive};
+use crate::{Plod, Result};
+
+/// Decode the records of type `T` stored one after the other in `bytes`, in parallel.
+///
+/// `T` must have a fixed size at rest or be length prefixed, an error of kind `Unsupported` is
+/// returned otherwise. The buffer must end with a whole record. Current
+/// [`Limits`](crate::fuzz::Limits) apply to each record on every thread.
+pub fn par_read_records<T>(bytes: &[u8]) -> Result<Vec<T>>
+where
+    T: Plod + Send,
+    T::Context: Default,
+{
+    let records = split::<T>(bytes)?;
+    let limits = crate::fuzz::current_limits();
+    records
+        .into_par_iter()
+        .map(|(start, end)| {
+            let read = || {
+                let mut record = &bytes[start..end];
+                let value = T::impl_read_from(&mut record, &T::Context::default(), start)?;
+                if !record.is_empty() {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("{} unread bytes in the record at {}", record.len(), start),
+                    ));
+                }
+                Ok(value)
+            };
+            match limits {
+                Some(limits) => crate::fuzz::with_limits(limits, read),
+                None => read(),
+            }
+        })
+        .collect()
+}
+
+/// Start and end of each record
+fn split<T: Plod>(bytes: &[u8]) -> Result<Vec<(usize, usize)>> {
+    let layout = T::describe().resolve();
+    if let Some(size) = layout.fixed_size().filter(|s| *s > 0) {
+        if !bytes.len().is_multiple_of(size) {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "{} bytes are not a whole number of {} byte records",
+                    bytes.len(),
+                    size
+                ),
+            ));
+        }
+        crate::fuzz::check_len(bytes.len() / size)?;
+        return Ok((0..bytes.len())
+            .step_by(size)
+            .map(|s| (s, s + size))
+            .collect());
+    }
+    let length = match &layout {
+        Layout::Struct(s) => s.length.as_ref(),
+        Layout::Enum(e) => e.length.as_ref(),
+        _ => None,
+    };
+    let length = length.ok_or_else(|| {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "Records of {} have neither a fixed size nor a length prefix",
+                std::any::type_name::<T>()
+            ),
+        )
+    })?;
+    let mut records = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let content = start + length.size;
+        let prefix = bytes
+            .get(start..content)
+            .ok_or_else(|| Error::from(ErrorKind::UnexpectedEof))?;
+        let end = content
+            .checked_add(decode(length, prefix)?)
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| Error::from(ErrorKind::UnexpectedEof))?;
+        records.push((start, end));
+        crate::fuzz::check_len(records.len())?;
+        start = end;
+    }
+    Ok(records)
+}
+
+/// Value of an unsigned length prefix
+fn decode(length: &Primitive, bytes: &[u8]) -> Result<usize> {
+    let mut le = bytes.to_vec();
+    if length.endianness.resolve() == Endianness::Big {
+        le.reverse();
+    }
+    le.resize(16, 0);
+    let value = u128::from_le_bytes(le.try_into().unwrap());
+    usize::try_from(value).map_err(|_| Error::new(ErrorKind::InvalidData, "Length too large"))
+}
@@ -0,0 +1,147 @@
+//! Rewriting a single field in place
+//!
+//! When a field is at an offset that doesn't depend on the value, see
+//! [`Plod::offset_of`], it can be rewritten without reading and writing back the whole value.
+//! [`patch_field`] seeks to the field and writes the new value with the endianness of the
+//! layout, after checking that it has the type of the field.
+//!
+//! ```
+//! use std::io::Cursor;
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! #[plod(big_endian)]
+//! struct Header {
+//!     version: u16,
+//!     flags: [u8; 2],
+//!     #[plod(size_type(u32))]
+//!     data: Vec<u8>,
+//! }
+//!
+//! let mut image = Cursor::new(vec![0, 1, 0, 0, 0, 0, 0, 1, 42]);
+//! plod::patch::patch_field::<Header, _, _>(&mut image, 0, "flags[1]", &1_u8).unwrap();
+//! plod::patch::patch_field::<Header, _, _>(&mut image, 0, "version", &2_u16).unwrap();
+//! assert_eq!(image.into_inner(), vec![0, 2, 0, 1, 0, 0, 0, 1, 42]);
+//! ```
+
+use std::io::{Error, ErrorKind, Seek, SeekFrom, Write};
+
+use crate::layout::{Endianness, Layout};
+use crate::{Plod, Result};
+
+/// A value that can replace a field at rest
+pub trait PatchValue {
+    /// Bytes of this value at rest in place of a field of the given layout, an error of kind
+    /// `InvalidInput` if the layout is not the one of this value
+    fn patch_bytes(&self, layout: &Layout) -> Result<Vec<u8>>;
+}
+
+fn mismatch(layout: &Layout) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("The value doesn't match the field layout {:?}", layout),
+    )
+}
+
+macro_rules! patch_primitive {
+    ($($ty:ident)*) => {
+        $(
+            impl PatchValue for $ty {
+                fn patch_bytes(&self, layout: &Layout) -> Result<Vec<u8>> {
+                    match layout {
+                        Layout::Primitive(p) if p.ty == stringify!($ty) => {
+                            Ok(match p.endianness.resolve() {
+                                Endianness::Big => self.to_be_bytes().to_vec(),
+                                _ => self.to_le_bytes().to_vec(),
+                            })
+                        }
+                        _ => Err(mismatch(layout)),
+                    }
+                }
+            }
+
+            impl<const N: usize> PatchValue for [$ty; N] {
+                fn patch_bytes(&self, layout: &Layout) -> Result<Vec<u8>> {
+                    match layout {
+                        Layout::Array {
+                            len,
+                            item,
+                            item_align: None,
+                        } if *len == N => {
+                            let item = item.resolve();
+                            let mut bytes = Vec::new();
+                            for value in self {
+                                bytes.extend(value.patch_bytes(&item)?);
+                            }
+                            Ok(bytes)
+                        }
+                        _ => Err(mismatch(layout)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+patch_primitive!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+
+/// Is `layout` the one of a value described by `own`: same type, or arrays of the same type
+fn same_layout(layout: &Layout, own: &Layout) -> bool {
+    match (layout, own) {
+        (
+            Layout::Array {
+                len,
+                item,
+                item_align,
+            },
+            Layout::Array {
+                len: own_len,
+                item: own_item,
+                item_align: own_align,
+            },
+        ) => len == own_len && item_align == own_align && same_layout(item, own_item),
+        _ => layout.name().is_some() && layout.name() == own.name(),
+    }
+}
+
+/// Derived types and arrays of them are written with their own layout, which must have a fixed
+/// size
+impl<T: Plod> PatchValue for T
+where
+    T::Context: Default,
+{
+    fn patch_bytes(&self, layout: &Layout) -> Result<Vec<u8>> {
+        let same_type = same_layout(layout, &T::describe());
+        let size = layout.fixed_size();
+        if !same_type || size != Some(self.size_at_rest()) {
+            return Err(mismatch(layout));
+        }
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Rewrite the field at `path` of a value of type `T` that starts at `start` in `to`.
+/// Returns an error of kind `NotFound` if the field has no fixed offset, see
+/// [`Plod::offset_of`]. The writer is left after the field.
+pub fn patch_field<T: Plod, V: PatchValue, W: Write + Seek>(
+    to: &mut W,
+    start: u64,
+    path: &str,
+    value: &V,
+) -> Result<()> {
+    let (offset, layout) = T::describe().field(path).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "{} has no field {} at a fixed offset",
+                std::any::type_name::<T>(),
+                path
+            ),
+        )
+    })?;
+    let bytes = value.patch_bytes(&layout)?;
+    to.seek(SeekFrom::Start(start + offset as u64))?;
+    to.write_all(&bytes)
+}
@@ -0,0 +1,172 @@
+//! Plain old data casts for fixed layout types
+//!
+//! A struct deriving `Plod` with `#[plod(pod_cast)]` and `#[repr(C)]` has the same bytes in
+//! memory and at rest when its endianness is the one of the target. The derive checks the
+//! layout and implements [`Pod`], reading and writing such a value is then a single copy and
+//! record arrays can be cast or copied at once with [`cast_slice`] and [`read_records`].
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Clone, Copy, PartialEq, Debug)]
+//! #[plod(little_endian, pod_cast)]
+//! #[repr(C)]
+//! struct Sample {
+//!     time: u32,
+//!     value: [i16; 2],
+//! }
+//!
+//! let bytes = [1, 0, 0, 0, 2, 0, 3, 0, 4, 0, 0, 0, 5, 0, 6, 0];
+//! let samples = plod::pod::read_records::<Sample>(&bytes).unwrap();
+//! assert_eq!(samples[1], Sample { time: 4, value: [5, 6] });
+//! ```
+//!
+//! Only the endianness can be set on such a struct and its fields, and attributes like `no_pos`
+//! or `expected_size` that don't change its bytes. Anything that adds bytes around the fields or
+//! checks the values read is rejected, since a copy of the memory would skip it:
+//!
+//! ```compile_fail
+//! # use plod::Plod;
+//! #[derive(Plod, Clone, Copy)]
+//! #[plod(little_endian, pod_cast)]
+//! #[repr(C)]
+//! struct Checked {
+//!     value: u16,
+//!     #[plod(checksum(plod::checksum::Crc::CRC16_ARC))]
+//!     crc: u16,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! # use plod::Plod;
+//! #[derive(Plod, Clone, Copy)]
+//! #[plod(little_endian, pod_cast, trailer_magic(u16 = 0xbeef))]
+//! #[repr(C)]
+//! struct Trailed {
+//!     value: u32,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! # use plod::Plod;
+//! #[derive(Plod, Clone, Copy)]
+//! #[plod(little_endian, pod_cast, length_footer(u16))]
+//! #[repr(C)]
+//! struct Footed {
+//!     value: u32,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! # use plod::Plod;
+//! #[derive(Plod, Clone, Copy)]
+//! #[plod(little_endian, pod_cast, construct_with = Range::checked)]
+//! #[repr(C)]
+//! struct Range {
+//!     low: u16,
+//!     high: u16,
+//! }
+//!
+//! impl Range {
+//!     fn checked(low: u16, high: u16) -> plod::Result<Range> {
+//!         Ok(Range { low, high })
+//!     }
+//! }
+//! ```
+//!
+//! This is the only place where plod uses `unsafe`, its soundness relies on the checks of the
+//! derive: no padding and only fields for which any bit pattern is valid.
+
+use std::io::{Error, ErrorKind, Read, Write};
+use std::mem::{align_of, size_of, MaybeUninit};
+
+use crate::{Plod, Result};
+
+/// A type whose in memory representation is its representation at rest
+///
+/// # Safety
+///
+/// The type must have no padding, any bit pattern must be a valid value, and its at rest layout
+/// must be its in memory layout when `NATIVE` is true. Use `#[plod(pod_cast)]` to implement it.
+pub unsafe trait Pod: Plod + Copy + 'static {
+    /// The byte order at rest is the byte order of the target
+    const NATIVE: bool;
+}
+
+/// Bytes of a value in memory
+pub fn bytes_of<T: Pod>(value: &T) -> &[u8] {
+    // SAFETY: a Pod has no padding so all its bytes are initialized
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) }
+}
+
+/// Bytes of values in memory
+pub fn as_bytes<T: Pod>(values: &[T]) -> &[u8] {
+    // SAFETY: a Pod has no padding so all its bytes are initialized
+    unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+    }
+}
+
+/// Read a value with a single copy of its in memory representation
+pub fn read_value<T: Pod, R: Read + ?Sized>(from: &mut R) -> Result<T> {
+    let mut value = MaybeUninit::<T>::zeroed();
+    // SAFETY: the value is zeroed so its bytes are initialized
+    let bytes =
+        unsafe { std::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+    from.read_exact(bytes)?;
+    // SAFETY: any bit pattern is a valid Pod
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Write a value with a single copy of its in memory representation
+pub fn write_value<T: Pod, W: Write + ?Sized>(value: &T, to: &mut W) -> Result<()> {
+    to.write_all(bytes_of(value))
+}
+
+/// View bytes as records without copying them, `None` if the byte order is not the one of the
+/// target, if the bytes are not aligned for `T` or if they are not a whole number of records
+pub fn cast_slice<T: Pod>(bytes: &[u8]) -> Option<&[T]> {
+    let size = size_of::<T>();
+    if !T::NATIVE
+        || size == 0
+        || !bytes.len().is_multiple_of(size)
+        || !(bytes.as_ptr() as usize).is_multiple_of(align_of::<T>())
+    {
+        return None;
+    }
+    // SAFETY: alignment and size are checked above and any bit pattern is a valid Pod
+    Some(unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const T, bytes.len() / size) })
+}
+
+/// Read consecutive records filling `bytes`, with a single copy when the byte order is the one
+/// of the target and with regular reads otherwise
+pub fn read_records<T: Pod>(bytes: &[u8]) -> Result<Vec<T>>
+where
+    T::Context: Default,
+{
+    let size = size_of::<T>();
+    if size == 0 || !bytes.len().is_multiple_of(size) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{} bytes are not a whole number of {} byte records",
+                bytes.len(),
+                size
+            ),
+        ));
+    }
+    let count = bytes.len() / size;
+    crate::fuzz::check_items::<T>(count)?;
+    if !T::NATIVE {
+        let mut from = bytes;
+        return (0..count).map(|_| T::read_from(&mut from)).collect();
+    }
+    let mut records = Vec::<T>::with_capacity(count);
+    // SAFETY: the capacity holds count records, they are initialized by the copy and any bit
+    // pattern is a valid Pod
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), records.as_mut_ptr() as *mut u8, bytes.len());
+        records.set_len(count);
+    }
+    Ok(records)
+}
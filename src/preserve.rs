@@ -0,0 +1,74 @@
+//! Bytes read without being modelled, kept to be written back as they were
+//!
+//! A struct with `#[plod(preserve_unknown = <field>)]` keeps in `<field>`, a [`Preserved`] that is
+//! not stored itself, the bytes of its `reserved` regions and the unknown trailing bytes of a
+//! `length_prefixed` struct. They are written back instead of zeroes and counted in the size at
+//! rest, so that reading a value, changing a field and writing it doesn't lose data the type
+//! doesn't describe. Unknown enum payloads are kept with a catch-all variant ending with a
+//! `#[plod(rest)]` field.
+//!
+//! ```
+//! use plod::{Plod, Preserved};
+//!
+//! #[derive(Plod, Debug)]
+//! #[plod(big_endian, length_prefixed(u8), preserve_unknown = unknown)]
+//! struct Record {
+//!     id: u16,
+//!     #[plod(reserved(2))]
+//!     flags: u8,
+//!     unknown: Preserved,
+//! }
+//!
+//! // a newer version of the format added 2 bytes at the end
+//! let data = [7, 0, 1, 0xaa, 0xbb, 3, 0xcc, 0xdd];
+//! let mut record = Record::read_from(&mut data.as_slice()).unwrap();
+//! assert_eq!(record.unknown.trailing(), [0xcc, 0xdd]);
+//! record.flags = 4;
+//! let mut written = Vec::new();
+//! record.write_to(&mut written).unwrap();
+//! assert_eq!(written, [7, 0, 1, 0xaa, 0xbb, 4, 0xcc, 0xdd]);
+//! ```
+
+/// Bytes kept by a derived type with `preserve_unknown`, empty for a value that was not read
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Preserved {
+    reserved: Vec<(&'static str, Vec<u8>)>,
+    trailing: Vec<u8>,
+}
+
+impl Preserved {
+    /// Bytes of the reserved region before the field `name`, if they have been read
+    pub fn reserved(&self, name: &str) -> Option<&[u8]> {
+        self.reserved.iter().find(|(field, _)| *field == name).map(|(_, bytes)| bytes.as_slice())
+    }
+
+    /// Unknown bytes read after the last field of a `length_prefixed` struct
+    pub fn trailing(&self) -> &[u8] {
+        &self.trailing
+    }
+
+    /// Forget the kept bytes, reserved regions are then written as zeroes again
+    pub fn clear(&mut self) {
+        self.reserved.clear();
+        self.trailing.clear();
+    }
+
+    /// Keep the bytes of a reserved region, called by derived code
+    #[doc(hidden)]
+    pub fn keep_reserved(&mut self, name: &'static str, bytes: &[u8]) {
+        self.reserved.push((name, bytes.to_vec()));
+    }
+
+    /// Keep the trailing bytes, called by derived code
+    #[doc(hidden)]
+    pub fn keep_trailing(&mut self, bytes: Vec<u8>) {
+        self.trailing = bytes;
+    }
+
+    /// The kept bytes of a reserved region if they still have its size, zeroes otherwise, called
+    /// by derived code
+    #[doc(hidden)]
+    pub fn reserved_bytes<const N: usize>(&self, name: &str) -> [u8; N] {
+        self.reserved(name).and_then(|bytes| bytes.try_into().ok()).unwrap_or([0; N])
+    }
+}
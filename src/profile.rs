@@ -0,0 +1,59 @@
+//! Reusable attribute profiles
+//!
+//! The messages of a protocol usually share the same endianness, size type and context. Instead
+//! of repeating these attributes on every type, [`plod_profile!`](crate::plod_profile!) names them
+//! once and defines a macro that derives `Plod` with them for each item it is given. Attributes
+//! written on an item are applied after the profile, so they can override it.
+//!
+//! A derive cannot read definitions made elsewhere in the crate, so a profile is applied by
+//! wrapping items in its macro rather than with a `#[plod(...)]` attribute. The macro follows the
+//! usual `macro_rules!` scoping: it is available after its definition, and can be shared with
+//! `pub(crate) use`.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! plod::plod_profile!(dns = big_endian, size_type(u16));
+//!
+//! dns! {
+//!     #[derive(Debug, PartialEq)]
+//!     struct Question {
+//!         name: Vec<u8>,
+//!         kind: u16,
+//!     }
+//!
+//!     #[derive(Debug, PartialEq)]
+//!     #[plod(size_type(u8))]
+//!     struct Label {
+//!         text: Vec<u8>,
+//!     }
+//! }
+//!
+//! let question = Question { name: b"a".to_vec(), kind: 1 };
+//! let mut data = Vec::new();
+//! question.write_to(&mut data).unwrap();
+//! assert_eq!(data, [0, 1, b'a', 0, 1]);
+//! assert_eq!(Label { text: b"a".to_vec() }.size_at_rest(), 2);
+//! ```
+
+/// Define a macro deriving `Plod` with a bundle of attributes.
+///
+/// `plod::plod_profile!(name = <attributes>)` defines `name! { <items> }`, which adds
+/// `#[derive(Plod)]` and `#[plod(<attributes>)]` to each struct or enum of `<items>`.
+#[macro_export]
+macro_rules! plod_profile {
+    (@define ($d:tt) $name:ident ($($attribute:tt)*)) => {
+        macro_rules! $name {
+            ($d($d item:item)*) => {
+                $d(
+                    #[derive($crate::Plod)]
+                    #[plod($($attribute)*)]
+                    $d item
+                )*
+            };
+        }
+    };
+    ($name:ident = $($attribute:tt)*) => {
+        $crate::plod_profile!(@define ($) $name ($($attribute)*));
+    };
+}
@@ -0,0 +1,85 @@
+//! Progress of long reads
+//!
+//! While [`with_progress`] runs, derived readers call a callback with their position each time
+//! they have read at least `interval` more bytes, so that an application parsing a large file can
+//! show a progress bar. Positions are checked after each item of a `Vec` and after each record of
+//! [`iter`](crate::iter()), a value without collections only reports progress as a whole.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod)]
+//! struct Samples {
+//!     #[plod(size_type(u32))]
+//!     values: Vec<u16>,
+//! }
+//!
+//! let mut data = vec![0_u8; 4 + 2000];
+//! data[..4].copy_from_slice(&1000_u32.to_le_bytes());
+//! let samples = plod::progress::with_progress(512, |pos| println!("{} bytes read", pos), || {
+//!     Samples::read_from(&mut data.as_slice())
+//! });
+//! assert_eq!(samples.unwrap().values.len(), 1000);
+//! ```
+
+use std::cell::{Cell, RefCell};
+
+struct Progress {
+    interval: usize,
+    callback: Box<dyn FnMut(usize)>,
+}
+
+thread_local! {
+    // position of the next call, usize::MAX when there is no callback
+    static NEXT: Cell<usize> = const { Cell::new(usize::MAX) };
+    static PROGRESS: RefCell<Option<Progress>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous callback even if the closure panics
+struct Restore(usize, Option<Progress>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.1.take();
+        PROGRESS.with(|p| *p.borrow_mut() = previous);
+        NEXT.with(|n| n.set(self.0));
+    }
+}
+
+/// Run `f` with `callback` called by all reads of derived types on the current thread, each time
+/// the position has grown by at least `interval` bytes
+pub fn with_progress<T, C, F>(interval: usize, callback: C, f: F) -> T
+where
+    C: FnMut(usize) + 'static,
+    F: FnOnce() -> T,
+{
+    let progress = Progress {
+        interval: interval.max(1),
+        callback: Box::new(callback),
+    };
+    let next = NEXT.with(|n| n.replace(progress.interval));
+    let previous = PROGRESS.with(|p| p.borrow_mut().replace(progress));
+    let _restore = Restore(next, previous);
+    f()
+}
+
+/// Report the current position, called by derived code
+#[doc(hidden)]
+#[inline]
+pub fn tick(pos: usize) {
+    if pos >= NEXT.with(|n| n.get()) {
+        report(pos);
+    }
+}
+
+#[cold]
+fn report(pos: usize) {
+    // the callback is taken out so that it can itself read values
+    let progress = PROGRESS.with(|p| p.borrow_mut().take());
+    if let Some(mut progress) = progress {
+        NEXT.with(|n| n.set(usize::MAX));
+        (progress.callback)(pos);
+        NEXT.with(|n| n.set(pos.saturating_add(progress.interval)));
+        PROGRESS.with(|p| *p.borrow_mut() = Some(progress));
+    }
+}
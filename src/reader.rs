@@ -0,0 +1,121 @@
+//! Readers of inputs that may be compressed
+//!
+//! Tools often accept a format both raw and compressed as a whole, like `.bin` and `.bin.gz`.
+//! [`auto_decompress`] looks at the first bytes of a reader and decompresses it when they are the
+//! magic of a known compression format:
+//! - gzip, with the `gzip` feature, several members are read one after the other,
+//! - zstd, with the `zstd` feature,
+//! - lz4 frames, with the `lz4` feature.
+//!
+//! Other inputs are read as is. An input compressed with a format whose feature is not enabled is
+//! an error of kind `Unsupported` rather than garbage handed to the parser.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! struct Point {
+//!     x: u16,
+//!     y: u16,
+//! }
+//!
+//! let mut reader = plod::reader::auto_decompress([1_u8, 0, 2, 0].as_slice()).unwrap();
+//! assert_eq!(reader.compression(), None);
+//! assert_eq!(Point::read_from(&mut reader).unwrap(), Point { x: 1, y: 2 });
+//! ```
+
+use std::io::{Chain, Cursor, Error, ErrorKind, Read};
+
+use crate::Result;
+
+/// The input with its first bytes put back in front
+type Sniffed<R> = Chain<Cursor<Vec<u8>>, R>;
+
+enum Inner<R: Read> {
+    Plain(Sniffed<R>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::read::MultiGzDecoder<Sniffed<R>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::Decoder<'static, std::io::BufReader<Sniffed<R>>>),
+    #[cfg(feature = "lz4")]
+    Lz4(lz4_flex::frame::FrameDecoder<Sniffed<R>>),
+}
+
+/// A reader decompressing its input if needed, created by [`auto_decompress`]
+pub struct AutoDecompress<R: Read> {
+    inner: Inner<R>,
+}
+
+impl<R: Read> AutoDecompress<R> {
+    /// Name of the compression format of the input, `None` if it is read as is
+    pub fn compression(&self) -> Option<&'static str> {
+        match self.inner {
+            Inner::Plain(_) => None,
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(_) => Some("gzip"),
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(_) => Some("zstd"),
+            #[cfg(feature = "lz4")]
+            Inner::Lz4(_) => Some("lz4"),
+        }
+    }
+}
+
+impl<R: Read> Read for AutoDecompress<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match &mut self.inner {
+            Inner::Plain(r) => r.read(buf),
+            #[cfg(feature = "gzip")]
+            Inner::Gzip(r) => r.read(buf),
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(r) => r.read(buf),
+            #[cfg(feature = "lz4")]
+            Inner::Lz4(r) => r.read(buf),
+        }
+    }
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const LZ4_MAGIC: &[u8] = &[0x04, 0x22, 0x4d, 0x18];
+
+/// Wrap `reader` in a decompressor chosen from its first bytes, or read it as is
+pub fn auto_decompress<R: Read>(mut reader: R) -> Result<AutoDecompress<R>> {
+    let mut start = vec![0_u8; 4];
+    let mut len = 0;
+    while len < start.len() {
+        match crate::helpers::read_some(&mut reader, &mut start[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    start.truncate(len);
+    let magic = |m: &[u8]| start.starts_with(m);
+    let unsupported = |name: &str| {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!("{} input needs the {} feature of plod", name, name),
+        )
+    };
+    let format = if magic(GZIP_MAGIC) {
+        "gzip"
+    } else if magic(ZSTD_MAGIC) {
+        "zstd"
+    } else if magic(LZ4_MAGIC) {
+        "lz4"
+    } else {
+        ""
+    };
+    let sniffed = Cursor::new(start).chain(reader);
+    let inner = match format {
+        "" => Inner::Plain(sniffed),
+        #[cfg(feature = "gzip")]
+        "gzip" => Inner::Gzip(flate2::read::MultiGzDecoder::new(sniffed)),
+        #[cfg(feature = "zstd")]
+        "zstd" => Inner::Zstd(zstd::stream::Decoder::new(sniffed)?),
+        #[cfg(feature = "lz4")]
+        "lz4" => Inner::Lz4(lz4_flex::frame::FrameDecoder::new(sniffed)),
+        name => return Err(unsupported(name)),
+    };
+    Ok(AutoDecompress { inner })
+}
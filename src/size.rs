@@ -0,0 +1,156 @@
+//! Counting bytes read and written
+//!
+//! [`SizeWriter`] counts the bytes written through it, to measure a value without allocating a
+//! buffer or to check [`Plod::size_at_rest`] against what is really written. [`SizeReader`] counts
+//! the bytes read through it, to report progress or throughput over a long stream. A size that doesn't
+//! match the bytes written corrupts length prefixes computed from it, so debug builds check
+//! every [`Plod::write_to`].
+
+use std::io::{Error, ErrorKind, Read, Sink, Write};
+
+use crate::{Plod, Result};
+
+/// A writer counting the bytes written, and forwarding them to an inner writer if any
+#[derive(Debug)]
+pub struct SizeWriter<W = Sink> {
+    inner: W,
+    size: usize,
+}
+
+impl SizeWriter {
+    /// A writer that only counts bytes
+    pub fn new() -> Self {
+        SizeWriter::with_writer(std::io::sink())
+    }
+}
+
+impl Default for SizeWriter {
+    fn default() -> Self {
+        SizeWriter::new()
+    }
+}
+
+impl<W: Write> SizeWriter<W> {
+    /// Count the bytes written to `inner`
+    pub fn with_writer(inner: W) -> Self {
+        SizeWriter { inner, size: 0 }
+    }
+
+    /// Number of bytes written so far
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get back the inner writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for SizeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.size += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader counting the bytes read from an inner reader
+#[derive(Debug)]
+pub struct SizeReader<R> {
+    inner: R,
+    size: usize,
+}
+
+impl<R: Read> SizeReader<R> {
+    /// Count the bytes read from `inner`
+    pub fn new(inner: R) -> Self {
+        SizeReader { inner, size: 0 }
+    }
+
+    /// Number of bytes read so far
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get back the inner reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for SizeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.size += read;
+        Ok(read)
+    }
+}
+
+/// Write a value to a [`SizeWriter`] and check that its size at rest is the number of bytes
+/// written, returns an error of kind `InvalidData` otherwise
+pub fn check_size<T: Plod>(value: &T) -> Result<()>
+where
+    T::Context: Default,
+{
+    let mut counter = SizeWriter::new();
+    value.impl_write_to(&mut counter, &T::Context::default(), 0)?;
+    if counter.size() != value.size_at_rest() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            size_mismatch::<T>(value.size_at_rest(), counter.size()),
+        ));
+    }
+    Ok(())
+}
+
+/// Number of units of `unit` items or bytes in `size`, called by derived code for sizes stored
+/// with `#[plod(size_unit = <N>)]`. Returns an error of kind `InvalidInput` if `size` is not a
+/// multiple of `unit`.
+#[doc(hidden)]
+pub fn in_units(size: usize, unit: usize) -> Result<usize> {
+    if !size.is_multiple_of(unit) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Size {} is not a multiple of the size unit {}", size, unit),
+        ));
+    }
+    Ok(size / unit)
+}
+
+/// Size stored for an actual `size` with `#[plod(size_adjust = <adjust>)]`, called by derived
+/// code. Returns an error of kind `InvalidInput` if it would be negative.
+#[doc(hidden)]
+pub fn adjusted(size: usize, adjust: i64) -> Result<usize> {
+    usize::try_from(size as i128 + adjust as i128).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("Size {} adjusted by {} is negative", size, adjust),
+        )
+    })
+}
+
+/// Actual size of a `stored` size with `#[plod(size_adjust = <adjust>)]`, called by derived
+/// code. Returns an error of kind `InvalidData` if it would be negative.
+#[doc(hidden)]
+pub fn unadjusted(stored: usize, adjust: i64) -> Result<usize> {
+    usize::try_from(stored as i128 - adjust as i128).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Stored size {} is less than its adjustment {}", stored, adjust),
+        )
+    })
+}
+
+pub(crate) fn size_mismatch<T>(expected: usize, written: usize) -> String {
+    format!(
+        "{}::size_at_rest() is {} but {} bytes were written",
+        std::any::type_name::<T>(),
+        expected,
+        written
+    )
+}
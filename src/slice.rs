@@ -0,0 +1,232 @@
+//! In memory input
+//!
+//! [`SliceReader`] reads from a byte slice and keeps track of its position, so that reading past
+//! the end reports the exact offset of the missing bytes with an [`OutOfBounds`] error.
+//! It is used by [`Plod::read_from_slice`](crate::Plod::read_from_slice).
+//! [`Plod::from_bytes_exact`](crate::Plod::from_bytes_exact) also requires the whole slice to be
+//! consumed, and fails with a [`TrailingBytes`] error otherwise.
+//! [`Plod::write_to_slice`](crate::Plod::write_to_slice) reports a slice too short to write to
+//! with the same error payload.
+//!
+//! [`ChunksReader`] reads from a sequence of non-contiguous buffers, such as the `IoSlice`s of a
+//! scatter list or the chunks of a network stream, without concatenating them first. A value
+//! split between two chunks is read as if the chunks were a single slice.
+//! With the `bytes` feature, a chain of buffers built with `bytes::Buf::chain` can also be read
+//! directly with [`Plod::read_from_buf`](crate::Plod::read_from_buf).
+//!
+//! ```
+//! use plod::slice::ChunksReader;
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! #[plod(little_endian)]
+//! struct Header {
+//!     magic: u32,
+//!     version: u16,
+//! }
+//!
+//! let chunks: [&[u8]; 3] = [&[0x50, 0x4c], &[0x4f, 0x44, 1], &[0]];
+//! let mut reader = ChunksReader::new(chunks);
+//! let header = Header::read_from(&mut reader).unwrap();
+//! assert_eq!(header, Header { magic: 0x444f4c50, version: 1 });
+//! assert_eq!(reader.position(), 6);
+//! ```
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Read};
+use std::ops::Deref;
+
+/// Error payload of a read past the end of a slice, available with `std::io::Error::get_ref`
+/// on errors of kind `UnexpectedEof`, or of a write to a slice too short on errors of kind
+/// `WriteZero`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfBounds {
+    /// position of the read that failed, relative to the start of the slice
+    pub offset: usize,
+    /// number of bytes requested
+    pub needed: usize,
+    /// number of bytes that were available
+    pub available: usize,
+}
+
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unexpected end of input at offset {}: {} bytes needed, {} available",
+            self.offset, self.needed, self.available
+        )
+    }
+}
+
+impl std::error::Error for OutOfBounds {}
+
+/// Error payload of an input that is not fully consumed by the value read, available with
+/// `std::io::Error::get_ref` on errors of kind `InvalidData` returned by
+/// [`Plod::from_bytes_exact`](crate::Plod::from_bytes_exact) and
+/// [`Plod::read_exactly_from`](crate::Plod::read_exactly_from)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrailingBytes {
+    /// size of the value read, where the trailing bytes start
+    pub offset: usize,
+    /// number of bytes left after the value
+    pub remaining: usize,
+}
+
+impl fmt::Display for TrailingBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes left after the value, at offset {}",
+            self.remaining, self.offset
+        )
+    }
+}
+
+impl std::error::Error for TrailingBytes {}
+
+/// Error of a value followed by `remaining` bytes
+pub(crate) fn trailing_bytes(offset: usize, remaining: usize) -> Error {
+    Error::new(ErrorKind::InvalidData, TrailingBytes { offset, remaining })
+}
+
+/// A reader over a byte slice that knows its position
+#[derive(Clone, Debug)]
+pub struct SliceReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    /// Create a reader at the start of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        SliceReader { data, pos: 0 }
+    }
+
+    /// Number of bytes read so far
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Bytes not read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+}
+
+impl Read for SliceReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        let available = self.data.len() - self.pos;
+        if buf.len() > available {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                OutOfBounds {
+                    offset: self.pos,
+                    needed: buf.len(),
+                    available,
+                },
+            ));
+        }
+        buf.copy_from_slice(&self.data[self.pos..self.pos + buf.len()]);
+        self.pos += buf.len();
+        Ok(())
+    }
+}
+
+/// A reader over a sequence of byte buffers that knows its position
+///
+/// Buffers can be anything that dereferences to `[u8]`: slices, `Vec<u8>`, `std::io::IoSlice`,
+/// or `bytes::Bytes` for example. A buffer is only taken from the iterator once the previous one
+/// has been fully read.
+#[derive(Clone, Debug)]
+pub struct ChunksReader<I, B> {
+    chunks: I,
+    current: Option<B>,
+    offset: usize,
+    pos: usize,
+}
+
+impl<I, B> ChunksReader<I, B>
+where
+    I: Iterator<Item = B>,
+    B: Deref<Target = [u8]>,
+{
+    /// Create a reader at the start of the first buffer of `chunks`
+    pub fn new<C: IntoIterator<IntoIter = I>>(chunks: C) -> Self {
+        ChunksReader {
+            chunks: chunks.into_iter(),
+            current: None,
+            offset: 0,
+            pos: 0,
+        }
+    }
+
+    /// Number of bytes read so far, across all buffers
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Bytes left in the current buffer, an empty slice if the next buffer has not been taken yet
+    pub fn remaining(&self) -> &[u8] {
+        match &self.current {
+            Some(chunk) => &chunk[self.offset..],
+            None => &[],
+        }
+    }
+
+    // current buffer with bytes left to read, None at the end of input
+    fn fill(&mut self) -> Option<&[u8]> {
+        while self.current.as_ref().is_none_or(|c| self.offset >= c.len()) {
+            self.current = Some(self.chunks.next()?);
+            self.offset = 0;
+        }
+        self.current.as_ref().map(|c| &c[self.offset..])
+    }
+}
+
+impl<I, B> Read for ChunksReader<I, B>
+where
+    I: Iterator<Item = B>,
+    B: Deref<Target = [u8]>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = match self.fill() {
+            Some(chunk) => {
+                let n = buf.len().min(chunk.len());
+                buf[..n].copy_from_slice(&chunk[..n]);
+                n
+            }
+            None => 0,
+        };
+        self.offset += n;
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        let start = self.pos;
+        let mut filled = 0;
+        while filled < buf.len() {
+            let n = self.read(&mut buf[filled..])?;
+            if n == 0 {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    OutOfBounds {
+                        offset: start,
+                        needed: buf.len(),
+                        available: filled,
+                    },
+                ));
+            }
+            filled += n;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! Headers followed by a stream
+//!
+//! Many formats are a header followed by a payload that the application wants to stream itself,
+//! to decompress it or to copy it somewhere else. [`read_header_then`] reads the header and returns
+//! the reader right after it, wrapped in a [`Payload`] that knows how many bytes the header took
+//! and the offset of the next byte. Derived types never read past their own bytes, so no byte of
+//! the payload is lost in a buffer.
+//!
+//! ```
+//! use plod::Plod;
+//! use std::io::Read;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! #[plod(magic(u16 = 0x4d49))]
+//! struct Header {
+//!     width: u8,
+//!     height: u8,
+//! }
+//!
+//! let data = [b'I', b'M', 2, 2, 10, 20, 30, 40];
+//! let (header, mut payload) = plod::read_header_then::<Header, _>(data.as_slice()).unwrap();
+//! assert_eq!(header, Header { width: 2, height: 2 });
+//! assert_eq!(payload.header_size(), 4);
+//! let mut pixels = Vec::new();
+//! payload.read_to_end(&mut pixels).unwrap();
+//! assert_eq!(pixels, [10, 20, 30, 40]);
+//! assert_eq!(payload.offset(), 8);
+//! ```
+
+use std::io::{BufRead, Read};
+
+use crate::size::SizeReader;
+use crate::{Plod, Result};
+
+/// The rest of a reader after a header, created by [`read_header_then`]
+#[derive(Debug)]
+pub struct Payload<R> {
+    inner: R,
+    header_size: usize,
+    offset: usize,
+}
+
+impl<R> Payload<R> {
+    /// Number of bytes taken by the header
+    pub fn header_size(&self) -> usize {
+        self.header_size
+    }
+
+    /// Offset of the next byte from the start of the header
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Borrow the inner reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get back the inner reader, positioned at [`Payload::offset`]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Payload<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.offset += read;
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Payload<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.offset += amt;
+        self.inner.consume(amt)
+    }
+}
+
+/// Read a header of type `H` and return it with the rest of `reader`
+pub fn read_header_then<H: Plod, R: Read>(reader: R) -> Result<(H, Payload<R>)>
+where
+    H::Context: Default,
+{
+    read_header_then_with(reader, &H::Context::default())
+}
+
+/// Read a header of type `H` with a context and return it with the rest of `reader`
+pub fn read_header_then_with<H: Plod, R: Read>(mut reader: R, ctx: &H::Context) -> Result<(H, Payload<R>)> {
+    let mut counter = SizeReader::new(&mut reader);
+    let header = H::read_with(&mut counter, ctx)?;
+    let header_size = counter.size();
+    Ok((
+        header,
+        Payload {
+            inner: reader,
+            header_size,
+            offset: header_size,
+        },
+    ))
+}
@@ -0,0 +1,172 @@
+//! Helpers to test types implementing [`Plod`](crate::Plod)
+//!
+//! With the `arbitrary` feature, types deriving `Plod` with `#[plod(arbitrary)]` also implement
+//! [`arbitrary::Arbitrary`], only producing values that can be written. Combined with
+//! [`check_round_trip`] this makes a one line property test or fuzz target, and
+//! [`write_seed_corpus`] gives a coverage guided fuzzer a starting point.
+//!
+//! [`golden`] locks down the representation at rest of a value against a checked-in file, so
+//! that a release cannot change the bytes of a format by accident.
+
+#[cfg(feature = "arbitrary")]
+pub use arbitrary;
+
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::diff::diff_spans;
+use crate::dump::{hexdump, spans};
+use crate::{Plod, Result};
+
+/// Environment variable that makes [`golden`] write its files instead of comparing them
+pub const BLESS_VAR: &str = "PLOD_BLESS";
+
+/// Write `value` and compare its bytes with the golden file at `path`.
+///
+/// On mismatch, the error of kind `InvalidData` gives the offset of the first different byte and
+/// the field written there, the fields that differ if the golden file can still be read as a
+/// `T`, and a labeled hexdump of the value written. A missing golden file is an error of kind
+/// `NotFound`. When the environment variable [`BLESS_VAR`] is set to a value other than `0`, the
+/// file is written with the bytes of `value` instead, creating its directory if needed, to
+/// record the format after an intended change.
+///
+/// ```no_run
+/// use plod::Plod;
+///
+/// #[derive(Plod, PartialEq, Debug)]
+/// #[plod(big_endian)]
+/// struct Header {
+///     version: u16,
+///     #[plod(size_type(u8))]
+///     name: String,
+/// }
+///
+/// let header = Header { version: 3, name: "main".into() };
+/// plod::testing::golden(&header, "tests/golden/header.bin").unwrap();
+/// ```
+pub fn golden<T: Plod>(value: &T, path: impl AsRef<Path>) -> Result<()>
+where
+    T::Context: Default,
+{
+    let path = path.as_ref();
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes)?;
+    if std::env::var_os(BLESS_VAR).is_some_and(|v| v != "0") {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        return std::fs::write(path, &bytes);
+    }
+    let expected = std::fs::read(path).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("Cannot read golden file {}: {}, set {}=1 to write it", path.display(), e, BLESS_VAR),
+        )
+    })?;
+    if expected == bytes {
+        return Ok(());
+    }
+    let found_spans = spans(value);
+    let offset = expected.iter().zip(&bytes).take_while(|(e, f)| e == f).count();
+    let field = found_spans
+        .iter()
+        .find(|s| s.start <= offset && offset < s.end())
+        .map_or("the end of the value", |s| s.path.as_str());
+    let mut message = format!(
+        "Value differs from golden file {} at offset {}, in {}: {} bytes expected, {} written\n",
+        path.display(),
+        offset,
+        field,
+        expected.len(),
+        bytes.len()
+    );
+    // the fields can only be compared if the golden file is still readable
+    if let Ok(old) = T::from_bytes_exact(&expected) {
+        for difference in diff_spans(&expected, &spans(&old), &bytes, &found_spans) {
+            let _ = writeln!(message, "  {}", difference);
+        }
+    }
+    let _ = write!(message, "written:\n{}", hexdump(&bytes, &found_spans));
+    let _ = write!(message, "set {}=1 to update the golden file", BLESS_VAR);
+    Err(Error::new(ErrorKind::InvalidData, message))
+}
+
+/// Generate a value from `data`, write it, read it back and check that both are equal.
+///
+/// Returns `Ok(())` if `data` cannot produce a value, and an error of kind `InvalidData` if the
+/// value read is not the value written.
+#[cfg(feature = "arbitrary")]
+pub fn check_round_trip<T>(data: &[u8]) -> Result<()>
+where
+    T: Plod + for<'a> arbitrary::Arbitrary<'a> + PartialEq + std::fmt::Debug,
+    T::Context: Default,
+{
+    let mut u = arbitrary::Unstructured::new(data);
+    let value = match T::arbitrary(&mut u) {
+        Ok(value) => value,
+        Err(_) => return Ok(()),
+    };
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes)?;
+    let result = T::read_from(&mut bytes.as_slice())?;
+    if result != value {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Round trip mismatch, wrote {:?}, read {:?}", value, result),
+        ));
+    }
+    Ok(())
+}
+
+/// Write serialized values of `T` in `dir`, one file per distinct serialization, as seed inputs for
+/// a coverage guided fuzzer of a parser of `T`. Returns the number of files written.
+///
+/// Values are generated from patterns of bytes: every first byte, which selects the variant of
+/// a derived enum, followed by zeroes or `0xff` bytes in quantities from none to a few
+/// kilobytes, which gives the smallest and the largest collections. Files are named
+/// `seed-<index>.bin` in the order of their content, so that writing the corpus again gives the
+/// same files.
+///
+/// ```no_run
+/// use plod::Plod;
+///
+/// #[derive(Plod, PartialEq, Debug)]
+/// #[plod(tag_type(u8), size_type(u8), arbitrary)]
+/// enum Packet {
+///     #[plod(tag = 1)]
+///     Ping,
+///     #[plod(tag = 2)]
+///     Data(Vec<u8>),
+/// }
+///
+/// plod::testing::write_seed_corpus::<Packet>("fuzz/corpus/packet").unwrap();
+/// ```
+#[cfg(feature = "arbitrary")]
+pub fn write_seed_corpus<T>(dir: impl AsRef<std::path::Path>) -> Result<usize>
+where
+    T: Plod + for<'a> arbitrary::Arbitrary<'a>,
+    T::Context: Default,
+{
+    let mut seeds = std::collections::BTreeSet::new();
+    for first in 0..=u8::MAX {
+        for fill in [0x00, 0xff] {
+            for len in [0, 1, 2, 3, 4, 8, 16, 64, 256, 1024, 4096] {
+                let mut data = vec![fill; len + 1];
+                data[0] = first;
+                let mut u = arbitrary::Unstructured::new(&data);
+                if let Ok(value) = T::arbitrary(&mut u) {
+                    let mut bytes = Vec::new();
+                    value.write_to(&mut bytes)?;
+                    seeds.insert(bytes);
+                }
+            }
+        }
+    }
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    for (i, seed) in seeds.iter().enumerate() {
+        std::fs::write(dir.join(format!("seed-{:04}.bin", i)), seed)?;
+    }
+    Ok(seeds.len())
+}
@@ -0,0 +1,148 @@
+//! Text encodings of `String` and `Cow<str>` fields
+//!
+//! Text fields are stored like a `Vec<u8>`, after their size in bytes, and their bytes are
+//! decoded with the encoding selected by `#[plod(encoding = <encoding>)]` on the field or on the
+//! type. Legacy formats often store Latin-1 or plain ASCII text, which UTF-8 decoding rejects.
+//! Windows formats store UTF-16 text, and `#[plod(bstr)]` reads and writes a `BSTR`: its size in
+//! bytes as a little endian `u32`, its UTF-16LE characters, then a NUL character not counted in the
+//! size.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! #[plod(size_type(u8))]
+//! struct Entry {
+//!     #[plod(encoding = latin1)]
+//!     name: String,
+//!     #[plod(encoding = ascii)]
+//!     code: String,
+//! }
+//!
+//! let entry = Entry::read_from(&mut [3, b'd', 0xe9, b'j', 2, b'F', b'R'].as_slice()).unwrap();
+//! assert_eq!(entry, Entry { name: "déj".to_string(), code: "FR".to_string() });
+//! assert_eq!(entry.size_at_rest(), 7);
+//! ```
+
+use std::borrow::Cow;
+use std::io::{Error, ErrorKind};
+
+use crate::Result;
+
+/// How the characters of a text field are stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// UTF-8, invalid bytes fail to read (the default)
+    Utf8Strict,
+    /// UTF-8, invalid bytes are read as `U+FFFD`
+    Utf8Lossy,
+    /// ISO 8859-1, one byte per character, characters above `U+00FF` cannot be written
+    Latin1,
+    /// 7 bits ASCII, bytes above 0x7f cannot be read and other characters cannot be written
+    Ascii,
+    /// UTF-16 little endian, unpaired surrogates and odd sizes fail to read
+    Utf16Le,
+    /// UTF-16 big endian, unpaired surrogates and odd sizes fail to read
+    Utf16Be,
+}
+
+impl TextEncoding {
+    /// Name of the encoding, as given to `#[plod(encoding = ...)]`
+    pub fn name(self) -> &'static str {
+        match self {
+            TextEncoding::Utf8Strict => "utf8_strict",
+            TextEncoding::Utf8Lossy => "utf8_lossy",
+            TextEncoding::Latin1 => "latin1",
+            TextEncoding::Ascii => "ascii",
+            TextEncoding::Utf16Le => "utf16le",
+            TextEncoding::Utf16Be => "utf16be",
+        }
+    }
+
+    /// Highest character stored as a single byte, `None` for UTF-8 and UTF-16
+    fn max_char(self) -> Option<char> {
+        match self {
+            TextEncoding::Utf8Strict | TextEncoding::Utf8Lossy => None,
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => None,
+            TextEncoding::Latin1 => Some('\u{ff}'),
+            TextEncoding::Ascii => Some('\u{7f}'),
+        }
+    }
+
+    /// Decode bytes read at `pos`, the error of invalid bytes tells their absolute offset
+    pub fn decode(self, bytes: Vec<u8>, pos: usize) -> Result<String> {
+        let invalid = |index: usize| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid {} text at offset {}", self.name(), pos + index),
+            )
+        };
+        match self {
+            TextEncoding::Utf8Strict => {
+                String::from_utf8(bytes).map_err(|e| invalid(e.utf8_error().valid_up_to()))
+            }
+            TextEncoding::Utf8Lossy => Ok(match String::from_utf8_lossy(&bytes) {
+                Cow::Borrowed(_) => String::from_utf8(bytes).unwrap_or_default(),
+                Cow::Owned(text) => text,
+            }),
+            TextEncoding::Latin1 => Ok(bytes.iter().map(|b| *b as char).collect()),
+            TextEncoding::Ascii => match bytes.iter().position(|b| !b.is_ascii()) {
+                Some(index) => Err(invalid(index)),
+                None => Ok(bytes.iter().map(|b| *b as char).collect()),
+            },
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(invalid(bytes.len() - 1));
+                }
+                let units = bytes.chunks_exact(2).map(|unit| match self {
+                    TextEncoding::Utf16Le => u16::from_le_bytes([unit[0], unit[1]]),
+                    _ => u16::from_be_bytes([unit[0], unit[1]]),
+                });
+                let mut text = String::with_capacity(bytes.len() / 2);
+                for c in char::decode_utf16(units) {
+                    match c {
+                        Ok(c) => text.push(c),
+                        Err(_) => return Err(invalid(text.encode_utf16().count() * 2)),
+                    }
+                }
+                Ok(text)
+            }
+        }
+    }
+
+    /// Bytes of a text, characters that cannot be encoded are replaced with `?`, use
+    /// [`check`](TextEncoding::check) to reject them
+    pub fn encode(self, text: &str) -> Cow<'_, [u8]> {
+        match self {
+            TextEncoding::Utf16Le => {
+                return Cow::Owned(text.encode_utf16().flat_map(u16::to_le_bytes).collect())
+            }
+            TextEncoding::Utf16Be => {
+                return Cow::Owned(text.encode_utf16().flat_map(u16::to_be_bytes).collect())
+            }
+            _ => {}
+        }
+        match self.max_char() {
+            None => Cow::Borrowed(text.as_bytes()),
+            Some(max) => Cow::Owned(
+                text.chars()
+                    .map(|c| if c <= max { c as u8 } else { b'?' })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Fail with `InvalidInput` if the text has a character that cannot be encoded
+    pub fn check(self, text: &str) -> Result<()> {
+        match self
+            .max_char()
+            .and_then(|max| text.chars().find(|c| *c > max))
+        {
+            Some(c) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Character {:?} cannot be encoded in {}", c, self.name()),
+            )),
+            None => Ok(()),
+        }
+    }
+}
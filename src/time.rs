@@ -0,0 +1,321 @@
+//! Timestamps of common binary formats
+//!
+//! These types store timestamps the way well known formats do and convert them to and from
+//! `SystemTime`, they can be used as fields of derived types:
+//! - [`DosDateTime`]: the packed time and date of ZIP and FAT, 2 seconds resolution, from 1980
+//!   to 2107, in an unspecified time zone (handled as UTC)
+//! - [`WindowsFiletime`]: the 100 nanoseconds intervals since 1601 of NTFS and Windows APIs
+//! - [`Unix32Time`]: the 32 bits seconds since 1970 of PE headers, cpio or gzip, until 2106
+//!
+//! They are all little endian, like the formats that use them.
+//!
+//! Durations are stored as an integer number of a unit, with [`Seconds`], [`Millis`], [`Micros`]
+//! or [`Nanos`] of an integer type, eg: `plod::Millis<u32>`. They convert to and from `Duration`
+//! and a derived type stores them like their integer, with the endianness of the field. The
+//! derive recognizes them by their path, which must start with `plod::`.
+//!
+//! ```
+//! use std::time::{Duration, SystemTime};
+//! use plod::{DosDateTime, Plod};
+//!
+//! #[derive(Plod, Debug)]
+//! #[plod(little_endian)]
+//! struct LocalHeader {
+//!     version: u16,
+//!     flags: u16,
+//!     method: u16,
+//!     modified: DosDateTime,
+//! }
+//!
+//! let data = [20, 0, 0, 0, 8, 0, 0xaf, 0x6d, 0xb1, 0x50];
+//! let header = LocalHeader::read_from(&mut data.as_slice()).unwrap();
+//! assert_eq!(header.modified.year(), 2020);
+//! let modified = SystemTime::try_from(header.modified).unwrap();
+//! assert_eq!(modified, SystemTime::UNIX_EPOCH + Duration::from_secs(1_589_723_130));
+//! ```
+
+use std::borrow::Borrow;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Plod, Result};
+
+/// Seconds from 1601-01-01 to 1970-01-01
+const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+/// Filetime intervals per second
+const FILETIME_TICKS: u64 = 10_000_000;
+
+/// Number of days since 1970-01-01 of a date of the proleptic gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march as i64 + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month and day of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = ((month_from_march + 2) % 12 + 1) as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn out_of_range(kind: ErrorKind, what: &str) -> Error {
+    Error::new(kind, format!("{} is out of range", what))
+}
+
+/// Seconds since 1970 of a time that is not before 1970
+fn unix_seconds(time: SystemTime, what: &str) -> Result<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .map_err(|_| out_of_range(ErrorKind::InvalidInput, what))
+}
+
+/// Date and time packed in two `u16` as MS-DOS did, the time first as in ZIP and FAT entries.
+/// Seconds are stored divided by 2.
+#[derive(Plod, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[plod(little_endian)]
+pub struct DosDateTime {
+    /// hour in bits 15-11, minute in bits 10-5, seconds / 2 in bits 4-0
+    pub time: u16,
+    /// year - 1980 in bits 15-9, month in bits 8-5, day in bits 4-0
+    pub date: u16,
+}
+
+impl DosDateTime {
+    /// Pack a date and a time, odd seconds are rounded down.
+    /// Returns an error of kind `InvalidInput` if the date is not valid or not between 1980 and
+    /// 2107.
+    pub fn new(
+        year: u32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Result<Self> {
+        let valid = (1980..=2107).contains(&year)
+            && (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+            && hour < 24
+            && minute < 60
+            && second < 60;
+        if !valid {
+            return Err(out_of_range(ErrorKind::InvalidInput, "DOS date"));
+        }
+        Ok(DosDateTime {
+            time: ((hour << 11) | (minute << 5) | (second / 2)) as u16,
+            date: (((year - 1980) << 9) | (month << 5) | day) as u16,
+        })
+    }
+
+    /// Year, from 1980 to 2107
+    pub fn year(&self) -> u32 {
+        1980 + (self.date >> 9) as u32
+    }
+
+    /// Month, 1 to 12 in a valid date
+    pub fn month(&self) -> u32 {
+        (self.date >> 5 & 0xf) as u32
+    }
+
+    /// Day of the month, starting at 1 in a valid date
+    pub fn day(&self) -> u32 {
+        (self.date & 0x1f) as u32
+    }
+
+    /// Hour, 0 to 23 in a valid time
+    pub fn hour(&self) -> u32 {
+        (self.time >> 11) as u32
+    }
+
+    /// Minute, 0 to 59 in a valid time
+    pub fn minute(&self) -> u32 {
+        (self.time >> 5 & 0x3f) as u32
+    }
+
+    /// Second, always even
+    pub fn second(&self) -> u32 {
+        (self.time & 0x1f) as u32 * 2
+    }
+}
+
+/// Returns an error of kind `InvalidData` if the packed date or time is not valid
+impl TryFrom<DosDateTime> for SystemTime {
+    type Error = Error;
+
+    fn try_from(value: DosDateTime) -> Result<Self> {
+        // repacking checks every field
+        let (year, month, day) = (value.year(), value.month(), value.day());
+        let (hour, minute, second) = (value.hour(), value.minute(), value.second());
+        DosDateTime::new(year, month, day, hour, minute, second)
+            .map_err(|_| out_of_range(ErrorKind::InvalidData, "DOS date"))?;
+        let days = days_from_civil(year as i64, month, day) as u64;
+        let seconds = days * 86_400 + (hour * 3600 + minute * 60 + second) as u64;
+        Ok(UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+}
+
+/// Returns an error of kind `InvalidInput` if the time is not between 1980 and 2107
+impl TryFrom<SystemTime> for DosDateTime {
+    type Error = Error;
+
+    fn try_from(value: SystemTime) -> Result<Self> {
+        let seconds = unix_seconds(value, "DOS date")?;
+        let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+        let seconds = (seconds % 86_400) as u32;
+        let year =
+            u32::try_from(year).map_err(|_| out_of_range(ErrorKind::InvalidInput, "DOS date"))?;
+        DosDateTime::new(
+            year,
+            month,
+            day,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+        )
+    }
+}
+
+/// Number of 100 nanoseconds intervals since 1601-01-01 UTC
+#[derive(Plod, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[plod(little_endian)]
+pub struct WindowsFiletime(pub u64);
+
+impl From<WindowsFiletime> for SystemTime {
+    fn from(value: WindowsFiletime) -> Self {
+        let since_1601 = Duration::new(
+            value.0 / FILETIME_TICKS,
+            (value.0 % FILETIME_TICKS) as u32 * 100,
+        );
+        let offset = Duration::from_secs(FILETIME_UNIX_OFFSET);
+        match since_1601.checked_sub(offset) {
+            Some(after) => UNIX_EPOCH + after,
+            None => UNIX_EPOCH - (offset - since_1601),
+        }
+    }
+}
+
+/// Returns an error of kind `InvalidInput` if the time is before 1601, sub interval precision
+/// is truncated
+impl TryFrom<SystemTime> for WindowsFiletime {
+    type Error = Error;
+
+    fn try_from(value: SystemTime) -> Result<Self> {
+        let ticks = |d: Duration| d.as_nanos() as i128 / 100;
+        let since_1970 = match value.duration_since(UNIX_EPOCH) {
+            Ok(after) => ticks(after),
+            Err(before) => -ticks(before.duration()),
+        };
+        let since_1601 = since_1970 + (FILETIME_UNIX_OFFSET * FILETIME_TICKS) as i128;
+        u64::try_from(since_1601)
+            .map(WindowsFiletime)
+            .map_err(|_| out_of_range(ErrorKind::InvalidInput, "Windows filetime"))
+    }
+}
+
+/// Number of seconds since 1970-01-01 UTC stored in an unsigned 32 bits integer
+#[derive(Plod, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+#[plod(little_endian)]
+pub struct Unix32Time(pub u32);
+
+impl From<Unix32Time> for SystemTime {
+    fn from(value: Unix32Time) -> Self {
+        UNIX_EPOCH + Duration::from_secs(value.0 as u64)
+    }
+}
+
+/// Returns an error of kind `InvalidInput` if the time is before 1970 or after 2106, fractions
+/// of seconds are truncated
+impl TryFrom<SystemTime> for Unix32Time {
+    type Error = Error;
+
+    fn try_from(value: SystemTime) -> Result<Self> {
+        let seconds = unix_seconds(value, "Unix 32 bits time")?;
+        u32::try_from(seconds)
+            .map(Unix32Time)
+            .map_err(|_| out_of_range(ErrorKind::InvalidInput, "Unix 32 bits time"))
+    }
+}
+
+macro_rules! duration_from_integer {
+    ($name:ident: $($ty:ident)*) => {
+        $(
+            impl From<$ty> for $name<$ty> {
+                fn from(value: $ty) -> Self {
+                    $name(value)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! duration_unit {
+    ($($(#[$doc:meta])* $name:ident: $from:ident, $as:expr, $what:literal;)*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+            pub struct $name<T>(pub T);
+
+            duration_from_integer!($name: u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+
+            impl<T> Borrow<T> for $name<T> {
+                fn borrow(&self) -> &T {
+                    &self.0
+                }
+            }
+
+            impl<T: Into<u64>> From<$name<T>> for Duration {
+                fn from(value: $name<T>) -> Self {
+                    Duration::$from(value.0.into())
+                }
+            }
+
+            /// Returns an error of kind `InvalidInput` if the duration doesn't fit in `T`,
+            /// fractions of the unit are truncated
+            impl<T: TryFrom<u128>> TryFrom<Duration> for $name<T> {
+                type Error = Error;
+
+                fn try_from(value: Duration) -> Result<Self> {
+                    T::try_from($as(&value))
+                        .map($name)
+                        .map_err(|_| out_of_range(ErrorKind::InvalidInput, $what))
+                }
+            }
+        )*
+    };
+}
+
+duration_unit! {
+    /// A duration stored as a number of seconds
+    Seconds: from_secs, |d: &Duration| u128::from(d.as_secs()), "Duration in seconds";
+    /// A duration stored as a number of milliseconds
+    Millis: from_millis, Duration::as_millis, "Duration in milliseconds";
+    /// A duration stored as a number of microseconds
+    Micros: from_micros, Duration::as_micros, "Duration in microseconds";
+    /// A duration stored as a number of nanoseconds
+    Nanos: from_nanos, Duration::as_nanos, "Duration in nanoseconds";
+}
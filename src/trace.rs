@@ -0,0 +1,86 @@
+//! Tracing of derived reads and writes
+//!
+//! With the `tracing` feature, derived types emit a `trace` level event with the `plod` target
+//! when they start and end being read or written. Events contain the type name and the offset of
+//! the value, end events also contain its size or the error. Nested types emit their own events,
+//! so a misaligned parse shows where each value started and how many bytes it took.
+//!
+//! Without the feature, the functions called by generated code do nothing.
+
+use crate::{Plod, Result};
+
+/// Start of a read at `pos`, called by derived code
+#[doc(hidden)]
+#[inline(always)]
+pub fn read_start<T: Plod>(pos: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "plod", r#type = std::any::type_name::<T>(), offset = pos, "read start");
+    #[cfg(not(feature = "tracing"))]
+    let _ = pos;
+}
+
+/// End of a read started at `pos`, called by derived code
+#[doc(hidden)]
+#[inline(always)]
+pub fn read_end<T: Plod>(pos: usize, result: &Result<T>) {
+    #[cfg(feature = "tracing")]
+    match result {
+        Ok(value) => tracing::trace!(
+            target: "plod",
+            r#type = std::any::type_name::<T>(),
+            offset = pos,
+            size = value.size_at_rest(),
+            "read end"
+        ),
+        Err(error) => tracing::trace!(
+            target: "plod",
+            r#type = std::any::type_name::<T>(),
+            offset = pos,
+            %error,
+            "read failed"
+        ),
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (pos, result);
+}
+
+/// Start of a write at `pos`, called by derived code
+#[doc(hidden)]
+#[inline(always)]
+pub fn write_start<T: Plod>(value: &T, pos: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(
+        target: "plod",
+        r#type = std::any::type_name::<T>(),
+        offset = pos,
+        size = value.size_at_rest(),
+        "write start"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (value, pos);
+}
+
+/// End of a write started at `pos`, called by derived code
+#[doc(hidden)]
+#[inline(always)]
+pub fn write_end<T: Plod>(value: &T, pos: usize, result: &Result<()>) {
+    #[cfg(feature = "tracing")]
+    match result {
+        Ok(()) => tracing::trace!(
+            target: "plod",
+            r#type = std::any::type_name::<T>(),
+            offset = pos,
+            size = value.size_at_rest(),
+            "write end"
+        ),
+        Err(error) => tracing::trace!(
+            target: "plod",
+            r#type = std::any::type_name::<T>(),
+            offset = pos,
+            %error,
+            "write failed"
+        ),
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (value, pos, result);
+}
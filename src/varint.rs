@@ -0,0 +1,148 @@
+//! Variable length integers
+//!
+//! An unsigned integer field with `#[plod(varint = <flavor>)]` is stored in as few bytes as its
+//! value needs. Formats disagree on what a varint is, so the flavor is chosen by name:
+//! - `leb128`, or `protobuf` which is the same: groups of 7 bits, least significant first, the
+//!   high bit of each byte telling that another one follows.
+//! - `midi_vlq`: groups of 7 bits, most significant first, with the same continuation bit.
+//! - `git_vlq`: like `midi_vlq`, but each continuation adds one to the value so that no value has
+//!   two encodings, as in the offsets of git packfiles.
+//! - `sqlite`: most significant first, 1 to 9 bytes, the 9th byte holding 8 bits.
+//!
+//! A value that doesn't fit in the field type, or that overflows a `u64`, fails to read with an
+//! error of kind `InvalidData`.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug, PartialEq)]
+//! struct Event {
+//!     #[plod(varint = midi_vlq)]
+//!     delta: u32,
+//!     #[plod(varint = leb128)]
+//!     value: u64,
+//! }
+//!
+//! let event = Event::read_from(&mut [0x81, 0x00, 0xe5, 0x8e, 0x26].as_slice()).unwrap();
+//! assert_eq!(event, Event { delta: 128, value: 624485 });
+//! assert_eq!(event.size_at_rest(), 5);
+//! ```
+
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::Result;
+
+/// How a varint is stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Varint {
+    /// LEB128 and protobuf varints, least significant group first
+    Leb128,
+    /// MIDI variable length quantities, most significant group first
+    MidiVlq,
+    /// git offset encoding, most significant group first, each continuation adding one
+    GitVlq,
+    /// SQLite varints, most significant first, up to 9 bytes
+    Sqlite,
+}
+
+impl Varint {
+    /// Name of the flavor, as given to `#[plod(varint = ...)]`
+    pub fn name(self) -> &'static str {
+        match self {
+            Varint::Leb128 => "leb128",
+            Varint::MidiVlq => "midi_vlq",
+            Varint::GitVlq => "git_vlq",
+            Varint::Sqlite => "sqlite",
+        }
+    }
+
+    /// Number of bytes taken by `value`
+    pub fn size(self, value: u64) -> usize {
+        self.encode(value).len()
+    }
+
+    /// Bytes of `value`
+    pub fn encode(self, value: u64) -> Vec<u8> {
+        match self {
+            Varint::Leb128 => {
+                let mut bytes = Vec::new();
+                let mut value = value;
+                while value >= 0x80 {
+                    bytes.push(value as u8 | 0x80);
+                    value >>= 7;
+                }
+                bytes.push(value as u8);
+                bytes
+            }
+            Varint::MidiVlq | Varint::GitVlq => {
+                let mut bytes = vec![value as u8 & 0x7f];
+                let mut value = value >> 7;
+                while value > 0 {
+                    if self == Varint::GitVlq {
+                        value -= 1;
+                    }
+                    bytes.push(value as u8 | 0x80);
+                    value >>= 7;
+                }
+                bytes.reverse();
+                bytes
+            }
+            Varint::Sqlite if value >> 56 != 0 => {
+                // 8 groups of 7 bits, then the 8 low bits
+                let mut bytes: Vec<u8> = (0..8).map(|i| (value >> (57 - 7 * i)) as u8 | 0x80).collect();
+                bytes.push(value as u8);
+                bytes
+            }
+            Varint::Sqlite => Varint::MidiVlq.encode(value),
+        }
+    }
+
+    /// Read a value, returns it with the number of bytes read
+    pub fn read<R: Read>(self, from: &mut R) -> Result<(u64, usize)> {
+        let overflow = || Error::new(ErrorKind::InvalidData, format!("{} varint overflows 64 bits", self.name()));
+        let mut value: u64 = 0;
+        let mut size = 0;
+        loop {
+            let mut byte = [0_u8];
+            from.read_exact(&mut byte)?;
+            let byte = byte[0];
+            size += 1;
+            match self {
+                Varint::Leb128 => {
+                    let shift = 7 * (size - 1);
+                    let bits = (byte & 0x7f) as u64;
+                    if shift >= 64 || (shift > 0 && bits >> (64 - shift) != 0) {
+                        return Err(overflow());
+                    }
+                    value |= bits << shift;
+                }
+                Varint::Sqlite if size == 9 => return Ok(((value << 8) | byte as u64, size)),
+                Varint::MidiVlq | Varint::Sqlite => {
+                    if value >> 57 != 0 {
+                        return Err(overflow());
+                    }
+                    value = (value << 7) | (byte & 0x7f) as u64;
+                }
+                Varint::GitVlq => {
+                    if size > 1 {
+                        value = value.checked_add(1).ok_or_else(overflow)?;
+                    }
+                    if value >> 57 != 0 {
+                        return Err(overflow());
+                    }
+                    value = (value << 7) | (byte & 0x7f) as u64;
+                }
+            }
+            if byte & 0x80 == 0 {
+                return Ok((value, size));
+            }
+        }
+    }
+
+    /// Write a value, returns the number of bytes written
+    pub fn write<W: Write>(self, to: &mut W, value: u64) -> Result<usize> {
+        let bytes = self.encode(value);
+        to.write_all(&bytes)?;
+        Ok(bytes.len())
+    }
+}
@@ -0,0 +1,97 @@
+//! Checking writes by reading them back
+//!
+//! A derive configuration can produce bytes that don't read back as the value written, for
+//! example a kept tag selecting another variant. While [`with_verify`] runs, [`Plod::write_to`]
+//! reads back what it produced and writes the value read again before writing to the real
+//! writer. Asymmetries are reported as errors of kind `InvalidData` naming the first field that
+//! differs, during development instead of in production files. [`write_verified`] does the same
+//! for a single write.
+//!
+//! ```
+//! use plod::Plod;
+//!
+//! #[derive(Plod, Debug)]
+//! #[plod(tag_type(u8))]
+//! enum Command {
+//!     #[plod(tag = 1)]
+//!     Reset(u16),
+//!     // a kept tag of 1 would be read as a Reset
+//!     #[plod(keep_tag)]
+//!     Other(u8, u8),
+//! }
+//!
+//! let mut data = Vec::new();
+//! let result = plod::verify::with_verify(|| Command::Other(1, 5).write_to(&mut data));
+//! assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+//! assert!(data.is_empty());
+//! ```
+
+use std::cell::Cell;
+use std::io::{Error, ErrorKind, Write};
+
+use crate::diff::diff_spans;
+use crate::dump::spans;
+use crate::{Plod, Result};
+
+thread_local! {
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Restores the previous mode even if the closure panics
+struct Restore(bool);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        ACTIVE.with(|a| a.set(self.0));
+    }
+}
+
+/// Run `f` with all calls to [`Plod::write_to`] on the current thread verified by reading back
+/// what they write
+pub fn with_verify<T, F: FnOnce() -> T>(f: F) -> T {
+    let _restore = Restore(ACTIVE.with(|a| a.replace(true)));
+    f()
+}
+
+/// Tell if writes are verified on the current thread
+pub(crate) fn active() -> bool {
+    ACTIVE.with(|a| a.get())
+}
+
+fn mismatch<T>(message: String) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("{} {}", std::any::type_name::<T>(), message),
+    )
+}
+
+/// Write `value` to `to` after checking that its bytes read back as a value with the same
+/// bytes, nothing is written if they don't
+pub fn write_verified<T: Plod, W: Write>(value: &T, to: &mut W) -> Result<()>
+where
+    T::Context: Default,
+{
+    let ctx = T::Context::default();
+    let mut written = Vec::new();
+    value.impl_write_to(&mut written, &ctx, 0)?;
+    let (read, rest) = T::read_from_slice(&written)
+        .map_err(|e| mismatch::<T>(format!("cannot be read back: {}", e)))?;
+    if !rest.is_empty() {
+        return Err(mismatch::<T>(format!(
+            "reads back {} of the {} bytes written",
+            written.len() - rest.len(),
+            written.len()
+        )));
+    }
+    let mut rewritten = Vec::new();
+    read.impl_write_to(&mut rewritten, &ctx, 0)?;
+    if rewritten != written {
+        let differences = diff_spans(&written, &spans(value), &rewritten, &spans(&read));
+        let message = match differences.first() {
+            Some(difference) => format!("reads back as a different value, {}", difference),
+            None => "reads back as a different value".to_string(),
+        };
+        return Err(mismatch::<T>(message));
+    }
+    to.write_all(&written)
+}
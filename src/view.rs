@@ -0,0 +1,87 @@
+//! Writing data owned elsewhere
+//!
+//! Serializing a view over borrowed data should not need a clone into an owned value.
+//! [`PlodWrite`] is the write half of [`Plod`]: it is implemented by every [`Plod`] type and by
+//! slices `[T]`, whose items are written one after the other without a size. Functions taking a
+//! `&T` with `T: PlodWrite + ?Sized`, like [`write_framed`](crate::framing::write_framed), then
+//! accept a value, a `&[T]` borrowed from a `Vec` or a `Box<[T]>`, or a `Box<T>`.
+//!
+//! Its methods have their own names so that they don't clash with the ones of [`Plod`].
+//!
+//! ```
+//! use plod::{Plod, PlodWrite};
+//!
+//! #[derive(Plod)]
+//! struct Point {
+//!     x: u8,
+//!     y: u8,
+//! }
+//!
+//! let points = vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }];
+//! let mut data = Vec::new();
+//! points[1..].write_view(&mut data).unwrap();
+//! points.as_slice().write_view(&mut data).unwrap();
+//! assert_eq!(data, [3, 4, 1, 2, 3, 4]);
+//! assert_eq!(points.view_size(), 4);
+//! ```
+
+use std::io::Write;
+
+use crate::{Plod, Result};
+
+/// Values that can be written, see the [module documentation](self)
+pub trait PlodWrite {
+    /// Context passed to the write methods
+    type Context;
+
+    /// Size of the bytes written, like [`Plod::size_at_rest`]
+    fn view_size(&self) -> usize;
+
+    /// Write at position `pos` with a context, like [`Plod::impl_write_to`]
+    fn impl_write_view<W: Write>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()>;
+
+    /// Write with a context, like [`Plod::write_with`]
+    fn write_view_with<W: Write>(&self, to: &mut W, ctx: &Self::Context) -> Result<()> {
+        self.impl_write_view(to, ctx, 0)
+    }
+
+    /// Write with the default context, like [`Plod::write_to`]
+    fn write_view<W: Write>(&self, to: &mut W) -> Result<()>
+    where
+        Self::Context: Default,
+    {
+        self.impl_write_view(to, &Self::Context::default(), 0)
+    }
+}
+
+impl<T: Plod> PlodWrite for T {
+    type Context = T::Context;
+
+    fn view_size(&self) -> usize {
+        self.size_at_rest()
+    }
+
+    fn impl_write_view<W: Write>(&self, to: &mut W, ctx: &Self::Context, pos: usize) -> Result<()> {
+        self.impl_write_to(to, ctx, pos)
+    }
+
+    fn write_view_with<W: Write>(&self, to: &mut W, ctx: &Self::Context) -> Result<()> {
+        self.write_with(to, ctx)
+    }
+}
+
+impl<T: Plod> PlodWrite for [T] {
+    type Context = T::Context;
+
+    fn view_size(&self) -> usize {
+        self.iter().map(Plod::size_at_rest).sum()
+    }
+
+    fn impl_write_view<W: Write>(&self, to: &mut W, ctx: &Self::Context, mut pos: usize) -> Result<()> {
+        for item in self {
+            item.impl_write_to(to, ctx, pos)?;
+            pos += item.size_at_rest();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+//! Interpretation of a [`Layout`] on serialized bytes, without building values
+//!
+//! The walker reads a value as described by its layout and copies its bytes to a writer,
+//! reversing numbers on the way if needed. This is how [`convert`](crate::convert) changes the
+//! endianness of a value and how [`Plod::skip_from`](crate::Plod::skip_from) and
+//! [`Plod::validate_from`](crate::Plod::validate_from) go through a value without reading it.
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read, Write};
+
+use crate::layout::{aligned, Endianness, Field, Layout, Magic, Primitive};
+use crate::lenient::UnknownTag;
+use crate::Result;
+
+pub(crate) struct Walker {
+    /// endianness of the numbers read, `None` for the one of the layout
+    pub source: Option<Endianness>,
+    /// reverse the bytes of numbers when writing them
+    pub swap: bool,
+    /// check that magic values are one of the accepted values
+    pub check_magic: bool,
+}
+
+impl Walker {
+    /// Go through a value of the given layout, returns its size at rest
+    pub fn walk(&self, layout: &Layout, from: &mut dyn Read, to: &mut dyn Write) -> Result<usize> {
+        self.layout(layout, from, to, &HashMap::new())
+    }
+
+    /// Copy a number and return its value
+    fn primitive(
+        &self,
+        primitive: &Primitive,
+        from: &mut dyn Read,
+        to: &mut dyn Write,
+    ) -> Result<i128> {
+        let mut bytes = vec![0_u8; primitive.size];
+        from.read_exact(&mut bytes)?;
+        let endianness = self.source.unwrap_or(primitive.endianness).resolve();
+        let value = value(primitive, endianness, &bytes);
+        if self.swap {
+            bytes.reverse();
+        }
+        to.write_all(&bytes)?;
+        Ok(value)
+    }
+
+    fn magic(
+        &self,
+        magic: &Option<Magic>,
+        from: &mut dyn Read,
+        to: &mut dyn Write,
+    ) -> Result<usize> {
+        match magic {
+            Some(magic) => {
+                let value = self.primitive(&magic.ty, from, to)?;
+                if self.check_magic && !magic.values.contains(&value) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Invalid magic {:#x}, expected {}", value, magic.value),
+                    ));
+                }
+                Ok(magic.ty.size)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Walk fields, previous integer fields are available to `Vec` using them as their length.
+    /// `start` is the position of the first field, the value being walked starting at 0.
+    fn fields(
+        &self,
+        fields: &[Field],
+        start: usize,
+        from: &mut dyn Read,
+        to: &mut dyn Write,
+    ) -> Result<usize> {
+        let mut values = HashMap::new();
+        let mut size = 0;
+        for field in fields {
+            match &field.layout {
+                Layout::Primitive(primitive) => {
+                    values.insert(field.name, self.primitive(primitive, from, to)?);
+                    size += primitive.size;
+                }
+                Layout::PadTo { offset, .. } => {
+                    let padding = crate::helpers::padding_to(start + size, *offset)?;
+                    size += self.copy_bytes(0, padding, from, to)?;
+                }
+                layout => size += self.layout(layout, from, to, &values)?,
+            }
+        }
+        Ok(size)
+    }
+
+    /// Walk a region prefixed by its length if there is one
+    fn length_prefixed(
+        &self,
+        length: &Option<Primitive>,
+        from: &mut dyn Read,
+        to: &mut dyn Write,
+        content: impl Fn(&mut dyn Read, &mut dyn Write) -> Result<usize>,
+    ) -> Result<usize> {
+        let length = match length {
+            Some(length) => length,
+            None => return content(from, to),
+        };
+        let size = usize::try_from(self.primitive(length, from, to)?)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Negative length"))?;
+        crate::fuzz::check_len(size)?;
+        let mut region = vec![0_u8; size];
+        from.read_exact(&mut region)?;
+        let mut reader = region.as_slice();
+        let walked = content(&mut reader, to)?;
+        // unknown trailing bytes are copied as is
+        to.write_all(reader)?;
+        Ok(length.size + walked + reader.len())
+    }
+
+    /// Copy bytes as is from `size` up to `end`
+    fn copy_bytes(
+        &self,
+        size: usize,
+        end: usize,
+        from: &mut dyn Read,
+        to: &mut dyn Write,
+    ) -> Result<usize> {
+        let count = end.saturating_sub(size);
+        let copied = std::io::copy(&mut from.take(count as u64), to)?;
+        if copied as usize != count {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+        Ok(count)
+    }
+
+    /// Walk a collection item after the padding aligning it, `content` is the size of the
+    /// previous items
+    fn item(
+        &self,
+        content: usize,
+        item: &Layout,
+        item_align: Option<usize>,
+        from: &mut dyn Read,
+        to: &mut dyn Write,
+    ) -> Result<usize> {
+        let padding = self.copy_bytes(content, aligned(content, item_align), from, to)?;
+        Ok(padding + self.layout(item, from, to, &HashMap::new())?)
+    }
+
+    /// Walk any layout, `values` are the integer fields read before it in the same struct
+    fn layout(
+        &self,
+        layout: &Layout,
+        from: &mut dyn Read,
+        to: &mut dyn Write,
+        values: &HashMap<&str, i128>,
+    ) -> Result<usize> {
+        match layout {
+            Layout::Primitive(primitive) => {
+                self.primitive(primitive, from, to)?;
+                Ok(primitive.size)
+            }
+            Layout::Tuple(items) => items.iter().try_fold(0, |size, item| {
+                Ok(size + self.layout(item, from, to, &HashMap::new())?)
+            }),
+            Layout::Array {
+                len,
+                item,
+                item_align,
+            } => (0..*len).try_fold(0, |size, _| {
+                Ok(size + self.item(size, item, *item_align, from, to)?)
+            }),
+            Layout::Vec {
+                size,
+                byte_sized,
+                size_adjust,
+                size_unit,
+                length_field,
+                item,
+                item_align,
+            } => {
+                let (prefix, stored) = match length_field {
+                    Some(field) => match values.get(field) {
+                        Some(value) => (0, *value),
+                        None => return Err(unsupported(&format!("length field {}", field))),
+                    },
+                    None => (size.size, self.primitive(size, from, to)?),
+                };
+                // signed size types come from signed_size, negative sizes are empty
+                let len = if length_field.is_none() && stored < 0 {
+                    0
+                } else {
+                    usize::try_from(stored - *size_adjust as i128)
+                        .map_err(|_| Error::new(ErrorKind::InvalidData, "Negative size"))?
+                        .saturating_mul(*size_unit)
+                };
+                crate::fuzz::check_len(len)?;
+                let mut content = 0;
+                if let (false, None, Layout::Primitive(primitive)) =
+                    (self.swap, item_align, item.as_ref())
+                {
+                    // numbers are copied as is, at once
+                    let bytes = if *byte_sized {
+                        len
+                    } else {
+                        len.saturating_mul(primitive.size)
+                    };
+                    content = self.copy_bytes(0, bytes, from, to)?;
+                } else if *byte_sized {
+                    while content < len {
+                        match self.item(content, item, *item_align, from, to)? {
+                            // an empty item would loop forever
+                            0 => return Err(unsupported("empty items in a sized Vec")),
+                            n => content += n,
+                        }
+                    }
+                } else {
+                    for _ in 0..len {
+                        content += self.item(content, item, *item_align, from, to)?;
+                    }
+                }
+                Ok(prefix + content)
+            }
+            Layout::Encoded {
+                size, layout: None, ..
+            } => {
+                // raw bytes contain no number
+                let len = usize::try_from(self.primitive(size, from, to)?)
+                    .map_err(|_| Error::new(ErrorKind::InvalidData, "Negative size"))?;
+                Ok(size.size + self.copy_bytes(0, len, from, to)?)
+            }
+            Layout::Encoded { encoding, .. } => Err(unsupported(encoding)),
+            Layout::Rest => Ok(std::io::copy(from, to)? as usize),
+            // only meaningful between fields
+            Layout::PadTo { .. } => Err(unsupported("padding outside of a struct")),
+            Layout::IfRemaining(item) => {
+                let mut first = [0_u8; 1];
+                if crate::helpers::read_some(from, &mut first)? == 0 {
+                    return Ok(0);
+                }
+                let mut from = first.as_slice().chain(from);
+                self.layout(item, &mut from, to, values)
+            }
+            Layout::Struct(s) => self.length_prefixed(&s.length, from, to, |from, to| {
+                let _depth = crate::fuzz::enter()?;
+                let mut size = self.magic(&s.magic, from, to)?;
+                size += self.fields(s.stored_fields(), size, from, to)?;
+                size += self.magic(&s.trailer, from, to)?;
+                if let Some(align) = s.align_size {
+                    size += self.copy_bytes(size, aligned(size, Some(align)), from, to)?;
+                }
+                Ok(size)
+            }),
+            Layout::Enum(e) => self.length_prefixed(&e.length, from, to, |from, to| {
+                let _depth = crate::fuzz::enter()?;
+                let tag = self.primitive(&e.tag, from, to)?;
+                let variant = e
+                    .variants
+                    .iter()
+                    .filter(|v| !v.skip)
+                    .find(|v| match &v.tag {
+                        Some(t) => t.values.iter().any(|r| r.contains(&tag)),
+                        None => true,
+                    })
+                    .ok_or_else(|| {
+                        Error::other(UnknownTag {
+                            type_name: e.name,
+                            tag,
+                            expected: "",
+                            position: 0,
+                            skipped: None,
+                        })
+                    })?;
+                if variant.tag.as_ref().is_some_and(|t| t.guard.is_some()) {
+                    return Err(Error::new(
+                        ErrorKind::Unsupported,
+                        format!(
+                            "The variant {} of {} is selected by a guard on the context",
+                            variant.name, e.name
+                        ),
+                    ));
+                }
+                let mut size = self.magic(&variant.magic, from, to)?;
+                size += self.fields(&variant.stored_fields(), e.tag.size + size, from, to)?;
+                size += self.magic(&variant.trailer, from, to)?;
+                if let Some(variant_size) = e.variant_size {
+                    size += self.copy_bytes(size, variant_size, from, to)?;
+                }
+                size += e.tag.size;
+                if let Some(align) = e.align_size {
+                    size += self.copy_bytes(size, aligned(size, Some(align)), from, to)?;
+                }
+                Ok(size)
+            }),
+            Layout::Type { layout, read, .. } => match (layout(), read) {
+                // a value can be read when its bytes are copied as is
+                (Layout::Opaque { .. }, Some(read)) if !self.swap && self.source.is_none() => {
+                    read(&mut Tee { from, to })
+                }
+                (layout, _) => self.layout(&layout, from, to, values),
+            },
+            Layout::Opaque { name } => Err(unsupported(name)),
+        }
+    }
+}
+
+/// Reader copying the bytes it reads to a writer
+struct Tee<'a> {
+    from: &'a mut dyn Read,
+    to: &'a mut dyn Write,
+}
+
+impl Read for Tee<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.from.read(buf)?;
+        self.to.write_all(&buf[..read])?;
+        Ok(read)
+    }
+}
+
+/// Integer value of the bytes of a number
+fn value(primitive: &Primitive, endianness: Endianness, bytes: &[u8]) -> i128 {
+    if primitive.is_float() {
+        return 0;
+    }
+    let mut le = bytes.to_vec();
+    if endianness == Endianness::Big {
+        le.reverse();
+    }
+    let negative = primitive.is_signed() && le.last().is_some_and(|b| b & 0x80 != 0);
+    le.resize(16, if negative { 0xff } else { 0 });
+    i128::from_le_bytes(le.try_into().unwrap())
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!("The layout of {} is not known", what),
+    )
+}
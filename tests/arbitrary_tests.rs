@@ -0,0 +1,122 @@
+#![cfg(feature = "arbitrary")]
+
+use plod::testing::{check_round_trip, write_seed_corpus};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), arbitrary)]
+enum Message {
+    #[plod(tag = 1)]
+    Ping(u32),
+    #[plod(tag = 2, size_type(u8), byte_sized)]
+    Data { id: u16, values: Vec<(u16, u8)> },
+    #[plod(tag = 3..=9 | 12, keep_tag)]
+    Level(u8, [i16; 2]),
+    #[plod(tag = 10)]
+    Boxed(Box<(u8, u16)>),
+    #[plod(tag = 11)]
+    Raw(#[plod(as_vec, size_type(u8))] Bytes),
+    #[plod(keep_tag, keep_diff = 100)]
+    Other(u8),
+    #[plod(skip)]
+    Never,
+}
+
+#[derive(PartialEq, Debug)]
+struct Bytes(Vec<u8>);
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+impl std::borrow::Borrow<Vec<u8>> for Bytes {
+    fn borrow(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, magic(u16 = 0xcafe), arbitrary)]
+struct Frame<T: Plod<Context = ()>> {
+    #[plod(size_type(u8), size_is_next)]
+    messages: Vec<Message>,
+    #[plod(skip)]
+    cache: u32,
+    body: T,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(length_prefixed(u8), arbitrary)]
+struct Extended {
+    version: u8,
+    #[plod(if_remaining)]
+    flags: Option<u16>,
+    #[plod(if_remaining)]
+    name: Option<[u8; 4]>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(arbitrary)]
+struct Level {
+    value: plod::Bounded<i8, { -10 }, 10>,
+    #[plod(size_type(u8), bitmap)]
+    reached: Vec<bool>,
+    #[plod(size_type(u8), byte_sized, bitmap)]
+    unlocked: Vec<bool>,
+    #[plod(equals = b"LV")]
+    signature: [u8; 2],
+    #[plod(size_type(u8))]
+    name: plod::InlineStr<6>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, arbitrary)]
+struct Header {
+    #[plod(tag_for = payload)]
+    msg_type: u8,
+    #[plod(external_tag)]
+    payload: Message,
+}
+
+#[test]
+fn test_round_trips() {
+    // skipped variants cannot be written so they must never be generated
+    assert!(Message::Never.write_to(&mut Vec::new()).is_err());
+    let mut seed = 0x2545f4914f6cdd1d_u64;
+    for size in 0..500 {
+        let data: Vec<u8> = (0..size)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                seed as u8
+            })
+            .collect();
+        check_round_trip::<Message>(&data).unwrap();
+        check_round_trip::<Frame<Message>>(&data).unwrap();
+        check_round_trip::<Extended>(&data).unwrap();
+        check_round_trip::<Header>(&data).unwrap();
+        check_round_trip::<Level>(&data).unwrap();
+    }
+}
+
+#[test]
+fn test_seed_corpus() {
+    let dir = std::env::temp_dir().join(format!("plod_seed_corpus_{}", std::process::id()));
+    let count = write_seed_corpus::<Message>(&dir).unwrap();
+    let mut variants = std::collections::HashSet::new();
+    let mut largest = 0;
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let bytes = std::fs::read(entry.unwrap().path()).unwrap();
+        let message = Message::read_from(&mut bytes.as_slice()).unwrap();
+        variants.insert(std::mem::discriminant(&message));
+        largest = largest.max(bytes.len());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(count > 6);
+    // every variant but the skipped one, and the largest Data
+    assert_eq!(variants.len(), 6);
+    assert!(largest >= 3 + 255);
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "bumpalo")]
+
+use bumpalo::Bump;
+use plod::Plod;
+use std::io::ErrorKind;
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = &'a Bump, big_endian)]
+struct Record<'a> {
+    id: u32,
+    #[plod(size_type(u8))]
+    name: &'a str,
+    #[plod(len_of = payload)]
+    len: u16,
+    payload: &'a [u8],
+}
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = &'a Bump, big_endian)]
+struct Batch<'a> {
+    #[plod(size_type(u16))]
+    records: Vec<Record<'a>>,
+}
+
+#[test]
+fn test_arena() {
+    let batch = Batch {
+        records: vec![
+            Record { id: 1, name: "first", len: 0, payload: &[1, 2, 3] },
+            Record { id: 2, name: "", len: 0, payload: &[] },
+            Record { id: 3, name: "été", len: 0, payload: &[0xff; 40] },
+        ],
+    };
+    let bump = Bump::new();
+    let mut data = Vec::new();
+    batch.write_with(&mut data, &&bump).unwrap();
+    assert_eq!(data.len(), batch.size_at_rest());
+    assert_eq!(bump.allocated_bytes_including_metadata(), 0);
+
+    let arena = Bump::new();
+    let read = Batch::read_with(&mut data.as_slice(), &&arena).unwrap();
+    assert_eq!(read.records[0].len, 3);
+    assert_eq!(read.records[2].name, "été");
+    assert_eq!(read.records[2].payload, &[0xff; 40]);
+    // names and payloads are in the arena
+    assert!(arena.allocated_bytes() >= 5 + 3 + 4 + 40);
+
+    let mut invalid = data.clone();
+    invalid[2 + 4 + 1] = 0xff;
+    let err = Batch::read_with(&mut invalid.as_slice(), &&arena).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().ends_with("Invalid utf8_strict text at offset 7"), "{}", err);
+}
@@ -0,0 +1,62 @@
+use plod::layout::Layout;
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug, Clone, Copy)]
+#[plod(big_endian)]
+struct Point {
+    x: i16,
+    y: i16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, size_type(u8))]
+struct Name {
+    text: Vec<u8>,
+}
+
+// generic code only knows that the type implements Plod
+fn round_trip<T: Plod<Context = ()>>(value: &T) -> T {
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data.len(), value.size_at_rest());
+    T::read_from(&mut data.as_slice()).unwrap()
+}
+
+#[test]
+fn test_array() {
+    let points = [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }];
+    let mut data = Vec::new();
+    points.write_to(&mut data).unwrap();
+    assert_eq!(data, [0, 1, 0, 2, 0, 3, 0, 4]);
+    assert_eq!(round_trip(&points), points);
+    assert_eq!(<[Point; 2]>::FIXED_SIZE, Some(8));
+    assert_eq!(<[Point; 2]>::offset_of("[1].y"), Some(6));
+    assert!(matches!(
+        <[Point; 2]>::describe(),
+        Layout::Array { len: 2, .. }
+    ));
+    assert_eq!(<[Point; 2]>::skip_from(&mut data.as_slice()).unwrap(), 8);
+
+    let names = [
+        Name {
+            text: b"ab".to_vec(),
+        },
+        Name {
+            text: b"c".to_vec(),
+        },
+    ];
+    assert_eq!(<[Name; 2]>::FIXED_SIZE, None);
+    assert_eq!(round_trip(&names), names);
+    let spans = plod::dump::spans(&names);
+    let paths: Vec<_> = spans.iter().map(|s| (s.path.as_str(), s.start)).collect();
+    assert!(
+        paths
+            .iter()
+            .any(|&(p, start)| p.ends_with("[1].text") && start == 4),
+        "{:?}",
+        paths
+    );
+
+    let error = <[Point; 2]>::read_from(&mut [0_u8; 6].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
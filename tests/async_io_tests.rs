@@ -0,0 +1,95 @@
+#![cfg(feature = "futures-io")]
+
+use std::future::Future;
+use std::io::ErrorKind;
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll, Waker};
+
+use futures_io::AsyncRead;
+use plod::async_io::{write_to_async, AsyncPlodReader};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8))]
+enum Message {
+    #[plod(tag = 1)]
+    Ping(u32),
+    #[plod(tag = 2, size_type(u16))]
+    Text(Vec<u8>),
+}
+
+// a stream returning one byte at a time, pending every other poll
+struct Trickle {
+    data: Vec<u8>,
+    pos: usize,
+    ready: bool,
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.ready = !self.ready;
+        if !self.ready {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        if self.pos == self.data.len() {
+            return Poll::Ready(Ok(0));
+        }
+        buf[0] = self.data[self.pos];
+        self.pos += 1;
+        Poll::Ready(Ok(1))
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn test_async_io() {
+    let mut data = Vec::new();
+    block_on(async {
+        write_to_async(&Message::Ping(7), &mut data).await.unwrap();
+        write_to_async(&Message::Text(b"hi".to_vec()), &mut data)
+            .await
+            .unwrap();
+    });
+    assert_eq!(data, &[1, 0, 0, 0, 7, 2, 0, 2, b'h', b'i']);
+
+    let stream = Trickle {
+        data,
+        pos: 0,
+        ready: false,
+    };
+    let mut reader = AsyncPlodReader::new(stream);
+    block_on(async {
+        assert_eq!(reader.read::<Message>().await.unwrap(), Message::Ping(7));
+        assert_eq!(
+            reader.read::<Message>().await.unwrap(),
+            Message::Text(b"hi".to_vec())
+        );
+        let error = reader.read::<Message>().await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    });
+}
+
+#[test]
+fn test_async_io_errors() {
+    // hostile length
+    let mut reader = AsyncPlodReader::with_max_length(&[2_u8, 0xff, 0xff, 0][..], 16);
+    let error = block_on(reader.read::<Message>()).unwrap_err();
+    assert_ne!(error.kind(), ErrorKind::UnexpectedEof);
+    // unknown tag
+    let mut reader = AsyncPlodReader::new(&[3_u8, 0][..]);
+    assert!(block_on(reader.read::<Message>()).is_err());
+}
@@ -0,0 +1,84 @@
+use plod::{Blob, Plod};
+use std::io::{Cursor, ErrorKind, Read};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Entry {
+    id: u16,
+    #[plod(size_type(u16))]
+    data: plod::Blob,
+    tail: u8,
+}
+
+/// a blob at the end, nothing else is read after it
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Trailing {
+    id: u16,
+    #[plod(size_type(u16))]
+    data: plod::Blob,
+}
+
+#[test]
+fn test_blob() {
+    let entry = Entry {
+        id: 1,
+        data: Blob::from_reader(Cursor::new(vec![5; 300]), 300),
+        tail: 9,
+    };
+    assert_eq!(entry.size_at_rest(), 305);
+    assert_eq!(Entry::FIXED_SIZE, None);
+    let mut data = Vec::new();
+    entry.write_to(&mut data).unwrap();
+    assert_eq!(&data[..4], &[1, 0, 44, 1]);
+    assert_eq!(data[304], 9);
+    // the source is consumed
+    let error = entry.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+    // readers without seek read the content and drop it
+    let read = Entry::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(read.data.offset(), Some(4));
+    assert_eq!(read.data.len(), 300);
+    assert_eq!(read.tail, 9);
+    let mut content = Vec::new();
+    read.data
+        .open(Cursor::new(&data), 0)
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    assert_eq!(content, vec![5; 300]);
+    // its content is not available anymore
+    assert_eq!(
+        read.write_to(&mut Vec::new()).unwrap_err().kind(),
+        ErrorKind::Unsupported
+    );
+
+    // a truncated content
+    let error = Entry::read_from(&mut &data[..100]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    let error = Entry::read_from_seek(&mut Cursor::new(&data[..100])).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    let error = Trailing::read_from_seek(&mut Cursor::new(&data[..100])).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    let read = Entry::read_from_seek(&mut Cursor::new(&data)).unwrap();
+    assert_eq!(read.data.offset(), Some(4));
+    assert_eq!(read.tail, 9);
+
+    let small = Entry {
+        id: 2,
+        data: Blob::from_bytes(b"ab".to_vec()),
+        tail: 3,
+    };
+    let mut data = Vec::new();
+    small.write_to(&mut data).unwrap();
+    assert_eq!(data, vec![2, 0, 2, 0, b'a', b'b', 3]);
+    let paths: Vec<_> = plod::dump::spans(&small)
+        .into_iter()
+        .map(|s| s.path)
+        .collect();
+    assert_eq!(
+        paths,
+        vec!["Entry.id", "Entry.data.<len>", "Entry.data", "Entry.tail"]
+    );
+}
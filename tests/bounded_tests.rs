@@ -0,0 +1,33 @@
+use plod::{Bounded, Plod};
+use std::io::ErrorKind;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Sample {
+    channel: plod::Bounded<u8, 0, 7>,
+    #[plod(big_endian)]
+    level: plod::bounded::Bounded<i16, { -100 }, 100>,
+}
+
+#[test]
+fn test_bounded() {
+    let sample = Sample {
+        channel: Bounded::try_from(3).unwrap(),
+        level: Bounded::try_from(-20).unwrap(),
+    };
+    assert_eq!(Sample::SIZE, 3);
+    let mut data = Vec::new();
+    sample.write_to(&mut data).unwrap();
+    assert_eq!(data, [3, 0xff, 0xec]);
+    assert_eq!(Sample::read_from(&mut data.as_slice()).unwrap(), sample);
+    // out of range values are rejected when read
+    let error = Sample::read_from(&mut [8, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    let error = Sample::read_from(&mut [0, 0, 101].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    let error = Bounded::<u16, 1, 12>::try_from(0).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(Bounded::<u8, 1, 12>::saturating(200).get(), 12);
+    assert_eq!(Bounded::<u128, 1, 12>::saturating(u128::MAX).get(), 12);
+    assert_eq!(u8::from(sample.channel), 3);
+}
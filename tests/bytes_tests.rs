@@ -0,0 +1,45 @@
+#![cfg(feature = "bytes")]
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Chunk {
+    kind: u16,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[test]
+fn test_buf() {
+    let chunk = Chunk {
+        kind: 3,
+        data: vec![1, 2],
+    };
+    let mut buf = BytesMut::new();
+    chunk.write_to_buf(&mut buf).unwrap();
+    chunk.write_to_buf(&mut buf).unwrap();
+    assert_eq!(&buf[..], &[0, 3, 2, 1, 2, 0, 3, 2, 1, 2]);
+
+    let mut bytes: Bytes = buf.freeze();
+    assert_eq!(Chunk::read_from_buf(&mut bytes).unwrap(), chunk);
+    assert_eq!(bytes.remaining(), 5);
+    assert_eq!(Chunk::read_from_buf(&mut bytes).unwrap(), chunk);
+    assert!(!bytes.has_remaining());
+    let error = Chunk::read_from_buf(&mut bytes).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_buf_full() {
+    let chunk = Chunk {
+        kind: 3,
+        data: vec![1, 2],
+    };
+    let mut array = [0_u8; 4];
+    let mut buf = &mut array[..];
+    let error = chunk.write_to_buf(&mut buf).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+    assert_eq!(buf.remaining_mut(), 0);
+}
@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use plod::cancel::{is_cancelled, with_cancel, Cancelled};
+use plod::progress::with_progress;
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Record {
+    id: u32,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Table {
+    version: u8,
+    #[plod(size_type(u16))]
+    records: Vec<Record>,
+}
+
+fn table(count: u16) -> Vec<u8> {
+    let mut data = vec![1];
+    data.extend(count.to_be_bytes());
+    for id in 0..count as u32 {
+        data.extend(id.to_be_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_cancel() {
+    let data = table(100);
+    let flag = Arc::new(AtomicBool::new(false));
+    let table = with_cancel(flag.clone(), || Table::read_from(&mut data.as_slice()));
+    assert_eq!(table.unwrap().records.len(), 100);
+
+    // cancelled by the progress callback half way
+    let cancel = flag.clone();
+    let error = with_cancel(flag, || {
+        with_progress(
+            200,
+            move |_| cancel.store(true, Ordering::Relaxed),
+            || Table::read_from(&mut data.as_slice()),
+        )
+    })
+    .unwrap_err();
+    assert!(is_cancelled(&error));
+    assert_eq!(error.get_ref().unwrap().downcast_ref(), Some(&Cancelled));
+
+    // the flag is only checked within with_cancel
+    assert!(Table::read_from(&mut data.as_slice()).is_ok());
+}
+
+#[test]
+fn test_cancel_records() {
+    let mut data = Vec::new();
+    plod::write_all(&mut data, &[Record { id: 1 }, Record { id: 2 }]).unwrap();
+    let flag = Arc::new(AtomicBool::new(true));
+    let records: Vec<_> = with_cancel(flag, || plod::iter::<Record, _>(data.as_slice()).collect());
+    assert_eq!(records.len(), 1);
+    assert!(is_cancelled(records[0].as_ref().unwrap_err()));
+}
@@ -0,0 +1,82 @@
+use plod::checksum::*;
+use plod::Plod;
+use std::io::ErrorKind;
+
+#[test]
+fn test_check_values() {
+    let data = b"123456789";
+    assert_eq!(Crc::CRC8.checksum(data), 0xf4);
+    assert_eq!(Crc::CRC8_MAXIM.checksum(data), 0xa1);
+    assert_eq!(Crc::CRC16_ARC.checksum(data), 0xbb3d);
+    assert_eq!(Crc::CRC16_MODBUS.checksum(data), 0x4b37);
+    assert_eq!(Crc::CRC16_XMODEM.checksum(data), 0x31c3);
+    assert_eq!(Crc::CRC16_CCITT_FALSE.checksum(data), 0x29b1);
+    assert_eq!(Crc::CRC16_KERMIT.checksum(data), 0x2189);
+    assert_eq!(Crc::CRC32.checksum(data), 0xcbf4_3926);
+    assert_eq!(Crc::CRC32C.checksum(data), 0xe306_9283);
+    assert_eq!(Crc::CRC32_MPEG2.checksum(data), 0x0376_e6e7);
+    // CRC-64/XZ
+    let crc64 = Crc::new(64, 0x42f0_e1eb_a9ea_3693)
+        .init(u64::MAX)
+        .reflected()
+        .xor_out(u64::MAX);
+    assert_eq!(crc64.checksum(data), 0x995d_c9bb_df19_39fa);
+    assert_eq!(Adler32.checksum(data), 0x091e_01de);
+    assert_eq!(Fletcher16.checksum(b"abcde"), 0xc8f0);
+    assert_eq!(Fletcher32.checksum(b"abcde"), 0xf04f_c729);
+    assert_eq!(Sum8.checksum(data), 0xdd);
+    assert_eq!(Sum16.checksum(data), 0x1dd);
+    assert_eq!(TwosComplement8.checksum(data), 0x23);
+    assert_eq!(Xor8.checksum(data), 0x31);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), little_endian)]
+enum Packet {
+    #[plod(tag = 1, magic(u16 = 0xcafe))]
+    Data(
+        #[plod(size_type(u8))] Vec<u8>,
+        #[plod(checksum(Crc::CRC32))] u32,
+    ),
+    #[plod(tag = 2)]
+    Ack {
+        sequence: u16,
+        #[plod(checksum(TwosComplement8))]
+        sum: u8,
+    },
+}
+
+#[test]
+fn test_checksum_field() {
+    let mut data = Vec::new();
+    Packet::Data(b"plod".to_vec(), 0)
+        .write_to(&mut data)
+        .unwrap();
+    // the tag and the magic are not part of the checksum
+    let crc = Crc::CRC32.checksum(b"\x04plod") as u32;
+    let mut expected = vec![1, 0xfe, 0xca, 4, b'p', b'l', b'o', b'd'];
+    expected.extend(crc.to_le_bytes());
+    assert_eq!(data, expected);
+    let packet = Packet::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(packet, Packet::Data(b"plod".to_vec(), crc));
+
+    data[5] = b'L';
+    let error = Packet::read_from(&mut data.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    let mut data = Vec::new();
+    Packet::Ack {
+        sequence: 0x102,
+        sum: 7,
+    }
+    .write_to(&mut data)
+    .unwrap();
+    assert_eq!(data, vec![2, 2, 1, 0xfd]);
+    assert_eq!(
+        Packet::read_from(&mut data.as_slice()).unwrap(),
+        Packet::Ack {
+            sequence: 0x102,
+            sum: 0xfd
+        }
+    );
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "codec")]
+
+use bytes::BytesMut;
+use plod::codec::{PlodCodec, PlodDecoder};
+use plod::Plod;
+use tokio_util::codec::{Decoder, Encoder};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8))]
+enum Message {
+    #[plod(tag = 1)]
+    Ping(u32),
+    #[plod(tag = 2, size_type(u16))]
+    Text(Vec<u8>),
+}
+
+#[test]
+fn test_codec() {
+    let mut codec = PlodCodec::<Message>::new();
+    let mut buf = BytesMut::new();
+    codec.encode(Message::Ping(7), &mut buf).unwrap();
+    codec
+        .encode(Message::Text(b"hi".to_vec()), &mut buf)
+        .unwrap();
+    assert_eq!(&buf[..], &[1, 0, 0, 0, 7, 2, 0, 2, b'h', b'i']);
+
+    // feed bytes one at a time
+    let mut input = BytesMut::new();
+    let mut messages = Vec::new();
+    for b in buf.iter() {
+        input.extend_from_slice(&[*b]);
+        if let Some(message) = codec.decode(&mut input).unwrap() {
+            messages.push(message);
+        }
+    }
+    assert_eq!(
+        messages,
+        vec![Message::Ping(7), Message::Text(b"hi".to_vec())]
+    );
+    assert!(input.is_empty());
+}
+
+#[test]
+fn test_codec_errors() {
+    let mut decoder = PlodDecoder::<Message>::with_max_length(16);
+    let mut input = BytesMut::from(&[3_u8, 0][..]);
+    assert!(decoder.decode(&mut input).is_err());
+    // hostile length
+    let mut input = BytesMut::from(&[2_u8, 0xff, 0xff, 0][..]);
+    let error = decoder.decode(&mut input).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,121 @@
+use plod::Plod;
+use std::io::ErrorKind;
+
+#[derive(Plod, PartialEq, Debug, Clone)]
+#[plod(big_endian)]
+struct Point {
+    x: u16,
+    y: u8,
+    #[plod(skip)]
+    cached: bool,
+}
+
+#[derive(Plod, PartialEq, Debug, Clone)]
+#[plod(big_endian)]
+struct Pair(u8, i16);
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Cloud {
+    count: u8,
+    tag: u8,
+    #[plod(columnar(count = count))]
+    points: Vec<Point>,
+    #[plod(columnar, size_type(u16))]
+    pairs: Vec<Pair>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(magic(u8 = 1))]
+struct Marked {
+    a: u8,
+}
+
+/// a range whose start is never after its end
+#[derive(Plod, PartialEq, Debug)]
+#[plod(construct_with = Range::new)]
+struct Range {
+    start: u8,
+    end: u8,
+}
+
+impl Range {
+    fn new(start: u8, end: u8) -> Result<Self, String> {
+        if start > end {
+            return Err(format!("range {}..{} is reversed", start, end));
+        }
+        Ok(Range { start, end })
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct Raw {
+    #[plod(keep_raw = raw)]
+    a: u8,
+    raw: Vec<u8>,
+    b: u8,
+}
+
+#[test]
+fn test_columnar() {
+    let point = |x, y| Point {
+        x,
+        y,
+        cached: false,
+    };
+    let cloud = Cloud {
+        count: 3,
+        tag: 7,
+        points: vec![point(1, 2), point(3, 4), point(5, 6)],
+        pairs: vec![Pair(1, -1), Pair(2, 2)],
+    };
+    assert_eq!(cloud.size_at_rest(), 2 + 9 + 2 + 6);
+    let mut data = Vec::new();
+    cloud.write_to(&mut data).unwrap();
+    assert_eq!(
+        data,
+        vec![
+            3, 7, // header
+            0, 1, 0, 3, 0, 5, // x
+            2, 4, 6, // y
+            0, 2, // pairs length
+            1, 2, // .0
+            0xff, 0xff, 0, 2, // .1
+        ]
+    );
+    assert_eq!(Cloud::read_from(&mut data.as_slice()).unwrap(), cloud);
+
+    // the count is the length of the vector
+    let mut longer = Cloud {
+        count: 0,
+        points: vec![point(8, 9)],
+        ..cloud
+    };
+    let mut data = Vec::new();
+    longer.write_to(&mut data).unwrap();
+    assert_eq!(&data[..6], &[1, 7, 0, 8, 9, 0]);
+    longer.count = 1;
+    assert_eq!(Cloud::read_from(&mut data.as_slice()).unwrap(), longer);
+
+    // errors name the field of the column
+    let error = Cloud::read_from(&mut &data[..4]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    assert!(error.to_string().contains("Cloud.points.y"), "{}", error);
+
+    // types that are more than their fields cannot be stored in columns, a columnar Vec of them
+    // doesn't compile
+    let error = Marked::impl_read_columns(&mut [1_u8, 1].as_slice(), &(), 0, 1).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+    let error = Marked::impl_write_columns(&[Marked { a: 1 }], &mut Vec::new(), &(), 0).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+
+    // values built by a constructor are not just their columns
+    let error = Range::impl_read_columns(&mut [4_u8, 1].as_slice(), &(), 0, 1).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+    let error = Range::impl_write_columns(&[Range::new(1, 2).unwrap()], &mut Vec::new(), &(), 0).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+
+    // kept raw bytes are those of a single value
+    let error = Raw::impl_read_columns(&mut [9_u8, 1].as_slice(), &(), 0, 1).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
@@ -0,0 +1,99 @@
+#![cfg(all(feature = "zlib", feature = "zstd", feature = "lz4"))]
+
+use plod::fuzz::{with_limits, Limits};
+use plod::layout::Layout;
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Document {
+    version: u16,
+    #[plod(compress = zlib, size_type(u32))]
+    text: Vec<u8>,
+    #[plod(compress = zstd, size_type(u16))]
+    index: Index,
+    #[plod(compress = lz4, size_type(u32))]
+    raw: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Index {
+    #[plod(size_type(u16))]
+    offsets: Vec<u32>,
+}
+
+fn document() -> Document {
+    Document {
+        version: 2,
+        text: b"plain old data ".repeat(50),
+        index: Index {
+            offsets: (0..100).collect(),
+        },
+        raw: vec![0; 1000],
+    }
+}
+
+#[test]
+fn test_compress() {
+    let document = document();
+    let mut bytes = Vec::new();
+    document.write_to(&mut bytes).unwrap();
+    assert_eq!(document.size_at_rest(), bytes.len());
+    assert!(bytes.len() < 500);
+    // zlib header
+    assert_eq!(bytes[6], 0x78);
+    assert_eq!(
+        plod::compress::zlib::decompress(&bytes[6..]).unwrap(),
+        document.text
+    );
+    let result = Document::read_from(&mut bytes.as_slice()).unwrap();
+    assert_eq!(result, document);
+}
+
+#[test]
+fn test_decompression_limit() {
+    let mut bytes = Vec::new();
+    document().write_to(&mut bytes).unwrap();
+    let limits = Limits {
+        max_len: 500,
+        ..Limits::default()
+    };
+    let error = with_limits(limits, || Document::read_from(&mut bytes.as_slice())).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_compress_layout() {
+    let layout = Document::describe();
+    let fields = match layout {
+        Layout::Struct(s) => s.fields,
+        _ => panic!("struct expected"),
+    };
+    match &fields[1].layout {
+        Layout::Encoded {
+            encoding,
+            size,
+            layout,
+        } => {
+            assert_eq!(*encoding, "zlib");
+            assert_eq!(size.ty, "u32");
+            assert!(layout.is_none());
+        }
+        _ => panic!("encoded expected"),
+    }
+    assert!(matches!(
+        &fields[2].layout,
+        Layout::Encoded {
+            layout: Some(_),
+            ..
+        }
+    ));
+    let ksy = plod::export::kaitai::<Document>();
+    assert!(ksy.contains("process: zlib"));
+    assert!(plod::export::c_header::<Document>().is_err());
+    let spans = plod::dump::spans(&document());
+    assert_eq!(spans[1].path, "Document.text.<len>");
+    assert_eq!(spans[2].path, "Document.text");
+    assert_eq!(spans.last().unwrap().end(), document().size_at_rest());
+}
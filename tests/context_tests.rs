@@ -0,0 +1,241 @@
+use plod::Plod;
+
+#[derive(Default, Debug, PartialEq)]
+struct Limits {
+    scale: u16,
+}
+
+#[derive(Default, Debug, PartialEq)]
+struct Inner {
+    limits: Limits,
+}
+
+#[derive(Default, Debug, PartialEq)]
+struct Session {
+    inner: Inner,
+}
+
+plod::context!(Session => (), Inner: inner, Limits: inner.limits);
+plod::context!(Inner => ());
+
+#[derive(Debug, PartialEq)]
+struct Scaled(u16);
+
+impl Plod for Scaled {
+    type Context = Limits;
+
+    fn size_at_rest(&self) -> usize {
+        2
+    }
+
+    fn impl_read_from<R: std::io::Read>(
+        from: &mut R,
+        ctx: &Limits,
+        _pos: usize,
+    ) -> plod::Result<Self> {
+        let mut buffer = [0; 2];
+        from.read_exact(&mut buffer)?;
+        Ok(Scaled(u16::from_le_bytes(buffer) * ctx.scale))
+    }
+
+    fn impl_write_to<W: std::io::Write>(
+        &self,
+        to: &mut W,
+        ctx: &Limits,
+        _pos: usize,
+    ) -> plod::Result<()> {
+        to.write_all(&(self.0 / ctx.scale).to_le_bytes())
+    }
+}
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = Inner)]
+struct Record {
+    id: u8,
+}
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = Session)]
+struct Message {
+    kind: u8,
+    record: Record,
+    value: Scaled,
+}
+
+#[test]
+fn test_context_conversions() {
+    let session = Session {
+        inner: Inner {
+            limits: Limits { scale: 10 },
+        },
+    };
+    let message = Message {
+        kind: 1,
+        record: Record { id: 2 },
+        value: Scaled(30),
+    };
+    let mut bytes = Vec::new();
+    message.impl_write_to(&mut bytes, &session, 0).unwrap();
+    assert_eq!(bytes, vec![1, 2, 3, 0]);
+    let read = Message::impl_read_from(&mut bytes.as_slice(), &session, 0).unwrap();
+    assert_eq!(read, message);
+}
+
+#[test]
+fn test_context_projection() {
+    let session = Session::default();
+    let limits: &Limits = (&session).into();
+    assert!(std::ptr::eq(limits, &session.inner.limits));
+    let _: &() = (&session).into();
+}
+
+/// a context borrowing its data, it has no default
+struct Key<'a>(&'a [u8]);
+
+#[derive(Debug, PartialEq)]
+struct Masked(u8);
+
+impl Plod for Masked {
+    type Context = Key<'static>;
+
+    fn size_at_rest(&self) -> usize {
+        1
+    }
+
+    fn impl_read_from<R: std::io::Read>(
+        from: &mut R,
+        ctx: &Key<'static>,
+        pos: usize,
+    ) -> plod::Result<Self> {
+        let mut buffer = [0; 1];
+        from.read_exact(&mut buffer)?;
+        Ok(Masked(buffer[0] ^ ctx.0[pos % ctx.0.len()]))
+    }
+
+    fn impl_write_to<W: std::io::Write>(
+        &self,
+        to: &mut W,
+        ctx: &Key<'static>,
+        pos: usize,
+    ) -> plod::Result<()> {
+        to.write_all(&[self.0 ^ ctx.0[pos % ctx.0.len()]])
+    }
+}
+
+#[test]
+fn test_context_without_default() {
+    let key = Key(&[0x0f]);
+    let mut bytes = Vec::new();
+    Masked(0x12).write_with(&mut bytes, &key).unwrap();
+    assert_eq!(bytes, vec![0x1d]);
+    assert_eq!(Masked::read_with(&mut bytes.as_slice(), &key).unwrap(), Masked(0x12));
+}
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = u8)]
+struct Versioned {
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, Debug, PartialEq)]
+struct Point {
+    x: u8,
+    y: u8,
+}
+
+#[derive(Plod, Debug, PartialEq)]
+struct Packet {
+    #[plod(is_context)]
+    version: u8,
+    // neither needs a conversion from &u8
+    versioned: Versioned,
+    point: Point,
+}
+
+#[test]
+fn test_context_from_field() {
+    let packet = Packet {
+        version: 2,
+        versioned: Versioned { data: vec![7] },
+        point: Point { x: 1, y: 3 },
+    };
+    let mut bytes = Vec::new();
+    packet.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, vec![2, 1, 7, 1, 3]);
+    assert_eq!(Packet::read_from(&mut bytes.as_slice()).unwrap(), packet);
+}
+
+/// reusable under any context, its items share it
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = C)]
+struct Block<C, T: Plod<Context = C>> {
+    kind: u8,
+    #[plod(size_type(u8))]
+    items: Vec<T>,
+    point: Point,
+    marker: std::marker::PhantomData<C>,
+}
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = Limits)]
+struct Frame {
+    block: Block<Limits, Scaled>,
+}
+
+#[test]
+fn test_generic_context() {
+    let frame = Frame {
+        block: Block {
+            kind: 1,
+            items: vec![Scaled(20)],
+            point: Point { x: 2, y: 3 },
+            marker: std::marker::PhantomData,
+        },
+    };
+    let limits = Limits { scale: 10 };
+    let mut bytes = Vec::new();
+    frame.write_with(&mut bytes, &limits).unwrap();
+    assert_eq!(bytes, vec![1, 1, 2, 0, 2, 3]);
+    assert_eq!(Frame::read_with(&mut bytes.as_slice(), &limits).unwrap(), frame);
+}
+
+/// scales of the known versions, a missing version cannot be read
+impl TryFrom<&u8> for Limits {
+    type Error = String;
+
+    fn try_from(version: &u8) -> Result<Self, String> {
+        match version {
+            1 => Ok(Limits { scale: 10 }),
+            2 => Ok(Limits { scale: 100 }),
+            _ => Err(format!("unknown version {}", version)),
+        }
+    }
+}
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(little_endian)]
+struct Measures {
+    #[plod(is_context)]
+    version: u8,
+    #[plod(try_context)]
+    first: Scaled,
+    #[plod(try_context, size_type(u8))]
+    others: Vec<Scaled>,
+}
+
+#[test]
+fn test_try_context() {
+    let measures = Measures {
+        version: 2,
+        first: Scaled(300),
+        others: vec![Scaled(500)],
+    };
+    let mut bytes = Vec::new();
+    measures.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, vec![2, 3, 0, 1, 5, 0]);
+    assert_eq!(Measures::read_from(&mut bytes.as_slice()).unwrap(), measures);
+    let error = Measures::read_from(&mut [3_u8, 3, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("unknown version 3"));
+}
@@ -0,0 +1,106 @@
+use plod::layout::Endianness;
+use plod::{convert, Plod};
+
+#[derive(Plod, PartialEq, Debug, Clone)]
+#[plod(little_endian, magic(u16 = 0xCAFE))]
+struct Little {
+    id: u32,
+    #[plod(len_of = values)]
+    count: u16,
+    values: Vec<i16>,
+    #[plod(size_type(u16))]
+    items: Vec<LittleItem>,
+}
+
+#[derive(Plod, PartialEq, Debug, Clone)]
+#[plod(tag_type(u16), little_endian)]
+enum LittleItem {
+    #[plod(tag = 1)]
+    Number(i64),
+    #[plod(tag = 2)]
+    Pair(u8, f32),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, magic(u16 = 0xCAFE))]
+struct Big {
+    id: u32,
+    #[plod(len_of = values)]
+    count: u16,
+    values: Vec<i16>,
+    #[plod(size_type(u16))]
+    items: Vec<BigItem>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u16), big_endian)]
+enum BigItem {
+    #[plod(tag = 1)]
+    Number(i64),
+    #[plod(tag = 2)]
+    Pair(u8, f32),
+}
+
+#[test]
+fn test_convert() {
+    let little = Little {
+        id: 0x01020304,
+        count: 2,
+        values: vec![-2, 300],
+        items: vec![LittleItem::Number(-5), LittleItem::Pair(7, 1.5)],
+    };
+    let big = Big {
+        id: 0x01020304,
+        count: 2,
+        values: vec![-2, 300],
+        items: vec![BigItem::Number(-5), BigItem::Pair(7, 1.5)],
+    };
+    let mut little_bytes = Vec::new();
+    little.write_to(&mut little_bytes).unwrap();
+    let mut big_bytes = Vec::new();
+    big.write_to(&mut big_bytes).unwrap();
+
+    let mut converted = Vec::new();
+    convert::<Little, _, _>(
+        &mut little_bytes.as_slice(),
+        &mut converted,
+        Endianness::Little,
+        Endianness::Big,
+    )
+    .unwrap();
+    assert_eq!(converted, big_bytes);
+
+    // the declared endianness of the type doesn't matter
+    let mut back = Vec::new();
+    convert::<Little, _, _>(
+        &mut big_bytes.as_slice(),
+        &mut back,
+        Endianness::Big,
+        Endianness::Little,
+    )
+    .unwrap();
+    assert_eq!(back, little_bytes);
+
+    let mut same = Vec::new();
+    convert::<Big, _, _>(
+        &mut big_bytes.as_slice(),
+        &mut same,
+        Endianness::Big,
+        Endianness::Big,
+    )
+    .unwrap();
+    assert_eq!(same, big_bytes);
+}
+
+#[test]
+fn test_convert_unknown_tag() {
+    let bytes = [0xFE, 0xCA, 0, 0, 0, 0, 0, 0, 1, 0, 9, 0];
+    let error = convert::<Little, _, _>(
+        &mut bytes.as_slice(),
+        &mut Vec::new(),
+        Endianness::Little,
+        Endianness::Big,
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Other);
+}
@@ -0,0 +1,60 @@
+use plod::Plod;
+use std::borrow::Cow;
+use std::io::ErrorKind;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Record<'a> {
+    #[plod(size_type(u8))]
+    key: Cow<'a, str>,
+    #[plod(len_of = value)]
+    len: u16,
+    value: std::borrow::Cow<'a, [u8]>,
+}
+
+#[test]
+fn test_cow() {
+    let payload = [1, 2, 3];
+    let record = Record {
+        key: Cow::Borrowed("id"),
+        len: 0,
+        value: Cow::Borrowed(&payload),
+    };
+    assert_eq!(record.size_at_rest(), 8);
+    assert_eq!(Record::FIXED_SIZE, None);
+    let mut data = Vec::new();
+    record.write_to(&mut data).unwrap();
+    assert_eq!(data, vec![2, b'i', b'd', 3, 0, 1, 2, 3]);
+
+    // values are read owned, even from a slice, readers cannot lend their bytes
+    let read = Record::read_from(&mut data.as_slice()).unwrap();
+    assert!(matches!(read.key, Cow::Owned(_)));
+    assert!(matches!(read.value, Cow::Owned(_)));
+    assert_eq!(read.key, "id");
+    assert_eq!(&*read.value, &payload);
+    let paths: Vec<_> = plod::dump::spans(&read)
+        .into_iter()
+        .map(|s| s.path)
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "Record<'_>.key.<len>",
+            "Record<'_>.key",
+            "Record<'_>.len",
+            "Record<'_>.value"
+        ]
+    );
+
+    // the same bytes once owned
+    let owned = Record {
+        key: Cow::Owned("id".to_string()),
+        len: 3,
+        value: Cow::Owned(payload.to_vec()),
+    };
+    assert_eq!(read, owned);
+
+    data[1] = 0xff;
+    let error = Record::read_from(&mut data.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
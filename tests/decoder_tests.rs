@@ -0,0 +1,76 @@
+use plod::decoder::Decoder;
+use plod::Plod;
+use std::io::ErrorKind;
+use std::task::Poll;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8))]
+enum Message {
+    #[plod(tag = 1)]
+    Ping(u32),
+    #[plod(tag = 2, size_type(u16))]
+    Text(Vec<u8>),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Point {
+    x: u16,
+    y: u16,
+}
+
+fn ready<T>(poll: Poll<plod::Result<T>>) -> plod::Result<T> {
+    match poll {
+        Poll::Ready(result) => result,
+        Poll::Pending => panic!("value not decoded"),
+    }
+}
+
+#[test]
+fn test_decoder() {
+    let data = [1, 0, 0, 0, 7, 2, 0, 2, b'h', b'i', 1];
+    // one byte at a time
+    let mut decoder = Decoder::<Message>::new();
+    let mut messages = Vec::new();
+    for b in data.iter() {
+        if let Poll::Ready(message) = decoder.push(&[*b]) {
+            messages.push(message.unwrap());
+        }
+    }
+    assert_eq!(
+        messages,
+        vec![Message::Ping(7), Message::Text(b"hi".to_vec())]
+    );
+    assert_eq!(decoder.buffer(), &[1]);
+    assert!(!decoder.is_empty());
+
+    // all at once, the next values are decoded with empty pushes
+    let mut decoder = Decoder::<Message>::new();
+    assert_eq!(ready(decoder.push(&data[..10])).unwrap(), Message::Ping(7));
+    assert_eq!(
+        ready(decoder.push(&[])).unwrap(),
+        Message::Text(b"hi".to_vec())
+    );
+    assert!(decoder.push(&[]).is_pending());
+    assert!(decoder.is_empty());
+}
+
+#[test]
+fn test_decoder_fixed_size() {
+    let mut decoder = Decoder::<Point>::new();
+    assert!(decoder.push(&[1, 0, 2]).is_pending());
+    assert_eq!(ready(decoder.push(&[0, 3])).unwrap(), Point { x: 1, y: 2 });
+    assert_eq!(decoder.buffer(), &[3]);
+}
+
+#[test]
+fn test_decoder_errors() {
+    let mut decoder = Decoder::<Message>::new();
+    let error = ready(decoder.push(&[9, 0])).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Other);
+
+    // a hostile length is rejected before it is buffered
+    let mut decoder = Decoder::<Message>::with_max_length(16);
+    let error = ready(decoder.push(&[2, 0xff, 0xff])).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
@@ -0,0 +1,53 @@
+use plod::diff::Difference;
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug, Clone)]
+#[plod(big_endian)]
+struct Record {
+    id: u16,
+    #[plod(size_type(u8))]
+    values: Vec<u16>,
+    flags: u8,
+}
+
+#[test]
+fn test_diff() {
+    let record = Record {
+        id: 1,
+        values: vec![2, 3],
+        flags: 4,
+    };
+    assert!(plod::diff(&record, &record.clone()).unwrap().is_empty());
+
+    let mut other = record.clone();
+    other.values[1] = 5;
+    other.flags = 6;
+    let differences = plod::diff(&record, &other).unwrap();
+    assert_eq!(
+        differences,
+        vec![
+            Difference {
+                path: "Record.values[1]".to_string(),
+                left: Some((5, vec![0, 3])),
+                right: Some((5, vec![0, 5])),
+            },
+            Difference {
+                path: "Record.flags".to_string(),
+                left: Some((7, vec![4])),
+                right: Some((7, vec![6])),
+            },
+        ]
+    );
+    assert_eq!(
+        differences[1].to_string(),
+        "Record.flags: [04] at 7 != [06] at 7"
+    );
+
+    // fields that moved are still compared by path
+    let mut longer = record.clone();
+    longer.values.push(7);
+    let differences = plod::diff(&record, &longer).unwrap();
+    let paths: Vec<&str> = differences.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(paths, vec!["Record.values.<len>", "Record.values[2]"]);
+    assert_eq!(differences[1].left, None);
+}
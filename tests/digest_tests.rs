@@ -0,0 +1,36 @@
+#![cfg(feature = "digest")]
+
+use plod::digest::DigestWriter;
+use plod::Plod;
+use sha2::{Digest, Sha256};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Record {
+    id: u32,
+    #[plod(size_type(u16))]
+    name: Vec<u8>,
+    #[plod(skip)]
+    cached: u64,
+}
+
+#[test]
+fn test_digest() {
+    let record = Record {
+        id: 7,
+        name: b"plod".to_vec(),
+        cached: 1,
+    };
+    let mut buffer = Vec::new();
+    record.write_to(&mut buffer).unwrap();
+    let hash = plod::digest::<_, Sha256>(&record).unwrap();
+    assert_eq!(hash, Sha256::digest(&buffer));
+
+    // skipped fields are not part of the representation at rest
+    let other = Record { cached: 2, ..record };
+    assert_eq!(plod::digest::<_, Sha256>(&other).unwrap(), hash);
+
+    let mut writer = DigestWriter::<Sha256>::new();
+    other.write_to(&mut writer).unwrap();
+    assert_eq!(writer.finalize(), hash);
+}
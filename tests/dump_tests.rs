@@ -0,0 +1,90 @@
+use plod::dump::{field_sizes, spans, Span};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, magic(u8 = 0x7f), field_sizes)]
+struct Packet {
+    id: u16,
+    #[plod(size_type(u8))]
+    payload: Vec<u8>,
+    #[plod(size_type(u8))]
+    items: Vec<Item>,
+    pair: (u8, u8),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8), field_sizes)]
+enum Item {
+    #[plod(tag = 1)]
+    Flag(u8),
+    #[plod(tag = 2..=3, keep_tag)]
+    Level { level: u8, value: u16 },
+}
+
+#[test]
+fn test_spans() {
+    let packet = Packet {
+        id: 1,
+        payload: vec![1, 2, 3],
+        items: vec![Item::Flag(5), Item::Level { level: 3, value: 9 }],
+        pair: (1, 2),
+    };
+    let spans = spans(&packet);
+    let expected = vec![
+        Span::new("Packet.<magic>".to_string(), 0, 1),
+        Span::new("Packet.id".to_string(), 1, 2),
+        Span::new("Packet.payload.<len>".to_string(), 3, 1),
+        Span::new("Packet.payload".to_string(), 4, 3),
+        Span::new("Packet.items.<len>".to_string(), 7, 1),
+        Span::new("Packet.items[0].<tag>".to_string(), 8, 1),
+        Span::new("Packet.items[0]::Flag.0".to_string(), 9, 1),
+        Span::new("Packet.items[1].<tag>".to_string(), 10, 1),
+        Span::new("Packet.items[1]::Level.value".to_string(), 11, 2),
+        Span::new("Packet.pair.0".to_string(), 13, 1),
+        Span::new("Packet.pair.1".to_string(), 14, 1),
+    ];
+    assert_eq!(spans, expected);
+    assert_eq!(spans.last().unwrap().end(), packet.size_at_rest());
+}
+
+#[test]
+fn test_dump() {
+    let packet = Packet {
+        id: 0x1234,
+        payload: (0..20).collect(),
+        items: vec![],
+        pair: (1, 2),
+    };
+    let dump = plod::dump(&packet).unwrap();
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines[0], format!("00000000  7f{:45}  Packet.<magic>", ""));
+    assert_eq!(lines[1], format!("00000001  12 34{:42}  Packet.id", ""));
+    assert!(lines[3].starts_with("00000004  00 01 02 03"));
+    assert!(lines[3].ends_with("  Packet.payload"));
+    assert!(lines[4].starts_with("00000014  10 11 12 13"));
+    assert!(lines[4].trim_end().ends_with("10 11 12 13"));
+    assert_eq!(lines.len(), 8);
+}
+
+#[test]
+fn test_field_sizes() {
+    let packet = Packet {
+        id: 1,
+        payload: vec![1, 2, 3],
+        items: vec![Item::Flag(5), Item::Level { level: 3, value: 9 }],
+        pair: (1, 2),
+    };
+    let sizes = packet.field_sizes();
+    assert_eq!(
+        sizes,
+        vec![("<magic>", 1), ("id", 2), ("payload", 4), ("items", 6), ("pair", 2)]
+    );
+    assert_eq!(sizes.iter().map(|(_, size)| size).sum::<usize>(), packet.size_at_rest());
+
+    let level = Item::Level { level: 3, value: 9 };
+    assert_eq!(level.field_sizes(), vec![("<tag>", 1), ("Level.value", 2)]);
+    assert_eq!(
+        field_sizes(&Item::Flag(5)),
+        vec![("<tag>".to_string(), 1), ("Flag.0".to_string(), 1)]
+    );
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "embedded-io")]
+
+use std::io::ErrorKind;
+
+use plod::embedded::{read_from_embedded, write_to_embedded};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Packet {
+    address: u8,
+    #[plod(size_type(u8))]
+    payload: Vec<u8>,
+    crc: u16,
+}
+
+// a bus failing after a few bytes
+struct Bus(usize);
+
+#[derive(Debug)]
+struct BusError;
+
+impl embedded_io::Error for BusError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        embedded_io::ErrorKind::TimedOut
+    }
+}
+
+impl embedded_io::ErrorType for Bus {
+    type Error = BusError;
+}
+
+impl embedded_io::Read for Bus {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, BusError> {
+        if self.0 == 0 {
+            return Err(BusError);
+        }
+        self.0 -= 1;
+        buf[0] = 1;
+        Ok(1)
+    }
+}
+
+#[test]
+fn test_embedded() {
+    let packet = Packet {
+        address: 0x42,
+        payload: vec![1, 2, 3],
+        crc: 0xbeef,
+    };
+    let mut data = Vec::new();
+    write_to_embedded(&packet, &mut data).unwrap();
+    assert_eq!(data, &[0x42, 3, 1, 2, 3, 0xef, 0xbe]);
+    let read: Packet = read_from_embedded(&mut data.as_slice()).unwrap();
+    assert_eq!(read, packet);
+
+    let error = read_from_embedded::<Packet, _>(&mut Bus(3)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::TimedOut);
+}
@@ -0,0 +1,88 @@
+use std::io::ErrorKind;
+
+use plod::error::FieldError;
+use plod::fuzz::{with_limits, LimitExceeded, Limits};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Mode {
+    value: u16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Flags(u8, Mode);
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Header {
+    version: u8,
+    flags: Flags,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum Message {
+    #[plod(tag = 1)]
+    Hello { header: Header },
+}
+
+#[test]
+fn test_field_path() {
+    let error = Header::read_from(&mut [1_u8, 2, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(
+        error.to_string(),
+        "reading Header.flags.1.value: failed to fill whole buffer"
+    );
+    let field = error
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<FieldError>()
+        .unwrap();
+    assert!(field.reading);
+    assert_eq!(field.path, vec!["Header", "flags", "1", "value"]);
+    assert_eq!(field.error.kind(), ErrorKind::UnexpectedEof);
+
+    let error = Message::read_from(&mut [1_u8, 1].as_slice()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "reading Message::Hello.header.flags.0: failed to fill whole buffer"
+    );
+}
+
+#[test]
+fn test_write_path() {
+    let header = Header {
+        version: 1,
+        flags: Flags(2, Mode { value: 3 }),
+        data: vec![],
+    };
+    let mut buffer = [0_u8; 2];
+    let error = header.write_to(&mut buffer.as_mut_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WriteZero);
+    let field = error
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<FieldError>()
+        .unwrap();
+    assert!(!field.reading);
+    assert_eq!(field.path, vec!["Header", "flags", "1", "value"]);
+}
+
+#[test]
+fn test_payload_kept() {
+    let limits = Limits {
+        max_len: 2,
+        ..Limits::default()
+    };
+    let data = [1_u8, 2, 0, 3, 5, 1, 2, 3, 4, 5];
+    let error = with_limits(limits, || Header::read_from(&mut data.as_slice())).unwrap_err();
+    assert_eq!(
+        error.get_ref().unwrap().downcast_ref::<LimitExceeded>(),
+        Some(&LimitExceeded::Length { len: 5, max: 2 })
+    );
+}
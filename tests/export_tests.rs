@@ -0,0 +1,113 @@
+use plod::layout::Layout;
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, magic(u16 = 0xabcd))]
+struct Header {
+    version: u8,
+    #[plod(size_type(u16))]
+    records: Vec<Record>,
+    #[plod(skip)]
+    cache: u32,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8))]
+enum Record {
+    #[plod(tag = 1)]
+    Point { x: i32, y: i32 },
+    #[plod(tag = 2, size_type(u8), byte_sized)]
+    Path(Vec<(i16, i16)>),
+    #[plod(tag = 3..=4, keep_tag)]
+    Marker(u8, [u16; 2]),
+    #[plod(keep_tag)]
+    Other(u8),
+}
+
+#[test]
+fn test_describe() {
+    let layout = Header::describe();
+    let header = match layout {
+        Layout::Struct(s) => s,
+        _ => panic!("Header is a struct"),
+    };
+    assert_eq!(header.magic.unwrap().bytes, vec![0xab, 0xcd]);
+    let names: Vec<_> = header.fields.iter().map(|f| f.name).collect();
+    assert_eq!(names, vec!["version", "records"]);
+
+    let record = match Record::describe() {
+        Layout::Enum(e) => e,
+        _ => panic!("Record is an enum"),
+    };
+    assert_eq!(record.tag.ty, "u8");
+    let marker = &record.variants[2];
+    assert_eq!(marker.tag.as_ref().unwrap().values, vec![3..=4]);
+    assert_eq!(marker.stored_fields().len(), 1);
+    assert!(record.variants[3].tag.is_none());
+    assert_eq!(Header::describe().fixed_size(), None);
+}
+
+#[test]
+fn test_kaitai() {
+    let ksy = plod::export::kaitai::<Header>();
+    assert!(ksy.starts_with("meta:\n  id: header\n"));
+    assert!(ksy.contains("  - id: magic\n    contents: [0xab, 0xcd]\n"));
+    assert!(ksy.contains("    type: record\n    repeat: expr\n    repeat-expr: records_size\n"));
+    assert!(ksy.contains("3: record_marker\n"));
+    assert!(ksy.contains("4: record_marker\n"));
+    assert!(ksy.contains("_: record_other\n"));
+    assert!(ksy.contains("  record_point:\n    seq:\n      - id: x\n        type: s4be\n"));
+    assert!(ksy.contains("  record_other:\n    seq: []\n"));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, magic(u32 = 0x46464952))]
+struct Chunk {
+    id: [u8; 4],
+    kind: Kind,
+    range: (u16, u16),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum Kind {
+    #[plod(tag = 1)]
+    Data(u32),
+    #[plod(tag = 2)]
+    Offset { value: i32 },
+}
+
+#[test]
+fn test_c_header() {
+    let header = plod::export::c_header::<Chunk>().unwrap();
+    assert!(header.starts_with("#ifndef CHUNK_H\n"));
+    assert!(header.contains("struct kind {\n    uint8_t tag;\n    union {\n"));
+    assert!(header.contains("#define KIND_DATA_TAG 1\n"));
+    assert!(header.contains(
+        "struct chunk {\n    uint32_t magic; /* little endian */\n    /* magic must be 0x46464952 */\n    uint8_t id[4];\n    struct kind kind;\n"
+    ));
+    assert!(header.contains("#define CHUNK_SIZE 17\n"));
+    assert!(header.contains("int chunk_read(const uint8_t *buf, size_t len, struct chunk *out);\n"));
+    // kind must be declared before chunk
+    assert!(header.find("struct kind {") < header.find("struct chunk {"));
+
+    let error = plod::export::c_header::<Header>().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn test_patterns() {
+    let hexpat = plod::export::imhex_pattern::<Header>();
+    assert!(hexpat.contains("struct record {\n    u8 tag;\n    match (tag) {\n        (1): { // Point\n            be s32 x;\n"));
+    assert!(hexpat.contains("        (3 ... 4): { // Marker\n"));
+    assert!(hexpat.contains("    be u16 records_size;\n    record records[records_size];\n"));
+    assert!(hexpat
+        .contains("[while($ < addressof(field_0_size) + sizeof(field_0_size) + field_0_size)];\n"));
+    assert!(hexpat.ends_with("header root @ 0x00;\n"));
+
+    let bt = plod::export::template_010::<Header>();
+    assert!(bt.contains("    if (tag == 1) { // Point\n        BigEndian();\n        int x;\n"));
+    assert!(bt.contains("} else if (tag >= 3 && tag <= 4) { // Marker\n"));
+    assert!(bt.contains("    local int64 field_0_end = FTell() + field_0_size;\n"));
+    assert!(bt.ends_with("struct header root;\n"));
+}
@@ -0,0 +1,115 @@
+use plod::error::FieldError;
+use plod::ext::FileError;
+use plod::{Plod, PlodExt};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Config {
+    version: u16,
+    #[plod(size_type(u8))]
+    name: Vec<u8>,
+}
+
+#[test]
+fn test_bytes() {
+    let config = Config {
+        version: 2,
+        name: b"abc".to_vec(),
+    };
+    assert_eq!(config.size(), 6);
+    let bytes = config.to_bytes().unwrap();
+    assert_eq!(bytes, vec![2, 0, 3, b'a', b'b', b'c']);
+    assert_eq!(Config::from_bytes(&bytes).unwrap(), config);
+    // all bytes must be used
+    let mut longer = bytes.clone();
+    longer.push(0);
+    let error = Config::from_bytes(&longer).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Pointer {
+    offset: u8,
+    #[plod(seek_to = offset)]
+    data: u16,
+    flags: u8,
+}
+
+#[test]
+fn test_bytes_seek_to() {
+    // the value ends after the data it points to, not after its last field
+    let bytes = [3, 1, 0, 2, 0];
+    let value = Pointer::from_bytes(&bytes).unwrap();
+    assert_eq!(value, Pointer { offset: 3, data: 2, flags: 1 });
+    let error = Pointer::from_bytes(&[3, 1, 0, 2, 0, 0]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let path = std::env::temp_dir().join(format!("plod_ext_seek_test_{}", std::process::id()));
+    std::fs::write(&path, bytes).unwrap();
+    assert_eq!(Pointer::read_from_file(&path).unwrap(), value);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_file() {
+    let config = Config {
+        version: 3,
+        name: b"file".to_vec(),
+    };
+    let path = std::env::temp_dir().join(format!("plod_ext_test_{}", std::process::id()));
+    config.write_to_file(&path).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), config.to_bytes().unwrap());
+    assert_eq!(Config::read_from_file(&path).unwrap(), config);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_file_functions() {
+    let config = Config {
+        version: 4,
+        name: b"functions".to_vec(),
+    };
+    let path = std::env::temp_dir().join(format!("plod_ext_functions_{}", std::process::id()));
+    plod::write_file(&path, &config).unwrap();
+    assert_eq!(plod::read_file::<Config>(&path).unwrap(), config);
+    // bytes left after the value
+    std::fs::write(&path, [4, 0, 0, 1]).unwrap();
+    let error = plod::read_file::<Config>(&path).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().starts_with(&path.display().to_string()));
+    std::fs::remove_file(&path).unwrap();
+    // errors tell the path
+    let error = plod::read_file::<Config>(&path).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    assert!(error.to_string().contains("plod_ext_functions_"));
+}
+
+#[test]
+fn test_file_error_payload() {
+    let path = std::env::temp_dir().join(format!("plod_ext_payload_{}", std::process::id()));
+    // a name longer than the file
+    std::fs::write(&path, [4, 0, 9, 1]).unwrap();
+    let error = Config::read_from_file(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    let file_error = error.get_ref().unwrap().downcast_ref::<FileError>().unwrap();
+    assert_eq!(file_error.path, path);
+    // the payload of the original error is still there
+    let field_error = file_error.error.get_ref().unwrap().downcast_ref::<FieldError>().unwrap();
+    assert_eq!(field_error.path, ["Config", "name"]);
+}
+
+#[test]
+fn test_counted() {
+    let config = Config {
+        version: 2,
+        name: b"ab".to_vec(),
+    };
+    let mut stream = Vec::new();
+    assert_eq!(config.write_counted(&mut stream).unwrap(), 5);
+    assert_eq!(config.write_counted(&mut stream).unwrap(), 5);
+    let mut reader = stream.as_slice();
+    let (first, size) = Config::read_counted(&mut reader).unwrap();
+    assert_eq!((first, size), (config, 5));
+    assert_eq!(reader.len(), 5);
+}
@@ -0,0 +1,71 @@
+use plod::{read_framed, write_framed, Framing, Plod};
+use std::io::ErrorKind;
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(big_endian)]
+struct Sample {
+    time: u32,
+    #[plod(size_type(u16))]
+    values: Vec<i16>,
+}
+
+fn samples() -> Vec<Sample> {
+    vec![
+        Sample { time: 1, values: vec![1, -1] },
+        Sample { time: 2, values: vec![] },
+        Sample { time: 3, values: vec![300; 10] },
+    ]
+}
+
+#[test]
+fn test_framing() {
+    for framing in [Framing::new(), Framing::new().magic(b"\xfaREC"), Framing::new().crc(), Framing::new().magic(b"R").crc()] {
+        let mut log = Vec::new();
+        for sample in samples() {
+            write_framed(&mut log, &sample, &framing).unwrap();
+        }
+        let overhead = framing.magic.len() + 4 + if framing.crc { 4 } else { 0 };
+        let payload: usize = samples().iter().map(|s| s.size_at_rest()).sum();
+        assert_eq!(log.len(), payload + 3 * overhead);
+
+        let mut reader = log.as_slice();
+        let mut read = Vec::new();
+        while let Some(sample) = read_framed::<Sample, _>(&mut reader, &framing).unwrap() {
+            read.push(sample);
+        }
+        assert_eq!(read, samples());
+
+        // a frame cut while appending
+        for cut in 1..overhead + 6 {
+            let err = read_framed::<Sample, _>(&mut &log[..cut], &framing).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        }
+    }
+}
+
+#[test]
+fn test_framing_errors() {
+    let framing = Framing::new().magic(b"LOG").crc();
+    let mut log = Vec::new();
+    write_framed(&mut log, &samples()[0], &framing).unwrap();
+
+    let mut corrupted = log.clone();
+    corrupted[0] = b'X';
+    let err = read_framed::<Sample, _>(&mut corrupted.as_slice(), &framing).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let mut corrupted = log.clone();
+    corrupted[9] ^= 0x10;
+    let err = read_framed::<Sample, _>(&mut corrupted.as_slice(), &framing).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("CRC mismatch"), "{}", err);
+
+    // a size larger than the value leaves trailing bytes
+    let framing = Framing::new();
+    let mut log = Vec::new();
+    write_framed(&mut log, &samples()[1], &framing).unwrap();
+    log[0] += 1;
+    log.push(0);
+    let err = read_framed::<Sample, _>(&mut log.as_slice(), &framing).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
@@ -0,0 +1,147 @@
+use plod::fuzz::{check_read, with_limits, LimitExceeded, Limits};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Blob {
+    id: u16,
+    #[plod(size_type(u32))]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Blobs {
+    #[plod(size_type(u8))]
+    blobs: Vec<Blob>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct Outer {
+    inner: Middle,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct Middle {
+    inner: Inner,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct Inner {
+    value: u8,
+}
+
+#[test]
+fn test_check_read() {
+    let data = [0, 1, 0, 0, 0, 2, 5, 6, 7];
+    let blob = check_read::<Blob>(&data).unwrap();
+    assert_eq!(
+        blob,
+        Blob {
+            id: 1,
+            data: vec![5, 6]
+        }
+    );
+    // truncated input is not a value
+    assert!(check_read::<Blob>(&data[..4]).is_none());
+}
+
+#[test]
+fn test_hostile_length() {
+    // a 4GB Vec must not be allocated
+    let data = [0, 1, 0xff, 0xff, 0xff, 0xff, 5];
+    assert!(check_read::<Blob>(&data).is_none());
+    let limits = Limits {
+        max_len: 4,
+        ..Limits::default()
+    };
+    let error = with_limits(limits, || Blob::read_from(&mut &data[..])).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        error.get_ref().unwrap().downcast_ref::<LimitExceeded>(),
+        Some(&LimitExceeded::Length {
+            len: 0xffffffff,
+            max: 4
+        })
+    );
+    // no limit outside of with_limits
+    let data = [0, 1, 0, 0, 0, 5, 1, 2, 3, 4, 5];
+    assert!(with_limits(limits, || Blob::read_from(&mut &data[..])).is_err());
+    assert!(Blob::read_from(&mut &data[..]).is_ok());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Samples {
+    #[plod(size_type(u32))]
+    samples: Vec<u64>,
+}
+
+#[test]
+fn test_preallocation() {
+    assert_eq!(plod::fuzz::capacity::<u64>(10), 10);
+    assert_eq!(plod::fuzz::capacity::<u64>(usize::MAX), plod::fuzz::PREALLOCATION / 8);
+    assert_eq!(plod::fuzz::capacity::<()>(usize::MAX), plod::fuzz::PREALLOCATION);
+    let limits = Limits {
+        max_len: 4,
+        ..Limits::default()
+    };
+    assert_eq!(with_limits(limits, || plod::fuzz::capacity::<u64>(10)), 4);
+    // large items are capped by their size in memory, even under the default limits
+    let capacity = with_limits(Limits::default(), || plod::fuzz::capacity::<[u8; 1024]>(usize::MAX));
+    assert_eq!(capacity, plod::fuzz::PREALLOCATION / 1024);
+
+    // the vector is allocated once for the length read
+    let mut data = vec![0, 0, 0, 100];
+    data.extend((0..100_u64).flat_map(u64::to_be_bytes));
+    let samples = Samples::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(samples.samples.len(), 100);
+    assert_eq!(samples.samples.capacity(), 100);
+    // a hostile length doesn't allocate more than the input
+    let error = Samples::read_from(&mut [0xff_u8, 0xff, 0xff, 0xff, 1].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_total_bytes() {
+    // 3 blobs of 4 bytes, each within max_len
+    let mut data = vec![3];
+    for id in 0..3 {
+        data.extend([0, id, 0, 0, 0, 4, 1, 2, 3, 4]);
+    }
+    // the Vec of blobs counts the memory of its 3 items
+    let total = 3 * std::mem::size_of::<Blob>() + 12;
+    let limits = plod::Limits {
+        max_len: 4,
+        max_total_bytes: total - 1,
+        ..Limits::default()
+    };
+    let error = with_limits(limits, || Blobs::read_from(&mut &data[..])).unwrap_err();
+    let limit = error.get_ref().unwrap().downcast_ref::<LimitExceeded>();
+    assert_eq!(limit, Some(&LimitExceeded::TotalBytes { max: total - 1 }));
+    let limits = Limits {
+        max_total_bytes: total,
+        ..limits
+    };
+    let blobs = with_limits(limits, || Blobs::read_from(&mut &data[..])).unwrap();
+    assert_eq!(blobs.blobs.len(), 3);
+    // the total restarts with each with_limits
+    assert!(with_limits(limits, || Blobs::read_from(&mut &data[..])).is_ok());
+}
+
+#[test]
+fn test_depth() {
+    let data = [3];
+    let limits = Limits {
+        max_depth: 2,
+        ..Limits::default()
+    };
+    let error = with_limits(limits, || Outer::read_from(&mut &data[..])).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        error.get_ref().unwrap().downcast_ref::<LimitExceeded>(),
+        Some(&LimitExceeded::Depth { max: 2 })
+    );
+    assert!(with_limits(limits, || Middle::read_from(&mut &data[..])).is_ok());
+    assert_eq!(check_read::<Outer>(&data).unwrap().inner.inner.value, 3);
+}
@@ -0,0 +1,44 @@
+use plod::testing::{golden, BLESS_VAR};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Record {
+    id: u16,
+    #[plod(size_type(u8))]
+    values: Vec<u16>,
+    flags: u8,
+}
+
+#[test]
+fn test_golden() {
+    let dir = std::env::temp_dir().join(format!("plod_golden_{}", std::process::id()));
+    let path = dir.join("record.bin");
+    let record = Record {
+        id: 1,
+        values: vec![2, 3],
+        flags: 4,
+    };
+    let error = golden(&record, &path).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+
+    std::env::set_var(BLESS_VAR, "1");
+    golden(&record, &path).unwrap();
+    std::env::remove_var(BLESS_VAR);
+    assert_eq!(std::fs::read(&path).unwrap(), vec![0, 1, 2, 0, 2, 0, 3, 4]);
+    golden(&record, &path).unwrap();
+
+    let changed = Record { flags: 5, ..record };
+    let error = golden(&changed, &path).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let message = error.to_string();
+    assert!(message.contains("at offset 7, in Record.flags"), "{}", message);
+    assert!(message.contains("Record.flags: [04] at 7 != [05] at 7"), "{}", message);
+
+    // a golden file that is not a Record anymore still gives the first difference
+    std::fs::write(&path, [0, 1, 9]).unwrap();
+    let message = golden(&changed, &path).unwrap_err().to_string();
+    assert!(message.contains("at offset 2, in Record.values.<len>"), "{}", message);
+    assert!(!message.contains("!="), "{}", message);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,170 @@
+use std::io::{Read, Write};
+
+use plod::{helpers, Plod, Result};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Item {
+    id: u8,
+    #[plod(size_type(u8))]
+    name: Vec<u8>,
+}
+
+/// a magic, items sized in bytes by a u16 and a tail aligned on 4 bytes
+#[derive(PartialEq, Debug)]
+struct Table {
+    items: Vec<Item>,
+    kind: u8,
+}
+
+impl Plod for Table {
+    type Context = ();
+
+    fn size_at_rest(&self) -> usize {
+        let size = 2 + 2 + helpers::vec_size(&self.items) + 1;
+        size + helpers::padding(size, 4)
+    }
+
+    fn impl_read_from<R: Read>(from: &mut R, ctx: &(), mut pos: usize) -> Result<Self> {
+        helpers::check_magic(from, b"TB")?;
+        let size = u16::from_le_bytes(helpers::read_bytes(from)?) as usize;
+        pos += 4;
+        let items = helpers::read_vec_by_bytes(from, ctx, pos, size)?;
+        pos += size;
+        let [kind] = helpers::read_bytes(from)?;
+        if kind > 2 {
+            return Err(helpers::unknown_tag::<Self>(kind as i128, pos));
+        }
+        pos += 1;
+        helpers::read_padding(from, pos, 4)?;
+        Ok(Table { items, kind })
+    }
+
+    fn impl_write_to<W: Write>(&self, to: &mut W, ctx: &(), mut pos: usize) -> Result<()> {
+        to.write_all(b"TB")?;
+        to.write_all(&(helpers::vec_size(&self.items) as u16).to_le_bytes())?;
+        pos += 4;
+        helpers::write_vec(to, &self.items, ctx, pos)?;
+        pos += helpers::vec_size(&self.items);
+        to.write_all(&[self.kind])?;
+        helpers::write_padding(to, pos + 1, 4)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_manual_impl() {
+    let table = Table {
+        items: vec![
+            Item {
+                id: 1,
+                name: b"a".to_vec(),
+            },
+            Item {
+                id: 2,
+                name: vec![],
+            },
+        ],
+        kind: 1,
+    };
+    let mut memory = Vec::new();
+    table.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![b'T', b'B', 5, 0, 1, 1, b'a', 2, 0, 1, 0, 0]);
+    assert_eq!(table.size_at_rest(), memory.len());
+    assert_eq!(Table::read_from(&mut memory.as_slice()).unwrap(), table);
+
+    // bad magic and unknown tag
+    assert!(Table::read_from(&mut [b'T', b'C', 0, 0, 1, 0, 0, 0].as_slice()).is_err());
+    let error = Table::read_from(&mut [b'T', b'B', 0, 0, 5, 0, 0, 0].as_slice()).unwrap_err();
+    let unknown = error
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<plod::lenient::UnknownTag>()
+        .unwrap();
+    assert_eq!((unknown.tag, unknown.position), (5, 4));
+}
+
+#[test]
+fn test_read_vec_by_count() {
+    let data = [1, 0, 2, 0, 9];
+    let mut reader = data.as_slice();
+    let items: Vec<Item> = helpers::read_vec_by_count(&mut reader, &(), 0, 2).unwrap();
+    assert_eq!(items[1].id, 2);
+    assert_eq!(reader, &[9]);
+}
+
+/// fails every other call with `Interrupted`, like a reader receiving signals
+struct Interrupting<T> {
+    inner: T,
+    interrupt: bool,
+}
+
+impl<T> Interrupting<T> {
+    fn new(inner: T) -> Self {
+        Interrupting {
+            inner,
+            interrupt: true,
+        }
+    }
+
+    fn interrupted(&mut self) -> bool {
+        self.interrupt = !self.interrupt;
+        !self.interrupt
+    }
+}
+
+impl<T: Read> Read for Interrupting<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.interrupted() {
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        // a byte at a time, so that every read can be interrupted
+        let len = buf.len().min(1);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl<T: Write> Write for Interrupting<T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.interrupted() {
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        self.inner.write(&buf[..buf.len().min(1)])
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Optional {
+    id: u16,
+    #[plod(if_remaining, size_type(u8))]
+    name: Option<Vec<u8>>,
+}
+
+#[test]
+fn test_interrupted() {
+    let value = Optional {
+        id: 7,
+        name: Some(b"abc".to_vec()),
+    };
+    let mut to = Interrupting::new(Vec::new());
+    value.write_to(&mut to).unwrap();
+    assert_eq!(to.inner, vec![7, 0, 3, b'a', b'b', b'c']);
+
+    let mut from = Interrupting::new(to.inner.as_slice());
+    assert_eq!(Optional::read_from(&mut from).unwrap(), value);
+    let mut from = Interrupting::new(&to.inner[..2]);
+    assert_eq!(Optional::read_from(&mut from).unwrap().name, None);
+
+    let records: Vec<Item> =
+        plod::read_all(Interrupting::new([1_u8, 0, 2, 1, b'x'].as_slice())).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        helpers::read_some(&mut Interrupting::new([5_u8].as_slice()), &mut [0; 4]).unwrap(),
+        1
+    );
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "hex")]
+
+use plod::hex::InvalidHex;
+use plod::Plod;
+use std::io::ErrorKind;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Record {
+    id: u32,
+    #[plod(size_type(u8))]
+    name: Vec<u8>,
+}
+
+#[test]
+fn test_hex() {
+    let record = Record {
+        id: 0xabcd,
+        name: b"ok".to_vec(),
+    };
+    let hex = plod::to_hex(&record).unwrap();
+    assert_eq!(hex, "cdab0000026f6b");
+    assert_eq!(plod::from_hex::<Record>(&hex).unwrap(), record);
+    assert_eq!(
+        plod::from_hex::<Record>("CDAB0000\n02 6F6B").unwrap(),
+        record
+    );
+
+    let error = plod::from_hex::<Record>("cdab0000 02 6g6b").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    let invalid = error
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<InvalidHex>()
+        .unwrap();
+    assert_eq!(
+        *invalid,
+        InvalidHex {
+            position: 13,
+            found: Some('g')
+        }
+    );
+
+    let error = plod::from_hex::<Record>("cdab000002 6f6").unwrap_err();
+    assert_eq!(error.to_string(), "Missing hex digit at position 14");
+
+    let error = plod::from_hex::<Record>("cdab0000026f6bff").unwrap_err();
+    assert_eq!(error.to_string(), "1 bytes left after the value, at offset 7");
+    assert_eq!(
+        plod::from_hex::<Record>("cdab0000026f").unwrap_err().kind(),
+        ErrorKind::UnexpectedEof
+    );
+}
@@ -0,0 +1,70 @@
+use plod::{InlineBytes, InlineStr, Plod};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Route {
+    #[plod(size_type(u8))]
+    host: plod::InlineStr<16>,
+    port: u16,
+    #[plod(size_type(u16))]
+    token: plod::inline::InlineBytes<4>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct Keyed {
+    #[plod(len_of = key)]
+    len: u8,
+    flags: u8,
+    key: plod::InlineBytes<3>,
+}
+
+#[test]
+fn test_inline() {
+    let data = [3, b'w', b'e', b'b', 0, 80, 0, 2, 0xab, 0xcd];
+    let route = Route::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(&*route.host, "web");
+    assert_eq!(route.port, 80);
+    assert_eq!(&*route.token, [0xab, 0xcd]);
+    assert_eq!(route.size_at_rest(), data.len());
+    let mut buffer = Vec::new();
+    route.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, data);
+    let built = Route {
+        host: "web".try_into().unwrap(),
+        port: 80,
+        token: [0xab, 0xcd].as_slice().try_into().unwrap(),
+    };
+    assert_eq!(built, route);
+}
+
+#[test]
+fn test_too_long() {
+    // 5 bytes of token don't fit
+    let data = [0, 0, 80, 0, 5, 1, 2, 3, 4, 5];
+    let err = Route::read_from(&mut data.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("5 bytes at offset 5 don't fit in InlineBytes<4>"), "{}", err);
+    let err = InlineStr::<2>::try_from("abc").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    // invalid text is refused
+    let err = Route::read_from(&mut [1_u8, 0xff, 0, 80, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_len_of() {
+    let sized = Keyed::read_from(&mut [2_u8, 9, 1, 2].as_slice()).unwrap();
+    assert_eq!(&*sized.key, [1, 2]);
+    let mut buffer = Vec::new();
+    sized.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [2, 9, 1, 2]);
+}
+
+#[test]
+fn test_truncating() {
+    assert_eq!(&*InlineBytes::<2>::truncating(&[1, 2, 3]), [1, 2]);
+    // characters are not split
+    assert_eq!(&*InlineStr::<3>::truncating("aé€"), "aé");
+    assert_eq!(InlineStr::<8>::truncating("abc").to_string(), "abc");
+    assert_eq!(format!("{:?}", InlineStr::<8>::new()), "\"\"");
+}
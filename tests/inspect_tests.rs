@@ -0,0 +1,73 @@
+#![cfg(feature = "inspect")]
+
+use plod::dump::spans;
+use plod::inspect::{extract, tree};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Header {
+    id: u16,
+    #[plod(size_type(u8))]
+    records: Vec<Record>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Record {
+    kind: u8,
+    #[plod(size_type(u8))]
+    name: Vec<u8>,
+}
+
+fn header() -> (Header, Vec<u8>) {
+    let header = Header {
+        id: 0x1234,
+        records: vec![
+            Record {
+                kind: 1,
+                name: b"ab".to_vec(),
+            },
+            Record {
+                kind: 2,
+                name: (0..20).collect(),
+            },
+        ],
+    };
+    let mut bytes = Vec::new();
+    header.write_to(&mut bytes).unwrap();
+    (header, bytes)
+}
+
+#[test]
+fn test_tree() {
+    let (header, bytes) = header();
+    let tree = tree(&bytes, &spans(&header));
+    let lines: Vec<&str> = tree.lines().collect();
+    assert_eq!(lines[0], "00000000  Header");
+    assert_eq!(lines[1], "00000000    id: 12 34");
+    assert_eq!(lines[2], "00000002    records");
+    assert_eq!(lines[3], "00000002      <len>: 02");
+    assert_eq!(lines[4], "00000003      [0]");
+    assert_eq!(lines[5], "00000003        kind: 01");
+    assert!(lines.last().unwrap().ends_with("... (20 bytes)"));
+}
+
+#[test]
+fn test_extract() {
+    let (header, bytes) = header();
+    let spans = spans(&header);
+    assert_eq!(
+        extract(&bytes, &spans, "Header.id"),
+        Some(&[0x12, 0x34][..])
+    );
+    assert_eq!(
+        extract(&bytes, &spans, "Header.records[0]"),
+        Some(&[1, 2, b'a', b'b'][..])
+    );
+    assert_eq!(
+        extract(&bytes, &spans, "Header.records[0].name"),
+        Some(&b"ab"[..])
+    );
+    assert_eq!(extract(&bytes, &spans, "Header.record"), None);
+}
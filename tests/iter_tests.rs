@@ -0,0 +1,126 @@
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Entry {
+    time: u32,
+    #[plod(size_type(u8))]
+    message: Vec<u8>,
+}
+
+#[test]
+fn test_iter() {
+    let data = [0, 0, 0, 1, 2, b'o', b'k', 0, 0, 0, 2, 0];
+    let entries: Vec<Entry> = plod::iter(data.as_slice())
+        .collect::<plod::Result<_>>()
+        .unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            Entry {
+                time: 1,
+                message: b"ok".to_vec()
+            },
+            Entry {
+                time: 2,
+                message: vec![]
+            },
+        ]
+    );
+    assert_eq!(plod::iter::<Entry, _>([].as_slice()).count(), 0);
+}
+
+#[test]
+fn test_iter_truncated() {
+    let data = [0, 0, 0, 1, 0, 0, 0];
+    let mut records = plod::iter::<Entry, _>(data.as_slice());
+    assert_eq!(
+        records.next().unwrap().unwrap(),
+        Entry {
+            time: 1,
+            message: vec![]
+        }
+    );
+    assert_eq!(records.position(), 5);
+    let error = records.next().unwrap().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn test_read_n() {
+    let data = [0, 0, 0, 1, 2, b'o', b'k', 0, 0, 0, 2, 0, 0xff];
+    let mut reader = data.as_slice();
+    let entries: Vec<Entry> = plod::read_n(&mut reader, 2).unwrap();
+    assert_eq!(entries[1].time, 2);
+    assert_eq!(reader, &[0xff]);
+    let mut memory = Vec::new();
+    plod::write_all(&mut memory, &entries).unwrap();
+    assert_eq!(memory, &data[..12]);
+    // too few records
+    let error = plod::read_n::<Entry, _>(&mut data.as_slice(), 3).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_read_all() {
+    let data = [0, 0, 0, 1, 2, b'o', b'k', 0, 0, 0, 2, 0];
+    let entries: Vec<Entry> = plod::read_all(data.as_slice()).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].message, b"ok");
+    assert!(plod::read_all::<Entry, _>([].as_slice()).unwrap().is_empty());
+    // a truncated last record
+    let error = plod::read_all::<Entry, _>(&data[..10]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_iter_region() {
+    // two entries in a region of 12 bytes, followed by something else
+    let data = [0, 0, 0, 1, 2, b'o', b'k', 0, 0, 0, 2, 0, 0xff];
+    let mut reader = data.as_slice();
+    let mut entries = plod::iter_region::<Entry, _>(&mut reader, 12);
+    assert_eq!(entries.next().unwrap().unwrap().message, b"ok");
+    assert_eq!((entries.position(), entries.remaining()), (7, 5));
+    assert_eq!(entries.next().unwrap().unwrap().time, 2);
+    assert!(entries.next().is_none());
+    assert_eq!(reader, &[0xff]);
+    // unread records are skipped
+    let mut reader = data.as_slice();
+    let mut entries = plod::iter_region::<Entry, _>(&mut reader, 12);
+    entries.next().unwrap().unwrap();
+    assert_eq!(entries.finish().unwrap(), 5);
+    assert_eq!(reader, &[0xff]);
+    // a record cannot overflow the region
+    let mut reader = data.as_slice();
+    let mut entries = plod::iter_region::<Entry, _>(&mut reader, 10);
+    entries.next().unwrap().unwrap();
+    let error = entries.next().unwrap().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert!(entries.next().is_none());
+}
+
+/// unknown trailing bytes are read but not part of the size at rest
+#[derive(Plod, PartialEq, Debug)]
+#[plod(length_prefixed(u8))]
+struct Extensible {
+    id: u8,
+}
+
+#[test]
+fn test_positions_read() {
+    let data = [2, 1, 9, 1, 2];
+    let mut records = plod::iter::<Extensible, _>(data.as_slice());
+    assert_eq!(records.next().unwrap().unwrap(), Extensible { id: 1 });
+    assert_eq!(records.position(), 3);
+    assert_eq!(records.next().unwrap().unwrap(), Extensible { id: 2 });
+    assert_eq!(records.position(), 5);
+    assert!(records.next().is_none());
+
+    let mut reader = data.as_slice();
+    let mut region = plod::iter::iter_region::<Extensible, _>(&mut reader, 5);
+    assert_eq!(region.next().unwrap().unwrap(), Extensible { id: 1 });
+    assert_eq!((region.position(), region.remaining()), (3, 2));
+    assert_eq!(region.next().unwrap().unwrap(), Extensible { id: 2 });
+    assert!(region.next().is_none());
+}
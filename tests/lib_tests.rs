@@ -222,6 +222,624 @@ fn test_magic() {
     assert_eq!(memory, vec![0xab, 0xcd, 0x12, 0x34]);
 }
 
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, keep_magic, magic(u32 = 0xa1b2c3d4 | 0xd4c3b2a1))]
+struct TestKeepMagic {
+    magic: u32,
+    a: u16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestKeepMagicEnum {
+    #[plod(tag = 1, keep_magic, magic(u8 = 0x7f | 0x80))]
+    A(u8, u8),
+}
+
+#[test]
+fn test_keep_magic() {
+    let value = TestKeepMagic { magic: 0xd4c3b2a1, a: 2 };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0xa1, 0xb2, 0xc3, 0xd4, 2, 0]);
+    assert_eq!(value.size_at_rest(), 6);
+    assert_eq!(TestKeepMagic::describe().fixed_size(), Some(6));
+    // other values are rejected on read and on write
+    assert!(TestKeepMagic::read_from(&mut [0, 0, 0, 0, 2, 0].as_slice()).is_err());
+    let bad = TestKeepMagic { magic: 1, a: 2 };
+    assert!(bad.write_to(&mut Vec::new()).is_err());
+
+    it_reads_what_it_writes(&TestKeepMagicEnum::A(0x80, 3));
+    let mut memory: Vec<u8> = Vec::new();
+    TestKeepMagicEnum::A(0x7f, 3).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 0x7f, 3]);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, magic(u16 = 0x5354), trailer_magic(u32 = 0xdeadbeef))]
+struct TestTrailer {
+    a: u16,
+    #[plod(size_type(u8))]
+    b: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), little_endian)]
+enum TestTrailerEnum {
+    #[plod(tag = 1, trailer_magic(u8 = 0xff | 0xfe))]
+    A(u16),
+    #[plod(tag = 2)]
+    B(u16, u8),
+}
+
+#[test]
+fn test_trailer_magic() {
+    let value = TestTrailer { a: 1, b: vec![2, 3] };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0x53, 0x54, 0, 1, 2, 2, 3, 0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(value.size_at_rest(), 11);
+    memory[10] = 0;
+    let error = TestTrailer::read_from(&mut memory.as_slice()).unwrap_err();
+    assert!(error.to_string().contains("Trailer magic value 0xdeadbeef expected, found 3735928320"));
+    // a missing trailer is an early end
+    assert!(TestTrailer::read_from(&mut &memory[..8]).is_err());
+
+    it_reads_what_it_writes(&TestTrailerEnum::A(5));
+    let mut memory: Vec<u8> = Vec::new();
+    TestTrailerEnum::A(5).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 5, 0, 0xff]);
+    assert!(TestTrailerEnum::read_from(&mut [1, 5, 0, 0xfe].as_slice()).is_ok());
+    assert!(TestTrailerEnum::read_from(&mut [1, 5, 0, 0].as_slice()).is_err());
+    // both variants have the same size at rest
+    assert_eq!(TestTrailerEnum::FIXED_SIZE, Some(4));
+    assert_eq!(TestTrailerEnum::describe().fixed_size(), Some(4));
+    let spans = plod::dump::spans(&TestTrailerEnum::A(5));
+    assert_eq!(spans.last().map(|s| (s.start, s.size)), Some((3, 1)));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestReserved {
+    a: u8,
+    #[plod(reserved(3))]
+    b: u32,
+    #[plod(reserved(2), skip)]
+    c: u8,
+}
+
+#[test]
+fn test_reserved() {
+    let value = TestReserved { a: 1, b: 2, c: 0 };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 0, 0, 0, 2, 0, 0, 0, 0, 0]);
+    assert_eq!(value.size_at_rest(), 10);
+    assert_eq!(TestReserved::describe().fixed_size(), Some(10));
+    // reserved bytes are ignored on read
+    let (read, rest) = TestReserved::read_from_slice(&[1, 9, 9, 9, 2, 0, 0, 0, 9, 9, 5]).unwrap();
+    assert_eq!(read, value);
+    assert_eq!(rest, &[5]);
+    // the consumed length includes the reserved bytes
+    let data = [1, 9, 9, 9, 2, 0, 0, 0, 9, 9, 5];
+    assert_eq!(TestReserved::read_prefix(&data).unwrap(), (value, 10));
+    assert!(TestReserved::read_prefix(&data[10..]).is_err());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestRest {
+    kind: u16,
+    #[plod(rest)]
+    payload: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, length_prefixed(u8))]
+struct TestRestRegion {
+    kind: u16,
+    #[plod(rest)]
+    payload: Vec<u8>,
+}
+
+#[test]
+fn test_rest() {
+    let value = TestRest { kind: 3, payload: vec![1, 2, 3] };
+    it_reads_what_it_writes(&value);
+    assert_eq!(value.size_at_rest(), 5);
+    let (read, rest) = TestRest::read_from_slice(&[3, 0, 9, 8]).unwrap();
+    assert_eq!(read, TestRest { kind: 3, payload: vec![9, 8] });
+    assert!(rest.is_empty());
+
+    // a length prefixed region bounds the rest
+    let value = TestRestRegion { kind: 3, payload: vec![1, 2] };
+    it_reads_what_it_writes(&value);
+    let (read, rest) = TestRestRegion::read_from_slice(&[4, 3, 0, 1, 2, 7]).unwrap();
+    assert_eq!(read, value);
+    assert_eq!(rest, &[7]);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), length_prefixed(u8))]
+enum TestRecord {
+    #[plod(tag = 1)]
+    A(u16),
+    #[plod(tag = 2)]
+    B(u8),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct TestRecords {
+    #[plod(size_type(u8), skip_unknown)]
+    records: Vec<TestRecord>,
+    #[plod(size_type(u8), byte_sized, skip_unknown)]
+    sized: Vec<TestRecord>,
+}
+
+#[test]
+fn test_skip_unknown() {
+    let data = [3, 3, 1, 5, 0, 3, 9, 1, 2, 2, 2, 7, 7, 3, 7, 1, 2, 2, 2, 3, 0xff];
+    let (records, skipped) = plod::lenient::collect(|| TestRecords::read_from_slice(&data));
+    let (records, rest) = records.unwrap();
+    assert_eq!(
+        records,
+        TestRecords {
+            records: vec![TestRecord::A(5), TestRecord::B(7)],
+            sized: vec![TestRecord::B(3)],
+        }
+    );
+    assert_eq!(rest, &[0xff]);
+    assert_eq!(skipped.len(), 2);
+    assert_eq!(skipped[0].tag, 9);
+    assert_eq!(skipped[0].position, 6);
+    assert_eq!(skipped[0].skipped, Some(4));
+    assert_eq!(skipped[1].tag, 7);
+    // unknown tags are still errors outside of such a Vec
+    let error = TestRecord::read_from(&mut [2, 9, 0].as_slice()).unwrap_err();
+    let unknown = error.get_ref().unwrap().downcast_ref::<plod::lenient::UnknownTag>().unwrap();
+    assert_eq!(unknown.skipped, Some(3));
+    assert_eq!(error.to_string(), "lib_tests::TestRecord expected 1, 2, found 9 at offset 0x1");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestCodes {
+    #[plod(tag = 1..=5, keep_tag)]
+    Low(u8),
+    #[plod(tag = 6..=8|10, keep_tag)]
+    High(u8),
+}
+
+#[test]
+fn test_unknown_tag_expected() {
+    let error = TestCodes::read_from(&mut [0x11].as_slice()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "lib_tests::TestCodes expected 1..=5, 6..=8|10, found 17 at offset 0x0"
+    );
+    let unknown = error.get_ref().unwrap().downcast_ref::<plod::lenient::UnknownTag>().unwrap();
+    assert_eq!(unknown.expected, "1..=5, 6..=8|10");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(after_read = normalize, before_write = validate)]
+struct TestHooks {
+    low: u8,
+    high: u8,
+}
+
+impl TestHooks {
+    fn normalize(&mut self, _ctx: &()) -> plod::Result<()> {
+        if self.low > self.high {
+            std::mem::swap(&mut self.low, &mut self.high);
+        }
+        Ok(())
+    }
+
+    fn validate(&self, _ctx: &()) -> plod::Result<()> {
+        if self.low > self.high {
+            return Err(std::io::Error::other("low is above high"));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_hooks() {
+    let value = TestHooks::read_from(&mut [5, 2].as_slice()).unwrap();
+    assert_eq!(value, TestHooks { low: 2, high: 5 });
+    it_reads_what_it_writes(&value);
+    let invalid = TestHooks { low: 5, high: 2 };
+    assert!(invalid.write_to(&mut Vec::new()).is_err());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestEntry {
+    offset: u16,
+    #[plod(seek_to = offset)]
+    data: u32,
+    id: u8,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestDirectory {
+    #[plod(size_type(u8))]
+    entries: Vec<TestEntry>,
+}
+
+#[test]
+fn test_seek_to() {
+    let data = [2, 8, 0, 1, 12, 0, 2, 0, 4, 3, 2, 1, 8, 7, 6, 5];
+    let directory = TestDirectory::read_from_seek(&mut std::io::Cursor::new(&data)).unwrap();
+    assert_eq!(
+        directory,
+        TestDirectory {
+            entries: vec![
+                TestEntry { offset: 8, data: 0x01020304, id: 1 },
+                TestEntry { offset: 12, data: 0x05060708, id: 2 },
+            ]
+        }
+    );
+    assert_eq!(directory.size_at_rest(), 7);
+    let mut cursor = std::io::Cursor::new(vec![0; 8]);
+    directory.write_to_seek(&mut cursor).unwrap();
+    assert_eq!(cursor.into_inner(), data);
+    // seek_to fields cannot be read without Seek
+    assert!(TestDirectory::read_from(&mut data.as_slice()).is_err());
+    assert!(directory.write_to(&mut Vec::new()).is_err());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestPaddedDirectory {
+    directory: TestDirectory,
+    #[plod(pad_to_offset = 8)]
+    end: u8,
+}
+
+#[test]
+fn test_seek_to_nested() {
+    let data = [1, 9, 0, 1, 0, 0, 0, 0, 7, 4, 3, 2, 1];
+    let value = TestPaddedDirectory::read_from_seek(&mut std::io::Cursor::new(&data)).unwrap();
+    assert_eq!(value.directory.entries, vec![TestEntry { offset: 9, data: 0x01020304, id: 1 }]);
+    assert_eq!(value.end, 7);
+    // the padding starts where the nested entries end, not where the pointed data ends
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    value.write_to_seek(&mut cursor).unwrap();
+    assert_eq!(cursor.into_inner(), data);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), little_endian, variant_size = 4)]
+enum TestSlot {
+    #[plod(tag = 1)]
+    Small(u8),
+    #[plod(tag = 2)]
+    Full(u32),
+    #[plod(tag = 3)]
+    Empty,
+    #[plod(tag = 4)]
+    TooLarge(u64),
+}
+
+#[test]
+fn test_variant_size() {
+    it_reads_what_it_writes(&TestSlot::Small(7));
+    it_reads_what_it_writes(&TestSlot::Full(7));
+    it_reads_what_it_writes(&TestSlot::Empty);
+    let mut memory: Vec<u8> = Vec::new();
+    TestSlot::Small(7).write_to(&mut memory).unwrap();
+    TestSlot::Full(0x01020304).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 7, 0, 0, 0, 2, 4, 3, 2, 1]);
+    assert_eq!(TestSlot::Small(7).size_at_rest(), 5);
+    assert_eq!(TestSlot::describe().fixed_size(), Some(5));
+    assert!(TestSlot::TooLarge(1).write_to(&mut Vec::new()).is_err());
+    // padding is skipped on read
+    let (slot, rest) = TestSlot::read_from_slice(&[1, 7, 9, 9, 9, 3]).unwrap();
+    assert_eq!(slot, TestSlot::Small(7));
+    assert_eq!(rest, &[3]);
+    let spans = plod::dump::spans(&TestSlot::Small(7));
+    assert_eq!(spans.last().unwrap().path, "TestSlot.<padding>");
+    assert_eq!((spans.last().unwrap().start, spans.last().unwrap().size), (2, 3));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, align_size(4))]
+struct TestAligned {
+    kind: u8,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[test]
+fn test_align_size() {
+    let value = TestAligned { kind: 1, data: vec![5] };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 1, 5, 0]);
+    let value = TestAligned { kind: 1, data: vec![5, 6, 7] };
+    assert_eq!(value.size_at_rest(), 8);
+    it_reads_what_it_writes(&value);
+    let (read, rest) = TestAligned::read_from_slice(&[1, 0, 9, 9, 3]).unwrap();
+    assert_eq!(read, TestAligned { kind: 1, data: vec![] });
+    assert_eq!(rest, &[3]);
+    let spans = plod::dump::spans(&TestAligned { kind: 1, data: vec![] });
+    assert_eq!(spans.last().unwrap().path, "TestAligned.<padding>");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestNestedSizes {
+    #[plod(size_type(u16, u8))]
+    lines: Vec<Vec<u8>>,
+    #[plod(size_type(u8, u16), byte_sized)]
+    tables: Vec<Vec<Vec<u16>>>,
+}
+
+#[test]
+fn test_nested_size_types() {
+    let value = TestNestedSizes {
+        lines: vec![vec![1, 2], vec![]],
+        tables: vec![vec![vec![3]]],
+    };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![2, 0, 2, 1, 2, 0, 6, 4, 0, 2, 0, 3, 0]);
+    assert_eq!(value.size_at_rest(), memory.len());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, no_pos)]
+struct TestNoPos {
+    id: u16,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+/// the former spelling of no_pos
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, mo_pos)]
+struct TestMoPos {
+    id: u16,
+}
+
+#[test]
+fn test_no_pos() {
+    let value = TestNoPos { id: 1, data: vec![2, 3] };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 0, 2, 2, 3]);
+    it_reads_what_it_writes(&TestMoPos { id: 4 });
+}
+
+type Payload = Vec<u8>;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestStdPaths {
+    a: core::primitive::u16,
+    #[plod(size_type(u8))]
+    b: std::vec::Vec<std::primitive::u16>,
+    c: Box<u32>,
+    d: ::std::boxed::Box<(u8, u16)>,
+    #[plod(as = "Vec<u8>", size_type(u8))]
+    e: Payload,
+}
+
+#[test]
+fn test_std_paths() {
+    let value = TestStdPaths {
+        a: 1,
+        b: vec![2],
+        c: Box::new(3),
+        d: Box::new((4, 6)),
+        e: vec![5],
+    };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 0, 1, 2, 0, 3, 0, 0, 0, 4, 6, 0, 1, 5]);
+    assert_eq!(value.size_at_rest(), memory.len());
+}
+
+#[derive(PartialEq, Debug)]
+struct Id(u32);
+
+impl From<u32> for Id {
+    fn from(id: u32) -> Self {
+        Id(id)
+    }
+}
+
+impl std::borrow::Borrow<u32> for Id {
+    fn borrow(&self) -> &u32 {
+        &self.0
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct Bytes(Vec<u8>);
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+impl std::borrow::Borrow<Vec<u8>> for Bytes {
+    fn borrow(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestNewtypes {
+    #[plod(as_primitive(u32))]
+    id: Id,
+    #[plod(as_vec, size_type(u8))]
+    data: Bytes,
+}
+
+#[test]
+fn test_as_newtype() {
+    let value = TestNewtypes {
+        id: Id(7),
+        data: Bytes(vec![1, 2]),
+    };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0, 0, 0, 7, 2, 1, 2]);
+    let spans = plod::dump::spans(&value);
+    assert_eq!(spans.last().unwrap().size, 2);
+}
+
+#[test]
+fn test_layout_doc() {
+    assert_eq!(
+        TestAligned::LAYOUT_DOC,
+        "Wire layout (little endian):\n\
+         - `kind`: `u8`\n\
+         - `data`: `Vec<u8>`, after its item count as a `u8`\n\
+         \n\
+         The whole is padded with zeroes to a multiple of 4 bytes.\n"
+    );
+    assert!(TestNewtypes::LAYOUT_DOC.contains("- `id`: `u32`\n"));
+    assert_eq!(PosMarker::LAYOUT_DOC, "");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, magic(u16 = 0xcafe), length_prefixed(u8), expected_size = 17)]
+struct TestHeader {
+    version: (u8, u8),
+    #[plod(reserved(2))]
+    flags: [u16; 3],
+    inner: TestMagic,
+    #[plod(skip)]
+    cache: u64,
+}
+
+#[test]
+fn test_const_size() {
+    let buffer = [0_u8; TestHeader::SIZE];
+    assert_eq!(buffer.len(), 1 + 2 + 2 + 2 + 6 + 4);
+    let header = TestHeader {
+        version: (1, 2),
+        flags: [3, 4, 5],
+        inner: TestMagic { a: 6 },
+        cache: 0,
+    };
+    assert_eq!(header.size_at_rest(), TestHeader::SIZE);
+    assert_eq!(TestHeader::describe().fixed_size(), TestHeader::FIXED_SIZE);
+    assert_eq!(TestKeepMagic::FIXED_SIZE, Some(6));
+    assert_eq!(TestSlot::SIZE, 5);
+    assert_eq!(TestKeepMagicEnum::FIXED_SIZE, Some(3));
+    // variants of different sizes
+    assert_eq!(TestEnum1::FIXED_SIZE, TestEnum1::describe().fixed_size());
+    assert_eq!(TestAligned::FIXED_SIZE, None);
+}
+
+#[test]
+fn test_const_offsets() {
+    // after the length prefix and the magic
+    assert_eq!(TestHeader::OFFSET_VERSION, 3);
+    // after the reserved bytes
+    assert_eq!(TestHeader::OFFSET_FLAGS, 7);
+    assert_eq!(TestHeader::OFFSET_INNER, 13);
+    let header = TestHeader {
+        version: (1, 2),
+        flags: [3, 4, 5],
+        inner: TestMagic { a: 6 },
+        cache: 0,
+    };
+    let mut buffer = Vec::new();
+    header.write_to(&mut buffer).unwrap();
+    let offset = TestHeader::OFFSET_FLAGS;
+    assert_eq!(buffer[offset..offset + 2], [0, 3]);
+    assert_eq!(TestPair::OFFSET_2, 2);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestPair(u16, #[plod(skip)] u8, u32);
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, expected_size = 4)]
+struct TestSector {
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[test]
+#[should_panic(expected = "TestSector is not 4 bytes at rest")]
+fn test_expected_size() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestSector { data: vec![1, 2, 3] }.write_to(&mut memory).unwrap();
+    assert_eq!(memory.len(), 4);
+    TestSector { data: vec![1] }.write_to(&mut memory).unwrap();
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u16), big_endian)]
+enum TestMixedEndian {
+    #[plod(tag = 1, little_endian)]
+    Little(u16, #[plod(big_endian)] u16),
+    #[plod(tag = 2..=3, keep_tag, little_endian)]
+    Kept(u16, u32),
+    #[plod(tag = 4)]
+    Big(u16),
+}
+
+#[test]
+fn test_variant_endianness() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestMixedEndian::Little(1, 2).write_to(&mut memory).unwrap();
+    TestMixedEndian::Kept(3, 4).write_to(&mut memory).unwrap();
+    TestMixedEndian::Big(5).write_to(&mut memory).unwrap();
+    assert_eq!(
+        memory,
+        vec![0, 1, 1, 0, 0, 2, 0, 3, 4, 0, 0, 0, 0, 4, 0, 5]
+    );
+    it_reads_what_it_writes(&TestMixedEndian::Little(1, 2));
+    it_reads_what_it_writes(&TestMixedEndian::Kept(3, 4));
+    assert!(TestMixedEndian::LAYOUT_DOC.contains("- `Kept` (tag `2 ..= 3`), little endian,"));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u16), tag_endianness(big_endian), little_endian)]
+enum TestTagEndianness {
+    #[plod(tag = 1)]
+    Record(u16),
+    #[plod(tag = 2..=3, keep_tag)]
+    Kept(u16, u16),
+}
+
+#[test]
+fn test_tag_endianness() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestTagEndianness::Record(0x0102).write_to(&mut memory).unwrap();
+    TestTagEndianness::Kept(3, 4).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0, 1, 2, 1, 0, 3, 4, 0]);
+    it_reads_what_it_writes(&TestTagEndianness::Record(0x0102));
+    it_reads_what_it_writes(&TestTagEndianness::Kept(2, 4));
+    match TestTagEndianness::describe() {
+        plod::layout::Layout::Enum(e) => {
+            assert_eq!(e.tag.endianness, plod::layout::Endianness::Big)
+        }
+        _ => panic!("not an enum"),
+    }
+    assert!(TestTagEndianness::LAYOUT_DOC.contains("a `u16` tag (big endian) followed by"));
+}
+
 #[test]
 fn test_option() {
     let s1 = TestStruct1 {
@@ -369,5 +987,1398 @@ struct TestPartialContext {
     b: TestWithContext,
 }
 
+#[test]
+fn test_partial_context() {
+    let val = TestPartialContext {
+        a: 1,
+        c: Context { count: 42 },
+        b: TestWithContext {
+            a: 2,
+            b: TestWithContext2 { a: 42 },
+        },
+    };
+    it_reads_what_it_writes(&val);
+}
+
 // TODO test with generic in struct
 // TODO test endianness mix and match
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, length_prefixed(u16))]
+struct TestLengthPrefixed {
+    kind: u8,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8), length_prefixed(u8))]
+enum TestLengthPrefixedEnum {
+    #[plod(tag = 1)]
+    A(u16),
+    #[plod(tag = 2)]
+    B(TestLengthPrefixed),
+}
+
+#[test]
+fn test_length_prefixed() {
+    let value = TestLengthPrefixed {
+        kind: 7,
+        data: vec![1, 2],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0, 4, 7, 2, 1, 2]);
+    assert_eq!(value.size_at_rest(), 6);
+    it_reads_what_it_writes(&value);
+    it_reads_what_it_writes(&TestLengthPrefixedEnum::A(3));
+    it_reads_what_it_writes(&TestLengthPrefixedEnum::B(value));
+
+    // unknown trailing bytes are skipped
+    let memory = [0, 6, 7, 1, 1, 0xee, 0xee, 0xee, 9];
+    let mut reader = &memory[..];
+    let value = TestLengthPrefixed::read_from(&mut reader).unwrap();
+    assert_eq!(value.data, vec![1]);
+    assert_eq!(reader, &[9]);
+
+    // content cannot go past the announced length
+    let memory = [0, 2, 7, 1, 1];
+    assert!(TestLengthPrefixed::read_from(&mut &memory[..]).is_err());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, length_footer(u16))]
+struct TestLengthFooter {
+    kind: u8,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, tag_type(u8), length_footer(u8, checksum(u8, Sum8)))]
+enum TestLengthFooterEnum {
+    #[plod(tag = 1)]
+    A(u16),
+    #[plod(tag = 2)]
+    B(TestLengthFooter),
+}
+
+#[test]
+fn test_length_footer() {
+    let value = TestLengthFooter {
+        kind: 7,
+        data: vec![1, 2],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![7, 2, 1, 2, 0, 6]);
+    assert_eq!(value.size_at_rest(), 6);
+    it_reads_what_it_writes(&value);
+    let spans = plod::dump::spans(&value);
+    assert_eq!(spans.last().map(|s| (s.path.as_str(), s.start)), Some(("TestLengthFooter.<len>", 4)));
+    // the length is checked
+    let error = TestLengthFooter::read_from(&mut [7, 2, 1, 2, 0, 7].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("Length 7 found in the footer, 6 bytes were read"));
+
+    let value = TestLengthFooterEnum::B(value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![2, 7, 2, 1, 2, 0, 6, 9, 29]);
+    assert_eq!(value.size_at_rest(), 9);
+    it_reads_what_it_writes(&value);
+    it_reads_what_it_writes(&TestLengthFooterEnum::A(3));
+    assert_eq!(TestLengthFooterEnum::FIXED_SIZE, None);
+    // the checksum is checked
+    memory[4] = 3;
+    let error = TestLengthFooterEnum::read_from(&mut memory.as_slice()).unwrap_err();
+    assert!(error.to_string().contains("Checksum 0x1e expected, found 0x1d"));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, length_prefixed(u32), patched_size)]
+struct TestPatched {
+    #[plod(size_type(u16), byte_sized)]
+    items: Vec<TestPatchedItem>,
+    tail: u8,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestPatchedItem {
+    #[plod(size_type(u8), byte_sized, patched_size)]
+    values: Vec<u16>,
+}
+
+#[test]
+fn test_patched_size() {
+    let value = TestPatched {
+        items: vec![
+            TestPatchedItem { values: vec![1, 2] },
+            TestPatchedItem { values: vec![] },
+        ],
+        tail: 9,
+    };
+    let mut direct: Vec<u8> = Vec::new();
+    value.write_to(&mut direct).unwrap();
+    let mut patched = std::io::Cursor::new(Vec::new());
+    value.write_to_seek(&mut patched).unwrap();
+    let patched = patched.into_inner();
+    assert_eq!(patched, direct);
+    assert_eq!(patched, vec![9, 0, 0, 0, 6, 0, 4, 1, 0, 2, 0, 0, 9]);
+    it_reads_what_it_writes(&value);
+}
+
+#[test]
+fn test_byte_sized_overshoot() {
+    // 3 bytes of u16 items, the second one crosses the end
+    let error = TestPatchedItem::read_from(&mut [3, 1, 0, 2, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("Vec item exceeds the declared byte size"), "{}", error);
+    // a nested item of 3 bytes in a Vec of 2 bytes
+    let value = TestPatched::read_from(&mut [6, 0, 0, 0, 2, 0, 2, 1, 0, 9].as_slice()).unwrap_err();
+    assert_eq!(value.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct TestEmptyItems {
+    #[plod(size_type(u8), byte_sized)]
+    items: Vec<TestEmptyItem>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct TestEmptyItem {}
+
+#[test]
+fn test_byte_sized_empty_items() {
+    // items of 0 bytes never reach the declared size
+    let error = TestEmptyItems::read_from(&mut [1, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let value = TestEmptyItems::read_from(&mut [0].as_slice()).unwrap();
+    assert!(value.items.is_empty());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestLenOf {
+    #[plod(len_of = data)]
+    data_len: u16,
+    #[plod(len_of = words)]
+    words_size: u8,
+    kind: u8,
+    data: Vec<u8>,
+    #[plod(byte_sized, size_is_next)]
+    words: Vec<u16>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestLenOfEnum {
+    #[plod(tag = 1)]
+    A {
+        #[plod(len_of = items)]
+        count: u32,
+        items: Vec<(u8, u8)>,
+    },
+}
+
+#[test]
+fn test_len_of() {
+    let value = TestLenOf {
+        data_len: 0,
+        words_size: 0,
+        kind: 3,
+        data: vec![1, 2, 3],
+        words: vec![7, 8],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0, 3, 5, 3, 1, 2, 3, 0, 7, 0, 8]);
+    assert_eq!(value.size_at_rest(), memory.len());
+    let result = TestLenOf::read_from(&mut memory.as_slice()).unwrap();
+    assert_eq!(result.data_len, 3);
+    assert_eq!(result.words_size, 5);
+    assert_eq!(result.words, vec![7, 8]);
+    it_reads_what_it_writes(&result);
+    it_reads_what_it_writes(&TestLenOfEnum::A {
+        count: 2,
+        items: vec![(1, 2), (3, 4)],
+    });
+    let ksy = plod::export::kaitai::<TestLenOf>();
+    assert!(ksy.contains("size: data_len"), "{}", ksy);
+    assert!(!ksy.contains("data_size"), "{}", ksy);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestSizeUnit {
+    #[plod(len_of = sectors)]
+    sector_count: u8,
+    #[plod(size_type(u16), byte_sized, size_unit = 4)]
+    words: Vec<u16>,
+    #[plod(size_unit = 4)]
+    sectors: Vec<u8>,
+}
+
+#[test]
+fn test_size_unit() {
+    let value = TestSizeUnit {
+        sector_count: 0,
+        words: vec![1, 2, 3, 4],
+        sectors: vec![9; 8],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(&memory[..5], &[2, 2, 0, 1, 0]);
+    assert_eq!(memory.len(), 19);
+    let result = TestSizeUnit::read_from(&mut memory.as_slice()).unwrap();
+    assert_eq!(result, TestSizeUnit { sector_count: 2, ..value });
+    assert_eq!(TestSizeUnit::skip_from(&mut memory.as_slice()).unwrap(), 19);
+    let ksy = plod::export::kaitai::<TestSizeUnit>();
+    assert!(ksy.contains("size: (sector_count) * 4"), "{}", ksy);
+    assert!(TestSizeUnit::LAYOUT_DOC.contains("size in units of 4 bytes"));
+
+    // only whole units can be written
+    let value = TestSizeUnit { words: vec![1], ..result };
+    let error = value.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("not a multiple of the size unit 4"), "{}", error);
+
+    // the size in units must fit in the size type
+    let value = TestSizeUnit { words: vec![0; 2 * 65536], ..value };
+    let error = value.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("Size 65536 does not fit in u16"), "{}", error);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestSizeAdjust {
+    #[plod(len_of = payload)]
+    total_len: u16,
+    #[plod(size_type(u8), size_adjust = -1)]
+    tags: Vec<u8>,
+    #[plod(size_adjust = 4)]
+    payload: Vec<u8>,
+}
+
+#[test]
+fn test_size_adjust() {
+    let value = TestSizeAdjust {
+        total_len: 0,
+        tags: vec![1, 2],
+        payload: vec![7, 8],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    // the length includes its own 2 bytes and the tags
+    assert_eq!(memory, vec![0, 6, 1, 1, 2, 7, 8]);
+    let result = TestSizeAdjust::read_from(&mut memory.as_slice()).unwrap();
+    assert_eq!(result, TestSizeAdjust { total_len: 6, ..value });
+    assert_eq!(TestSizeAdjust::skip_from(&mut memory.as_slice()).unwrap(), 7);
+    assert!(TestSizeAdjust::LAYOUT_DOC.contains("item count minus 1"));
+
+    let error = TestSizeAdjust::read_from(&mut [0, 3, 0, 9].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("less than its adjustment 4"), "{}", error);
+    let value = TestSizeAdjust { tags: vec![], ..result };
+    let error = value.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    // the adjusted size must fit in the size type
+    let value = TestSizeAdjust { tags: vec![0; 257], ..value };
+    let error = value.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("Size 256 does not fit in u8"), "{}", error);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, size_type(i16), signed_size)]
+struct TestSignedSize {
+    names: Vec<u8>,
+    #[plod(size_is_next)]
+    values: Vec<u16>,
+}
+
+#[test]
+fn test_signed_size() {
+    let value = TestSignedSize::read_from(&mut [0xff, 0xff, 0, 2, 0, 7].as_slice()).unwrap();
+    assert_eq!(
+        value,
+        TestSignedSize {
+            names: vec![],
+            values: vec![7],
+        }
+    );
+    assert_eq!(TestSignedSize::skip_from(&mut [0xff, 0xfe, 0xff, 0xff].as_slice()).unwrap(), 4);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0, 0, 0, 2, 0, 7]);
+    assert!(TestSignedSize::LAYOUT_DOC.contains("a negative size meaning empty"));
+    // sizes above the maximum would be read back as negative
+    let value = TestSignedSize { names: vec![0; 32768], ..value };
+    let error = value.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("Size 32768 does not fit in i16"), "{}", error);
+}
+
+#[derive(Default)]
+struct Dialect {
+    extended: bool,
+}
+
+plod::context!(Dialect => ());
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), context = Dialect)]
+enum TestGuard {
+    #[plod(tag = 1)]
+    Ping,
+    #[plod(tag = 0x80..=0xFF if ctx.extended, keep_tag)]
+    Extended(u8, u16),
+    #[plod(tag = 0x80..=0xFF, keep_tag)]
+    Legacy(u8),
+}
+
+#[test]
+fn test_tag_guard() {
+    let memory = [0x90_u8, 1, 0];
+    let extended = Dialect { extended: true };
+    let result = TestGuard::impl_read_from(&mut memory.as_slice(), &extended, 0).unwrap();
+    assert_eq!(result, TestGuard::Extended(0x90, 1));
+    let result = TestGuard::read_from(&mut memory.as_slice()).unwrap();
+    assert_eq!(result, TestGuard::Legacy(0x90));
+    let mut written = Vec::new();
+    TestGuard::Extended(0x90, 1).impl_write_to(&mut written, &extended, 0).unwrap();
+    assert_eq!(written, memory);
+    let error = TestGuard::skip_from(&mut memory.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+    assert!(TestGuard::LAYOUT_DOC.contains("if `ctx.extended`"), "{}", TestGuard::LAYOUT_DOC);
+}
+
+const OPCODE_PING: u8 = 1;
+const HEADER_MAGIC: u32 = 0xcafe_f00d;
+struct Opcode;
+impl Opcode {
+    const DATA: u8 = 2;
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), big_endian)]
+enum TestNamedConstants {
+    #[plod(tag = OPCODE_PING)]
+    Ping,
+    #[plod(tag = Opcode::DATA, magic(u32 = HEADER_MAGIC | 0xd00d))]
+    Data(u16),
+}
+
+#[test]
+fn test_named_constants() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestNamedConstants::Ping.write_to(&mut memory).unwrap();
+    TestNamedConstants::Data(3).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 2, 0xca, 0xfe, 0xf0, 0x0d, 0, 3]);
+    it_reads_what_it_writes(&TestNamedConstants::Ping);
+    it_reads_what_it_writes(&TestNamedConstants::Data(4));
+    let result = TestNamedConstants::read_from(&mut [2_u8, 0, 0, 0xd0, 0x0d, 0, 5].as_slice());
+    assert_eq!(result.unwrap(), TestNamedConstants::Data(5));
+    assert!(TestNamedConstants::read_from(&mut [3_u8].as_slice()).is_err());
+    assert!(TestNamedConstants::LAYOUT_DOC.contains("tag `OPCODE_PING`"));
+}
+
+const SAMPLES: usize = 700;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestSampleBlock {
+    channel: u8,
+    samples: [i16; SAMPLES],
+    #[plod(little_endian)]
+    gains: [f32; 3],
+}
+
+#[test]
+fn test_primitive_array() {
+    let mut samples = [0_i16; SAMPLES];
+    for (i, sample) in samples.iter_mut().enumerate() {
+        *sample = (i as i16 - 350) * 91;
+    }
+    let block = TestSampleBlock {
+        channel: 2,
+        samples,
+        gains: [0.5, -1.0, 2.25],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    block.write_to(&mut memory).unwrap();
+    assert_eq!(memory.len(), 1 + 1400 + 12);
+    assert_eq!(block.size_at_rest(), memory.len());
+    assert_eq!(&memory[1..5], &[0x83, 0x96, 0x83, 0xf1]);
+    assert_eq!(&memory[1401..1405], &0.5_f32.to_le_bytes());
+    it_reads_what_it_writes(&block);
+    assert!(TestSampleBlock::read_from(&mut &memory[..1000]).is_err());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(length_prefixed(u8))]
+struct TestExtendedHeader {
+    version: u8,
+    #[plod(if_remaining)]
+    flags: Option<u16>,
+    #[plod(if_remaining, size_type(u8))]
+    name: Option<Vec<u8>>,
+}
+
+#[test]
+fn test_if_remaining() {
+    let legacy = TestExtendedHeader { version: 1, flags: None, name: None };
+    let extended = TestExtendedHeader { version: 2, flags: Some(3), name: Some(vec![4, 5]) };
+    let mut memory: Vec<u8> = Vec::new();
+    legacy.write_to(&mut memory).unwrap();
+    extended.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 1, 6, 2, 3, 0, 2, 4, 5]);
+    let mut from = memory.as_slice();
+    assert_eq!(TestExtendedHeader::read_from(&mut from).unwrap(), legacy);
+    assert_eq!(TestExtendedHeader::read_from(&mut from).unwrap(), extended);
+    let flags_only = [3_u8, 2, 7, 0];
+    let result = TestExtendedHeader::read_from(&mut flags_only.as_slice()).unwrap();
+    assert_eq!(result.flags, Some(7));
+    assert_eq!(result.name, None);
+    assert_eq!(TestExtendedHeader::skip_from(&mut memory.as_slice()).unwrap(), 2);
+    assert!(TestExtendedHeader::LAYOUT_DOC.contains("`u16`, only if bytes remain"));
+    let ksy = plod::export::kaitai::<TestExtendedHeader>();
+    assert!(ksy.contains("if: not _io.eof"), "{}", ksy);
+}
+
+#[test]
+fn test_if_remaining_gap() {
+    // name would be read as flags
+    let gap = TestExtendedHeader { version: 2, flags: None, name: Some(vec![4]) };
+    let error = gap.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestItemAlign {
+    #[plod(size_type(u8), item_align(4))]
+    entries: Vec<(u8, u16)>,
+    #[plod(item_align(2))]
+    bytes: [u8; 3],
+    #[plod(size_type(u16), byte_sized, item_align(4))]
+    sized: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct TestAlignedTable {
+    #[plod(item_align(4))]
+    offsets: [u16; 3],
+}
+
+#[test]
+fn test_item_align() {
+    let value = TestItemAlign {
+        entries: vec![(1, 2), (3, 4)],
+        bytes: [5, 6, 7],
+        sized: vec![8, 9],
+    };
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(
+        memory,
+        vec![2, 1, 2, 0, 0, 3, 4, 0, 5, 0, 6, 0, 7, 5, 0, 8, 0, 0, 0, 9]
+    );
+    assert_eq!(value.size_at_rest(), memory.len());
+    it_reads_what_it_writes(&value);
+    assert_eq!(TestItemAlign::skip_from(&mut memory.as_slice()).unwrap(), memory.len());
+    assert!(TestItemAlign::LAYOUT_DOC.contains("each item starting at a multiple of 4 bytes"));
+
+    assert_eq!(TestAlignedTable::FIXED_SIZE, Some(10));
+    assert_eq!(TestAlignedTable::describe().fixed_size(), Some(10));
+    assert_eq!(TestAlignedTable::offset_of("offsets[2]"), Some(8));
+    it_reads_what_it_writes(&TestAlignedTable { offsets: [1, 2, 3] });
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, context = Dialect)]
+struct TestVersion {
+    major: u8,
+    minor: u8,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, context = Dialect)]
+struct TestFlatten {
+    magic: u16,
+    #[plod(flatten)]
+    version: TestVersion,
+    size: u32,
+}
+
+#[test]
+fn test_flatten() {
+    let value = TestFlatten {
+        magic: 0x7f45,
+        version: TestVersion { major: 1, minor: 2 },
+        size: 3,
+    };
+    let ctx = Dialect { extended: false };
+    let mut memory: Vec<u8> = Vec::new();
+    value.impl_write_to(&mut memory, &ctx, 0).unwrap();
+    assert_eq!(memory, vec![0x7f, 0x45, 1, 2, 0, 0, 0, 3]);
+    let result = TestFlatten::impl_read_from(&mut memory.as_slice(), &ctx, 0).unwrap();
+    assert_eq!(result, value);
+    let names: Vec<_> = match TestFlatten::describe() {
+        plod::layout::Layout::Struct(s) => s.fields.iter().map(|f| f.name).collect(),
+        _ => panic!("not a struct"),
+    };
+    assert_eq!(names, vec!["magic", "major", "minor", "size"]);
+    assert_eq!(TestFlatten::offset_of("minor"), Some(3));
+    let spans = plod::dump::spans(&value);
+    assert!(spans.iter().any(|s| s.path.ends_with("TestFlatten.minor") && s.start == 3), "{:?}", spans);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(endian_marker(u16, big = 0x4D4D, little = 0x4949))]
+struct TestTiffHeader {
+    order: u16,
+    version: u16,
+    #[plod(size_type(u16))]
+    offsets: Vec<u32>,
+}
+
+#[test]
+fn test_endian_marker() {
+    let big = TestTiffHeader {
+        order: 0x4D4D,
+        version: 42,
+        offsets: vec![8],
+    };
+    it_reads_what_it_writes(&big);
+    let mut memory: Vec<u8> = Vec::new();
+    big.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0x4d, 0x4d, 0, 42, 0, 1, 0, 0, 0, 8]);
+    let little = TestTiffHeader {
+        order: 0x4949,
+        ..big
+    };
+    it_reads_what_it_writes(&little);
+    let mut memory: Vec<u8> = Vec::new();
+    little.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0x49, 0x49, 42, 0, 1, 0, 8, 0, 0, 0]);
+    let error = TestTiffHeader::read_from(&mut [0x49, 0x4d, 0, 42, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let wrong = TestTiffHeader {
+        order: 0x4d49,
+        version: 42,
+        offsets: vec![],
+    };
+    assert!(wrong.write_to(&mut Vec::new()).is_err());
+    assert!(TestTiffHeader::LAYOUT_DOC.contains("big endian if the first field, a `u16`, is `0x4D4D`"));
+}
+
+const TEST_ROOTFS_OFFSET: usize = 12;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestFirmware {
+    version: u16,
+    #[plod(pad_to_offset = 4, fill = 0xff)]
+    #[plod(size_type(u8))]
+    kernel: Vec<u8>,
+    #[plod(pad_to_offset = TEST_ROOTFS_OFFSET)]
+    rootfs: u32,
+}
+
+#[test]
+fn test_pad_to_offset() {
+    let value = TestFirmware {
+        version: 1,
+        kernel: vec![1, 2, 3],
+        rootfs: 7,
+    };
+    it_reads_what_it_writes(&value);
+    let mut memory: Vec<u8> = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 0, 0xff, 0xff, 3, 1, 2, 3, 0, 0, 0, 0, 7, 0, 0, 0]);
+    assert_eq!(value.size_at_rest(), 16);
+    assert_eq!(TestFirmware::FIXED_SIZE, None);
+    assert_eq!(TestFirmware::skip_from(&mut memory.as_slice()).unwrap(), 16);
+    let spans = plod::dump::spans(&value);
+    assert!(spans.iter().any(|s| s.path.ends_with("TestFirmware.<padding>") && (s.start, s.size) == (8, 4)), "{:?}", spans);
+    // the kernel doesn't fit before the root file system
+    let value = TestFirmware {
+        kernel: vec![0; 9],
+        ..value
+    };
+    let error = value.impl_write_to(&mut Vec::new(), &(), 0).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8), size_type(u8))]
+enum TestNoInherit {
+    #[plod(tag = 1)]
+    Short(Vec<u8>, u16),
+    #[plod(tag = 2, no_inherit, little_endian)]
+    Long(#[plod(size_type(u32))] Vec<u8>, u16),
+    #[plod(tag = 3)]
+    Mixed(Vec<u8>, #[plod(no_inherit, size_type(u16))] Vec<u8>),
+}
+
+#[test]
+fn test_no_inherit() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestNoInherit::Short(vec![1], 2).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![1, 1, 1, 0, 2]);
+    // the tag keeps the endianness of the enum
+    let mut memory: Vec<u8> = Vec::new();
+    TestNoInherit::Long(vec![1], 2).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![2, 1, 0, 0, 0, 1, 2, 0]);
+    // back to native endianness
+    let mut memory: Vec<u8> = Vec::new();
+    TestNoInherit::Mixed(vec![1], vec![2]).write_to(&mut memory).unwrap();
+    assert_eq!(&memory[..3], &[3, 1, 1]);
+    assert_eq!(&memory[3..5], &1_u16.to_ne_bytes());
+    for value in [
+        TestNoInherit::Short(vec![1], 2),
+        TestNoInherit::Long(vec![1, 2], 3),
+        TestNoInherit::Mixed(vec![1], vec![2, 3]),
+    ] {
+        it_reads_what_it_writes(&value);
+    }
+}
+
+#[derive(Plod, PartialEq, Debug, Clone, Copy)]
+struct TestMethodKey {
+    class: u8,
+    method: u8,
+}
+
+const TEST_OPEN: TestMethodKey = TestMethodKey { class: 10, method: 1 };
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(TestMethodKey))]
+enum TestComposite {
+    #[plod(tag = TEST_OPEN)]
+    Open(u16),
+    #[plod(tag = TestMethodKey { class: 10, method: 2 })]
+    Close,
+    #[plod(keep_tag)]
+    Other(TestMethodKey, u8),
+}
+
+#[test]
+fn test_composite_tag() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestComposite::Open(3).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![10, 1, 0, 3]);
+    let mut memory: Vec<u8> = Vec::new();
+    TestComposite::Close.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![10, 2]);
+    let value = TestComposite::read_from(&mut [20_u8, 1, 7].as_slice()).unwrap();
+    assert_eq!(value, TestComposite::Other(TestMethodKey { class: 20, method: 1 }, 7));
+    for value in [
+        TestComposite::Open(3),
+        TestComposite::Close,
+        TestComposite::Other(TestMethodKey { class: 20, method: 1 }, 7),
+    ] {
+        it_reads_what_it_writes(&value);
+    }
+    assert_eq!(TestComposite::FIXED_SIZE, None);
+    assert_eq!(TestComposite::skip_from(&mut [10_u8, 1, 0, 3].as_slice()).unwrap(), 4);
+    let spans = plod::dump::spans(&TestComposite::Close);
+    assert_eq!((spans[0].start, spans[0].size), (0, 2), "{:?}", spans);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(TestMethodKey))]
+enum TestCompositeStrict {
+    #[plod(tag = TEST_OPEN)]
+    Open,
+}
+
+#[test]
+fn test_composite_tag_unknown() {
+    let error = TestCompositeStrict::read_from(&mut [1_u8, 2].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Other);
+    assert_eq!(TestCompositeStrict::FIXED_SIZE, Some(2));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_fields(class: u8, method: u16))]
+enum TestTagFields {
+    #[plod(tag = (10, 1))]
+    Open(u8),
+    #[plod(tag = (10, 2))]
+    Close,
+    #[plod(tag = (20, 0..=9), keep_tag)]
+    Basic((u8, u16), u8),
+    #[plod(keep_tag)]
+    Other((u8, u16)),
+}
+
+#[test]
+fn test_tag_fields() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestTagFields::Open(3).write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![10, 0, 1, 3]);
+    let value = TestTagFields::read_from(&mut [20_u8, 0, 5, 7].as_slice()).unwrap();
+    assert_eq!(value, TestTagFields::Basic((20, 5), 7));
+    let value = TestTagFields::read_from(&mut [20_u8, 0, 10].as_slice()).unwrap();
+    assert_eq!(value, TestTagFields::Other((20, 10)));
+    for value in [
+        TestTagFields::Open(3),
+        TestTagFields::Close,
+        TestTagFields::Basic((20, 5), 7),
+        TestTagFields::Other((1, 2)),
+    ] {
+        it_reads_what_it_writes(&value);
+    }
+    let spans = plod::dump::spans(&TestTagFields::Close);
+    assert_eq!((spans[0].start, spans[0].size), (0, 3), "{:?}", spans);
+    assert!(TestTagFields::LAYOUT_DOC.contains("`(class: u8, method: u16)` tag"));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestIsTag {
+    #[plod(tag = 1..=3)]
+    Data {
+        len: u16,
+        #[plod(is_tag)]
+        kind: u8,
+        value: u8,
+    },
+    #[plod(tag = 4..=5)]
+    Pair(u8, #[plod(is_tag)] u8),
+}
+
+#[test]
+fn test_is_tag() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestIsTag::Data { len: 7, kind: 2, value: 9 }.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![2, 7, 0, 9]);
+    let value = TestIsTag::read_from(&mut [5_u8, 8].as_slice()).unwrap();
+    assert_eq!(value, TestIsTag::Pair(8, 5));
+    for value in [TestIsTag::Data { len: 7, kind: 3, value: 9 }, TestIsTag::Pair(1, 4)] {
+        it_reads_what_it_writes(&value);
+    }
+    assert_eq!(TestIsTag::Data { len: 7, kind: 3, value: 9 }.size_at_rest(), 4);
+    assert!(TestIsTag::LAYOUT_DOC.contains("the field `kind` holds the tag"));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, dispatch_on_magic)]
+enum TestChunk {
+    #[plod(magic(u32 = 0x52444849))]
+    Header { width: u16, height: u16 },
+    #[plod(magic(u32 = 0x54414449 | 0x74616469), keep_magic)]
+    Data(u32, #[plod(size_type(u8))] Vec<u8>),
+    #[plod(magic(u32 = 0x444e4549))]
+    End,
+}
+
+#[test]
+fn test_dispatch_on_magic() {
+    let mut memory: Vec<u8> = Vec::new();
+    TestChunk::Header { width: 2, height: 3 }.write_to(&mut memory).unwrap();
+    assert_eq!(memory, vec![0x49, 0x48, 0x44, 0x52, 2, 0, 3, 0]);
+    let value = TestChunk::read_from(&mut [0x69_u8, 0x64, 0x61, 0x74, 1, 9].as_slice()).unwrap();
+    assert_eq!(value, TestChunk::Data(0x74616469, vec![9]));
+    for value in [
+        TestChunk::Header { width: 2, height: 3 },
+        TestChunk::Data(0x54414449, vec![1, 2]),
+        TestChunk::End,
+    ] {
+        it_reads_what_it_writes(&value);
+    }
+    let error = TestChunk::read_from(&mut [0_u8, 0, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Other);
+    assert_eq!(TestChunk::skip_from(&mut [0x49_u8, 0x45, 0x4e, 0x44].as_slice()).unwrap(), 4);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestCfg {
+    a: u8,
+    #[cfg(not(test))]
+    missing: u32,
+    #[cfg(test)]
+    #[plod(reserved(1))]
+    present: u16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestCfgEnum {
+    #[plod(tag = 1)]
+    A {
+        #[cfg(not(test))]
+        #[plod(size_type(u8))]
+        missing: Vec<u8>,
+        #[cfg(not(test))]
+        #[cfg(test)]
+        also_missing: u8,
+        b: u8,
+    },
+}
+
+#[test]
+fn test_cfg_fields() {
+    assert_eq!(TestCfg::FIXED_SIZE, Some(4));
+    let value = TestCfg { a: 1, present: 2 };
+    assert_eq!(value.size_at_rest(), 4);
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data, vec![1, 0, 2, 0]);
+    assert_eq!(TestCfg::read_from(&mut data.as_slice()).unwrap(), value);
+    let paths: Vec<_> = plod::dump::spans(&value).into_iter().map(|s| s.path).collect();
+    assert_eq!(paths, vec!["TestCfg.a", "TestCfg.<reserved>", "TestCfg.present"]);
+    match TestCfg::describe() {
+        plod::layout::Layout::Struct(s) => assert_eq!(s.fields.len(), 3),
+        _ => panic!("not a struct"),
+    }
+
+    let value = TestCfgEnum::A { b: 3 };
+    assert_eq!(TestCfgEnum::FIXED_SIZE, Some(2));
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data, vec![1, 3]);
+    assert_eq!(TestCfgEnum::read_from(&mut data.as_slice()).unwrap(), value);
+    assert_eq!(plod::dump::spans(&value).len(), 2);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, magic(u16 = 0xcafe), keep_magic, constructor)]
+struct TestConstructor {
+    magic: u16,
+    #[plod(len_of = names)]
+    count: u8,
+    #[plod(reserved(1))]
+    flags: u8,
+    #[plod(size_type(u8))]
+    names: Vec<u8>,
+    #[plod(skip)]
+    cache: Option<u32>,
+}
+
+#[test]
+fn test_constructor() {
+    let value = TestConstructor::new(3, vec![1, 2]);
+    assert_eq!(
+        value,
+        TestConstructor { magic: 0xcafe, count: 0, flags: 3, names: vec![1, 2], cache: None }
+    );
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data, vec![0xca, 0xfe, 2, 0, 3, 1, 2]);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, tag_type(u8), common_fields(length: u16, flags: u8))]
+enum TestCommon {
+    #[plod(tag = 1)]
+    Ping { length: u16, flags: u8 },
+    #[plod(tag = 2 if flags & 0x80 != 0)]
+    Extended { flags: u8, value: u32, length: u16 },
+    #[plod(tag = 2)]
+    Data { length: u16, flags: u8, value: u16 },
+}
+
+#[test]
+fn test_common_fields() {
+    let value = TestCommon::Data { length: 7, flags: 1, value: 0x0102 };
+    assert_eq!(value.size_at_rest(), 6);
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data, vec![0, 7, 1, 2, 1, 2]);
+    assert_eq!(TestCommon::read_from(&mut data.as_slice()).unwrap(), value);
+
+    // the guard sees the common fields
+    let value = TestCommon::Extended { flags: 0x80, value: 3, length: 9 };
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data, vec![0, 9, 0x80, 2, 0, 0, 0, 3]);
+    assert_eq!(TestCommon::read_from(&mut data.as_slice()).unwrap(), value);
+
+    let value = TestCommon::Ping { length: 0, flags: 0 };
+    assert_eq!(TestCommon::FIXED_SIZE, None);
+    let paths: Vec<_> = plod::dump::spans(&value).into_iter().map(|s| s.path).collect();
+    assert_eq!(paths, vec!["TestCommon.length", "TestCommon.flags", "TestCommon.<tag>"]);
+    assert!(TestCommon::LAYOUT_DOC.contains("the common fields `length`: `u16`, `flags`: `u8`"));
+
+    // errors name the enum
+    let error = TestCommon::read_from(&mut [0_u8, 1].as_slice()).unwrap_err();
+    assert!(error.to_string().contains("TestCommon.flags"), "{}", error);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestTypedBody {
+    #[plod(tag = 1)]
+    Ping,
+    #[plod(tag = 2)]
+    Data(u16),
+    #[plod(tag = 3..=4, keep_tag)]
+    Level(u8, u8),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestTypedHeader {
+    #[plod(tag_for = payload)]
+    msg_type: u8,
+    length: u16,
+    #[plod(external_tag)]
+    payload: TestTypedBody,
+}
+
+#[test]
+fn test_external_tag() {
+    let value = TestTypedHeader { msg_type: 0, length: 9, payload: TestTypedBody::Data(0x0102) };
+    assert_eq!(value.size_at_rest(), 5);
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data, vec![2, 9, 0, 2, 1]);
+    let value = TestTypedHeader::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(value, TestTypedHeader { msg_type: 2, length: 9, payload: TestTypedBody::Data(0x0102) });
+    for (msg_type, payload) in [(1, TestTypedBody::Ping), (2, TestTypedBody::Data(5)), (4, TestTypedBody::Level(4, 7))] {
+        it_reads_what_it_writes(&TestTypedHeader { msg_type, length: 0, payload });
+    }
+    let error = TestTypedHeader::read_from(&mut [5_u8, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Other);
+    let value = TestTypedHeader { msg_type: 3, length: 0, payload: TestTypedBody::Level(3, 1) };
+    let paths: Vec<_> = plod::dump::spans(&value).into_iter().map(|s| s.path).collect();
+    assert_eq!(paths, vec!["TestTypedHeader.msg_type", "TestTypedHeader.length", "TestTypedHeader.payload"]);
+    assert!(TestTypedHeader::LAYOUT_DOC.contains("the tag of `payload`"));
+}
+
+// arrays of tuples wider than the std Debug impls
+#[allow(clippy::type_complexity)]
+#[derive(Plod)]
+#[plod(big_endian)]
+struct TestWide(
+    u8,
+    u8,
+    u8,
+    u8,
+    u8,
+    u8,
+    u8,
+    u8,
+    u8,
+    u8,
+    u8,
+    u8,
+    [(u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u16); 2],
+);
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestWideEnum {
+    #[plod(tag = 1)]
+    Wide(u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u16),
+}
+
+#[test]
+fn test_wide_tuples() {
+    let row = (1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
+    let wide = TestWide(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, [row, row]);
+    let mut buffer = Vec::new();
+    wide.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer.len(), TestWide::SIZE);
+    let read = TestWide::read_from(&mut buffer.as_slice()).unwrap();
+    assert_eq!((read.11, read.12[1].0, read.12[1].15), (12, 1, 16));
+    it_reads_what_it_writes(&TestWideEnum::Wide(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestSigned {
+    #[plod(keep_raw = raw_header)]
+    header: TestMagic,
+    raw_header: Vec<u8>,
+    signature: u16,
+}
+
+#[test]
+fn test_keep_raw() {
+    let data = [0xab, 0xcd, 0, 1, 0x12, 0x34];
+    let signed = TestSigned::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(signed.header, TestMagic { a: 1 });
+    assert_eq!(signed.raw_header, [0xab, 0xcd, 0, 1]);
+    assert_eq!(signed.size_at_rest(), 6);
+    let mut written = Vec::new();
+    signed.write_to(&mut written).unwrap();
+    assert_eq!(written, data);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, length_prefixed(u8))]
+struct TestSizeAfter {
+    kind: u8,
+    #[plod(size_type(u16), size_after)]
+    values: Vec<u16>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestSizesAfter {
+    first: TestSizeAfter,
+    #[plod(size_type(u8), byte_sized, size_after)]
+    second: Vec<TestSizeAfter>,
+}
+
+// the names of the fields don't clash with the generated code
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct TestSizeAfterItems {
+    items_len: u8,
+    #[plod(size_type(u8), size_after)]
+    items: Vec<u8>,
+}
+
+#[test]
+fn test_size_after() {
+    let value = TestSizeAfter {
+        kind: 1,
+        values: vec![2, 3],
+    };
+    it_reads_what_it_writes(&value);
+    let mut buffer = Vec::new();
+    value.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [7, 1, 2, 0, 3, 0, 2, 0]);
+    it_reads_what_it_writes(&TestSizesAfter {
+        first: value,
+        second: vec![TestSizeAfter { kind: 4, values: vec![] }],
+    });
+    assert!(TestSizeAfter::LAYOUT_DOC.contains("followed by its item count as a `u16`"));
+    let value = TestSizeAfterItems { items_len: 2, items: vec![5, 6] };
+    it_reads_what_it_writes(&value);
+    assert_eq!(TestSizeAfterItems::read_from(&mut [2, 5, 6, 2].as_slice()).unwrap(), value);
+    // bytes left between the items and the size
+    let error = TestSizeAfter::read_from(&mut [4, 1, 2, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_size_after_limits() {
+    // the items and their size are buffered, 5 bytes here
+    let data = [3, 1, 0, 0, 3, 4, 0, 0, 4];
+    let limits = |max_len| plod::Limits { max_len, ..plod::Limits::default() };
+    let error = plod::fuzz::with_limits(limits(4), || TestSizesAfter::read_from(&mut data.as_slice())).unwrap_err();
+    assert!(error.get_ref().unwrap().downcast_ref::<plod::fuzz::LimitExceeded>().is_some(), "{:?}", error);
+    let value = plod::fuzz::with_limits(limits(5), || TestSizesAfter::read_from(&mut data.as_slice())).unwrap();
+    assert_eq!(value.second, vec![TestSizeAfter { kind: 4, values: vec![] }]);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestBitmap {
+    #[plod(size_type(u16), bitmap)]
+    used: Vec<bool>,
+    count: u8,
+    #[plod(size_type(u8), byte_sized, bitmap)]
+    features: Vec<bool>,
+    #[plod(len_of = flags)]
+    flag_count: u8,
+    #[plod(size_type(u8), bitmap)]
+    flags: Vec<bool>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct TestBitOrder {
+    #[plod(size_type(u8), bitmap, bit_order = msb_first)]
+    msb: Vec<bool>,
+    #[plod(size_type(u8), bitmap, bit_order = lsb_first)]
+    lsb: Vec<bool>,
+}
+
+#[test]
+fn test_bitmap() {
+    let value = TestBitmap {
+        used: vec![true, false, false, true, false, false, false, false, true, true],
+        count: 2,
+        features: vec![false, true, false, false, false, false, false, false],
+        flag_count: 0,
+        flags: vec![true, true, true],
+    };
+    let mut buffer = Vec::new();
+    value.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [0, 10, 0x09, 0x03, 2, 1, 0x02, 3, 0x07]);
+    assert_eq!(value.size_at_rest(), buffer.len());
+    let read = TestBitmap::read_from(&mut buffer.as_slice()).unwrap();
+    assert_eq!(read, TestBitmap { flag_count: 3, ..value });
+    let spans = plod::dump::spans(&read);
+    assert_eq!(spans[1].path, "TestBitmap.used");
+    assert_eq!((spans[1].start, spans[1].size), (2, 2));
+    assert!(TestBitmap::LAYOUT_DOC.contains("packed 8 per byte, the first item in the lowest bit, after its bit count as a `u16`"));
+
+    let bits = vec![true, false, false, true, false, false, false, false, true, true];
+    let value = TestBitOrder { msb: bits.clone(), lsb: bits };
+    let mut buffer = Vec::new();
+    value.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [10, 0x90, 0xc0, 10, 0x09, 0x03]);
+    assert_eq!(TestBitOrder::read_from(&mut buffer.as_slice()).unwrap(), value);
+    assert!(TestBitOrder::LAYOUT_DOC.contains("- `msb`: `Vec<bool>` packed 8 per byte, the first item in the highest bit"));
+    assert!(TestBitOrder::LAYOUT_DOC.contains("- `lsb`: `Vec<bool>` packed 8 per byte, the first item in the lowest bit"));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), tag_names)]
+enum TestTagNames {
+    #[plod(tag = 1)]
+    Ping,
+    #[plod(tag = 2..=4 | 8, keep_tag)]
+    Data(u8),
+    #[plod(skip)]
+    Never,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(i16), tag_names)]
+enum TestTagNamesDefault {
+    #[plod(tag = -1)]
+    End,
+    #[plod(keep_tag)]
+    Other(i16),
+}
+
+#[test]
+fn test_tag_names() {
+    assert_eq!(TestTagNames::tag_name(1), Some("Ping"));
+    assert_eq!(TestTagNames::tag_name(3), Some("Data"));
+    assert_eq!(TestTagNames::tag_name(8), Some("Data"));
+    assert_eq!(TestTagNames::tag_name(5), None);
+    // skipped variants are never read
+    assert!(TestTagNames::Never.write_to(&mut Vec::new()).is_err());
+    assert_eq!(TestTagNamesDefault::tag_name(-1), Some("End"));
+    assert_eq!(TestTagNamesDefault::tag_name(7), Some("Other"));
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u16), tag_consts)]
+enum TestTagConsts {
+    #[plod(tag = 0x0101)]
+    Ping,
+    #[plod(tag = 0x0202, tag_aliases(0x0203))]
+    DataChunk(u8),
+    #[plod(tag = 4..=8, keep_tag)]
+    Range(u16),
+    #[plod(tag = 9)]
+    V2Header,
+}
+
+#[test]
+fn test_tag_consts() {
+    assert_eq!(TestTagConsts::PING_TAG, 0x0101);
+    assert_eq!(TestTagConsts::DATA_CHUNK_TAG, 0x0202);
+    assert_eq!(TestTagConsts::V2_HEADER_TAG, 9);
+    let data = TestTagConsts::DataChunk(1).to_bytes().unwrap();
+    assert_eq!(u16::from_le_bytes([data[0], data[1]]), TestTagConsts::DATA_CHUNK_TAG);
+    // the constants can be used as patterns on raw tags
+    let route = |tag: u16| match tag {
+        TestTagConsts::PING_TAG => "ping",
+        TestTagConsts::DATA_CHUNK_TAG => "data",
+        _ => "other",
+    };
+    assert_eq!(route(0x0101), "ping");
+    assert_eq!(route(5), "other");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, magic(u8 = 0xee))]
+struct TestRecordOffset {
+    #[plod(record_offset)]
+    offset: usize,
+    kind: u8,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+    #[plod(record_offset)]
+    end: usize,
+}
+
+#[test]
+fn test_record_offset() {
+    let data = [0xee, 1, 2, 7, 8, 0xee, 2, 0];
+    let records: Vec<TestRecordOffset> = plod::iter::read_n(&mut data.as_slice(), 2).unwrap();
+    assert_eq!((records[0].offset, records[0].end), (1, 5));
+    assert_eq!((records[1].offset, records[1].end), (6, 8));
+    // the offsets are not written
+    let mut buffer = Vec::new();
+    records[1].write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [0xee, 2, 0]);
+    assert_eq!(TestRecordOffset::read_from(&mut buffer.as_slice()).unwrap().offset, 1);
+}
+
+mod range {
+    use plod::Plod;
+
+    /// a range whose start is never after its end
+    #[derive(Plod, PartialEq, Debug)]
+    #[plod(construct_with = Range::new)]
+    #[non_exhaustive]
+    pub struct Range {
+        start: u16,
+        end: u16,
+    }
+
+    impl Range {
+        pub fn new(start: u16, end: u16) -> Result<Self, String> {
+            if start > end {
+                return Err(format!("range {}..{} is reversed", start, end));
+            }
+            Ok(Range { start, end })
+        }
+
+        pub fn len(&self) -> u16 {
+            self.end - self.start
+        }
+    }
+
+    #[derive(Plod, PartialEq, Debug)]
+    #[plod(construct_with = Span::checked)]
+    pub struct Span(u8, #[plod(skip)] bool);
+
+    impl Span {
+        fn checked(length: u8, _: bool) -> std::io::Result<Self> {
+            if length == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+            }
+            Ok(Span(length, true))
+        }
+    }
+}
+
+#[test]
+fn test_construct_with() {
+    let range = range::Range::read_from(&mut [1_u8, 0, 4, 0].as_slice()).unwrap();
+    assert_eq!(range.len(), 3);
+    let err = range::Range::read_from(&mut [4_u8, 0, 1, 0].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().ends_with("range 4..1 is reversed"), "{}", err);
+    let mut buffer = Vec::new();
+    range::Range::new(2, 2).unwrap().write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [2, 0, 2, 0]);
+
+    // io errors are returned as is
+    assert!(range::Span::read_from(&mut [3_u8].as_slice()).is_ok());
+    let err = range::Span::read_from(&mut [0_u8].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u16), big_endian, tag_names)]
+enum TestTagAliases {
+    #[plod(tag = 0x10, tag_aliases(0x01, 0x8001))]
+    Header(u8),
+    #[plod(tag = 0x20, tag_aliases(0x02))]
+    Body,
+}
+
+#[test]
+fn test_tag_aliases() {
+    for tag in [0x10_u16, 0x01, 0x8001] {
+        let mut data = tag.to_be_bytes().to_vec();
+        data.push(7);
+        assert_eq!(TestTagAliases::read_from(&mut data.as_slice()).unwrap(), TestTagAliases::Header(7));
+    }
+    assert_eq!(TestTagAliases::read_from(&mut [0_u8, 2].as_slice()).unwrap(), TestTagAliases::Body);
+    // the canonical tag is written
+    let mut buffer = Vec::new();
+    TestTagAliases::Body.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [0, 0x20]);
+    let err = TestTagAliases::read_from(&mut [0_u8, 3].as_slice()).unwrap_err();
+    assert!(err.to_string().contains("expected 0x10|0x01|0x8001, 0x20|0x02"), "{}", err);
+    assert_eq!(TestTagAliases::tag_name(0x8001), Some("Header"));
+    assert_eq!(TestTagAliases::skip_from(&mut [0_u8, 2].as_slice()).unwrap(), 2);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum TestWriteTag {
+    #[plod(tag = 0x10..0x20 | 0x30..=0x3f, write_tag = 0x30)]
+    Data(u8),
+    #[plod(tag = 0x80.., write_tag = 0x80)]
+    Extension,
+    #[plod(tag = 1..=2, write_tag = 3)]
+    Broken,
+}
+
+#[test]
+fn test_write_tag() {
+    for tag in [0x10_u8, 0x1f, 0x30, 0x3f] {
+        assert_eq!(TestWriteTag::read_from(&mut [tag, 7].as_slice()).unwrap(), TestWriteTag::Data(7));
+    }
+    assert!(TestWriteTag::read_from(&mut [0x20_u8, 7].as_slice()).is_err());
+    assert_eq!(TestWriteTag::read_from(&mut [0xff_u8].as_slice()).unwrap(), TestWriteTag::Extension);
+    let mut buffer = Vec::new();
+    TestWriteTag::Data(7).write_to(&mut buffer).unwrap();
+    TestWriteTag::Extension.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [0x30, 7, 0x80]);
+    // a tag that is not read back as its variant is not written
+    let err = TestWriteTag::Broken.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("TestWriteTag::Broken"), "{}", err);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), length_prefixed(u8), reserved_tags = 0x40..=0x4f)]
+enum TestReservedTags {
+    #[plod(tag = 1)]
+    Name(#[plod(size_type(u8))] String),
+    #[plod(tag = 0x40)]
+    Legacy,
+    Reserved { tag: u8, payload: Vec<u8> },
+}
+
+#[test]
+fn test_reserved_tags() {
+    let data = [3_u8, 0x42, 0xaa, 0xbb, 4, 1, 2, b'o', b'k', 1, 0x50];
+    let mut reader = data.as_slice();
+    let reserved = TestReservedTags::read_from(&mut reader).unwrap();
+    assert_eq!(reserved, TestReservedTags::Reserved { tag: 0x42, payload: vec![0xaa, 0xbb] });
+    assert_eq!(TestReservedTags::read_from(&mut reader).unwrap(), TestReservedTags::Name("ok".into()));
+    // tags outside the range are still unknown
+    assert!(TestReservedTags::read_from(&mut reader).is_err());
+    // the first matching variant wins
+    assert_eq!(TestReservedTags::read_from(&mut [1_u8, 0x40].as_slice()).unwrap(), TestReservedTags::Legacy);
+    assert_eq!(TestReservedTags::read_from(&mut [1_u8, 0x4f].as_slice()).unwrap(), TestReservedTags::Reserved { tag: 0x4f, payload: vec![] });
+
+    let mut buffer = Vec::new();
+    reserved.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, data[..4]);
+    assert_eq!(reserved.size_at_rest(), 4);
+}
+
+const TEST_TRAILER: [u8; 4] = *b"DONE";
+
+#[derive(Plod, PartialEq, Debug)]
+struct TestEquals {
+    kind: u8,
+    #[plod(equals = b"IHDR")]
+    signature: [u8; 4],
+    width: u16,
+    #[plod(equals = &TEST_TRAILER)]
+    trailer: [u8; 4],
+}
+
+#[test]
+fn test_equals() {
+    let data = [1_u8, b'I', b'H', b'D', b'R', 3, 0, b'D', b'O', b'N', b'E'];
+    let value = TestEquals::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(value, TestEquals { kind: 1, signature: *b"IHDR", width: 3, trailer: TEST_TRAILER });
+    assert_eq!(TestEquals::SIZE, 11);
+
+    let mut wrong = data;
+    wrong[3] = b'X';
+    let err = TestEquals::read_from(&mut wrong.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("expected at offset 1"), "{}", err);
+
+    // the constant is written whatever the field holds
+    let mut buffer = Vec::new();
+    TestEquals { signature: [0; 4], ..value }.write_to(&mut buffer).unwrap();
+    assert_eq!(buffer, data);
+    assert!(TestEquals::LAYOUT_DOC.contains("always `b\"IHDR\"`"), "{}", TestEquals::LAYOUT_DOC);
+}
@@ -0,0 +1,113 @@
+use plod::layout::Endianness;
+use plod::lenient::UnknownTag;
+use plod::migrate::Migrations;
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct V1 {
+    id: u8,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct V2 {
+    id: u16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct V3 {
+    id: u16,
+    #[plod(size_type(u8))]
+    name: Vec<u8>,
+}
+
+impl From<V1> for V2 {
+    fn from(v1: V1) -> Self {
+        V2 { id: v1.id as u16 }
+    }
+}
+
+impl From<V2> for V3 {
+    fn from(v2: V2) -> Self {
+        V3 {
+            id: v2.id,
+            name: Vec::new(),
+        }
+    }
+}
+
+fn migrations() -> Migrations<V3> {
+    Migrations::new(2, Endianness::Big, 3)
+        .version_via::<V1, V2>(1)
+        .version::<V2>(2)
+}
+
+#[test]
+fn test_read_versions() {
+    let migrations = migrations();
+    let v3 = V3 {
+        id: 5,
+        name: vec![],
+    };
+    assert_eq!(
+        migrations.read_from(&mut [0_u8, 1, 5].as_slice()).unwrap(),
+        v3
+    );
+    assert_eq!(
+        migrations
+            .read_from(&mut [0_u8, 2, 0, 5].as_slice())
+            .unwrap(),
+        v3
+    );
+    let v3 = V3 {
+        id: 5,
+        name: b"a".to_vec(),
+    };
+    let mut memory = Vec::new();
+    migrations.write_to(&v3, &mut memory).unwrap();
+    assert_eq!(memory, vec![0, 3, 0, 5, 1, b'a']);
+    assert_eq!(migrations.read_from(&mut memory.as_slice()).unwrap(), v3);
+}
+
+#[test]
+fn test_unknown_version() {
+    let error = migrations()
+        .read_from(&mut [0_u8, 4, 0].as_slice())
+        .unwrap_err();
+    let unknown = error
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<UnknownTag>()
+        .unwrap();
+    assert_eq!(unknown.tag, 4);
+}
+
+#[test]
+fn test_version_too_large() {
+    let migrations = Migrations::<V2>::new(1, Endianness::Big, 256);
+    let error = migrations.write_to(&V2 { id: 1 }, &mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct Padded {
+    id: u8,
+    #[plod(pad_to_offset = 4)]
+    tail: u8,
+}
+
+#[test]
+fn test_position() {
+    // the version number is at 2 in the enclosing value, the padding ends at 4
+    let migrations = Migrations::<Padded>::new(1, Endianness::Big, 1);
+    let value = Padded { id: 7, tail: 9 };
+    let mut memory = Vec::new();
+    migrations.impl_write_to(&value, &mut memory, 2).unwrap();
+    assert_eq!(memory, vec![1, 7, 9]);
+    assert_eq!(migrations.impl_read_from(&mut memory.as_slice(), 2).unwrap(), value);
+    let mut memory = Vec::new();
+    migrations.write_to(&value, &mut memory).unwrap();
+    assert_eq!(memory, vec![1, 7, 0, 0, 9]);
+}
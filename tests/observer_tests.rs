@@ -0,0 +1,81 @@
+use plod::lenient::UnknownTag;
+use plod::{Observer, Plod};
+use std::cell::RefCell;
+
+#[derive(Default)]
+struct Stats {
+    unknown_tags: RefCell<Vec<i128>>,
+    padding: RefCell<Vec<(usize, Vec<u8>)>>,
+    truncated: RefCell<Vec<(usize, &'static str)>>,
+}
+
+impl Observer for Stats {
+    fn on_unknown_tag(&self, tag: &UnknownTag) {
+        self.unknown_tags.borrow_mut().push(tag.tag);
+    }
+
+    fn on_padding(&self, position: usize, bytes: &[u8]) {
+        self.padding.borrow_mut().push((position, bytes.to_vec()));
+    }
+
+    fn on_truncated(&self, position: usize, field: &'static str) {
+        self.truncated.borrow_mut().push((position, field));
+    }
+}
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = Stats, tag_type(u8))]
+enum Record {
+    #[plod(tag = 1)]
+    Byte(u8),
+    #[plod(tag = 2)]
+    Word(u16),
+}
+
+#[derive(Plod, Debug, PartialEq)]
+#[plod(context = Stats)]
+struct Header {
+    #[plod(reserved(2))]
+    version: u8,
+    #[plod(pad_to_offset = 8, fill = 0xff)]
+    flags: u8,
+    #[plod(if_remaining)]
+    extra: Option<u16>,
+    #[plod(if_remaining)]
+    more: Option<u16>,
+}
+
+#[test]
+fn test_observer() {
+    let stats = Stats::default();
+    let header = Header::read_with(&mut [0_u8, 0, 1, 0xff, 0xff, 0xff, 0xff, 0xff, 3].as_slice(), &stats).unwrap();
+    assert_eq!(header.flags, 3);
+    assert!(stats.padding.borrow().is_empty());
+    assert_eq!(*stats.truncated.borrow(), vec![(9, "extra"), (9, "more")]);
+
+    let stats = Stats::default();
+    let data = [1_u8, 0, 1, 0xff, 0, 0xff, 0xff, 0xff, 3, 4, 0, 5, 0];
+    let header = Header::read_with(&mut data.as_slice(), &stats).unwrap();
+    assert_eq!(header.more, Some(5));
+    assert_eq!(*stats.padding.borrow(), vec![(0, vec![1, 0]), (3, vec![0xff, 0, 0xff, 0xff, 0xff])]);
+    assert!(stats.truncated.borrow().is_empty());
+
+    let stats = Stats::default();
+    assert!(Record::read_with(&mut [7_u8, 0].as_slice(), &stats).is_err());
+    assert_eq!(*stats.unknown_tags.borrow(), vec![7]);
+    assert_eq!(Record::read_with(&mut [1_u8, 9].as_slice(), &stats).unwrap(), Record::Byte(9));
+    assert_eq!(*stats.unknown_tags.borrow(), vec![7]);
+}
+
+#[derive(Plod, Debug, PartialEq)]
+struct Plain {
+    #[plod(reserved(1))]
+    value: u8,
+}
+
+#[test]
+fn test_no_observer() {
+    // a context that is not an observer reads as before
+    let plain = Plain::read_from(&mut [0xaa_u8, 2].as_slice()).unwrap();
+    assert_eq!(plain.value, 2);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "rayon")]
+
+use plod::{par_read_records, write_all, Limits, Plod};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Sample {
+    time: u32,
+    value: i16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, length_prefixed(u16))]
+struct Event {
+    id: u8,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct Unsized {
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[test]
+fn test_par_read_fixed_size() {
+    let samples: Vec<Sample> = (0..1000)
+        .map(|i| Sample {
+            time: i,
+            value: -(i as i16),
+        })
+        .collect();
+    let mut bytes = Vec::new();
+    write_all(&mut bytes, &samples).unwrap();
+    assert_eq!(par_read_records::<Sample>(&bytes).unwrap(), samples);
+    assert!(par_read_records::<Sample>(&bytes[1..]).is_err());
+}
+
+#[test]
+fn test_par_read_length_prefixed() {
+    let events: Vec<Event> = (0..100)
+        .map(|i| Event {
+            id: i,
+            data: vec![i; i as usize % 7],
+        })
+        .collect();
+    let mut bytes = Vec::new();
+    write_all(&mut bytes, &events).unwrap();
+    assert_eq!(par_read_records::<Event>(&bytes).unwrap(), events);
+    // truncated last record
+    assert!(par_read_records::<Event>(&bytes[..bytes.len() - 1]).is_err());
+    // limits apply to records decoded on other threads
+    let limits = Limits {
+        max_depth: 0,
+        ..Limits::default()
+    };
+    assert!(plod::fuzz::with_limits(limits, || par_read_records::<Event>(&bytes)).is_err());
+}
+
+#[test]
+fn test_par_read_unsupported() {
+    let error = par_read_records::<Unsized>(&[1, 2]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
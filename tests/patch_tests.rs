@@ -0,0 +1,75 @@
+use std::io::Cursor;
+
+use plod::patch::patch_field;
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug, Clone)]
+#[plod(little_endian)]
+struct Point {
+    x: i16,
+    y: i16,
+}
+
+#[derive(Plod, PartialEq, Debug, Clone)]
+#[plod(little_endian, magic(u32 = 0x12345678), length_prefixed(u16))]
+struct Image {
+    version: (u8, u8),
+    #[plod(reserved(2))]
+    flags: u32,
+    points: [Point; 2],
+    #[plod(size_type(u32))]
+    data: Vec<u8>,
+    after: u8,
+}
+
+fn image() -> Image {
+    Image {
+        version: (1, 0),
+        flags: 0,
+        points: [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }],
+        data: vec![1, 2, 3],
+        after: 9,
+    }
+}
+
+#[test]
+fn test_offset_of() {
+    assert_eq!(Image::offset_of("version"), Some(6));
+    assert_eq!(Image::offset_of("version.1"), Some(7));
+    assert_eq!(Image::offset_of("flags"), Some(10));
+    assert_eq!(Image::offset_of("points[1]"), Some(18));
+    assert_eq!(Image::offset_of("points[1].y"), Some(20));
+    assert_eq!(Image::offset_of("data"), Some(22));
+    // after a variable size field
+    assert_eq!(Image::offset_of("after"), None);
+    assert_eq!(Image::offset_of("points[2]"), None);
+    assert_eq!(Image::offset_of("missing"), None);
+}
+
+#[test]
+fn test_patch_field() {
+    let mut memory = vec![0xff; 3];
+    image().write_to(&mut memory).unwrap();
+    let mut file = Cursor::new(memory);
+    patch_field::<Image, _, _>(&mut file, 3, "flags", &0x0102_u32).unwrap();
+    let points = [Point { x: 0, y: 0 }, Point { x: 3, y: 6 }];
+    patch_field::<Image, _, _>(&mut file, 3, "points", &points).unwrap();
+    patch_field::<Image, _, _>(&mut file, 3, "points[1].y", &-5_i16).unwrap();
+    patch_field::<Image, _, _>(&mut file, 3, "points[0]", &Point { x: 7, y: 8 }).unwrap();
+    patch_field::<Image, _, _>(&mut file, 3, "version.0", &2_u8).unwrap();
+
+    let memory = file.into_inner();
+    let (patched, _) = Image::read_from_slice(&memory[3..]).unwrap();
+    let mut expected = image();
+    expected.flags = 0x0102;
+    expected.points = [Point { x: 7, y: 8 }, Point { x: 3, y: -5 }];
+    expected.version.0 = 2;
+    assert_eq!(patched, expected);
+
+    // wrong type or no fixed offset
+    let mut file = Cursor::new(memory);
+    let error = patch_field::<Image, _, _>(&mut file, 3, "flags", &1_u16).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    let error = patch_field::<Image, _, _>(&mut file, 3, "after", &1_u8).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
@@ -0,0 +1,99 @@
+use plod::pod::{as_bytes, cast_slice, read_records, Pod};
+use plod::Plod;
+
+#[derive(Plod, Clone, Copy, PartialEq, Debug)]
+#[plod(little_endian, pod_cast)]
+#[repr(C)]
+struct Point {
+    x: i16,
+    y: i16,
+}
+
+#[derive(Plod, Clone, Copy, PartialEq, Debug)]
+#[plod(little_endian, pod_cast)]
+#[repr(C)]
+struct Record {
+    id: u32,
+    points: [Point; 2],
+    value: f32,
+}
+
+/// The same layout, read field by field
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Fields {
+    id: u32,
+    points: [(i16, i16); 2],
+    value: f32,
+}
+
+#[derive(Plod, Clone, Copy, PartialEq, Debug)]
+#[plod(big_endian, pod_cast)]
+#[repr(C)]
+struct BigRecord {
+    id: u32,
+    value: u16,
+    other: u16,
+}
+
+fn record(id: u32) -> Record {
+    Record {
+        id,
+        points: [Point { x: 1, y: -2 }, Point { x: 300, y: 4 }],
+        value: 1.5,
+    }
+}
+
+#[test]
+fn test_pod_read_write() {
+    assert_eq!(Record::NATIVE, cfg!(target_endian = "little"));
+    assert_eq!(BigRecord::NATIVE, cfg!(target_endian = "big"));
+
+    let value = record(7);
+    let mut bytes = Vec::new();
+    value.write_to(&mut bytes).unwrap();
+    let fields = Fields {
+        id: 7,
+        points: [(1, -2), (300, 4)],
+        value: 1.5,
+    };
+    let mut expected = Vec::new();
+    fields.write_to(&mut expected).unwrap();
+    assert_eq!(bytes, expected);
+    assert_eq!(Record::read_from(&mut bytes.as_slice()).unwrap(), value);
+
+    let big = BigRecord {
+        id: 0x01020304,
+        value: 5,
+        other: 6,
+    };
+    let mut bytes = Vec::new();
+    big.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, [1, 2, 3, 4, 0, 5, 0, 6]);
+    assert_eq!(BigRecord::read_from(&mut bytes.as_slice()).unwrap(), big);
+}
+
+#[test]
+fn test_pod_records() {
+    let records = vec![record(1), record(2), record(3)];
+    let bytes = as_bytes(&records);
+    assert_eq!(bytes.len(), 3 * 16);
+    assert_eq!(read_records::<Record>(bytes).unwrap(), records);
+    if Record::NATIVE {
+        assert_eq!(cast_slice::<Record>(bytes).unwrap(), records.as_slice());
+    }
+    // not a whole number of records
+    assert!(read_records::<Record>(&bytes[1..]).is_err());
+    assert!(cast_slice::<Record>(&bytes[..15]).is_none());
+
+    let bytes = [0, 0, 0, 1, 0, 2, 0, 3, 0, 0, 0, 4, 0, 5, 0, 6];
+    let records = read_records::<BigRecord>(&bytes).unwrap();
+    assert_eq!(
+        records[1],
+        BigRecord {
+            id: 4,
+            value: 5,
+            other: 6
+        }
+    );
+}
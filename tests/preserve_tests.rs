@@ -0,0 +1,33 @@
+use plod::{Plod, Preserved};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, length_prefixed(u16), preserve_unknown = kept)]
+struct Entry {
+    kind: u8,
+    kept: Preserved,
+    #[plod(reserved(3))]
+    value: u32,
+}
+
+#[test]
+fn test_preserve_unknown() {
+    let data = [10, 0, 1, 0xde, 0xad, 0xbe, 2, 0, 0, 0, 0xf0, 0x0d];
+    let mut entry = Entry::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(entry.value, 2);
+    assert_eq!(entry.kept.reserved("value"), Some([0xde, 0xad, 0xbe].as_slice()));
+    assert_eq!(entry.kept.trailing(), [0xf0, 0x0d]);
+    assert_eq!(entry.size_at_rest(), data.len());
+    assert_eq!(Entry::FIXED_SIZE, None);
+    let spans = plod::dump::spans(&entry);
+    let trailing = spans.iter().find(|span| span.path == "Entry.<trailing>").unwrap();
+    assert_eq!((trailing.start, trailing.size), (10, 2));
+    entry.value = 3;
+    let mut written = Vec::new();
+    entry.write_to(&mut written).unwrap();
+    assert_eq!(written, [10, 0, 1, 0xde, 0xad, 0xbe, 3, 0, 0, 0, 0xf0, 0x0d]);
+    // a new value has nothing to preserve
+    entry.kept.clear();
+    let mut written = Vec::new();
+    entry.write_to(&mut written).unwrap();
+    assert_eq!(written, [8, 0, 1, 0, 0, 0, 3, 0, 0, 0]);
+}
@@ -0,0 +1,56 @@
+use plod::Plod;
+
+#[derive(Default)]
+struct Session {
+    extended: bool,
+}
+
+plod::plod_profile!(proto = little_endian, size_type(u8), context = Session);
+
+proto! {
+    #[derive(PartialEq, Debug)]
+    struct Header {
+        id: u16,
+        name: Vec<u8>,
+    }
+
+    #[derive(PartialEq, Debug)]
+    #[plod(tag_type(u8))]
+    enum Body {
+        #[plod(tag = 1 if ctx.extended)]
+        Extended(u32),
+        #[plod(tag = 1)]
+        Basic(u16),
+    }
+
+    #[derive(PartialEq, Debug)]
+    #[plod(big_endian)]
+    struct Trailer {
+        crc: u16,
+    }
+}
+
+#[test]
+fn test_profile() {
+    let header = Header {
+        id: 1,
+        name: vec![2, 3],
+    };
+    let mut data = Vec::new();
+    header.write_to(&mut data).unwrap();
+    assert_eq!(data, [1, 0, 2, 2, 3]);
+
+    let data = [1, 2, 0, 0, 0];
+    let session = Session { extended: true };
+    let body = Body::impl_read_from(&mut data.as_slice(), &session, 0).unwrap();
+    assert_eq!(body, Body::Extended(2));
+    let body = Body::impl_read_from(&mut data.as_slice(), &Session::default(), 0).unwrap();
+    assert_eq!(body, Body::Basic(2));
+
+    // attributes of the item override the profile
+    let mut data = Vec::new();
+    Trailer { crc: 0x1234 }
+        .impl_write_to(&mut data, &Session::default(), 0)
+        .unwrap();
+    assert_eq!(data, [0x12, 0x34]);
+}
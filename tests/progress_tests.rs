@@ -0,0 +1,72 @@
+use std::sync::{Arc, Mutex};
+
+use plod::progress::with_progress;
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Record {
+    id: u32,
+    #[plod(size_type(u8))]
+    data: Vec<u8>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Table {
+    #[plod(size_type(u16))]
+    records: Vec<Record>,
+}
+
+fn table(count: u16) -> Vec<u8> {
+    let mut data = count.to_be_bytes().to_vec();
+    for id in 0..count as u32 {
+        data.extend(id.to_be_bytes());
+        data.extend([3, 1, 2, 3]);
+    }
+    data
+}
+
+#[test]
+fn test_progress() {
+    let data = table(100);
+    let positions = Arc::new(Mutex::new(Vec::new()));
+    let reported = positions.clone();
+    let table = with_progress(
+        200,
+        move |pos| reported.lock().unwrap().push(pos),
+        || Table::read_from(&mut data.as_slice()),
+    )
+    .unwrap();
+    assert_eq!(table.records.len(), 100);
+    let positions = positions.lock().unwrap();
+    // records are 8 bytes long, the position is checked after each byte Vec and each record
+    assert_eq!(*positions, vec![202, 402, 602, 802]);
+
+    // nothing is reported outside of with_progress
+    let data = self::table(100);
+    assert!(Table::read_from(&mut data.as_slice()).is_ok());
+    assert_eq!(positions.len(), 4);
+}
+
+#[test]
+fn test_progress_records() {
+    let mut records = Vec::new();
+    for id in 0..10 {
+        Record {
+            id,
+            data: vec![0; 6],
+        }
+        .write_to(&mut records)
+        .unwrap();
+    }
+    let count = Arc::new(Mutex::new(0));
+    let counted = count.clone();
+    let read = with_progress(
+        30,
+        move |_| *counted.lock().unwrap() += 1,
+        || plod::iter::<Record, _>(records.as_slice()).count(),
+    );
+    assert_eq!(read, 10);
+    assert_eq!(*count.lock().unwrap(), 3);
+}
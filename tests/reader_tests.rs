@@ -0,0 +1,48 @@
+#![cfg(all(feature = "gzip", feature = "zstd", feature = "lz4"))]
+
+use plod::reader::auto_decompress;
+use plod::Plod;
+
+#[derive(Plod, Debug, PartialEq)]
+struct Point {
+    x: u16,
+    y: u16,
+}
+
+// two gzip members, like the output of `cat a.gz b.gz`
+const GZIP: &[u8] = &[
+    0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x63, 0x64, 0x60, 0x62, 0x00, 0x00, 0xfb, 0xda, 0xce,
+    0xab, 0x04, 0x00, 0x00, 0x00, 0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x63, 0x66, 0x60, 0x61,
+    0x00, 0x00, 0xf6, 0xb5, 0x9d, 0x57, 0x04, 0x00, 0x00, 0x00,
+];
+
+const RAW: &[u8] = &[1, 0, 2, 0, 3, 0, 4, 0];
+
+fn points() -> Vec<Point> {
+    vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]
+}
+
+#[test]
+fn test_auto_decompress() {
+    let inputs = [
+        (RAW.to_vec(), None),
+        (GZIP.to_vec(), Some("gzip")),
+        (plod::compress::zstd::compress(RAW), Some("zstd")),
+        (plod::compress::lz4::compress(RAW), Some("lz4")),
+    ];
+    for (input, compression) in inputs {
+        let reader = auto_decompress(input.as_slice()).unwrap();
+        assert_eq!(reader.compression(), compression);
+        assert_eq!(plod::read_all::<Point, _>(reader).unwrap(), points());
+    }
+}
+
+#[test]
+fn test_auto_decompress_short() {
+    // inputs shorter than a magic are read as is
+    let mut reader = auto_decompress([0x1f_u8].as_slice()).unwrap();
+    assert_eq!(reader.compression(), None);
+    assert!(Point::read_from(&mut reader).is_err());
+    let reader = auto_decompress([].as_slice()).unwrap();
+    assert!(plod::read_all::<Point, _>(reader).unwrap().is_empty());
+}
@@ -0,0 +1,114 @@
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), little_endian)]
+enum List {
+    #[plod(tag = 0)]
+    Nil,
+    #[plod(tag = 1)]
+    Cons(u16, Box<List>),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Node {
+    id: u8,
+    #[plod(size_type(u8))]
+    edges: Vec<Edge>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Edge {
+    weight: u8,
+    target: Box<Node>,
+}
+
+const FORM: u32 = 0x464f_524d;
+const BODY: u32 = 0x424f_4459;
+
+/// IFF like chunks, a FORM contains other chunks
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, dispatch_on_magic)]
+enum Chunk {
+    #[plod(magic(u32 = FORM))]
+    Form {
+        kind: u32,
+        #[plod(size_type(u32), byte_sized)]
+        chunks: Vec<Chunk>,
+    },
+    #[plod(magic(u32 = BODY))]
+    Body(#[plod(size_type(u32))] Vec<u8>),
+}
+
+fn round_trip<T: Plod + PartialEq + std::fmt::Debug>(value: &T) -> Vec<u8>
+where
+    T::Context: Default,
+{
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data.len(), value.size_at_rest());
+    assert_eq!(&T::read_from(&mut data.as_slice()).unwrap(), value);
+    data
+}
+
+#[test]
+fn test_recursive_box() {
+    let list = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Nil))));
+    assert_eq!(round_trip(&list), vec![1, 1, 0, 1, 2, 0, 0]);
+    assert_eq!(List::FIXED_SIZE, None);
+    assert_eq!(List::describe().fixed_size(), None);
+    assert_eq!(plod::dump::spans(&list).len(), 5);
+    // exporters reference recursive types instead of expanding them
+    assert!(plod::export::kaitai::<List>().contains("list"));
+    plod::export::imhex_pattern::<Chunk>();
+    plod::export::template_010::<Node>();
+}
+
+#[test]
+fn test_mutually_recursive() {
+    let leaf = Node {
+        id: 2,
+        edges: Vec::new(),
+    };
+    let node = Node {
+        id: 1,
+        edges: vec![Edge {
+            weight: 5,
+            target: Box::new(leaf),
+        }],
+    };
+    assert_eq!(round_trip(&node), vec![1, 1, 5, 2, 0]);
+    assert_eq!(Edge::FIXED_SIZE, None);
+    assert_eq!(Edge::describe().fixed_size(), None);
+}
+
+#[test]
+fn test_nested_chunks() {
+    let chunk = Chunk::Form {
+        kind: 0x4149_4646,
+        chunks: vec![
+            Chunk::Body(b"ab".to_vec()),
+            Chunk::Form {
+                kind: 1,
+                chunks: vec![Chunk::Body(b"c".to_vec())],
+            },
+        ],
+    };
+    let data = round_trip(&chunk);
+    assert_eq!(&data[..4], b"FORM");
+    assert_eq!(data.len(), 12 + 10 + 12 + 9);
+    // deep nesting of untrusted data is bounded by the fuzz limits
+    let mut deep = Vec::new();
+    for _ in 0..1000 {
+        deep.extend(FORM.to_be_bytes());
+        deep.extend([0; 4]);
+        deep.extend(12_u32.to_be_bytes());
+    }
+    let limits = plod::fuzz::Limits {
+        max_depth: 64,
+        ..Default::default()
+    };
+    let result = plod::fuzz::with_limits(limits, || Chunk::read_from(&mut deep.as_slice()));
+    assert!(result.is_err());
+}
@@ -0,0 +1,76 @@
+use std::io::{Read, Write};
+
+use plod::size::check_size;
+use plod::{Plod, Result, SizeReader, SizeWriter};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Message {
+    kind: u16,
+    #[plod(size_type(u8))]
+    payload: Vec<u32>,
+}
+
+/// Manual implementation whose size is wrong
+struct Wrong;
+
+impl Plod for Wrong {
+    type Context = ();
+    fn size_at_rest(&self) -> usize {
+        3
+    }
+    fn impl_read_from<R: Read>(from: &mut R, _ctx: &(), _pos: usize) -> Result<Self> {
+        plod::helpers::read_bytes::<2, _>(from)?;
+        Ok(Wrong)
+    }
+    fn impl_write_to<W: Write>(&self, to: &mut W, _ctx: &(), _pos: usize) -> Result<()> {
+        to.write_all(&[1, 2])
+    }
+}
+
+#[test]
+fn test_size_writer() {
+    let message = Message {
+        kind: 1,
+        payload: vec![2, 3],
+    };
+    let mut counter = SizeWriter::new();
+    message.write_to(&mut counter).unwrap();
+    assert_eq!(counter.size(), 2 + 1 + 8);
+
+    let mut counter = SizeWriter::with_writer(Vec::new());
+    message.write_to(&mut counter).unwrap();
+    assert_eq!(counter.size(), 11);
+    assert_eq!(counter.into_inner().len(), 11);
+}
+
+#[test]
+fn test_size_reader() {
+    let data = [0_u8, 1, 1, 0, 0, 0, 2, 0xff];
+    let mut counter = SizeReader::new(data.as_slice());
+    let message = Message::read_from(&mut counter).unwrap();
+    assert_eq!(message.payload, vec![2]);
+    assert_eq!(counter.size(), 7);
+    assert_eq!(counter.into_inner(), &[0xff]);
+}
+
+#[test]
+fn test_check_size() {
+    let message = Message {
+        kind: 1,
+        payload: vec![2, 3],
+    };
+    assert!(check_size(&message).is_ok());
+    let error = check_size(&Wrong).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error
+        .to_string()
+        .contains("size_at_rest() is 3 but 2 bytes were written"));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "size_at_rest() is 3 but 2 bytes were written")]
+fn test_write_to_checks_size() {
+    Wrong.write_to(&mut Vec::new()).unwrap();
+}
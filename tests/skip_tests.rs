@@ -0,0 +1,119 @@
+use std::io::{Read, Write};
+
+use plod::{Plod, Result};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian, magic(u16 = 0xcafe))]
+struct Index {
+    #[plod(size_type(u32))]
+    blob: Vec<u8>,
+    #[plod(size_type(u8))]
+    entries: Vec<Entry>,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), big_endian)]
+enum Entry {
+    #[plod(tag = 1)]
+    Offset(u64),
+    #[plod(tag = 2)]
+    Name(#[plod(size_type(u16))] Vec<u8>),
+}
+
+/// Manual implementation, without a layout
+#[derive(PartialEq, Debug)]
+struct Word(u16);
+
+impl Plod for Word {
+    type Context = ();
+    fn size_at_rest(&self) -> usize {
+        2
+    }
+    fn impl_read_from<R: Read>(from: &mut R, _ctx: &(), _pos: usize) -> Result<Self> {
+        let [a, b] = plod::helpers::read_bytes::<2, _>(from)?;
+        Ok(Word(u16::from_be_bytes([a, b])))
+    }
+    fn impl_write_to<W: Write>(&self, to: &mut W, _ctx: &(), _pos: usize) -> Result<()> {
+        to.write_all(&self.0.to_be_bytes())
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct WithWord {
+    word: Word,
+}
+
+fn index() -> Index {
+    Index {
+        blob: vec![7; 1000],
+        entries: vec![Entry::Offset(12), Entry::Name(b"name".to_vec())],
+    }
+}
+
+#[test]
+fn test_skip_from() {
+    let mut memory = Vec::new();
+    index().write_to(&mut memory).unwrap();
+    memory.push(42);
+    let mut reader = memory.as_slice();
+    assert_eq!(Index::skip_from(&mut reader).unwrap(), index().size_at_rest());
+    assert_eq!(reader, &[42]);
+    assert!(Index::skip_from(&mut &memory[..memory.len() - 2]).is_err());
+
+    // manual implementations are read, nested or not
+    assert_eq!(Word::skip_from(&mut [1_u8, 2].as_slice()).unwrap(), 2);
+    assert_eq!(WithWord::skip_from(&mut [1_u8, 2].as_slice()).unwrap(), 2);
+    assert!(WithWord::validate_from(&mut [1_u8, 2].as_slice()).is_ok());
+    assert!(WithWord::skip_from(&mut [1_u8].as_slice()).is_err());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8), little_endian)]
+enum Tagged {
+    #[plod(tag = 1..=2)]
+    Pair(u16, #[plod(is_tag)] u8, u8),
+}
+
+#[test]
+fn test_skip_kept_tag() {
+    // the tag is kept in the second field, the first one is stored
+    let value = Tagged::Pair(0x0304, 2, 5);
+    let mut memory = Vec::new();
+    value.write_to(&mut memory).unwrap();
+    assert_eq!(memory, [2, 4, 3, 5]);
+    assert_eq!(Tagged::skip_from(&mut memory.as_slice()).unwrap(), 4);
+}
+
+#[test]
+fn test_validate_from() {
+    let mut memory = Vec::new();
+    index().write_to(&mut memory).unwrap();
+    assert!(Index::validate_from(&mut memory.as_slice()).is_ok());
+
+    // unknown tag of the first entry
+    let tag = 2 + 4 + 1000 + 1;
+    let mut bad_tag = memory.clone();
+    bad_tag[tag] = 9;
+    assert!(Index::validate_from(&mut bad_tag.as_slice()).is_err());
+
+    // bad magic, only detected by validation
+    let mut bad_magic = memory.clone();
+    bad_magic[0] = 0;
+    assert!(Index::validate_from(&mut bad_magic.as_slice()).is_err());
+    assert!(Index::skip_from(&mut bad_magic.as_slice()).is_ok());
+}
+
+#[derive(Plod, PartialEq, Debug)]
+struct Signed {
+    #[plod(equals = b"SIG")]
+    signature: [u8; 3],
+    value: u8,
+}
+
+#[test]
+fn test_validate_structure_only() {
+    // values are not checked, only the structure
+    let data = [b'B', b'A', b'D', 1];
+    assert!(Signed::validate_from(&mut data.as_slice()).is_ok());
+    assert!(Signed::read_from(&mut data.as_slice()).is_err());
+}
@@ -0,0 +1,133 @@
+use plod::slice::{OutOfBounds, TrailingBytes};
+use plod::Plod;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian)]
+struct Section {
+    kind: u16,
+    #[plod(size_type(u16))]
+    data: Vec<u8>,
+    crc: u32,
+}
+
+#[test]
+fn test_read_from_slice() {
+    let image = [1, 0, 2, 0, 0xaa, 0xbb, 4, 3, 2, 1, 0xff];
+    let (section, rest) = Section::read_from_slice(&image).unwrap();
+    assert_eq!(
+        section,
+        Section {
+            kind: 1,
+            data: vec![0xaa, 0xbb],
+            crc: 0x01020304,
+        }
+    );
+    assert_eq!(rest, &[0xff]);
+}
+
+#[test]
+fn test_exact_read() {
+    let image = [1, 0, 2, 0, 0xaa, 0xbb, 4, 3, 2, 1, 0xff, 0xfe];
+    let section = Section::from_bytes_exact(&image[..10]).unwrap();
+    assert_eq!(section.crc, 0x01020304);
+    assert_eq!(Section::read_exactly_from(&mut &image[..10]).unwrap(), section);
+
+    let error = Section::from_bytes_exact(&image).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let expected = TrailingBytes {
+        offset: 10,
+        remaining: 2,
+    };
+    assert_eq!(error.get_ref().unwrap().downcast_ref(), Some(&expected));
+    let error = Section::read_exactly_from(&mut image.as_slice()).unwrap_err();
+    assert_eq!(error.get_ref().unwrap().downcast_ref(), Some(&expected));
+    assert_eq!(error.to_string(), "2 bytes left after the value, at offset 10");
+    // a truncated input is still an early end
+    let error = Section::from_bytes_exact(&image[..8]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_slice_out_of_bounds() {
+    let image = [1, 0, 2, 0, 0xaa, 0xbb, 4, 3];
+    let error = Section::read_from_slice(&image).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    let bounds = error.get_ref().unwrap().downcast_ref::<OutOfBounds>().unwrap();
+    assert_eq!(
+        bounds,
+        &OutOfBounds {
+            offset: 6,
+            needed: 4,
+            available: 2
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Unexpected end of input at offset 6: 4 bytes needed, 2 available"
+    );
+}
+
+#[test]
+fn test_write_to_slice() {
+    let section = Section {
+        kind: 1,
+        data: vec![0xaa, 0xbb],
+        crc: 0x01020304,
+    };
+    let mut buffer = [0xff; 12];
+    assert_eq!(section.write_to_slice(&mut buffer).unwrap(), 10);
+    assert_eq!(buffer, [1, 0, 2, 0, 0xaa, 0xbb, 4, 3, 2, 1, 0xff, 0xff]);
+
+    let mut buffer = [0xff; 8];
+    let error = section.write_to_slice(&mut buffer).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+    let bounds = error
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<OutOfBounds>()
+        .unwrap();
+    assert_eq!(
+        bounds,
+        &OutOfBounds {
+            offset: 0,
+            needed: 10,
+            available: 8,
+        }
+    );
+    assert_eq!(buffer, [0xff; 8]);
+}
+
+#[test]
+fn test_chunks_reader() {
+    let chunks = [vec![1, 0, 2], vec![], vec![0, 0xaa], vec![0xbb, 4, 3, 2, 1, 0xff]];
+    let mut reader = plod::slice::ChunksReader::new(chunks.iter().map(Vec::as_slice));
+    let section = Section::read_from(&mut reader).unwrap();
+    assert_eq!(
+        section,
+        Section {
+            kind: 1,
+            data: vec![0xaa, 0xbb],
+            crc: 0x01020304,
+        }
+    );
+    assert_eq!(reader.position(), 10);
+    assert_eq!(reader.remaining(), &[0xff]);
+
+    let slices = [std::io::IoSlice::new(&[1, 0, 2, 0]), std::io::IoSlice::new(&[0xaa])];
+    let mut reader = plod::slice::ChunksReader::new(slices);
+    let error = Section::read_from(&mut reader).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    let bounds = error
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<OutOfBounds>()
+        .unwrap();
+    assert_eq!(
+        bounds,
+        &OutOfBounds {
+            offset: 4,
+            needed: 2,
+            available: 1,
+        }
+    );
+}
@@ -0,0 +1,71 @@
+use plod::{read_header_then, read_header_then_with, Plod};
+use std::io::{BufRead, ErrorKind, Read};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Header {
+    version: u8,
+    #[plod(size_type(u8))]
+    name: String,
+    payload_size: u16,
+}
+
+struct Scaled {
+    value: u8,
+}
+
+impl Plod for Scaled {
+    type Context = u8;
+
+    fn size_at_rest(&self) -> usize {
+        1
+    }
+
+    fn impl_read_from<R: Read>(from: &mut R, ctx: &u8, _pos: usize) -> plod::Result<Self> {
+        let mut buffer = [0; 1];
+        from.read_exact(&mut buffer)?;
+        Ok(Scaled { value: buffer[0] * ctx })
+    }
+
+    fn impl_write_to<W: std::io::Write>(&self, to: &mut W, ctx: &u8, _pos: usize) -> plod::Result<()> {
+        to.write_all(&[self.value / ctx])
+    }
+}
+
+#[test]
+fn test_header_then_payload() {
+    let data = [1, 3, b'a', b'b', b'c', 0, 2, 7, 8, 9];
+    let (header, payload) = read_header_then::<Header, _>(data.as_slice()).unwrap();
+    assert_eq!(header.name, "abc");
+    assert_eq!(payload.header_size(), 7);
+    // the application limits itself to the payload announced by the header
+    let mut body = Vec::new();
+    let mut payload = payload;
+    payload.by_ref().take(header.payload_size as u64).read_to_end(&mut body).unwrap();
+    assert_eq!(body, [7, 8]);
+    assert_eq!(payload.offset(), 9);
+    assert_eq!(payload.into_inner(), [9]);
+}
+
+#[test]
+fn test_buf_read() {
+    let data = [0, 0, 0, 0, b'x', b'\n', b'y'];
+    let (_, mut payload) = read_header_then::<Header, _>(data.as_slice()).unwrap();
+    let mut line = String::new();
+    payload.read_line(&mut line).unwrap();
+    assert_eq!(line, "x\n");
+    assert_eq!(payload.offset(), 6);
+}
+
+#[test]
+fn test_context() {
+    let (scaled, payload) = read_header_then_with::<Scaled, _>([3_u8, 1].as_slice(), &2).unwrap();
+    assert_eq!(scaled.value, 6);
+    assert_eq!(payload.header_size(), 1);
+}
+
+#[test]
+fn test_short_header() {
+    let err = read_header_then::<Header, _>([1_u8, 5, b'a'].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,143 @@
+use plod::text::TextEncoding;
+use plod::Plod;
+use std::borrow::Cow;
+use std::io::ErrorKind;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(little_endian, size_type(u8), encoding = latin1)]
+struct Legacy<'a> {
+    id: u16,
+    title: String,
+    #[plod(len_of = comment)]
+    comment_len: u8,
+    comment: Cow<'a, str>,
+    #[plod(encoding = utf8_strict)]
+    utf8: String,
+    #[plod(encoding = utf8_lossy)]
+    lossy: String,
+    #[plod(encoding = ascii)]
+    code: String,
+}
+
+#[test]
+fn test_text_encodings() {
+    let value = Legacy {
+        id: 1,
+        title: "Café".to_string(),
+        comment_len: 0,
+        comment: Cow::Borrowed("né"),
+        utf8: "é".to_string(),
+        lossy: "a".to_string(),
+        code: "FR".to_string(),
+    };
+    assert_eq!(value.size_at_rest(), 2 + 5 + 1 + 2 + 3 + 2 + 3);
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(
+        data,
+        vec![1, 0, 4, b'C', b'a', b'f', 0xe9, 2, b'n', 0xe9, 2, 0xc3, 0xa9, 1, b'a', 2, b'F', b'R']
+    );
+    let read = Legacy::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(
+        read,
+        Legacy {
+            comment_len: 2,
+            ..value
+        }
+    );
+    let paths: Vec<_> = plod::dump::spans(&read)
+        .into_iter()
+        .map(|s| (s.path, s.size))
+        .collect();
+    assert_eq!(paths[2], ("Legacy<'_>.title".to_string(), 4));
+
+    // strict decoding tells where the invalid byte is
+    let mut invalid = data.clone();
+    invalid[12] = b'x';
+    let error = Legacy::read_from(&mut invalid.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(
+        error
+            .to_string()
+            .contains("Invalid utf8_strict text at offset 11"),
+        "{}",
+        error
+    );
+    let mut invalid = data.clone();
+    invalid[16] = 0x80;
+    let error = Legacy::read_from(&mut invalid.as_slice()).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("Invalid ascii text at offset 16"),
+        "{}",
+        error
+    );
+    // lossy decoding replaces it
+    let mut invalid = data.clone();
+    invalid[14] = 0xff;
+    let read = Legacy::read_from(&mut invalid.as_slice()).unwrap();
+    assert_eq!(read.lossy, "\u{fffd}");
+
+    // characters outside of the encoding cannot be written
+    let value = Legacy {
+        title: "€".to_string(),
+        ..read
+    };
+    let error = value.write_to(&mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(&*TextEncoding::Latin1.encode("€1"), b"?1");
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Property<'a> {
+    id: u16,
+    #[plod(bstr)]
+    name: String,
+    #[plod(size_type(u8), size_unit = 2, encoding = utf16be)]
+    label: Cow<'a, str>,
+    #[plod(len_of = wide)]
+    wide_len: u8,
+    #[plod(encoding = utf16le)]
+    wide: String,
+}
+
+#[test]
+fn test_wide_strings() {
+    let value = Property {
+        id: 1,
+        name: "é𝄞".to_string(),
+        label: Cow::Borrowed("Ok"),
+        wide_len: 0,
+        wide: "A".to_string(),
+    };
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(
+        data,
+        vec![
+            0, 1, 6, 0, 0, 0, 0xe9, 0, 0x34, 0xd8, 0x1e, 0xdd, 0, 0, 2, 0, b'O', 0, b'k', 2, b'A',
+            0
+        ]
+    );
+    assert_eq!(value.size_at_rest(), data.len());
+    let read = Property::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(read, Property { wide_len: 2, ..value });
+    let spans = plod::dump::spans(&read);
+    assert_eq!(spans[2].path, "Property<'_>.name");
+    assert_eq!((spans[2].start, spans[2].size), (6, 6));
+    assert_eq!(spans[3].path, "Property<'_>.name.<nul>");
+    assert!(Property::LAYOUT_DOC.contains("a `BSTR`, UTF-16LE text followed by a NUL character"));
+
+    // the terminator is checked
+    let mut unterminated = data.clone();
+    unterminated[12] = b'x';
+    let error = Property::read_from(&mut unterminated.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    // an unpaired surrogate
+    assert_eq!(
+        TextEncoding::Utf16Le.decode(vec![b'a', 0, 0x34, 0xd8], 10).unwrap_err().to_string(),
+        "Invalid utf16le text at offset 12"
+    );
+}
@@ -0,0 +1,114 @@
+use plod::{DosDateTime, Millis, Nanos, Plod, Seconds, Unix32Time, WindowsFiletime};
+use std::io::ErrorKind;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Entry {
+    id: u16,
+    modified: DosDateTime,
+    created: WindowsFiletime,
+    linked: Unix32Time,
+}
+
+#[test]
+fn test_times() {
+    // 2020-05-17 13:45:30 UTC
+    let time = UNIX_EPOCH + Duration::from_secs(1_589_723_130);
+    let entry = Entry {
+        id: 1,
+        modified: DosDateTime::try_from(time).unwrap(),
+        created: WindowsFiletime::try_from(time + Duration::from_nanos(150)).unwrap(),
+        linked: Unix32Time::try_from(time).unwrap(),
+    };
+    assert_eq!(
+        entry.modified,
+        DosDateTime::new(2020, 5, 17, 13, 45, 31).unwrap()
+    );
+    assert_eq!(entry.created, WindowsFiletime(132_341_967_300_000_001));
+    assert_eq!(Entry::SIZE, 18);
+    let mut data = Vec::new();
+    entry.write_to(&mut data).unwrap();
+    // the time types stay little endian in a big endian struct
+    assert_eq!(
+        data,
+        vec![
+            0, 1, // id
+            0xaf, 0x6d, 0xb1, 0x50, // modified
+            1, 0xf9, 0xca, 0x6d, 0x51, 0x2c, 0xd6, 0x01, // created
+            0xfa, 0x3f, 0xc1, 0x5e, // linked
+        ]
+    );
+    let read = Entry::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(read, entry);
+    assert_eq!(
+        (
+            read.modified.year(),
+            read.modified.month(),
+            read.modified.day()
+        ),
+        (2020, 5, 17)
+    );
+    assert_eq!(
+        (
+            read.modified.hour(),
+            read.modified.minute(),
+            read.modified.second()
+        ),
+        (13, 45, 30)
+    );
+    assert_eq!(SystemTime::try_from(read.modified).unwrap(), time);
+    assert_eq!(
+        SystemTime::from(read.created),
+        time + Duration::from_nanos(100)
+    );
+    assert_eq!(SystemTime::from(read.linked), time);
+
+    // epochs and limits
+    assert_eq!(
+        SystemTime::from(WindowsFiletime(116_444_736_000_000_000)),
+        UNIX_EPOCH
+    );
+    let before_1970 = SystemTime::from(WindowsFiletime(0));
+    assert_eq!(
+        WindowsFiletime::try_from(before_1970).unwrap(),
+        WindowsFiletime(0)
+    );
+    let error = Unix32Time::try_from(before_1970).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert!(DosDateTime::try_from(UNIX_EPOCH).is_err());
+    assert!(DosDateTime::new(2021, 2, 29, 0, 0, 0).is_err());
+    assert!(DosDateTime::new(2020, 2, 29, 23, 59, 58).is_ok());
+    let error = SystemTime::try_from(DosDateTime::default()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Timeouts {
+    idle: plod::Seconds<u16>,
+    retry: plod::Millis<u32>,
+    #[plod(little_endian)]
+    precise: plod::time::Nanos<u64>,
+}
+
+#[test]
+fn test_durations() {
+    let timeouts = Timeouts {
+        idle: Seconds::try_from(Duration::from_millis(300_500)).unwrap(),
+        retry: Millis::try_from(Duration::from_secs(2)).unwrap(),
+        precise: Nanos(7),
+    };
+    assert_eq!(timeouts.idle, Seconds(300));
+    assert_eq!(Timeouts::SIZE, 14);
+    let mut data = Vec::new();
+    timeouts.write_to(&mut data).unwrap();
+    // each duration has the endianness of its field
+    assert_eq!(data, vec![1, 44, 0, 0, 7, 208, 7, 0, 0, 0, 0, 0, 0, 0]);
+    let read = Timeouts::read_from(&mut data.as_slice()).unwrap();
+    assert_eq!(read, timeouts);
+    assert_eq!(Duration::from(read.retry), Duration::from_secs(2));
+    assert_eq!(Duration::from(read.precise), Duration::from_nanos(7));
+    let error = Millis::<u16>::try_from(Duration::from_secs(66)).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
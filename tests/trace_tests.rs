@@ -0,0 +1,84 @@
+#![cfg(feature = "tracing")]
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use plod::Plod;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Keep the fields of all events as text
+#[derive(Clone, Default)]
+struct Events(Arc<Mutex<Vec<String>>>);
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        self.0.push_str(&format!("{}={:?}", field.name(), value));
+    }
+}
+
+impl Subscriber for Events {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == "plod"
+    }
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        self.0.lock().unwrap().push(fields.0);
+    }
+    fn enter(&self, _: &Id) {}
+    fn exit(&self, _: &Id) {}
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Inner {
+    value: u16,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Outer {
+    id: u8,
+    inner: Inner,
+}
+
+#[test]
+fn test_trace() {
+    let events = Events::default();
+    let outer = tracing::subscriber::with_default(events.clone(), || {
+        Outer::read_from(&mut [1_u8, 0, 2].as_slice()).unwrap()
+    });
+    assert_eq!(
+        *events.0.lock().unwrap(),
+        vec![
+            "message=read start type=\"trace_tests::Outer\" offset=0",
+            "message=read start type=\"trace_tests::Inner\" offset=1",
+            "message=read end type=\"trace_tests::Inner\" offset=1 size=2",
+            "message=read end type=\"trace_tests::Outer\" offset=0 size=3",
+        ]
+    );
+
+    let events = Events::default();
+    tracing::subscriber::with_default(events.clone(), || {
+        assert!(outer.write_to(&mut [0_u8; 2].as_mut_slice()).is_err())
+    });
+    let events = events.0.lock().unwrap();
+    assert_eq!(events.len(), 4);
+    assert_eq!(
+        events[0],
+        "message=write start type=\"trace_tests::Outer\" offset=0 size=3"
+    );
+    assert!(events[2].starts_with("message=write failed type=\"trace_tests::Inner\" offset=1"));
+}
@@ -0,0 +1,91 @@
+use plod::error::FieldError;
+use plod::layout::Layout;
+use plod::Plod;
+
+/// XOR obfuscation with a constant key
+mod xor {
+    pub fn encode(data: &[u8]) -> Vec<u8> {
+        data.iter().map(|b| b ^ 0x5a).collect()
+    }
+
+    pub fn decode(data: &[u8]) -> plod::Result<Vec<u8>> {
+        Ok(encode(data))
+    }
+}
+
+/// Checksum appended to the region
+mod checked {
+    pub fn encode(data: &[u8]) -> Vec<u8> {
+        let mut result = data.to_vec();
+        result.push(data.iter().fold(0, |a, b| a ^ b));
+        result
+    }
+
+    pub fn decode(data: &[u8]) -> plod::Result<Vec<u8>> {
+        match data.split_last() {
+            Some((sum, data)) if data.iter().fold(0, |a, b| a ^ b) == *sum => Ok(data.to_vec()),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Bad checksum",
+            )),
+        }
+    }
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Save {
+    #[plod(transform = xor, size_type(u8))]
+    name: Vec<u8>,
+    #[plod(transform = checked, size_type(u8))]
+    stats: Stats,
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(big_endian)]
+struct Stats {
+    level: u16,
+    gold: u32,
+}
+
+#[test]
+fn test_transform() {
+    let save = Save {
+        name: b"ab".to_vec(),
+        stats: Stats {
+            level: 3,
+            gold: 0x0100,
+        },
+    };
+    let mut bytes = Vec::new();
+    save.write_to(&mut bytes).unwrap();
+    assert_eq!(
+        bytes,
+        vec![2, b'a' ^ 0x5a, b'b' ^ 0x5a, 7, 0, 3, 0, 0, 1, 0, 2]
+    );
+    assert_eq!(save.size_at_rest(), bytes.len());
+    assert_eq!(Save::read_from(&mut bytes.as_slice()).unwrap(), save);
+
+    bytes[10] = 0;
+    let error = Save::read_from(&mut bytes.as_slice()).unwrap_err();
+    assert_eq!(error.to_string(), "reading Save.stats: Bad checksum");
+    let field = error.get_ref().unwrap().downcast_ref::<FieldError>().unwrap();
+    assert_eq!(field.error.to_string(), "Bad checksum");
+}
+
+#[test]
+fn test_transform_layout() {
+    match Save::describe() {
+        Layout::Struct(s) => {
+            assert!(matches!(
+                s.fields[0].layout,
+                Layout::Encoded { encoding: "xor", .. }
+            ));
+            assert!(matches!(
+                s.fields[1].layout,
+                Layout::Encoded { encoding: "checked", .. }
+            ));
+        }
+        _ => panic!("struct expected"),
+    }
+}
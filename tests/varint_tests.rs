@@ -0,0 +1,66 @@
+use plod::varint::Varint;
+use plod::Plod;
+use std::io::ErrorKind;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(size_type(u8))]
+struct Varints {
+    #[plod(varint = protobuf)]
+    leb: u64,
+    #[plod(varint = midi_vlq)]
+    midi: u32,
+    #[plod(varint = git_vlq)]
+    git: u16,
+    #[plod(varint = sqlite)]
+    sqlite: u64,
+    #[plod(varint = leb128)]
+    items: Vec<u32>,
+}
+
+#[test]
+fn test_varint_fields() {
+    let value = Varints {
+        leb: 300,
+        midi: 0x3fff,
+        git: 128,
+        sqlite: 241,
+        items: vec![1, 128],
+    };
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(
+        data,
+        [0xac, 0x02, 0xff, 0x7f, 0x80, 0x00, 0x81, 0x71, 2, 1, 0x80, 0x01]
+    );
+    assert_eq!(value.size_at_rest(), data.len());
+    assert_eq!(Varints::read_from(&mut data.as_slice()).unwrap(), value);
+    assert_eq!(<Varints as Plod>::FIXED_SIZE, None);
+    let spans = plod::dump::spans(&value);
+    assert_eq!((spans[1].path.as_str(), spans[1].size), ("Varints.midi", 2));
+    assert!(Varints::LAYOUT_DOC.contains("`u32` as a `midi_vlq` varint"));
+
+    // 0x10000 does not fit in the u16
+    let data = [0x01, 0x00, 0x83, 0xfe, 0x80, 0x00];
+    let error = Varints::read_from(&mut data.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_varint_flavors() {
+    let values = [0, 1, 127, 128, 16383, 16384, 1 << 56, u64::MAX >> 1, u64::MAX];
+    for flavor in [Varint::Leb128, Varint::MidiVlq, Varint::GitVlq, Varint::Sqlite] {
+        for value in values {
+            let bytes = flavor.encode(value);
+            assert_eq!(flavor.read(&mut bytes.as_slice()).unwrap(), (value, bytes.len()), "{:?} {}", flavor, value);
+        }
+    }
+    assert_eq!(Varint::Leb128.encode(u64::MAX).len(), 10);
+    assert_eq!(Varint::Sqlite.encode(u64::MAX), [0xff; 9]);
+    assert_eq!(Varint::Sqlite.encode((1 << 56) - 1).len(), 8);
+    // the 2 byte git encodings start at 128
+    assert_eq!(Varint::GitVlq.encode(16511), [0xff, 0x7f]);
+    assert_eq!(Varint::GitVlq.encode(16512), [0x80, 0x80, 0x00]);
+    // 11 LEB128 bytes overflow 64 bits
+    let error = Varint::Leb128.read(&mut [0xff; 11].as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
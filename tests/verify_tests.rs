@@ -0,0 +1,46 @@
+use plod::verify::{with_verify, write_verified};
+use plod::Plod;
+use std::io::ErrorKind;
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum Command {
+    #[plod(tag = 1)]
+    Reset(u16),
+    #[plod(keep_tag)]
+    Other(u8, u8, u8, u8),
+}
+
+#[derive(Plod, PartialEq, Debug)]
+#[plod(tag_type(u8))]
+enum Short {
+    #[plod(tag = 1)]
+    Reset(u16),
+    #[plod(keep_tag)]
+    Other(u8),
+}
+
+#[test]
+fn test_verify() {
+    let mut data = Vec::new();
+    with_verify(|| Command::Reset(3).write_to(&mut data)).unwrap();
+    assert_eq!(data, vec![1, 3, 0]);
+
+    // tag 1 is read as Reset which leaves a byte
+    let err = with_verify(|| Command::Other(1, 5, 6, 7).write_to(&mut data)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(
+        err.to_string().contains("reads back 3 of the 4 bytes"),
+        "{}",
+        err
+    );
+    assert_eq!(data, vec![1, 3, 0]);
+
+    // tag 1 is read as Reset which misses a byte
+    let err = write_verified(&Short::Other(1), &mut data).unwrap_err();
+    assert!(err.to_string().contains("cannot be read back"), "{}", err);
+
+    // unverified writes are not checked
+    Short::Other(1).write_to(&mut data).unwrap();
+    assert_eq!(data, vec![1, 3, 0, 1]);
+}
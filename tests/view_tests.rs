@@ -0,0 +1,61 @@
+use plod::framing::{read_framed, write_framed, Framing};
+use plod::{Plod, PlodWrite};
+
+#[derive(Plod, Debug, PartialEq, Clone)]
+#[plod(big_endian)]
+struct Sample {
+    id: u16,
+    #[plod(size_type(u8))]
+    name: String,
+}
+
+fn samples() -> Vec<Sample> {
+    vec![
+        Sample { id: 1, name: "a".into() },
+        Sample { id: 2, name: "bc".into() },
+    ]
+}
+
+#[test]
+fn test_box() {
+    let value = Box::new(Sample { id: 3, name: "boxed".into() });
+    assert_eq!(Box::<Sample>::FIXED_SIZE, None);
+    let mut data = Vec::new();
+    value.write_to(&mut data).unwrap();
+    assert_eq!(data, [0, 3, 5, b'b', b'o', b'x', b'e', b'd']);
+    assert_eq!(value.size_at_rest(), data.len());
+    assert_eq!(Box::<Sample>::from_bytes_exact(&data).unwrap(), value);
+}
+
+#[test]
+fn test_slice() {
+    let samples = samples();
+    let slice: &[Sample] = &samples;
+    let mut data = Vec::new();
+    slice.write_view(&mut data).unwrap();
+    assert_eq!(data, [0, 1, 1, b'a', 0, 2, 2, b'b', b'c']);
+    assert_eq!(slice.view_size(), data.len());
+    let boxed: Box<[Sample]> = samples.clone().into_boxed_slice();
+    let mut other = Vec::new();
+    boxed.write_view(&mut other).unwrap();
+    assert_eq!(other, data);
+    // a single value goes through the same trait
+    let mut single = Vec::new();
+    samples[1].write_view(&mut single).unwrap();
+    assert_eq!(single, data[4..]);
+}
+
+#[test]
+fn test_framed_slice() {
+    let samples = samples();
+    let framing = Framing::new();
+    let mut log = Vec::new();
+    write_framed(&mut log, samples.as_slice(), &framing).unwrap();
+    write_framed(&mut log, &samples[0], &framing).unwrap();
+    assert_eq!(log[..4], [9, 0, 0, 0]);
+    let items: Vec<Sample> = plod::read_all(&log[4..13]).unwrap();
+    assert_eq!(items, samples);
+    let mut reader = &log[13..];
+    let single: Sample = read_framed(&mut reader, &framing).unwrap().unwrap();
+    assert_eq!(single, samples[0]);
+}